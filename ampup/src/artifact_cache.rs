@@ -0,0 +1,387 @@
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+use sha2::{Digest, Sha256};
+
+use crate::archive;
+
+/// Downloaded artifacts kept by their published `sha256:` digest, so
+/// reinstalling a version (or installing it into another directory) skips
+/// the download.
+///
+/// Each entry is a file named `sha256-<hex>`: its name is the digest it must
+/// match. Entries are re-hashed on every read, and one that no longer
+/// matches (e.g. after disk corruption) is evicted and downloaded again
+/// rather than installed.
+///
+/// With a [`CacheCompression`] configured, new entries are stored compressed
+/// under `sha256-<hex>.<extension>` and decompressed transparently on read;
+/// the digest always refers to the decompressed content. Entries of either
+/// kind are read whatever the current setting.
+pub struct ArtifactCache {
+    dir: PathBuf,
+    compression: Option<Box<dyn CacheCompression>>,
+}
+
+/// A way of compressing cache entries, trading CPU on every store and read
+/// for disk space.
+pub trait CacheCompression: Send + Sync {
+    /// Suffix added to the names of entries it compressed (e.g. `zst`).
+    fn extension(&self) -> &'static str;
+
+    fn compress(&self, data: &[u8]) -> std::io::Result<Vec<u8>>;
+
+    fn decompress(&self, data: &[u8]) -> std::io::Result<Vec<u8>>;
+
+    /// Decompressed size recorded in the header of `data`, if any.
+    fn content_size(&self, data: &[u8]) -> Option<u64>;
+}
+
+/// zstd compression at `level` (1-22; higher is smaller and slower).
+pub struct Zstd {
+    pub level: i32,
+}
+
+impl CacheCompression for Zstd {
+    fn extension(&self) -> &'static str {
+        "zst"
+    }
+
+    fn compress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        zstd::bulk::compress(data, self.level)
+    }
+
+    fn decompress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        zstd::stream::decode_all(data)
+    }
+
+    fn content_size(&self, data: &[u8]) -> Option<u64> {
+        zstd::zstd_safe::get_frame_content_size(data).ok().flatten()
+    }
+}
+
+/// Compressions entries can be read back with, whatever the one configured
+/// for new entries.
+const DECOMPRESSORS: &[&dyn CacheCompression] = &[&Zstd { level: 0 }];
+
+/// A cache entry as listed by [`ArtifactCache::entries`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct CacheEntry {
+    /// File name, e.g. `sha256-<hex>.zst`
+    pub name: String,
+    /// Size of the artifact once decompressed, when known
+    pub logical_bytes: Option<u64>,
+    /// Size on disk
+    pub physical_bytes: u64,
+}
+
+/// Outcome of [`ArtifactCache::verify`].
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// Entries whose content matches their digest.
+    pub valid: usize,
+    /// Total size of the valid entries once decompressed, in bytes.
+    pub valid_bytes: u64,
+    /// Total size of the valid entries on disk, in bytes.
+    pub physical_bytes: u64,
+    /// File names of entries that failed verification and were removed.
+    pub evicted: Vec<String>,
+}
+
+impl ArtifactCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            compression: None,
+        }
+    }
+
+    /// Store new entries with `compression`, or uncompressed for `None`.
+    pub fn with_compression(mut self, compression: Option<Box<dyn CacheCompression>>) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Cached content for `digest`, or `None` on a miss, for digests in
+    /// other algorithms, and for corrupt entries, which are evicted.
+    pub fn get(&self, digest: &str) -> Option<Vec<u8>> {
+        let hex = digest.strip_prefix("sha256:")?;
+        let name = entry_name(hex);
+        let (path, data) = std::iter::once((self.dir.join(&name), None))
+            .chain(DECOMPRESSORS.iter().map(|compression| {
+                let path = self
+                    .dir
+                    .join(format!("{}.{}", name, compression.extension()));
+                (path, Some(*compression))
+            }))
+            .find_map(|(path, compression)| {
+                let data = fs::read(&path).ok()?;
+                let data = match compression {
+                    Some(compression) => compression.decompress(&data).ok(),
+                    None => Some(data),
+                };
+                Some((path, data))
+            })?;
+
+        if let Some(data) = data
+            && sha256_hex(&data).eq_ignore_ascii_case(hex)
+        {
+            return Some(data);
+        }
+
+        crate::ui::warn!(
+            "Cached artifact {} is corrupt, downloading it again",
+            crate::ui::path(path.display())
+        );
+        let _ = fs::remove_file(&path);
+        None
+    }
+
+    /// Store `data` under `digest`, compressed unless it already is (e.g. a
+    /// tarball). Best effort: a cache that can't be written only costs a
+    /// download next time.
+    pub fn put(&self, digest: &str, data: &[u8]) {
+        let Some(hex) = digest.strip_prefix("sha256:") else {
+            return;
+        };
+        if fs::create_dir_all(&self.dir).is_err() {
+            return;
+        }
+
+        let compressed = self
+            .compression
+            .as_ref()
+            .filter(|_| !archive::is_packed(data))
+            .and_then(|compression| {
+                let compressed = compression.compress(data).ok()?;
+                Some((compression.extension(), compressed))
+            });
+        let (name, data) = match &compressed {
+            Some((extension, compressed)) => (
+                format!("{}.{}", entry_name(hex), extension),
+                compressed.as_slice(),
+            ),
+            None => (entry_name(hex), data),
+        };
+
+        // Write under a temporary name so a crash never leaves a truncated
+        // entry behind
+        let path = self.dir.join(&name);
+        let tmp = self.dir.join(format!(".{}.tmp", name));
+        if fs::write(&tmp, data).is_err() || fs::rename(&tmp, &path).is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+    }
+
+    /// Every entry with its size on disk and, when known without
+    /// decompressing it, its logical size, sorted by name.
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+        if !self.dir.exists() {
+            return Ok(entries);
+        }
+
+        for entry in fs::read_dir(&self.dir).context("Failed to read artifact cache")? {
+            let entry = entry.context("Failed to read artifact cache entry")?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some((_, compression)) = parse_entry_name(&name) else {
+                continue;
+            };
+            let physical_bytes = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            let logical_bytes = match compression {
+                Some(compression) => {
+                    read_header(&entry.path()).and_then(|header| compression.content_size(&header))
+                }
+                None => Some(physical_bytes),
+            };
+            entries.push(CacheEntry {
+                name,
+                logical_bytes,
+                physical_bytes,
+            });
+        }
+
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    /// Re-hash every entry, evicting those that don't match their digest
+    /// along with leftover temporary files and unrecognized names.
+    pub fn verify(&self) -> Result<VerifyReport> {
+        let mut report = VerifyReport::default();
+        if !self.dir.exists() {
+            return Ok(report);
+        }
+
+        for entry in fs::read_dir(&self.dir).context("Failed to read artifact cache")? {
+            let entry = entry.context("Failed to read artifact cache entry")?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let path = entry.path();
+
+            let valid_len = parse_entry_name(&name).and_then(|(hex, compression)| {
+                let data = fs::read(&path).ok()?;
+                let data = match compression {
+                    Some(compression) => compression.decompress(&data).ok()?,
+                    None => data,
+                };
+                sha256_hex(&data)
+                    .eq_ignore_ascii_case(hex)
+                    .then_some(data.len() as u64)
+            });
+
+            if let Some(len) = valid_len {
+                report.valid += 1;
+                report.valid_bytes += len;
+                report.physical_bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            } else {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to evict {}", path.display()))?;
+                report.evicted.push(name);
+            }
+        }
+
+        report.evicted.sort();
+        Ok(report)
+    }
+}
+
+fn entry_name(hex: &str) -> String {
+    format!("sha256-{}", hex.to_ascii_lowercase())
+}
+
+/// The digest hex of an entry file name and the compression it was stored
+/// with, or `None` for names that aren't entries.
+fn parse_entry_name(name: &str) -> Option<(&str, Option<&'static dyn CacheCompression>)> {
+    let name = name.strip_prefix("sha256-")?;
+    match name.split_once('.') {
+        None => Some((name, None)),
+        Some((hex, extension)) => DECOMPRESSORS
+            .iter()
+            .find(|compression| compression.extension() == extension)
+            .map(|compression| (hex, Some(*compression))),
+    }
+}
+
+/// Leading bytes of the file at `path`, enough for a compression header.
+fn read_header(path: &Path) -> Option<Vec<u8>> {
+    let mut header = Vec::with_capacity(32);
+    fs::File::open(path)
+        .ok()?
+        .take(32)
+        .read_to_end(&mut header)
+        .ok()?;
+    Some(header)
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest_of(data: &[u8]) -> String {
+        format!("sha256:{}", sha256_hex(data))
+    }
+
+    #[test]
+    fn get_with_stored_entry_returns_content() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cache = ArtifactCache::new(dir.path().to_path_buf());
+        let digest = digest_of(b"ampd");
+        cache.put(&digest, b"ampd");
+
+        //* When
+        let data = cache.get(&digest);
+
+        //* Then
+        assert_eq!(data.as_deref(), Some(&b"ampd"[..]));
+    }
+
+    #[test]
+    fn get_with_corrupt_entry_evicts_it() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cache = ArtifactCache::new(dir.path().to_path_buf());
+        let digest = digest_of(b"ampd");
+        let path = dir.path().join(entry_name(&sha256_hex(b"ampd")));
+        fs::write(&path, b"amp\0").expect("write corrupt entry");
+
+        //* When
+        let data = cache.get(&digest);
+
+        //* Then
+        assert!(data.is_none(), "corrupt entries should be a miss");
+        assert!(!path.exists(), "corrupt entries should be evicted");
+    }
+
+    #[test]
+    fn verify_with_mixed_entries_evicts_corrupt_and_keeps_valid() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cache = ArtifactCache::new(dir.path().to_path_buf());
+        cache.put(&digest_of(b"ampd"), b"ampd");
+        let corrupt = entry_name(&sha256_hex(b"ampctl"));
+        fs::write(dir.path().join(&corrupt), b"ampct1").expect("write corrupt entry");
+        fs::write(dir.path().join(".stale.tmp"), b"partial").expect("write stale file");
+
+        //* When
+        let report = cache.verify().expect("verify");
+
+        //* Then
+        assert_eq!(report.valid, 1);
+        assert_eq!(report.valid_bytes, 4);
+        assert_eq!(report.evicted, vec![".stale.tmp".to_string(), corrupt]);
+    }
+
+    #[test]
+    fn get_with_compressed_entry_returns_decompressed_content() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cache = ArtifactCache::new(dir.path().to_path_buf())
+            .with_compression(Some(Box::new(Zstd { level: 3 })));
+        let data = b"ampd ".repeat(1000);
+        let digest = digest_of(&data);
+        cache.put(&digest, &data);
+
+        //* When
+        let uncompressed = ArtifactCache::new(dir.path().to_path_buf());
+        let read = uncompressed.get(&digest);
+
+        //* Then
+        assert_eq!(read.as_deref(), Some(data.as_slice()));
+        let name = format!("{}.zst", entry_name(&sha256_hex(&data)));
+        assert!(
+            dir.path().join(&name).exists(),
+            "entry should be compressed"
+        );
+    }
+
+    #[test]
+    fn entries_with_compressed_entry_reports_logical_and_physical_sizes() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cache = ArtifactCache::new(dir.path().to_path_buf())
+            .with_compression(Some(Box::new(Zstd { level: 3 })));
+        let data = b"ampctl ".repeat(1000);
+        cache.put(&digest_of(&data), &data);
+
+        //* When
+        let entries = cache.entries().expect("list entries");
+
+        //* Then
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].logical_bytes, Some(data.len() as u64));
+        assert!(entries[0].physical_bytes < data.len() as u64);
+        let report = cache.verify().expect("verify");
+        assert_eq!(report.valid, 1);
+        assert_eq!(report.valid_bytes, data.len() as u64);
+        assert_eq!(report.physical_bytes, entries[0].physical_bytes);
+    }
+}
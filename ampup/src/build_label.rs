@@ -0,0 +1,244 @@
+/// Placeholders a label template may use.
+pub const PLACEHOLDERS: [&str; 4] = ["repo_slug", "kind", "ref", "hash"];
+
+/// Longest label a template may produce.
+const MAX_LABEL_LEN: usize = 100;
+
+#[derive(Debug)]
+pub enum LabelError {
+    /// The template names a placeholder that doesn't exist.
+    UnknownPlaceholder {
+        template: String,
+        placeholder: String,
+    },
+    /// The template opens a placeholder without closing it.
+    Unclosed { template: String },
+    /// The template or prefix can produce a label unsafe as a directory
+    /// name.
+    Unsafe { value: String, reason: String },
+    /// A custom build name lacks the configured prefix.
+    MissingPrefix { name: String, prefix: String },
+}
+
+impl std::fmt::Display for LabelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownPlaceholder {
+                template,
+                placeholder,
+            } => {
+                writeln!(f, "Unknown placeholder in build label template")?;
+                writeln!(f, "  Template: {}", template)?;
+                writeln!(f, "  Placeholder: {{{}}}", placeholder)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Known placeholders: {}",
+                    PLACEHOLDERS.map(|name| format!("{{{}}}", name)).join(", ")
+                )?;
+            }
+            Self::Unclosed { template } => {
+                writeln!(f, "Unclosed placeholder in build label template")?;
+                writeln!(f, "  Template: {}", template)?;
+                writeln!(f)?;
+                write!(f, "  Close each placeholder with '}}', e.g. {{ref}}")?;
+            }
+            Self::Unsafe { value, reason } => {
+                writeln!(f, "Build label is not a safe directory name")?;
+                writeln!(f, "  Label: {}", value)?;
+                writeln!(f, "  Reason: {}", reason)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Labels may only contain letters, digits, '.', '_', '+' and '-', and must start with a letter or digit."
+                )?;
+            }
+            Self::MissingPrefix { name, prefix } => {
+                writeln!(f, "Build name lacks the required prefix")?;
+                writeln!(f, "  Name: {}", name)?;
+                writeln!(f, "  Prefix: {}", prefix)?;
+                writeln!(f)?;
+                write!(f, "  Try: --name {}{}", prefix, name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LabelError {}
+
+/// What a source build's label is made of.
+#[derive(Debug, Clone, Copy)]
+pub struct LabelFields<'a> {
+    /// Repository as `owner-repo`, or `local`
+    pub repo_slug: &'a str,
+    /// `local`, `branch`, `commit`, `pr` or `main`
+    pub kind: &'a str,
+    /// Branch, pull request number, short commit or checkout directory name
+    pub reference: &'a str,
+    /// Short hash of the commit built, when known
+    pub hash: Option<&'a str>,
+}
+
+/// How source builds are labeled: the built-in scheme (e.g.
+/// `branch-fix-abc1234`) or a `build.label_template`, with
+/// `build.label_prefix` put in front of generated labels and required of
+/// custom names.
+#[derive(Debug, Clone, Default)]
+pub struct LabelScheme {
+    template: Option<String>,
+    prefix: Option<String>,
+}
+
+impl LabelScheme {
+    /// A scheme rendering `template` (the built-in scheme when `None`) and
+    /// enforcing `prefix`, both validated.
+    pub fn new(template: Option<String>, prefix: Option<String>) -> Result<Self, LabelError> {
+        if let Some(template) = &template {
+            validate_template(template)?;
+        }
+        if let Some(prefix) = &prefix {
+            check_safe(prefix)?;
+        }
+        Ok(Self { template, prefix })
+    }
+
+    /// Label for a build with `fields`, `default` being what the built-in
+    /// scheme produces.
+    pub fn label(&self, fields: &LabelFields, default: String) -> String {
+        let label = match &self.template {
+            Some(template) => render(template, fields),
+            None => default,
+        };
+        match &self.prefix {
+            Some(prefix) if !label.starts_with(prefix.as_str()) => format!("{}{}", prefix, label),
+            _ => label,
+        }
+    }
+
+    /// Fail when `name`, given with `--name`, lacks the required prefix.
+    pub fn check_name(&self, name: &str) -> Result<(), LabelError> {
+        match &self.prefix {
+            Some(prefix) if !name.starts_with(prefix.as_str()) => Err(LabelError::MissingPrefix {
+                name: name.to_string(),
+                prefix: prefix.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Check that `template` only uses known placeholders and that what it
+/// renders is a safe directory name.
+pub fn validate_template(template: &str) -> Result<(), LabelError> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| LabelError::Unclosed {
+                template: template.to_string(),
+            })?;
+        let placeholder = &rest[start + 1..start + end];
+        if !PLACEHOLDERS.contains(&placeholder) {
+            return Err(LabelError::UnknownPlaceholder {
+                template: template.to_string(),
+                placeholder: placeholder.to_string(),
+            });
+        }
+        rest = &rest[start + end + 1..];
+    }
+    let sample = LabelFields {
+        repo_slug: "edgeandnode-amp",
+        kind: "branch",
+        reference: "main",
+        hash: None,
+    };
+    check_safe(&render(template, &sample))
+}
+
+/// `template` with its placeholders replaced by `fields`, made safe as a
+/// directory name. Separators left around an empty `{hash}` are dropped.
+fn render(template: &str, fields: &LabelFields) -> String {
+    let rendered = template
+        .replace("{repo_slug}", fields.repo_slug)
+        .replace("{kind}", fields.kind)
+        .replace("{ref}", fields.reference)
+        .replace("{hash}", fields.hash.unwrap_or_default());
+    let sanitized: String = rendered
+        .chars()
+        .map(|c| if is_safe_char(c) { c } else { '-' })
+        .collect();
+    let mut label = String::with_capacity(sanitized.len());
+    for c in sanitized.chars() {
+        if !(c == '-' && label.ends_with('-')) {
+            label.push(c);
+        }
+    }
+    label.trim_matches('-').to_string()
+}
+
+fn is_safe_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '+' | '-')
+}
+
+fn check_safe(value: &str) -> Result<(), LabelError> {
+    let unsafe_because = |reason: &str| LabelError::Unsafe {
+        value: value.to_string(),
+        reason: reason.to_string(),
+    };
+    if value.is_empty() {
+        return Err(unsafe_because("it is empty"));
+    }
+    if value.len() > MAX_LABEL_LEN {
+        return Err(unsafe_because("it is longer than 100 characters"));
+    }
+    if !value.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Err(unsafe_because("it doesn't start with a letter or digit"));
+    }
+    if !value.chars().all(is_safe_char) {
+        return Err(unsafe_because("it contains other characters"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_with_template_and_prefix_renders_safe_prefixed_label() {
+        //* Given
+        let scheme = LabelScheme::new(
+            Some("{repo_slug}-{kind}-{ref}-{hash}".to_string()),
+            Some("acme-".to_string()),
+        )
+        .expect("should accept template");
+        let fields = LabelFields {
+            repo_slug: "acme-amp",
+            kind: "branch",
+            reference: "feature/new-thing",
+            hash: None,
+        };
+
+        //* When
+        let label = scheme.label(&fields, "branch-feature-new-thing".to_string());
+
+        //* Then
+        assert_eq!(label, "acme-amp-branch-feature-new-thing");
+        assert!(scheme.check_name("nightly-test").is_err());
+    }
+
+    #[test]
+    fn validate_template_with_unknown_placeholder_or_unsafe_literal_fails() {
+        //* When
+        let unknown = validate_template("{repo}-{ref}");
+        let unsafe_literal = validate_template("../{ref}");
+
+        //* Then
+        assert!(matches!(
+            unknown,
+            Err(LabelError::UnknownPlaceholder { placeholder, .. }) if placeholder == "repo"
+        ));
+        assert!(matches!(unsafe_literal, Err(LabelError::Unsafe { .. })));
+    }
+}
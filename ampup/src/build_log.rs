@@ -0,0 +1,259 @@
+//! Full output of source builds, written to `~/.amp/logs/` so a failed
+//! build can be looked into after the terminal (or CI job) is gone.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::ui;
+
+/// Lines of the log shown on a failure when output is summarized.
+const FAILURE_TAIL_LINES: usize = 30;
+
+/// Log a build's git and cargo output is appended to while it's shown on
+/// the terminal, in full or, when summarizing, only the errors.
+pub struct BuildLog {
+    path: PathBuf,
+    file: Mutex<fs::File>,
+    started: String,
+    summarize: bool,
+}
+
+impl BuildLog {
+    /// Start `build-<label>-<timestamp>.log` in `logs_dir`, or
+    /// `build-<timestamp>.log` until [`BuildLog::set_label`] names it.
+    pub fn create(logs_dir: &Path, label: Option<&str>, summarize: bool) -> Result<Self> {
+        fs::create_dir_all(logs_dir).context("Failed to create logs directory")?;
+        let started = timestamp(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+        );
+        let path = unused_path(logs_dir.join(file_name(label, &started)))?;
+        let file = fs::File::create(&path).context("Failed to create build log")?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            started,
+            summarize,
+        })
+    }
+
+    /// Where the log is written.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Rename the log after the version being built, once it's known.
+    pub fn set_label(&mut self, label: &str) -> Result<()> {
+        let renamed = unused_path(
+            self.path
+                .with_file_name(file_name(Some(label), &self.started)),
+        )?;
+        fs::rename(&self.path, &renamed).context("Failed to rename build log")?;
+        self.path = renamed;
+        Ok(())
+    }
+
+    /// Run `command`, appending its output to the log and passing it on to
+    /// the terminal. The command is always waited for; a failure to write
+    /// the log is reported after it exits.
+    pub fn run(&self, command: &mut Command) -> Result<ExitStatus> {
+        self.run_with(command, std::io::stdout(), std::io::stderr())
+    }
+
+    /// [`BuildLog::run`], echoing to `stdout` and `stderr` instead of the
+    /// terminal.
+    fn run_with(
+        &self,
+        command: &mut Command,
+        stdout: impl Write + Send,
+        stderr: impl Write,
+    ) -> Result<ExitStatus> {
+        self.write(format!("$ {:?}\n", command).as_bytes())?;
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let copied = match (child.stdout.take(), child.stderr.take()) {
+            (Some(child_out), Some(child_err)) => std::thread::scope(|scope| {
+                let out = scope.spawn(|| self.copy(child_out, stdout));
+                let err = self.copy(child_err, stderr);
+                let out = out
+                    .join()
+                    .map_err(|_| anyhow::anyhow!("Build log output thread panicked"))?;
+                out.and(err)
+            }),
+            _ => {
+                let _ = child.kill();
+                Err(anyhow::anyhow!("Failed to capture command output"))
+            }
+        };
+        let status = child.wait().context("Failed to wait for command")?;
+        copied?;
+        Ok(status)
+    }
+
+    /// After a failure, show the last lines of a summarized log, whose
+    /// output the terminal mostly didn't see.
+    pub fn show_tail(&self) {
+        if !self.summarize {
+            return;
+        }
+        let log = fs::read_to_string(&self.path).unwrap_or_default();
+        let lines: Vec<&str> = log.lines().collect();
+        ui::detail!("Last lines of the build log:");
+        for line in &lines[lines.len().saturating_sub(FAILURE_TAIL_LINES)..] {
+            ui::detail!("  {}", line);
+        }
+    }
+
+    /// Append each line of `output` to the log, echoing it to `terminal`
+    /// unless summarizing and it isn't an error (from cargo or git). Keeps
+    /// reading after a failed write, so the command never blocks on a full
+    /// pipe, and returns the first failure at the end.
+    fn copy(&self, output: impl Read, mut terminal: impl Write) -> Result<()> {
+        let mut output = BufReader::new(output);
+        let mut line = Vec::new();
+        let mut failed = None;
+        loop {
+            line.clear();
+            match output.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(err) => {
+                    failed.get_or_insert(anyhow::Error::from(err));
+                    break;
+                }
+            }
+            if let Err(err) = self.write(&line) {
+                failed.get_or_insert(err);
+            }
+            if !self.summarize || line.starts_with(b"error") || line.starts_with(b"fatal") {
+                // The terminal going away mustn't fail the build
+                let _ = terminal.write_all(&line);
+            }
+        }
+        failed.map_or(Ok(()), Err)
+    }
+
+    fn write(&self, bytes: &[u8]) -> Result<()> {
+        self.file
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Build log lock poisoned"))?
+            .write_all(bytes)
+            .context("Failed to write build log")
+    }
+}
+
+/// `build-<label>-<started>.log`, with the label's `/` made file-name safe.
+fn file_name(label: Option<&str>, started: &str) -> String {
+    match label {
+        Some(label) => format!("build-{}-{}.log", label.replace('/', "-"), started),
+        None => format!("build-{}.log", started),
+    }
+}
+
+/// `path`, or when a log started in the same second has it,
+/// `<stem>-2.log`, `<stem>-3.log` and so on.
+fn unused_path(path: PathBuf) -> Result<PathBuf> {
+    if !path.exists() {
+        return Ok(path);
+    }
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    (2..)
+        .map(|n| path.with_file_name(format!("{}-{}.log", stem, n)))
+        .find(|candidate| !candidate.exists())
+        .context("No unused build log name")
+}
+
+/// `unix_secs` as a sortable UTC timestamp, e.g. `20250305-143000`.
+fn timestamp(unix_secs: u64) -> String {
+    let (year, month, day) = ui::civil_date(unix_secs);
+    let secs = unix_secs % 86_400;
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp_with_known_time_formats_utc_date_and_time() {
+        //* Then
+        assert_eq!(timestamp(1_741_185_000), "20250305-143000");
+    }
+
+    #[test]
+    fn file_name_with_version_key_replaces_slash() {
+        //* Then
+        assert_eq!(
+            file_name(Some("local/local-ab12cd34"), "20250305-143000"),
+            "build-local-local-ab12cd34-20250305-143000.log"
+        );
+        assert_eq!(
+            file_name(None, "20250305-143000"),
+            "build-20250305-143000.log"
+        );
+    }
+
+    #[test]
+    fn run_with_summarize_logs_everything_and_echoes_only_errors() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let log = BuildLog::create(dir.path(), Some("local/test"), true)
+            .expect("should create build log");
+        let mut command = Command::new("sh");
+        command.args([
+            "-c",
+            "echo 'Compiling ampd'; echo 'error: mismatched types' >&2; \
+             echo 'warning: unused import' >&2; echo 'fatal: not a git repository' >&2",
+        ]);
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+
+        //* When
+        let status = log
+            .run_with(&mut command, &mut stdout, &mut stderr)
+            .expect("should run command");
+
+        //* Then
+        assert!(status.success());
+        let contents = fs::read_to_string(log.path()).expect("should read build log");
+        for line in [
+            "Compiling ampd",
+            "error: mismatched types",
+            "warning: unused import",
+            "fatal: not a git repository",
+        ] {
+            assert!(contents.contains(line), "log should contain {:?}", line);
+        }
+        assert!(
+            stdout.is_empty(),
+            "progress shouldn't be echoed: {:?}",
+            stdout
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&stderr),
+            "error: mismatched types\nfatal: not a git repository\n"
+        );
+    }
+}
@@ -1,13 +1,30 @@
 use std::{
     fmt,
     path::{Path, PathBuf},
-    process::{Command, Stdio},
+    process::{Command, ExitStatus, Stdio},
+    sync::Arc,
 };
 
 use anyhow::{Context, Result};
 use fs_err as fs;
-
-use crate::{DEFAULT_REPO, ui, version_manager::VersionManager};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    DEFAULT_REPO,
+    build_label::{LabelFields, LabelScheme},
+    build_log::BuildLog,
+    environment::{Environment, ProcessEnv},
+    github::PullRequestHead,
+    health,
+    heartbeat::Heartbeat,
+    manifest::{VersionManifest, binary_checksums},
+    policy::PolicyError,
+    reproducible::BuildRecipe,
+    transaction::InstallTransaction,
+    ui,
+    version_manager::VersionManager,
+    version_store::{LOCAL_NAMESPACE, repo_namespace, split_key, version_key},
+};
 
 #[derive(Debug)]
 pub enum BuildError {
@@ -30,7 +47,17 @@ pub enum BuildError {
     GitFetchPrFailed {
         pr: u32,
     },
-    CargoBuildFailed,
+    PrHeadMismatch {
+        pr: u32,
+        expected: String,
+        actual: String,
+    },
+    GitFetchFailed {
+        reference: String,
+    },
+    CargoBuildFailed {
+        log: Option<PathBuf>,
+    },
     BinaryNotFound {
         path: PathBuf,
     },
@@ -93,10 +120,35 @@ impl std::fmt::Display for BuildError {
                 writeln!(f)?;
                 writeln!(f, "  Ensure the pull request exists and is accessible.")?;
             }
-            Self::CargoBuildFailed => {
+            Self::PrHeadMismatch {
+                pr,
+                expected,
+                actual,
+            } => {
+                writeln!(f, "Fetched pull request head doesn't match")?;
+                writeln!(f, "  PR: #{}", pr)?;
+                writeln!(f, "  Expected: {}", expected)?;
+                writeln!(f, "  Fetched: {}", actual)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  The pull request was probably updated during the build; try again."
+                )?;
+            }
+            Self::GitFetchFailed { reference } => {
+                writeln!(f, "Failed to fetch git reference")?;
+                writeln!(f, "  Reference: {}", reference)?;
+                writeln!(f)?;
+                writeln!(f, "  The branch or tag may not exist in the repository.")?;
+                writeln!(f, "  Pass --fresh if the cached clone is in a bad state.")?;
+            }
+            Self::CargoBuildFailed { log } => {
                 writeln!(f, "Cargo build failed")?;
+                if let Some(log) = log {
+                    writeln!(f, "  Full output: {}", log.display())?;
+                }
                 writeln!(f)?;
-                writeln!(f, "  Check the build output above for compilation errors.")?;
+                writeln!(f, "  Check the build output for compilation errors.")?;
                 writeln!(
                     f,
                     "  Ensure all dependencies are installed and the code compiles."
@@ -153,65 +205,134 @@ pub enum BuildSource {
     Branch { repo: String, branch: String },
     /// Build from a specific commit
     Commit { repo: String, commit: String },
-    /// Build from a pull request
-    Pr { repo: String, number: u32 },
+    /// Build from a release tag
+    Tag { repo: String, tag: String },
+    /// Build from a pull request, fetched from the repository its head
+    /// lives in when `head` was looked up and names another one (a fork)
+    Pr {
+        repo: String,
+        number: u32,
+        head: Option<PullRequestHead>,
+    },
     /// Build from main branch
     Main { repo: String },
 }
 
 impl BuildSource {
-    /// Generate version label for this build source
-    fn generate_version_label(&self, git_hash: Option<&str>, name: Option<&str>) -> String {
-        // Custom name always takes precedence
+    /// Namespace builds from this source are stored under: the
+    /// repository's, or `local` for a local checkout
+    pub(crate) fn namespace(&self) -> String {
+        match self {
+            Self::Local { .. } => LOCAL_NAMESPACE.to_string(),
+            Self::Branch { repo, .. }
+            | Self::Commit { repo, .. }
+            | Self::Tag { repo, .. }
+            | Self::Pr { repo, .. }
+            | Self::Main { repo } => repo_namespace(repo),
+        }
+    }
+
+    /// Where a rebuild clones the source from: the repository's URL under
+    /// `web_url`, or the local checkout's path
+    pub(crate) fn origin(&self, web_url: &str) -> String {
+        match self {
+            Self::Local { path } => fs::canonicalize(path)
+                .unwrap_or_else(|_| path.clone())
+                .display()
+                .to_string(),
+            Self::Pr {
+                head: Some(head), ..
+            } => format!("{}/{}.git", web_url, head.repo),
+            Self::Branch { repo, .. }
+            | Self::Commit { repo, .. }
+            | Self::Tag { repo, .. }
+            | Self::Pr { repo, .. }
+            | Self::Main { repo } => format!("{}/{}.git", web_url, repo),
+        }
+    }
+
+    /// Where the sources of this build are: a local directory, or the
+    /// repository and revision to check out
+    fn location(&self, web_url: &str) -> SourceLocation<'_> {
+        let (repo, revision) = match self {
+            Self::Local { path } => return SourceLocation::Local(path),
+            Self::Branch { repo, branch } => (repo, Revision::Ref(branch)),
+            Self::Tag { repo, tag } => (repo, Revision::Ref(tag)),
+            Self::Commit { repo, commit } => (repo, Revision::Commit(commit)),
+            Self::Pr {
+                repo,
+                number,
+                head: Some(head),
+            } if !head.repo.eq_ignore_ascii_case(repo) => (
+                repo,
+                Revision::Fork {
+                    number: *number,
+                    url: format!("{}/{}.git", web_url, head.repo),
+                    branch: &head.branch,
+                    sha: &head.sha,
+                },
+            ),
+            Self::Pr { repo, number, .. } => (repo, Revision::Pr(*number)),
+            Self::Main { repo } => (repo, Revision::Ref("HEAD")),
+        };
+        SourceLocation::Remote { repo, revision }
+    }
+
+    /// Generate the version key for this build source: its namespace and a
+    /// label naming the build (e.g. `edgeandnode-amp/branch-fix-abc1234`),
+    /// labeled by `scheme` unless a custom `name` is given
+    pub(crate) fn generate_version_key(
+        &self,
+        git_hash: Option<&str>,
+        name: Option<&str>,
+        scheme: &LabelScheme,
+    ) -> Result<String> {
+        // Custom name always takes precedence, but must carry the prefix
         if let Some(name) = name {
-            return name.to_string();
+            scheme.check_name(name)?;
+            return Ok(version_key(&self.namespace(), name));
         }
+        // Append git hash if available
+        let with_hash = |base: &str| match git_hash {
+            Some(hash) => format!("{}-{}", base, hash),
+            None => base.to_string(),
+        };
 
-        // Generate base label
-        let base = match self {
-            Self::Local { .. } => "local".to_string(),
-            Self::Branch { repo, branch } => {
-                if repo != DEFAULT_REPO {
-                    let slug = repo.replace('/', "-");
-                    format!("{}-branch-{}", slug, branch)
-                } else {
-                    format!("branch-{}", branch)
-                }
-            }
-            Self::Commit { repo, commit } => {
-                // Commit already has hash in it, don't append git hash later
-                let commit_hash = &commit[..8.min(commit.len())];
-                if repo != DEFAULT_REPO {
-                    let slug = repo.replace('/', "-");
-                    return format!("{}-commit-{}", slug, commit_hash);
-                } else {
-                    return format!("commit-{}", commit_hash);
-                }
-            }
-            Self::Pr { repo, number } => {
-                if repo != DEFAULT_REPO {
-                    let slug = repo.replace('/', "-");
-                    format!("{}-pr-{}", slug, number)
-                } else {
-                    format!("pr-{}", number)
-                }
+        let namespace = self.namespace();
+        let (kind, reference, default) = match self {
+            Self::Local { path } => {
+                let dir = fs::canonicalize(path)
+                    .ok()
+                    .and_then(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+                    .unwrap_or_else(|| "local".to_string());
+                ("local", dir, with_hash("local"))
             }
-            Self::Main { repo } => {
-                if repo != DEFAULT_REPO {
-                    let slug = repo.replace('/', "-");
-                    format!("{}-main", slug)
-                } else {
-                    "main".to_string()
-                }
+            Self::Branch { branch, .. } => (
+                "branch",
+                branch.clone(),
+                with_hash(&format!("branch-{}", branch)),
+            ),
+            // Commit already has hash in it, don't append git hash
+            Self::Commit { commit, .. } => {
+                let short = &commit[..8.min(commit.len())];
+                ("commit", short.to_string(), format!("commit-{}", short))
             }
+            Self::Pr { number, .. } => (
+                "pr",
+                number.to_string(),
+                with_hash(&format!("pr-{}", number)),
+            ),
+            Self::Main { .. } => ("main", "main".to_string(), with_hash("main")),
+            // Installed under the tag so it stands in for the release binaries
+            Self::Tag { tag, .. } => return Ok(version_key(&namespace, tag)),
         };
-
-        // Append git hash if available
-        if let Some(hash) = git_hash {
-            format!("{}-{}", base, hash)
-        } else {
-            base
-        }
+        let fields = LabelFields {
+            repo_slug: &namespace,
+            kind,
+            reference: &reference,
+            hash: git_hash,
+        };
+        Ok(version_key(&namespace, &scheme.label(&fields, default)))
     }
 }
 
@@ -233,7 +354,14 @@ impl fmt::Display for BuildSource {
                     write!(f, "commit: {}", commit)
                 }
             }
-            Self::Pr { repo, number } => {
+            Self::Tag { repo, tag } => {
+                if repo != DEFAULT_REPO {
+                    write!(f, "repository: {}, tag: {}", repo, tag)
+                } else {
+                    write!(f, "tag: {}", tag)
+                }
+            }
+            Self::Pr { repo, number, .. } => {
                 if repo != DEFAULT_REPO {
                     write!(f, "repository: {}, pull request #{}", repo, number)
                 } else {
@@ -251,28 +379,290 @@ impl fmt::Display for BuildSource {
     }
 }
 
+/// Where the sources of a build are
+enum SourceLocation<'a> {
+    /// A local checkout, built in place
+    Local(&'a PathBuf),
+    /// `revision` of a remote repository, built in its cached clone
+    Remote {
+        repo: &'a str,
+        revision: Revision<'a>,
+    },
+}
+
+/// Revision of a remote repository to build
+enum Revision<'a> {
+    /// A branch or tag name, or `HEAD` for the default branch
+    Ref(&'a str),
+    /// A full or abbreviated commit hash
+    Commit(&'a str),
+    /// The head of a pull request
+    Pr(u32),
+    /// The head commit `sha` of a pull request opened from `branch` of the
+    /// fork at `url`
+    Fork {
+        number: u32,
+        url: String,
+        branch: &'a str,
+        sha: &'a str,
+    },
+}
+
 /// Options for building ampd
 pub struct BuildOptions {
     /// Custom version name
     pub name: Option<String>,
     /// Number of CPU cores to use
     pub jobs: Option<usize>,
+    /// Web base URL to clone remote repositories from (e.g. https://github.com)
+    pub web_url: String,
+    /// Build reproducibly and record how in the version's manifest
+    pub reproducible: bool,
+    /// Build a local checkout even when it hasn't changed since its last build
+    pub force: bool,
+    /// Clone a remote repository afresh instead of updating its cached clone
+    pub fresh: bool,
+    /// Flags passed through to `cargo build`
+    pub cargo: CargoOptions,
+}
+
+/// Flags passed through to `cargo build`. Builds with anything but the
+/// defaults get a label suffix naming them, so they don't replace a default
+/// build of the same source.
+#[derive(Debug, Clone, Default)]
+pub struct CargoOptions {
+    /// Cargo profile to build with (default: `release`)
+    pub profile: Option<String>,
+    /// Features to enable, each possibly comma- or space-separated
+    pub features: Vec<String>,
+    /// Build without the packages' default features
+    pub no_default_features: bool,
+    /// Further arguments passed to cargo as given, after all others
+    pub extra_args: Vec<String>,
+}
+
+impl CargoOptions {
+    /// The profile to build with.
+    fn profile(&self) -> &str {
+        self.profile.as_deref().unwrap_or("release")
+    }
+
+    /// Full `cargo build` arguments for ampd and ampctl.
+    pub(crate) fn build_args(&self) -> Vec<String> {
+        let mut args = vec!["build".to_string()];
+        match self.profile() {
+            "release" => args.push("--release".to_string()),
+            profile => args.extend(["--profile".to_string(), profile.to_string()]),
+        }
+        args.extend(["-p", "ampd", "-p", "ampctl"].map(String::from));
+        args.extend(self.args());
+        args.extend(self.extra_args.iter().cloned());
+        args
+    }
+
+    /// Directory cargo writes the profile's binaries to: under `target/` in
+    /// `repo_path`, or the `--target-dir` among the extra arguments, and in
+    /// a subdirectory named after their `--target`, if any.
+    pub(crate) fn output_dir(&self, repo_path: &Path) -> PathBuf {
+        let mut dir = match self.extra_arg("--target-dir") {
+            Some(target_dir) => repo_path.join(target_dir),
+            None => repo_path.join("target"),
+        };
+        if let Some(target) = self.extra_arg("--target") {
+            dir.push(target);
+        }
+        dir.join(profile_dir(self.profile()))
+    }
+
+    /// Value of `flag` among the extra arguments, as `--flag value` or
+    /// `--flag=value`; the last one wins, as with cargo.
+    fn extra_arg(&self, flag: &str) -> Option<&str> {
+        let mut value = None;
+        let mut args = self.extra_args.iter();
+        while let Some(arg) = args.next() {
+            if arg == flag {
+                value = args.next().map(String::as_str);
+            } else if let Some(rest) = arg
+                .strip_prefix(flag)
+                .and_then(|rest| rest.strip_prefix('='))
+            {
+                value = Some(rest);
+            }
+        }
+        value
+    }
+
+    /// Enabled features, split, sorted and deduplicated.
+    fn feature_list(&self) -> Vec<&str> {
+        let mut features: Vec<&str> = self
+            .features
+            .iter()
+            .flat_map(|features| features.split([',', ' ']))
+            .map(str::trim)
+            .filter(|feature| !feature.is_empty())
+            .collect();
+        features.sort_unstable();
+        features.dedup();
+        features
+    }
+
+    /// Feature arguments to add to `cargo build`.
+    fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let features = self.feature_list();
+        if !features.is_empty() {
+            args.push("--features".to_string());
+            args.push(features.join(","));
+        }
+        if self.no_default_features {
+            args.push("--no-default-features".to_string());
+        }
+        args
+    }
+
+    /// Suffix for the labels of builds with these options, e.g.
+    /// `+dev.nodefault.otel`; empty with the defaults.
+    pub(crate) fn label_suffix(&self) -> String {
+        let mut parts = Vec::new();
+        if self.profile() != "release" {
+            parts.push(self.profile().to_string());
+        }
+        if self.no_default_features {
+            parts.push("nodefault".to_string());
+        }
+        // `dep/feature` names a dependency's feature; `/` can't be in a label
+        parts.extend(
+            self.feature_list()
+                .iter()
+                .map(|feature| feature.replace('/', "_")),
+        );
+        match parts.is_empty() {
+            true => String::new(),
+            false => format!("+{}", parts.join(".")),
+        }
+    }
+}
+
+/// Directory under `target/` cargo writes `profile`'s binaries to: `debug`
+/// for `dev` and `test`, `release` for `release` and `bench`, and the
+/// profile's own name otherwise.
+pub(crate) fn profile_dir(profile: &str) -> &str {
+    match profile {
+        "dev" | "test" => "debug",
+        "bench" => "release",
+        profile => profile,
+    }
 }
 
 /// Builder for ampd from source
 pub struct Builder {
     version_manager: VersionManager,
+    env: Arc<dyn Environment>,
+    smoke_tests: Vec<String>,
+    /// Refuse local checkouts with uncommitted changes
+    clean_builds: bool,
+    /// How builds without a custom name are labeled
+    label_scheme: LabelScheme,
+    /// Show only errors of git and cargo on the terminal; the build log
+    /// has everything
+    summarize_output: bool,
+    /// Switch to the version once built, rather than only installing it
+    activate: bool,
 }
 
 impl Builder {
     pub fn new(version_manager: VersionManager) -> Self {
-        Self { version_manager }
+        Self::with_env(version_manager, Arc::new(ProcessEnv))
+    }
+
+    /// Create a builder that finds `git` and `cargo` through `env`
+    pub fn with_env(version_manager: VersionManager, env: Arc<dyn Environment>) -> Self {
+        Self {
+            version_manager,
+            env,
+            smoke_tests: health::DEFAULT_SMOKE_TESTS
+                .iter()
+                .map(|command| command.to_string())
+                .collect(),
+            clean_builds: false,
+            label_scheme: LabelScheme::default(),
+            summarize_output: false,
+            activate: true,
+        }
+    }
+
+    /// Switch to each version once built (the default), or only install it.
+    pub fn with_activation(mut self, activate: bool) -> Self {
+        self.activate = activate;
+        self
+    }
+
+    /// Run `commands` as smoke tests after building instead of the defaults.
+    pub fn with_smoke_tests(mut self, commands: Vec<String>) -> Self {
+        self.smoke_tests = commands;
+        self
+    }
+
+    /// Refuse to build local checkouts with uncommitted changes.
+    pub fn with_clean_builds(mut self, clean_builds: bool) -> Self {
+        self.clean_builds = clean_builds;
+        self
+    }
+
+    /// Show only the errors of git and cargo instead of their full output,
+    /// which is still written to the build log.
+    pub fn with_summarized_output(mut self, summarize: bool) -> Self {
+        self.summarize_output = summarize;
+        self
+    }
+
+    /// Start a log in `~/.amp/logs/` for a build of `version_key`, or of a
+    /// version named later.
+    fn start_log(&self, version_key: Option<&str>) -> Result<BuildLog> {
+        BuildLog::create(
+            &self.version_manager.config().logs_dir(),
+            version_key,
+            self.summarize_output,
+        )
+    }
+
+    /// Label builds without a custom name with `scheme`.
+    pub fn with_label_scheme(mut self, scheme: LabelScheme) -> Self {
+        self.label_scheme = scheme;
+        self
+    }
+
+    /// Key to install a build of `source` at `git_hash` under: the custom
+    /// name as given, or a generated label marked `-dirty` for a checkout
+    /// with uncommitted changes, then the cargo options' suffix.
+    fn version_key(
+        &self,
+        source: &BuildSource,
+        git_hash: Option<&str>,
+        dirty: bool,
+        options: &BuildOptions,
+    ) -> Result<String> {
+        let key =
+            source.generate_version_key(git_hash, options.name.as_deref(), &self.label_scheme)?;
+        Ok(match options.name {
+            Some(_) => key,
+            None => format!(
+                "{}{}{}",
+                key,
+                if dirty { "-dirty" } else { "" },
+                options.cargo.label_suffix()
+            ),
+        })
     }
 
     /// Execute the build for a given source
     pub async fn build(&self, source: BuildSource, options: BuildOptions) -> Result<()> {
-        match &source {
-            BuildSource::Local { path } => {
+        let env = self.env.as_ref();
+        let origin = options
+            .reproducible
+            .then(|| source.origin(&options.web_url));
+        match source.location(&options.web_url) {
+            SourceLocation::Local(path) => {
                 // Validate path exists and is a directory
                 if !path.exists() {
                     return Err(BuildError::LocalPathNotFound { path: path.clone() }.into());
@@ -282,104 +672,158 @@ impl Builder {
                 }
 
                 // Check for git repository and extract commit hash
-                let git = GitRepo::new(path);
+                let git = GitRepo::new(path, env);
                 let git_hash = git.get_commit_hash()?;
 
                 // If not a git repo and no custom name provided, error out
                 if git_hash.is_none() && options.name.is_none() {
                     return Err(BuildError::LocalPathNotGitRepo { path: path.clone() }.into());
                 }
+                let dirty = git_hash.is_some() && git.is_dirty()?;
+                if self.clean_builds && (git_hash.is_none() || dirty) {
+                    return Err(PolicyError::DirtyBuild { path: path.clone() }.into());
+                }
 
                 // Generate version label and build
-                let version_label =
-                    source.generate_version_label(git_hash.as_deref(), options.name.as_deref());
-                build_and_install(&self.version_manager, path, &version_label, options.jobs)?;
-
-                Ok(())
-            }
-            BuildSource::Branch { repo, branch } => {
-                let temp_dir =
-                    tempfile::tempdir().context("Failed to create temporary directory")?;
+                let version_key =
+                    self.version_key(&source, git_hash.as_deref(), dirty, &options)?;
+                if dirty {
+                    ui::warn!(
+                        "{} has uncommitted changes; building them as {}",
+                        path.display(),
+                        ui::version(&version_key)
+                    );
+                }
 
-                // Clone repository with specific branch
-                let git = GitRepo::clone(repo, temp_dir.path(), Some(branch.as_str())).await?;
+                // Skip cargo when the last build of this key saw the same sources
+                let fingerprint = match git_hash {
+                    Some(_) => Some(git.source_fingerprint()?),
+                    None => None,
+                };
+                if let Some(fingerprint) = &fingerprint
+                    && !options.force
+                    && is_up_to_date(
+                        &self.version_manager,
+                        &version_key,
+                        fingerprint,
+                        options.reproducible,
+                    )?
+                {
+                    if self.activate
+                        && self.version_manager.get_current()?.as_deref() != Some(&version_key)
+                    {
+                        self.version_manager.activate(&version_key)?;
+                    }
+                    ui::success!(
+                        "ampd and ampctl {} are up to date, skipped the build",
+                        ui::version(&version_key)
+                    );
+                    ui::detail!("Nothing changed since the last build; pass --force to rebuild");
+                    return Ok(());
+                }
 
-                // Extract git commit hash, generate version label, and build
-                let git_hash = git.get_commit_hash()?;
-                let version_label =
-                    source.generate_version_label(git_hash.as_deref(), options.name.as_deref());
+                let log = self.start_log(Some(&version_key))?;
                 build_and_install(
                     &self.version_manager,
-                    temp_dir.path(),
-                    &version_label,
+                    path,
+                    &version_key,
                     options.jobs,
+                    &options.cargo,
+                    &self.smoke_tests,
+                    env,
+                    origin.as_deref(),
+                    Some(&log),
+                    self.activate,
                 )?;
 
-                Ok(())
-            }
-            BuildSource::Commit { repo, commit } => {
-                let temp_dir =
-                    tempfile::tempdir().context("Failed to create temporary directory")?;
-
-                // Clone repository and checkout specific commit
-                let git = GitRepo::clone(repo, temp_dir.path(), None).await?;
-                git.checkout_commit(commit)?;
-
-                // Extract git commit hash, generate version label, and build
-                let git_hash = git.get_commit_hash()?;
-                let version_label =
-                    source.generate_version_label(git_hash.as_deref(), options.name.as_deref());
-                build_and_install(
-                    &self.version_manager,
-                    temp_dir.path(),
-                    &version_label,
-                    options.jobs,
-                )?;
+                if let Some(fingerprint) = fingerprint {
+                    let dirty_diff = match dirty {
+                        true => Some(git.changes_hash()?),
+                        false => None,
+                    };
+                    let version_dir = self.version_manager.version_dir(&version_key);
+                    let mut manifest = VersionManifest::load(&version_dir)?;
+                    manifest.source_fingerprint = Some(fingerprint);
+                    manifest.dirty_diff = dirty_diff;
+                    manifest.save(&version_dir)?;
+                }
 
                 Ok(())
             }
-            BuildSource::Pr { repo, number } => {
-                let temp_dir =
-                    tempfile::tempdir().context("Failed to create temporary directory")?;
-
-                // Clone repository and checkout pull request
-                let git = GitRepo::clone(repo, temp_dir.path(), None).await?;
-                git.fetch_and_checkout_pr(*number)?;
+            SourceLocation::Remote { repo, revision } => {
+                let mut log = self.start_log(None)?;
+                let path = self.checkout(repo, revision, &options, &log).await?;
 
                 // Extract git commit hash, generate version label, and build
+                let git = GitRepo::new(&path, env);
                 let git_hash = git.get_commit_hash()?;
-                let version_label =
-                    source.generate_version_label(git_hash.as_deref(), options.name.as_deref());
+                let version_key =
+                    self.version_key(&source, git_hash.as_deref(), false, &options)?;
+                log.set_label(&version_key)?;
                 build_and_install(
                     &self.version_manager,
-                    temp_dir.path(),
-                    &version_label,
+                    &path,
+                    &version_key,
                     options.jobs,
+                    &options.cargo,
+                    &self.smoke_tests,
+                    env,
+                    origin.as_deref(),
+                    Some(&log),
+                    self.activate,
                 )?;
 
                 Ok(())
             }
-            BuildSource::Main { repo } => {
-                let temp_dir =
-                    tempfile::tempdir().context("Failed to create temporary directory")?;
-
-                // Clone repository (main branch)
-                let git = GitRepo::clone(repo, temp_dir.path(), None).await?;
+        }
+    }
 
-                // Extract git commit hash, generate version label, and build
-                let git_hash = git.get_commit_hash()?;
-                let version_label =
-                    source.generate_version_label(git_hash.as_deref(), options.name.as_deref());
-                build_and_install(
-                    &self.version_manager,
-                    temp_dir.path(),
-                    &version_label,
-                    options.jobs,
-                )?;
+    /// Check out `revision` of `repo` in its cached clone under
+    /// `~/.amp/cache/src/`, cloning it first when there is none (or with
+    /// `options.fresh`). The clone's `target/` is kept between builds, so
+    /// cargo only rebuilds what changed.
+    async fn checkout(
+        &self,
+        repo: &str,
+        revision: Revision<'_>,
+        options: &BuildOptions,
+        log: &BuildLog,
+    ) -> Result<PathBuf> {
+        let env = self.env.as_ref();
+        let repo_url = format!("{}/{}.git", options.web_url, repo);
+        let path = self
+            .version_manager
+            .config()
+            .source_cache_dir()
+            .join(repo_namespace(repo));
+
+        let reusable = !options.fresh && GitRepo::new(&path, env).get_commit_hash()?.is_some();
+        if reusable {
+            ui::info!("Updating cached clone of {}", repo);
+            ui::detail!("Clone: {}", ui::path(path.display()));
+            let git = GitRepo::new(&path, env).with_log(log);
+            git.set_remote_url(&repo_url)?;
+            git.fetch_and_checkout(&revision)?;
+            return Ok(path);
+        }
 
-                Ok(())
-            }
+        if path.exists() {
+            fs::remove_dir_all(&path).context("Failed to remove cached clone")?;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create source cache directory")?;
         }
+        let cloned = async {
+            let git = GitRepo::clone_shallow(&repo_url, &path, env, log).await?;
+            git.fetch_and_checkout(&revision)
+        }
+        .await;
+        if let Err(err) = cloned {
+            // Don't leave a partial clone to be reused by the next build
+            let _ = fs::remove_dir_all(&path);
+            return Err(err);
+        }
+        Ok(path)
     }
 }
 
@@ -387,48 +831,117 @@ impl Builder {
 pub struct GitRepo<'a> {
     path: &'a Path,
     remote: String,
+    env: &'a dyn Environment,
+    log: Option<&'a BuildLog>,
 }
 
 impl<'a> GitRepo<'a> {
     /// Create a new GitRepo instance for an existing repository
-    pub fn new(path: &'a Path) -> Self {
+    pub fn new(path: &'a Path, env: &'a dyn Environment) -> Self {
         Self {
             path,
             remote: "origin".to_string(),
+            env,
+            log: None,
         }
     }
 
-    /// Clone a repository from GitHub and create a GitRepo instance
-    pub async fn clone(repo: &str, destination: &'a Path, branch: Option<&str>) -> Result<Self> {
-        check_command_exists("git")?;
+    /// Write the output of clones, fetches and checkouts to `log`.
+    pub fn with_log(mut self, log: &'a BuildLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    /// Clone a repository from GitHub (or a GitHub Enterprise host) and create a GitRepo instance
+    pub async fn clone(
+        web_url: &str,
+        repo: &str,
+        destination: &'a Path,
+        branch: Option<&str>,
+        env: &'a dyn Environment,
+    ) -> Result<Self> {
+        let repo_url = format!("{}/{}.git", web_url, repo);
+        Self::clone_url(&repo_url, destination, branch, env).await
+    }
+
+    /// Clone the repository at `repo_url` (or a local path) and create a
+    /// GitRepo instance
+    pub async fn clone_url(
+        repo_url: &str,
+        destination: &'a Path,
+        branch: Option<&str>,
+        env: &'a dyn Environment,
+    ) -> Result<Self> {
+        Self::clone_with(repo_url, destination, branch, &[], env, None)
+    }
+
+    /// Clone only the latest commit of the repository at `repo_url`, without
+    /// file contents or a checkout, for `fetch_and_checkout` to fill in.
+    /// Local paths are cloned in full.
+    pub async fn clone_shallow(
+        repo_url: &str,
+        destination: &'a Path,
+        env: &'a dyn Environment,
+        log: &'a BuildLog,
+    ) -> Result<Self> {
+        Self::clone_with(
+            repo_url,
+            destination,
+            None,
+            &["--depth", "1", "--filter=blob:none", "--no-checkout"],
+            env,
+            Some(log),
+        )
+    }
 
-        let repo_url = format!("https://github.com/{}.git", repo);
+    fn clone_with(
+        repo_url: &str,
+        destination: &'a Path,
+        branch: Option<&str>,
+        options: &[&str],
+        env: &'a dyn Environment,
+        log: Option<&'a BuildLog>,
+    ) -> Result<Self> {
+        check_command_exists("git", env)?;
+        let repo = Self {
+            log,
+            ..Self::new(destination, env)
+        };
 
         ui::info!("Cloning {}", repo_url);
 
         let mut args = vec!["clone"];
+        args.extend(options);
 
         if let Some(branch) = branch {
             args.extend(["--branch", branch]);
         }
 
-        args.push(&repo_url);
-        args.push(destination.to_str().unwrap());
+        args.push(repo_url);
 
-        let status = Command::new("git")
-            .args(&args)
-            .status()
+        let _heartbeat = Heartbeat::start(format!("cloning {}", repo_url));
+        let status = repo
+            .run(env.command("git").args(&args).arg(destination))
             .context("Failed to execute git clone")?;
 
         if !status.success() {
             return Err(BuildError::GitCloneFailed {
-                repo: repo.to_string(),
+                repo: repo_url.to_string(),
                 branch: branch.map(|s| s.to_string()),
             }
             .into());
         }
 
-        Ok(Self::new(destination))
+        Ok(repo)
+    }
+
+    /// Run `command` with its output going to the build log, if there is
+    /// one, or straight to the terminal.
+    fn run(&self, command: &mut Command) -> Result<ExitStatus> {
+        match self.log {
+            Some(log) => log.run(command),
+            None => Ok(command.status()?),
+        }
     }
 
     /// Get the commit hash from this repository
@@ -440,7 +953,9 @@ impl<'a> GitRepo<'a> {
         }
 
         // Try to get the commit hash
-        let output = Command::new("git")
+        let output = self
+            .env
+            .command("git")
             .args(["rev-parse", "--short=8", "HEAD"])
             .current_dir(self.path)
             .stdout(Stdio::piped())
@@ -460,9 +975,74 @@ impl<'a> GitRepo<'a> {
         Ok(Some(hash))
     }
 
+    /// Hash of the checkout's sources: the HEAD commit, uncommitted changes
+    /// to tracked files, and untracked files that aren't ignored
+    pub fn source_fingerprint(&self) -> Result<String> {
+        let mut hasher = Sha256::new();
+        hasher.update(self.git_stdout(&["rev-parse", "HEAD"])?);
+        self.hash_changes(&mut hasher)?;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Hash of the checkout's uncommitted changes to tracked files and its
+    /// untracked files that aren't ignored
+    pub fn changes_hash(&self) -> Result<String> {
+        let mut hasher = Sha256::new();
+        self.hash_changes(&mut hasher)?;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    fn hash_changes(&self, hasher: &mut Sha256) -> Result<()> {
+        hasher.update(self.git_stdout(&["diff", "HEAD", "--binary"])?);
+        let untracked = self.git_stdout(&["ls-files", "--others", "--exclude-standard", "-z"])?;
+        for name in untracked.split(|&b| b == 0).filter(|name| !name.is_empty()) {
+            hasher.update(name);
+            let path = self.path.join(String::from_utf8_lossy(name).as_ref());
+            // Unreadable entries (e.g. sockets) change the hash through their name only
+            if let Ok(contents) = fs::read(&path) {
+                hasher.update(Sha256::digest(&contents));
+            }
+        }
+        Ok(())
+    }
+
+    /// Output of a git command that must succeed.
+    fn git_stdout(&self, args: &[&str]) -> Result<Vec<u8>> {
+        let output = self
+            .env
+            .command("git")
+            .args(args)
+            .current_dir(self.path)
+            .stderr(Stdio::inherit())
+            .output()
+            .with_context(|| format!("Failed to execute git {}", args[0]))?;
+        if !output.status.success() {
+            anyhow::bail!("git {} failed with {}", args[0], output.status);
+        }
+        Ok(output.stdout)
+    }
+
+    /// Whether the working tree has uncommitted changes, including untracked
+    /// files
+    pub fn is_dirty(&self) -> Result<bool> {
+        let output = self
+            .env
+            .command("git")
+            .args(["status", "--porcelain"])
+            .current_dir(self.path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .context("Failed to execute git status")?;
+
+        Ok(!output.status.success() || !output.stdout.is_empty())
+    }
+
     /// Checkout a specific commit
     pub fn checkout_commit(&self, commit: &str) -> Result<()> {
-        let status = Command::new("git")
+        let status = self
+            .env
+            .command("git")
             .args(["checkout", commit])
             .current_dir(self.path)
             .status()
@@ -478,70 +1058,238 @@ impl<'a> GitRepo<'a> {
         Ok(())
     }
 
-    /// Fetch and checkout a pull request
-    pub fn fetch_and_checkout_pr(&self, number: u32) -> Result<()> {
-        // Fetch the PR
-        let pr_ref = format!("pull/{}/head:pr-{}", number, number);
-        let status = Command::new("git")
-            .args(["fetch", &self.remote, &pr_ref])
-            .current_dir(self.path)
-            .status()
-            .context("Failed to execute git fetch")?;
+    /// Point the remote at `url`, which may have changed since the clone
+    /// was made (e.g. a different `api_url`)
+    fn set_remote_url(&self, url: &str) -> Result<()> {
+        self.git_stdout(&["remote", "set-url", &self.remote, url])?;
+        Ok(())
+    }
 
-        if !status.success() {
-            return Err(BuildError::GitFetchPrFailed { pr: number }.into());
-        }
+    /// Fetch `revision` from the remote and check it out detached,
+    /// discarding local changes and untracked files but keeping ignored
+    /// ones such as `target/`
+    fn fetch_and_checkout(&self, revision: &Revision<'_>) -> Result<()> {
+        let target = match revision {
+            Revision::Ref(reference) => {
+                self.fetch(&["--depth", "1"], &self.remote, reference, || {
+                    BuildError::GitFetchFailed {
+                        reference: reference.to_string(),
+                    }
+                })?;
+                "FETCH_HEAD".to_string()
+            }
+            Revision::Pr(number) => {
+                let pr_ref = format!("pull/{}/head", number);
+                self.fetch(&["--depth", "1"], &self.remote, &pr_ref, || {
+                    BuildError::GitFetchPrFailed { pr: *number }
+                })?;
+                "FETCH_HEAD".to_string()
+            }
+            Revision::Fork {
+                number,
+                url,
+                branch,
+                sha,
+            } => {
+                // The PR's head commit rather than the branch, which may have
+                // moved on since or been deleted after a merge
+                ui::detail!(
+                    "Fetching {} ({}) from {}",
+                    branch,
+                    &sha[..sha.len().min(8)],
+                    url
+                );
+                let error = || BuildError::GitFetchPrFailed { pr: *number };
+                if self.fetch(&["--depth", "1"], url, sha, error).is_err() {
+                    ui::warn!(
+                        "Could not fetch the head of PR #{} from {}; fetching it from {}",
+                        number,
+                        url,
+                        self.remote
+                    );
+                    let pr_ref = format!("pull/{}/head", number);
+                    self.fetch(&["--depth", "1"], &self.remote, &pr_ref, error)?;
+                }
+                let fetched =
+                    String::from_utf8_lossy(&self.git_stdout(&["rev-parse", "FETCH_HEAD"])?)
+                        .trim()
+                        .to_string();
+                if !fetched.eq_ignore_ascii_case(sha) {
+                    return Err(BuildError::PrHeadMismatch {
+                        pr: *number,
+                        expected: sha.to_string(),
+                        actual: fetched,
+                    }
+                    .into());
+                }
+                fetched
+            }
+            Revision::Commit(commit) => {
+                self.fetch_commit(commit)?;
+                commit.to_string()
+            }
+        };
 
-        // Checkout the PR
-        let status = Command::new("git")
-            .args(["checkout", &format!("pr-{}", number)])
-            .current_dir(self.path)
-            .status()
+        let status = self
+            .run(
+                self.env
+                    .command("git")
+                    .args(["checkout", "--quiet", "--force", "--detach", &target])
+                    .current_dir(self.path),
+            )
             .context("Failed to execute git checkout")?;
-
         if !status.success() {
             return Err(BuildError::GitCheckoutFailed {
-                target: format!("PR #{}", number),
+                target: match revision {
+                    Revision::Pr(number) | Revision::Fork { number, .. } => {
+                        format!("PR #{}", number)
+                    }
+                    _ => target,
+                },
             }
             .into());
         }
 
+        self.git_stdout(&["clean", "--quiet", "-ffd"])?;
         Ok(())
     }
-}
 
-/// Build and install the ampd and ampctl binaries
-fn build_and_install(
-    version_manager: &VersionManager,
-    repo_path: &Path,
-    version_label: &str,
-    jobs: Option<usize>,
-) -> Result<()> {
-    check_command_exists("cargo")?;
+    /// Make `commit` available locally: it may be there from an earlier
+    /// build, a full hash can be fetched on its own, and otherwise it's looked
+    /// up in the full history of every branch
+    fn fetch_commit(&self, commit: &str) -> Result<()> {
+        if self.has_commit(commit)? {
+            return Ok(());
+        }
+        let full_hash = commit.len() == 40 && commit.chars().all(|c| c.is_ascii_hexdigit());
+        let error = || BuildError::GitFetchFailed {
+            reference: commit.to_string(),
+        };
+        if full_hash
+            && self
+                .fetch(&["--depth", "1"], &self.remote, commit, error)
+                .is_ok()
+        {
+            return Ok(());
+        }
 
-    ui::info!("Building ampd and ampctl");
+        ui::detail!(
+            "Commit {} isn't reachable in a shallow clone; fetching full history",
+            commit
+        );
+        let options: &[&str] = if self.is_shallow()? {
+            &["--unshallow"]
+        } else {
+            &[]
+        };
+        let branches = format!("+refs/heads/*:refs/remotes/{}/*", self.remote);
+        self.fetch(options, &self.remote, &branches, error)
+    }
 
-    let mut args = vec!["build", "--release", "-p", "ampd", "-p", "ampctl"];
+    /// Run `git fetch <options> <from> <refspec>`, where `from` is a remote
+    /// or a URL, failing with `error`.
+    fn fetch(
+        &self,
+        options: &[&str],
+        from: &str,
+        refspec: &str,
+        error: impl FnOnce() -> BuildError,
+    ) -> Result<()> {
+        let _heartbeat = Heartbeat::start(format!("fetching {}", refspec));
+        let status = self
+            .run(
+                self.env
+                    .command("git")
+                    .args(["fetch", "--quiet"])
+                    .args(options)
+                    .args([from, refspec])
+                    .current_dir(self.path),
+            )
+            .context("Failed to execute git fetch")?;
+        if !status.success() {
+            return Err(error().into());
+        }
+        Ok(())
+    }
 
-    let jobs_str;
-    if let Some(j) = jobs {
-        jobs_str = j.to_string();
-        args.extend(["-j", &jobs_str]);
+    /// Whether `commit` names a commit that's already in the repository
+    fn has_commit(&self, commit: &str) -> Result<bool> {
+        let status = self
+            .env
+            .command("git")
+            .args(["cat-file", "-e", &format!("{}^{{commit}}", commit)])
+            .current_dir(self.path)
+            .stderr(Stdio::null())
+            .status()
+            .context("Failed to execute git cat-file")?;
+        Ok(status.success())
     }
 
-    let status = Command::new("cargo")
-        .args(&args)
-        .current_dir(repo_path)
-        .status()
-        .context("Failed to execute cargo build")?;
+    /// Whether the repository is a shallow clone
+    fn is_shallow(&self) -> Result<bool> {
+        let output = self.git_stdout(&["rev-parse", "--is-shallow-repository"])?;
+        Ok(output.trim_ascii() == b"true")
+    }
+}
 
-    if !status.success() {
-        return Err(BuildError::CargoBuildFailed.into());
+/// Whether the build installed as `version_key` was made from sources with
+/// `fingerprint` (and reproducibly, when `reproducible` is asked for).
+fn is_up_to_date(
+    version_manager: &VersionManager,
+    version_key: &str,
+    fingerprint: &str,
+    reproducible: bool,
+) -> Result<bool> {
+    let version_dir = version_manager.version_dir(version_key);
+    if !["ampd", "ampctl"]
+        .iter()
+        .all(|binary| version_dir.join(binary).is_file())
+    {
+        return Ok(false);
     }
+    let manifest = VersionManifest::load(&version_dir)?;
+    Ok(manifest.source_fingerprint.as_deref() == Some(fingerprint)
+        && (!reproducible || manifest.build.is_some()))
+}
+
+/// Build and install the ampd and ampctl binaries, writing cargo's output
+/// to `log` when given, and switch to them when `activate`. With a
+/// `reproducible` origin, the build is pinned and its recipe recorded in the
+/// manifest.
+#[expect(clippy::too_many_arguments)]
+fn build_and_install(
+    version_manager: &VersionManager,
+    repo_path: &Path,
+    version_key: &str,
+    jobs: Option<usize>,
+    cargo: &CargoOptions,
+    smoke_tests: &[String],
+    env: &dyn Environment,
+    reproducible: Option<&str>,
+    log: Option<&BuildLog>,
+    activate: bool,
+) -> Result<()> {
+    check_command_exists("cargo", env)?;
+
+    // The recipe records the cargo arguments, so a rebuild uses them too
+    let mut recipe = reproducible
+        .map(|origin| BuildRecipe::prepare(origin, repo_path, cargo.build_args(), env))
+        .transpose()?;
+    if let Some(recipe) = &recipe {
+        ui::info!(
+            "Building ampd and ampctl reproducibly with {} for {}",
+            recipe.toolchain,
+            recipe.host
+        );
+    } else {
+        ui::info!("Building ampd and ampctl");
+    }
+    cargo_build(repo_path, jobs, recipe.as_ref(), cargo, env, log)?;
 
     // Find the built binaries
-    let ampd_source = repo_path.join("target/release/ampd");
-    let ampctl_source = repo_path.join("target/release/ampctl");
+    let output_dir = cargo.output_dir(repo_path);
+    let ampd_source = output_dir.join("ampd");
+    let ampctl_source = output_dir.join("ampctl");
 
     if !ampd_source.exists() {
         return Err(BuildError::BinaryNotFound {
@@ -557,11 +1305,12 @@ fn build_and_install(
         .into());
     }
 
-    let config = version_manager.config();
-
-    // Create version directory
-    let version_dir = config.versions_dir.join(version_label);
+    // Create version directory, removed again if a later step fails
+    let mut transaction = InstallTransaction::begin(version_manager.config())?;
+    version_manager.store().ensure_root()?;
+    let version_dir = version_manager.version_dir(version_key);
     fs::create_dir_all(&version_dir).context("Failed to create version directory")?;
+    transaction.remove_on_rollback(&version_dir);
 
     // Copy ampd binary
     let ampd_dest = version_dir.join("ampd");
@@ -593,21 +1342,89 @@ fn build_and_install(
             .context("Failed to set executable permissions on ampctl")?;
     }
 
+    let mut manifest = VersionManifest::load(&version_dir)?;
+    manifest.checksums = binary_checksums(&version_dir)?;
+    if let Some(recipe) = &mut recipe {
+        recipe.record_binaries(&version_dir)?;
+        manifest.build = Some(recipe.clone());
+    }
+    manifest.save(&version_dir)?;
+
+    // Check the binaries run before activating them
+    let (_, label) = split_key(version_key);
+    health::check(&version_dir, label, env)?;
+    // A version failing its smoke tests stays installed but inactive
+    transaction.keep(&version_dir);
+    health::smoke_test(&version_dir, version_key, smoke_tests, env)?;
+
     // Activate this version
-    version_manager.activate(version_label)?;
+    if activate {
+        version_manager.activate(version_key)?;
+    }
+    transaction.commit();
 
     ui::success!(
         "Built and installed ampd and ampctl {}",
-        ui::version(version_label)
+        ui::version(version_key)
     );
-    ui::detail!("Run 'ampd --version' and 'ampctl --version' to verify installation");
+    if let Some(log) = log {
+        ui::detail!("Build log: {}", ui::path(log.path().display()));
+    }
+    if !activate {
+        ui::detail!("Activate it with: ampup use {}", version_key);
+    }
+
+    Ok(())
+}
+
+/// Run `cargo build` for ampd and ampctl in `repo_path` with `cargo`'s
+/// flags, or following `recipe` (which records its own) for reproducible
+/// builds. With a `log`, the output is written there too.
+pub(crate) fn cargo_build(
+    repo_path: &Path,
+    jobs: Option<usize>,
+    recipe: Option<&BuildRecipe>,
+    cargo: &CargoOptions,
+    env: &dyn Environment,
+    log: Option<&BuildLog>,
+) -> Result<()> {
+    let mut command = env.command("cargo");
+    command.current_dir(repo_path);
+    match recipe {
+        Some(recipe) => {
+            command.args(&recipe.cargo_args);
+            recipe.apply(&mut command, repo_path, env);
+        }
+        None => {
+            command.args(cargo.build_args());
+        }
+    }
+    if let Some(jobs) = jobs {
+        command.args(["-j", &jobs.to_string()]);
+    }
 
+    let _heartbeat = Heartbeat::start("building ampd and ampctl");
+    let status = match log {
+        Some(log) => log.run(&mut command),
+        None => Ok(command.status()?),
+    }
+    .context("Failed to execute cargo build")?;
+    if !status.success() {
+        if let Some(log) = log {
+            log.show_tail();
+        }
+        return Err(BuildError::CargoBuildFailed {
+            log: log.map(|log| log.path().to_path_buf()),
+        }
+        .into());
+    }
     Ok(())
 }
 
 /// Check if a command exists
-fn check_command_exists(command: &str) -> Result<()> {
-    let status = Command::new(command)
+pub(crate) fn check_command_exists(command: &str, env: &dyn Environment) -> Result<()> {
+    let status = env
+        .command(command)
         .arg("--version")
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
@@ -0,0 +1,501 @@
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+    path::{Component, Path},
+};
+
+use anyhow::{Context, Result};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use fs_err as fs;
+use ring::signature::{ED25519, Ed25519KeyPair, KeyPair, UnparsedPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    platform::{Architecture, Platform},
+    portable::{self, is_safe_name},
+};
+
+/// Layout version of bundles, raised when it changes incompatibly.
+pub const BUNDLE_FORMAT: u32 = 1;
+
+/// Top-level directory of a bundle.
+const ROOT: &str = "amp-bundle";
+
+/// File describing what a bundle holds.
+pub const BUNDLE_FILE: &str = "bundle.json";
+
+/// File listing the SHA-256 of every other file, in `sha256sum` format.
+pub const CHECKSUMS_FILE: &str = "SHA256SUMS";
+
+/// Hex Ed25519 signature of [`CHECKSUMS_FILE`], in signed bundles.
+pub const SIGNATURE_FILE: &str = "SHA256SUMS.sig";
+
+/// File holding the ampup binary.
+pub const AMPUP_FILE: &str = "ampup";
+
+#[derive(Debug)]
+pub enum BundleError {
+    /// The file isn't laid out like a bundle.
+    NotABundle { reason: String },
+    /// The bundle was written by a newer ampup with another layout.
+    UnsupportedFormat { format: u32 },
+    /// An entry would be written outside the bundle or isn't a regular file.
+    UnsafeEntry { path: String },
+    /// A file doesn't match its checksum, or has none.
+    ChecksumMismatch { file: String },
+    /// Trusted keys were given but the bundle isn't signed by one of them.
+    Untrusted { reason: String },
+    /// The bundle's binaries don't run on this machine.
+    TargetMismatch {
+        target: String,
+        platform: String,
+        arch: String,
+    },
+    /// A signing or public key couldn't be read.
+    InvalidKey { reason: String },
+}
+
+impl std::fmt::Display for BundleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotABundle { reason } => {
+                writeln!(f, "File is not an ampup bundle")?;
+                writeln!(f, "  Reason: {}", reason)?;
+                writeln!(f)?;
+                write!(f, "  Create bundles with: ampup bundle create")?;
+            }
+            Self::UnsupportedFormat { format } => {
+                writeln!(f, "Bundle was written in a newer format")?;
+                writeln!(f, "  Format: {}", format)?;
+                writeln!(f, "  Supported: {}", BUNDLE_FORMAT)?;
+                writeln!(f)?;
+                write!(f, "  Install the ampup the bundle carries first.")?;
+            }
+            Self::UnsafeEntry { path } => {
+                writeln!(f, "Bundle contains an unsafe entry")?;
+                writeln!(f, "  Entry: {}", path)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Bundles only hold regular files under one top-level directory."
+                )?;
+            }
+            Self::ChecksumMismatch { file } => {
+                writeln!(f, "Bundled file doesn't match its checksum")?;
+                writeln!(f, "  File: {}", file)?;
+                writeln!(f)?;
+                write!(f, "  The bundle was damaged or modified; create it again.")?;
+            }
+            Self::Untrusted { reason } => {
+                writeln!(f, "Bundle is not signed by a trusted key")?;
+                writeln!(f, "  Reason: {}", reason)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Check --trusted-key against the public key of whoever created the bundle."
+                )?;
+            }
+            Self::TargetMismatch {
+                target,
+                platform,
+                arch,
+            } => {
+                writeln!(f, "Bundle is for another platform")?;
+                writeln!(f, "  Bundle: {}", target)?;
+                writeln!(f, "  This machine: {} {}", platform, arch)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Try: ampup bundle create --platform {} --arch {} on a connected machine",
+                    platform, arch
+                )?;
+            }
+            Self::InvalidKey { reason } => {
+                writeln!(f, "Invalid bundle signing key")?;
+                writeln!(f, "  Reason: {}", reason)?;
+                writeln!(f)?;
+                write!(f, "  Create a key pair with: ampup bundle keygen")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+/// What a bundle holds, kept in its [`BUNDLE_FILE`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleInfo {
+    /// [`BUNDLE_FORMAT`] of the ampup that wrote it
+    pub format: u32,
+    /// `owner/repo` the versions were released from
+    pub repo: String,
+    /// Platform the binaries run on (e.g. `linux`)
+    pub platform: String,
+    /// Architecture the binaries run on (e.g. `x86_64`)
+    pub arch: String,
+    /// Bundled versions, each under `versions/<version>/`
+    pub versions: Vec<String>,
+    /// Version of the bundled ampup, when one is included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ampup: Option<String>,
+}
+
+impl BundleInfo {
+    /// Fail unless the bundle's binaries run on `platform` and `arch`.
+    pub fn check_target(&self, platform: Platform, arch: Architecture) -> Result<()> {
+        if self.platform == platform.as_str() && self.arch == arch.as_str() {
+            return Ok(());
+        }
+        Err(BundleError::TargetMismatch {
+            target: format!("{} {}", self.platform, self.arch),
+            platform: platform.as_str().to_string(),
+            arch: arch.as_str().to_string(),
+        }
+        .into())
+    }
+}
+
+/// A bundle read and checked against its checksums.
+#[derive(Debug)]
+pub struct Bundle {
+    pub info: BundleInfo,
+    /// Hex public key of the trusted key that signed it, when checked
+    pub signed_by: Option<String>,
+    /// Whether the bundle carries a signature, checked or not
+    pub signed: bool,
+    /// Every file but the metadata, by `/`-separated path
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl Bundle {
+    /// The `ampd` and `ampctl` of bundled `version`, by file name.
+    pub fn version_files(&self, version: &str) -> BTreeMap<String, &[u8]> {
+        let prefix = format!("versions/{}/", version);
+        self.files
+            .iter()
+            .filter_map(|(path, data)| {
+                Some((path.strip_prefix(&prefix)?.to_string(), data.as_slice()))
+            })
+            .collect()
+    }
+
+    /// The bundled ampup binary, when included.
+    pub fn ampup(&self) -> Option<&[u8]> {
+        self.files.get(AMPUP_FILE).map(Vec::as_slice)
+    }
+}
+
+/// An Ed25519 key pair signing bundles.
+pub struct SigningKey(Ed25519KeyPair);
+
+impl SigningKey {
+    /// Generate a key pair, returning it with its PKCS#8 encoding in hex.
+    pub fn generate() -> Result<(Self, String)> {
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).map_err(|_| BundleError::InvalidKey {
+            reason: "failed to generate a key".to_string(),
+        })?;
+        let encoded = to_hex(pkcs8.as_ref());
+        Ok((Self::from_hex(&encoded)?, encoded))
+    }
+
+    /// Read a key pair from the hex PKCS#8 encoding `ampup bundle keygen`
+    /// writes.
+    pub fn from_hex(encoded: &str) -> Result<Self> {
+        let invalid = |reason: &str| BundleError::InvalidKey {
+            reason: reason.to_string(),
+        };
+        let pkcs8 = from_hex(encoded.trim()).ok_or_else(|| invalid("not hex-encoded"))?;
+        let key_pair =
+            Ed25519KeyPair::from_pkcs8(&pkcs8).map_err(|_| invalid("not an Ed25519 PKCS#8 key"))?;
+        Ok(Self(key_pair))
+    }
+
+    /// Hex public key, to pass to `ampup bundle install --trusted-key`.
+    pub fn public_key(&self) -> String {
+        to_hex(self.0.public_key().as_ref())
+    }
+}
+
+/// Write a bundle of `files` (by `/`-separated path under the bundle
+/// directory) described by `info` to `output`, signed with `key` when
+/// given. The same contents always give the same bytes.
+pub fn write(
+    info: &BundleInfo,
+    files: &BTreeMap<String, Vec<u8>>,
+    key: Option<&SigningKey>,
+    output: impl Write,
+) -> Result<()> {
+    let mut info_json =
+        serde_json::to_string_pretty(info).context("Failed to serialize bundle info")?;
+    info_json.push('\n');
+
+    let mut listed = BTreeMap::new();
+    listed.insert(BUNDLE_FILE.to_string(), info_json.as_bytes());
+    for (path, data) in files {
+        listed.insert(path.clone(), data.as_slice());
+    }
+    let checksums: String = listed
+        .iter()
+        .map(|(path, data)| format!("{:x}  {}\n", Sha256::digest(data), path))
+        .collect();
+
+    let mut archive = tar::Builder::new(GzEncoder::new(output, Compression::default()));
+    portable::append(
+        &mut archive,
+        ROOT,
+        CHECKSUMS_FILE,
+        checksums.as_bytes(),
+        0o644,
+    )?;
+    if let Some(key) = key {
+        let signature = format!("{}\n", to_hex(key.0.sign(checksums.as_bytes()).as_ref()));
+        portable::append(
+            &mut archive,
+            ROOT,
+            SIGNATURE_FILE,
+            signature.as_bytes(),
+            0o644,
+        )?;
+    }
+    for (path, data) in &listed {
+        let mode = match path.as_str() {
+            BUNDLE_FILE => 0o644,
+            _ => 0o755,
+        };
+        portable::append(&mut archive, ROOT, path, data, mode)?;
+    }
+    archive
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .context("Failed to write bundle")?;
+    Ok(())
+}
+
+/// Read a bundle from `input`, checking every file against its checksum
+/// and, when `trusted_keys` (hex public keys) are given, that one of them
+/// signed it.
+pub fn read(input: impl Read, trusted_keys: &[String]) -> Result<Bundle> {
+    let mut entries: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    let unreadable = "Failed to read bundle; is it a file written by ampup bundle create?";
+    let mut archive = tar::Archive::new(GzDecoder::new(input));
+    for entry in archive.entries().context(unreadable)? {
+        let mut entry = entry.context(unreadable)?;
+        let path = entry.path().context(unreadable)?.into_owned();
+        let name = bundle_path(&path).ok_or_else(|| BundleError::UnsafeEntry {
+            path: path.display().to_string(),
+        })?;
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => continue,
+            tar::EntryType::Regular => {}
+            _ => {
+                return Err(BundleError::UnsafeEntry {
+                    path: path.display().to_string(),
+                }
+                .into());
+            }
+        }
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .with_context(|| format!("Failed to read {} from bundle", name))?;
+        entries.insert(name, data);
+    }
+
+    let missing = |file: &str| BundleError::NotABundle {
+        reason: format!("{} is missing", file),
+    };
+    let checksums = entries
+        .remove(CHECKSUMS_FILE)
+        .ok_or_else(|| missing(CHECKSUMS_FILE))?;
+    let signature = entries.remove(SIGNATURE_FILE);
+    let signed_by = match trusted_keys.is_empty() {
+        true => None,
+        false => Some(check_signature(
+            &checksums,
+            signature.as_deref(),
+            trusted_keys,
+        )?),
+    };
+
+    let checksums = String::from_utf8_lossy(&checksums);
+    let mut listed = BTreeMap::new();
+    for line in checksums.lines().filter(|line| !line.trim().is_empty()) {
+        let (digest, path) = line
+            .split_once("  ")
+            .ok_or_else(|| BundleError::NotABundle {
+                reason: format!("malformed {} line '{}'", CHECKSUMS_FILE, line),
+            })?;
+        listed.insert(path.to_string(), digest.to_string());
+    }
+    for (path, data) in &entries {
+        let matches = listed.remove(path).is_some_and(|digest| {
+            digest.eq_ignore_ascii_case(&format!("{:x}", Sha256::digest(data)))
+        });
+        if !matches {
+            return Err(BundleError::ChecksumMismatch { file: path.clone() }.into());
+        }
+    }
+    if let Some(path) = listed.into_keys().next() {
+        return Err(BundleError::ChecksumMismatch { file: path }.into());
+    }
+
+    let info: BundleInfo = serde_json::from_slice(
+        &entries
+            .remove(BUNDLE_FILE)
+            .ok_or_else(|| missing(BUNDLE_FILE))?,
+    )
+    .context("Failed to parse bundle info")?;
+    if info.format > BUNDLE_FORMAT {
+        return Err(BundleError::UnsupportedFormat {
+            format: info.format,
+        }
+        .into());
+    }
+    if let Some(version) = info.versions.iter().find(|version| !is_safe_name(version)) {
+        return Err(BundleError::UnsafeEntry {
+            path: format!("versions/{}", version),
+        }
+        .into());
+    }
+
+    Ok(Bundle {
+        info,
+        signed_by,
+        signed: signature.is_some(),
+        files: entries,
+    })
+}
+
+/// Check that one of `trusted_keys` made `signature` of `checksums`,
+/// returning that key.
+fn check_signature(
+    checksums: &[u8],
+    signature: Option<&[u8]>,
+    trusted_keys: &[String],
+) -> Result<String> {
+    let untrusted = |reason: &str| BundleError::Untrusted {
+        reason: reason.to_string(),
+    };
+    let signature = signature.ok_or_else(|| untrusted("the bundle is not signed"))?;
+    let signature = from_hex(String::from_utf8_lossy(signature).trim())
+        .ok_or_else(|| untrusted("the signature is not hex-encoded"))?;
+    for trusted in trusted_keys {
+        let public_key = from_hex(trusted.trim()).ok_or_else(|| BundleError::InvalidKey {
+            reason: format!("trusted key '{}' is not hex-encoded", trusted),
+        })?;
+        if UnparsedPublicKey::new(&ED25519, &public_key)
+            .verify(checksums, &signature)
+            .is_ok()
+        {
+            return Ok(trusted.trim().to_lowercase());
+        }
+    }
+    Err(untrusted("no trusted key made its signature").into())
+}
+
+/// `/`-separated path of an entry below the bundle's top-level directory,
+/// or `None` when it lies elsewhere.
+fn bundle_path(path: &Path) -> Option<String> {
+    let mut components = path.components();
+    if components.next() != Some(Component::Normal(ROOT.as_ref())) {
+        return None;
+    }
+    let parts = components
+        .map(|component| match component {
+            Component::Normal(part) => part.to_str().map(str::to_string),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Read the signing key in `path`, written by `ampup bundle keygen`.
+pub fn load_signing_key(path: &Path) -> Result<SigningKey> {
+    let encoded = fs::read_to_string(path).context("Failed to read signing key")?;
+    SigningKey::from_hex(&encoded)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info() -> BundleInfo {
+        BundleInfo {
+            format: BUNDLE_FORMAT,
+            repo: "edgeandnode/amp".to_string(),
+            platform: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            versions: vec!["v0.3.0".to_string()],
+            ampup: None,
+        }
+    }
+
+    fn files() -> BTreeMap<String, Vec<u8>> {
+        BTreeMap::from([
+            ("versions/v0.3.0/ampd".to_string(), b"ampd".to_vec()),
+            ("versions/v0.3.0/ampctl".to_string(), b"ampctl".to_vec()),
+        ])
+    }
+
+    #[test]
+    fn read_with_signed_bundle_checks_trusted_key() -> Result<()> {
+        //* Given
+        let (key, _) = SigningKey::generate()?;
+        let (other, _) = SigningKey::generate()?;
+        let mut bundle = Vec::new();
+        write(&info(), &files(), Some(&key), &mut bundle)?;
+
+        //* When
+        let trusted = read(bundle.as_slice(), &[key.public_key()])?;
+        let untrusted = read(bundle.as_slice(), &[other.public_key()]);
+
+        //* Then
+        assert_eq!(trusted.info, info());
+        assert_eq!(trusted.signed_by, Some(key.public_key()));
+        assert_eq!(
+            trusted.version_files("v0.3.0").keys().collect::<Vec<_>>(),
+            vec!["ampctl", "ampd"]
+        );
+        assert!(matches!(
+            untrusted
+                .err()
+                .and_then(|err| err.downcast::<BundleError>().ok()),
+            Some(BundleError::Untrusted { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn read_with_unsigned_bundle_and_trusted_key_fails() -> Result<()> {
+        //* Given
+        let (key, _) = SigningKey::generate()?;
+        let mut bundle = Vec::new();
+        write(&info(), &files(), None, &mut bundle)?;
+
+        //* When
+        let unchecked = read(bundle.as_slice(), &[])?;
+        let checked = read(bundle.as_slice(), &[key.public_key()]);
+
+        //* Then
+        assert!(!unchecked.signed);
+        assert!(checked.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::Result;
+
+use crate::{
+    config::Config, manifest::VersionManifest, resolver::Channel, settings::Settings, ui,
+    version_manager::VersionManager,
+};
+
+/// Follow `channel` from now on: `ampup update` and bare `ampup` install its
+/// newest release instead of latest.
+pub fn track(config: &Config, channel: Channel) -> Result<()> {
+    Settings::set(config, "update.channel", channel.as_str())?;
+    ui::info!("Now tracking the {} channel", channel.as_str());
+    Ok(())
+}
+
+/// Record version key `key` as the version installed from `channel`,
+/// taking the channel off whichever version held it before, so each
+/// channel's manifest entry names exactly one installed version.
+pub fn record(version_manager: &VersionManager, key: &str, channel: Channel) -> Result<()> {
+    for installed in version_manager.list_installed()? {
+        let dir = version_manager.version_dir(&installed);
+        let mut manifest = VersionManifest::load(&dir)?;
+        let on_channel = manifest.channels.contains(&channel);
+        if installed == key && !on_channel {
+            manifest.channels.push(channel);
+        } else if installed != key && on_channel {
+            manifest.channels.retain(|c| *c != channel);
+        } else {
+            continue;
+        }
+        manifest.save(&dir)?;
+    }
+    Ok(())
+}
+
+/// Each channel with the installed version key last installed from it, in
+/// channel order.
+pub fn installed(version_manager: &VersionManager) -> Result<Vec<(Channel, String)>> {
+    let mut channels = Vec::new();
+    for key in version_manager.list_installed()? {
+        let manifest = VersionManifest::load(&version_manager.version_dir(&key))?;
+        for channel in manifest.channels {
+            channels.push((channel, key.clone()));
+        }
+    }
+    channels.sort_by_key(|(channel, _)| *channel as u8);
+    Ok(channels)
+}
+
+#[cfg(test)]
+mod tests {
+    use fs_err as fs;
+
+    use super::*;
+
+    fn version_manager(dir: &std::path::Path, keys: &[&str]) -> VersionManager {
+        let config = Config::new(Some(dir.to_path_buf())).expect("should create config");
+        config.ensure_dirs().expect("should create directories");
+        let version_manager = VersionManager::new(config);
+        for key in keys {
+            fs::create_dir_all(version_manager.version_dir(key)).expect("should create version");
+        }
+        version_manager
+    }
+
+    #[test]
+    fn record_with_newer_version_moves_channel_to_it() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let version_manager = version_manager(
+            dir.path(),
+            &["edgeandnode-amp/v0.5.0-rc.1", "edgeandnode-amp/v0.5.0-rc.2"],
+        );
+        record(
+            &version_manager,
+            "edgeandnode-amp/v0.5.0-rc.1",
+            Channel::Beta,
+        )
+        .expect("should record");
+
+        //* When
+        record(
+            &version_manager,
+            "edgeandnode-amp/v0.5.0-rc.2",
+            Channel::Beta,
+        )
+        .expect("should record");
+
+        //* Then
+        assert_eq!(
+            installed(&version_manager).expect("should list channels"),
+            vec![(Channel::Beta, "edgeandnode-amp/v0.5.0-rc.2".to_string())]
+        );
+    }
+
+    #[test]
+    fn record_with_several_channels_keeps_each() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let version_manager = version_manager(dir.path(), &["edgeandnode-amp/v0.4.0"]);
+
+        //* When
+        record(&version_manager, "edgeandnode-amp/v0.4.0", Channel::Beta).expect("should record");
+        record(&version_manager, "edgeandnode-amp/v0.4.0", Channel::Stable).expect("should record");
+
+        //* Then
+        assert_eq!(
+            installed(&version_manager).expect("should list channels"),
+            vec![
+                (Channel::Stable, "edgeandnode-amp/v0.4.0".to_string()),
+                (Channel::Beta, "edgeandnode-amp/v0.4.0".to_string()),
+            ]
+        );
+    }
+}
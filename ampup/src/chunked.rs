@@ -0,0 +1,280 @@
+use anyhow::{Context, Result};
+use futures::{StreamExt, future::try_join_all};
+use reqwest::{
+    RequestBuilder, Response, StatusCode,
+    header::{CONTENT_RANGE, HeaderMap, RANGE},
+};
+
+/// Size of the first ranged request, which also reveals the total size.
+const FIRST_CHUNK_BYTES: u64 = 1024 * 1024;
+
+/// Smallest range worth its own connection.
+const MIN_CHUNK_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Request only the first chunk when downloading in `chunks` parts.
+///
+/// With a single chunk the request is left untouched.
+pub fn with_first_range(request: RequestBuilder, chunks: usize) -> RequestBuilder {
+    if chunks > 1 {
+        request.header(RANGE, format!("bytes=0-{}", FIRST_CHUNK_BYTES - 1))
+    } else {
+        request
+    }
+}
+
+/// Read a successful response to a request sent with [`with_first_range`].
+///
+/// A `206 Partial Content` response reveals the total size; the remainder is
+/// fetched in up to `chunks` concurrent ranged requests built by
+/// `build_request`, each streamed straight into its place in a buffer
+/// allocated once for the whole file, so the parts are never held twice.
+/// Servers that ignore `Range` answer `200` with the whole body, which is
+/// read as-is.
+pub async fn read_response(
+    first: Response,
+    build_request: impl Fn() -> RequestBuilder,
+    chunks: usize,
+) -> Result<Vec<u8>> {
+    let partial = first.status() == StatusCode::PARTIAL_CONTENT;
+    let total = content_range_total(first.headers());
+    let mut buffer = read_body(first).await?;
+    if !partial {
+        return Ok(buffer);
+    }
+
+    let total = total.context("Partial response is missing the total size in Content-Range")?;
+    let received = buffer.len();
+    let ranges = split_ranges(received as u64, total, chunks);
+    let Some(&(_, last)) = ranges.last() else {
+        return Ok(buffer);
+    };
+    let size = usize::try_from(last + 1).context("File too large to download")?;
+    buffer
+        .try_reserve_exact(size - received)
+        .context("File too large to download")?;
+    buffer.resize(size, 0);
+
+    let mut rest = &mut buffer[received..];
+    let mut parts = Vec::with_capacity(ranges.len());
+    for &(start, end) in &ranges {
+        let (part, tail) = rest.split_at_mut((end - start + 1) as usize);
+        parts.push((start, end, part));
+        rest = tail;
+    }
+    try_join_all(
+        parts
+            .into_iter()
+            .map(|(start, end, part)| fetch_range(&build_request, start, end, part)),
+    )
+    .await?;
+
+    Ok(buffer)
+}
+
+/// Download bytes `start..=end` into `part`, which holds exactly that many.
+async fn fetch_range(
+    build_request: &impl Fn() -> RequestBuilder,
+    start: u64,
+    end: u64,
+    part: &mut [u8],
+) -> Result<()> {
+    let response = build_request()
+        .header(RANGE, format!("bytes={}-{}", start, end))
+        .send()
+        .await
+        .with_context(|| format!("Failed to download bytes {}-{}", start, end))?;
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        anyhow::bail!(
+            "Failed to download bytes {}-{}: HTTP {}",
+            start,
+            end,
+            response.status().as_u16()
+        );
+    }
+
+    let mut filled = 0;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.context("Error while downloading file")?;
+        crate::heartbeat::record_bytes(chunk.len());
+        let Some(dest) = part.get_mut(filled..filled + chunk.len()) else {
+            anyhow::bail!(
+                "Failed to download bytes {}-{}: received more than {} bytes",
+                start,
+                end,
+                part.len()
+            );
+        };
+        dest.copy_from_slice(&chunk);
+        filled += chunk.len();
+    }
+    if filled != part.len() {
+        anyhow::bail!(
+            "Failed to download bytes {}-{}: received {} bytes",
+            start,
+            end,
+            filled
+        );
+    }
+    Ok(())
+}
+
+/// Read the whole body, sized up front from `Content-Length` when the
+/// server sends one so the buffer isn't regrown (and briefly held twice)
+/// as it fills.
+async fn read_body(response: Response) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    if let Some(len) = response.content_length() {
+        // Only a hint: a length that can't be reserved leaves the buffer
+        // growing as it fills
+        let _ = buffer.try_reserve_exact(usize::try_from(len).unwrap_or(0));
+    }
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.context("Error while downloading file")?;
+        crate::heartbeat::record_bytes(chunk.len());
+        buffer.extend_from_slice(&chunk);
+    }
+    Ok(buffer)
+}
+
+/// Total size from a `Content-Range: bytes 0-1023/4096` header.
+fn content_range_total(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit_once('/')?
+        .1
+        .parse()
+        .ok()
+}
+
+/// Split the bytes after `received` into at most `chunks` inclusive ranges
+/// of at least [`MIN_CHUNK_BYTES`] each.
+fn split_ranges(received: u64, total: u64, chunks: usize) -> Vec<(u64, u64)> {
+    if received >= total {
+        return Vec::new();
+    }
+
+    let remaining = total - received;
+    let count = (remaining / MIN_CHUNK_BYTES).clamp(1, chunks.max(1) as u64);
+    let size = remaining.div_ceil(count);
+    (0..count)
+        .map(|i| received + i * size)
+        .filter(|&start| start < total)
+        .map(|start| (start, (start + size).min(total) - 1))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[test]
+    fn split_ranges_with_large_remainder_splits_evenly_up_to_chunk_count() {
+        //* When
+        let ranges = split_ranges(
+            FIRST_CHUNK_BYTES,
+            FIRST_CHUNK_BYTES + 3 * MIN_CHUNK_BYTES,
+            2,
+        );
+
+        //* Then
+        let half = 3 * MIN_CHUNK_BYTES / 2;
+        assert_eq!(
+            ranges,
+            [
+                (FIRST_CHUNK_BYTES, FIRST_CHUNK_BYTES + half - 1),
+                (FIRST_CHUNK_BYTES + half, FIRST_CHUNK_BYTES + 2 * half - 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_ranges_with_small_remainder_uses_single_range() {
+        //* When
+        let ranges = split_ranges(FIRST_CHUNK_BYTES, FIRST_CHUNK_BYTES + 10, 8);
+
+        //* Then
+        assert_eq!(ranges, [(FIRST_CHUNK_BYTES, FIRST_CHUNK_BYTES + 9)]);
+    }
+
+    #[tokio::test]
+    async fn read_response_with_range_support_reassembles_chunks_in_order() {
+        //* Given — a server honouring Range for a body spanning several chunks
+        let body: Vec<u8> = (0..(FIRST_CHUNK_BYTES + 2 * MIN_CHUNK_BYTES + 123))
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("should bind to a random port");
+        let url = format!(
+            "http://{}/asset",
+            listener.local_addr().expect("should have a local address")
+        );
+        let server = tokio::spawn(serve_ranges(listener, body.clone()));
+        let client = reqwest::Client::new();
+
+        //* When
+        let first = with_first_range(client.get(&url), 4)
+            .send()
+            .await
+            .expect("should send first request");
+        let data = read_response(first, || client.get(&url), 4)
+            .await
+            .expect("should download all chunks");
+        server.abort();
+
+        //* Then
+        assert_eq!(data.len(), body.len());
+        assert!(data == body, "chunks should be reassembled in order");
+    }
+
+    /// Minimal HTTP server answering `Range: bytes=a-b` with 206 responses.
+    async fn serve_ranges(listener: tokio::net::TcpListener, body: Vec<u8>) {
+        loop {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                break;
+            };
+            let body = body.clone();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).await.expect("should read request");
+                let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                let range = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("range: bytes="))
+                    .and_then(|range| range.trim().split_once('-'))
+                    .map(|(start, end)| {
+                        let start: usize = start.parse().expect("should parse range start");
+                        let end: usize = end.parse().expect("should parse range end");
+                        (start, end.min(body.len() - 1))
+                    });
+                let (head, part) = match range {
+                    Some((start, end)) => (
+                        format!(
+                            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\nContent-Length: {}\r\n\r\n",
+                            start,
+                            end,
+                            body.len(),
+                            end - start + 1
+                        ),
+                        &body[start..=end],
+                    ),
+                    None => (
+                        format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()),
+                        &body[..],
+                    ),
+                };
+                stream
+                    .write_all(head.as_bytes())
+                    .await
+                    .expect("should write headers");
+                stream.write_all(part).await.expect("should write body");
+            });
+        }
+    }
+}
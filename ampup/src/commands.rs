@@ -1,7 +1,36 @@
+pub mod adopt;
+pub mod audit;
 pub mod build;
+pub mod bundle;
+pub mod cache;
+pub mod clean;
+pub mod component;
+pub mod config;
+pub mod debug;
+pub mod doctor;
+pub mod export;
+pub mod fetch;
+pub mod fix_permissions;
+pub mod generate;
+pub mod hooks;
+pub mod import;
 pub mod init;
 pub mod install;
+pub mod instances;
 pub mod list;
+pub mod news;
+pub mod overrides;
+pub mod pin;
+pub mod prune;
+pub mod resolve;
+pub mod rollback;
+pub mod shell;
+pub mod status;
+pub mod support_bundle;
 pub mod uninstall;
 pub mod update;
+pub mod upgrade;
 pub mod use_version;
+pub mod verify;
+pub mod verify_install_script;
+pub mod which;
@@ -0,0 +1,210 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::{
+    commands::use_version,
+    config::Config,
+    github::ClientOptions,
+    health::run_with_timeout,
+    manifest::{VersionManifest, binary_checksums},
+    portable, ui,
+    version_manager::VersionManager,
+    version_store::{LOCAL_NAMESPACE, version_key},
+};
+
+/// How long `ampd --version` may take when deriving a label.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub enum AdoptError {
+    NotABinary { path: PathBuf },
+    UnknownVersion { path: PathBuf, reason: String },
+    NoAmpctl { path: PathBuf },
+    AlreadyInstalled { key: String },
+}
+
+impl std::fmt::Display for AdoptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotABinary { path } => {
+                writeln!(f, "Nothing to adopt")?;
+                writeln!(f, "  Path: {}", path.display())?;
+                writeln!(f)?;
+                write!(f, "  Pass the path of an ampd binary.")?;
+            }
+            Self::UnknownVersion { path, reason } => {
+                writeln!(f, "Could not tell which version the binary is")?;
+                writeln!(f, "  Path: {}", path.display())?;
+                writeln!(f, "  Reason: {}", reason)?;
+                writeln!(f)?;
+                write!(f, "  Pass --name to label it yourself.")?;
+            }
+            Self::NoAmpctl { path } => {
+                writeln!(f, "No ampctl to adopt with ampd")?;
+                writeln!(f, "  Path: {}", path.display())?;
+                writeln!(f)?;
+                write!(f, "  Pass --ampctl with the path of the ampctl binary.")?;
+            }
+            Self::AlreadyInstalled { key } => {
+                writeln!(f, "Version is already installed")?;
+                writeln!(f, "  Version: {}", key)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Pass --force to replace it, or --name to adopt it under another label."
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for AdoptError {}
+
+/// Copy (or with `move_files`, move) the ampd binary at `path` and the
+/// ampctl beside it, or at `ampctl`, into the version store as
+/// `local/<name>`, deriving the name from `ampd --version` when not given.
+#[expect(clippy::too_many_arguments)]
+pub async fn run(
+    install_dir: Option<PathBuf>,
+    path: PathBuf,
+    ampctl: Option<PathBuf>,
+    name: Option<String>,
+    move_files: bool,
+    activate: bool,
+    force: bool,
+    options: ClientOptions,
+) -> Result<()> {
+    let version_manager = VersionManager::new(Config::new(install_dir.clone())?);
+    if !path.is_file() {
+        return Err(AdoptError::NotABinary { path }.into());
+    }
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", path.display()))?;
+
+    let name = match name {
+        Some(name) => name,
+        None => query_label(&path)?,
+    };
+    if !is_label(&name) {
+        anyhow::bail!(
+            "'{}' can't be used as a version name; use letters, digits, '.', '_', '+' and '-'",
+            name
+        );
+    }
+    let key = version_key(LOCAL_NAMESPACE, &name);
+    if version_manager.is_installed(&key) && !force {
+        return Err(AdoptError::AlreadyInstalled { key }.into());
+    }
+
+    // Activating a version needs both binaries
+    let ampctl = ampctl.unwrap_or_else(|| path.with_file_name("ampctl"));
+    if !ampctl.is_file() {
+        return Err(AdoptError::NoAmpctl { path: ampctl }.into());
+    }
+    let sources = [("ampd", path.clone()), ("ampctl", ampctl)];
+
+    let mut files = BTreeMap::new();
+    for (binary, source) in &sources {
+        let data = fs::read(source).with_context(|| format!("Failed to read {}", binary))?;
+        files.insert(binary.to_string(), (data, 0o755));
+    }
+    portable::install_files(&version_manager, &key, &files)?;
+    let version_dir = version_manager.version_dir(&key);
+    let checksums = binary_checksums(&version_dir)?;
+    VersionManifest::update(&version_dir, |manifest| {
+        manifest.checksums = checksums;
+        manifest.adopted_from = Some(path.clone());
+    })?;
+
+    for (binary, source) in &sources {
+        if move_files {
+            fs::remove_file(source).with_context(|| {
+                format!("Adopted {}, but failed to remove the original", binary)
+            })?;
+        }
+        ui::detail!(
+            "{} {}",
+            if move_files { "Moved" } else { "Copied" },
+            source.display()
+        );
+    }
+    ui::success!(
+        "Adopted {} from {}",
+        ui::version(&key),
+        ui::path(path.display())
+    );
+
+    if activate {
+        use_version::run(install_dir, Some(key), None, false, false, options).await?;
+    } else {
+        ui::detail!("Switch to it with: ampup use {}", key);
+    }
+    Ok(())
+}
+
+/// Label for the binary at `path` from its `--version` output.
+fn query_label(path: &Path) -> Result<String> {
+    let unknown = |reason: String| AdoptError::UnknownVersion {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let mut command = Command::new(path);
+    command.arg("--version");
+    let (status, stdout, _) = run_with_timeout(command, VERSION_TIMEOUT).map_err(unknown)?;
+    if !status.success() {
+        return Err(unknown(format!("--version exited with {}", status)).into());
+    }
+    parse_label(&stdout).ok_or_else(|| unknown(format!("no version in '{}'", stdout.trim())).into())
+}
+
+/// The first version-looking word of `--version` output (e.g.
+/// `ampd 0.3.0` → `v0.3.0`), with a leading `v`.
+fn parse_label(output: &str) -> Option<String> {
+    output
+        .lines()
+        .next()?
+        .split_whitespace()
+        .map(|word| word.strip_prefix('v').unwrap_or(word))
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()) && is_label(word))
+        .map(|version| format!("v{}", version))
+}
+
+/// Whether `name` is usable as a version label.
+fn is_label(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '+' | '-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_label_with_version_output_returns_prefixed_version() {
+        //* Then
+        assert_eq!(parse_label("ampd 0.3.0\n").as_deref(), Some("v0.3.0"));
+        assert_eq!(
+            parse_label("ampd v0.4.0-5-gabc123 (2024-05-01)").as_deref(),
+            Some("v0.4.0-5-gabc123")
+        );
+    }
+
+    #[test]
+    fn parse_label_without_version_returns_none() {
+        //* Then
+        assert_eq!(parse_label("ampd\n"), None);
+        assert_eq!(parse_label(""), None);
+    }
+}
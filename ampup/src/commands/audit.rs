@@ -0,0 +1,217 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use console::style;
+use fs_err as fs;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    advisory::{self, DEFAULT_ADVISORIES_URL},
+    config::Config,
+    github::{ClientOptions, GitHubClient},
+    platform::{Architecture, Platform},
+    resolver::tag_version,
+    settings::Settings,
+    ui,
+    version_manager::VersionManager,
+    version_store::{repo_namespace, split_key},
+};
+
+#[derive(Debug)]
+pub enum AuditError {
+    /// Installed versions are affected by an advisory, yanked, or differ
+    /// from the binaries their release published.
+    Vulnerable {
+        versions: Vec<String>,
+        active: Option<String>,
+    },
+}
+
+impl std::fmt::Display for AuditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Vulnerable { versions, active } => {
+                writeln!(f, "Audit found vulnerable versions installed")?;
+                writeln!(f, "  Versions: {}", versions.join(", "))?;
+                if let Some(active) = active {
+                    writeln!(f, "  Active version {} is affected", active)?;
+                }
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Upgrade with `ampup upgrade` and remove affected versions with `ampup uninstall <version>`."
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for AuditError {}
+
+/// How an installed binary compares to the one its release published.
+enum Integrity {
+    /// Matches the release asset's `sha256:` digest
+    Verified,
+    /// The release publishes no digest for the bare binary to compare with
+    Unverified,
+    /// Differs from the release asset
+    Modified,
+}
+
+/// Audit every installed version against the advisory feed, the release's
+/// yanked status and the digests published with the release, printing a
+/// report to stdout.
+///
+/// Fails with `AuditError::Vulnerable` when any installed version has a
+/// finding, so scripts and CI can gate on the exit code.
+pub async fn run(
+    install_dir: Option<PathBuf>,
+    repo: Option<String>,
+    github_token: Option<String>,
+    advisories_url: Option<String>,
+    options: ClientOptions,
+) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let settings = Settings::load(&config)?;
+    let options = options.or(settings.client_options());
+    let github = settings.github_client(settings.repo(repo), github_token, options.clone())?;
+    let platform = Platform::detect()?;
+    let arch = Architecture::detect()?;
+    let version_manager = VersionManager::new(config);
+
+    let installed = version_manager.list_installed()?;
+    if installed.is_empty() {
+        ui::info!("No versions installed");
+        return Ok(());
+    }
+    let current = version_manager.get_current()?;
+
+    let url = advisories_url.unwrap_or_else(|| DEFAULT_ADVISORIES_URL.to_string());
+    ui::info!("Fetching advisories from {}", url);
+    let feed = advisory::fetch(&url, &options.timeouts).await?;
+
+    let namespace = repo_namespace(github.repo());
+    let mut vulnerable = Vec::new();
+    for key in &installed {
+        let is_active = current.as_deref() == Some(key.as_str());
+        if is_active {
+            println!("{} {}", key, style("(active)").dim());
+        } else {
+            println!("{}", key);
+        }
+
+        let (key_namespace, version) = split_key(key);
+        if key_namespace != Some(namespace.as_str()) {
+            println!(
+                "  {:<10} not from {}, audit it with --repo",
+                "skipped",
+                github.repo()
+            );
+            continue;
+        }
+        if tag_version(version).is_none() {
+            println!("  {:<10} source build, not audited", "skipped");
+            continue;
+        }
+
+        let mut findings = 0;
+        for advisory in feed.affecting(version) {
+            let severity = advisory.severity.as_deref().unwrap_or("unknown");
+            let fixed = match &advisory.fixed {
+                Some(fixed) => format!(" (fixed in {})", fixed),
+                None => " (no fix released)".to_string(),
+            };
+            println!(
+                "  {:<10} {} [{}] {}{}",
+                "advisory", advisory.id, severity, advisory.summary, fixed
+            );
+            findings += 1;
+        }
+
+        match github.is_yanked(version).await {
+            Ok(true) => {
+                println!("  {:<10} release was withdrawn", "yanked");
+                findings += 1;
+            }
+            Ok(false) => {}
+            Err(_) => {
+                println!(
+                    "  {:<10} could not look up the release in {}",
+                    "release",
+                    github.repo()
+                );
+                if findings > 0 {
+                    vulnerable.push(key.clone());
+                }
+                continue;
+            }
+        }
+
+        // The release is cached by the lookup above, so the digest checks
+        // below don't query GitHub again
+        let dir = version_manager.version_dir(key);
+        for binary in ["ampd", "ampctl"] {
+            let asset_name = format!("{}-{}-{}", binary, platform.as_str(), arch.as_str());
+            match integrity(&github, version, &asset_name, &dir.join(binary)).await {
+                Integrity::Verified => {
+                    println!("  {:<10} matches the release digest", binary)
+                }
+                Integrity::Unverified => {
+                    println!("  {:<10} no published digest to compare", binary)
+                }
+                Integrity::Modified => {
+                    println!("  {:<10} differs from the release digest", binary);
+                    findings += 1;
+                }
+            }
+        }
+
+        if findings > 0 {
+            vulnerable.push(key.clone());
+        }
+    }
+
+    if vulnerable.is_empty() {
+        ui::success!("No known issues in {} installed versions", installed.len());
+        return Ok(());
+    }
+
+    let active = current.filter(|version| vulnerable.contains(version));
+    Err(AuditError::Vulnerable {
+        versions: vulnerable,
+        active,
+    }
+    .into())
+}
+
+/// Compare the installed `path` with the digest `version`'s release
+/// publishes for `asset_name`.
+///
+/// Releases that publish the binary compressed or archived only have a
+/// digest of the packaged file, which can't be compared with the installed
+/// binary.
+async fn integrity(
+    github: &GitHubClient,
+    version: &str,
+    asset_name: &str,
+    path: &Path,
+) -> Integrity {
+    let digest = match github.resolve_release_assets(version, &[asset_name]).await {
+        Ok(assets) => assets
+            .into_iter()
+            .find(|asset| asset.name == asset_name)
+            .and_then(|asset| asset.digest),
+        Err(_) => None,
+    };
+    let Some(hex) = digest.as_deref().and_then(|d| d.strip_prefix("sha256:")) else {
+        return Integrity::Unverified;
+    };
+
+    match fs::read(path) {
+        Ok(data) if format!("{:x}", Sha256::digest(&data)).eq_ignore_ascii_case(hex) => {
+            Integrity::Verified
+        }
+        _ => Integrity::Modified,
+    }
+}
@@ -3,9 +3,10 @@ use std::path::PathBuf;
 use anyhow::Result;
 
 use crate::{
-    DEFAULT_REPO,
-    builder::{BuildOptions, BuildSource, Builder},
+    builder::{BuildOptions, BuildSource, Builder, CargoOptions},
     config::Config,
+    github::{ClientOptions, PullRequestHead},
+    settings::Settings,
     ui,
     version_manager::VersionManager,
 };
@@ -18,44 +19,106 @@ pub async fn run(
     path: Option<PathBuf>,
     branch: Option<String>,
     commit: Option<String>,
+    tag: Option<String>,
     pr: Option<u32>,
     name: Option<String>,
     jobs: Option<usize>,
+    reproducible: bool,
+    force: bool,
+    fresh: bool,
+    summary: bool,
+    cargo: CargoOptions,
+    api_url: Option<String>,
 ) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let settings = Settings::load(&config)?;
+    let options = ClientOptions {
+        api_url: api_url.or(settings.api_url.clone()),
+        ..Default::default()
+    };
+
     // Determine build source based on provided options
-    let source = match (path, repo, branch, commit, pr) {
-        (Some(path), _, None, None, None) => BuildSource::Local { path },
-        (None, Some(repo), Some(branch), None, None) => BuildSource::Branch { repo, branch },
-        (None, Some(repo), None, Some(commit), None) => BuildSource::Commit { repo, commit },
-        (None, Some(repo), None, None, Some(number)) => BuildSource::Pr { repo, number },
-        (None, Some(repo), None, None, None) => BuildSource::Main { repo },
-        (None, None, Some(branch), None, None) => BuildSource::Branch {
-            repo: DEFAULT_REPO.to_string(),
-            branch,
-        },
-        (None, None, None, Some(commit), None) => BuildSource::Commit {
-            repo: DEFAULT_REPO.to_string(),
-            commit,
-        },
-        (None, None, None, None, Some(number)) => BuildSource::Pr {
-            repo: DEFAULT_REPO.to_string(),
-            number,
-        },
-        (None, None, None, None, None) => BuildSource::Main {
-            repo: DEFAULT_REPO.to_string(),
-        },
-        _ => unreachable!("Clap should prevent conflicting options"),
+    let source = match path {
+        Some(path) => BuildSource::Local { path },
+        None => {
+            let repo = settings.repo(repo);
+            match (branch, commit, tag, pr) {
+                (Some(branch), None, None, None) => BuildSource::Branch { repo, branch },
+                (None, Some(commit), None, None) => BuildSource::Commit { repo, commit },
+                (None, None, Some(tag), None) => BuildSource::Tag { repo, tag },
+                (None, None, None, Some(number)) => {
+                    let head = pr_head(&settings, &repo, number, options.clone()).await;
+                    BuildSource::Pr { repo, number, head }
+                }
+                (None, None, None, None) => BuildSource::Main { repo },
+                _ => unreachable!("Clap should prevent conflicting options"),
+            }
+        }
     };
 
     ui::info!("Building from source: {}", source);
 
     // Create builder
-    let config = Config::new(install_dir)?;
     let version_manager = VersionManager::new(config);
-    let builder = Builder::new(version_manager);
+    let builder = Builder::new(version_manager)
+        .with_smoke_tests(settings.smoke_tests())
+        .with_clean_builds(settings.policy().clean_builds)
+        .with_label_scheme(settings.label_scheme()?)
+        .with_summarized_output(summary);
 
     // Execute the build
-    builder.build(source, BuildOptions { name, jobs }).await?;
+    let web_url = options.web_url();
+    builder
+        .build(
+            source,
+            BuildOptions {
+                name,
+                jobs,
+                web_url,
+                reproducible,
+                force,
+                fresh,
+                cargo,
+            },
+        )
+        .await?;
 
     Ok(())
 }
+
+/// Head of pull request `number`, so a PR opened from a fork can be fetched
+/// from there. Without it (e.g. the API is unreachable), the PR is fetched
+/// through the base repository's `pull/<number>/head` ref.
+async fn pr_head(
+    settings: &Settings,
+    repo: &str,
+    number: u32,
+    options: ClientOptions,
+) -> Option<PullRequestHead> {
+    let lookup = async {
+        let github = settings.github_client(repo.to_string(), None, options)?;
+        github.get_pull_request(number).await
+    };
+    match lookup.await {
+        Ok(head) => {
+            if !head.repo.eq_ignore_ascii_case(repo) {
+                ui::detail!(
+                    "Pull request #{} is from {}:{}",
+                    number,
+                    head.repo,
+                    head.branch
+                );
+            }
+            Some(head)
+        }
+        Err(err) => {
+            ui::warn!(
+                "Could not look up pull request #{}: {:#}; fetching it from {}",
+                number,
+                err,
+                repo
+            );
+            None
+        }
+    }
+}
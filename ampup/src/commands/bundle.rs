@@ -0,0 +1,315 @@
+use std::{
+    collections::BTreeMap,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::{
+    archive,
+    bundle::{self, AMPUP_FILE, BUNDLE_FORMAT, BundleInfo, SigningKey},
+    config::Config,
+    download_manager::{DownloadManager, DownloadTask},
+    github::ClientOptions,
+    manifest::VersionManifest,
+    platform::{Architecture, Platform},
+    portable, progress, release_source,
+    resolver::{VersionResolver, VersionSpec},
+    settings::Settings,
+    ui,
+    version_manager::VersionManager,
+    version_store::{repo_namespace, version_key},
+};
+
+/// Download `versions` of ampd and ampctl for `platform` and `arch`
+/// (default: this host) along with ampup into one bundle at `output`, for
+/// `ampup bundle install` on a machine without network access. Signed with
+/// the key in `signing_key` when given.
+#[expect(clippy::too_many_arguments)]
+pub async fn create(
+    install_dir: Option<PathBuf>,
+    versions: Vec<String>,
+    platform: Option<String>,
+    arch: Option<String>,
+    output: PathBuf,
+    repo: Option<String>,
+    github_token: Option<String>,
+    source: Option<String>,
+    jobs: usize,
+    signing_key: Option<PathBuf>,
+    allow_prerelease: bool,
+    options: ClientOptions,
+) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let settings = Settings::load(&config)?;
+    let options = options.or(settings.client_options());
+    let policy = settings.policy();
+    let repo = settings.repo(repo);
+    let github = settings.github_client(repo.clone(), github_token.clone(), options.clone())?;
+    // Load the key first, so a bad one fails before anything is downloaded
+    let signing_key = signing_key
+        .as_deref()
+        .map(bundle::load_signing_key)
+        .transpose()?;
+
+    let platform = match platform {
+        Some(platform) => Platform::from_override(Some(platform))?,
+        None => Platform::detect()?,
+    };
+    let arch = match arch {
+        Some(arch) => Architecture::from_override(Some(arch))?,
+        None => Architecture::detect()?,
+    };
+
+    let resolver = VersionResolver::new(&github);
+    let mut resolved = Vec::new();
+    for version in &versions {
+        let spec: VersionSpec = version.parse()?;
+        let version = resolver.resolve_release(&spec).await?;
+        resolver
+            .check_prerelease(&spec, &version, allow_prerelease, &policy)
+            .await?;
+        if !resolved.contains(&version) {
+            resolved.push(version);
+        }
+    }
+
+    let source = release_source::with_mirrors(
+        source.or(settings.source.clone()).as_deref(),
+        &settings.mirrors,
+        &github,
+        options.timeouts,
+    )?;
+    ui::detail!("Source: {}", source.describe());
+    let downloads = DownloadManager::new(source, jobs)
+        .with_cache(settings.artifact_cache(config.artifact_cache_dir()))
+        .with_require_digests(policy.require_digests);
+
+    let staging = tempfile::tempdir().context("Failed to create staging directory")?;
+    let mut files = BTreeMap::new();
+    for version in &resolved {
+        ui::info!(
+            "Fetching {} for {}-{}",
+            ui::version(version),
+            platform,
+            arch
+        );
+        let tasks: Vec<_> = ["ampd", "ampctl"]
+            .map(|binary| DownloadTask {
+                artifact_name: format!("{}-{}-{}", binary, platform.as_str(), arch.as_str()),
+                dest_filename: binary.to_string(),
+                target: Some((platform, arch)),
+            })
+            .into_iter()
+            .collect();
+        let dir = staging.path().join(version);
+        downloads
+            .download_all(tasks, version, dir.clone(), progress::create_reporter())
+            .await?;
+        for binary in ["ampd", "ampctl"] {
+            files.insert(
+                format!("versions/{}/{}", version, binary),
+                fs::read(dir.join(binary))?,
+            );
+        }
+    }
+
+    let (ampup_version, ampup) =
+        if (platform, arch) == (Platform::detect()?, Architecture::detect()?) {
+            let exe = std::env::current_exe().context("Failed to get current executable path")?;
+            (env!("VERGEN_GIT_DESCRIBE").to_string(), fs::read(exe)?)
+        } else {
+            let self_github =
+                settings.github_client(settings.self_repo(None), github_token, options)?;
+            let latest = self_github.get_latest_version().await?;
+            ui::info!(
+                "Fetching ampup {} for {}-{}",
+                ui::version(&latest),
+                platform,
+                arch
+            );
+            let artifact_name = format!("ampup-{}-{}", platform.as_str(), arch.as_str());
+            let data = self_github
+                .download_release_asset(&latest, &artifact_name)
+                .await
+                .context("Failed to download ampup binary")?;
+            let data =
+                archive::unpack_binary(data, "ampup").context("Failed to extract ampup binary")?;
+            (latest, data)
+        };
+    files.insert(AMPUP_FILE.to_string(), ampup);
+
+    let info = BundleInfo {
+        format: BUNDLE_FORMAT,
+        repo,
+        platform: platform.as_str().to_string(),
+        arch: arch.as_str().to_string(),
+        versions: resolved,
+        ampup: Some(ampup_version),
+    };
+    let parent = match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let staged = tempfile::NamedTempFile::new_in(&parent)
+        .context("Failed to create temporary bundle file")?;
+    bundle::write(&info, &files, signing_key.as_ref(), staged.as_file())?;
+    staged
+        .persist(&output)
+        .map_err(|err| err.error)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+
+    ui::success!(
+        "Bundled {} for {}-{} into {} ({})",
+        info.versions.join(", "),
+        platform,
+        arch,
+        ui::path(output.display()),
+        ui::size(fs::metadata(&output)?.len())
+    );
+    match &signing_key {
+        Some(key) => ui::detail!("Signed by {}", key.public_key()),
+        None => ui::detail!("Unsigned; pass --signing-key to sign it"),
+    }
+    ui::detail!(
+        "Install offline with: ampup bundle install {}",
+        output.display()
+    );
+    Ok(())
+}
+
+/// Install the versions in a bundle written by `ampup bundle create`, and
+/// unless `keep_ampup`, the ampup it carries. With `trusted_keys`, the
+/// bundle must be signed by one of them. Installed versions are only
+/// replaced with `force`.
+pub fn install(
+    install_dir: Option<PathBuf>,
+    file: &Path,
+    trusted_keys: Vec<String>,
+    force: bool,
+    keep_ampup: bool,
+) -> Result<()> {
+    let version_manager = VersionManager::new(Config::new(install_dir)?);
+    let input = fs::File::open(file).context("Failed to open bundle")?;
+    let bundle = bundle::read(BufReader::new(input), &trusted_keys)?;
+    let info = &bundle.info;
+
+    match (&bundle.signed_by, bundle.signed) {
+        (Some(key), _) => ui::detail!("Signed by trusted key {}", key),
+        (None, true) => ui::warn!("Bundle signature not checked; pass --trusted-key to check it"),
+        (None, false) => ui::warn!("Bundle is unsigned; only its checksums were checked"),
+    }
+    info.check_target(Platform::detect()?, Architecture::detect()?)?;
+
+    let namespace = repo_namespace(&info.repo);
+    let mut installed = Vec::new();
+    for version in &info.versions {
+        let key = version_key(&namespace, version);
+        if version_manager.is_installed(&key) {
+            version_manager.check_repo(&key, &info.repo)?;
+            if !force {
+                ui::detail!("{} is already installed; skipping", ui::version(&key));
+                continue;
+            }
+        }
+        let files = bundle
+            .version_files(version)
+            .into_iter()
+            .map(|(name, data)| (name, (data, 0o755)))
+            .collect();
+        portable::install_files(&version_manager, &key, &files)?;
+        VersionManifest::update(&version_manager.version_dir(&key), |manifest| {
+            manifest.repo = Some(info.repo.clone());
+        })?;
+        ui::detail!("Installed {}", ui::version(&key));
+        installed.push(key);
+    }
+
+    let current_ampup = env!("VERGEN_GIT_DESCRIBE");
+    if let (Some(ampup), Some(version)) = (bundle.ampup(), &info.ampup)
+        && !keep_ampup
+        && (version != current_ampup || !version_manager.config().ampup_binary_path().exists())
+    {
+        replace_ampup(version_manager.config(), ampup)?;
+        ui::detail!(
+            "Updated ampup from {} to {}",
+            current_ampup,
+            ui::version(version)
+        );
+    }
+
+    ui::success!(
+        "Installed {} of {} bundled version(s) from {}",
+        installed.len(),
+        info.versions.len(),
+        ui::path(file.display())
+    );
+    let active = version_manager
+        .get_current()?
+        .filter(|current| version_manager.is_installed(current));
+    match (active, installed.last()) {
+        (None, Some(key)) => {
+            version_manager.activate(key)?;
+            ui::success!("Switched to ampd {}", ui::version(key));
+        }
+        (Some(_), Some(key)) => ui::detail!("Switch with: ampup use {}", key),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Generate a bundle signing key pair, writing the private key to `output`
+/// and the public key to `<output>.pub`.
+pub fn keygen(output: &Path) -> Result<()> {
+    let public_path = PathBuf::from(format!("{}.pub", output.display()));
+    for path in [output, public_path.as_path()] {
+        if path.exists() {
+            anyhow::bail!(
+                "{} already exists; refusing to overwrite it",
+                path.display()
+            );
+        }
+    }
+
+    let (key, encoded) = SigningKey::generate()?;
+    write_private(output, &encoded)?;
+    fs::write(&public_path, format!("{}\n", key.public_key()))
+        .context("Failed to write public key")?;
+
+    ui::success!("Wrote signing key to {}", ui::path(output.display()));
+    ui::detail!("Public key (also in {}):", public_path.display());
+    println!("{}", key.public_key());
+    ui::detail!("Keep the private key secret; pass the public key to bundle install --trusted-key");
+    Ok(())
+}
+
+/// Write the private key readable only by its owner.
+fn write_private(path: &Path, encoded: &str) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use fs_err::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).context("Failed to create signing key")?;
+    std::io::Write::write_all(&mut file, format!("{}\n", encoded).as_bytes())
+        .context("Failed to write signing key")
+}
+
+/// Replace the ampup binary in the installation's `bin/` with `data`,
+/// writing it beside and moving it into place.
+fn replace_ampup(config: &Config, data: &[u8]) -> Result<()> {
+    let path = config.ampup_binary_path();
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, data).context("Failed to write ampup binary")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&temp_path, std::fs::Permissions::from_mode(0o755))
+            .context("Failed to set executable permissions")?;
+    }
+    fs::rename(&temp_path, &path).context("Failed to replace ampup binary")
+}
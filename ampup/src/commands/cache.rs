@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::{artifact_cache::ArtifactCache, config::Config, settings::Settings, ui};
+
+/// List cached artifacts with their size once decompressed and on disk.
+pub fn list(install_dir: Option<PathBuf>) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let settings = Settings::load(&config)?;
+    let entries = settings
+        .artifact_cache(config.artifact_cache_dir())
+        .entries()?;
+
+    if entries.is_empty() {
+        ui::info!("No cached artifacts");
+        return Ok(());
+    }
+
+    let (mut logical, mut physical) = (0, 0);
+    for entry in &entries {
+        let size = entry.logical_bytes.unwrap_or(entry.physical_bytes);
+        logical += size;
+        physical += entry.physical_bytes;
+        println!(
+            "{:>10} {:>10}  {}",
+            ui::size(size),
+            ui::size(entry.physical_bytes),
+            entry.name
+        );
+    }
+    ui::detail!(
+        "{} cached artifacts: {} logical, {} on disk",
+        entries.len(),
+        ui::size(logical),
+        ui::size(physical)
+    );
+    Ok(())
+}
+
+/// Re-hash every cached artifact, evicting those that fail verification.
+pub fn verify(install_dir: Option<PathBuf>) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let dir = config.artifact_cache_dir();
+
+    ui::info!("Verifying artifact cache {}", ui::path(dir.display()));
+    let report = ArtifactCache::new(dir).verify()?;
+
+    for name in &report.evicted {
+        println!("evicted {}", name);
+    }
+
+    if !report.evicted.is_empty() {
+        ui::warn!(
+            "Evicted {} corrupt cache entries; they will be downloaded again on next install",
+            report.evicted.len()
+        );
+    }
+    ui::success!(
+        "{} cached artifacts verified ({} logical, {} on disk)",
+        report.valid,
+        ui::size(report.valid_bytes),
+        ui::size(report.physical_bytes)
+    );
+    Ok(())
+}
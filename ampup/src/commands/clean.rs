@@ -0,0 +1,83 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::Result;
+
+use crate::{
+    config::Config,
+    temp_files, ui,
+    version_manager::{VersionManager, unix_now},
+};
+
+/// Remove temporary files and directories interrupted runs left behind,
+/// only those last modified longer than `older_than` ago when given, and
+/// with `incomplete` also version directories missing `ampd`. With
+/// `dry_run`, only list them.
+pub fn run(
+    install_dir: Option<PathBuf>,
+    incomplete: bool,
+    older_than: Option<Duration>,
+    dry_run: bool,
+) -> Result<()> {
+    let version_manager = VersionManager::new(Config::new(install_dir)?);
+
+    let versions = match incomplete {
+        true => version_manager.list_incomplete()?,
+        false => Vec::new(),
+    };
+    let artifacts = temp_files::find(&version_manager, older_than.unwrap_or_default(), unix_now())?;
+    if versions.is_empty() && artifacts.is_empty() {
+        ui::info!("Nothing to clean");
+        return Ok(());
+    }
+
+    let mut freed = 0;
+    for key in &versions {
+        let size = version_manager.disk_usage(key)?;
+        if dry_run {
+            ui::info!(
+                "Would remove incomplete {} ({})",
+                ui::version(key),
+                ui::size(size)
+            );
+        } else {
+            version_manager.uninstall(key)?;
+            ui::detail!(
+                "Removed incomplete {} ({})",
+                ui::version(key),
+                ui::size(size)
+            );
+        }
+        freed += size;
+    }
+    for artifact in &artifacts {
+        if dry_run {
+            ui::info!(
+                "Would remove {} {} ({})",
+                artifact.kind,
+                ui::path(artifact.path.display()),
+                ui::size(artifact.bytes)
+            );
+        } else {
+            temp_files::remove(artifact)?;
+            ui::detail!(
+                "Removed {} {} ({})",
+                artifact.kind,
+                ui::path(artifact.path.display()),
+                ui::size(artifact.bytes)
+            );
+        }
+        freed += artifact.bytes;
+    }
+
+    let count = versions.len() + artifacts.len();
+    if dry_run {
+        ui::info!(
+            "Would remove {} item(s), freeing {}",
+            count,
+            ui::size(freed)
+        );
+    } else {
+        ui::success!("Removed {} item(s), freeing {}", count, ui::size(freed));
+    }
+    Ok(())
+}
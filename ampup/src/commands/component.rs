@@ -0,0 +1,206 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use console::style;
+
+use crate::{
+    components::{self, ComponentError, InstalledComponent},
+    config::Config,
+    download_manager::{DownloadManager, DownloadTask},
+    environment::ProcessEnv,
+    gatekeeper,
+    github::{ClientOptions, GitHubClient},
+    manifest::VersionManifest,
+    platform::{Architecture, Platform},
+    progress, release_source,
+    settings::Settings,
+    ui,
+    version_manager::{VersionError, VersionManager},
+    version_store::{repo_namespace, split_key},
+};
+
+/// The active version's key, release tag and directory.
+struct Active {
+    key: String,
+    version: String,
+    dir: PathBuf,
+}
+
+/// The active version, which must be a release of `github`'s repository
+/// for its components to be looked up.
+fn active_release(version_manager: &VersionManager, github: &GitHubClient) -> Result<Active> {
+    let key = version_manager
+        .get_current()?
+        .ok_or(VersionError::NoVersionsInstalled)?;
+    let (namespace, version) = split_key(&key);
+    if namespace != Some(repo_namespace(github.repo()).as_str()) {
+        return Err(ComponentError::NotARelease {
+            version: key.clone(),
+            repo: github.repo().to_string(),
+        }
+        .into());
+    }
+    Ok(Active {
+        version: version.to_string(),
+        dir: version_manager.version_dir(&key),
+        key,
+    })
+}
+
+/// Print the components the active version's release declares, marking
+/// those added. Without the release, only the added ones are listed.
+pub async fn list(
+    install_dir: Option<PathBuf>,
+    repo: Option<String>,
+    github_token: Option<String>,
+    options: ClientOptions,
+) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let settings = Settings::load(&config)?;
+    let options = options.or(settings.client_options());
+    let github = settings.github_client(settings.repo(repo), github_token, options)?;
+    let version_manager = VersionManager::new(config);
+    let active = active_release(&version_manager, &github)?;
+    let added = VersionManifest::load(&active.dir)?.components;
+    let is_added = |name: &str| added.iter().any(|c| c.name == name);
+
+    let declared = match components::fetch(&github, &active.version).await {
+        Ok(manifest) => manifest.components,
+        Err(err) => {
+            ui::warn!("Failed to fetch the release's components: {:#}", err);
+            for component in &added {
+                println!("{} {}", component.name, style("(added)").dim());
+            }
+            return Ok(());
+        }
+    };
+    if declared.is_empty() && added.is_empty() {
+        ui::info!("{} declares no components", ui::version(&active.key));
+        return Ok(());
+    }
+
+    for component in &declared {
+        let note = if is_added(&component.name) {
+            format!(" {}", style("(added)").dim())
+        } else {
+            String::new()
+        };
+        match &component.description {
+            Some(description) => println!("{}{}\t{}", component.name, note, description),
+            None => println!("{}{}", component.name, note),
+        }
+    }
+    Ok(())
+}
+
+/// Download the components `names` of the active version's release into
+/// its directory and link their binaries into `bin/`.
+pub async fn add(
+    install_dir: Option<PathBuf>,
+    repo: Option<String>,
+    github_token: Option<String>,
+    names: Vec<String>,
+    jobs: usize,
+    source: Option<String>,
+    options: ClientOptions,
+) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let settings = Settings::load(&config)?;
+    let options = options.or(settings.client_options());
+    let github = settings.github_client(settings.repo(repo), github_token, options.clone())?;
+    let artifact_cache = settings.artifact_cache(config.artifact_cache_dir());
+    let version_manager = VersionManager::new(config);
+    let active = active_release(&version_manager, &github)?;
+
+    // Check every name before downloading any
+    let manifest = components::fetch(&github, &active.version).await?;
+    let mut specs = Vec::new();
+    for name in &names {
+        let spec = manifest
+            .get(name)
+            .ok_or_else(|| ComponentError::Unknown {
+                name: name.clone(),
+                version: active.version.clone(),
+                available: manifest.names(),
+            })?
+            .clone();
+        spec.validate()?;
+        specs.push(spec);
+    }
+
+    let platform = Platform::detect()?;
+    let arch = Architecture::detect()?;
+    let source = release_source::with_mirrors(
+        source.or(settings.source.clone()).as_deref(),
+        &settings.mirrors,
+        &github,
+        options.timeouts,
+    )?;
+    let download_manager = DownloadManager::new(source, jobs)
+        .with_cache(artifact_cache)
+        .with_require_digests(settings.policy().require_digests);
+
+    for spec in specs {
+        ui::info!("Adding {} to {}", spec.name, ui::version(&active.version));
+        let target = format!("{}-{}", platform.as_str(), arch.as_str());
+        let binaries = spec.binaries.iter().map(|binary| DownloadTask {
+            artifact_name: format!("{}-{}", binary, target),
+            dest_filename: binary.clone(),
+            target: Some((platform, arch)),
+        });
+        let files = spec.files.iter().map(|file| DownloadTask {
+            artifact_name: format!("{}-{}", file, target),
+            dest_filename: file.clone(),
+            target: None,
+        });
+        let dir = components::component_dir(&active.dir, &spec.name);
+        download_manager
+            .download_all(
+                binaries.chain(files).collect(),
+                &active.version,
+                dir.clone(),
+                progress::create_reporter(),
+            )
+            .await?;
+
+        if cfg!(target_os = "macos") {
+            let binaries: Vec<&str> = spec.binaries.iter().map(String::as_str).collect();
+            gatekeeper::prepare(&dir, &binaries, settings.macos.codesign, &ProcessEnv);
+        }
+
+        let component = InstalledComponent {
+            name: spec.name.clone(),
+            binaries: spec.binaries.clone(),
+        };
+        components::record(&active.dir, component.clone())?;
+        components::link(version_manager.config(), &active.dir, &component)?;
+        ui::success!("Added {}", spec.name);
+        for binary in &spec.binaries {
+            ui::detail!("Linked {}", binary);
+        }
+    }
+    Ok(())
+}
+
+/// Remove the components `names` from the active version.
+pub fn remove(install_dir: Option<PathBuf>, names: Vec<String>) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let version_manager = VersionManager::new(config);
+    let key = version_manager
+        .get_current()?
+        .ok_or(VersionError::NoVersionsInstalled)?;
+    let dir = version_manager.version_dir(&key);
+
+    for name in names {
+        let Some(component) = components::remove(&dir, &name)? else {
+            return Err(ComponentError::NotAdded {
+                name,
+                version: key.clone(),
+            }
+            .into());
+        };
+        components::unlink(version_manager.config(), &component)?;
+        ui::success!("Removed {}", name);
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use console::style;
+
+use crate::{
+    config::Config,
+    settings::{KEYS, Settings},
+    ui,
+};
+
+/// Print the value of a key, or nothing when it is not set.
+pub fn get(install_dir: Option<PathBuf>, key: &str) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    if let Some(value) = Settings::get(&config, key)? {
+        println!("{}", value);
+    }
+    Ok(())
+}
+
+pub fn set(install_dir: Option<PathBuf>, key: &str, value: &str) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    Settings::set(&config, key, value)?;
+    ui::success!("Set {} = {}", key, ui::version(value));
+    Ok(())
+}
+
+pub fn unset(install_dir: Option<PathBuf>, key: &str) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    if Settings::unset(&config, key)? {
+        ui::success!("Unset {}", key);
+    } else {
+        ui::info!("{} was not set", key);
+    }
+    Ok(())
+}
+
+pub fn list(install_dir: Option<PathBuf>) -> Result<()> {
+    let config = Config::new(install_dir)?;
+
+    ui::info!("Settings ({})", ui::path(config.settings_file().display()));
+    for (key, description) in KEYS {
+        // Per-repository keys are listed below, one per configured repository
+        if key.contains('<') {
+            continue;
+        }
+        let value = Settings::get(&config, key)?;
+        let value = match value {
+            Some(value) => style(value).bold().to_string(),
+            None => style("(not set)").dim().to_string(),
+        };
+        println!("  {:<22} {}", key, value);
+        println!("  {:<22} {}", "", style(description).dim());
+    }
+
+    let settings = Settings::load(&config)?;
+    for (repo, repo_settings) in &settings.repos {
+        if let Some(command) = &repo_settings.token_command {
+            println!("  repos.{}.token_command {}", repo, style(command).bold());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::{
+    config::Config,
+    github::ClientOptions,
+    platform::{Architecture, Platform},
+    release_source,
+    request_plan::PlannedRequest,
+    resolver::{VersionResolver, VersionSpec},
+    settings::Settings,
+};
+
+/// Print the HTTP requests `ampup install <version>` would send, as curl
+/// commands, followed by the hosts they reach. Nothing is sent.
+///
+/// The plan assumes an empty artifact cache and no binary patches, so it
+/// lists every request an install could need.
+#[expect(clippy::too_many_arguments)]
+pub fn requests_install(
+    install_dir: Option<PathBuf>,
+    repo: Option<String>,
+    github_token: Option<String>,
+    version: Option<String>,
+    arch_override: Option<String>,
+    platform_override: Option<String>,
+    source: Option<String>,
+    options: ClientOptions,
+) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let settings = Settings::load(&config)?;
+    let options = options.or(settings.client_options());
+    let github = settings.github_client(settings.repo(repo), github_token, options.clone())?;
+
+    let spec: VersionSpec = version.as_deref().unwrap_or("latest").parse()?;
+    let mut requests = VersionResolver::new(&github).plan_release(&spec)?;
+    let version = match &spec {
+        VersionSpec::Exact(tag) => tag.clone(),
+        _ => "<version>".to_string(),
+    };
+
+    let platform = Platform::from_override(platform_override)?;
+    let arch = Architecture::from_override(arch_override)?;
+    let ampd_artifact = format!("ampd-{}-{}", platform.as_str(), arch.as_str());
+    let ampctl_artifact = format!("ampctl-{}-{}", platform.as_str(), arch.as_str());
+
+    let source = release_source::with_mirrors(
+        source.or(settings.source).as_deref(),
+        &settings.mirrors,
+        &github,
+        options.timeouts,
+    )?;
+    requests.extend(source.plan_requests(&version, &[&ampd_artifact, &ampctl_artifact]));
+
+    let requests = dedup(requests);
+    for (index, request) in requests.iter().enumerate() {
+        println!("# {}. {}", index + 1, request.purpose);
+        println!("{}", request.to_curl());
+        println!();
+    }
+    println!("# Hosts: {}", hosts(&requests).join(", "));
+    Ok(())
+}
+
+/// Drop repeated requests, which the client answers from responses it
+/// already has (e.g. one GraphQL query serving every release lookup).
+fn dedup(requests: Vec<PlannedRequest>) -> Vec<PlannedRequest> {
+    let mut unique: Vec<PlannedRequest> = Vec::new();
+    for request in requests {
+        let seen = unique
+            .iter()
+            .any(|r| r.method == request.method && r.url == request.url && r.body == request.body);
+        if !seen {
+            unique.push(request);
+        }
+    }
+    unique
+}
+
+/// Hosts the requests reach, in order of first use.
+fn hosts(requests: &[PlannedRequest]) -> Vec<String> {
+    let mut hosts: Vec<String> = Vec::new();
+    for request in requests {
+        let Some((_, rest)) = request.url.split_once("://") else {
+            continue;
+        };
+        let host = rest.split('/').next().unwrap_or(rest).to_string();
+        if !hosts.contains(&host) {
+            hosts.push(host);
+        }
+    }
+    hosts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedup_with_repeated_graphql_query_keeps_first() {
+        //* Given
+        let query = PlannedRequest::new("POST", "https://api.github.com/graphql", "Releases")
+            .with_body("{}".to_string());
+        let requests = vec![
+            query.clone(),
+            query,
+            PlannedRequest::new("GET", "https://github.com/a/b", "Download"),
+        ];
+
+        //* When
+        let requests = dedup(requests);
+
+        //* Then
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            hosts(&requests),
+            vec!["api.github.com".to_string(), "github.com".to_string()]
+        );
+    }
+}
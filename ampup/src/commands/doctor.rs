@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::{
+    config::Config,
+    egress::Allowlist,
+    github::ClientOptions,
+    ownership::{self, OwnershipError},
+    settings::Settings,
+    ui,
+};
+
+/// Most files with the wrong owner listed before the rest are summarized.
+const MAX_LISTED_FILES: usize = 10;
+
+/// Print the hosts and ports ampup needs to reach with the current settings,
+/// for firewall allow-lists.
+pub fn egress(
+    install_dir: Option<PathBuf>,
+    repo: Option<String>,
+    source: Option<String>,
+    json: bool,
+    options: ClientOptions,
+) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let settings = Settings::load(&config)?;
+    let allowlist = Allowlist::for_settings(&settings, repo, source, options)?;
+
+    if json {
+        let rendered =
+            serde_json::to_string_pretty(&allowlist).context("Failed to serialize allowlist")?;
+        println!("{}", rendered);
+    } else {
+        print!("{}", allowlist.render());
+    }
+    Ok(())
+}
+
+/// Check that every file in the installation belongs to its owner, as
+/// after an earlier run with sudo they may not.
+pub fn permissions(install_dir: Option<PathBuf>) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let owner = ownership::installation_owner(&config.amp_dir)?;
+    let foreign = ownership::foreign_files(&config.amp_dir, owner)?;
+
+    if foreign.is_empty() {
+        ui::success!(
+            "All files in {} belong to uid {}",
+            ui::path(config.amp_dir.display()),
+            owner.uid
+        );
+        return Ok(());
+    }
+    for path in foreign.iter().take(MAX_LISTED_FILES) {
+        ui::detail!("{}", path.display());
+    }
+    if foreign.len() > MAX_LISTED_FILES {
+        ui::detail!("... and {} more", foreign.len() - MAX_LISTED_FILES);
+    }
+    Err(OwnershipError::ForeignFiles {
+        amp_dir: config.amp_dir,
+        count: foreign.len(),
+    }
+    .into())
+}
@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::{
+    config::Config,
+    portable, ui,
+    version_manager::{VersionError, VersionManager},
+};
+
+/// Write installed `version` to a portable `.tar.gz`, `output` or
+/// `<namespace>-<version>.tar.gz` in the current directory, for
+/// `ampup import` on another machine.
+pub fn run(
+    install_dir: Option<PathBuf>,
+    version: &str,
+    repo: Option<String>,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let version_manager = VersionManager::new(Config::new(install_dir)?);
+    let key = version_manager.resolve(version, repo.as_deref())?;
+    if !version_manager.is_installed(&key) {
+        return Err(VersionError::BinaryNotFound { version: key }.into());
+    }
+    let output = output.unwrap_or_else(|| PathBuf::from(portable::default_file_name(&key)));
+
+    // Write next to the output and move it into place, so a failed export
+    // never leaves a truncated tarball behind
+    let parent = match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let staged = tempfile::NamedTempFile::new_in(&parent)
+        .context("Failed to create temporary export file")?;
+    portable::export(&version_manager, &key, staged.as_file())?;
+    staged
+        .persist(&output)
+        .map_err(|err| err.error)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+
+    let size = fs::metadata(&output)?.len();
+    ui::success!(
+        "Exported {} to {} ({})",
+        ui::version(&key),
+        ui::path(output.display()),
+        ui::size(size)
+    );
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::{
+    config::Config,
+    download_manager::{DownloadManager, DownloadTask},
+    github::ClientOptions,
+    platform::{Architecture, Platform},
+    progress, release_source,
+    resolver::{VersionResolver, VersionSpec},
+    settings::Settings,
+    target_matrix, ui,
+};
+
+/// Download the ampd and ampctl artifacts of `version` for each of `targets`
+/// (`<platform>-<arch>`, default: this host) into `<output>/<version>/`,
+/// without installing or activating anything.
+///
+/// Artifacts keep their release names, so the output directory can serve
+/// offline hosts as a `file://` mirror.
+#[expect(clippy::too_many_arguments)]
+pub async fn run(
+    install_dir: Option<PathBuf>,
+    repo: Option<String>,
+    github_token: Option<String>,
+    version: Option<String>,
+    targets: Vec<String>,
+    output: PathBuf,
+    jobs: usize,
+    source: Option<String>,
+    allow_prerelease: bool,
+    options: ClientOptions,
+) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let settings = Settings::load(&config)?;
+    let options = options.or(settings.client_options());
+    let policy = settings.policy();
+    let github = settings.github_client(settings.repo(repo), github_token, options.clone())?;
+
+    let cross = !targets.is_empty();
+    let targets = if targets.is_empty() {
+        vec![(Platform::detect()?, Architecture::detect()?)]
+    } else {
+        targets
+            .iter()
+            .map(|target| parse_target(target))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    let spec: VersionSpec = version.as_deref().unwrap_or("latest").parse()?;
+    if spec.needs_lookup() {
+        ui::info!("Resolving version {}", ui::version(&spec));
+    }
+    let resolver = VersionResolver::new(&github);
+    let version = resolver.resolve_release(&spec).await?;
+    resolver
+        .check_prerelease(&spec, &version, allow_prerelease, &policy)
+        .await?;
+    if cross {
+        target_matrix::check(&github, &version, &targets).await?;
+    }
+
+    let source = release_source::with_mirrors(
+        source.or(settings.source.clone()).as_deref(),
+        &settings.mirrors,
+        &github,
+        options.timeouts,
+    )?;
+    ui::detail!("Source: {}", source.describe());
+
+    let tasks: Vec<DownloadTask> = targets
+        .iter()
+        .flat_map(|&(platform, arch)| {
+            ["ampd", "ampctl"].map(|binary| {
+                let artifact_name = format!("{}-{}-{}", binary, platform.as_str(), arch.as_str());
+                DownloadTask {
+                    dest_filename: artifact_name.clone(),
+                    artifact_name,
+                    target: Some((platform, arch)),
+                }
+            })
+        })
+        .collect();
+    let count = tasks.len();
+
+    let dir = output.join(&version);
+    fs::create_dir_all(&output).context("Failed to create output directory")?;
+    ui::info!(
+        "Fetching {} for {}",
+        ui::version(&version),
+        targets
+            .iter()
+            .map(|(platform, arch)| format!("{}-{}", platform, arch))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    DownloadManager::new(source, jobs)
+        .with_cache(settings.artifact_cache(config.artifact_cache_dir()))
+        .with_require_digests(policy.require_digests)
+        .download_all(tasks, &version, dir.clone(), progress::create_reporter())
+        .await?;
+
+    ui::success!("Fetched {} artifacts to {}", count, ui::path(dir.display()));
+    ui::detail!(
+        "Install offline with: ampup install {} --source file://{}",
+        version,
+        output.canonicalize().unwrap_or(output).display()
+    );
+    Ok(())
+}
+
+/// Parse a `<platform>-<arch>` target such as `linux-x86_64`.
+fn parse_target(target: &str) -> Result<(Platform, Architecture)> {
+    let (platform, arch) = target.split_once('-').unwrap_or((target, ""));
+    Ok((
+        Platform::from_override(Some(platform.to_string()))?,
+        Architecture::from_override(Some(arch.to_string()))?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_target_with_platform_and_arch_returns_both() {
+        //* Then
+        assert_eq!(
+            parse_target("darwin-arm64").expect("valid target"),
+            (Platform::Darwin, Architecture::Aarch64)
+        );
+        assert_eq!(
+            parse_target("linux-x86_64").expect("valid target"),
+            (Platform::Linux, Architecture::X86_64)
+        );
+    }
+
+    #[test]
+    fn parse_target_without_arch_fails() {
+        //* Then
+        assert!(parse_target("linux").is_err());
+    }
+}
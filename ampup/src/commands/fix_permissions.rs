@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::{
+    config::Config,
+    ownership::{self, OwnershipError},
+    ui,
+};
+
+/// Give every file in the installation back to its owner, e.g. after
+/// `sudo ampup install` left root-owned files in a user's `~/.amp`.
+pub fn run(install_dir: Option<PathBuf>) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let owner = ownership::installation_owner(&config.amp_dir)?;
+    let foreign = ownership::foreign_files(&config.amp_dir, owner)?;
+
+    if foreign.is_empty() {
+        ui::success!(
+            "All files in {} already belong to uid {}",
+            ui::path(config.amp_dir.display()),
+            owner.uid
+        );
+        return Ok(());
+    }
+    if !ownership::is_root() {
+        return Err(OwnershipError::NeedsRoot {
+            amp_dir: config.amp_dir,
+            count: foreign.len(),
+        }
+        .into());
+    }
+
+    ownership::chown_all(&foreign, owner)?;
+    ui::success!(
+        "Gave {} file(s) in {} back to uid {}",
+        foreign.len(),
+        ui::path(config.amp_dir.display()),
+        owner.uid
+    );
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::{
+    config::Config,
+    environment::{Environment, ProcessEnv},
+    instances::{InstanceError, Instances},
+    platform::Platform,
+    service::{RestartPolicy, ServiceSpec},
+    ui,
+};
+
+#[derive(Debug)]
+pub enum GenerateError {
+    /// `systemctl` or `launchctl` failed while enabling the service.
+    ServiceManagerFailed {
+        command: String,
+        reason: String,
+        path: PathBuf,
+    },
+}
+
+impl std::fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ServiceManagerFailed {
+                command,
+                reason,
+                path,
+            } => {
+                writeln!(f, "Failed to enable the service")?;
+                writeln!(f, "  Command: {}", command)?;
+                writeln!(f, "  Error: {}", reason)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  The service file was written to {}; enable it manually.",
+                    path.display()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for GenerateError {}
+
+/// Print a systemd unit (a launchd plist on macOS) running the active ampd,
+/// or `instance`'s wrapper script, or with `install`, write it where the
+/// user's service manager loads it from and enable it.
+pub fn systemd(
+    install_dir: Option<PathBuf>,
+    instance: Option<String>,
+    restart: RestartPolicy,
+    platform: Option<String>,
+    install: bool,
+    args: Vec<String>,
+) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let platform = Platform::from_override(platform)?;
+
+    let spec = match instance {
+        Some(name) => {
+            if Instances::load(&config)?.get(&name).is_none() {
+                return Err(InstanceError::NotFound { name }.into());
+            }
+            ServiceSpec {
+                program: config.instance_wrapper_path(&name),
+                name: format!("ampd-{}", name),
+                args,
+                restart,
+            }
+        }
+        None => ServiceSpec {
+            name: "ampd".to_string(),
+            program: config.active_binary_path(),
+            args,
+            restart,
+        },
+    };
+    let contents = spec.render(platform);
+
+    if !install {
+        print!("{}", contents);
+        return Ok(());
+    }
+
+    let path = spec.install_path(platform, &ProcessEnv)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Failed to create service directory")?;
+    }
+    fs::write(&path, &contents).context("Failed to write service file")?;
+    ui::info!("Wrote {}", ui::path(path.display()));
+
+    let steps: Vec<Vec<String>> = match platform {
+        Platform::Linux => vec![
+            vec!["systemctl".into(), "--user".into(), "daemon-reload".into()],
+            vec![
+                "systemctl".into(),
+                "--user".into(),
+                "enable".into(),
+                "--now".into(),
+                format!("{}.service", spec.name),
+            ],
+        ],
+        Platform::Darwin => vec![vec![
+            "launchctl".into(),
+            "load".into(),
+            "-w".into(),
+            path.display().to_string(),
+        ]],
+    };
+    for step in steps {
+        run_service_manager(&step, &path, &ProcessEnv)?;
+    }
+
+    ui::success!("Enabled and started {}", spec.name);
+    if platform == Platform::Linux {
+        ui::detail!("Run 'loginctl enable-linger' to keep it running after you log out");
+    }
+    Ok(())
+}
+
+fn run_service_manager(step: &[String], path: &Path, env: &dyn Environment) -> Result<()> {
+    let failed = |reason: String| GenerateError::ServiceManagerFailed {
+        command: step.join(" "),
+        reason,
+        path: path.to_path_buf(),
+    };
+
+    let status = env
+        .command(&step[0])
+        .args(&step[1..])
+        .status()
+        .map_err(|err| failed(err.to_string()))?;
+    if !status.success() {
+        return Err(failed(format!("exited with {}", status)).into());
+    }
+    Ok(())
+}
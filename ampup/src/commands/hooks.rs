@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::{
+    config::Config,
+    hooks::{HookContext, HookError, HookEvent, Hooks},
+    ui,
+    version_manager::{VersionError, VersionManager},
+};
+
+/// Print the hooks of every event, in the order they run.
+pub fn list(install_dir: Option<PathBuf>) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let hooks = Hooks::new(&config);
+
+    let mut count = 0;
+    for event in HookEvent::ALL {
+        for name in hooks.list(event)? {
+            println!("{}\t{}", event.as_str(), name);
+            count += 1;
+        }
+    }
+    if count == 0 {
+        ui::info!("No hooks installed");
+        ui::detail!("Add one with: ampup hooks add <event> <script>");
+    }
+    Ok(())
+}
+
+/// Install `script` as a hook for `event`.
+pub fn add(
+    install_dir: Option<PathBuf>,
+    event: HookEvent,
+    script: &Path,
+    name: Option<String>,
+) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let path = Hooks::new(&config).add(event, script, name.as_deref())?;
+    ui::success!("Added {} hook {}", event.as_str(), ui::path(path.display()));
+    ui::detail!("Try it with: ampup hooks run {} --test", event.as_str());
+    Ok(())
+}
+
+/// Remove the hook `name` of `event`.
+pub fn remove(install_dir: Option<PathBuf>, event: HookEvent, name: &str) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    Hooks::new(&config).remove(event, name)?;
+    ui::success!("Removed {} hook {}", event.as_str(), name);
+    Ok(())
+}
+
+/// Run the hooks of `event` (or only `name`) and report each outcome.
+///
+/// With `test`, hooks get made-up event data (`AMPUP_HOOK_TEST=1`) and a
+/// scratch version directory; otherwise they see the active version being
+/// re-activated.
+pub fn run(
+    install_dir: Option<PathBuf>,
+    event: HookEvent,
+    name: Option<String>,
+    test: bool,
+) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let hooks = Hooks::new(&config);
+    let scratch = tempfile::tempdir()?;
+    let context = if test {
+        HookContext {
+            from: Some("edgeandnode-amp/v0.0.0-test".to_string()),
+            to: "edgeandnode-amp/v0.0.1-test".to_string(),
+            version_dir: scratch.path().to_path_buf(),
+            test: true,
+        }
+    } else {
+        let version_manager = VersionManager::new(config);
+        let active = version_manager
+            .get_current()?
+            .ok_or(VersionError::NoVersionsInstalled)?;
+        HookContext {
+            from: Some(active.clone()),
+            version_dir: version_manager.version_dir(&active),
+            to: active,
+            test: false,
+        }
+    };
+
+    let runs = hooks.run(event, &context, name.as_deref())?;
+    if runs.is_empty() {
+        ui::info!("No {} hooks installed", event.as_str());
+        return Ok(());
+    }
+
+    let mut failed = Vec::new();
+    for run in runs {
+        match &run.error {
+            None => ui::success!("{} passed", run.name),
+            Some(reason) => ui::warn!("{} failed: {}", run.name, reason),
+        }
+        for line in run.output.lines() {
+            ui::detail!("{}", line);
+        }
+        if run.error.is_some() {
+            failed.push(run.name);
+        }
+    }
+    if !failed.is_empty() {
+        return Err(HookError::Failed {
+            event: event.as_str().to_string(),
+            failed,
+        }
+        .into());
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::{
+    commands::use_version,
+    config::Config,
+    github::ClientOptions,
+    platform::{Architecture, Platform},
+    portable, ui,
+    version_manager::VersionManager,
+};
+
+/// Install the version in a tarball written by `ampup export`, replacing an
+/// installed one of the same key with `force`, and with `activate` switch
+/// to it.
+pub async fn run(
+    install_dir: Option<PathBuf>,
+    file: PathBuf,
+    activate: bool,
+    force: bool,
+    options: ClientOptions,
+) -> Result<()> {
+    let version_manager = VersionManager::new(Config::new(install_dir.clone())?);
+    let input = fs::File::open(&file).context("Failed to open export")?;
+
+    let key = portable::import(
+        &version_manager,
+        std::io::BufReader::new(input),
+        Platform::detect()?,
+        Architecture::detect()?,
+        force,
+    )?;
+    ui::success!(
+        "Imported {} from {}",
+        ui::version(&key),
+        ui::path(file.display())
+    );
+
+    if activate {
+        use_version::run(install_dir, Some(key), None, false, false, options).await?;
+    } else {
+        ui::detail!("Switch to it with: ampup use {}", key);
+    }
+    Ok(())
+}
@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use anyhow::{Context, Result};
 use fs_err as fs;
 
-use crate::{DEFAULT_REPO, config::Config, shell, ui};
+use crate::{config::Config, environment::ProcessEnv, github::ClientOptions, shell, ui};
 
 #[derive(Debug)]
 pub enum InitError {
@@ -32,6 +32,7 @@ pub async fn run(
     no_modify_path: bool,
     no_install_latest: bool,
     github_token: Option<String>,
+    options: ClientOptions,
 ) -> Result<()> {
     // Create config to get all the paths
     let config = Config::new(install_dir)?;
@@ -76,7 +77,7 @@ pub async fn run(
     // Modify PATH if requested
     if !no_modify_path {
         let bin_dir_str = config.bin_dir.to_string_lossy();
-        if let Err(e) = shell::add_to_path(&bin_dir_str) {
+        if let Err(e) = shell::add_to_path(&bin_dir_str, &ProcessEnv) {
             ui::warn!("Failed to add to PATH: {}", e);
             ui::detail!("Please manually add {} to your PATH", bin_dir_str);
         }
@@ -93,12 +94,24 @@ pub async fn run(
         // We'll use the existing install command
         crate::commands::install::run(
             Some(config.amp_dir),
-            DEFAULT_REPO.to_string(),
+            None,
             github_token,
             None,
             None,
             None,
             crate::DEFAULT_DOWNLOAD_JOBS,
+            None,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            None,
+            true,
+            options,
         )
         .await?;
     } else {
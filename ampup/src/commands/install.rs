@@ -1,101 +1,406 @@
+use std::io::IsTerminal;
+
 use anyhow::Result;
+use dialoguer::{Confirm, theme::ColorfulTheme};
 
 use crate::{
+    builder::{BuildOptions, BuildSource, Builder, CargoOptions},
+    channels,
+    commands::{upgrade, use_version},
     config::Config,
-    download_manager::DownloadManager,
-    github::GitHubClient,
-    install::Installer,
+    data_dir, downgrade,
+    download_manager::{DownloadError, DownloadManager},
+    environment::ProcessEnv,
+    github::{ClientOptions, GitHubError},
+    health,
+    install::VersionInstaller,
+    latest_cache::LatestCache,
+    manifest::VersionManifest,
     platform::{Architecture, Platform},
-    token, ui,
+    release_source,
+    resolver::{ResolveError, VersionResolver, VersionSpec},
+    settings::Settings,
+    target_matrix, ui,
     version_manager::VersionManager,
+    version_store::{repo_namespace, split_key, version_key},
 };
 
+#[expect(clippy::too_many_arguments)]
 pub async fn run(
     install_dir: Option<std::path::PathBuf>,
-    repo: String,
+    repo: Option<String>,
     github_token: Option<String>,
     version: Option<String>,
     arch_override: Option<String>,
     platform_override: Option<String>,
     jobs: usize,
+    source: Option<String>,
+    fallback_build: bool,
+    include_drafts: bool,
+    force: bool,
+    allow_prerelease: bool,
+    prerelease: bool,
+    allow_downgrade: bool,
+    smoke_test: bool,
+    reuse_latest: bool,
+    dest_root: Option<std::path::PathBuf>,
+    activate: bool,
+    options: ClientOptions,
 ) -> Result<()> {
     let config = Config::new(install_dir)?;
+    let amp_dir = config.amp_dir.clone();
+    let settings = Settings::load(&config)?;
+    let smoke_tests = settings.smoke_tests();
+    let policy = settings.policy();
 
-    // Resolve token with fallback chain: explicit → gh auth token → unauthenticated
-    let resolved_token = token::resolve_github_token(github_token);
-
-    let github = GitHubClient::new(repo, resolved_token)?;
-    let version_manager = VersionManager::new(config);
+    // Token fallback chain: explicit → token_command → GITHUB_TOKEN → gh auth token → unauthenticated
+    let options = options.or(settings.client_options());
+    let mut github = settings.github_client(settings.repo(repo), github_token, options.clone())?;
+    if include_drafts {
+        github = github.with_drafts()?;
+    }
+    let artifact_cache = settings.artifact_cache(config.artifact_cache_dir());
+    let latest_cache = LatestCache::new(&config.cache_dir(), github.repo());
+    // A cross-install lays out a separate tree with links relative to it, so
+    // it can be copied to the target machine
+    let version_manager = VersionManager::new(match &dest_root {
+        Some(root) => Config::new(Some(root.clone()))?.with_relative_links(),
+        None => config,
+    });
 
-    // Determine version to install
-    let version = match version {
-        Some(v) => v,
+    // Determine version to install, reusing a recent latest-version lookup
+    // when asked. Without a version, follow the tracked channel if any.
+    let spec = version
+        .as_deref()
+        .or(settings.update.channel.map(|channel| channel.as_str()))
+        .unwrap_or("latest")
+        .parse::<VersionSpec>()?;
+    let channel = match spec {
+        VersionSpec::Channel(channel) => Some(channel),
+        _ => None,
+    };
+    let spec = spec.including_prereleases(prerelease || settings.update.prerelease);
+    let cached = match spec {
+        VersionSpec::Latest if reuse_latest => latest_cache.get(settings.latest_cache_ttl()),
+        _ => None,
+    };
+    let version = match cached {
+        Some((version, age)) => {
+            ui::detail!(
+                "Latest version {} checked {}s ago (--refresh to check again)",
+                version,
+                age.as_secs()
+            );
+            version
+        }
         None => {
-            ui::info!("Fetching latest version");
-            github.get_latest_version().await?
+            if spec.needs_lookup() {
+                ui::info!("Resolving version {}", ui::version(&spec));
+            }
+            let version = VersionResolver::new(&github).resolve_release(&spec).await?;
+            if spec == VersionSpec::Latest
+                && let Err(err) = latest_cache.set(&version)
+            {
+                ui::warn!("Failed to cache latest version: {}", err);
+            }
+            version
         }
     };
+    let namespace = repo_namespace(github.repo());
+    let key = version_key(&namespace, &version);
 
     // Check if this version is already installed
-    if version_manager.is_installed(&version) {
+    if version_manager.is_installed(&key) {
+        version_manager.check_repo(&key, github.repo())?;
         ui::info!("Version {} is already installed", ui::version(&version));
+        if let Some(channel) = channel {
+            channels::record(&version_manager, &key, channel)?;
+        }
+
+        // Re-run the smoke tests when asked, or when the last run failed so
+        // a broken version isn't activated
+        let version_dir = version_manager.version_dir(&key);
+        let last_failed = VersionManifest::load(&version_dir)?
+            .smoke_test
+            .is_some_and(|report| !report.passed());
+        if smoke_test || last_failed {
+            health::smoke_test(&version_dir, &version, &smoke_tests, &ProcessEnv)?;
+        }
+
+        if !activate {
+            return Ok(());
+        }
 
         // Check if it's the current version
         let current_version = version_manager.get_current()?;
-        if current_version.as_deref() == Some(&version) {
+        if current_version.as_deref() == Some(&key) {
             ui::success!("Already using version {}", ui::version(&version));
             return Ok(());
         }
 
         // Switch to this version
         ui::info!("Switching to version {}", ui::version(&version));
-        crate::commands::use_version::switch_to_version(&version_manager, &version)?;
+        downgrade::confirm(current_version.as_deref(), &key, allow_downgrade)?;
+        use_version::warn_if_prerelease(&settings, &version_manager, &key)?;
+        data_dir::warn_on_switch(
+            &settings,
+            &version_manager,
+            Some(&github),
+            current_version.as_deref(),
+            &key,
+        )
+        .await?;
+        use_version::switch_to_version(&version_manager, &key)?;
         ui::success!("Switched to version {}", ui::version(&version));
         ui::detail!("Run 'ampd --version' and 'ampctl --version' to verify installation");
         return Ok(());
     }
 
-    ui::info!("Installing version {}", ui::version(&version));
-
-    // Detect or override platform and architecture
-    let platform = match platform_override {
-        Some(p) => match p.as_str() {
-            "linux" => Platform::Linux,
-            "darwin" => Platform::Darwin,
-            _ => {
-                return Err(
-                    crate::platform::PlatformError::UnsupportedPlatform { detected: p }.into(),
-                );
+    // Resolved specs already skip yanked releases; an explicit tag must be
+    // forced. Hosts that can't reach the release API skip the check.
+    if !spec.needs_lookup() && github.is_yanked(&version).await.unwrap_or(false) {
+        if !force {
+            return Err(ResolveError::Yanked {
+                version,
+                repo: github.repo().to_string(),
             }
-        },
-        None => Platform::detect()?,
-    };
+            .into());
+        }
+        ui::warn!(
+            "Installing yanked release {} (--force)",
+            ui::version(&version)
+        );
+    }
+
+    // Prereleases are only installed through a channel that tracks them or
+    // with --allow-prerelease
+    let prerelease = VersionResolver::new(&github)
+        .check_prerelease(&spec, &version, allow_prerelease, &policy)
+        .await?;
+    if prerelease && settings.is_production() {
+        use_version::warn_prerelease_in_production(&version);
+    }
+    // A version that isn't activated is only checked once 'ampup use'
+    // switches to it
+    let current_version = version_manager.get_current()?;
+    if activate {
+        downgrade::confirm(current_version.as_deref(), &key, allow_downgrade)?;
+    }
 
-    let arch = match arch_override {
-        Some(a) => match a.as_str() {
-            "x86_64" | "amd64" => Architecture::X86_64,
-            "aarch64" | "arm64" => Architecture::Aarch64,
-            _ => {
-                return Err(crate::platform::PlatformError::UnsupportedArchitecture {
-                    detected: a,
-                }
-                .into());
+    // Hosts with a registered data directory record the release's data
+    // boundary for compatibility warnings; releases without notes have none
+    let data_boundary = match &settings.data_dir {
+        Some(_) => match upgrade::fetch_upgrade_notes(&github, &version).await {
+            Ok(notes) => notes.data_dir,
+            Err(err) => {
+                ui::warn!("Failed to fetch upgrade notes: {:#}", err);
+                None
             }
         },
-        None => Architecture::detect()?,
+        None => None,
     };
 
+    ui::info!("Installing version {}", ui::version(&version));
+
+    // Detect or override platform and architecture
+    let cross = platform_override.is_some() || arch_override.is_some();
+    let platform = Platform::from_override(platform_override)?;
+    let arch = Architecture::from_override(arch_override)?;
+
     ui::detail!("Platform: {}, Architecture: {}", platform, arch);
 
+    // Binaries for another target can't be run here to check them
+    let runs_here =
+        Platform::detect().ok() == Some(platform) && Architecture::detect().ok() == Some(arch);
+    if !runs_here {
+        ui::detail!(
+            "Skipping health checks and smoke tests for a {}-{} target",
+            platform,
+            arch
+        );
+    }
+
+    // Overridden targets are checked up front, so a combination the release
+    // doesn't publish fails with the ones it does
+    if cross {
+        target_matrix::check(&github, &version, &[(platform, arch)]).await?;
+    }
+
+    let repo = github.repo().to_string();
+
+    // Download from the selected source (GitHub by default), falling back
+    // through the configured mirrors
+    let source = release_source::with_mirrors(
+        source.or(settings.source.clone()).as_deref(),
+        &settings.mirrors,
+        &github,
+        options.timeouts,
+    )?;
+    ui::detail!("Source: {}", source.describe());
+
     // Install the binary
-    let download_manager = DownloadManager::new(github, jobs);
-    let installer = Installer::new(version_manager, download_manager);
-    installer
-        .install_from_release(&version, platform, arch)
-        .await?;
+    let mut download_manager = DownloadManager::new(source, jobs)
+        .with_cache(artifact_cache)
+        .with_require_digests(policy.require_digests);
+    // Releases may publish patches against the active version of the same
+    // repository
+    if let Some(current) = version_manager.get_current()?
+        && let (Some(current_namespace), current_version) = split_key(&current)
+        && current_namespace == namespace
+    {
+        let current_dir = version_manager.version_dir(&current);
+        download_manager = download_manager.with_delta_base(current_version, current_dir);
+    }
+    let installer = VersionInstaller::new(version_manager, download_manager, &repo)
+        .with_smoke_tests(smoke_tests.clone())
+        .with_codesign(settings.macos.codesign)
+        .with_prerelease(prerelease)
+        .with_data_boundary(data_boundary)
+        .with_channel(channel)
+        .with_run_checks(runs_here);
+    let result = if activate {
+        installer
+            .install_from_release(&version, platform, arch)
+            .await
+    } else {
+        installer.stage_release(&version, platform, arch).await
+    };
+
+    if let Err(err) = result {
+        if !is_missing_asset(&err)
+            || dest_root.is_some()
+            || !should_build_instead(&version, platform, arch, fallback_build)?
+        {
+            return Err(err);
+        }
+
+        let source = BuildSource::Tag {
+            repo,
+            tag: version.clone(),
+        };
+        let build_options = BuildOptions {
+            name: None,
+            jobs: None,
+            web_url: options.web_url(),
+            reproducible: false,
+            force: false,
+            fresh: false,
+            cargo: CargoOptions::default(),
+        };
+        let builder = Builder::new(VersionManager::new(Config::new(Some(amp_dir.clone()))?))
+            .with_smoke_tests(smoke_tests)
+            .with_activation(activate);
+        builder.build(source, build_options).await?;
+        if activate {
+            data_dir::warn_on_switch(
+                &settings,
+                &VersionManager::new(Config::new(Some(amp_dir))?),
+                Some(&github),
+                current_version.as_deref(),
+                &key,
+            )
+            .await?;
+        }
+        return Ok(());
+    }
 
     ui::success!("Installed ampd and ampctl {}", ui::version(&version));
+    if let Some(root) = &dest_root {
+        ui::detail!(
+            "Copy {} to the target machine's ~/.amp, e.g. rsync -a {}/ <host>:.amp/",
+            ui::path(root.display()),
+            root.display()
+        );
+        return Ok(());
+    }
+    if !activate {
+        ui::detail!("Activate it with: ampup use {}", version);
+        return Ok(());
+    }
+    data_dir::warn_on_switch(
+        &settings,
+        &VersionManager::new(Config::new(Some(amp_dir))?),
+        Some(&github),
+        current_version.as_deref(),
+        &key,
+    )
+    .await?;
     ui::detail!("Run 'ampd --version' and 'ampctl --version' to verify installation");
 
     Ok(())
 }
+
+/// Repository `update` installs from: `repo` when given, else `repo` from
+/// `config.toml`, else the one the active version was installed from, so a
+/// fork's install keeps updating from the fork. `None` leaves the default.
+pub fn update_repo(
+    install_dir: Option<std::path::PathBuf>,
+    repo: Option<String>,
+) -> Result<Option<String>> {
+    if repo.is_some() {
+        return Ok(repo);
+    }
+    let config = Config::new(install_dir)?;
+    if Settings::load(&config)?.repo.is_some() {
+        return Ok(None);
+    }
+    VersionManager::new(config).current_repo()
+}
+
+/// Whether an install failure means the release has no usable binaries for
+/// this platform, as opposed to a network or filesystem error.
+fn is_missing_asset(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<GitHubError>(),
+        Some(GitHubError::AssetNotFound { .. })
+    ) || matches!(
+        err.downcast_ref::<DownloadError>(),
+        Some(DownloadError::AssetsUnavailable { .. })
+    )
+}
+
+/// Decide whether to build `version` from source after a missing-asset failure.
+///
+/// `--fallback-build` opts in without asking; otherwise the user is prompted
+/// when attached to a terminal. Never builds when the target platform was
+/// overridden, since a local build produces binaries for this host only.
+fn should_build_instead(
+    version: &str,
+    platform: Platform,
+    arch: Architecture,
+    fallback_build: bool,
+) -> Result<bool> {
+    if platform != Platform::detect()? || arch != Architecture::detect()? {
+        return Ok(false);
+    }
+
+    ui::warn!(
+        "No {}-{} binaries available for {}",
+        platform,
+        arch,
+        ui::version(version)
+    );
+
+    if fallback_build {
+        ui::info!(
+            "Building {} from source (--fallback-build)",
+            ui::version(version)
+        );
+        ui::detail!("Compiling ampd and ampctl with cargo, this can take 10+ minutes");
+        return Ok(true);
+    }
+
+    if !std::io::stdin().is_terminal() {
+        ui::detail!("Pass --fallback-build to build from source automatically");
+        return Ok(false);
+    }
+
+    Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "Build {} from source instead? Compiling with cargo can take 10+ minutes",
+            version
+        ))
+        .default(false)
+        .interact()
+        .map_err(Into::into)
+}
@@ -0,0 +1,108 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use console::style;
+use fs_err as fs;
+
+use crate::{
+    config::Config,
+    instances::{self, Instance, Instances},
+    ui,
+    version_manager::VersionManager,
+};
+
+/// Pin instance `name` to an installed `version` and (re)generate its
+/// wrapper script.
+pub fn pin(
+    install_dir: Option<PathBuf>,
+    name: &str,
+    version: &str,
+    args: Vec<String>,
+) -> Result<()> {
+    let version_manager = VersionManager::new(Config::new(install_dir)?);
+    let mut instances = Instances::load(version_manager.config())?;
+
+    let version = &version_manager.resolve(version, None)?;
+    version_manager.store().prepare(version)?;
+
+    let previous = instances.get(name).map(|instance| instance.version.clone());
+    instances.insert(
+        name,
+        Instance {
+            version: version.to_string(),
+            args: args.clone(),
+        },
+    )?;
+
+    let config = version_manager.config();
+    let binary = version_manager.version_dir(version).join("ampd");
+    instances::write_wrapper(config, name, &binary, &args)?;
+    instances.save(config)?;
+
+    match previous {
+        Some(previous) if previous != *version => ui::success!(
+            "Repinned instance {} from {} to {}",
+            name,
+            ui::version(previous),
+            ui::version(version)
+        ),
+        _ => ui::success!("Pinned instance {} to {}", name, ui::version(version)),
+    }
+    ui::detail!(
+        "Run it with {}",
+        ui::path(config.instance_wrapper_path(name).display())
+    );
+    Ok(())
+}
+
+/// Print every instance with its pinned version and wrapper script.
+pub fn list(install_dir: Option<PathBuf>) -> Result<()> {
+    let version_manager = VersionManager::new(Config::new(install_dir)?);
+    let instances = Instances::load(version_manager.config())?;
+    let installed = version_manager.list_installed()?;
+
+    if instances.iter().next().is_none() {
+        ui::info!("No instances configured");
+        ui::detail!("Run 'ampup instances pin <name> <version>' to add one");
+        return Ok(());
+    }
+
+    for (name, instance) in instances.iter() {
+        let missing = if installed.contains(&instance.version) {
+            String::new()
+        } else {
+            format!(" {}", style("(not installed)").red())
+        };
+        println!(
+            "{:<20} {}{}  {}",
+            name,
+            ui::version(&instance.version),
+            missing,
+            version_manager
+                .config()
+                .instance_wrapper_path(name)
+                .display()
+        );
+    }
+    Ok(())
+}
+
+/// Forget instance `name` and delete its wrapper script.
+pub fn remove(install_dir: Option<PathBuf>, name: &str) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let mut instances = Instances::load(&config)?;
+    let instance = instances.remove(name)?;
+
+    let wrapper = config.instance_wrapper_path(name);
+    if wrapper.exists() {
+        fs::remove_file(&wrapper)?;
+    }
+    instances.save(&config)?;
+
+    ui::success!(
+        "Removed instance {} (was pinned to {})",
+        name,
+        ui::version(instance.version)
+    );
+    Ok(())
+}
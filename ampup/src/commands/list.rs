@@ -1,16 +1,96 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use console::style;
+use serde::Serialize;
 
-use crate::{config::Config, ui, version_manager::VersionManager};
+use crate::{
+    config::Config, dispatch, manifest::VersionManifest, resolver::Channel, ui,
+    version_manager::VersionManager, version_store::split_key,
+};
 
-pub fn run(install_dir: Option<std::path::PathBuf>) -> Result<()> {
+/// An installed version as `ampup list --json` prints it, with raw sizes
+/// and timestamps.
+#[derive(Debug, Serialize)]
+struct ListedVersion<'a> {
+    key: &'a str,
+    namespace: Option<&'a str>,
+    version: &'a str,
+    current: bool,
+    /// Whether the version's directory holds `ampd`
+    complete: bool,
+    prerelease: bool,
+    pinned: bool,
+    /// `owner/repo` the release was installed from, when recorded
+    repo: Option<String>,
+    channels: Vec<Channel>,
+    /// Bytes taken by the version's directory
+    size_bytes: u64,
+    /// Unix timestamp of the installation, where the filesystem records it
+    installed_at: Option<u64>,
+    /// Unix timestamp of the latest activation
+    last_activated: Option<u64>,
+    /// Unix timestamp of the latest run through the shims
+    last_used: Option<u64>,
+}
+
+/// Print the installed versions, or with `by_size`, each version's disk
+/// usage, largest first, and their total. With `long`, also print when each
+/// was last activated and run.
+pub fn run(
+    install_dir: Option<std::path::PathBuf>,
+    json: bool,
+    by_size: bool,
+    long: bool,
+) -> Result<()> {
     let config = Config::new(install_dir)?;
     let version_manager = VersionManager::new(config);
 
-    let versions = version_manager.list_installed()?;
+    let mut versions = version_manager.list_installed()?;
+    if by_size {
+        let mut sized = versions
+            .into_iter()
+            .map(|key| Ok((version_manager.disk_usage(&key)?, key)))
+            .collect::<Result<Vec<_>>>()?;
+        sized.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        if !json {
+            return print_sizes(&version_manager, &sized);
+        }
+        versions = sized.into_iter().map(|(_, key)| key).collect();
+    }
+
+    if json {
+        let current = version_manager.get_current()?;
+        let listed = versions
+            .iter()
+            .map(|key| {
+                let (namespace, version) = split_key(key);
+                let manifest = VersionManifest::load(&version_manager.version_dir(key))?;
+                Ok(ListedVersion {
+                    key,
+                    namespace,
+                    version,
+                    current: current.as_ref() == Some(key),
+                    complete: version_manager.is_installed(key),
+                    prerelease: manifest.prerelease,
+                    pinned: manifest.pinned,
+                    repo: manifest.repo,
+                    channels: manifest.channels,
+                    size_bytes: version_manager.disk_usage(key)?,
+                    installed_at: version_manager.installed_at(key),
+                    last_activated: manifest.last_activated,
+                    last_used: manifest.last_used,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let rendered =
+            serde_json::to_string_pretty(&listed).context("Failed to serialize versions")?;
+        println!("{}", rendered);
+        return Ok(());
+    }
 
+    let shim_problems = dispatch::check_shims(&version_manager)?;
     if versions.is_empty() {
         ui::info!("No versions installed");
+        dispatch::warn_shim_problems(&shim_problems, &versions);
         return Ok(());
     }
 
@@ -18,18 +98,110 @@ pub fn run(install_dir: Option<std::path::PathBuf>) -> Result<()> {
 
     ui::info!("Installed versions:");
 
-    for version in versions {
-        if Some(&version) == current_version.as_ref() {
+    let mut incomplete = 0;
+
+    // Keys sort by namespace, so each repository's versions are adjacent
+    let mut namespace = None;
+    for key in &versions {
+        let (key_namespace, version) = split_key(key);
+        if key_namespace != namespace {
+            namespace = key_namespace;
+            println!("  {}", style(key_namespace.unwrap_or_default()).dim());
+        }
+
+        let mut notes = Vec::new();
+        if Some(key) == current_version.as_ref() {
+            notes.push("current");
+            if !shim_problems.is_empty() {
+                notes.push("broken");
+            }
+        }
+        if !version_manager.is_installed(key) {
+            notes.push("incomplete");
+            incomplete += 1;
+        }
+        let manifest = VersionManifest::load(&version_manager.version_dir(key))?;
+        if manifest.adopted_from.is_some() {
+            notes.push("adopted");
+        }
+        if manifest.pinned {
+            notes.push("pinned");
+        }
+        if manifest.prerelease {
+            notes.push("prerelease");
+        }
+        notes.extend(manifest.channels.iter().map(|channel| channel.as_str()));
+        let notes = match notes.is_empty() {
+            true => String::new(),
+            false => format!(" {}", style(format!("({})", notes.join(", "))).dim()),
+        };
+
+        let mut usage = ui::size(version_manager.disk_usage(key)?);
+        if let Some(installed_at) = version_manager.installed_at(key) {
+            usage = format!("{}, installed {}", usage, ui::ago(installed_at));
+        }
+        if long {
+            let last = |at: Option<u64>| at.map_or_else(|| "never".to_string(), ui::ago);
+            usage = format!(
+                "{}, activated {}, last run {}",
+                usage,
+                last(manifest.last_activated),
+                last(manifest.last_used)
+            );
+        }
+        let usage = style(usage).dim();
+
+        if Some(key) == current_version.as_ref() {
             println!(
-                "  {} {} {}",
+                "  {} {}{}  {}",
                 style("*").green().bold(),
-                style(&version).bold(),
-                style("(current)").dim()
+                style(version).bold(),
+                notes,
+                usage
             );
         } else {
-            println!("    {}", version);
+            println!("    {}{}  {}", version, notes, usage);
         }
     }
+    dispatch::warn_shim_problems(&shim_problems, &versions);
+    if incomplete > 0 {
+        ui::warn!(
+            "{} version(s) missing ampd, likely left by an interrupted install",
+            incomplete
+        );
+        ui::detail!("Try: ampup clean --incomplete");
+    }
 
     Ok(())
 }
+
+/// Print `sized` versions with their disk usage and the total.
+fn print_sizes(version_manager: &VersionManager, sized: &[(u64, String)]) -> Result<()> {
+    if sized.is_empty() {
+        ui::info!("No versions installed");
+        return Ok(());
+    }
+    let current_version = version_manager.get_current()?;
+
+    ui::info!("Disk usage by version:");
+    let sizes: Vec<String> = sized.iter().map(|(bytes, _)| ui::size(*bytes)).collect();
+    let width = sizes.iter().map(String::len).max().unwrap_or_default();
+    for ((_, key), size) in sized.iter().zip(&sizes) {
+        let installed = version_manager
+            .installed_at(key)
+            .map(|at| format!("  {}", style(format!("installed {}", ui::ago(at))).dim()))
+            .unwrap_or_default();
+        let marker = match Some(key) == current_version.as_ref() {
+            true => format!(" {}", style("(current)").dim()),
+            false => String::new(),
+        };
+        println!("  {:>width$}  {}{}{}", size, key, marker, installed);
+    }
+    let total: u64 = sized.iter().map(|(bytes, _)| bytes).sum();
+    println!(
+        "  {:>width$}  {}",
+        ui::size(total),
+        style(format!("total, {} version(s)", sized.len())).dim()
+    );
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::{
+    config::Config,
+    github::ClientOptions,
+    news::{CACHE_TTL, Digest},
+    resolver::tag_version,
+    settings::Settings,
+    ui,
+    version_manager::VersionManager,
+    version_store::{repo_namespace, split_key},
+};
+
+#[derive(Debug)]
+pub enum NewsError {
+    /// No release from the repository is active to report news since.
+    NoInstalledRelease { repo: String },
+}
+
+impl std::fmt::Display for NewsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoInstalledRelease { repo } => {
+                writeln!(f, "No release of {} is active", repo)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Pick a starting point with 'ampup news --since <version>'."
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for NewsError {}
+
+/// Print a digest of releases, notable merged pull requests and
+/// announcements since the active version (or `since`).
+///
+/// A digest fetched within the last hour is reused unless `refresh` is set.
+pub async fn run(
+    install_dir: Option<PathBuf>,
+    repo: Option<String>,
+    github_token: Option<String>,
+    since: Option<String>,
+    refresh: bool,
+    options: ClientOptions,
+) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let settings = Settings::load(&config)?;
+    let repo = settings.repo(repo);
+    let cache_path = Digest::cache_path(&config.cache_dir(), &repo);
+
+    let since = match since {
+        Some(since) => since,
+        None => active_release(VersionManager::new(config).get_current()?, &repo)
+            .ok_or_else(|| NewsError::NoInstalledRelease { repo: repo.clone() })?,
+    };
+
+    let cached = if refresh {
+        None
+    } else {
+        Digest::load_cached(&cache_path, &repo, &since, CACHE_TTL)
+    };
+    let digest = match cached {
+        Some(digest) => digest,
+        None => {
+            let github = settings.github_client(repo, github_token, options)?;
+            let digest = Digest::fetch(&github, &since).await?;
+            if let Err(err) = digest.save(&cache_path) {
+                ui::warn!("Failed to cache news: {}", err);
+            }
+            digest
+        }
+    };
+
+    print!("{}", digest.render());
+    if digest.announcements.is_none() {
+        ui::detail!("Set GITHUB_TOKEN to include announcements");
+    }
+    if let Some(latest) = digest.releases.first() {
+        ui::detail!("Upgrade with 'ampup upgrade {}'", latest.tag);
+    }
+    Ok(())
+}
+
+/// The bare release tag of the active version, when it was installed from
+/// `repo` (source builds and other repositories have no place in its
+/// release history).
+fn active_release(current: Option<String>, repo: &str) -> Option<String> {
+    let current = current?;
+    let (namespace, version) = split_key(&current);
+    (namespace == Some(repo_namespace(repo).as_str()) && tag_version(version).is_some())
+        .then(|| version.to_string())
+}
@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use console::style;
+
+use crate::{config::Config, overrides::Overrides, ui, version_manager::VersionManager};
+
+/// Override the version run in `path` (the current directory by default)
+/// and below it with an installed `version`.
+pub fn set(install_dir: Option<PathBuf>, version: &str, path: Option<PathBuf>) -> Result<()> {
+    let version_manager = VersionManager::new(Config::new(install_dir)?);
+    let dir = target_dir(path)?;
+    if !dir.is_dir() {
+        anyhow::bail!("Directory does not exist: {}", dir.display());
+    }
+    let version = version_manager.resolve(version, None)?;
+
+    let config = version_manager.config();
+    let mut overrides = Overrides::load(config)?;
+    let previous = overrides.set(&dir, &version);
+    overrides.save(config)?;
+
+    match previous {
+        Some(previous) if previous != version => ui::success!(
+            "Changed override of {} from {} to {}",
+            ui::path(dir.display()),
+            ui::version(previous),
+            ui::version(&version)
+        ),
+        _ => ui::success!(
+            "Set override of {} to {}",
+            ui::path(dir.display()),
+            ui::version(&version)
+        ),
+    }
+    Ok(())
+}
+
+/// Remove the override of `path` (the current directory by default), or
+/// with `nonexistent`, those of every directory that no longer exists.
+pub fn unset(install_dir: Option<PathBuf>, path: Option<PathBuf>, nonexistent: bool) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let mut overrides = Overrides::load(&config)?;
+
+    if nonexistent {
+        let removed = overrides.remove_nonexistent();
+        if removed.is_empty() {
+            ui::info!("No overrides of missing directories");
+            return Ok(());
+        }
+        overrides.save(&config)?;
+        ui::success!(
+            "Removed {} override(s) of missing directories",
+            removed.len()
+        );
+        for dir in &removed {
+            ui::detail!("{}", dir.display());
+        }
+        return Ok(());
+    }
+
+    let dir = target_dir(path)?;
+    let version = overrides.unset(&dir)?;
+    overrides.save(&config)?;
+
+    ui::success!(
+        "Removed override of {} (was {})",
+        ui::path(dir.display()),
+        ui::version(version)
+    );
+    Ok(())
+}
+
+/// Print every directory override with its version.
+pub fn list(install_dir: Option<PathBuf>) -> Result<()> {
+    let version_manager = VersionManager::new(Config::new(install_dir)?);
+    let overrides = Overrides::load(version_manager.config())?;
+    let installed = version_manager.list_installed()?;
+
+    if overrides.iter().next().is_none() {
+        ui::info!("No directory overrides set");
+        ui::detail!("Run 'ampup override set <version>' in a directory to add one");
+        return Ok(());
+    }
+
+    for (dir, version) in overrides.iter() {
+        println!(
+            "{}  {}{}",
+            dir.display(),
+            ui::version(version),
+            status(dir, installed.contains(version))
+        );
+    }
+    Ok(())
+}
+
+/// Markers for an override whose directory or version is gone.
+fn status(dir: &Path, installed: bool) -> String {
+    let mut status = String::new();
+    if !installed {
+        status.push_str(&format!(" {}", style("(not installed)").red()));
+    }
+    if !dir.is_dir() {
+        status.push_str(&format!(" {}", style("(directory missing)").yellow()));
+    }
+    status
+}
+
+fn target_dir(path: Option<PathBuf>) -> Result<PathBuf> {
+    match path {
+        Some(path) => Ok(path),
+        None => std::env::current_dir().context("Failed to get current directory"),
+    }
+}
@@ -0,0 +1,63 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::{config::Config, manifest::VersionManifest, ui, version_manager::VersionManager};
+
+/// Hold an installed `version` so prune skips it and uninstall needs
+/// `--force`, or without one, list the held versions.
+pub fn pin(
+    install_dir: Option<PathBuf>,
+    version: Option<String>,
+    repo: Option<String>,
+) -> Result<()> {
+    let version_manager = VersionManager::new(Config::new(install_dir)?);
+    let Some(version) = version else {
+        return list(&version_manager);
+    };
+    let key = version_manager.resolve(&version, repo.as_deref())?;
+    set_pinned(&version_manager, &key, true)?;
+    ui::success!("Pinned {}", ui::version(&key));
+    ui::detail!("prune skips it, and uninstalling it needs --force");
+    Ok(())
+}
+
+/// Release the hold `ampup pin` put on `version`.
+pub fn unpin(install_dir: Option<PathBuf>, version: &str, repo: Option<String>) -> Result<()> {
+    let version_manager = VersionManager::new(Config::new(install_dir)?);
+    let key = version_manager.resolve(version, repo.as_deref())?;
+    if set_pinned(&version_manager, &key, false)? {
+        ui::success!("Unpinned {}", ui::version(&key));
+    } else {
+        ui::info!("{} is not pinned", ui::version(&key));
+    }
+    Ok(())
+}
+
+/// Set the hold on `key`, returning whether it was held before.
+fn set_pinned(version_manager: &VersionManager, key: &str, pinned: bool) -> Result<bool> {
+    let mut was_pinned = false;
+    VersionManifest::update(&version_manager.version_dir(key), |manifest| {
+        was_pinned = manifest.pinned;
+        manifest.pinned = pinned;
+    })?;
+    Ok(was_pinned)
+}
+
+fn list(version_manager: &VersionManager) -> Result<()> {
+    let mut pinned = Vec::new();
+    for key in version_manager.list_installed()? {
+        if VersionManifest::load(&version_manager.version_dir(&key))?.pinned {
+            pinned.push(key);
+        }
+    }
+    if pinned.is_empty() {
+        ui::info!("No versions pinned");
+        ui::detail!("Run 'ampup pin <version>' to keep a version around");
+        return Ok(());
+    }
+    for key in &pinned {
+        println!("{}", ui::version(key));
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    config::Config,
+    instances::Instances,
+    manifest::VersionManifest,
+    overrides::Overrides,
+    project,
+    prune::{Installed, Retention},
+    ui,
+    version_manager::{VersionManager, unix_now},
+};
+
+/// Remove the installed versions `retention` matches, never the active
+/// version, ones held with `ampup pin` or ones pinned by instances,
+/// directory overrides or the current directory's project file. With `dry_run`, only list them.
+pub fn run(install_dir: Option<PathBuf>, retention: Retention, dry_run: bool) -> Result<()> {
+    if retention.is_empty() {
+        anyhow::bail!(
+            "No retention policy given; pass --keep-latest, --older-than, --unused-for or --builds-only"
+        );
+    }
+    let version_manager = VersionManager::new(Config::new(install_dir)?);
+
+    let installed: Vec<Installed> = version_manager
+        .list_installed()?
+        .into_iter()
+        .map(|key| {
+            let manifest = VersionManifest::load(&version_manager.version_dir(&key))?;
+            Ok(Installed {
+                installed_at: version_manager.installed_at(&key),
+                last_used: manifest.last_used_at(),
+                key,
+            })
+        })
+        .collect::<Result<_>>()?;
+    let now = unix_now();
+    let protected = protected_versions(&version_manager)?;
+
+    let (kept, removed): (Vec<String>, Vec<String>) = retention
+        .select(&installed, now)
+        .into_iter()
+        .partition(|key| protected.contains_key(key));
+
+    for key in &kept {
+        ui::detail!("Keeping {} ({})", ui::version(key), protected[key]);
+    }
+    if removed.is_empty() {
+        ui::info!("Nothing to prune");
+        return Ok(());
+    }
+
+    let mut freed = 0;
+    for key in &removed {
+        let size = version_manager.disk_usage(key)?;
+        let installed = version_manager
+            .installed_at(key)
+            .map(|at| format!(", installed {}", ui::ago(at)))
+            .unwrap_or_default();
+        if dry_run {
+            ui::info!(
+                "Would remove {} ({}{})",
+                ui::version(key),
+                ui::size(size),
+                installed
+            );
+        } else {
+            version_manager.uninstall(key)?;
+            ui::detail!("Removed {} ({})", ui::version(key), ui::size(size));
+        }
+        freed += size;
+    }
+
+    if dry_run {
+        ui::info!(
+            "Would remove {} version(s), freeing {}",
+            removed.len(),
+            ui::size(freed)
+        );
+    } else {
+        ui::success!(
+            "Removed {} version(s), freeing {}",
+            removed.len(),
+            ui::size(freed)
+        );
+    }
+    Ok(())
+}
+
+/// Versions prune never removes, with why.
+fn protected_versions(version_manager: &VersionManager) -> Result<BTreeMap<String, String>> {
+    let config = version_manager.config();
+    let mut protected = BTreeMap::new();
+
+    for (dir, version) in Overrides::load(config)?.iter() {
+        protected.insert(
+            version.clone(),
+            format!("directory override of {}", dir.display()),
+        );
+    }
+    for (name, instance) in Instances::load(config)?.iter() {
+        protected.insert(
+            instance.version.clone(),
+            format!("pinned by instance {}", name),
+        );
+    }
+    for key in version_manager.list_installed()? {
+        if VersionManifest::load(&version_manager.version_dir(&key))?.pinned {
+            protected.insert(key, "pinned with ampup pin".to_string());
+        }
+    }
+    let cwd = std::env::current_dir().context("Failed to get current directory")?;
+    if let Some(pinned) = project::find(&cwd)?
+        && let Ok(key) = version_manager.resolve(&pinned.version, None)
+    {
+        protected.insert(key, format!("pinned by {}", pinned.file.display()));
+    }
+    if let Some(current) = version_manager.get_current()? {
+        protected.insert(current, "active version".to_string());
+    }
+    Ok(protected)
+}
@@ -0,0 +1,122 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{
+    config::Config,
+    github::ClientOptions,
+    platform::{Architecture, Platform},
+    resolver::{Resolution, VersionResolver, VersionSpec},
+    settings::Settings,
+};
+
+/// Machine-readable output of `ampup resolve --json`
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Output {
+    Release {
+        spec: String,
+        version: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        assets: Vec<AssetOutput>,
+    },
+    Source {
+        spec: String,
+        version: String,
+        repo: String,
+        sha: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct AssetOutput {
+    name: String,
+    url: String,
+}
+
+/// Resolve a version spec and print the result to stdout.
+///
+/// Status messages are deliberately omitted so the output can be captured
+/// directly, e.g. `VERSION=$(ampup resolve latest)`.
+#[expect(clippy::too_many_arguments)]
+pub async fn run(
+    install_dir: Option<std::path::PathBuf>,
+    repo: Option<String>,
+    github_token: Option<String>,
+    spec: String,
+    arch_override: Option<String>,
+    platform_override: Option<String>,
+    assets: bool,
+    json: bool,
+    options: ClientOptions,
+) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let settings = Settings::load(&config)?;
+
+    let github = settings.github_client(settings.repo(repo), github_token, options)?;
+
+    let spec: VersionSpec = spec.parse()?;
+    let resolution = VersionResolver::new(&github)
+        .with_label_scheme(settings.label_scheme()?)
+        .resolve(&spec)
+        .await?;
+
+    let output = match resolution {
+        Resolution::Release { tag } => {
+            let assets = if assets {
+                let platform = Platform::from_override(platform_override)?;
+                let arch = Architecture::from_override(arch_override)?;
+                let names = [
+                    format!("ampd-{}-{}", platform.as_str(), arch.as_str()),
+                    format!("ampctl-{}-{}", platform.as_str(), arch.as_str()),
+                ];
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                github
+                    .resolve_release_assets(&tag, &names)
+                    .await?
+                    .into_iter()
+                    .map(|asset| AssetOutput {
+                        name: asset.name,
+                        url: asset.url,
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            Output::Release {
+                spec: spec.to_string(),
+                version: tag,
+                assets,
+            }
+        }
+        Resolution::Source { label, repo, sha } => Output::Source {
+            spec: spec.to_string(),
+            version: label,
+            repo,
+            sha,
+        },
+    };
+
+    if json {
+        let rendered =
+            serde_json::to_string_pretty(&output).context("Failed to serialize resolution")?;
+        println!("{}", rendered);
+        return Ok(());
+    }
+
+    match output {
+        Output::Release {
+            version, assets, ..
+        } => {
+            println!("{}", version);
+            for asset in assets {
+                println!("{}", asset.url);
+            }
+        }
+        Output::Source { version, sha, .. } => {
+            println!("{}", version);
+            println!("{}", sha);
+        }
+    }
+
+    Ok(())
+}
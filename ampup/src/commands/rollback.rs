@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::{
+    config::Config,
+    data_dir,
+    github::ClientOptions,
+    settings::Settings,
+    ui,
+    version_manager::{VersionError, VersionManager},
+};
+
+/// Switch back to the version that was active before the current one.
+/// Rolling back twice returns to where it started. With `force`, skip
+/// checking its binaries against their recorded checksums.
+pub async fn run(install_dir: Option<PathBuf>, force: bool, options: ClientOptions) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let settings = Settings::load(&config)?;
+    let version_manager = VersionManager::new(config).with_checksum_verification(!force);
+
+    let previous = version_manager
+        .get_previous()?
+        .ok_or(VersionError::NoPreviousVersion)?;
+    let current = version_manager.get_current()?;
+    let github = data_dir::release_client(&settings, &version_manager, &previous, options);
+    data_dir::warn_on_switch(
+        &settings,
+        &version_manager,
+        github.as_ref(),
+        current.as_deref(),
+        &previous,
+    )
+    .await?;
+    version_manager.activate(&previous)?;
+
+    match current {
+        Some(current) => ui::success!(
+            "Rolled back to ampd {} (from {})",
+            ui::version(&previous),
+            ui::version(current)
+        ),
+        None => ui::success!("Rolled back to ampd {}", ui::version(&previous)),
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::{ffi::OsString, path::PathBuf, process::Command};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    config::Config,
+    dispatch,
+    environment::ProcessEnv,
+    shell::{self, Shell},
+    ui,
+    version_manager::VersionManager,
+};
+
+/// Environment variable set inside an `ampup shell` session to the pinned version.
+pub const SHELL_VERSION_ENV: &str = "AMPUP_SHELL_VERSION";
+
+/// Start a subshell where ampd and ampctl run `version`, or with
+/// `print_env`, print the variables to `eval` for the same effect in the
+/// current shell.
+///
+/// ampup exits with the subshell's exit code once it returns, so scripts
+/// running `ampup shell` see the session's outcome.
+pub fn run(install_dir: Option<PathBuf>, version: &str, print_env: bool) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let version_manager = VersionManager::new(config);
+    let version = &version_manager.resolve(version, None)?;
+
+    let version_dir = version_manager.version_dir(version);
+    let path = shell::prepend_to_path(&version_dir, std::env::var_os("PATH"))?;
+    // The version directory leads PATH, and the `bin/` shims (run by
+    // absolute path, e.g. from scripts) follow AMP_VERSION
+    let vars = [
+        ("PATH", path),
+        (SHELL_VERSION_ENV, OsString::from(version)),
+        (dispatch::VERSION_ENV, OsString::from(version)),
+    ];
+
+    if print_env {
+        let shell = Shell::detect(&ProcessEnv).unwrap_or(Shell::Ash);
+        for (name, value) in &vars {
+            println!("{}", shell.export_line(name, &value.to_string_lossy()));
+        }
+        return Ok(());
+    }
+
+    if let Ok(active) = std::env::var(SHELL_VERSION_ENV) {
+        ui::warn!(
+            "Already inside an ampup shell for {}, nesting a new one",
+            ui::version(&active)
+        );
+    }
+
+    let program = std::env::var_os("SHELL")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/bin/sh"));
+
+    let mut command = Command::new(&program);
+    command.envs(vars);
+
+    // Kept alive until the subshell exits; holds generated startup files.
+    let scratch_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let label = format!("amp {}", version);
+    match Shell::detect(&ProcessEnv) {
+        Some(shell) => {
+            shell.annotate_prompt(&mut command, &label, scratch_dir.path(), &ProcessEnv)?
+        }
+        None => ui::detail!("Unrecognized shell, prompt will not be annotated"),
+    }
+
+    ui::info!(
+        "Entering subshell with ampd and ampctl {}",
+        ui::version(version)
+    );
+    ui::detail!("Type 'exit' to return to your previous environment");
+
+    let status = command
+        .status()
+        .with_context(|| format!("Failed to start shell {}", program.display()))?;
+
+    ui::info!("Left ampup shell for {}", ui::version(version));
+    if !status.success() {
+        ui::detail!("Subshell exited with {}", status);
+        drop(scratch_dir);
+        std::process::exit(status.code().unwrap_or(1));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use console::style;
+
+use crate::{
+    channels,
+    config::Config,
+    dispatch,
+    overrides::Overrides,
+    project,
+    settings::Settings,
+    ui,
+    version_manager::VersionManager,
+    version_store::{LOCAL_NAMESPACE, split_key},
+};
+
+/// Print the installation location, active version and effective settings.
+pub fn run(install_dir: Option<PathBuf>) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let settings = Settings::load(&config)?;
+
+    let repo = settings.repo(None);
+    let repo_source = source_label(settings.repo.is_some());
+    let self_repo = settings.self_repo(None);
+    let self_repo_source = source_label(settings.self_repo.is_some());
+    let client_options = settings.client_options();
+    let api_url = client_options.github_api().to_string();
+    let api_url_source = source_label(settings.api_url.is_some());
+    let amp_dir = config.amp_dir.display().to_string();
+    let settings_file = config.settings_file();
+    let settings_file_label = if settings_file.exists() {
+        settings_file.display().to_string()
+    } else {
+        format!(
+            "{} {}",
+            settings_file.display(),
+            style("(not created)").dim()
+        )
+    };
+
+    let overrides = Overrides::load(&config)?;
+
+    let version_manager = VersionManager::new(config);
+    let installed = version_manager.list_installed()?;
+    let current = version_manager.get_current()?;
+    let shim_problems = dispatch::check_shims(&version_manager)?;
+
+    println!(
+        "  {:<14} {}",
+        "ampup",
+        ui::version(env!("VERGEN_GIT_DESCRIBE"))
+    );
+    println!("  {:<14} {}", "Install dir", ui::path(amp_dir));
+    println!("  {:<14} {}", "Settings", settings_file_label);
+    match current {
+        Some(version) => {
+            let installed_at = version_manager
+                .installed_at(&version)
+                .map(|at| {
+                    format!(
+                        " {}",
+                        style(format!("(installed {}, {})", ui::date(at), ui::ago(at))).dim()
+                    )
+                })
+                .unwrap_or_default();
+            let broken = match shim_problems.is_empty() {
+                true => String::new(),
+                false => format!(" {}", style("(broken)").red()),
+            };
+            println!(
+                "  {:<14} {}{}{}",
+                "Active",
+                ui::version(version),
+                broken,
+                installed_at
+            );
+        }
+        None => println!("  {:<14} {}", "Active", style("none").dim()),
+    }
+    let mut disk_usage = 0;
+    let mut build_usage = 0;
+    for key in &installed {
+        let usage = version_manager.disk_usage(key)?;
+        disk_usage += usage;
+        if split_key(key).0 == Some(LOCAL_NAMESPACE) {
+            build_usage += usage;
+        }
+    }
+    let usage = match build_usage {
+        0 => ui::size(disk_usage),
+        _ => format!(
+            "{}, {} in source builds",
+            ui::size(disk_usage),
+            ui::size(build_usage)
+        ),
+    };
+    println!(
+        "  {:<14} {} {}",
+        "Installed",
+        installed.len(),
+        style(format!("({})", usage)).dim()
+    );
+    if let Some(version) = std::env::var(dispatch::VERSION_ENV)
+        .ok()
+        .filter(|version| !version.is_empty())
+    {
+        println!(
+            "  {:<14} {} {}",
+            "Override",
+            ui::version(version),
+            style(format!("({})", dispatch::VERSION_ENV)).dim()
+        );
+    }
+    let cwd = std::env::current_dir().ok();
+    if let Some(cwd) = &cwd
+        && let Some((dir, version)) = overrides.find(cwd)
+    {
+        println!(
+            "  {:<14} {} {}",
+            "Directory",
+            ui::version(version),
+            style(format!("({})", dir.display())).dim()
+        );
+    }
+    if let Some(pinned) = cwd
+        .as_deref()
+        .and_then(|cwd| project::find(cwd).ok().flatten())
+    {
+        println!(
+            "  {:<14} {} {}",
+            "Project",
+            ui::version(&pinned.version),
+            style(format!("({})", pinned.file.display())).dim()
+        );
+    }
+    match settings.update.channel {
+        Some(channel) => println!("  {:<14} {}", "Channel", channel.as_str()),
+        None => println!("  {:<14} {}", "Channel", style("none (latest)").dim()),
+    }
+    for (channel, key) in channels::installed(&version_manager)? {
+        println!("  {:<14} {}", channel.as_str(), ui::version(key));
+    }
+    println!("  {:<14} {} {}", "Repo", repo, style(repo_source).dim());
+    println!(
+        "  {:<14} {} {}",
+        "Self repo",
+        self_repo,
+        style(self_repo_source).dim()
+    );
+    println!(
+        "  {:<14} {} {}",
+        "GitHub API",
+        api_url,
+        style(api_url_source).dim()
+    );
+    dispatch::warn_shim_problems(&shim_problems, &installed);
+
+    Ok(())
+}
+
+fn source_label(configured: bool) -> &'static str {
+    if configured { "(config)" } else { "(default)" }
+}
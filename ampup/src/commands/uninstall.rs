@@ -1,10 +1,42 @@
 use anyhow::Result;
 
-use crate::{config::Config, ui, version_manager::VersionManager};
+use crate::{
+    config::Config,
+    instances::{InstanceError, Instances},
+    manifest::VersionManifest,
+    ui,
+    version_manager::{VersionError, VersionManager},
+};
 
-pub fn run(install_dir: Option<std::path::PathBuf>, version: &str) -> Result<()> {
+pub fn run(
+    install_dir: Option<std::path::PathBuf>,
+    version: &str,
+    repo: Option<String>,
+    force: bool,
+) -> Result<()> {
     let config = Config::new(install_dir)?;
     let version_manager = VersionManager::new(config);
+    let version = &version_manager.resolve(version, repo.as_deref())?;
+    let config = version_manager.config();
+
+    // Instances run their pinned version directly, so removing it would
+    // break their wrapper scripts
+    let pinned_by = Instances::load(config)?.pinning(version);
+    if !pinned_by.is_empty() {
+        return Err(InstanceError::VersionPinned {
+            version: version.to_string(),
+            instances: pinned_by,
+        }
+        .into());
+    }
+
+    // Versions held with 'ampup pin' are kept as fallbacks
+    if !force && VersionManifest::load(&version_manager.version_dir(version))?.pinned {
+        return Err(VersionError::Pinned {
+            version: version.to_string(),
+        }
+        .into());
+    }
 
     // Check if this is the current version before uninstalling
     let was_current = version_manager.get_current()?.as_deref() == Some(version);
@@ -1,13 +1,22 @@
+use std::path::PathBuf;
+
 use anyhow::{Context, Result};
 use semver::Version;
 
-use crate::{github::GitHubClient, token, ui, updater::Updater};
+use crate::{config::Config, github::ClientOptions, settings::Settings, ui, updater::Updater};
 
-pub async fn run(repo: String, github_token: Option<String>) -> Result<()> {
+pub async fn run(
+    install_dir: Option<PathBuf>,
+    repo: Option<String>,
+    github_token: Option<String>,
+    options: ClientOptions,
+) -> Result<()> {
     ui::info!("Checking for updates");
 
-    let resolved_token = token::resolve_github_token(github_token);
-    let github = GitHubClient::new(repo, resolved_token)?;
+    let config = Config::new(install_dir)?;
+    let settings = Settings::load(&config)?;
+
+    let github = settings.github_client(settings.self_repo(repo), github_token, options)?;
     let updater = Updater::new(github);
 
     let current_version = updater.get_current_version();
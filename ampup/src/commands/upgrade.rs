@@ -0,0 +1,440 @@
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result};
+use dialoguer::{Confirm, theme::ColorfulTheme};
+use semver::VersionReq;
+use serde::Deserialize;
+
+use crate::{
+    commands::use_version,
+    config::Config,
+    data_dir::{self, DataBoundary},
+    downgrade,
+    download_manager::DownloadManager,
+    github::{ClientOptions, GitHubClient, GitHubError},
+    install::VersionInstaller,
+    platform::{Architecture, Platform},
+    release_source,
+    resolver::{VersionResolver, VersionSpec, tag_version},
+    settings::Settings,
+    transaction::InstallTransaction,
+    ui,
+    version_manager::VersionManager,
+    version_store::{repo_namespace, split_key, version_key},
+};
+
+/// Release asset describing the steps needed to upgrade to that release.
+pub const UPGRADE_NOTES_ASSET: &str = "upgrade.json";
+
+#[derive(Debug)]
+pub enum UpgradeError {
+    NoActiveVersion,
+    NotInteractive,
+    MigrationsRequireGuided {
+        version: String,
+        count: usize,
+    },
+    MigrationFailed {
+        description: String,
+        command: String,
+        reason: String,
+    },
+    VerificationFailed {
+        version: String,
+        binary: String,
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for UpgradeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoActiveVersion => {
+                writeln!(f, "No active version to upgrade from")?;
+                writeln!(f)?;
+                writeln!(f, "  Try: ampup install")?;
+            }
+            Self::NotInteractive => {
+                writeln!(f, "Guided upgrade requires an interactive terminal")?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Run 'ampup upgrade' without --guided when no migrations are needed."
+                )?;
+            }
+            Self::MigrationsRequireGuided { version, count } => {
+                writeln!(f, "Upgrade requires manual migration steps")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Steps: {}", count)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Nothing was changed. Re-run with --guided to walk through them:"
+                )?;
+                writeln!(f, "    ampup upgrade {} --guided", version)?;
+            }
+            Self::MigrationFailed {
+                description,
+                command,
+                reason,
+            } => {
+                writeln!(f, "Migration step failed")?;
+                writeln!(f, "  Step: {}", description)?;
+                writeln!(f, "  Command: {}", command)?;
+                writeln!(f, "  Error: {}", reason)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  The new version is staged but not active; the previous version is still in use."
+                )?;
+                writeln!(
+                    f,
+                    "  Fix the problem and re-run the upgrade, or switch manually with 'ampup use'."
+                )?;
+            }
+            Self::VerificationFailed {
+                version,
+                binary,
+                reason,
+            } => {
+                writeln!(f, "Upgraded binaries failed verification")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Binary: {}", binary)?;
+                writeln!(f, "  Error: {}", reason)?;
+                writeln!(f)?;
+                writeln!(f, "  The previous version was re-activated.")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UpgradeError {}
+
+/// Contents of an [`UPGRADE_NOTES_ASSET`] published with a release.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct UpgradeNotes {
+    /// Breaking changes in this release, shown before upgrading
+    pub summary: Option<String>,
+    /// Steps to run before activating the new version, in order
+    pub migrations: Vec<Migration>,
+    /// Earlier versions whose ampd data directories this release can't use
+    pub data_dir: Option<DataBoundary>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Migration {
+    /// What the step does, shown to the operator
+    pub description: String,
+    /// Command to run; a leading `ampd` or `ampctl` runs the staged binary
+    pub command: Vec<String>,
+    /// Versions the step applies to (e.g. `<0.4`); all versions when unset
+    #[serde(default)]
+    pub from: Option<VersionReq>,
+}
+
+impl Migration {
+    /// Whether this step applies when upgrading from `current`.
+    ///
+    /// Non-semver versions (source builds) get every step, since their
+    /// position in the release history is unknown.
+    fn applies_to(&self, current: &str) -> bool {
+        match (&self.from, tag_version(current)) {
+            (Some(req), Some(version)) => req.matches(&version),
+            _ => true,
+        }
+    }
+}
+
+/// Upgrade the active version to `version` (default: latest) in stages:
+/// pre-checks, migrations from the release's upgrade notes, staged install,
+/// activation and verification.
+///
+/// With `guided`, each stage is confirmed interactively; without it, the
+/// upgrade refuses to run when the release requires migrations.
+#[expect(clippy::too_many_arguments)]
+pub async fn run(
+    install_dir: Option<PathBuf>,
+    repo: Option<String>,
+    github_token: Option<String>,
+    version: Option<String>,
+    jobs: usize,
+    guided: bool,
+    allow_prerelease: bool,
+    prerelease: bool,
+    allow_downgrade: bool,
+    options: ClientOptions,
+) -> Result<()> {
+    if guided && !std::io::stdin().is_terminal() {
+        return Err(UpgradeError::NotInteractive.into());
+    }
+
+    let config = Config::new(install_dir)?;
+    let amp_dir = config.amp_dir.clone();
+    let settings = Settings::load(&config)?;
+    let options = options.or(settings.client_options());
+    let policy = settings.policy();
+    let github = settings.github_client(settings.repo(repo), github_token, options.clone())?;
+    let artifact_cache_dir = config.artifact_cache_dir();
+    let version_manager = VersionManager::new(config);
+
+    // 1. Pre-checks
+    ui::info!("Checking current installation");
+    let current_key = version_manager
+        .get_current()?
+        .ok_or(UpgradeError::NoActiveVersion)?;
+    let (current_namespace, current) = split_key(&current_key);
+    let platform = Platform::detect()?;
+    let arch = Architecture::detect()?;
+    let spec = version
+        .as_deref()
+        .unwrap_or("latest")
+        .parse::<VersionSpec>()?
+        .including_prereleases(prerelease || settings.update.prerelease);
+    let resolver = VersionResolver::new(&github);
+    let target = resolver.resolve_release(&spec).await?;
+    let namespace = repo_namespace(github.repo());
+    let target_key = version_key(&namespace, &target);
+
+    if target_key == current_key {
+        ui::success!("Already using version {}", ui::version(current));
+        return Ok(());
+    }
+
+    let major_change = match (tag_version(current), tag_version(&target)) {
+        (Some(from), Some(to)) => {
+            from.major != to.major || (from.major == 0 && from.minor != to.minor)
+        }
+        _ => true,
+    };
+    let prerelease = resolver
+        .check_prerelease(&spec, &target, allow_prerelease, &policy)
+        .await?;
+    downgrade::confirm(Some(&current_key), &target_key, allow_downgrade)?;
+    ui::detail!(
+        "Upgrading {} → {}{}",
+        ui::version(current),
+        ui::version(&target),
+        if major_change { " (breaking)" } else { "" }
+    );
+
+    let notes = fetch_upgrade_notes(&github, &target).await?;
+    if let Some(summary) = &notes.summary {
+        ui::info!("Breaking changes in {}", ui::version(&target));
+        for line in summary.lines() {
+            ui::detail!("{}", line);
+        }
+    }
+    let migrations: Vec<&Migration> = notes
+        .migrations
+        .iter()
+        .filter(|m| m.applies_to(current))
+        .collect();
+    for (index, migration) in migrations.iter().enumerate() {
+        ui::detail!("Step {}: {}", index + 1, migration.description);
+    }
+
+    if !migrations.is_empty() && !guided {
+        return Err(UpgradeError::MigrationsRequireGuided {
+            version: target,
+            count: migrations.len(),
+        }
+        .into());
+    }
+    // Fetch the release's asset metadata while the user reads the plan;
+    // cancelling the upgrade drops (and aborts) the fetch
+    let prefetch =
+        (!version_manager.is_installed(&target_key)).then(|| github.prefetch_release(&target));
+    if guided && !confirm(&format!("Upgrade to {}?", target), true)? {
+        ui::info!("Upgrade cancelled");
+        return Ok(());
+    }
+    if let Some(prefetch) = prefetch {
+        prefetch.finish().await;
+    }
+
+    // 2. Stage the new version without activating it
+    if version_manager.is_installed(&target_key) {
+        version_manager.check_repo(&target_key, github.repo())?;
+        ui::info!("Version {} is already staged", ui::version(&target));
+    } else {
+        let source = release_source::with_mirrors(
+            settings.source.as_deref(),
+            &settings.mirrors,
+            &github,
+            options.timeouts,
+        )?;
+        let mut download_manager = DownloadManager::new(source, jobs)
+            .with_cache(settings.artifact_cache(artifact_cache_dir))
+            .with_require_digests(policy.require_digests);
+        // Patches are made against the same repository's releases
+        if current_namespace == Some(namespace.as_str()) {
+            download_manager = download_manager
+                .with_delta_base(current, version_manager.version_dir(&current_key));
+        }
+        let installer = VersionInstaller::new(
+            VersionManager::new(Config::new(Some(amp_dir.clone()))?),
+            download_manager,
+            github.repo(),
+        )
+        .with_smoke_tests(settings.smoke_tests())
+        .with_codesign(settings.macos.codesign)
+        .with_prerelease(prerelease)
+        .with_data_boundary(notes.data_dir.clone());
+        installer.stage_release(&target, platform, arch).await?;
+    }
+    let staged_dir = version_manager.version_dir(&target_key);
+
+    // 3. Migrations, run with the staged binaries
+    for migration in migrations {
+        ui::info!("{}", migration.description);
+        ui::detail!("$ {}", migration.command.join(" "));
+        if !confirm("Run this step?", true)? {
+            ui::warn!("Skipped: {}", migration.description);
+            continue;
+        }
+        run_migration(migration, &staged_dir)?;
+    }
+
+    // 4. Activate
+    if guided && !confirm(&format!("Activate {}?", target), true)? {
+        ui::info!(
+            "Version {} is staged; activate it later with 'ampup use {}'",
+            ui::version(&target),
+            target_key
+        );
+        return Ok(());
+    }
+    use_version::warn_if_prerelease(&settings, &version_manager, &target_key)?;
+    data_dir::warn_on_switch(
+        &settings,
+        &version_manager,
+        Some(&github),
+        Some(&current_key),
+        &target_key,
+    )
+    .await?;
+    let transaction = InstallTransaction::begin(version_manager.config())?;
+    version_manager.activate(&target_key)?;
+
+    // 5. Verify, rolling back on failure
+    ui::info!("Verifying {}", ui::version(&target));
+    verify(&version_manager, &target)?;
+    transaction.commit();
+
+    ui::success!("Upgraded to {}", ui::version(&target));
+    Ok(())
+}
+
+/// Download the release's upgrade notes, if it publishes any.
+pub async fn fetch_upgrade_notes(github: &GitHubClient, version: &str) -> Result<UpgradeNotes> {
+    let assets = match github
+        .resolve_release_assets(version, &[UPGRADE_NOTES_ASSET])
+        .await
+    {
+        Ok(assets) => assets,
+        Err(err)
+            if matches!(
+                err.downcast_ref::<GitHubError>(),
+                Some(GitHubError::AssetNotFound { .. })
+            ) =>
+        {
+            return Ok(UpgradeNotes::default());
+        }
+        Err(err) => return Err(err),
+    };
+
+    let data = github.download_resolved_asset(&assets[0], 1).await?;
+    serde_json::from_slice(&data).context("Failed to parse upgrade notes")
+}
+
+/// Run a migration step, substituting the staged binaries for a leading
+/// `ampd` or `ampctl`.
+fn run_migration(migration: &Migration, staged_dir: &Path) -> Result<()> {
+    let failed = |reason: String| UpgradeError::MigrationFailed {
+        description: migration.description.clone(),
+        command: migration.command.join(" "),
+        reason,
+    };
+
+    let (program, args) = migration
+        .command
+        .split_first()
+        .ok_or_else(|| failed("empty command".to_string()))?;
+    let program = match program.as_str() {
+        "ampd" | "ampctl" => staged_dir.join(program),
+        other => PathBuf::from(other),
+    };
+
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|err| failed(err.to_string()))?;
+    if !status.success() {
+        return Err(failed(format!("exited with {}", status)).into());
+    }
+    Ok(())
+}
+
+/// Check that the activated binaries run.
+fn verify(version_manager: &VersionManager, version: &str) -> Result<()> {
+    for binary in ["ampd", "ampctl"] {
+        let path = version_manager.config().bin_dir.join(binary);
+        let failed = |reason: String| UpgradeError::VerificationFailed {
+            version: version.to_string(),
+            binary: binary.to_string(),
+            reason,
+        };
+        let output = Command::new(&path)
+            .arg("--version")
+            .output()
+            .map_err(|err| failed(err.to_string()))?;
+        if !output.status.success() {
+            return Err(failed(format!("--version exited with {}", output.status)).into());
+        }
+        ui::detail!(
+            "{}",
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .unwrap_or(binary)
+        );
+    }
+    Ok(())
+}
+
+fn confirm(prompt: &str, default: bool) -> Result<bool> {
+    Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(default)
+        .interact()
+        .map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_to_with_version_range_matches_only_older_versions() {
+        //* Given
+        let notes: UpgradeNotes = serde_json::from_str(
+            r#"{"migrations":[{"description":"Migrate config","command":["ampctl","config","migrate"],"from":"<0.4"}]}"#,
+        )
+        .expect("should parse upgrade notes");
+        let migration = &notes.migrations[0];
+
+        //* Then
+        assert!(migration.applies_to("v0.3.2"));
+        assert!(!migration.applies_to("v0.4.0"));
+        assert!(
+            migration.applies_to("main-abc1234"),
+            "source builds should get every step"
+        );
+    }
+}
@@ -3,32 +3,122 @@ use dialoguer::{Select, theme::ColorfulTheme};
 
 use crate::{
     config::Config,
+    data_dir, dispatch, downgrade,
+    github::ClientOptions,
+    manifest::VersionManifest,
+    project,
+    settings::Settings,
     ui,
     version_manager::{VersionError, VersionManager},
+    version_store::split_key,
 };
 
-pub fn run(install_dir: Option<std::path::PathBuf>, version: Option<String>) -> Result<()> {
+pub async fn run(
+    install_dir: Option<std::path::PathBuf>,
+    version: Option<String>,
+    repo: Option<String>,
+    allow_downgrade: bool,
+    force: bool,
+    options: ClientOptions,
+) -> Result<()> {
     let config = Config::new(install_dir)?;
-    let version_manager = VersionManager::new(config);
+    let settings = Settings::load(&config)?;
+    let version_manager = VersionManager::new(config).with_checksum_verification(!force);
 
     // If version is provided, use it directly, otherwise prompt user to select from installed versions
     let version = match version {
-        Some(v) => v,
+        Some(v) => version_manager.resolve(&v, repo.as_deref())?,
         None => select_version(&version_manager)?,
     };
 
+    let current = version_manager.get_current()?;
+    // Leaving a version that's no longer installed isn't a downgrade
+    let installed_current = current
+        .as_deref()
+        .filter(|current| version_manager.is_installed(current));
+    downgrade::confirm(installed_current, &version, allow_downgrade)?;
+    warn_if_prerelease(&settings, &version_manager, &version)?;
+    let github = data_dir::release_client(&settings, &version_manager, &version, options);
+    data_dir::warn_on_switch(
+        &settings,
+        &version_manager,
+        github.as_ref(),
+        current.as_deref(),
+        &version,
+    )
+    .await?;
     switch_to_version(&version_manager, &version)?;
     ui::success!("Switched to ampd {}", ui::version(&version));
 
     Ok(())
 }
 
-/// Switch to a specific installed version
+/// Pin an installed version for the project in the current directory, so
+/// the `bin/` shims run it anywhere in the project's tree.
+pub fn pin_project(
+    install_dir: Option<std::path::PathBuf>,
+    version: Option<String>,
+    repo: Option<String>,
+) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let version_manager = VersionManager::new(config);
+    let key = match version {
+        Some(v) => version_manager.resolve(&v, repo.as_deref())?,
+        None => select_version(&version_manager)?,
+    };
+
+    // Write the bare version when it's only installed from one repository,
+    // so the file works for teammates whatever their repo namespace
+    let bare = split_key(&key).1;
+    let pinned = match version_manager.resolve(bare, None) {
+        Ok(_) => bare,
+        Err(_) => key.as_str(),
+    };
+    let cwd = std::env::current_dir().context("Failed to get current directory")?;
+    let file = project::write(&cwd, pinned)?;
+    ui::success!(
+        "Pinned {} in {}",
+        ui::version(pinned),
+        ui::path(file.display())
+    );
+
+    // Installs from before the shims still link bin/ to the active version
+    if version_manager.config().active_binary_path().is_symlink() {
+        dispatch::write_shims(version_manager.config())?;
+    }
+    Ok(())
+}
+
+/// Switch to a specific installed version, by its key
 pub fn switch_to_version(version_manager: &VersionManager, version: &str) -> Result<()> {
     version_manager.activate(version)?;
     Ok(())
 }
 
+/// Warn before activating a prerelease on a host whose settings flag it as
+/// production.
+pub fn warn_if_prerelease(
+    settings: &Settings,
+    version_manager: &VersionManager,
+    version: &str,
+) -> Result<()> {
+    if !settings.is_production() {
+        return Ok(());
+    }
+    if VersionManifest::load(&version_manager.version_dir(version))?.prerelease {
+        warn_prerelease_in_production(version);
+    }
+    Ok(())
+}
+
+pub fn warn_prerelease_in_production(version: &str) {
+    ui::warn!(
+        "Activating prerelease {} on a production host (environment = \"production\")",
+        ui::version(version)
+    );
+    ui::warn!("Prereleases may change or break before the final release");
+}
+
 fn select_version(version_manager: &VersionManager) -> Result<String> {
     let versions = version_manager.list_installed()?;
 
@@ -0,0 +1,122 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use console::style;
+
+use crate::{
+    builder::{self, CargoOptions, GitRepo},
+    config::Config,
+    environment::ProcessEnv,
+    manifest::VersionManifest,
+    reproducible::{self, ReproducibleError},
+    ui,
+    version_manager::VersionManager,
+};
+
+/// Rebuild `version` from the recipe recorded by `ampup build
+/// --reproducible` and compare the binaries' hashes with the recorded ones
+/// and with those installed.
+pub async fn rebuild(
+    install_dir: Option<PathBuf>,
+    version: &str,
+    repo: Option<String>,
+    jobs: Option<usize>,
+) -> Result<()> {
+    let config = Config::new(install_dir)?;
+    let version_manager = VersionManager::new(config);
+    let key = version_manager.resolve(version, repo.as_deref())?;
+    let version_dir = version_manager.version_dir(&key);
+    let recipe =
+        VersionManifest::load(&version_dir)?
+            .build
+            .ok_or_else(|| ReproducibleError::NoRecipe {
+                version: key.clone(),
+            })?;
+
+    let env = ProcessEnv;
+    let temp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let git = GitRepo::clone_url(&recipe.source, temp_dir.path(), None, &env).await?;
+    git.checkout_commit(&recipe.commit)?;
+
+    match recipe.pinned_rustc(temp_dir.path(), &env) {
+        Ok((rustc, host)) => {
+            if rustc != recipe.rustc {
+                ui::warn!(
+                    "Toolchain {} is {} here, but the build used {}",
+                    recipe.toolchain,
+                    rustc,
+                    recipe.rustc
+                );
+            }
+            if host != recipe.host {
+                ui::warn!(
+                    "Building for {}, but the binaries were built for {}",
+                    host,
+                    recipe.host
+                );
+            }
+        }
+        Err(err) => {
+            ui::warn!("Failed to run toolchain {}: {:#}", recipe.toolchain, err);
+            ui::detail!(
+                "Install it with: rustup toolchain install {}",
+                recipe.toolchain
+            );
+        }
+    }
+
+    ui::info!(
+        "Rebuilding {} at {} with {}",
+        ui::version(&key),
+        recipe.commit,
+        recipe.toolchain
+    );
+    builder::cargo_build(
+        temp_dir.path(),
+        jobs,
+        Some(&recipe),
+        &CargoOptions::default(),
+        &env,
+        None,
+    )?;
+
+    let output_dir = temp_dir
+        .path()
+        .join("target")
+        .join(builder::profile_dir(recipe.profile()));
+    let mut differing = Vec::new();
+    for binary in reproducible::BINARIES {
+        let recorded = recipe.sha256.get(binary).map(String::as_str);
+        let rebuilt = reproducible::sha256_file(&output_dir.join(binary))?;
+        let installed = reproducible::sha256_file(&version_dir.join(binary)).ok();
+
+        println!("{}", binary);
+        println!("  {:<10} sha256:{}", "Recorded", recorded.unwrap_or("-"));
+        println!("  {:<10} sha256:{}", "Rebuilt", rebuilt);
+        println!(
+            "  {:<10} sha256:{}",
+            "Installed",
+            installed.as_deref().unwrap_or("-")
+        );
+        if recorded != Some(rebuilt.as_str()) {
+            println!(
+                "  {}",
+                style("rebuild differs from the recorded build").red()
+            );
+            differing.push(binary.to_string());
+        } else if installed.as_deref() != recorded {
+            println!("  {}", style("installed binary was modified").red());
+            differing.push(format!("{} (installed)", binary));
+        }
+    }
+    if !differing.is_empty() {
+        return Err(ReproducibleError::Mismatch {
+            version: key,
+            binaries: differing,
+        }
+        .into());
+    }
+
+    ui::success!("Rebuilt binaries match {}", ui::version(&key));
+    Ok(())
+}
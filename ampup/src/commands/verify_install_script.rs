@@ -0,0 +1,202 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::Config,
+    github::{ClientOptions, HttpTimeouts},
+    settings::Settings,
+    ui,
+};
+
+/// Where the install script piped to `sh` is published.
+pub const DEFAULT_INSTALL_SCRIPT_URL: &str = "https://ampup.sh/install";
+
+/// Name of the install script among the ampup release assets.
+const INSTALL_SCRIPT_ASSET: &str = "install.sh";
+
+#[derive(Debug)]
+pub enum InstallScriptError {
+    /// The script differs from the one the release published.
+    Mismatch {
+        source: String,
+        version: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl std::fmt::Display for InstallScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mismatch {
+                source,
+                version,
+                expected,
+                actual,
+            } => {
+                writeln!(f, "Install script does not match the release")?;
+                writeln!(f, "  Script: {}", source)?;
+                writeln!(f, "  Release: {}", version)?;
+                writeln!(f, "  Expected: sha256:{}", expected)?;
+                writeln!(f, "  Actual: sha256:{}", actual)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Do not run it. The script may have been tampered with, or a new release is being published; try again later."
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InstallScriptError {}
+
+/// Check the install script at `url` (or the local copy `file`) against the
+/// `install.sh` asset of ampup's `version` release (default: latest), then
+/// print one-liners that install with it.
+///
+/// The release's published `sha256:` digest is trusted when present, as for
+/// binaries; otherwise the release asset itself is downloaded and hashed.
+pub async fn run(
+    repo: Option<String>,
+    github_token: Option<String>,
+    version: Option<String>,
+    url: Option<String>,
+    file: Option<PathBuf>,
+    options: ClientOptions,
+) -> Result<()> {
+    let config = Config::new(std::env::var_os("AMP_DIR").map(PathBuf::from))?;
+    let settings = Settings::load(&config)?;
+    let options = options.or(settings.client_options());
+    let github = settings.github_client(settings.self_repo(repo), github_token, options.clone())?;
+
+    let version = match version {
+        Some(version) => version,
+        None => github.get_latest_version().await?,
+    };
+    let url = url.unwrap_or_else(|| DEFAULT_INSTALL_SCRIPT_URL.to_string());
+    let (source, script) = match &file {
+        Some(file) => {
+            let script = fs::read(file).context("Failed to read install script")?;
+            (file.display().to_string(), script)
+        }
+        None => {
+            ui::info!("Fetching install script from {}", url);
+            (url.clone(), fetch(&url, &options.timeouts).await?)
+        }
+    };
+
+    ui::info!(
+        "Checking it against {} {}",
+        github.repo(),
+        ui::version(&version)
+    );
+    let published = github
+        .resolve_release_assets(&version, &[INSTALL_SCRIPT_ASSET])
+        .await?
+        .pop()
+        .and_then(|asset| asset.digest)
+        .and_then(|digest| digest.strip_prefix("sha256:").map(str::to_lowercase));
+    let expected = match published {
+        Some(digest) => digest,
+        None => {
+            ui::detail!("No published digest, comparing with the release asset");
+            let asset = github
+                .download_release_asset(&version, INSTALL_SCRIPT_ASSET)
+                .await
+                .context("Failed to download install script from the release")?;
+            sha256_hex(&asset)
+        }
+    };
+    let actual = sha256_hex(&script);
+    if actual != expected {
+        return Err(InstallScriptError::Mismatch {
+            source,
+            version,
+            expected,
+            actual,
+        }
+        .into());
+    }
+
+    ui::success!("Install script matches {} {}", github.repo(), version);
+    for line in one_liners(&url, &actual) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// The usual `curl | sh` one-liner for `url`, and one that refuses to run
+/// a script whose SHA-256 isn't `digest`.
+fn one_liners(url: &str, digest: &str) -> [String; 2] {
+    [
+        format!("curl --proto '=https' --tlsv1.2 -sSf {} | sh", url),
+        format!(
+            "curl --proto '=https' --tlsv1.2 -sSfo ampup-install.sh {} && echo '{}  ampup-install.sh' | sha256sum -c - && sh ampup-install.sh",
+            url, digest
+        ),
+    ]
+}
+
+/// Download the install script at `url`.
+async fn fetch(url: &str, timeouts: &HttpTimeouts) -> Result<Vec<u8>> {
+    let client = timeouts
+        .apply(reqwest::Client::builder().user_agent("ampup"))
+        .build()
+        .context("Failed to create request client")?;
+
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to fetch install script from {}", url))?
+        .error_for_status()
+        .with_context(|| format!("Failed to fetch install script from {}", url))?;
+    let body = response
+        .bytes()
+        .await
+        .with_context(|| format!("Failed to read install script from {}", url))?;
+    Ok(body.to_vec())
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_liners_with_digest_pins_script_to_it() {
+        //* When
+        let [plain, pinned] = one_liners(DEFAULT_INSTALL_SCRIPT_URL, "abc123");
+
+        //* Then
+        assert_eq!(
+            plain,
+            "curl --proto '=https' --tlsv1.2 -sSf https://ampup.sh/install | sh"
+        );
+        assert!(
+            pinned.contains("echo 'abc123  ampup-install.sh' | sha256sum -c -"),
+            "pinned one-liner: {}",
+            pinned
+        );
+        assert!(pinned.ends_with("&& sh ampup-install.sh"));
+    }
+
+    #[test]
+    fn sha256_hex_with_known_input_returns_digest() {
+        //* Then
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::{config::Config, dispatch, ui, version_manager::VersionManager};
+
+/// Print the path of the `binary` a shim would run in the current
+/// directory, and why that version was selected.
+pub fn run(install_dir: Option<PathBuf>, binary: &str) -> Result<()> {
+    let version_manager = VersionManager::new(Config::new(install_dir)?);
+    let cwd = std::env::current_dir().context("Failed to get current directory")?;
+    let override_version = std::env::var(dispatch::VERSION_ENV).ok();
+    let (key, selection) = dispatch::resolve(&version_manager, &cwd, override_version.as_deref())?;
+
+    let path = version_manager.version_dir(&key).join(binary);
+    println!("{}", path.display());
+    ui::detail!("{} selected by the {}", ui::version(&key), selection);
+
+    // Another binary earlier on PATH runs instead of the shim
+    let shim = version_manager.config().bin_dir.join(binary);
+    if let Some(found) = find_on_path(binary)
+        && !same_file(&found, &shim)
+        && !same_file(&found, &path)
+    {
+        ui::warn!(
+            "'{}' on PATH is {}, which runs instead of ampup's",
+            binary,
+            ui::path(found.display())
+        );
+    }
+    Ok(())
+}
+
+/// First executable named `binary` in a `PATH` directory.
+fn find_on_path(binary: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(binary))
+        .find(|candidate| candidate.is_file())
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
@@ -0,0 +1,317 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    github::{GitHubClient, GitHubError},
+    manifest::VersionManifest,
+};
+
+/// Release asset declaring the optional components of a release.
+pub const COMPONENTS_ASSET: &str = "components.json";
+
+/// Directory inside a version directory holding one directory per added
+/// component.
+const COMPONENTS_DIR: &str = "components";
+
+/// Names in `bin/` that components can't take over.
+const RESERVED_BINARIES: [&str; 3] = ["ampd", "ampctl", "ampup"];
+
+#[derive(Debug)]
+pub enum ComponentError {
+    /// The release doesn't declare the component.
+    Unknown {
+        name: String,
+        version: String,
+        available: Vec<String>,
+    },
+    /// The component isn't added to the version.
+    NotAdded { name: String, version: String },
+    /// A component binary would replace a binary ampup manages, or a name
+    /// would escape the component's directory.
+    InvalidEntry { name: String, entry: String },
+    /// The active version isn't a release of the repository, so its
+    /// components can't be looked up.
+    NotARelease { version: String, repo: String },
+}
+
+impl std::fmt::Display for ComponentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unknown {
+                name,
+                version,
+                available,
+            } => {
+                writeln!(f, "Component not available")?;
+                writeln!(f, "  Component: {}", name)?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f)?;
+                if available.is_empty() {
+                    write!(f, "  This release declares no components.")?;
+                } else {
+                    write!(f, "  Available: {}", available.join(", "))?;
+                }
+            }
+            Self::NotAdded { name, version } => {
+                writeln!(f, "Component not added")?;
+                writeln!(f, "  Component: {}", name)?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f)?;
+                write!(f, "  Try: ampup component list")?;
+            }
+            Self::InvalidEntry { name, entry } => {
+                writeln!(f, "Component declares a file ampup can't install")?;
+                writeln!(f, "  Component: {}", name)?;
+                writeln!(f, "  Entry: {}", entry)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  The release's components.json is invalid; report it upstream."
+                )?;
+            }
+            Self::NotARelease { version, repo } => {
+                writeln!(f, "Active version is not a release of the repository")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Repository: {}", repo)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Source builds have no components. For a version from another repository, pass --repo."
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ComponentError {}
+
+/// Contents of a [`COMPONENTS_ASSET`] published with a release.
+#[derive(Debug, Default, Deserialize)]
+pub struct ComponentManifest {
+    #[serde(default)]
+    pub components: Vec<ComponentSpec>,
+}
+
+impl ComponentManifest {
+    pub fn get(&self, name: &str) -> Option<&ComponentSpec> {
+        self.components
+            .iter()
+            .find(|component| component.name == name)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.components.iter().map(|c| c.name.clone()).collect()
+    }
+}
+
+/// An optional component a release declares, e.g. a language server or
+/// debug symbols.
+///
+/// Each of its binaries and files is published per target as
+/// `<name>-<platform>-<arch>`, like ampd.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ComponentSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Executables linked into `bin/` while the version is active
+    #[serde(default)]
+    pub binaries: Vec<String>,
+    /// Other files kept in the version directory only
+    #[serde(default)]
+    pub files: Vec<String>,
+}
+
+impl ComponentSpec {
+    /// Fail when a binary would replace one ampup manages, or a name isn't
+    /// a plain file name that stays inside the component's directory.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |name: &String| {
+            name.is_empty() || name.starts_with('.') || name.contains('/') || name.contains('\\')
+        };
+        let mut names = std::iter::once(&self.name)
+            .chain(&self.binaries)
+            .chain(&self.files);
+        match names.find(|name| invalid(name)).or_else(|| {
+            self.binaries
+                .iter()
+                .find(|binary| RESERVED_BINARIES.contains(&binary.as_str()))
+        }) {
+            Some(entry) => Err(ComponentError::InvalidEntry {
+                name: self.name.clone(),
+                entry: entry.clone(),
+            }
+            .into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A component added to an installed version, recorded in its manifest so
+/// switching versions can relink it without the release.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstalledComponent {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub binaries: Vec<String>,
+}
+
+/// Fetch the components `version`'s release declares; releases without a
+/// [`COMPONENTS_ASSET`] declare none.
+pub async fn fetch(github: &GitHubClient, version: &str) -> Result<ComponentManifest> {
+    let assets = match github
+        .resolve_release_assets(version, &[COMPONENTS_ASSET])
+        .await
+    {
+        Ok(assets) => assets,
+        Err(err)
+            if matches!(
+                err.downcast_ref::<GitHubError>(),
+                Some(GitHubError::AssetNotFound { .. })
+            ) =>
+        {
+            return Ok(ComponentManifest::default());
+        }
+        Err(err) => return Err(err),
+    };
+
+    let data = github.download_resolved_asset(&assets[0], 1).await?;
+    serde_json::from_slice(&data).context("Failed to parse components manifest")
+}
+
+/// Directory of component `name` in `version_dir`.
+pub fn component_dir(version_dir: &Path, name: &str) -> PathBuf {
+    version_dir.join(COMPONENTS_DIR).join(name)
+}
+
+/// Record `component` as added to the version in `version_dir`.
+pub fn record(version_dir: &Path, component: InstalledComponent) -> Result<()> {
+    let mut manifest = VersionManifest::load(version_dir)?;
+    manifest.components.retain(|c| c.name != component.name);
+    manifest.components.push(component);
+    manifest.components.sort_by(|a, b| a.name.cmp(&b.name));
+    manifest.save(version_dir)
+}
+
+/// Remove component `name` from the version in `version_dir`, returning
+/// what it had installed.
+pub fn remove(version_dir: &Path, name: &str) -> Result<Option<InstalledComponent>> {
+    let mut manifest = VersionManifest::load(version_dir)?;
+    let Some(index) = manifest.components.iter().position(|c| c.name == name) else {
+        return Ok(None);
+    };
+    let component = manifest.components.remove(index);
+    manifest.save(version_dir)?;
+
+    let dir = component_dir(version_dir, name);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).context("Failed to remove component directory")?;
+    }
+    Ok(Some(component))
+}
+
+/// Link the binaries of `component` in `version_dir` into `bin/`.
+pub fn link(config: &Config, version_dir: &Path, component: &InstalledComponent) -> Result<()> {
+    let dir = component_dir(version_dir, &component.name);
+    for binary in &component.binaries {
+        let link = config.bin_dir.join(binary);
+        remove_link(&link)?;
+        config
+            .symlink(&dir.join(binary), &link)
+            .with_context(|| format!("Failed to link component binary {}", binary))?;
+    }
+    Ok(())
+}
+
+/// Remove the `bin/` links of `component`'s binaries.
+pub fn unlink(config: &Config, component: &InstalledComponent) -> Result<()> {
+    for binary in &component.binaries {
+        remove_link(&config.bin_dir.join(binary))?;
+    }
+    Ok(())
+}
+
+/// Swap the component links of the version in `from` (if any) for those of
+/// the version in `to`, when the active version changes.
+pub fn relink(config: &Config, from: Option<&Path>, to: &Path) -> Result<()> {
+    if let Some(from) = from {
+        for component in VersionManifest::load(from)?.components {
+            unlink(config, &component)?;
+        }
+    }
+    for component in VersionManifest::load(to)?.components {
+        link(config, to, &component)?;
+    }
+    Ok(())
+}
+
+/// Remove `link` if it's a symlink; binaries the user put there are kept.
+fn remove_link(link: &Path) -> Result<()> {
+    if link.is_symlink() {
+        fs::remove_file(link).context("Failed to remove component link")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version_with_component(amp_dir: &Path, version: &str, binary: &str) -> PathBuf {
+        let version_dir = amp_dir.join("versions").join(version);
+        let dir = component_dir(&version_dir, "amp-lsp");
+        fs::create_dir_all(&dir).expect("should create component dir");
+        fs::write(dir.join(binary), version).expect("should write binary");
+        record(
+            &version_dir,
+            InstalledComponent {
+                name: "amp-lsp".to_string(),
+                binaries: vec![binary.to_string()],
+            },
+        )
+        .expect("should record component");
+        version_dir
+    }
+
+    #[test]
+    fn relink_with_component_in_both_versions_points_bin_at_new_one() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        config.ensure_dirs().expect("should create directories");
+        let old = version_with_component(dir.path(), "v0.1.0", "amp-lsp");
+        let new = version_with_component(dir.path(), "v0.2.0", "amp-lsp");
+        relink(&config, None, &old).expect("should link old version");
+
+        //* When
+        relink(&config, Some(&old), &new).expect("should relink");
+
+        //* Then
+        assert_eq!(
+            fs::read_to_string(config.bin_dir.join("amp-lsp")).expect("should read link"),
+            "v0.2.0"
+        );
+    }
+
+    #[test]
+    fn validate_with_reserved_binary_fails() {
+        //* Given
+        let spec: ComponentSpec = serde_json::from_str(r#"{"name": "evil", "binaries": ["ampd"]}"#)
+            .expect("should parse");
+
+        //* When
+        let result = spec.validate();
+
+        //* Then
+        let err = result.expect_err("ampd should be reserved");
+        assert!(matches!(
+            err.downcast_ref::<ComponentError>(),
+            Some(ComponentError::InvalidEntry { .. })
+        ));
+    }
+}
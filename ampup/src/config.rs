@@ -1,8 +1,10 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 use fs_err as fs;
 
+use crate::environment::{Environment, ProcessEnv};
+
 /// Configuration for ampup
 pub struct Config {
     /// Base directory for amp installation (~/.amp)
@@ -11,21 +13,30 @@ pub struct Config {
     pub bin_dir: PathBuf,
     /// Versions directory (~/.amp/versions)
     pub versions_dir: PathBuf,
+    /// Whether links point relative to the installation directory
+    relative_links: bool,
 }
 
 impl Config {
     /// Create a new configuration
     pub fn new(install_dir: Option<PathBuf>) -> Result<Self> {
+        Self::from_env(install_dir, &ProcessEnv)
+    }
+
+    /// Create a configuration, locating the default directory through `env`
+    pub fn from_env(install_dir: Option<PathBuf>, env: &dyn Environment) -> Result<Self> {
         let amp_dir = if let Some(dir) = install_dir {
             dir
         } else {
-            let home = std::env::var("HOME")
-                .or_else(|_| std::env::var("USERPROFILE"))
+            let home = env
+                .var("HOME")
+                .or_else(|| env.var("USERPROFILE"))
                 .context("Could not determine home directory")?;
 
-            let base = std::env::var("XDG_CONFIG_HOME")
+            let base = env
+                .var("XDG_CONFIG_HOME")
                 .map(PathBuf::from)
-                .unwrap_or_else(|_| PathBuf::from(&home));
+                .unwrap_or_else(|| PathBuf::from(&home));
 
             base.join(".amp")
         };
@@ -37,9 +48,34 @@ impl Config {
             amp_dir,
             bin_dir,
             versions_dir,
+            relative_links: false,
         })
     }
 
+    /// Link `bin/` and `share/` entries (and write shims) relative to the
+    /// installation directory, so the tree keeps working when copied to another machine
+    /// or path.
+    pub fn with_relative_links(mut self) -> Self {
+        self.relative_links = true;
+        self
+    }
+
+    /// Whether links and shims point relative to the installation directory
+    pub fn relative_links(&self) -> bool {
+        self.relative_links
+    }
+
+    /// Create a symlink at `link` to `target`, relative to the link's
+    /// directory when relative links are on and both are inside the
+    /// installation directory.
+    pub fn symlink(&self, target: &Path, link: &Path) -> std::io::Result<()> {
+        let relative = self
+            .relative_links
+            .then(|| relative_target(&self.amp_dir, target, link))
+            .flatten();
+        std::os::unix::fs::symlink(relative.as_deref().unwrap_or(target), link)
+    }
+
     /// Get the path to the current version file
     pub fn current_version_file(&self) -> PathBuf {
         self.amp_dir.join(".version")
@@ -68,6 +104,90 @@ impl Config {
         Ok(())
     }
 
+    /// Get the path to the previous version file (~/.amp/.previous-version)
+    pub fn previous_version_file(&self) -> PathBuf {
+        self.amp_dir.join(".previous-version")
+    }
+
+    /// Get the version that was active before the current one
+    pub fn previous_version(&self) -> Result<Option<String>> {
+        let version_file = self.previous_version_file();
+        if !version_file.exists() {
+            return Ok(None);
+        }
+
+        let version = fs::read_to_string(&version_file)
+            .context("Failed to read previous version file")?
+            .trim()
+            .to_string();
+
+        Ok(Some(version).filter(|version| !version.is_empty()))
+    }
+
+    /// Record the version that was active before the current one
+    pub fn set_previous_version(&self, version: &str) -> Result<()> {
+        fs::write(self.previous_version_file(), version)
+            .context("Failed to write previous version file")?;
+        Ok(())
+    }
+
+    /// Get the path to the installation lock file (~/.amp/.lock)
+    pub fn lock_file(&self) -> PathBuf {
+        self.amp_dir.join(".lock")
+    }
+
+    /// Get the path to the user settings file (~/.amp/config.toml)
+    pub fn settings_file(&self) -> PathBuf {
+        self.amp_dir.join("config.toml")
+    }
+
+    /// Get the path to the instances file (~/.amp/instances.toml)
+    pub fn instances_file(&self) -> PathBuf {
+        self.amp_dir.join("instances.toml")
+    }
+
+    /// Get the path to the directory overrides file (~/.amp/overrides.toml)
+    pub fn overrides_file(&self) -> PathBuf {
+        self.amp_dir.join("overrides.toml")
+    }
+
+    /// Get the wrapper script path for an instance (~/.amp/bin/<instance>-ampd)
+    pub fn instance_wrapper_path(&self, name: &str) -> PathBuf {
+        self.bin_dir.join(format!("{}-ampd", name))
+    }
+
+    /// Get the cache directory (~/.amp/cache)
+    pub fn cache_dir(&self) -> PathBuf {
+        self.amp_dir.join("cache")
+    }
+
+    /// Get the directory build logs are written to (~/.amp/logs)
+    pub fn logs_dir(&self) -> PathBuf {
+        self.amp_dir.join("logs")
+    }
+
+    /// Get the hook scripts directory (~/.amp/hooks)
+    pub fn hooks_dir(&self) -> PathBuf {
+        self.amp_dir.join("hooks")
+    }
+
+    /// Get the directory the active version's completions and man pages are
+    /// linked into (~/.amp/share)
+    pub fn share_dir(&self) -> PathBuf {
+        self.amp_dir.join("share")
+    }
+
+    /// Get the downloaded artifact cache directory (~/.amp/cache/artifacts)
+    pub fn artifact_cache_dir(&self) -> PathBuf {
+        self.cache_dir().join("artifacts")
+    }
+
+    /// Get the directory cached clones of remote sources are built in
+    /// (~/.amp/cache/src)
+    pub fn source_cache_dir(&self) -> PathBuf {
+        self.cache_dir().join("src")
+    }
+
     /// Get the path to the ampup binary
     pub fn ampup_binary_path(&self) -> PathBuf {
         self.bin_dir.join("ampup")
@@ -78,7 +198,7 @@ impl Config {
         self.versions_dir.join(version).join("ampd")
     }
 
-    /// Get the active ampd binary symlink path
+    /// Get the ampd shim path
     pub fn active_binary_path(&self) -> PathBuf {
         self.bin_dir.join("ampd")
     }
@@ -88,7 +208,7 @@ impl Config {
         self.versions_dir.join(version).join("ampctl")
     }
 
-    /// Get the active ampctl binary symlink path
+    /// Get the ampctl shim path
     pub fn active_ampctl_path(&self) -> PathBuf {
         self.bin_dir.join("ampctl")
     }
@@ -102,6 +222,15 @@ impl Config {
     }
 }
 
+/// `target` as seen from `link`'s directory, when both are inside `root`.
+fn relative_target(root: &Path, target: &Path, link: &Path) -> Option<PathBuf> {
+    let target = target.strip_prefix(root).ok()?;
+    let link_dir = link.parent()?.strip_prefix(root).ok()?;
+    let mut relative: PathBuf = link_dir.components().map(|_| "..").collect();
+    relative.push(target);
+    Some(relative)
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self::new(None).expect("Failed to create default config")
@@ -0,0 +1,307 @@
+use anyhow::Result;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::upgrade::fetch_upgrade_notes,
+    github::{ClientOptions, GitHubClient},
+    manifest::VersionManifest,
+    resolver::tag_version,
+    settings::Settings,
+    ui,
+    version_manager::VersionManager,
+    version_store::{split_key, version_key},
+};
+
+/// A release's declaration, under `data_dir` in its upgrade notes, that it
+/// can't use ampd data directories written by some earlier versions as-is.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DataBoundary {
+    /// Versions whose data directories need a resync or migration (e.g. `<0.5`)
+    pub incompatible: VersionReq,
+    /// What changed and what to do about it, shown to the operator
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Where the migration notes are published
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes_url: Option<String>,
+}
+
+/// Namespace and the older and newer release of a switch between version
+/// keys `from` and `to`, when both are releases of the same repository.
+fn switch_range<'a>(from: &'a str, to: &str) -> Option<(Option<&'a str>, Version, Version)> {
+    let (from_namespace, from_version) = split_key(from);
+    let (to_namespace, to_version) = split_key(to);
+    let from_version = tag_version(from_version)?;
+    let to_version = tag_version(to_version)?;
+    if from_namespace != to_namespace {
+        return None;
+    }
+    match from_version < to_version {
+        true => Some((from_namespace, from_version, to_version)),
+        false => Some((from_namespace, to_version, from_version)),
+    }
+}
+
+/// Boundaries recorded by installed releases that switching between version
+/// keys `from` and `to` crosses, in either direction, oldest release first.
+///
+/// Only releases of the same repository are compared; source builds and
+/// versions installed before their boundary was recorded are unknown.
+pub fn crossed_boundaries(
+    version_manager: &VersionManager,
+    from: &str,
+    to: &str,
+) -> Result<Vec<(String, DataBoundary)>> {
+    let Some((from_namespace, older, newer)) = switch_range(from, to) else {
+        return Ok(Vec::new());
+    };
+
+    let mut crossed = Vec::new();
+    for key in version_manager.list_installed()? {
+        let (namespace, version) = split_key(&key);
+        let Some(version) = tag_version(version) else {
+            continue;
+        };
+        if namespace != from_namespace || version <= older || version > newer {
+            continue;
+        }
+        let manifest = VersionManifest::load(&version_manager.version_dir(&key))?;
+        if let Some(boundary) = manifest.data_boundary
+            && boundary.incompatible.matches(&older)
+        {
+            crossed.push((version, key, boundary));
+        }
+    }
+    crossed.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(crossed
+        .into_iter()
+        .map(|(_, key, boundary)| (key, boundary))
+        .collect())
+}
+
+/// Boundaries declared in the upgrade notes of `github`'s releases that
+/// switching between version keys `from` and `to` crosses, in either
+/// direction, oldest release first. Unlike [`crossed_boundaries`], this
+/// covers releases that were never installed here.
+pub async fn release_boundaries(
+    github: &GitHubClient,
+    from: &str,
+    to: &str,
+) -> Result<Vec<(String, DataBoundary)>> {
+    let Some((namespace, older, newer)) = switch_range(from, to) else {
+        return Ok(Vec::new());
+    };
+    let mut releases: Vec<(Version, String)> = github
+        .list_releases()
+        .await?
+        .into_iter()
+        .filter_map(|release| Some((tag_version(&release.tag)?, release.tag)))
+        .filter(|(version, _)| *version > older && *version <= newer)
+        .collect();
+    releases.sort();
+
+    let mut crossed = Vec::new();
+    for (_, tag) in releases {
+        if let Some(boundary) = fetch_upgrade_notes(github, &tag).await?.data_dir
+            && boundary.incompatible.matches(&older)
+        {
+            let key = match namespace {
+                Some(namespace) => version_key(namespace, &tag),
+                None => tag,
+            };
+            crossed.push((key, boundary));
+        }
+    }
+    Ok(crossed)
+}
+
+/// Client for the repository version `key` was installed from, to look up
+/// release notes for [`warn_on_switch`]; `None` when no `data_dir` is
+/// registered (so nothing needs looking up) or the repository is unknown.
+pub fn release_client(
+    settings: &Settings,
+    version_manager: &VersionManager,
+    key: &str,
+    options: ClientOptions,
+) -> Option<GitHubClient> {
+    settings.data_dir.as_ref()?;
+    let repo = VersionManifest::load(&version_manager.version_dir(key))
+        .ok()?
+        .repo?;
+    settings.github_client(repo, None, options).ok()
+}
+
+/// Warn when switching from the active version `from` to `to` crosses a
+/// data boundary, for hosts that registered their ampd `data_dir`.
+///
+/// The boundaries come from the upgrade notes of every release in between
+/// published on `github`. Without a client, or when the notes can't be
+/// fetched, only those recorded by installed versions are known.
+pub async fn warn_on_switch(
+    settings: &Settings,
+    version_manager: &VersionManager,
+    github: Option<&GitHubClient>,
+    from: Option<&str>,
+    to: &str,
+) -> Result<()> {
+    let (Some(data_dir), Some(from)) = (&settings.data_dir, from) else {
+        return Ok(());
+    };
+    let crossed = match github {
+        Some(github) => match release_boundaries(github, from, to).await {
+            Ok(crossed) => crossed,
+            Err(err) => {
+                ui::warn!(
+                    "Could not fetch the upgrade notes of releases between {} and {}: {:#}",
+                    ui::version(from),
+                    ui::version(to),
+                    err
+                );
+                ui::detail!("Checking only the data boundaries installed versions recorded");
+                crossed_boundaries(version_manager, from, to)?
+            }
+        },
+        None => crossed_boundaries(version_manager, from, to)?,
+    };
+    if crossed.is_empty() {
+        return Ok(());
+    }
+
+    ui::warn!(
+        "Data directory {} may need a resync or migration for {}",
+        ui::path(data_dir.display()),
+        ui::version(to)
+    );
+    for (key, boundary) in crossed {
+        let (_, version) = split_key(&key);
+        match &boundary.description {
+            Some(description) => ui::detail!("{}: {}", version, description),
+            None => ui::detail!(
+                "{}: data written by {} is incompatible",
+                version,
+                boundary.incompatible
+            ),
+        }
+        if let Some(url) = &boundary.notes_url {
+            ui::detail!("Migration notes: {}", url);
+        }
+    }
+    ui::detail!("Check the notes before starting ampd");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use fs_err as fs;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        testing::{MockReleaseServer, Route},
+    };
+
+    fn install(version_manager: &VersionManager, key: &str, incompatible: Option<&str>) {
+        let dir = version_manager.version_dir(key);
+        fs::create_dir_all(&dir).expect("should create version dir");
+        let manifest = VersionManifest {
+            data_boundary: incompatible.map(|req| DataBoundary {
+                incompatible: req.parse().expect("valid requirement"),
+                description: None,
+                notes_url: None,
+            }),
+            ..Default::default()
+        };
+        manifest.save(&dir).expect("should save manifest");
+    }
+
+    fn version_manager(dir: &Path) -> VersionManager {
+        let config = Config::new(Some(dir.to_path_buf())).expect("should create config");
+        config.ensure_dirs().expect("should create directories");
+        VersionManager::new(config)
+    }
+
+    #[test]
+    fn crossed_boundaries_with_release_in_between_returns_it_in_both_directions() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let version_manager = version_manager(dir.path());
+        install(&version_manager, "edgeandnode-amp/v0.4.2", None);
+        install(&version_manager, "edgeandnode-amp/v0.5.0", Some("<0.5"));
+        install(&version_manager, "edgeandnode-amp/v0.6.1", None);
+
+        //* When
+        let upgrade = crossed_boundaries(
+            &version_manager,
+            "edgeandnode-amp/v0.4.2",
+            "edgeandnode-amp/v0.6.1",
+        )
+        .expect("should compare versions");
+        let downgrade = crossed_boundaries(
+            &version_manager,
+            "edgeandnode-amp/v0.6.1",
+            "edgeandnode-amp/v0.4.2",
+        )
+        .expect("should compare versions");
+
+        //* Then
+        let keys: Vec<&str> = upgrade.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["edgeandnode-amp/v0.5.0"]);
+        assert_eq!(upgrade, downgrade);
+    }
+
+    #[test]
+    fn crossed_boundaries_within_compatible_range_returns_none() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let version_manager = version_manager(dir.path());
+        install(&version_manager, "edgeandnode-amp/v0.5.0", Some("<0.5"));
+        install(&version_manager, "edgeandnode-amp/v0.5.3", None);
+
+        //* When
+        let crossed = crossed_boundaries(
+            &version_manager,
+            "edgeandnode-amp/v0.5.0",
+            "edgeandnode-amp/v0.5.3",
+        )
+        .expect("should compare versions");
+
+        //* Then
+        assert!(crossed.is_empty(), "v0.5.0 can read its own data");
+    }
+
+    #[tokio::test]
+    async fn release_boundaries_with_uninstalled_release_in_between_returns_it() {
+        //* Given
+        let server = MockReleaseServer::start()
+            .await
+            .expect("should start server");
+        let releases = serde_json::json!([
+            { "tag_name": "v0.6.0" },
+            { "tag_name": "v0.5.0" },
+            { "tag_name": "v0.4.0" },
+        ]);
+        let server = server
+            .with_route(Route::ok("?per_page", releases.to_string()))
+            .with_release("v0.6.0", &[])
+            .with_release("v0.5.0", &["upgrade.json"])
+            .with_route(Route::ok(
+                "download/upgrade.json",
+                r#"{"data_dir": {"incompatible": "<0.5", "description": "Resync"}}"#,
+            ));
+        let github = server.github_client().expect("should create client");
+
+        //* When
+        let crossed =
+            release_boundaries(&github, "edgeandnode-amp/v0.4.0", "edgeandnode-amp/v0.6.0")
+                .await
+                .expect("should fetch upgrade notes");
+
+        //* Then
+        let keys: Vec<&str> = crossed.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["edgeandnode-amp/v0.5.0"]);
+        assert_eq!(crossed[0].1.description.as_deref(), Some("Resync"));
+    }
+}
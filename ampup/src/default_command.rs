@@ -0,0 +1,77 @@
+use std::io::IsTerminal;
+
+use anyhow::Result;
+use dialoguer::{Confirm, theme::ColorfulTheme};
+use serde::{Deserialize, Serialize};
+
+use crate::ui;
+
+/// What bare `ampup`, run without a command, does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultCommand {
+    /// Install and activate the latest version, as `ampup update` does
+    Update,
+    /// Print the command-line help
+    Help,
+    /// Show the installation status, as `ampup status` does
+    Status,
+}
+
+/// The command bare `ampup` runs, given the one chosen with
+/// `--default-command` or `default_command` in config.toml.
+///
+/// Without a choice, bare `ampup` still updates during the transition to a
+/// new default, but warns about the change and, on a terminal, asks before
+/// updating; declining shows the help instead.
+pub fn choose(configured: Option<DefaultCommand>) -> Result<DefaultCommand> {
+    if let Some(command) = configured {
+        return Ok(command);
+    }
+
+    ui::warn!("Running ampup without a command updates ampd to the latest version");
+    ui::detail!("A future release will show the help instead. To choose explicitly:");
+    ui::detail!("  ampup config set default_command update   # keep updating");
+    ui::detail!("  ampup config set default_command help     # or status");
+    if !std::io::stdin().is_terminal() {
+        return Ok(DefaultCommand::Update);
+    }
+    let update = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt("Update ampd to the latest version?")
+        .default(true)
+        .interact()?;
+    Ok(if update {
+        DefaultCommand::Update
+    } else {
+        DefaultCommand::Help
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn choose_with_configured_command_returns_it_without_asking() {
+        //* When
+        let command = choose(Some(DefaultCommand::Status)).expect("should choose");
+
+        //* Then
+        assert_eq!(command, DefaultCommand::Status);
+    }
+
+    #[test]
+    fn deserialize_with_lowercase_name_returns_command() {
+        //* Given
+        #[derive(Deserialize)]
+        struct Table {
+            default_command: DefaultCommand,
+        }
+
+        //* When
+        let table: Table = toml::from_str(r#"default_command = "help""#).expect("should parse");
+
+        //* Then
+        assert_eq!(table.default_command, DefaultCommand::Help);
+    }
+}
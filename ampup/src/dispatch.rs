@@ -0,0 +1,530 @@
+use std::{
+    ffi::{OsStr, OsString},
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::{
+    config::Config,
+    instances::shell_quote,
+    manifest::VersionManifest,
+    overrides::{OverrideError, Overrides},
+    project::{self, ProjectError},
+    settings::Settings,
+    ui,
+    version_manager::{VersionError, VersionManager, unix_now},
+    version_store::{LOCAL_NAMESPACE, split_key},
+};
+
+/// Binaries ampup dispatches to.
+const BINARIES: [&str; 2] = ["ampd", "ampctl"];
+
+/// Argument the shims pass ampup before the binary to run.
+const DISPATCH_ARG: &str = "--dispatch";
+
+/// Environment variable selecting the version to run, over any project file.
+pub const VERSION_ENV: &str = "AMP_VERSION";
+
+#[derive(Debug)]
+pub enum DispatchError {
+    /// The version [`VERSION_ENV`] selects isn't installed.
+    OverrideNotInstalled { version: String },
+}
+
+impl std::fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OverrideNotInstalled { version } => {
+                writeln!(f, "Version selected by {} not installed", VERSION_ENV)?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Try: ampup install {}, or unset {} to use the project or active version",
+                    version, VERSION_ENV
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DispatchError {}
+
+/// The binary to dispatch to and its arguments, when ampup was started by a
+/// shim (`ampup --dispatch ampd ...`) or under the binary's name.
+pub fn parse(args: &[OsString]) -> Option<(&'static str, &[OsString])> {
+    let find = |name: &OsStr| BINARIES.into_iter().find(|binary| name == *binary);
+    if let Some(binary) = args
+        .first()
+        .and_then(|arg0| Path::new(arg0).file_name())
+        .and_then(find)
+    {
+        return Some((binary, &args[1..]));
+    }
+    match args {
+        [_, flag, binary, rest @ ..] if flag == DISPATCH_ARG => Some((find(binary)?, rest)),
+        _ => None,
+    }
+}
+
+/// What selected the version run in a directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    /// [`VERSION_ENV`]
+    Env,
+    /// A directory override set for this directory or an ancestor
+    Override { dir: PathBuf },
+    /// A project file in this directory or an ancestor
+    Project { file: PathBuf },
+    /// The active version
+    Active,
+}
+
+impl std::fmt::Display for Selection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Env => write!(f, "{} environment variable", VERSION_ENV),
+            Self::Override { dir } => write!(f, "directory override of {}", dir.display()),
+            Self::Project { file } => write!(f, "project file {}", file.display()),
+            Self::Active => write!(f, "active version"),
+        }
+    }
+}
+
+/// Key of the version to run in `cwd`: the one `override_version` names
+/// (from `AMP_VERSION`), else the directory override set for `cwd` or an
+/// ancestor, else the one the nearest project file pins, else the active
+/// version.
+pub fn resolve(
+    version_manager: &VersionManager,
+    cwd: &Path,
+    override_version: Option<&str>,
+) -> Result<(String, Selection)> {
+    if let Some(version) = override_version.filter(|version| !version.is_empty()) {
+        return match version_manager.resolve(version, None) {
+            Ok(key) => Ok((key, Selection::Env)),
+            Err(err) if is_not_installed(&err) => Err(DispatchError::OverrideNotInstalled {
+                version: version.to_string(),
+            }
+            .into()),
+            Err(err) => Err(err),
+        };
+    }
+    let overrides = Overrides::load(version_manager.config())?;
+    if let Some((dir, version)) = overrides.find(cwd) {
+        return match version_manager.resolve(version, None) {
+            Ok(key) => Ok((
+                key,
+                Selection::Override {
+                    dir: dir.to_path_buf(),
+                },
+            )),
+            Err(err) if is_not_installed(&err) => Err(OverrideError::NotInstalled {
+                version: version.to_string(),
+                dir: dir.to_path_buf(),
+            }
+            .into()),
+            Err(err) => Err(err),
+        };
+    }
+    let Some(pinned) = project::find(cwd)? else {
+        let current = version_manager
+            .get_current()?
+            .ok_or(VersionError::NoVersionsInstalled)?;
+        return Ok((current, Selection::Active));
+    };
+    match version_manager.resolve(&pinned.version, None) {
+        Ok(key) => Ok((key, Selection::Project { file: pinned.file })),
+        Err(err) if is_not_installed(&err) => Err(ProjectError::NotInstalled {
+            version: pinned.version,
+            file: pinned.file,
+        }
+        .into()),
+        Err(err) => Err(err),
+    }
+}
+
+fn is_not_installed(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<VersionError>(),
+        Some(VersionError::NotInstalled { .. })
+    )
+}
+
+/// Replace this process with `binary` of the version resolved for the
+/// current directory, passing `args` through. Only returns on failure.
+pub fn exec(binary: &str, args: &[OsString]) -> Result<()> {
+    let config = Config::new(std::env::var_os("AMP_DIR").map(PathBuf::from))?;
+    let version_manager = VersionManager::new(config);
+    let cwd = std::env::current_dir().context("Failed to get current directory")?;
+    let override_version = std::env::var(VERSION_ENV).ok();
+    let (key, _) = resolve(&version_manager, &cwd, override_version.as_deref())?;
+
+    let version_dir = version_manager.version_dir(&key);
+    // Usage is best-effort: a read-only store or a broken settings file
+    // mustn't keep the binary from running
+    let track_usage =
+        Settings::load(version_manager.config()).is_ok_and(|settings| settings.shims.track_usage);
+    if track_usage {
+        let now = unix_now();
+        let _ = VersionManifest::update(&version_dir, |manifest| manifest.last_used = Some(now));
+    }
+
+    let path = version_dir.join(binary);
+    let err = Command::new(&path).arg0(binary).args(args).exec();
+    Err(err).with_context(|| format!("Failed to run {}", path.display()))
+}
+
+/// Write the `bin/ampd` and `bin/ampctl` shims, replacing the symlinks
+/// earlier releases linked there.
+pub fn write_shims(config: &Config) -> Result<()> {
+    fs::create_dir_all(&config.bin_dir).context("Failed to create bin directory")?;
+    for binary in BINARIES {
+        let path = config.bin_dir.join(binary);
+        let script = shim_script(config, binary)?;
+        if !path.is_symlink() && fs::read_to_string(&path).ok().as_deref() == Some(&script) {
+            continue;
+        }
+        if path.exists() || path.is_symlink() {
+            fs::remove_file(&path).with_context(|| format!("Failed to replace {}", binary))?;
+        }
+        fs::write(&path, script).with_context(|| format!("Failed to write {} shim", binary))?;
+
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to set permissions on {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Script running `binary` through ampup, or the active version directly
+/// when ampup isn't next to it (e.g. a tree staged with `--dest-root`).
+/// With relative links, every path is relative to the script's directory.
+fn shim_script(config: &Config, binary: &str) -> Result<String> {
+    let (setup, ampup, versions, version_file) = if config.relative_links() {
+        (
+            "dir=\"$(dirname \"$0\")\"\n",
+            "\"$dir/ampup\"".to_string(),
+            "\"$dir/../versions\"".to_string(),
+            "\"$dir/../.version\"".to_string(),
+        )
+    } else {
+        // A development build runs itself until ampup is installed in bin/
+        let ampup = match config.ampup_binary_path() {
+            path if path.exists() => path,
+            _ => std::env::current_exe().context("Failed to get current executable path")?,
+        };
+        let quote = |path: &Path| shell_quote(&path.to_string_lossy());
+        (
+            "",
+            quote(&ampup),
+            quote(&config.versions_dir),
+            quote(&config.current_version_file()),
+        )
+    };
+    Ok(format!(
+        "#!/bin/sh\n\
+         # Generated by ampup: runs the {binary} version selected for the current directory\n\
+         {setup}\
+         if [ -x {ampup} ]; then\n    exec {ampup} {DISPATCH_ARG} {binary} \"$@\"\nfi\n\
+         exec {versions}/\"$(cat {version_file})\"/{binary} \"$@\"\n"
+    ))
+}
+
+/// Why `bin/ampd` or `bin/ampctl` wouldn't run the active version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShimProblem {
+    /// A symlink left by releases before the shims points at a removed
+    /// version.
+    Dangling { link: PathBuf, target: PathBuf },
+    /// The shim is gone while a version is active.
+    Missing { path: PathBuf },
+    /// The active version's directory is gone, e.g. deleted by hand.
+    VersionMissing { version: String, dir: PathBuf },
+    /// The active version's binary is gone from its directory.
+    BinaryMissing { version: String, path: PathBuf },
+}
+
+impl std::fmt::Display for ShimProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Dangling { link, target } => write!(
+                f,
+                "{} points at {}, which no longer exists",
+                link.display(),
+                target.display()
+            ),
+            Self::Missing { path } => write!(f, "{} is missing", path.display()),
+            Self::VersionMissing { version, dir } => write!(
+                f,
+                "Active version {} is no longer installed ({} is missing)",
+                version,
+                dir.display()
+            ),
+            Self::BinaryMissing { version, path } => write!(
+                f,
+                "Active version {} is missing {}",
+                version,
+                path.display()
+            ),
+        }
+    }
+}
+
+impl ShimProblem {
+    /// Commands that fix the problem, given the installed versions.
+    pub fn fix(&self, installed: &[String]) -> String {
+        let version = match self {
+            Self::VersionMissing { version, .. } | Self::BinaryMissing { version, .. } => {
+                Some(version)
+            }
+            Self::Dangling { .. } | Self::Missing { .. } => None,
+        };
+        // Activating any working version rewrites the shims
+        let switch = installed
+            .iter()
+            .rfind(|key| Some(*key) != version)
+            .map(|key| format!("ampup use {}", key));
+        let reinstall = version.and_then(|version| match split_key(version) {
+            (Some(LOCAL_NAMESPACE), _) => None,
+            (_, bare) if installed.contains(version) => Some(format!(
+                "ampup uninstall {} --force && ampup install {}",
+                version, bare
+            )),
+            (_, bare) => Some(format!("ampup install {}", bare)),
+        });
+        let fixes: Vec<String> = reinstall.into_iter().chain(switch).collect();
+        match fixes.is_empty() {
+            true => "ampup install".to_string(),
+            false => fixes.join(", or "),
+        }
+    }
+}
+
+/// Check that `bin/ampd` and `bin/ampctl` would run the active version:
+/// no dangling links, no missing shims and no missing binaries.
+pub fn check_shims(version_manager: &VersionManager) -> Result<Vec<ShimProblem>> {
+    let config = version_manager.config();
+    let current = version_manager.get_current()?;
+    let mut problems = Vec::new();
+
+    // A missing version is reported once rather than per binary
+    let mut current_dir = None;
+    if let Some(version) = &current {
+        let dir = version_manager.version_dir(version);
+        if dir.exists() {
+            current_dir = Some(dir);
+        } else {
+            problems.push(ShimProblem::VersionMissing {
+                version: version.clone(),
+                dir,
+            });
+        }
+    }
+
+    for binary in BINARIES {
+        let path = config.bin_dir.join(binary);
+        if path.is_symlink() && !path.exists() {
+            let target = fs::read_link(&path).context("Failed to read link")?;
+            problems.push(ShimProblem::Dangling { link: path, target });
+        } else if !path.exists() && current.is_some() {
+            problems.push(ShimProblem::Missing { path });
+        } else if let (Some(version), Some(dir)) = (&current, &current_dir)
+            && !dir.join(binary).exists()
+        {
+            problems.push(ShimProblem::BinaryMissing {
+                version: version.clone(),
+                path: dir.join(binary),
+            });
+        }
+    }
+    Ok(problems)
+}
+
+/// Warn about each of `problems` with how to fix it.
+pub fn warn_shim_problems(problems: &[ShimProblem], installed: &[String]) {
+    for problem in problems {
+        ui::warn!("{}", problem);
+        ui::detail!("Try: {}", problem.fix(installed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn install(config: &Config, key: &str) {
+        let dir = config.versions_dir.join(key);
+        fs::create_dir_all(&dir).expect("should create version directory");
+        fs::write(dir.join("ampd"), "").expect("should write ampd");
+        fs::write(dir.join("ampctl"), "").expect("should write ampctl");
+    }
+
+    #[test]
+    fn resolve_with_project_file_returns_pinned_version_over_active() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config = Config::new(Some(dir.path().join(".amp"))).expect("should create config");
+        config.ensure_dirs().expect("should create directories");
+        install(&config, "edgeandnode-amp/v0.3.0");
+        install(&config, "edgeandnode-amp/v0.4.0");
+        let version_manager = VersionManager::new(config);
+        version_manager
+            .activate("edgeandnode-amp/v0.4.0")
+            .expect("should activate");
+        let project_dir = dir.path().join("project");
+        fs::create_dir_all(&project_dir).expect("should create project");
+        project::write(&project_dir, "v0.3.0").expect("should pin version");
+
+        //* When
+        let (key, selection) =
+            resolve(&version_manager, &project_dir, None).expect("should resolve");
+
+        //* Then
+        assert_eq!(key, "edgeandnode-amp/v0.3.0");
+        assert_eq!(
+            selection,
+            Selection::Project {
+                file: project_dir.join(project::VERSION_FILE)
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_with_override_prefers_it_over_project_file() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config = Config::new(Some(dir.path().join(".amp"))).expect("should create config");
+        config.ensure_dirs().expect("should create directories");
+        install(&config, "edgeandnode-amp/v0.3.0");
+        install(&config, "edgeandnode-amp/v0.4.0");
+        let version_manager = VersionManager::new(config);
+        project::write(dir.path(), "v0.3.0").expect("should pin version");
+
+        //* When
+        let (key, selection) =
+            resolve(&version_manager, dir.path(), Some("v0.4.0")).expect("should resolve");
+
+        //* Then
+        assert_eq!(key, "edgeandnode-amp/v0.4.0");
+        assert_eq!(selection, Selection::Env);
+    }
+
+    #[test]
+    fn resolve_with_override_not_installed_fails_naming_the_variable() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config = Config::new(Some(dir.path().join(".amp"))).expect("should create config");
+        config.ensure_dirs().expect("should create directories");
+        install(&config, "edgeandnode-amp/v0.3.0");
+        let version_manager = VersionManager::new(config);
+
+        //* When
+        let result = resolve(&version_manager, dir.path(), Some("v0.9.0"));
+
+        //* Then
+        let err = result.expect_err("missing version should fail");
+        assert!(matches!(
+            err.downcast_ref::<DispatchError>(),
+            Some(DispatchError::OverrideNotInstalled { version }) if version == "v0.9.0"
+        ));
+    }
+
+    #[test]
+    fn resolve_with_directory_override_prefers_it_over_project_file() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config = Config::new(Some(dir.path().join(".amp"))).expect("should create config");
+        config.ensure_dirs().expect("should create directories");
+        install(&config, "edgeandnode-amp/v0.3.0");
+        install(&config, "edgeandnode-amp/v0.4.0");
+        let project_dir = dir.path().join("project");
+        fs::create_dir_all(project_dir.join("src")).expect("should create project");
+        project::write(&project_dir, "v0.3.0").expect("should pin version");
+        let mut overrides = Overrides::default();
+        overrides.set(&project_dir, "edgeandnode-amp/v0.4.0");
+        overrides.save(&config).expect("should save overrides");
+        let version_manager = VersionManager::new(config);
+
+        //* When
+        let (key, selection) =
+            resolve(&version_manager, &project_dir.join("src"), None).expect("should resolve");
+
+        //* Then
+        assert_eq!(key, "edgeandnode-amp/v0.4.0");
+        assert_eq!(
+            selection,
+            Selection::Override {
+                dir: fs::canonicalize(&project_dir).expect("should canonicalize")
+            }
+        );
+    }
+
+    #[test]
+    fn parse_with_shim_arguments_returns_binary_and_forwarded_args() {
+        //* Given
+        let args: Vec<OsString> = ["/home/amp/.amp/bin/ampup", "--dispatch", "ampctl", "--help"]
+            .map(OsString::from)
+            .to_vec();
+
+        //* When
+        let parsed = parse(&args);
+
+        //* Then
+        assert_eq!(parsed, Some(("ampctl", &args[3..])));
+        assert_eq!(parse(&args[..1]), None);
+    }
+
+    #[test]
+    fn resolve_with_pinned_version_not_installed_fails_with_install_hint() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config = Config::new(Some(dir.path().join(".amp"))).expect("should create config");
+        config.ensure_dirs().expect("should create directories");
+        let version_manager = VersionManager::new(config);
+        project::write(dir.path(), "v0.9.0").expect("should pin version");
+
+        //* When
+        let result = resolve(&version_manager, dir.path(), None);
+
+        //* Then
+        let err = result.expect_err("missing version should fail");
+        assert!(matches!(
+            err.downcast_ref::<ProjectError>(),
+            Some(ProjectError::NotInstalled { version, .. }) if version == "v0.9.0"
+        ));
+    }
+
+    #[test]
+    fn check_shims_with_removed_active_version_reports_it_once() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config = Config::new(Some(dir.path().join(".amp"))).expect("should create config");
+        config.ensure_dirs().expect("should create directories");
+        install(&config, "edgeandnode-amp/v0.4.0");
+        let version_manager = VersionManager::new(config);
+        version_manager
+            .activate("edgeandnode-amp/v0.4.0")
+            .expect("should activate");
+        let healthy = check_shims(&version_manager).expect("should check shims");
+        fs::remove_dir_all(version_manager.version_dir("edgeandnode-amp/v0.4.0"))
+            .expect("should remove version");
+
+        //* When
+        let problems = check_shims(&version_manager).expect("should check shims");
+
+        //* Then
+        assert!(healthy.is_empty(), "unexpected problems: {:?}", healthy);
+        assert_eq!(problems.len(), 1);
+        assert!(matches!(
+            &problems[0],
+            ShimProblem::VersionMissing { version, .. } if version == "edgeandnode-amp/v0.4.0"
+        ));
+        assert_eq!(problems[0].fix(&[]), "ampup install v0.4.0");
+    }
+}
@@ -0,0 +1,133 @@
+use std::io::IsTerminal;
+
+use anyhow::Result;
+use dialoguer::{Confirm, theme::ColorfulTheme};
+use semver::Version;
+
+use crate::{resolver::tag_version, ui, version_store::split_key};
+
+#[derive(Debug)]
+pub enum DowngradeError {
+    /// Activating an older version was neither allowed nor confirmed.
+    NotConfirmed { from: String, to: String },
+}
+
+impl std::fmt::Display for DowngradeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotConfirmed { from, to } => {
+                writeln!(f, "Refusing to downgrade without confirmation")?;
+                writeln!(f, "  From: {}", from)?;
+                writeln!(f, "  To: {}", to)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Older versions may not read state written by newer ones."
+                )?;
+                write!(f, "  To downgrade anyway, pass --allow-downgrade.")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DowngradeError {}
+
+/// How far back version key `to` is from `from` (e.g. `2 minor versions`),
+/// or `None` unless `to` is an older release of the same repository. Source
+/// builds and other repositories are never downgrades.
+pub fn downgrade_delta(from: &str, to: &str) -> Option<String> {
+    let (from_namespace, from_version) = split_key(from);
+    let (to_namespace, to_version) = split_key(to);
+    if from_namespace != to_namespace {
+        return None;
+    }
+    let (from, to) = (tag_version(from_version)?, tag_version(to_version)?);
+    (to < from).then(|| delta(&from, &to))
+}
+
+fn delta(from: &Version, to: &Version) -> String {
+    let (count, kind) = if from.major != to.major {
+        (from.major - to.major, "major")
+    } else if from.minor != to.minor {
+        (from.minor - to.minor, "minor")
+    } else if from.patch != to.patch {
+        (from.patch - to.patch, "patch")
+    } else {
+        return "an earlier prerelease".to_string();
+    };
+    format!(
+        "{} {} version{}",
+        count,
+        kind,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+/// Check activating version key `to` in place of the active `from`: a
+/// downgrade is warned about with its delta and then needs `allow` or an
+/// interactive confirmation, failing with [`DowngradeError::NotConfirmed`]
+/// otherwise.
+pub fn confirm(from: Option<&str>, to: &str, allow: bool) -> Result<()> {
+    let Some(from) = from else {
+        return Ok(());
+    };
+    let Some(delta) = downgrade_delta(from, to) else {
+        return Ok(());
+    };
+
+    ui::warn!(
+        "Downgrading {} → {} ({} back)",
+        ui::version(from),
+        ui::version(to),
+        delta
+    );
+    if allow {
+        return Ok(());
+    }
+    let confirmed = std::io::stdin().is_terminal()
+        && Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Activate the older version?")
+            .default(false)
+            .interact()?;
+    if !confirmed {
+        return Err(DowngradeError::NotConfirmed {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downgrade_delta_with_older_release_names_largest_change() {
+        //* Then
+        assert_eq!(
+            downgrade_delta("edgeandnode-amp/v0.10.0", "edgeandnode-amp/v0.8.2").as_deref(),
+            Some("2 minor versions")
+        );
+        assert_eq!(
+            downgrade_delta("edgeandnode-amp/v1.0.0", "edgeandnode-amp/v1.0.0-rc.2").as_deref(),
+            Some("an earlier prerelease")
+        );
+    }
+
+    #[test]
+    fn downgrade_delta_with_upgrade_or_other_namespace_returns_none() {
+        //* Then
+        assert!(downgrade_delta("edgeandnode-amp/v0.9.2", "edgeandnode-amp/v0.10.0").is_none());
+        assert!(
+            downgrade_delta("edgeandnode-amp/v0.10.0", "myorg-amp/v0.9.2").is_none(),
+            "another repository's release is not a downgrade"
+        );
+        assert!(
+            downgrade_delta("edgeandnode-amp/v0.10.0", "edgeandnode-amp/main-abc1234").is_none(),
+            "a source build is not a downgrade"
+        );
+    }
+}
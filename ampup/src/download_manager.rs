@@ -1,27 +1,45 @@
 use std::{
+    ffi::OsString,
     path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result};
 use fs_err as fs;
-use tokio::{sync::Semaphore, task::JoinSet};
+use sha2::{Digest, Sha256};
+use tokio::task::JoinSet;
 
 use crate::{
-    github::{GitHubClient, ResolvedAsset},
+    archive,
+    artifact_cache::ArtifactCache,
+    executable::ExecutableFormat,
+    heartbeat::Heartbeat,
+    platform::{Architecture, Platform},
+    policy::PolicyError,
+    preallocate,
     progress::ProgressReporter,
+    release_source::{ReleaseSource, ResolvedAsset},
+    scheduler::DownloadScheduler,
+    ui,
 };
 
+/// Bytes fed to the hasher at a time when verifying an artifact.
+const HASH_CHUNK_SIZE: usize = 1024 * 1024;
+
 // ---------------------------------------------------------------------------
 // Public types
 // ---------------------------------------------------------------------------
 
-/// A single artifact to download from a GitHub release.
+/// A single artifact to download from a release.
 pub struct DownloadTask {
     /// GitHub release asset name (e.g., "ampd-linux-x86_64")
     pub artifact_name: String,
     /// Destination filename inside the version directory (e.g., "ampd")
     pub dest_filename: String,
+    /// Platform and architecture the binary must run on, checked against its
+    /// header before it's written; `None` skips the check
+    pub target: Option<(Platform, Architecture)>,
 }
 
 /// Errors that occur during bounded-concurrent download operations.
@@ -46,6 +64,17 @@ pub enum DownloadError {
         source: anyhow::Error,
     },
 
+    /// The source reports one or more resolved assets as gone.
+    ///
+    /// Detected by probing every asset before any download starts, so a
+    /// release whose assets were removed (e.g. by a retention policy) fails
+    /// immediately instead of after downloading part of the release.
+    AssetsUnavailable {
+        version: String,
+        source: String,
+        missing: Vec<String>,
+    },
+
     /// Downloaded artifact was empty (zero bytes).
     ///
     /// The HTTP request succeeded but the response body contained no data.
@@ -53,6 +82,38 @@ pub enum DownloadError {
     /// rather than a network issue.
     EmptyArtifact { artifact_name: String },
 
+    /// Downloaded artifact does not match the digest published by the source.
+    ///
+    /// Only checked when the source provides a digest (OCI layers, GitHub
+    /// assets with a `digest` field). Indicates a corrupted or tampered
+    /// download; nothing is installed.
+    DigestMismatch {
+        artifact_name: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// A downloaded archive could not be unpacked.
+    ///
+    /// Releases may publish `.tar.gz` or `.zip` archives instead of bare
+    /// binaries; the archive was corrupt or didn't contain the binary.
+    Extract {
+        artifact_name: String,
+        source: anyhow::Error,
+    },
+
+    /// The binary was built for another platform or architecture, or isn't
+    /// an executable at all.
+    ///
+    /// Read from the ELF or Mach-O header before the binary is written, so a
+    /// mispackaged release or a wrong `--arch` fails here instead of with
+    /// "exec format error" when the binary is first run.
+    TargetMismatch {
+        artifact_name: String,
+        expected: String,
+        found: String,
+    },
+
     /// Failed to write an artifact to the staging directory.
     ///
     /// The download and verification succeeded, but writing the artifact data
@@ -86,6 +147,25 @@ impl std::fmt::Display for DownloadError {
                 writeln!(f, "  The download was retried once and still failed.")?;
                 write!(f, "  Check your network connection and try again.")?;
             }
+            Self::AssetsUnavailable {
+                version,
+                source,
+                missing,
+            } => {
+                writeln!(f, "Release assets are no longer available")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Source: {}", source)?;
+                writeln!(f, "  Missing:")?;
+                for name in missing {
+                    writeln!(f, "    - {}", name)?;
+                }
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Nothing was downloaded. To install this version anyway,"
+                )?;
+                write!(f, "  build it from source: ampup build --tag {}", version)?;
+            }
             Self::EmptyArtifact { artifact_name } => {
                 writeln!(f, "Downloaded artifact is empty")?;
                 writeln!(f, "  Artifact: {}", artifact_name)?;
@@ -99,6 +179,48 @@ impl std::fmt::Display for DownloadError {
                     "  This may indicate a problem with the release packaging."
                 )?;
             }
+            Self::DigestMismatch {
+                artifact_name,
+                expected,
+                actual,
+            } => {
+                writeln!(f, "Downloaded artifact failed digest verification")?;
+                writeln!(f, "  Artifact: {}", artifact_name)?;
+                writeln!(f, "  Expected: {}", expected)?;
+                writeln!(f, "  Actual: {}", actual)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  The download may be corrupted or tampered with. Nothing was installed."
+                )?;
+            }
+            Self::Extract {
+                artifact_name,
+                source,
+            } => {
+                writeln!(f, "Failed to extract downloaded archive")?;
+                writeln!(f, "  Artifact: {}", artifact_name)?;
+                write!(f, "  Error: {}", source)?;
+            }
+            Self::TargetMismatch {
+                artifact_name,
+                expected,
+                found,
+            } => {
+                writeln!(f, "Downloaded binary does not match the target platform")?;
+                writeln!(f, "  Artifact: {}", artifact_name)?;
+                writeln!(f, "  Expected: {}", expected)?;
+                writeln!(f, "  Found: {}", found)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Nothing was installed. If --platform or --arch was given, check it"
+                )?;
+                write!(
+                    f,
+                    "  matches this machine; otherwise the release may be mispackaged."
+                )?;
+            }
             Self::StagingWrite {
                 artifact_name,
                 path,
@@ -121,9 +243,13 @@ impl std::fmt::Display for DownloadError {
 impl std::error::Error for DownloadError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Self::TaskFailed { source, .. } => Some(source.as_ref()),
+            Self::TaskFailed { source, .. } | Self::Extract { source, .. } => Some(source.as_ref()),
             Self::StagingWrite { source, .. } => Some(source),
-            Self::EmptyArtifact { .. } | Self::SemaphoreClosed { .. } => None,
+            Self::AssetsUnavailable { .. }
+            | Self::EmptyArtifact { .. }
+            | Self::DigestMismatch { .. }
+            | Self::TargetMismatch { .. }
+            | Self::SemaphoreClosed { .. } => None,
         }
     }
 }
@@ -134,8 +260,11 @@ impl std::error::Error for DownloadError {
 
 /// Manages bounded-concurrent downloads of release artifacts.
 ///
-/// Downloads proceed in parallel up to `max_concurrent` tasks. Each task
-/// downloads an artifact, verifies it (currently: non-empty check), and
+/// Downloads proceed in parallel up to `max_concurrent` tasks, gated by a
+/// [`DownloadScheduler`] that may be shared across managers, and connections
+/// left over are used to split each artifact into ranged chunks (e.g. 8 jobs
+/// for 2 artifacts fetch each in 4 chunks). Each task
+/// downloads an artifact, verifies it (non-empty, digest when known), and
 /// writes it to a staging directory. Only after all tasks succeed does
 /// the staging directory get atomically renamed to the final version
 /// directory.
@@ -143,22 +272,128 @@ impl std::error::Error for DownloadError {
 /// If any task fails (after one retry), all in-flight tasks are cancelled
 /// and the staging directory is cleaned up automatically via `TempDir` drop.
 pub struct DownloadManager {
-    github: GitHubClient,
+    source: Arc<dyn ReleaseSource>,
+    scheduler: Arc<DownloadScheduler>,
     max_concurrent: usize,
+    cache: Option<Arc<ArtifactCache>>,
+    delta_base: Option<Arc<DeltaBase>>,
+    require_digests: bool,
+}
+
+/// An installed version that binary patches can be applied to.
+struct DeltaBase {
+    version: String,
+    dir: PathBuf,
 }
 
 impl DownloadManager {
-    /// Create a new download manager.
+    /// Create a new download manager with its own scheduler.
     ///
     /// `max_concurrent` is clamped to a minimum of 1 to prevent deadlocks.
     /// Pass 1 for sequential downloads (useful for debugging).
-    pub fn new(github: GitHubClient, max_concurrent: usize) -> Self {
+    pub fn new(source: Arc<dyn ReleaseSource>, max_concurrent: usize) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        let scheduler = Arc::new(DownloadScheduler::new(max_concurrent, max_concurrent));
+        Self::with_scheduler(source, scheduler, max_concurrent)
+    }
+
+    /// Create a download manager that takes slots from a shared scheduler, so
+    /// several managers in one batch stay under a single concurrency cap.
+    ///
+    /// `max_concurrent` still bounds this manager's chunking (see
+    /// [`DownloadManager::download_all`]).
+    pub fn with_scheduler(
+        source: Arc<dyn ReleaseSource>,
+        scheduler: Arc<DownloadScheduler>,
+        max_concurrent: usize,
+    ) -> Self {
         Self {
-            github,
+            source,
+            scheduler,
             max_concurrent: max_concurrent.max(1),
+            cache: None,
+            delta_base: None,
+            require_digests: false,
         }
     }
 
+    /// Try patching the binaries of installed `version` in `dir` before
+    /// downloading in full.
+    ///
+    /// A release may publish `<artifact>.from-<version>.zst` patches, made
+    /// with `zstd --patch-from`. A patch is only used once it matches its
+    /// published digest and the rebuilt binary matches the full artifact's
+    /// digest (when the full artifact is a bare binary); otherwise the full
+    /// artifact is downloaded.
+    pub fn with_delta_base(mut self, version: &str, dir: PathBuf) -> Self {
+        self.delta_base = Some(Arc::new(DeltaBase {
+            version: version.to_string(),
+            dir,
+        }));
+        self
+    }
+
+    /// Refuse to download artifacts the source publishes no digest for.
+    pub fn with_require_digests(mut self, require_digests: bool) -> Self {
+        self.require_digests = require_digests;
+        self
+    }
+
+    /// Reuse artifacts with a published digest from `cache`, and store
+    /// newly downloaded ones in it.
+    pub fn with_cache(mut self, cache: ArtifactCache) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Download tasks for assets a release may not publish into `dir`, one
+    /// at a time, returning the names of the files written.
+    ///
+    /// Missing assets are skipped silently and failed ones with a warning,
+    /// so they never fail an install.
+    pub async fn download_optional(
+        &self,
+        tasks: Vec<DownloadTask>,
+        version: &str,
+        dir: &Path,
+    ) -> Vec<String> {
+        let mut written = Vec::new();
+        for task in tasks {
+            let Some(asset) = self
+                .source
+                .resolve_assets(version, &[&task.artifact_name])
+                .await
+                .ok()
+                .and_then(|mut assets| assets.pop())
+            else {
+                continue;
+            };
+            // Sources that can't list assets (mirrors) resolve any name
+            if !self.source.asset_available(&asset).await {
+                continue;
+            }
+            if self.require_digests && asset.digest.is_none() {
+                ui::warn!("Skipping {}: no published digest", task.artifact_name);
+                continue;
+            }
+
+            let result = async {
+                let data = self.source.download_asset(&asset, 1).await?;
+                verify_artifact(&task.artifact_name, asset.digest.as_deref(), &data)?;
+                let data = unpack(&task, data)?;
+                fs::create_dir_all(dir).context("Failed to create directory")?;
+                write_to_staging(dir, &task.dest_filename, &data)?;
+                anyhow::Ok(())
+            }
+            .await;
+            match result {
+                Ok(()) => written.push(task.dest_filename),
+                Err(err) => ui::warn!("Skipping {}: {}", task.artifact_name, err),
+            }
+        }
+        written
+    }
+
     /// Download all tasks concurrently and write results to `version_dir`.
     ///
     /// Fetches release metadata once, then spawns bounded-concurrent download
@@ -176,15 +411,43 @@ impl DownloadManager {
         // Resolve all asset metadata with a single API call so that each
         // spawned task can download directly without re-fetching the release.
         let asset_names: Vec<&str> = tasks.iter().map(|t| t.artifact_name.as_str()).collect();
-        let resolved = self
-            .github
-            .resolve_release_assets(version, &asset_names)
-            .await?;
+        let resolved = self.source.resolve_assets(version, &asset_names).await?;
+        if self.require_digests
+            && let Some(asset) = resolved.iter().find(|asset| asset.digest.is_none())
+        {
+            return Err(PolicyError::MissingDigest {
+                artifact_name: asset.name.clone(),
+            }
+            .into());
+        }
+
+        // Probe every asset before downloading any, so removed assets fail
+        // fast instead of after the first artifact has been fetched.
+        let available =
+            futures::future::join_all(resolved.iter().map(|a| self.source.asset_available(a)))
+                .await;
+        let missing: Vec<String> = resolved
+            .iter()
+            .zip(available)
+            .filter(|(_, available)| !available)
+            .map(|(asset, _)| asset.name.clone())
+            .collect();
+        if !missing.is_empty() {
+            return Err(DownloadError::AssetsUnavailable {
+                version: version.to_string(),
+                source: self.source.describe(),
+                missing,
+            }
+            .into());
+        }
 
         let parent = version_dir.parent().ok_or_else(|| {
             anyhow::anyhow!("version_dir has no parent: {}", version_dir.display())
         })?;
 
+        // The parent is a repository namespace that may not exist yet
+        fs::create_dir_all(parent).context("Failed to create versions directory")?;
+
         // Staging dir in the same parent ensures same filesystem for atomic rename
         let staging_dir =
             tempfile::tempdir_in(parent).context("Failed to create staging directory")?;
@@ -192,38 +455,85 @@ impl DownloadManager {
         let names: Vec<String> = tasks.iter().map(|t| t.artifact_name.clone()).collect();
         reporter.set_total(tasks.len(), names);
 
-        let semaphore = Arc::new(Semaphore::new(self.max_concurrent));
-        let mut join_set: JoinSet<std::result::Result<String, DownloadError>> = JoinSet::new();
+        let chunks = (self.max_concurrent / tasks.len().max(1)).max(1);
+        let mut join_set: JoinSet<std::result::Result<(String, ArtifactTimings), DownloadError>> =
+            JoinSet::new();
 
         for (task, asset) in tasks.into_iter().zip(resolved) {
-            let github = self.github.clone();
-            let sem = semaphore.clone();
+            let source = self.source.clone();
+            let scheduler = self.scheduler.clone();
             let staging_path = staging_dir.path().to_path_buf();
             let reporter = reporter.clone();
+            let cache = self.cache.clone();
+            let delta_base = self.delta_base.clone();
+            let version = version.to_string();
 
             join_set.spawn(async move {
-                let _permit = sem
-                    .acquire()
-                    .await
-                    .map_err(|_| DownloadError::SemaphoreClosed {
+                let mut timings = ArtifactTimings::default();
+                let started = Instant::now();
+                let cached = cache
+                    .as_deref()
+                    .zip(asset.digest.as_deref())
+                    .and_then(|(cache, digest)| cache.get(digest));
+                if let Some(data) = cached {
+                    reporter.component_started(&task.artifact_name);
+                    timings.origin = "read from cache";
+                    timings.fetch = started.elapsed();
+                    let data = unpack(&task, data)?;
+                    check_target(&task, &data)?;
+                    let written = Instant::now();
+                    write_to_staging(&staging_path, &task.dest_filename, &data)?;
+                    timings.write = written.elapsed();
+                    return Ok((task.artifact_name, timings));
+                }
+
+                reporter.component_queued(&task.artifact_name, scheduler.queued());
+                let _slot = scheduler.acquire(&asset.url).await.map_err(|_| {
+                    DownloadError::SemaphoreClosed {
                         artifact_name: task.artifact_name.clone(),
-                    })?;
+                    }
+                })?;
 
                 reporter.component_started(&task.artifact_name);
+                let started = Instant::now();
+
+                if let Some(base) = &delta_base
+                    && let Some(data) =
+                        download_patched(source.as_ref(), base, &version, &task, &asset).await
+                {
+                    timings.origin = "patched";
+                    timings.fetch = started.elapsed();
+                    check_target(&task, &data)?;
+                    let written = Instant::now();
+                    write_to_staging(&staging_path, &task.dest_filename, &data)?;
+                    timings.write = written.elapsed();
+                    return Ok((task.artifact_name, timings));
+                }
 
-                let data = download_with_retry(&github, &asset).await?;
-                verify_artifact(&task.artifact_name, &data)?;
-                write_to_staging(&staging_path, &task.dest_filename, &data)?;
+                let data = download_with_retry(source.as_ref(), &asset, chunks).await?;
+                timings.fetch = started.elapsed();
+                verify_and_stage(
+                    &task,
+                    &asset,
+                    data,
+                    &staging_path,
+                    cache.as_deref(),
+                    &mut timings,
+                )
+                .await?;
 
-                Ok(task.artifact_name)
+                Ok((task.artifact_name, timings))
             });
         }
 
         // Collect results — fail fast on first error
+        let _heartbeat = Heartbeat::start(format!("downloading {}", version));
+        let mut timings = Vec::new();
         while let Some(result) = join_set.join_next().await {
             match result {
-                Ok(Ok(artifact_name)) => {
+                Ok(Ok((artifact_name, artifact_timings))) => {
                     reporter.component_completed(&artifact_name);
+                    timings.push((artifact_name, artifact_timings));
                 }
                 Ok(Err(e)) => {
                     let artifact_name = download_error_artifact_name(&e);
@@ -241,6 +551,12 @@ impl DownloadManager {
         }
 
         reporter.finish();
+        if ui::timings() {
+            timings.sort_by(|a, b| a.0.cmp(&b.0));
+            for (artifact_name, artifact_timings) in &timings {
+                ui::detail!("{}: {}", artifact_name, artifact_timings);
+            }
+        }
 
         // Set executable permissions on all staged files
         #[cfg(unix)]
@@ -249,12 +565,7 @@ impl DownloadManager {
         // Safe replacement: rename existing dir to backup, swap staging in, then
         // remove backup. If the swap fails, restore from backup so the previous
         // install is never lost.
-        //
-        // NOTE: `with_extension("old")` is wrong for semver names because it
-        // replaces the last dotted segment (v0.1.0 → v0.1.old), causing
-        // collisions across patch versions. Instead, append ".old" to the full
-        // directory name.
-        let backup_dir = append_extension(&version_dir, "old");
+        let backup_dir = backup_path(&version_dir);
         // Remove stale backup from a previous crashed install so the backup
         // rename below doesn't fail with ENOTEMPTY.
         if backup_dir.exists() {
@@ -308,17 +619,18 @@ impl DownloadManager {
 // Private helpers
 // ---------------------------------------------------------------------------
 
-/// Append an extension to a path without replacing an existing dotted segment.
+/// Hidden sibling a reinstall moves `version_dir` to until the new version is
+/// in place (e.g. `v0.1.0` → `.v0.1.0.old`).
 ///
 /// Unlike [`Path::with_extension`], which replaces the last dotted segment
-/// (e.g. `v0.1.0` → `v0.1.old`), this function appends to the full name
-/// (e.g. `v0.1.0` → `v0.1.0.old`). This is critical for semver-style
-/// directory names where dot-separated components are not file extensions.
-fn append_extension(path: &Path, ext: &str) -> PathBuf {
-    let mut s = path.as_os_str().to_os_string();
-    s.push(".");
-    s.push(ext);
-    PathBuf::from(s)
+/// (e.g. `v0.1.0` → `v0.1.old`), this appends to the full name, so patch
+/// versions of one minor don't share a backup. The leading dot keeps it apart
+/// from versions whose own name ends in `.old`.
+fn backup_path(version_dir: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(version_dir.file_name().unwrap_or_default());
+    name.push(".old");
+    version_dir.with_file_name(name)
 }
 
 /// Download a resolved asset with one retry on failure.
@@ -328,16 +640,17 @@ fn append_extension(path: &Path, ext: &str) -> PathBuf {
 /// so a rate-limited request will have been retried there before surfacing
 /// as an error here.
 async fn download_with_retry(
-    github: &GitHubClient,
+    source: &dyn ReleaseSource,
     asset: &ResolvedAsset,
+    chunks: usize,
 ) -> std::result::Result<Vec<u8>, DownloadError> {
-    match github.download_resolved_asset(asset).await {
+    match source.download_asset(asset, chunks).await {
         Ok(data) => Ok(data),
         Err(first_err) => {
             crate::ui::warn!("Download failed for {}, retrying once...", asset.name);
 
-            github
-                .download_resolved_asset(asset)
+            source
+                .download_asset(asset, chunks)
                 .await
                 .map_err(|retry_err| DownloadError::TaskFailed {
                     artifact_name: asset.name.clone(),
@@ -348,17 +661,229 @@ async fn download_with_retry(
     }
 }
 
-/// Verify a downloaded artifact. Currently checks non-empty.
-/// Per-artifact checksum/attestation verification will be added in a follow-up PR.
-fn verify_artifact(artifact_name: &str, data: &[u8]) -> std::result::Result<(), DownloadError> {
+/// Rebuild the task's binary from `base` and a published patch, or `None`
+/// when there is no usable patch and the full artifact must be downloaded.
+async fn download_patched(
+    source: &dyn ReleaseSource,
+    base: &DeltaBase,
+    version: &str,
+    task: &DownloadTask,
+    full: &ResolvedAsset,
+) -> Option<Vec<u8>> {
+    let patch_name = format!("{}.from-{}.zst", task.artifact_name, base.version);
+    let installed = fs::read(base.dir.join(&task.dest_filename)).ok()?;
+    let patch = source
+        .resolve_assets(version, &[&patch_name])
+        .await
+        .ok()?
+        .pop()
+        .filter(|asset| asset.name == patch_name)?;
+    // Sources that can't list assets (mirrors) resolve any name
+    if !source.asset_available(&patch).await {
+        return None;
+    }
+
+    let result = async {
+        let data = source.download_asset(&patch, 1).await?;
+        verify_artifact(&patch_name, patch.digest.as_deref(), &data)?;
+        let binary = archive::apply_patch(&installed, &data)?;
+        // Digests of compressed or archived artifacts don't describe the binary
+        if full.name == task.artifact_name {
+            verify_artifact(&task.artifact_name, full.digest.as_deref(), &binary)?;
+        }
+        anyhow::Ok(binary)
+    }
+    .await;
+
+    match result {
+        Ok(binary) => {
+            crate::ui::detail!("Patched {} from {}", task.dest_filename, base.version);
+            Some(binary)
+        }
+        Err(err) => {
+            crate::ui::warn!(
+                "Patch {} could not be applied ({}), downloading in full",
+                patch_name,
+                err
+            );
+            None
+        }
+    }
+}
+
+/// Verify a downloaded artifact is non-empty and, when the source published
+/// one, matches its `sha256:` digest. Digests in other algorithms are skipped.
+fn verify_artifact(
+    artifact_name: &str,
+    digest: Option<&str>,
+    data: &[u8],
+) -> std::result::Result<(), DownloadError> {
     if data.is_empty() {
         return Err(DownloadError::EmptyArtifact {
             artifact_name: artifact_name.to_string(),
         });
     }
+
+    if let Some(expected) = digest.and_then(|d| d.strip_prefix("sha256:")) {
+        let actual = sha256_hex(data);
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(DownloadError::DigestMismatch {
+                artifact_name: artifact_name.to_string(),
+                expected: format!("sha256:{}", expected),
+                actual: format!("sha256:{}", actual),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Verify a downloaded artifact, add it to the cache and write its binary to
+/// the staging directory.
+///
+/// The digest is computed on the blocking pool. A bare binary is written
+/// while it's hashed, so verification adds little wall time, and is removed
+/// again on a mismatch; compressed and archived artifacts are only unpacked
+/// once verified.
+async fn verify_and_stage(
+    task: &DownloadTask,
+    asset: &ResolvedAsset,
+    data: Vec<u8>,
+    staging_path: &Path,
+    cache: Option<&ArtifactCache>,
+    timings: &mut ArtifactTimings,
+) -> std::result::Result<(), DownloadError> {
+    if data.is_empty() {
+        return Err(DownloadError::EmptyArtifact {
+            artifact_name: task.artifact_name.clone(),
+        });
+    }
+    let task_failed = |err: tokio::task::JoinError| DownloadError::TaskFailed {
+        artifact_name: task.artifact_name.clone(),
+        source: err.into(),
+    };
+
+    let data = Arc::new(data);
+    let started = Instant::now();
+    let expected = asset
+        .digest
+        .as_deref()
+        .and_then(|digest| digest.strip_prefix("sha256:"));
+    let hashing = expected.map(|_| {
+        let data = data.clone();
+        tokio::task::spawn_blocking(move || sha256_hex(&data))
+    });
+
+    let overlapped = hashing.is_some() && !archive::is_packed(&data);
+    if overlapped {
+        check_target(task, &data)?;
+        let written = Instant::now();
+        write_to_staging_blocking(staging_path, &task.dest_filename, data.clone())
+            .await
+            .map_err(task_failed)??;
+        timings.write = written.elapsed();
+        timings.overlapped = true;
+    }
+    if let (Some(hashing), Some(expected)) = (hashing, expected) {
+        let actual = hashing.await.map_err(task_failed)?;
+        timings.verify = started.elapsed();
+        if !actual.eq_ignore_ascii_case(expected) {
+            let _ = fs::remove_file(staging_path.join(&task.dest_filename));
+            return Err(DownloadError::DigestMismatch {
+                artifact_name: task.artifact_name.clone(),
+                expected: format!("sha256:{}", expected),
+                actual: format!("sha256:{}", actual),
+            });
+        }
+    }
+
+    // Every other reference was dropped with the finished blocking tasks
+    let data = Arc::unwrap_or_clone(data);
+    if let (Some(cache), Some(digest)) = (cache, &asset.digest) {
+        cache.put(digest, &data);
+    }
+    if !overlapped {
+        let data = unpack(task, data)?;
+        check_target(task, &data)?;
+        let written = Instant::now();
+        write_to_staging_blocking(staging_path, &task.dest_filename, Arc::new(data))
+            .await
+            .map_err(task_failed)??;
+        timings.write = written.elapsed();
+    }
     Ok(())
 }
 
+/// Hex SHA-256 of `data`, hashed in chunks of [`HASH_CHUNK_SIZE`].
+fn sha256_hex(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    for chunk in data.chunks(HASH_CHUNK_SIZE) {
+        hasher.update(chunk);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// How long an artifact took in each stage, reported with `--timings`.
+#[derive(Debug)]
+struct ArtifactTimings {
+    /// How the artifact was obtained
+    origin: &'static str,
+    fetch: Duration,
+    verify: Duration,
+    write: Duration,
+    /// Written while its digest was computed
+    overlapped: bool,
+}
+
+impl Default for ArtifactTimings {
+    fn default() -> Self {
+        Self {
+            origin: "downloaded",
+            fetch: Duration::ZERO,
+            verify: Duration::ZERO,
+            write: Duration::ZERO,
+            overlapped: false,
+        }
+    }
+}
+
+impl std::fmt::Display for ArtifactTimings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} in {:.2}s", self.origin, self.fetch.as_secs_f64())?;
+        if !self.verify.is_zero() {
+            write!(f, ", verified in {:.2}s", self.verify.as_secs_f64())?;
+        }
+        write!(f, ", written in {:.2}s", self.write.as_secs_f64())?;
+        if self.overlapped {
+            write!(f, " (while verifying)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Extract the task's binary when the artifact is an archive.
+fn unpack(task: &DownloadTask, data: Vec<u8>) -> std::result::Result<Vec<u8>, DownloadError> {
+    archive::unpack_binary(data, &task.dest_filename).map_err(|source| DownloadError::Extract {
+        artifact_name: task.artifact_name.clone(),
+        source,
+    })
+}
+
+/// Check the binary's header against the task's target, if it has one.
+fn check_target(task: &DownloadTask, data: &[u8]) -> std::result::Result<(), DownloadError> {
+    let Some((platform, arch)) = task.target else {
+        return Ok(());
+    };
+    let format = ExecutableFormat::detect(data);
+    if format.runs_on(platform, arch) {
+        return Ok(());
+    }
+    Err(DownloadError::TargetMismatch {
+        artifact_name: task.artifact_name.clone(),
+        expected: format!("{} {}", platform, arch),
+        found: format.to_string(),
+    })
+}
+
 /// Write artifact data to the staging directory.
 fn write_to_staging(
     staging_path: &Path,
@@ -366,18 +891,35 @@ fn write_to_staging(
     data: &[u8],
 ) -> std::result::Result<(), DownloadError> {
     let dest = staging_path.join(dest_filename);
-    fs::write(&dest, data).map_err(|err| DownloadError::StagingWrite {
+    preallocate::write(&dest, data).map_err(|err| DownloadError::StagingWrite {
         artifact_name: dest_filename.to_string(),
         path: dest,
         source: err,
     })
 }
 
+/// [`write_to_staging`] on the blocking pool.
+async fn write_to_staging_blocking(
+    staging_path: &Path,
+    dest_filename: &str,
+    data: Arc<Vec<u8>>,
+) -> std::result::Result<std::result::Result<(), DownloadError>, tokio::task::JoinError> {
+    let (staging_path, dest_filename) = (staging_path.to_path_buf(), dest_filename.to_string());
+    tokio::task::spawn_blocking(move || write_to_staging(&staging_path, &dest_filename, &data))
+        .await
+}
+
 /// Extract the artifact name from a [`DownloadError`].
 fn download_error_artifact_name(err: &DownloadError) -> &str {
     match err {
+        DownloadError::AssetsUnavailable { missing, .. } => {
+            missing.first().map(String::as_str).unwrap_or_default()
+        }
         DownloadError::TaskFailed { artifact_name, .. }
         | DownloadError::EmptyArtifact { artifact_name }
+        | DownloadError::DigestMismatch { artifact_name, .. }
+        | DownloadError::Extract { artifact_name, .. }
+        | DownloadError::TargetMismatch { artifact_name, .. }
         | DownloadError::StagingWrite { artifact_name, .. }
         | DownloadError::SemaphoreClosed { artifact_name } => artifact_name,
     }
@@ -419,7 +961,7 @@ mod tests {
             let data: Vec<u8> = vec![];
 
             //* When
-            let result = verify_artifact("ampd-linux-x86_64", &data);
+            let result = verify_artifact("ampd-linux-x86_64", None, &data);
 
             //* Then
             let err = result.expect_err("should return DownloadError for empty data");
@@ -429,6 +971,104 @@ mod tests {
                 err
             );
         }
+
+        /// Accepts data whose SHA-256 matches the published digest.
+        #[test]
+        fn verify_artifact_with_matching_digest_succeeds() {
+            //* Given
+            let data = b"ampd-binary";
+            let digest = format!("sha256:{:x}", Sha256::digest(data));
+
+            //* When
+            let result = verify_artifact("ampd-linux-x86_64", Some(&digest), data);
+
+            //* Then
+            assert!(result.is_ok(), "matching digest should verify");
+        }
+
+        /// Rejects data that differs from the published digest.
+        #[test]
+        fn verify_artifact_with_mismatched_digest_returns_digest_mismatch_error() {
+            //* Given
+            let digest = format!("sha256:{:x}", Sha256::digest(b"original"));
+
+            //* When
+            let result = verify_artifact("ampd-linux-x86_64", Some(&digest), b"tampered");
+
+            //* Then
+            let err = result.expect_err("should reject mismatched data");
+            assert!(
+                matches!(err, DownloadError::DigestMismatch { .. }),
+                "expected DigestMismatch error, got: {:?}",
+                err
+            );
+        }
+    }
+
+    mod verify_and_stage {
+        use super::*;
+
+        fn task_and_asset(digest: &[u8]) -> (DownloadTask, ResolvedAsset) {
+            let task = DownloadTask {
+                artifact_name: "ampd-linux-x86_64".to_string(),
+                dest_filename: "ampd".to_string(),
+                target: None,
+            };
+            let asset = ResolvedAsset {
+                id: 0,
+                name: "ampd-linux-x86_64".to_string(),
+                url: "https://example.com/ampd-linux-x86_64".to_string(),
+                digest: Some(format!("sha256:{:x}", Sha256::digest(digest))),
+            };
+            (task, asset)
+        }
+
+        #[tokio::test]
+        async fn verify_and_stage_with_bare_binary_writes_while_verifying() {
+            //* Given
+            let dir = tempfile::tempdir().expect("should create temp directory");
+            let data = vec![7u8; 3 * HASH_CHUNK_SIZE + 1];
+            let (task, asset) = task_and_asset(&data);
+            let mut timings = ArtifactTimings::default();
+
+            //* When
+            let result =
+                verify_and_stage(&task, &asset, data.clone(), dir.path(), None, &mut timings).await;
+
+            //* Then
+            assert!(result.is_ok(), "should stage: {:?}", result.err());
+            assert!(
+                timings.overlapped,
+                "bare binary should be written while hashed"
+            );
+            assert_eq!(fs::read(dir.path().join("ampd")).expect("staged"), data);
+        }
+
+        #[tokio::test]
+        async fn verify_and_stage_with_mismatched_digest_removes_staged_binary() {
+            //* Given
+            let dir = tempfile::tempdir().expect("should create temp directory");
+            let (task, asset) = task_and_asset(b"original");
+            let mut timings = ArtifactTimings::default();
+
+            //* When
+            let result = verify_and_stage(
+                &task,
+                &asset,
+                b"tampered".to_vec(),
+                dir.path(),
+                None,
+                &mut timings,
+            )
+            .await;
+
+            //* Then
+            assert!(matches!(result, Err(DownloadError::DigestMismatch { .. })));
+            assert!(
+                !dir.path().join("ampd").exists(),
+                "tampered binary should be removed"
+            );
+        }
     }
 
     #[cfg(unix)]
@@ -478,7 +1118,7 @@ mod tests {
             //* Given
             let parent = tempfile::tempdir().expect("should create parent directory");
             let version_dir = parent.path().join("v1.0.0");
-            let backup_dir = append_extension(&version_dir, "old");
+            let backup_dir = backup_path(&version_dir);
             fs::create_dir_all(&version_dir).expect("should create stale version directory");
             fs::write(version_dir.join("stale-file"), b"stale").expect("should write stale file");
 
@@ -513,7 +1153,7 @@ mod tests {
             //* Given — a leftover .old dir from a previous crashed install
             let parent = tempfile::tempdir().expect("should create parent directory");
             let version_dir = parent.path().join("v1.0.0");
-            let backup_dir = append_extension(&version_dir, "old");
+            let backup_dir = backup_path(&version_dir);
             fs::create_dir_all(&version_dir).expect("should create version directory");
             fs::write(version_dir.join("ampd"), b"current").expect("should write current binary");
             fs::create_dir_all(&backup_dir).expect("should create stale backup directory");
@@ -544,23 +1184,23 @@ mod tests {
         }
     }
 
-    mod append_extension {
+    mod backup_path {
         use super::*;
 
         /// Guards against `with_extension` truncating semver names like `v0.1.0`.
         #[test]
-        fn append_extension_with_semver_name_preserves_full_name() {
+        fn backup_path_with_semver_name_preserves_full_name() {
             //* Given
             let path = PathBuf::from("/versions/v0.1.0");
 
             //* When
-            let result = append_extension(&path, "old");
+            let result = backup_path(&path);
 
             //* Then
             assert_eq!(
                 result,
-                PathBuf::from("/versions/v0.1.0.old"),
-                "should append .old to the full directory name, not replace .0"
+                PathBuf::from("/versions/.v0.1.0.old"),
+                "should hide the full directory name with .old appended, not replace .0"
             );
         }
     }
@@ -569,164 +1209,31 @@ mod tests {
     /// Exercises bounded concurrency, fail-fast cancellation, retry
     /// behavior, and the staging-to-version-dir swap.
     mod it_download_all {
-        use std::sync::atomic::{AtomicUsize, Ordering};
-
-        use tokio::io::{AsyncReadExt, AsyncWriteExt};
-
         use super::*;
-        use crate::progress::ProgressReporter;
+        use crate::{
+            progress::ProgressReporter,
+            testing::{MockReleaseServer, Route},
+        };
 
         /// No-op reporter for tests that don't need progress output.
         struct NoopReporter;
 
         impl ProgressReporter for NoopReporter {
             fn set_total(&self, _total: usize, _names: Vec<String>) {}
+            fn component_queued(&self, _name: &str, _ahead: usize) {}
             fn component_started(&self, _name: &str) {}
             fn component_completed(&self, _name: &str) {}
             fn component_failed(&self, _name: &str) {}
             fn finish(&self) {}
         }
 
-        /// Route configuration for the mock HTTP server.
-        #[derive(Clone)]
-        struct Route {
-            /// Path substring to match against the request path.
-            prefix: &'static str,
-            /// Response body to return on success.
-            body: Vec<u8>,
-            /// Number of times to return 500 before succeeding.
-            /// Shared across connections so retries see the updated count.
-            fail_count: Arc<AtomicUsize>,
-        }
-
-        impl Route {
-            /// Create a route that always succeeds.
-            fn ok(prefix: &'static str, body: Vec<u8>) -> Self {
-                Self {
-                    prefix,
-                    body,
-                    fail_count: Arc::new(AtomicUsize::new(0)),
-                }
-            }
-
-            /// Create a route that returns 500 for the first `n` requests,
-            /// then succeeds.
-            fn fail_then_ok(prefix: &'static str, body: Vec<u8>, n: usize) -> Self {
-                Self {
-                    prefix,
-                    body,
-                    fail_count: Arc::new(AtomicUsize::new(n)),
-                }
-            }
-        }
-
-        /// Spawn a mock HTTP server on a pre-bound listener.
-        ///
-        /// `routes` maps path substrings to response bodies. The server accepts
-        /// connections in a loop, reads the request line to extract the path,
-        /// and sends back the matching response with 200 OK — or 404 if no
-        /// route matches. Routes with a non-zero `fail_count` return 500 until
-        /// the count is exhausted.
-        fn start_mock_server(
-            listener: tokio::net::TcpListener,
-            routes: Vec<Route>,
-        ) -> tokio::task::JoinHandle<()> {
-            tokio::spawn(async move {
-                loop {
-                    let Ok((mut stream, _)) = listener.accept().await else {
-                        break;
-                    };
-                    let routes = routes.clone();
-
-                    tokio::spawn(async move {
-                        let mut buf = [0u8; 4096];
-                        let n = stream.read(&mut buf).await.expect("should read request");
-                        let request = String::from_utf8_lossy(&buf[..n]);
-                        let path = request
-                            .lines()
-                            .next()
-                            .and_then(|line| line.split_whitespace().nth(1))
-                            .unwrap_or("/");
-
-                        let response = routes
-                            .iter()
-                            .find(|r| path.contains(r.prefix))
-                            .map(|route| {
-                                // Atomically decrement the fail counter. Uses
-                                // compare_exchange in a loop to avoid the race
-                                // where fetch_sub wraps 0 → usize::MAX.
-                                let should_fail = loop {
-                                    let current = route.fail_count.load(Ordering::Relaxed);
-                                    if current == 0 {
-                                        break false;
-                                    }
-                                    if route
-                                        .fail_count
-                                        .compare_exchange(
-                                            current,
-                                            current - 1,
-                                            Ordering::Relaxed,
-                                            Ordering::Relaxed,
-                                        )
-                                        .is_ok()
-                                    {
-                                        break true;
-                                    }
-                                };
-
-                                if should_fail {
-                                    b"HTTP/1.1 500 Internal Server Error\r\n\
-                                      Content-Length: 0\r\n\r\n"
-                                        .to_vec()
-                                } else {
-                                    format!(
-                                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
-                                        route.body.len()
-                                    )
-                                    .into_bytes()
-                                    .into_iter()
-                                    .chain(route.body.iter().copied())
-                                    .collect::<Vec<u8>>()
-                                }
-                            })
-                            .unwrap_or_else(|| {
-                                b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_vec()
-                            });
-
-                        stream
-                            .write_all(&response)
-                            .await
-                            .expect("should write response");
-                    });
-                }
-            })
-        }
-
-        /// Build release JSON for the mock server with the given assets.
-        fn release_json(addr: std::net::SocketAddr, asset_names: &[&str]) -> Vec<u8> {
-            let assets: Vec<String> = asset_names
-                .iter()
-                .enumerate()
-                .map(|(i, name)| {
-                    format!(
-                        r#"{{"id":{},"name":"{}","browser_download_url":"http://{}/download/{}"}}"#,
-                        i + 1,
-                        name,
-                        addr,
-                        name,
-                    )
-                })
-                .collect();
-            format!(r#"{{"tag_name":"v1.0.0","assets":[{}]}}"#, assets.join(",")).into_bytes()
-        }
-
         /// Common test setup: bind a mock server, create a `DownloadManager`,
         /// and prepare a temp directory with a version path.
         struct TestFixture {
             manager: DownloadManager,
             version_dir: PathBuf,
             _tmp: tempfile::TempDir,
-            server_handle: tokio::task::JoinHandle<()>,
+            _server: MockReleaseServer,
         }
 
         impl TestFixture {
@@ -738,21 +1245,15 @@ mod tests {
                 download_routes: Vec<Route>,
                 max_concurrent: usize,
             ) -> Self {
-                let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
-                    .await
-                    .expect("should bind to a random port");
-                let addr = listener.local_addr().expect("should have a local address");
-
-                let release_body = release_json(addr, release_assets);
-                let mut routes = vec![Route::ok("tags/v1.0.0", release_body)];
-                routes.extend(download_routes);
-
-                let server_handle = start_mock_server(listener, routes);
-
-                let api_base = format!("http://{}", addr);
-                let github =
-                    GitHubClient::with_api_base(api_base).expect("should create test client");
-                let manager = DownloadManager::new(github, max_concurrent);
+                let server = download_routes.into_iter().fold(
+                    MockReleaseServer::start()
+                        .await
+                        .expect("should start mock server")
+                        .with_release("v1.0.0", release_assets),
+                    MockReleaseServer::with_route,
+                );
+                let github = server.github_client().expect("should create test client");
+                let manager = DownloadManager::new(Arc::new(github), max_concurrent);
 
                 let tmp = tempfile::tempdir().expect("should create temp directory");
                 let version_dir = tmp.path().join("v1.0.0");
@@ -761,7 +1262,7 @@ mod tests {
                     manager,
                     version_dir,
                     _tmp: tmp,
-                    server_handle,
+                    _server: server,
                 }
             }
 
@@ -774,22 +1275,18 @@ mod tests {
             }
         }
 
-        impl Drop for TestFixture {
-            fn drop(&mut self) {
-                self.server_handle.abort();
-            }
-        }
-
         /// The standard two-artifact task list (ampd + ampctl).
         fn standard_tasks() -> Vec<DownloadTask> {
             vec![
                 DownloadTask {
                     artifact_name: "ampd-linux-x86_64".to_string(),
                     dest_filename: "ampd".to_string(),
+                    target: None,
                 },
                 DownloadTask {
                     artifact_name: "ampctl-linux-x86_64".to_string(),
                     dest_filename: "ampctl".to_string(),
+                    target: None,
                 },
             ]
         }
@@ -832,7 +1329,158 @@ mod tests {
             );
         }
 
+        /// A binary for another architecture is refused before it's written.
+        #[tokio::test]
+        async fn download_all_with_wrong_architecture_fails_with_target_mismatch() {
+            //* Given
+            let mut aarch64_elf = b"\x7fELF\x02\x01\x01".to_vec();
+            aarch64_elf.resize(18, 0);
+            aarch64_elf.extend_from_slice(&183u16.to_le_bytes());
+            aarch64_elf.resize(64, 0);
+
+            let fixture = TestFixture::new(
+                &["ampd-linux-x86_64"],
+                vec![Route::ok("download/ampd-linux-x86_64", aarch64_elf)],
+                4,
+            )
+            .await;
+            let tasks = vec![DownloadTask {
+                artifact_name: "ampd-linux-x86_64".to_string(),
+                dest_filename: "ampd".to_string(),
+                target: Some((Platform::Linux, Architecture::X86_64)),
+            }];
+
+            //* When
+            let result = fixture.download(tasks).await;
+
+            //* Then
+            let err = result.expect_err("a binary for another architecture should be refused");
+            assert!(matches!(
+                err.downcast_ref::<DownloadError>(),
+                Some(DownloadError::TargetMismatch { found, .. }) if found == "linux aarch64 (ELF)"
+            ));
+            assert!(!fixture.version_dir.exists(), "nothing should be installed");
+        }
+
         /// A missing asset fails the whole batch and leaves no partial install.
+        #[tokio::test]
+        async fn download_all_with_tar_gz_asset_extracts_binary() {
+            //* Given
+            let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+                Vec::new(),
+                flate2::Compression::default(),
+            ));
+            let mut header = tar::Header::new_gnu();
+            header.set_size(16);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, "ampd", &b"fake-ampd-binary"[..])
+                .expect("should append tar entry");
+            let archive = builder
+                .into_inner()
+                .and_then(|encoder| encoder.finish())
+                .expect("should finish tar.gz");
+
+            let fixture = TestFixture::new(
+                &["ampd-linux-x86_64.tar.gz", "ampctl-linux-x86_64"],
+                vec![
+                    Route::ok("download/ampd-linux-x86_64.tar.gz", archive),
+                    Route::ok("download/ampctl-linux-x86_64", b"fake-ampctl".to_vec()),
+                ],
+                4,
+            )
+            .await;
+
+            //* When
+            let result = fixture.download(standard_tasks()).await;
+
+            //* Then
+            assert!(
+                result.is_ok(),
+                "download_all should succeed: {:?}",
+                result.err()
+            );
+            assert_eq!(
+                fs::read(fixture.version_dir.join("ampd")).expect("should read ampd"),
+                b"fake-ampd-binary",
+                "ampd should be extracted from the archive"
+            );
+        }
+
+        #[tokio::test]
+        async fn download_all_with_delta_base_applies_published_patch() {
+            //* Given
+            let installed: Vec<u8> = (0..100_000u32).map(|i| (i * 13 % 251) as u8).collect();
+            let mut new = installed.clone();
+            new[500..508].copy_from_slice(b"v1.0.0!!");
+            let mut encoder = zstd::stream::Encoder::with_dictionary(Vec::new(), 3, &installed)
+                .expect("should create encoder");
+            std::io::Write::write_all(&mut encoder, &new).expect("should compress");
+            let patch = encoder.finish().expect("should finish patch");
+
+            let server = MockReleaseServer::start()
+                .await
+                .expect("should start mock server")
+                .with_release(
+                    "v1.0.0",
+                    &[
+                        "ampd-linux-x86_64",
+                        "ampctl-linux-x86_64",
+                        "ampd-linux-x86_64.from-v0.9.0.zst",
+                    ],
+                )
+                .with_route(Route::ok(
+                    "download/ampd-linux-x86_64.from-v0.9.0.zst",
+                    patch,
+                ))
+                .with_route(Route::ok(
+                    "download/ampd-linux-x86_64",
+                    b"full-download".to_vec(),
+                ))
+                .with_route(Route::ok(
+                    "download/ampctl-linux-x86_64",
+                    b"fake-ampctl".to_vec(),
+                ));
+
+            let tmp = tempfile::tempdir().expect("should create temp directory");
+            let base_dir = tmp.path().join("v0.9.0");
+            fs::create_dir_all(&base_dir).expect("should create base dir");
+            fs::write(base_dir.join("ampd"), &installed).expect("should write installed ampd");
+            let github = server.github_client().expect("should create test client");
+            let manager =
+                DownloadManager::new(Arc::new(github), 4).with_delta_base("v0.9.0", base_dir);
+            let version_dir = tmp.path().join("v1.0.0");
+
+            //* When
+            let result = manager
+                .download_all(
+                    standard_tasks(),
+                    "v1.0.0",
+                    version_dir.clone(),
+                    Arc::new(NoopReporter),
+                )
+                .await;
+            drop(server);
+
+            //* Then
+            assert!(
+                result.is_ok(),
+                "download_all should succeed: {:?}",
+                result.err()
+            );
+            assert_eq!(
+                fs::read(version_dir.join("ampd")).expect("should read ampd"),
+                new,
+                "ampd should be rebuilt from the patch"
+            );
+            assert_eq!(
+                fs::read(version_dir.join("ampctl")).expect("should read ampctl"),
+                b"fake-ampctl",
+                "artifacts without a patch should be downloaded in full"
+            );
+        }
+
         #[tokio::test]
         async fn download_all_with_missing_asset_fails_without_partial_install() {
             //* Given — release only contains ampd; ampctl is missing
@@ -913,6 +1561,7 @@ mod tests {
             let tasks = vec![DownloadTask {
                 artifact_name: "ampd-linux-x86_64".to_string(),
                 dest_filename: "ampd".to_string(),
+                target: None,
             }];
 
             //* When
@@ -931,6 +1580,33 @@ mod tests {
             );
         }
 
+        /// An asset listed in the release but gone from the download host fails
+        /// before anything is downloaded.
+        #[tokio::test]
+        async fn download_all_with_removed_asset_fails_before_downloading() {
+            //* Given — both assets are listed, but only ampd is still served
+            let fixture = TestFixture::new(
+                &["ampd-linux-x86_64", "ampctl-linux-x86_64"],
+                vec![Route::ok("download/ampd-linux-x86_64", b"ampd".to_vec())],
+                4,
+            )
+            .await;
+
+            //* When
+            let result = fixture.download(standard_tasks()).await;
+
+            //* Then
+            let err = result.expect_err("download_all should fail when an asset is gone");
+            let Some(DownloadError::AssetsUnavailable { missing, .. }) = err.downcast_ref() else {
+                panic!("expected AssetsUnavailable, got: {:?}", err);
+            };
+            assert_eq!(missing, &["ampctl-linux-x86_64".to_string()]);
+            assert!(
+                !fixture.version_dir.exists(),
+                "version_dir should not exist when assets are missing"
+            );
+        }
+
         /// Persistent 500s exhaust all retries and fail with no partial install.
         #[tokio::test]
         async fn download_all_with_persistent_failure_fails_after_retry() {
@@ -952,6 +1628,7 @@ mod tests {
             let tasks = vec![DownloadTask {
                 artifact_name: "ampd-linux-x86_64".to_string(),
                 dest_filename: "ampd".to_string(),
+                target: None,
             }];
 
             //* When
@@ -0,0 +1,185 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    advisory::DEFAULT_ADVISORIES_URL,
+    github::{ClientOptions, GitHubClient},
+    release_source,
+    request_plan::PlannedRequest,
+    settings::Settings,
+};
+
+/// A host and port ampup connects to, and the features that need it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+    pub purposes: Vec<&'static str>,
+}
+
+/// Every endpoint some ampup feature connects to, in order of first use.
+#[derive(Debug, Default, Serialize)]
+pub struct Allowlist {
+    pub endpoints: Vec<Endpoint>,
+}
+
+impl Allowlist {
+    /// The endpoints needed with `settings`, the selected `repo` and
+    /// `source`, and the connection `options`.
+    ///
+    /// Requests are planned both with and without a token, since the two
+    /// reach different hosts; no token is resolved and nothing is sent.
+    pub fn for_settings(
+        settings: &Settings,
+        repo: Option<String>,
+        source: Option<String>,
+        options: ClientOptions,
+    ) -> Result<Self> {
+        let options = options.or(settings.client_options());
+        let repo = settings.repo(repo);
+        let self_repo = settings.self_repo(None);
+        let source = source.or(settings.source.clone());
+
+        let mut allowlist = Self::default();
+        for token in [None, Some("<token>".to_string())] {
+            let github = GitHubClient::new(repo.clone(), token.clone(), options.clone())?;
+            allowlist.add_requests(&github.plan_latest_version(), "install");
+            allowlist.add_requests(&github.plan_list_releases(), "install");
+            let source = release_source::with_mirrors(
+                source.as_deref(),
+                &settings.mirrors,
+                &github,
+                options.timeouts,
+            )?;
+            allowlist.add_requests(
+                &source.plan_requests("<version>", &["ampd", "ampctl"]),
+                "install",
+            );
+
+            let ampup = GitHubClient::new(self_repo.clone(), token, options.clone())?;
+            allowlist.add_requests(&ampup.plan_latest_version(), "self-update");
+            allowlist.add_requests(
+                &ampup.plan_release_assets("<version>", &["ampup"]),
+                "self-update",
+            );
+        }
+        for url in options.asset_redirect_urls() {
+            allowlist.add(&url, "install");
+            allowlist.add(&url, "self-update");
+        }
+        allowlist.add(DEFAULT_ADVISORIES_URL, "audit");
+        allowlist.add(options.github_api(), "news");
+        allowlist.add(&options.graphql_api(), "news");
+        allowlist.add(&options.web_url(), "build");
+        Ok(allowlist)
+    }
+
+    /// Record that `purpose` connects to the host of `url`. URLs without a
+    /// network host (local files, placeholders) are ignored.
+    pub fn add(&mut self, url: &str, purpose: &'static str) {
+        let Some((host, port)) = host_port(url) else {
+            return;
+        };
+        match self
+            .endpoints
+            .iter_mut()
+            .find(|endpoint| endpoint.host == host && endpoint.port == port)
+        {
+            Some(endpoint) => {
+                if !endpoint.purposes.contains(&purpose) {
+                    endpoint.purposes.push(purpose);
+                }
+            }
+            None => self.endpoints.push(Endpoint {
+                host,
+                port,
+                purposes: vec![purpose],
+            }),
+        }
+    }
+
+    fn add_requests(&mut self, requests: &[PlannedRequest], purpose: &'static str) {
+        for request in requests {
+            self.add(&request.url, purpose);
+        }
+    }
+
+    /// One `host:port` line per endpoint, followed by the features using it.
+    pub fn render(&self) -> String {
+        let addresses: Vec<String> = self
+            .endpoints
+            .iter()
+            .map(|endpoint| format!("{}:{}", endpoint.host, endpoint.port))
+            .collect();
+        let width = addresses.iter().map(String::len).max().unwrap_or(0);
+        let mut out = String::new();
+        for (address, endpoint) in addresses.iter().zip(&self.endpoints) {
+            out.push_str(&format!(
+                "{:<width$}  {}\n",
+                address,
+                endpoint.purposes.join(", ")
+            ));
+        }
+        out
+    }
+}
+
+/// Host and port (explicit or the scheme's default) of a network URL.
+fn host_port(url: &str) -> Option<(String, u16)> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let host = url.host_str()?.to_string();
+    Some((host, url.port_or_known_default()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_with_same_host_merges_purposes() {
+        //* Given
+        let mut allowlist = Allowlist::default();
+
+        //* When
+        allowlist.add("https://api.github.com/graphql", "install");
+        allowlist.add("https://api.github.com/search/issues", "news");
+        allowlist.add("http://mirror.example.com:8080/amp/ampd", "install");
+        allowlist.add("file:///srv/amp", "install");
+        allowlist.add(
+            "<realm from the registry's WWW-Authenticate challenge>",
+            "install",
+        );
+
+        //* Then
+        assert_eq!(
+            allowlist.render(),
+            "api.github.com:443       install, news\n\
+             mirror.example.com:8080  install\n"
+        );
+    }
+
+    #[test]
+    fn for_settings_with_mirror_and_enterprise_lists_their_hosts() -> Result<()> {
+        //* Given
+        let settings = Settings {
+            mirrors: vec!["https://mirror.example.com/{version}/{asset}".to_string()],
+            api_url: Some("https://github.mycorp.com/api/v3".to_string()),
+            ..Default::default()
+        };
+
+        //* When
+        let allowlist = Allowlist::for_settings(&settings, None, None, ClientOptions::default())?;
+
+        //* Then
+        let hosts: Vec<&str> = allowlist
+            .endpoints
+            .iter()
+            .map(|endpoint| endpoint.host.as_str())
+            .collect();
+        assert_eq!(
+            hosts,
+            vec!["github.mycorp.com", "mirror.example.com", "ampup.sh"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+
+/// Where release metadata for amp and ampup is read from. Downloads always
+/// use the canonical asset URLs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataEndpoint {
+    /// Probe both APIs on first use and pick the faster healthy one
+    #[default]
+    Auto,
+    /// The ampup.sh release API
+    Ampup,
+    /// The GitHub REST API
+    Github,
+}
+
+impl MetadataEndpoint {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Ampup => "ampup",
+            Self::Github => "github",
+        }
+    }
+}
+
+/// How long a probe's outcome is reused by later runs.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Longest a probe may take before both endpoints count as unhealthy.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The endpoints [`MetadataEndpoint::Auto`] chooses between for one
+/// repository, as release API base URLs.
+pub struct EndpointSelection {
+    pub ampup: String,
+    pub github: String,
+    /// Where the outcome is remembered between runs
+    pub cache: Option<EndpointCache>,
+}
+
+impl EndpointSelection {
+    /// Release API base URL to use: the cached choice when fresh, otherwise
+    /// the winner of a new probe, and the ampup.sh API when neither endpoint
+    /// answers in time.
+    pub async fn select(&self, client: &reqwest::Client) -> String {
+        let cached = self.cache.as_ref().and_then(|cache| cache.get(DEFAULT_TTL));
+        let endpoint = match cached {
+            Some(endpoint) => endpoint,
+            None => {
+                let candidates = [
+                    (MetadataEndpoint::Ampup, format!("{}/latest", self.ampup)),
+                    (MetadataEndpoint::Github, format!("{}/latest", self.github)),
+                ];
+                match probe(client, &candidates).await {
+                    Some(endpoint) => {
+                        if let Some(cache) = &self.cache {
+                            let _ = cache.set(endpoint);
+                        }
+                        crate::ui::detail!("Reading release metadata from {}", endpoint.as_str());
+                        endpoint
+                    }
+                    None => MetadataEndpoint::Ampup,
+                }
+            }
+        };
+        match endpoint {
+            MetadataEndpoint::Github => self.github.clone(),
+            MetadataEndpoint::Auto | MetadataEndpoint::Ampup => self.ampup.clone(),
+        }
+    }
+}
+
+/// Race a HEAD request to each candidate URL, returning the endpoint that
+/// answered healthily first, or `None` when none did within
+/// [`PROBE_TIMEOUT`].
+///
+/// Server errors, rate limiting (403, 429) and connection failures are
+/// unhealthy; anything else, including 404 from an API without HEAD
+/// support, shows the endpoint is up.
+pub async fn probe(
+    client: &reqwest::Client,
+    candidates: &[(MetadataEndpoint, String)],
+) -> Option<MetadataEndpoint> {
+    let probes = candidates.iter().map(|(endpoint, url)| {
+        async move {
+            let response = client.head(url).send().await.map_err(|_| ())?;
+            let status = response.status();
+            let limited = matches!(
+                status,
+                reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::TOO_MANY_REQUESTS
+            );
+            if status.is_server_error() || limited {
+                Err(())
+            } else {
+                Ok(*endpoint)
+            }
+        }
+        .boxed()
+    });
+    match tokio::time::timeout(PROBE_TIMEOUT, futures::future::select_ok(probes)).await {
+        Ok(Ok((endpoint, _))) => Some(endpoint),
+        _ => None,
+    }
+}
+
+/// The endpoint a previous run's probe picked.
+pub struct EndpointCache {
+    path: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    endpoint: MetadataEndpoint,
+    /// When the endpoints were probed, in seconds since the Unix epoch
+    checked_at: u64,
+}
+
+impl EndpointCache {
+    /// The cache kept in `cache_dir/metadata-endpoint.json`.
+    pub fn new(cache_dir: &Path) -> Self {
+        Self {
+            path: cache_dir.join("metadata-endpoint.json"),
+        }
+    }
+
+    /// The endpoint picked less than `ttl` ago, if any.
+    pub fn get(&self, ttl: Duration) -> Option<MetadataEndpoint> {
+        let contents = fs::read_to_string(&self.path).ok()?;
+        let entry: Entry = serde_json::from_str(&contents).ok()?;
+        let age = Duration::from_secs(now().saturating_sub(entry.checked_at));
+        (age < ttl).then_some(entry.endpoint)
+    }
+
+    /// Record `endpoint` as picked now.
+    pub fn set(&self, endpoint: MetadataEndpoint) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).context("Failed to create cache directory")?;
+        }
+        let entry = Entry {
+            endpoint,
+            checked_at: now(),
+        };
+        let contents = serde_json::to_string(&entry).context("Failed to serialize endpoint")?;
+        fs::write(&self.path, contents).context("Failed to write endpoint cache")
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    /// Serve every request with `status` after `delay`, returning the base URL.
+    async fn serve(status: &'static str, delay: Duration) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("should bind to a random port");
+        let url = format!(
+            "http://{}",
+            listener.local_addr().expect("should have a local address")
+        );
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    tokio::time::sleep(delay).await;
+                    let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn probe_with_faster_unhealthy_endpoint_picks_healthy_one() {
+        //* Given
+        let failing = serve("503 Service Unavailable", Duration::ZERO).await;
+        let slow = serve("200 OK", Duration::from_millis(200)).await;
+        let candidates = [
+            (MetadataEndpoint::Ampup, failing),
+            (MetadataEndpoint::Github, slow),
+        ];
+
+        //* When
+        let endpoint = probe(&reqwest::Client::new(), &candidates).await;
+
+        //* Then
+        assert_eq!(endpoint, Some(MetadataEndpoint::Github));
+    }
+
+    #[test]
+    fn get_with_stale_entry_returns_none() -> Result<()> {
+        //* Given
+        let dir = tempfile::tempdir()?;
+        let cache = EndpointCache::new(dir.path());
+        cache.set(MetadataEndpoint::Github)?;
+
+        //* Then
+        assert_eq!(cache.get(DEFAULT_TTL), Some(MetadataEndpoint::Github));
+        assert_eq!(cache.get(Duration::ZERO), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use std::{collections::HashMap, ffi::OsString, process::Command};
+
+/// Source of environment variables.
+///
+/// Code that reads the environment takes an `&dyn Environment` so tests can
+/// supply their own variables instead of mutating the process-wide
+/// environment, which races with other tests running in parallel.
+pub trait Environment: Send + Sync {
+    /// Value of `name`, or `None` when unset.
+    fn var_os(&self, name: &str) -> Option<OsString>;
+
+    /// Value of `name` as UTF-8, or `None` when unset or not valid UTF-8.
+    fn var(&self, name: &str) -> Option<String> {
+        self.var_os(name)?.into_string().ok()
+    }
+
+    /// Create a [`Command`] for `program`, resolved against this
+    /// environment's `PATH`.
+    fn command(&self, program: &str) -> Command {
+        let mut command = Command::new(program);
+        if let Some(path) = self.var_os("PATH") {
+            command.env("PATH", path);
+        }
+        command
+    }
+}
+
+/// The environment of the running process.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProcessEnv;
+
+impl Environment for ProcessEnv {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        std::env::var_os(name)
+    }
+
+    fn command(&self, program: &str) -> Command {
+        Command::new(program)
+    }
+}
+
+/// A fixed set of variables; everything else is unset.
+#[derive(Debug, Default, Clone)]
+pub struct MapEnv {
+    vars: HashMap<String, OsString>,
+}
+
+impl MapEnv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `name` to `value`.
+    pub fn with(mut self, name: &str, value: impl Into<OsString>) -> Self {
+        self.vars.insert(name.to_string(), value.into());
+        self
+    }
+}
+
+impl Environment for MapEnv {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.vars.get(name).cloned()
+    }
+}
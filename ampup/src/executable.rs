@@ -0,0 +1,200 @@
+use crate::platform::{Architecture, Platform};
+
+/// ELF `e_machine` values.
+const EM_X86_64: u16 = 62;
+const EM_AARCH64: u16 = 183;
+
+/// Mach-O `cputype` values.
+const CPU_TYPE_X86_64: u32 = 0x0100_0007;
+const CPU_TYPE_ARM64: u32 = 0x0100_000c;
+
+/// Upper bound on the architectures in a universal binary, so other files
+/// starting with the same magic (Java class files) aren't misread as one.
+const MAX_FAT_ARCHS: u32 = 16;
+
+/// What kind of executable a file is, read from its header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecutableFormat {
+    /// Linux ELF binary, with its `e_machine`
+    Elf { machine: u16 },
+    /// macOS Mach-O binary, with the `cputype` of each slice (several for a
+    /// universal binary)
+    MachO { cpu_types: Vec<u32> },
+    /// Script started with `#!`, which runs on any platform
+    Script,
+    /// Anything else, such as an HTML error page
+    Unknown,
+}
+
+impl ExecutableFormat {
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(b"\x7fELF") {
+            return elf(data).unwrap_or(Self::Unknown);
+        }
+        if data.starts_with(b"#!") {
+            return Self::Script;
+        }
+        mach_o(data).unwrap_or(Self::Unknown)
+    }
+
+    /// Whether this executable can run on `platform` and `arch`.
+    pub fn runs_on(&self, platform: Platform, arch: Architecture) -> bool {
+        match self {
+            Self::Elf { machine } => {
+                platform == Platform::Linux && elf_architecture(*machine) == Some(arch)
+            }
+            Self::MachO { cpu_types } => {
+                platform == Platform::Darwin
+                    && cpu_types
+                        .iter()
+                        .any(|&cpu_type| mach_o_architecture(cpu_type) == Some(arch))
+            }
+            Self::Script => true,
+            Self::Unknown => false,
+        }
+    }
+}
+
+impl std::fmt::Display for ExecutableFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Elf { machine } => match elf_architecture(*machine) {
+                Some(arch) => write!(f, "linux {} (ELF)", arch),
+                None => write!(f, "ELF for machine {}", machine),
+            },
+            Self::MachO { cpu_types } => {
+                let archs: Vec<String> = cpu_types
+                    .iter()
+                    .map(|&cpu_type| match mach_o_architecture(cpu_type) {
+                        Some(arch) => arch.to_string(),
+                        None => format!("cpu type {:#x}", cpu_type),
+                    })
+                    .collect();
+                write!(f, "darwin {} (Mach-O)", archs.join(" + "))
+            }
+            Self::Script => write!(f, "script"),
+            Self::Unknown => write!(f, "not an executable"),
+        }
+    }
+}
+
+fn elf_architecture(machine: u16) -> Option<Architecture> {
+    match machine {
+        EM_X86_64 => Some(Architecture::X86_64),
+        EM_AARCH64 => Some(Architecture::Aarch64),
+        _ => None,
+    }
+}
+
+fn mach_o_architecture(cpu_type: u32) -> Option<Architecture> {
+    match cpu_type {
+        CPU_TYPE_X86_64 => Some(Architecture::X86_64),
+        CPU_TYPE_ARM64 => Some(Architecture::Aarch64),
+        _ => None,
+    }
+}
+
+fn elf(data: &[u8]) -> Option<ExecutableFormat> {
+    // EI_DATA picks the byte order; e_machine follows e_ident and e_type
+    let machine: [u8; 2] = data.get(18..20)?.try_into().ok()?;
+    let machine = match data.get(5)? {
+        1 => u16::from_le_bytes(machine),
+        2 => u16::from_be_bytes(machine),
+        _ => return None,
+    };
+    Some(ExecutableFormat::Elf { machine })
+}
+
+fn mach_o(data: &[u8]) -> Option<ExecutableFormat> {
+    let u32_at = |offset: usize, big_endian: bool| -> Option<u32> {
+        let bytes: [u8; 4] = data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+
+    match data.get(..4)? {
+        // Thin 32- and 64-bit binaries, either byte order
+        [0xce | 0xcf, 0xfa, 0xed, 0xfe] => Some(ExecutableFormat::MachO {
+            cpu_types: vec![u32_at(4, false)?],
+        }),
+        [0xfe, 0xed, 0xfa, 0xce | 0xcf] => Some(ExecutableFormat::MachO {
+            cpu_types: vec![u32_at(4, true)?],
+        }),
+        // Universal binaries, always big-endian, with 32- or 64-bit slice
+        // entries
+        [0xca, 0xfe, 0xba, magic @ (0xbe | 0xbf)] => {
+            let count = u32_at(4, true)?;
+            if count == 0 || count > MAX_FAT_ARCHS {
+                return None;
+            }
+            let entry_size = if *magic == 0xbe { 20 } else { 32 };
+            let cpu_types = (0..count as usize)
+                .map(|index| u32_at(8 + index * entry_size, true))
+                .collect::<Option<Vec<_>>>()?;
+            Some(ExecutableFormat::MachO { cpu_types })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elf_header(machine: u16) -> Vec<u8> {
+        let mut header = b"\x7fELF\x02\x01\x01".to_vec();
+        header.resize(18, 0);
+        header.extend_from_slice(&machine.to_le_bytes());
+        header.resize(64, 0);
+        header
+    }
+
+    #[test]
+    fn runs_on_with_aarch64_elf_rejects_x86_64_linux() {
+        //* Given
+        let format = ExecutableFormat::detect(&elf_header(EM_AARCH64));
+
+        //* When
+        let on_x86_64 = format.runs_on(Platform::Linux, Architecture::X86_64);
+        let on_aarch64 = format.runs_on(Platform::Linux, Architecture::Aarch64);
+
+        //* Then
+        assert!(!on_x86_64);
+        assert!(on_aarch64);
+        assert_eq!(format.to_string(), "linux aarch64 (ELF)");
+    }
+
+    #[test]
+    fn detect_with_universal_binary_lists_every_slice() {
+        //* Given
+        let mut data = vec![0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 2];
+        for cpu_type in [CPU_TYPE_X86_64, CPU_TYPE_ARM64] {
+            data.extend_from_slice(&cpu_type.to_be_bytes());
+            data.extend_from_slice(&[0; 16]);
+        }
+
+        //* When
+        let format = ExecutableFormat::detect(&data);
+
+        //* Then
+        assert!(format.runs_on(Platform::Darwin, Architecture::Aarch64));
+        assert!(!format.runs_on(Platform::Linux, Architecture::Aarch64));
+        assert_eq!(format.to_string(), "darwin x86_64 + aarch64 (Mach-O)");
+    }
+
+    #[test]
+    fn detect_with_html_page_is_unknown() {
+        //* Given
+        let data = b"<!DOCTYPE html><html><body>Not Found</body></html>";
+
+        //* When
+        let format = ExecutableFormat::detect(data);
+
+        //* Then
+        assert_eq!(format, ExecutableFormat::Unknown);
+        assert!(!format.runs_on(Platform::Linux, Architecture::X86_64));
+    }
+}
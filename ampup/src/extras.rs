@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::{config::Config, download_manager::DownloadTask, fetcher::AssetFetcher};
+
+/// Directory inside a version directory holding its auxiliary files.
+const EXTRAS_DIR: &str = "extras";
+
+/// Binaries whose auxiliary files are looked for in a release.
+const BINARIES: [&str; 2] = ["ampd", "ampctl"];
+
+/// Auxiliary files a release may publish next to the binaries, named after
+/// the binary they belong to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtraKind {
+    /// `<binary>.bash`
+    BashCompletion,
+    /// `_<binary>`
+    ZshCompletion,
+    /// `<binary>.fish`
+    FishCompletion,
+    /// `<binary>.1`
+    ManPage,
+}
+
+impl ExtraKind {
+    const ALL: [ExtraKind; 4] = [
+        Self::BashCompletion,
+        Self::ZshCompletion,
+        Self::FishCompletion,
+        Self::ManPage,
+    ];
+
+    /// Release asset name of this kind of file for `binary`.
+    pub fn asset_name(self, binary: &str) -> String {
+        match self {
+            Self::BashCompletion => format!("{}.bash", binary),
+            Self::ZshCompletion => format!("_{}", binary),
+            Self::FishCompletion => format!("{}.fish", binary),
+            Self::ManPage => format!("{}.1", binary),
+        }
+    }
+
+    /// Where the active version's file is linked, relative to
+    /// `~/.amp/share`, in the layout shells and `man` look for next to a
+    /// `bin` directory on `PATH`.
+    pub fn link_path(self, binary: &str) -> PathBuf {
+        match self {
+            Self::BashCompletion => Path::new("bash-completion/completions").join(binary),
+            Self::ZshCompletion => Path::new("zsh/site-functions").join(format!("_{}", binary)),
+            Self::FishCompletion => {
+                Path::new("fish/vendor_completions.d").join(format!("{}.fish", binary))
+            }
+            Self::ManPage => Path::new("man/man1").join(format!("{}.1", binary)),
+        }
+    }
+}
+
+/// Download whichever auxiliary files the release of `version` publishes
+/// into `version_dir/extras`, returning their names. Releases without them
+/// install as before.
+pub async fn download(
+    fetcher: &dyn AssetFetcher,
+    version: &str,
+    version_dir: &Path,
+) -> Vec<String> {
+    let tasks = BINARIES
+        .iter()
+        .flat_map(|binary| ExtraKind::ALL.map(|kind| kind.asset_name(binary)))
+        .map(|name| DownloadTask {
+            artifact_name: name.clone(),
+            dest_filename: name,
+            target: None,
+        })
+        .collect();
+    fetcher
+        .fetch_optional(tasks, version, &version_dir.join(EXTRAS_DIR))
+        .await
+}
+
+/// Link the auxiliary files of the version in `version_dir` into
+/// `~/.amp/share`, replacing those of the previously active version.
+pub fn link(config: &Config, version_dir: &Path) -> Result<()> {
+    let share_dir = config.share_dir();
+    for binary in BINARIES {
+        for kind in ExtraKind::ALL {
+            let link = share_dir.join(kind.link_path(binary));
+            remove_link(&link)?;
+
+            let file = version_dir.join(EXTRAS_DIR).join(kind.asset_name(binary));
+            if !file.is_file() {
+                continue;
+            }
+            if let Some(parent) = link.parent() {
+                fs::create_dir_all(parent).context("Failed to create share directory")?;
+            }
+            config
+                .symlink(&file, &link)
+                .with_context(|| format!("Failed to link {}", kind.asset_name(binary)))?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove the links to the active version's auxiliary files.
+pub fn unlink(config: &Config) -> Result<()> {
+    let share_dir = config.share_dir();
+    for binary in BINARIES {
+        for kind in ExtraKind::ALL {
+            remove_link(&share_dir.join(kind.link_path(binary)))?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove `link` if it's a symlink; files the user put there are kept.
+fn remove_link(link: &Path) -> Result<()> {
+    if link.is_symlink() {
+        fs::remove_file(link).context("Failed to remove auxiliary file link")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version_with_extras(dir: &Path, version: &str, extras: &[&str]) -> PathBuf {
+        let version_dir = dir.join("versions").join(version);
+        fs::create_dir_all(version_dir.join(EXTRAS_DIR)).expect("should create extras");
+        for name in extras {
+            fs::write(version_dir.join(EXTRAS_DIR).join(name), name).expect("should write extra");
+        }
+        version_dir
+    }
+
+    #[test]
+    fn link_with_completions_and_man_page_links_them_into_share() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        let version_dir =
+            version_with_extras(dir.path(), "v0.2.0", &["ampd.bash", "_ampd", "ampd.1"]);
+
+        //* When
+        link(&config, &version_dir).expect("should link");
+
+        //* Then
+        let share = config.share_dir();
+        assert_eq!(
+            fs::read_to_string(share.join("bash-completion/completions/ampd")).expect("bash"),
+            "ampd.bash"
+        );
+        assert_eq!(
+            fs::read_to_string(share.join("zsh/site-functions/_ampd")).expect("zsh"),
+            "_ampd"
+        );
+        assert_eq!(
+            fs::read_to_string(share.join("man/man1/ampd.1")).expect("man"),
+            "ampd.1"
+        );
+        assert!(!share.join("fish/vendor_completions.d/ampd.fish").exists());
+    }
+
+    #[test]
+    fn link_with_version_lacking_extras_removes_previous_links() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        let old = version_with_extras(dir.path(), "v0.1.0", &["ampctl.fish"]);
+        let new = version_with_extras(dir.path(), "v0.2.0", &[]);
+        link(&config, &old).expect("should link old version");
+
+        //* When
+        link(&config, &new).expect("should link new version");
+
+        //* Then
+        let link = config
+            .share_dir()
+            .join("fish/vendor_completions.d/ampctl.fish");
+        assert!(!link.is_symlink(), "stale link should be removed");
+    }
+}
@@ -0,0 +1,64 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{
+    download_manager::{DownloadManager, DownloadTask},
+    progress::ProgressReporter,
+};
+
+/// Fetches a release's artifacts into a version directory.
+///
+/// [`DownloadManager`] is the implementation ampup uses; frontends (the web
+/// installer, a GUI) can hand [`crate::install::VersionInstaller`] their own
+/// to fetch through another transport while keeping ampup's install steps.
+#[async_trait]
+pub trait AssetFetcher: Send + Sync {
+    /// Fetch every task's artifact of `version` into `version_dir`, leaving
+    /// the directory untouched unless all of them succeed, and report each
+    /// one to `reporter`.
+    async fn fetch_all(
+        &self,
+        tasks: Vec<DownloadTask>,
+        version: &str,
+        version_dir: PathBuf,
+        reporter: Arc<dyn ProgressReporter>,
+    ) -> Result<()>;
+
+    /// Fetch the artifacts a release may not publish into `dir`, returning
+    /// the names of the files written. Missing or failing artifacts are
+    /// skipped.
+    async fn fetch_optional(
+        &self,
+        tasks: Vec<DownloadTask>,
+        version: &str,
+        dir: &Path,
+    ) -> Vec<String>;
+}
+
+#[async_trait]
+impl AssetFetcher for DownloadManager {
+    async fn fetch_all(
+        &self,
+        tasks: Vec<DownloadTask>,
+        version: &str,
+        version_dir: PathBuf,
+        reporter: Arc<dyn ProgressReporter>,
+    ) -> Result<()> {
+        self.download_all(tasks, version, version_dir, reporter)
+            .await
+    }
+
+    async fn fetch_optional(
+        &self,
+        tasks: Vec<DownloadTask>,
+        version: &str,
+        dir: &Path,
+    ) -> Vec<String> {
+        self.download_optional(tasks, version, dir).await
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use crate::{environment::Environment, ui};
+
+/// Extended attribute macOS puts on downloaded files, which makes Gatekeeper
+/// vet them before their first run.
+const QUARANTINE_ATTRIBUTE: &str = "com.apple.quarantine";
+
+/// Let Gatekeeper run freshly installed binaries: remove the quarantine
+/// attribute from each of `binaries` in `version_dir` and, with `codesign`,
+/// replace their signature with an ad-hoc one.
+///
+/// Failures only warn, since the health check that follows reports a binary
+/// that still can't run.
+pub fn prepare(version_dir: &Path, binaries: &[&str], codesign: bool, env: &dyn Environment) {
+    for binary in binaries {
+        let path = version_dir.join(binary);
+
+        // `xattr -p` fails when the attribute isn't set
+        let quarantined = env
+            .command("xattr")
+            .args(["-p", QUARANTINE_ATTRIBUTE])
+            .arg(&path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if quarantined {
+            let mut command = env.command("xattr");
+            command.args(["-d", QUARANTINE_ATTRIBUTE]).arg(&path);
+            match run(command) {
+                Ok(()) => ui::detail!("Removed quarantine from {}", binary),
+                Err(reason) => ui::warn!("Failed to remove quarantine from {}: {}", binary, reason),
+            }
+        }
+
+        if codesign {
+            let mut command = env.command("codesign");
+            command.args(["--force", "--sign", "-"]).arg(&path);
+            match run(command) {
+                Ok(()) => ui::detail!("Signed {} (ad-hoc)", binary),
+                Err(reason) => ui::warn!("Failed to sign {}: {}", binary, reason),
+            }
+        }
+    }
+}
+
+/// Run `command`, returning its last line of stderr (or exit status) when it
+/// fails.
+fn run(mut command: Command) -> Result<(), String> {
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| err.to_string())?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(match stderr.lines().last() {
+        Some(line) => format!("exited with {}: {}", output.status, line.trim()),
+        None => format!("exited with {}", output.status),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use fs_err as fs;
+
+    use super::*;
+    use crate::environment::MapEnv;
+
+    /// Install fake `xattr` and `codesign` tools in `dir` that append their
+    /// arguments to `dir/calls`, with `xattr -p` reporting the attribute as
+    /// set only when `quarantined`.
+    fn fake_tools(dir: &Path, quarantined: bool) {
+        let log = dir.join("calls");
+        let xattr = format!(
+            "#!/bin/sh\necho \"xattr $1 $2\" >> {}\nif [ \"$1\" = -p ]; then exit {}; fi\n",
+            log.display(),
+            if quarantined { 0 } else { 1 }
+        );
+        let codesign = format!(
+            "#!/bin/sh\necho \"codesign $1 $2 $3\" >> {}\n",
+            log.display()
+        );
+        for (name, script) in [("xattr", xattr), ("codesign", codesign)] {
+            let path = dir.join(name);
+            fs::write(&path, script).expect("write fake tool");
+            fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+                .expect("make fake tool executable");
+        }
+    }
+
+    #[test]
+    fn prepare_with_quarantined_binary_removes_attribute_and_signs() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        fake_tools(dir.path(), true);
+        let env = MapEnv::new().with("PATH", dir.path());
+
+        //* When
+        prepare(dir.path(), &["ampd"], true, &env);
+
+        //* Then
+        let calls = fs::read_to_string(dir.path().join("calls")).expect("read calls");
+        assert_eq!(
+            calls,
+            "xattr -p com.apple.quarantine\nxattr -d com.apple.quarantine\ncodesign --force --sign -\n"
+        );
+    }
+
+    #[test]
+    fn prepare_without_quarantine_or_codesign_only_checks_attribute() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        fake_tools(dir.path(), false);
+        let env = MapEnv::new().with("PATH", dir.path());
+
+        //* When
+        prepare(dir.path(), &["ampd", "ampctl"], false, &env);
+
+        //* Then
+        let calls = fs::read_to_string(dir.path().join("calls")).expect("read calls");
+        assert_eq!(
+            calls,
+            "xattr -p com.apple.quarantine\nxattr -p com.apple.quarantine\n"
+        );
+    }
+}
@@ -1,13 +1,66 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use anyhow::{Context, Result};
-use futures::StreamExt;
-use serde::Deserialize;
-
-use crate::{DEFAULT_REPO, DEFAULT_SELF_REPO, rate_limiter::GitHubRateLimiter};
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+
+use crate::{
+    DEFAULT_REPO, DEFAULT_SELF_REPO,
+    archive::{COMPRESSED_SUFFIX, archive_suffixes},
+    chunked,
+    endpoint::{EndpointCache, EndpointSelection, MetadataEndpoint},
+    rate_limiter::{self, GitHubRateLimiter},
+    release_source::{ResolvedAsset, head_available},
+    request_plan::{PlannedAuth, PlannedRequest},
+};
 
 const AMPUP_API_URL: &str = "https://ampup.sh/api";
 const GITHUB_API_URL: &str = "https://api.github.com";
+const GITHUB_WEB_URL: &str = "https://github.com";
+/// Hosts github.com redirects release asset downloads to.
+const GITHUB_ASSET_URLS: &[&str] = &[
+    "https://objects.githubusercontent.com",
+    "https://release-assets.githubusercontent.com",
+];
+
+/// Release asset whose presence marks a release as yanked.
+pub const YANKED_ASSET: &str = "YANKED";
+
+/// Fetches the latest tag and the newest releases with their assets in one
+/// request.
+const RELEASES_QUERY: &str = r#"
+query($owner: String!, $name: String!) {
+  repository(owner: $owner, name: $name) {
+    latestRelease { tagName }
+    releases(first: 100, orderBy: {field: CREATED_AT, direction: DESC}) {
+      nodes {
+        tagName
+        isDraft
+        isPrerelease
+        releaseAssets(first: 100) {
+          nodes { databaseId name downloadUrl digest }
+        }
+      }
+    }
+  }
+}
+"#;
+
+/// Fetches the most recent discussions with their categories.
+const DISCUSSIONS_QUERY: &str = r#"
+query($owner: String!, $name: String!) {
+  repository(owner: $owner, name: $name) {
+    discussions(first: 50, orderBy: {field: CREATED_AT, direction: DESC}) {
+      nodes { title url createdAt category { name } }
+    }
+  }
+}
+"#;
 
 #[derive(Debug)]
 pub enum GitHubError {
@@ -44,6 +97,14 @@ pub enum GitHubError {
         retry_after_secs: u64,
         has_token: bool,
     },
+    RefNotFound {
+        repo: String,
+        reference: String,
+        url: String,
+    },
+    DraftsRequireToken {
+        repo: String,
+    },
 }
 
 impl std::fmt::Display for GitHubError {
@@ -115,6 +176,12 @@ impl std::fmt::Display for GitHubError {
                         writeln!(f, "    - {}", asset)?;
                     }
                 }
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Try building from source: ampup build --repo {} --tag {}",
+                    repo, version
+                )?;
             }
             Self::DownloadFailed {
                 repo,
@@ -162,6 +229,35 @@ impl std::fmt::Display for GitHubError {
                     writeln!(f, "  Unauthenticated requests have lower rate limits.")?;
                     writeln!(f, "  Try: export GITHUB_TOKEN=$(gh auth token)")?;
                 }
+                writeln!(
+                    f,
+                    "  To wait it out instead, raise --max-rate-limit-wait (seconds)."
+                )?;
+            }
+            Self::DraftsRequireToken { repo } => {
+                writeln!(f, "Draft releases require authentication")?;
+                writeln!(f, "  Repository: {}", repo)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Drafts are only visible to users with push access to the repository."
+                )?;
+                writeln!(f, "  Try: export GITHUB_TOKEN=$(gh auth token)")?;
+            }
+            Self::RefNotFound {
+                repo,
+                reference,
+                url,
+            } => {
+                writeln!(f, "Git reference not found")?;
+                writeln!(f, "  Repository: {}", repo)?;
+                writeln!(f, "  Reference: {}", reference)?;
+                writeln!(f, "  URL: {}", url)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Check that it exists and that your token can access the repository."
+                )?;
             }
         }
         Ok(())
@@ -170,35 +266,390 @@ impl std::fmt::Display for GitHubError {
 
 impl std::error::Error for GitHubError {}
 
-/// A release asset resolved from GitHub metadata, ready to download.
-///
-/// Produced by [`GitHubClient::resolve_release_assets`] and consumed by
-/// [`GitHubClient::download_resolved_asset`]. This allows fetching release
-/// metadata once and then downloading multiple assets without redundant API
-/// calls.
+/// Summary of a published release, as returned by [`GitHubClient::list_releases`].
 #[derive(Clone, Debug)]
-pub struct ResolvedAsset {
-    /// Asset ID on GitHub (used for API-based downloads of private repos).
-    pub id: u64,
-    /// Asset name (e.g. "ampd-linux-x86_64").
+pub struct ReleaseSummary {
+    /// Release tag (e.g. "v0.3.0").
+    pub tag: String,
+    /// Whether the release is marked as a pre-release.
+    pub prerelease: bool,
+}
+
+/// A published release with its notes, as returned by
+/// [`GitHubClient::list_release_notes`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReleaseNotes {
+    #[serde(rename = "tag_name")]
+    pub tag: String,
+    /// Release title, when it differs from the tag
+    #[serde(default)]
+    pub name: Option<String>,
+    /// RFC 3339 timestamp (e.g. "2026-09-01T12:00:00Z")
+    #[serde(default)]
+    pub published_at: Option<String>,
+    /// Release notes in Markdown
+    #[serde(default)]
+    pub body: Option<String>,
+    pub html_url: String,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+}
+
+/// A merged pull request, as returned by [`GitHubClient::merged_pull_requests`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MergedPullRequest {
+    pub number: u64,
+    pub title: String,
+    pub html_url: String,
+    #[serde(default)]
+    pub labels: Vec<Label>,
+    /// Number of comments, a rough measure of how much it was discussed
+    #[serde(default)]
+    pub comments: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Label {
     pub name: String,
-    /// Direct browser download URL (used for public repos).
+}
+
+/// A discussion in the repository's announcements category, as returned by
+/// [`GitHubClient::announcements`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Announcement {
+    pub title: String,
     pub url: String,
+    /// RFC 3339 timestamp
+    pub created_at: String,
 }
 
-#[derive(Debug, Deserialize)]
+/// Head commit of a pull request, as returned by [`GitHubClient::get_pull_request`].
+#[derive(Clone, Debug)]
+pub struct PullRequestHead {
+    /// Full SHA of the head commit.
+    pub sha: String,
+    /// `owner/repo` the head branch lives in (differs from the base for forks).
+    pub repo: String,
+    /// Head branch name.
+    pub branch: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
 struct Release {
     #[serde(rename = "tag_name")]
     tag: String,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
+    /// Set by release APIs that track yanked releases server-side
+    #[serde(default)]
+    yanked: bool,
+    #[serde(default)]
     assets: Vec<Asset>,
 }
 
+impl Release {
+    /// Whether the release was withdrawn, through the `yanked` field or a
+    /// [`YANKED_ASSET`] uploaded to it.
+    fn is_yanked(&self) -> bool {
+        self.yanked || self.assets.iter().any(|a| a.name == YANKED_ASSET)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequest {
+    head: PullRequestRef,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequestRef {
+    sha: String,
+    #[serde(rename = "ref")]
+    branch: String,
+    repo: Option<PullRequestRepo>,
+}
+
 #[derive(Debug, Deserialize)]
+struct PullRequestRepo {
+    full_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Commit {
+    sha: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
 struct Asset {
     id: u64,
     name: String,
     #[serde(rename = "browser_download_url")]
     url: String,
+    /// Content digest (e.g. `sha256:...`), present on newer releases
+    #[serde(default)]
+    digest: Option<String>,
+}
+
+/// Releases fetched in a single GraphQL request.
+#[derive(Debug)]
+struct ReleaseSnapshot {
+    latest: Option<String>,
+    /// Newest first, drafts included when visible to the token.
+    releases: Vec<Release>,
+}
+
+impl ReleaseSnapshot {
+    /// Build a snapshot from a GraphQL response, or `None` if the query
+    /// reported errors or returned no repository.
+    fn from_response(response: GraphQlResponse) -> Option<Self> {
+        if !response.errors.is_empty() {
+            return None;
+        }
+        let repository = response.data?.repository?;
+
+        let releases = repository
+            .releases
+            .nodes
+            .into_iter()
+            .map(|r| Release {
+                tag: r.tag_name,
+                prerelease: r.is_prerelease,
+                draft: r.is_draft,
+                yanked: false,
+                assets: r
+                    .release_assets
+                    .nodes
+                    .into_iter()
+                    .map(|a| Asset {
+                        id: a.database_id,
+                        name: a.name,
+                        url: a.download_url,
+                        digest: a.digest,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Some(Self {
+            latest: repository.latest_release.map(|r| r.tag_name),
+            releases,
+        })
+    }
+
+    /// The release tagged `version`; drafts only when `include_drafts`.
+    fn tagged(&self, version: &str, include_drafts: bool) -> Option<&Release> {
+        self.releases
+            .iter()
+            .find(|r| r.tag == version && (include_drafts || !r.draft))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResults<T> {
+    total_count: u64,
+    items: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlResponse {
+    data: Option<GraphQlData>,
+    #[serde(default)]
+    errors: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlData {
+    repository: Option<GraphQlRepository>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlRepository {
+    latest_release: Option<GraphQlTag>,
+    releases: GraphQlNodes<GraphQlRelease>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlTag {
+    tag_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlNodes<T> {
+    nodes: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlRelease {
+    tag_name: String,
+    is_draft: bool,
+    is_prerelease: bool,
+    release_assets: GraphQlNodes<GraphQlAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlAsset {
+    database_id: u64,
+    name: String,
+    download_url: String,
+    digest: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlDiscussionsResponse {
+    data: Option<GraphQlDiscussionsData>,
+    #[serde(default)]
+    errors: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlDiscussionsData {
+    repository: Option<GraphQlDiscussionsRepository>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlDiscussionsRepository {
+    discussions: GraphQlNodes<GraphQlDiscussion>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlDiscussion {
+    title: String,
+    url: String,
+    created_at: String,
+    category: GraphQlCategory,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlCategory {
+    name: String,
+}
+
+/// Timeouts applied to every request made by a [`GitHubClient`].
+///
+/// `None` leaves the corresponding reqwest default in place (no timeout).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HttpTimeouts {
+    /// Time allowed to establish a connection.
+    pub connect: Option<Duration>,
+    /// Time allowed between successive reads of a response body.
+    pub read: Option<Duration>,
+    /// Time allowed for the whole request, from connect to the end of the body.
+    pub total: Option<Duration>,
+}
+
+impl HttpTimeouts {
+    /// Fill any unset timeout from `fallback`.
+    pub fn or(self, fallback: HttpTimeouts) -> HttpTimeouts {
+        HttpTimeouts {
+            connect: self.connect.or(fallback.connect),
+            read: self.read.or(fallback.read),
+            total: self.total.or(fallback.total),
+        }
+    }
+
+    pub(crate) fn apply(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        if let Some(connect) = self.connect {
+            builder = builder.connect_timeout(connect);
+        }
+        if let Some(read) = self.read {
+            builder = builder.read_timeout(read);
+        }
+        if let Some(total) = self.total {
+            builder = builder.timeout(total);
+        }
+        builder
+    }
+}
+
+/// Connection options for a [`GitHubClient`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClientOptions {
+    /// Request timeouts.
+    pub timeouts: HttpTimeouts,
+    /// REST API base URL for GitHub Enterprise Server
+    /// (e.g. `https://github.mycorp.com/api/v3`). `None` means github.com.
+    pub api_url: Option<String>,
+    /// Longest rate-limit pause to wait out before failing.
+    pub max_rate_limit_wait: Option<Duration>,
+    /// Which API release metadata of amp and ampup is read from.
+    pub metadata_endpoint: Option<MetadataEndpoint>,
+}
+
+impl ClientOptions {
+    /// Fill any unset option from `fallback`.
+    pub fn or(self, fallback: ClientOptions) -> ClientOptions {
+        ClientOptions {
+            timeouts: self.timeouts.or(fallback.timeouts),
+            api_url: self.api_url.or(fallback.api_url),
+            max_rate_limit_wait: self.max_rate_limit_wait.or(fallback.max_rate_limit_wait),
+            metadata_endpoint: self.metadata_endpoint.or(fallback.metadata_endpoint),
+        }
+    }
+
+    /// REST API base URL, without a trailing slash.
+    pub fn github_api(&self) -> &str {
+        self.api_url
+            .as_deref()
+            .map(|url| url.trim_end_matches('/'))
+            .unwrap_or(GITHUB_API_URL)
+    }
+
+    /// GraphQL endpoint, served at `<host>/api/graphql` by GitHub Enterprise.
+    pub fn graphql_api(&self) -> String {
+        let api = self.github_api();
+        match api.strip_suffix("/api/v3") {
+            Some(host) => format!("{}/api/graphql", host),
+            None => format!("{}/graphql", api),
+        }
+    }
+
+    /// Web base URL used for cloning, derived from the API URL.
+    ///
+    /// GitHub Enterprise serves its API under `<host>/api/v3`, so stripping
+    /// that suffix yields the web host.
+    pub fn web_url(&self) -> String {
+        match self.api_url.as_deref() {
+            None => GITHUB_WEB_URL.to_string(),
+            Some(url) => {
+                let url = url.trim_end_matches('/');
+                if url == GITHUB_API_URL {
+                    GITHUB_WEB_URL.to_string()
+                } else {
+                    url.trim_end_matches("/api/v3").to_string()
+                }
+            }
+        }
+    }
+
+    /// Base URLs release asset downloads are redirected to. GitHub
+    /// Enterprise serves assets from its own host.
+    pub fn asset_redirect_urls(&self) -> Vec<String> {
+        match self.enterprise_host() {
+            Some(_) => Vec::new(),
+            None => GITHUB_ASSET_URLS
+                .iter()
+                .map(|url| url.to_string())
+                .collect(),
+        }
+    }
+
+    /// Host name of a GitHub Enterprise instance, or `None` for github.com.
+    pub fn enterprise_host(&self) -> Option<String> {
+        let url = self.api_url.as_deref()?;
+        if url.trim_end_matches('/') == GITHUB_API_URL {
+            return None;
+        }
+        let host = url.split_once("://").map_or(url, |(_, rest)| rest);
+        Some(host.split('/').next().unwrap_or(host).to_string())
+    }
 }
 
 /// Cloneable so `DownloadManager` can move a handle into each spawned task.
@@ -209,13 +660,47 @@ pub struct GitHubClient {
     client: reqwest::Client,
     repo: String,
     token: Option<String>,
-    /// Base URL for API requests (either custom API or GitHub API)
+    /// Base URL for release requests (either custom API or GitHub API)
     api: String,
+    /// Endpoints to probe on the first release request, in place of `api`
+    endpoint_selection: Option<Arc<EndpointSelection>>,
+    /// Release API base URL picked by `endpoint_selection`, shared between
+    /// clones so the probe runs once per invocation
+    selected_api: Arc<OnceCell<String>>,
+    /// GitHub REST API base URL for non-release requests
+    github_api: String,
     rate_limiter: Arc<GitHubRateLimiter>,
+    /// Whether tagged release lookups fall back to draft releases
+    include_drafts: bool,
+    /// GraphQL endpoint, used only by authenticated clients
+    graphql_api: Option<String>,
+    /// Releases fetched over GraphQL, shared between clones so one request
+    /// serves every lookup in an invocation. `None` once the query failed.
+    snapshot: Arc<OnceCell<Option<ReleaseSnapshot>>>,
+    /// Tagged releases already fetched over REST, shared between clones
+    tagged: Arc<Mutex<HashMap<String, Release>>>,
+}
+
+/// Release metadata being fetched in the background, e.g. while a prompt
+/// waits for the user. Dropping it cancels the fetch.
+pub struct Prefetch(tokio::task::JoinHandle<()>);
+
+impl Prefetch {
+    /// Wait for the fetch to finish, so the lookup it warms isn't sent
+    /// twice. Failures are left for that lookup to report.
+    pub async fn finish(mut self) {
+        let _ = (&mut self.0).await;
+    }
+}
+
+impl Drop for Prefetch {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
 }
 
 impl GitHubClient {
-    pub fn new(repo: String, github_token: Option<String>) -> Result<Self> {
+    pub fn new(repo: String, github_token: Option<String>, options: ClientOptions) -> Result<Self> {
         let mut headers = reqwest::header::HeaderMap::new();
         headers.insert(
             reqwest::header::USER_AGENT,
@@ -231,58 +716,654 @@ impl GitHubClient {
             );
         }
 
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()
-            .context("Failed to create request client")?;
+        let client = options
+            .timeouts
+            .apply(reqwest::Client::builder().default_headers(headers))
+            .build()
+            .context("Failed to create request client")?;
+
+        let mut api = match &options.api_url {
+            Some(_) => format!("{}/repos/{}/releases", options.github_api(), repo),
+            None => release_api_base(&repo),
+        };
+        let mut endpoint_selection = None;
+        if options.api_url.is_none() && repo_slug(&repo).is_some() {
+            let github = format!("{}/repos/{}/releases", GITHUB_API_URL, repo);
+            match options.metadata_endpoint.unwrap_or_default() {
+                MetadataEndpoint::Auto => {
+                    endpoint_selection = Some(Arc::new(EndpointSelection {
+                        ampup: api.clone(),
+                        github,
+                        cache: None,
+                    }));
+                }
+                MetadataEndpoint::Github => api = github,
+                MetadataEndpoint::Ampup => {}
+            }
+        }
+        let github_api = options.github_api().to_string();
+        let graphql_api = github_token.as_ref().map(|_| options.graphql_api());
+
+        let rate_limiter = Arc::new(
+            GitHubRateLimiter::new(github_token.is_some()).with_max_wait(
+                options
+                    .max_rate_limit_wait
+                    .unwrap_or(rate_limiter::DEFAULT_MAX_WAIT),
+            ),
+        );
+
+        Ok(Self {
+            client,
+            repo,
+            token: github_token,
+            api,
+            endpoint_selection,
+            selected_api: Arc::new(OnceCell::new()),
+            github_api,
+            rate_limiter,
+            include_drafts: false,
+            graphql_api,
+            snapshot: Arc::new(OnceCell::new()),
+            tagged: Arc::default(),
+        })
+    }
+
+    /// Create a client with a custom API base URL for testing.
+    ///
+    /// `api_base` replaces the standard GitHub API URL so requests go to a
+    /// local mock server instead.
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn with_api_base(api_base: String) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .build()
+            .context("Failed to create request client")?;
+        let rate_limiter = Arc::new(GitHubRateLimiter::new(false));
+
+        Ok(Self {
+            client,
+            repo: "test/repo".to_string(),
+            token: None,
+            github_api: api_base.clone(),
+            api: api_base,
+            endpoint_selection: None,
+            selected_api: Arc::new(OnceCell::new()),
+            rate_limiter,
+            include_drafts: false,
+            graphql_api: None,
+            snapshot: Arc::new(OnceCell::new()),
+            tagged: Arc::default(),
+        })
+    }
+
+    /// Let tagged release lookups fall back to draft releases, which GitHub
+    /// only exposes to authenticated users with push access.
+    ///
+    /// Fails with `GitHubError::DraftsRequireToken` when the client has no
+    /// token.
+    pub fn with_drafts(mut self) -> Result<Self> {
+        if self.token.is_none() {
+            return Err(GitHubError::DraftsRequireToken { repo: self.repo }.into());
+        }
+        self.include_drafts = true;
+        Ok(self)
+    }
+
+    /// Persist rate-limit state under `cache_dir`, so a pause hit by one
+    /// invocation is honoured by the next.
+    ///
+    /// State is kept per API host and per authenticated/anonymous client,
+    /// since GitHub tracks those limits separately.
+    pub fn with_rate_limit_state(mut self, cache_dir: &Path) -> Self {
+        let host = self
+            .github_api
+            .split_once("://")
+            .map_or(self.github_api.as_str(), |(_, rest)| rest)
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .replace(':', "_");
+        let auth = if self.token.is_some() {
+            "authenticated"
+        } else {
+            "anonymous"
+        };
+        let path = cache_dir.join(format!("rate-limit-{}-{}.json", host, auth));
+        self.rate_limiter = Arc::new(
+            GitHubRateLimiter::with_state_file(self.token.is_some(), path)
+                .with_max_wait(self.rate_limiter.max_wait()),
+        );
+        self
+    }
+
+    /// Remember which metadata endpoint a probe picked under `cache_dir`, so
+    /// later invocations skip the probe.
+    pub fn with_endpoint_cache(mut self, cache_dir: &Path) -> Self {
+        self.endpoint_selection = self.endpoint_selection.map(|selection| {
+            Arc::new(EndpointSelection {
+                ampup: selection.ampup.clone(),
+                github: selection.github.clone(),
+                cache: Some(EndpointCache::new(cache_dir)),
+            })
+        });
+        self
+    }
+
+    /// The `owner/repo` this client reads releases from.
+    pub fn repo(&self) -> &str {
+        &self.repo
+    }
+
+    /// The resolved GitHub token, reused to authenticate with ghcr.io.
+    pub(crate) fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// Requests [`Self::get_latest_version`] sends, for
+    /// `ampup debug requests`.
+    pub fn plan_latest_version(&self) -> Vec<PlannedRequest> {
+        match self.plan_release_snapshot() {
+            Some(request) => vec![request],
+            None => self.plan_rest("latest", "Look up the latest release"),
+        }
+    }
+
+    /// Requests [`Self::list_releases`] sends.
+    pub fn plan_list_releases(&self) -> Vec<PlannedRequest> {
+        match self.plan_release_snapshot() {
+            Some(request) => vec![request],
+            None => self.plan_rest("?per_page=100", "List the newest 100 releases"),
+        }
+    }
+
+    /// Requests resolving `asset_names` in the release tagged `version` and
+    /// downloading them send. Assets are shown by their bare name, though the
+    /// release may publish them compressed or archived instead.
+    pub(crate) fn plan_release_assets(
+        &self,
+        version: &str,
+        asset_names: &[&str],
+    ) -> Vec<PlannedRequest> {
+        let mut requests = match self.plan_release_snapshot() {
+            Some(request) => vec![request],
+            None => self.plan_rest(
+                &format!("tags/{}", version),
+                format!("Fetch release {} and its asset list", version),
+            ),
+        };
+
+        let web_url = ClientOptions {
+            api_url: Some(self.github_api.clone()),
+            ..Default::default()
+        }
+        .web_url();
+        for name in asset_names {
+            if self.token.is_some() {
+                let url = format!(
+                    "{}/repos/{}/releases/assets/<{} asset id>",
+                    self.github_api, self.repo, name
+                );
+                requests.push(
+                    self.planned("GET", url, format!("Download {}", name))
+                        .with_header("Accept", "application/octet-stream"),
+                );
+            } else {
+                let url = format!(
+                    "{}/{}/releases/download/{}/{}",
+                    web_url, self.repo, version, name
+                );
+                requests.push(self.planned(
+                    "HEAD",
+                    url.clone(),
+                    format!("Check that {} is still published", name),
+                ));
+                requests.push(self.planned(
+                    "GET",
+                    url,
+                    format!("Download {} (redirects to the release asset host)", name),
+                ));
+            }
+        }
+        requests
+    }
+
+    /// A GET of `path` under the release API, preceded by the endpoint probe
+    /// when the client picks its endpoint automatically.
+    fn plan_rest(&self, path: &str, purpose: impl Into<String>) -> Vec<PlannedRequest> {
+        let mut requests = Vec::new();
+        if let Some(selection) = &self.endpoint_selection {
+            for api in [&selection.ampup, &selection.github] {
+                requests.push(self.planned(
+                    "HEAD",
+                    format!("{}/latest", api),
+                    "Probe a release metadata endpoint (once per hour)",
+                ));
+            }
+        }
+        let separator = if path.starts_with('?') { "" } else { "/" };
+        requests.push(self.planned("GET", format!("{}{}{}", self.api, separator, path), purpose));
+        requests
+    }
+
+    /// The GraphQL release query, which answers every release lookup of a
+    /// run when the client has a token.
+    fn plan_release_snapshot(&self) -> Option<PlannedRequest> {
+        let url = self.graphql_api.clone()?;
+        let (owner, name) = self.repo.split_once('/')?;
+        let body = serde_json::json!({
+            "query": RELEASES_QUERY,
+            "variables": { "owner": owner, "name": name },
+        });
+        Some(
+            self.planned(
+                "POST",
+                url,
+                "Fetch the latest release and the newest 100 releases with their assets",
+            )
+            .with_header("Content-Type", "application/json")
+            .with_body(body.to_string()),
+        )
+    }
+
+    /// A request from this client, carrying its token when it has one.
+    fn planned(
+        &self,
+        method: &'static str,
+        url: String,
+        purpose: impl Into<String>,
+    ) -> PlannedRequest {
+        PlannedRequest::new(method, url, purpose)
+            .with_auth(self.token.is_some().then_some(PlannedAuth::Bearer))
+    }
+
+    /// Get the latest release version, skipping yanked releases
+    pub async fn get_latest_version(&self) -> Result<String> {
+        let release = self.get_latest_release().await?;
+        if !release.is_yanked() {
+            return Ok(release.tag);
+        }
+
+        crate::ui::warn!(
+            "Latest release {} is yanked, using the newest release before it",
+            release.tag
+        );
+        let releases = self.list_releases().await?;
+        match releases.into_iter().find(|r| !r.prerelease) {
+            Some(release) => Ok(release.tag),
+            None => Err(GitHubError::ReleaseNotFound {
+                repo: self.repo.clone(),
+                has_token: self.token.is_some(),
+                url: format!("{}/latest", self.release_api().await),
+                is_latest: true,
+            }
+            .into()),
+        }
+    }
+
+    /// Start fetching the release tagged `version` and its asset metadata
+    /// (names, URLs and digests), so a later install of it resolves
+    /// assets without waiting on the network.
+    pub fn prefetch_release(&self, version: &str) -> Prefetch {
+        let client = self.clone();
+        let version = version.to_string();
+        Prefetch(tokio::spawn(async move {
+            let _ = client.get_tagged_release(&version).await;
+        }))
+    }
+
+    /// Whether the release tagged `version` is yanked.
+    pub async fn is_yanked(&self, version: &str) -> Result<bool> {
+        Ok(self.get_tagged_release(version).await?.is_yanked())
+    }
+
+    /// Names of the assets the release tagged `version` publishes.
+    pub async fn release_asset_names(&self, version: &str) -> Result<Vec<String>> {
+        let release = self.get_tagged_release(version).await?;
+        Ok(release.assets.iter().map(|a| a.name.clone()).collect())
+    }
+
+    /// Whether the release tagged `version` is marked as a prerelease.
+    pub async fn is_prerelease(&self, version: &str) -> Result<bool> {
+        Ok(self.get_tagged_release(version).await?.prerelease)
+    }
+
+    /// Get the latest release
+    async fn get_latest_release(&self) -> Result<Release> {
+        if let Some(snapshot) = self.release_snapshot().await
+            && let Some(latest) = &snapshot.latest
+            && let Some(release) = snapshot.tagged(latest, false)
+        {
+            return Ok(release.clone());
+        }
+        self.get_release("latest").await
+    }
+
+    /// Releases fetched over GraphQL, or `None` when the client has no
+    /// token or the query failed, in which case callers use the REST API.
+    ///
+    /// The query runs at most once per client and its clones.
+    async fn release_snapshot(&self) -> Option<&ReleaseSnapshot> {
+        let url = self.graphql_api.as_deref()?;
+        self.snapshot
+            .get_or_init(|| async { self.fetch_release_snapshot(url).await.ok().flatten() })
+            .await
+            .as_ref()
+    }
+
+    /// Run [`RELEASES_QUERY`] against `url`.
+    async fn fetch_release_snapshot(&self, url: &str) -> Result<Option<ReleaseSnapshot>> {
+        let Some((owner, name)) = self.repo.split_once('/') else {
+            return Ok(None);
+        };
+        let body = serde_json::json!({
+            "query": RELEASES_QUERY,
+            "variables": { "owner": owner, "name": name },
+        });
+
+        let response = self
+            .send_with_rate_limit(
+                || self.client.post(url).json(&body),
+                "Failed to query releases",
+            )
+            .await?;
+        if !response.status().is_success() {
+            return Ok(None);
+        }
+
+        let response: GraphQlResponse = response
+            .json()
+            .await
+            .context("Failed to parse GraphQL response")?;
+        Ok(ReleaseSnapshot::from_response(response))
+    }
+
+    /// List published releases, newest first.
+    ///
+    /// Drafts and yanked releases are skipped. Only the first page (100 releases) is fetched,
+    /// which covers every version a user would reasonably resolve against.
+    pub async fn list_releases(&self) -> Result<Vec<ReleaseSummary>> {
+        let releases = match self.release_snapshot().await {
+            Some(snapshot) => snapshot.releases.clone(),
+            None => {
+                let url = format!("{}?per_page=100", self.release_api().await);
+                let response = self
+                    .send_with_rate_limit(|| self.client.get(&url), "Failed to list releases")
+                    .await?;
+                let response = self.check_release_response(response, &url, false).await?;
+
+                response
+                    .json::<Vec<Release>>()
+                    .await
+                    .context("Failed to parse release list response")?
+            }
+        };
+
+        Ok(releases
+            .into_iter()
+            .filter(|r| !r.draft && !r.is_yanked())
+            .map(|r| ReleaseSummary {
+                tag: r.tag,
+                prerelease: r.prerelease,
+            })
+            .collect())
+    }
+
+    /// List published releases with their notes, newest first.
+    ///
+    /// Always reads the GitHub API, since release mirrors don't serve notes.
+    /// Drafts are skipped and only the first page (100 releases) is fetched.
+    pub async fn list_release_notes(&self) -> Result<Vec<ReleaseNotes>> {
+        let url = format!(
+            "{}/repos/{}/releases?per_page=100",
+            self.github_api, self.repo
+        );
+        let releases: Vec<ReleaseNotes> = self.get_repo_json(&url, "releases").await?;
+        Ok(releases.into_iter().filter(|r| !r.draft).collect())
+    }
+
+    /// Get the release tagged `version` with its notes.
+    pub async fn get_release_notes(&self, version: &str) -> Result<ReleaseNotes> {
+        let url = format!(
+            "{}/repos/{}/releases/tags/{}",
+            self.github_api, self.repo, version
+        );
+        self.get_repo_json(&url, &format!("release {}", version))
+            .await
+    }
+
+    /// Pull requests merged on or after `since` (a `YYYY-MM-DD` date), most
+    /// discussed first, along with how many matched in total.
+    ///
+    /// Only the first page (100 pull requests) is returned.
+    pub async fn merged_pull_requests(&self, since: &str) -> Result<(u64, Vec<MergedPullRequest>)> {
+        let url = format!("{}/search/issues", self.github_api);
+        let query = format!("repo:{} is:pr is:merged merged:>={}", self.repo, since);
+        let response = self
+            .send_with_rate_limit(
+                || {
+                    self.client.get(&url).query(&[
+                        ("q", query.as_str()),
+                        ("sort", "comments"),
+                        ("order", "desc"),
+                        ("per_page", "100"),
+                    ])
+                },
+                "Failed to search pull requests",
+            )
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(GitHubError::HttpError {
+                repo: self.repo.clone(),
+                status_code: status.as_u16(),
+                url,
+                body,
+            }
+            .into());
+        }
+
+        let results: SearchResults<MergedPullRequest> = response
+            .json()
+            .await
+            .context("Failed to parse pull request search response")?;
+        Ok((results.total_count, results.items))
+    }
+
+    /// Discussions in the repository's "Announcements" category created at
+    /// or after `since` (an RFC 3339 timestamp), newest first.
+    ///
+    /// Returns `None` when the client has no token, since discussions are
+    /// only available over GraphQL.
+    pub async fn announcements(&self, since: &str) -> Result<Option<Vec<Announcement>>> {
+        let (Some(url), Some((owner, name))) = (&self.graphql_api, self.repo.split_once('/'))
+        else {
+            return Ok(None);
+        };
+        let body = serde_json::json!({
+            "query": DISCUSSIONS_QUERY,
+            "variables": { "owner": owner, "name": name },
+        });
+
+        let response = self
+            .send_with_rate_limit(
+                || self.client.post(url).json(&body),
+                "Failed to query discussions",
+            )
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(GitHubError::HttpError {
+                repo: self.repo.clone(),
+                status_code: status.as_u16(),
+                url: url.clone(),
+                body,
+            }
+            .into());
+        }
+
+        let response: GraphQlDiscussionsResponse = response
+            .json()
+            .await
+            .context("Failed to parse GraphQL response")?;
+        if let Some(error) = response.errors.first() {
+            anyhow::bail!("GitHub GraphQL query failed: {}", error);
+        }
 
-        let api = release_api_base(&repo);
+        let discussions = response
+            .data
+            .and_then(|data| data.repository)
+            .map(|repository| repository.discussions.nodes)
+            .unwrap_or_default();
+        Ok(Some(
+            discussions
+                .into_iter()
+                .filter(|d| {
+                    d.category.name.eq_ignore_ascii_case("announcements")
+                        && d.created_at.as_str() >= since
+                })
+                .map(|d| Announcement {
+                    title: d.title,
+                    url: d.url,
+                    created_at: d.created_at,
+                })
+                .collect(),
+        ))
+    }
 
-        let rate_limiter = Arc::new(GitHubRateLimiter::new(github_token.is_some()));
+    /// Look up the head commit of a pull request.
+    pub async fn get_pull_request(&self, number: u32) -> Result<PullRequestHead> {
+        let url = format!("{}/repos/{}/pulls/{}", self.github_api, self.repo, number);
+        let pr: PullRequest = self
+            .get_repo_json(&url, &format!("pull request #{}", number))
+            .await?;
 
-        Ok(Self {
-            client,
-            repo,
-            token: github_token,
-            api,
-            rate_limiter,
+        Ok(PullRequestHead {
+            sha: pr.head.sha,
+            repo: pr
+                .head
+                .repo
+                .map(|r| r.full_name)
+                .unwrap_or_else(|| self.repo.clone()),
+            branch: pr.head.branch,
         })
     }
 
-    /// Create a client with a custom API base URL for testing.
-    ///
-    /// `api_base` replaces the standard GitHub API URL so requests go to a
-    /// local mock server instead.
-    #[cfg(test)]
-    pub(crate) fn with_api_base(api_base: String) -> Result<Self> {
-        let client = reqwest::Client::builder()
-            .build()
-            .context("Failed to create request client")?;
-        let rate_limiter = Arc::new(GitHubRateLimiter::new(false));
+    /// Expand a (possibly abbreviated) commit reference to its full SHA.
+    pub async fn get_commit_sha(&self, reference: &str) -> Result<String> {
+        let url = format!(
+            "{}/repos/{}/commits/{}",
+            self.github_api, self.repo, reference
+        );
+        let commit: Commit = self
+            .get_repo_json(&url, &format!("commit {}", reference))
+            .await?;
+        Ok(commit.sha)
+    }
 
-        Ok(Self {
-            client,
-            repo: "test/repo".to_string(),
-            token: None,
-            api: api_base,
-            rate_limiter,
-        })
+    /// Fetch a JSON document from the repository API, mapping 404 to
+    /// `GitHubError::RefNotFound`.
+    async fn get_repo_json<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        reference: &str,
+    ) -> Result<T> {
+        let response = self
+            .send_with_rate_limit(|| self.client.get(url), "Failed to query repository")
+            .await?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(GitHubError::RefNotFound {
+                repo: self.repo.clone(),
+                reference: reference.to_string(),
+                url: url.to_string(),
+            }
+            .into());
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(GitHubError::HttpError {
+                repo: self.repo.clone(),
+                status_code: status.as_u16(),
+                url: url.to_string(),
+                body,
+            }
+            .into());
+        }
+
+        response
+            .json()
+            .await
+            .context("Failed to parse repository API response")
     }
 
-    /// Get the latest release version
-    pub async fn get_latest_version(&self) -> Result<String> {
-        let release = self.get_latest_release().await?;
-        Ok(release.tag)
+    /// Get a tagged release, or a draft with that tag when drafts are
+    /// included.
+    async fn get_tagged_release(&self, version: &str) -> Result<Release> {
+        if let Some(release) = self
+            .release_snapshot()
+            .await
+            .and_then(|snapshot| snapshot.tagged(version, self.include_drafts))
+        {
+            if release.draft {
+                crate::ui::detail!("Using draft release {}", crate::ui::version(version));
+            }
+            return Ok(release.clone());
+        }
+        if let Some(release) = self.cached_release(version) {
+            return Ok(release);
+        }
+
+        let result = self.get_release(&format!("tags/{}", version)).await;
+        let not_found = matches!(
+            result
+                .as_ref()
+                .map_err(|err| err.downcast_ref::<GitHubError>()),
+            Err(Some(GitHubError::ReleaseNotFound { .. }))
+        );
+        let release = if self.include_drafts
+            && not_found
+            && let Some(draft) = self.find_draft_release(version).await?
+        {
+            crate::ui::detail!("Using draft release {}", crate::ui::version(version));
+            draft
+        } else {
+            result?
+        };
+
+        if let Ok(mut tagged) = self.tagged.lock() {
+            tagged.insert(version.to_string(), release.clone());
+        }
+        Ok(release)
     }
 
-    /// Get the latest release
-    async fn get_latest_release(&self) -> Result<Release> {
-        self.get_release("latest").await
+    /// A tagged release already fetched by this client or a clone.
+    fn cached_release(&self, version: &str) -> Option<Release> {
+        self.tagged.lock().ok()?.get(version).cloned()
     }
 
-    /// Get a tagged release
-    async fn get_tagged_release(&self, version: &str) -> Result<Release> {
-        self.get_release(&format!("tags/{}", version)).await
+    /// Find an unpublished draft release by its tag.
+    ///
+    /// Drafts aren't served by the `tags/` endpoint or the ampup API, so the
+    /// repository's release list is searched through the GitHub API.
+    async fn find_draft_release(&self, version: &str) -> Result<Option<Release>> {
+        let url = format!(
+            "{}/repos/{}/releases?per_page=100",
+            self.github_api, self.repo
+        );
+        let response = self
+            .send_with_rate_limit(|| self.client.get(&url), "Failed to list releases")
+            .await?;
+        let response = self.check_release_response(response, &url, false).await?;
+
+        let releases: Vec<Release> = response
+            .json()
+            .await
+            .context("Failed to parse release list response")?;
+
+        Ok(releases.into_iter().find(|r| r.draft && r.tag == version))
     }
 
     /// Wait for any active rate-limit pause, or fail if the wait is too long.
@@ -297,27 +1378,32 @@ impl GitHubClient {
         Ok(())
     }
 
-    /// Find an asset by name within a release, returning `AssetNotFound` if
-    /// no match exists.
+    /// Find an asset by name within a release, preferring a zstd-compressed
+    /// `<name>.zst` and falling back to an archive of it (e.g.
+    /// `<name>.tar.gz`), returning `AssetNotFound` if none exists.
     fn find_asset<'a>(
         &self,
         release: &'a Release,
         asset_name: &str,
         version: &str,
     ) -> Result<&'a Asset> {
-        release
-            .assets
-            .iter()
-            .find(|a| a.name == asset_name)
-            .ok_or_else(|| {
-                GitHubError::AssetNotFound {
-                    repo: self.repo.clone(),
-                    asset_name: asset_name.to_string(),
-                    version: version.to_string(),
-                    available_assets: release.assets.iter().map(|a| a.name.clone()).collect(),
-                }
-                .into()
-            })
+        let archive_names = archive_suffixes().map(|suffix| format!("{}{}", asset_name, suffix));
+        [
+            format!("{}{}", asset_name, COMPRESSED_SUFFIX),
+            asset_name.to_string(),
+        ]
+        .into_iter()
+        .chain(archive_names)
+        .find_map(|name| release.assets.iter().find(|a| a.name == name))
+        .ok_or_else(|| {
+            GitHubError::AssetNotFound {
+                repo: self.repo.clone(),
+                asset_name: asset_name.to_string(),
+                version: version.to_string(),
+                available_assets: release.assets.iter().map(|a| a.name.clone()).collect(),
+            }
+            .into()
+        })
     }
 
     /// Resolve multiple asset names from a single release, fetching the release
@@ -340,6 +1426,7 @@ impl GitHubClient {
                 id: asset.id,
                 name: asset.name.clone(),
                 url: asset.url.clone(),
+                digest: asset.digest.clone(),
             });
         }
         Ok(resolved)
@@ -347,14 +1434,33 @@ impl GitHubClient {
 
     /// Download a previously resolved asset without re-fetching release
     /// metadata.
-    pub async fn download_resolved_asset(&self, asset: &ResolvedAsset) -> Result<Vec<u8>> {
+    ///
+    /// Large assets are split into up to `chunks` concurrent ranged requests.
+    pub async fn download_resolved_asset(
+        &self,
+        asset: &ResolvedAsset,
+        chunks: usize,
+    ) -> Result<Vec<u8>> {
         if self.token.is_some() {
-            self.download_asset_via_api(asset.id, &asset.name).await
+            self.download_asset_via_api(asset.id, &asset.name, chunks)
+                .await
         } else {
-            self.download_asset_direct(&asset.url, &asset.name).await
+            self.download_asset_direct(&asset.url, &asset.name, chunks)
+                .await
         }
     }
 
+    /// Check that a previously resolved asset is still downloadable.
+    ///
+    /// Authenticated clients trust the release metadata, since browser
+    /// download URLs of private repositories can't be probed with a token.
+    pub async fn probe_resolved_asset(&self, asset: &ResolvedAsset) -> bool {
+        if self.token.is_some() {
+            return true;
+        }
+        head_available(&self.client, &asset.url).await
+    }
+
     /// Send a request with rate-limit awareness, one retry on 429, and one
     /// retry on transient server/transport errors.
     ///
@@ -378,7 +1484,7 @@ impl GitHubClient {
             Err(first_err) => {
                 // One retry on transport errors (connection reset, DNS, timeout)
                 crate::ui::warn!("Request failed ({}), retrying once...", first_err);
-                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                tokio::time::sleep(rate_limiter::jittered(Duration::from_secs(1))).await;
                 self.check_rate_limit_pause().await?;
 
                 build_request().send().await.with_context(|| {
@@ -422,7 +1528,7 @@ impl GitHubClient {
                 "Server error (HTTP {}), retrying once...",
                 response.status().as_u16()
             );
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            tokio::time::sleep(rate_limiter::jittered(Duration::from_secs(1))).await;
             self.check_rate_limit_pause().await?;
 
             let response = build_request()
@@ -444,46 +1550,29 @@ impl GitHubClient {
         Ok(response)
     }
 
+    /// Base URL for release requests, probing the candidate endpoints on
+    /// first use when the choice is automatic.
+    async fn release_api(&self) -> &str {
+        match &self.endpoint_selection {
+            Some(selection) => {
+                self.selected_api
+                    .get_or_init(|| selection.select(&self.client))
+                    .await
+            }
+            None => &self.api,
+        }
+    }
+
     /// Fetch release from GitHub API
     async fn get_release(&self, path: &str) -> Result<Release> {
-        let url = format!("{}/{}", self.api, path);
+        let url = format!("{}/{}", self.release_api().await, path);
 
         let response = self
             .send_with_rate_limit(|| self.client.get(&url), "Failed to fetch release")
             .await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            match status {
-                reqwest::StatusCode::NOT_FOUND => {
-                    return Err(GitHubError::ReleaseNotFound {
-                        repo: self.repo.clone(),
-                        has_token: self.token.is_some(),
-                        url: url.clone(),
-                        is_latest: path == "latest",
-                    }
-                    .into());
-                }
-                reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
-                    return Err(GitHubError::AuthFailed {
-                        status_code: status.as_u16(),
-                        repo: self.repo.clone(),
-                        url: url.clone(),
-                    }
-                    .into());
-                }
-                _ => {
-                    let body = response.text().await.unwrap_or_default();
-                    return Err(GitHubError::HttpError {
-                        repo: self.repo.clone(),
-                        status_code: status.as_u16(),
-                        url: url.clone(),
-                        body,
-                    }
-                    .into());
-                }
-            }
-        }
+        let response = self
+            .check_release_response(response, &url, path == "latest")
+            .await?;
 
         let release: Release = response
             .json()
@@ -493,6 +1582,47 @@ impl GitHubClient {
         Ok(release)
     }
 
+    /// Map a non-success release API response to the matching `GitHubError`.
+    async fn check_release_response(
+        &self,
+        response: reqwest::Response,
+        url: &str,
+        is_latest: bool,
+    ) -> Result<reqwest::Response> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        match status {
+            reqwest::StatusCode::NOT_FOUND => Err(GitHubError::ReleaseNotFound {
+                repo: self.repo.clone(),
+                has_token: self.token.is_some(),
+                url: url.to_string(),
+                is_latest,
+            }
+            .into()),
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
+                Err(GitHubError::AuthFailed {
+                    status_code: status.as_u16(),
+                    repo: self.repo.clone(),
+                    url: url.to_string(),
+                }
+                .into())
+            }
+            _ => {
+                let body = response.text().await.unwrap_or_default();
+                Err(GitHubError::HttpError {
+                    repo: self.repo.clone(),
+                    status_code: status.as_u16(),
+                    url: url.to_string(),
+                    body,
+                }
+                .into())
+            }
+        }
+    }
+
     /// Download a release asset by name.
     pub async fn download_release_asset(&self, version: &str, asset_name: &str) -> Result<Vec<u8>> {
         let release = self.get_tagged_release(version).await?;
@@ -500,49 +1630,70 @@ impl GitHubClient {
 
         if self.token.is_some() {
             // For private repositories, we need to use the API to download
-            self.download_asset_via_api(asset.id, asset_name).await
+            self.download_asset_via_api(asset.id, asset_name, 1).await
         } else {
             // For public repositories, use direct download URL
-            self.download_asset_direct(&asset.url, asset_name).await
+            self.download_asset_direct(&asset.url, asset_name, 1).await
         }
     }
 
     /// Download asset via GitHub API (for private repos)
-    async fn download_asset_via_api(&self, asset_id: u64, asset_name: &str) -> Result<Vec<u8>> {
+    async fn download_asset_via_api(
+        &self,
+        asset_id: u64,
+        asset_name: &str,
+        chunks: usize,
+    ) -> Result<Vec<u8>> {
         let url = format!(
-            "https://api.github.com/repos/{}/releases/assets/{}",
-            self.repo, asset_id
+            "{}/repos/{}/releases/assets/{}",
+            self.github_api, self.repo, asset_id
         );
+        let build_request = || {
+            self.client
+                .get(&url)
+                .header(reqwest::header::ACCEPT, "application/octet-stream")
+        };
 
         let response = self
             .send_with_rate_limit(
-                || {
-                    self.client
-                        .get(&url)
-                        .header(reqwest::header::ACCEPT, "application/octet-stream")
-                },
+                || chunked::with_first_range(build_request(), chunks),
                 "Failed to download asset",
             )
             .await?;
 
-        self.download_response(response, &url, asset_name).await
+        self.download_response(response, &url, asset_name, build_request, chunks)
+            .await
     }
 
     /// Download asset directly (for public repos)
-    async fn download_asset_direct(&self, url: &str, asset_name: &str) -> Result<Vec<u8>> {
+    async fn download_asset_direct(
+        &self,
+        url: &str,
+        asset_name: &str,
+        chunks: usize,
+    ) -> Result<Vec<u8>> {
+        let build_request = || self.client.get(url);
+
         let response = self
-            .send_with_rate_limit(|| self.client.get(url), "Failed to download asset")
+            .send_with_rate_limit(
+                || chunked::with_first_range(build_request(), chunks),
+                "Failed to download asset",
+            )
             .await?;
 
-        self.download_response(response, url, asset_name).await
+        self.download_response(response, url, asset_name, build_request, chunks)
+            .await
     }
 
-    /// Stream a response body into a buffer.
+    /// Read a download response, fetching any remaining ranged chunks with
+    /// `build_request`.
     async fn download_response(
         &self,
         response: reqwest::Response,
         url: &str,
         asset_name: &str,
+        build_request: impl Fn() -> reqwest::RequestBuilder,
+        chunks: usize,
     ) -> Result<Vec<u8>> {
         if !response.status().is_success() {
             let status = response.status();
@@ -555,16 +1706,7 @@ impl GitHubClient {
             .into());
         }
 
-        // Stream and collect chunks
-        let mut buffer = Vec::new();
-        let mut stream = response.bytes_stream();
-
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk.context("Error while downloading file")?;
-            buffer.extend_from_slice(&chunk);
-        }
-
-        Ok(buffer)
+        chunked::read_response(response, build_request, chunks).await
     }
 }
 
@@ -619,7 +1761,11 @@ mod tests {
         let github_token = Some("test-token".to_string());
 
         //* When
-        let client = GitHubClient::new(DEFAULT_SELF_REPO.to_string(), github_token)?;
+        let client = GitHubClient::new(
+            DEFAULT_SELF_REPO.to_string(),
+            github_token,
+            ClientOptions::default(),
+        )?;
 
         //* Then
         assert_eq!(
@@ -630,6 +1776,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn http_timeouts_or_with_partial_overrides_fills_from_fallback() {
+        //* Given
+        let flags = HttpTimeouts {
+            connect: Some(Duration::from_secs(2)),
+            ..Default::default()
+        };
+        let settings = HttpTimeouts {
+            connect: Some(Duration::from_secs(30)),
+            read: Some(Duration::from_secs(90)),
+            total: None,
+        };
+
+        //* When
+        let merged = flags.or(settings);
+
+        //* Then
+        assert_eq!(
+            merged,
+            HttpTimeouts {
+                connect: Some(Duration::from_secs(2)),
+                read: Some(Duration::from_secs(90)),
+                total: None,
+            },
+            "explicit values should win and unset values should come from the fallback"
+        );
+    }
+
     #[test]
     fn release_api_base_with_other_repo_uses_github_releases_api() {
         //* Given
@@ -644,4 +1818,178 @@ mod tests {
             "unsupported repos should keep using the GitHub releases API"
         );
     }
+
+    #[test]
+    fn new_with_enterprise_api_url_uses_enterprise_releases_api() -> Result<()> {
+        //* Given
+        let options = ClientOptions {
+            api_url: Some("https://github.mycorp.com/api/v3/".to_string()),
+            ..Default::default()
+        };
+
+        //* When
+        let client = GitHubClient::new(DEFAULT_REPO.to_string(), None, options.clone())?;
+
+        //* Then
+        assert_eq!(
+            client.api, "https://github.mycorp.com/api/v3/repos/edgeandnode/amp/releases",
+            "a custom API URL should bypass the ampup.sh proxy"
+        );
+        assert_eq!(options.web_url(), "https://github.mycorp.com");
+        assert_eq!(
+            options.enterprise_host().as_deref(),
+            Some("github.mycorp.com")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn release_is_yanked_with_yanked_asset_returns_true() -> Result<()> {
+        //* Given
+        let release: Release = serde_json::from_str(
+            r#"{
+                "tag_name": "v0.3.1",
+                "assets": [
+                    {"id": 1, "name": "YANKED", "browser_download_url": "https://example.com/YANKED"}
+                ]
+            }"#,
+        )?;
+
+        //* When
+        let yanked = release.is_yanked();
+
+        //* Then
+        assert!(yanked, "a YANKED asset should mark the release as yanked");
+
+        Ok(())
+    }
+
+    #[test]
+    fn graphql_api_with_enterprise_api_url_uses_api_graphql() {
+        //* Given
+        let options = ClientOptions {
+            api_url: Some("https://github.mycorp.com/api/v3".to_string()),
+            ..Default::default()
+        };
+
+        //* When
+        let url = options.graphql_api();
+
+        //* Then
+        assert_eq!(url, "https://github.mycorp.com/api/graphql");
+        assert_eq!(
+            ClientOptions::default().graphql_api(),
+            "https://api.github.com/graphql"
+        );
+    }
+
+    #[test]
+    fn release_snapshot_from_response_maps_releases_and_assets() -> Result<()> {
+        //* Given
+        let response: GraphQlResponse = serde_json::from_str(
+            r#"{"data": {"repository": {
+                "latestRelease": {"tagName": "v0.2.0"},
+                "releases": {"nodes": [
+                    {"tagName": "v0.3.0-rc.1", "isDraft": true, "isPrerelease": true,
+                     "releaseAssets": {"nodes": []}},
+                    {"tagName": "v0.2.0", "isDraft": false, "isPrerelease": false,
+                     "releaseAssets": {"nodes": [
+                        {"databaseId": 7, "name": "ampd-linux-x86_64",
+                         "downloadUrl": "https://example.com/ampd", "digest": "sha256:abc"}
+                     ]}}
+                ]}
+            }}}"#,
+        )?;
+
+        //* When
+        let snapshot = ReleaseSnapshot::from_response(response).expect("snapshot");
+
+        //* Then
+        assert_eq!(snapshot.latest.as_deref(), Some("v0.2.0"));
+        let release = snapshot.tagged("v0.2.0", false).expect("tagged release");
+        assert_eq!(release.assets[0].id, 7);
+        assert_eq!(release.assets[0].digest.as_deref(), Some("sha256:abc"));
+        assert!(
+            snapshot.tagged("v0.3.0-rc.1", false).is_none(),
+            "drafts should only match when drafts are included"
+        );
+        assert!(snapshot.tagged("v0.3.0-rc.1", true).is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn release_snapshot_from_response_with_errors_returns_none() -> Result<()> {
+        //* Given
+        let response: GraphQlResponse = serde_json::from_str(
+            r#"{"data": null, "errors": [{"message": "Field 'digest' doesn't exist"}]}"#,
+        )?;
+
+        //* When
+        let snapshot = ReleaseSnapshot::from_response(response);
+
+        //* Then
+        assert!(
+            snapshot.is_none(),
+            "errors should fall back to the REST API"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn prefetch_release_then_resolve_assets_reuses_fetched_release() -> Result<()> {
+        //* Given
+        // The server answers a single request, so the lookup after the
+        // prefetch must be served from the client's cache
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+            let (mut stream, _) = listener.accept().await.expect("accept");
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request).await;
+            let body = r#"{"tag_name":"v1.0.0","assets":[{"id":1,"name":"ampd-linux-x86_64","browser_download_url":"http://localhost/ampd","digest":"sha256:abc"}]}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+        let client = GitHubClient::with_api_base(format!("http://{}", addr))?;
+
+        //* When
+        client.prefetch_release("v1.0.0").finish().await;
+        server.await?;
+        let assets = client
+            .clone()
+            .resolve_release_assets("v1.0.0", &["ampd-linux-x86_64"])
+            .await?;
+
+        //* Then
+        assert_eq!(assets[0].digest.as_deref(), Some("sha256:abc"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn with_drafts_without_token_fails_with_drafts_require_token() -> Result<()> {
+        //* Given
+        let client = GitHubClient::new(DEFAULT_REPO.to_string(), None, ClientOptions::default())?;
+
+        //* When
+        let result = client.with_drafts();
+
+        //* Then
+        let err = result.err().expect("drafts should require a token");
+        assert!(matches!(
+            err.downcast_ref::<GitHubError>(),
+            Some(GitHubError::DraftsRequireToken { .. })
+        ));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,354 @@
+use std::{
+    io::Read,
+    path::Path,
+    process::{Command, ExitStatus, Stdio},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{environment::Environment, manifest::VersionManifest, resolver::tag_version, ui};
+
+/// How long `--version` may run before the binary is considered hung.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long each smoke test may run.
+const SMOKE_TEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Smoke tests run after each install and build unless
+/// `smoke_test.commands` is set.
+pub const DEFAULT_SMOKE_TESTS: &[&str] = &["ampd --version", "ampctl --help"];
+
+#[derive(Debug)]
+pub enum HealthError {
+    /// An installed binary crashed, hung or exited with an error when asked
+    /// for its version.
+    BinaryFailed {
+        version: String,
+        binary: String,
+        reason: String,
+    },
+    /// One or more smoke tests failed; the version is installed but was not
+    /// activated.
+    SmokeTestFailed {
+        version: String,
+        /// Failed commands with the reason each failed
+        failed: Vec<(String, String)>,
+    },
+}
+
+impl std::fmt::Display for HealthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BinaryFailed {
+                version,
+                binary,
+                reason,
+            } => {
+                writeln!(f, "Installed binary failed its health check")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Binary: {}", binary)?;
+                writeln!(f, "  Error: {}", reason)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  The version was not activated. The download may be corrupt or built"
+                )?;
+                write!(
+                    f,
+                    "  for another platform; try installing it again, or building it from source."
+                )?;
+            }
+            Self::SmokeTestFailed { version, failed } => {
+                writeln!(f, "Smoke tests failed")?;
+                writeln!(f, "  Version: {}", version)?;
+                for (command, reason) in failed {
+                    writeln!(f, "  Failed: {} ({})", command, reason)?;
+                }
+                writeln!(f)?;
+                writeln!(f, "  The version is installed but was not activated.")?;
+                write!(
+                    f,
+                    "  Re-run the tests with 'ampup install {} --smoke-test', or remove it with 'ampup uninstall {}'.",
+                    version, version
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for HealthError {}
+
+/// Run `ampd --version` and `ampctl --version` from `version_dir` before the
+/// version is activated.
+///
+/// Fails when either binary can't be executed, exits with an error or hangs.
+/// A release version that doesn't appear in the output only warns, since a
+/// build may legitimately describe itself differently.
+pub fn check(version_dir: &Path, version: &str, env: &dyn Environment) -> Result<()> {
+    for binary in ["ampd", "ampctl"] {
+        let failed = |reason: String| HealthError::BinaryFailed {
+            version: version.to_string(),
+            binary: binary.to_string(),
+            reason,
+        };
+        let output = run_version(&version_dir.join(binary), env).map_err(failed)?;
+        let first_line = output.lines().next().unwrap_or_default().trim();
+
+        if let Some(expected) = tag_version(version)
+            && !first_line.contains(&expected.to_string())
+        {
+            ui::warn!(
+                "{} reports '{}', expected version {}",
+                binary,
+                first_line,
+                expected
+            );
+            ui::detail!(
+                "If this is unexpected, reinstall with 'ampup install {}'",
+                version
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Outcome of a smoke test run, recorded in the version's manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmokeTestReport {
+    /// When the tests ran, in seconds since the Unix epoch
+    pub ran_at: u64,
+    pub results: Vec<SmokeTestResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmokeTestResult {
+    pub command: String,
+    pub passed: bool,
+    /// Why the command failed: its exit status and last line of stderr, or
+    /// a timeout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl SmokeTestReport {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.passed)
+    }
+}
+
+/// Run each smoke test command through `sh -c` with `version_dir` first on
+/// `PATH`, so `ampd` and `ampctl` are the version under test, and record the
+/// results in the version's manifest.
+///
+/// Fails with `HealthError::SmokeTestFailed` when any command fails.
+pub fn smoke_test(
+    version_dir: &Path,
+    version: &str,
+    commands: &[String],
+    env: &dyn Environment,
+) -> Result<SmokeTestReport> {
+    let mut path = vec![version_dir.to_path_buf()];
+    if let Some(existing) = env.var_os("PATH") {
+        path.extend(std::env::split_paths(&existing));
+    }
+    let path = std::env::join_paths(path)?;
+
+    ui::info!("Running {} smoke tests", commands.len());
+    let mut results = Vec::new();
+    for command in commands {
+        let started = Instant::now();
+        let mut process = env.command("/bin/sh");
+        process.args(["-c", command]).env("PATH", &path);
+        let error = match run_with_timeout(process, SMOKE_TEST_TIMEOUT) {
+            Ok((status, _, _)) if status.success() => None,
+            Ok((status, _, stderr)) => Some(match stderr.lines().last() {
+                Some(line) => format!("exited with {}: {}", status, line.trim()),
+                None => format!("exited with {}", status),
+            }),
+            Err(reason) => Some(reason),
+        };
+
+        match &error {
+            None => ui::detail!("Passed: {}", command),
+            Some(reason) => ui::warn!("Failed: {} ({})", command, reason),
+        }
+        results.push(SmokeTestResult {
+            command: command.clone(),
+            passed: error.is_none(),
+            error,
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+    }
+
+    let report = SmokeTestReport {
+        ran_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        results,
+    };
+    let mut manifest = VersionManifest::load(version_dir)?;
+    manifest.smoke_test = Some(report.clone());
+    manifest.save(version_dir)?;
+
+    if !report.passed() {
+        return Err(HealthError::SmokeTestFailed {
+            version: version.to_string(),
+            failed: report
+                .results
+                .iter()
+                .filter(|result| !result.passed)
+                .map(|result| {
+                    (
+                        result.command.clone(),
+                        result.error.clone().unwrap_or_default(),
+                    )
+                })
+                .collect(),
+        }
+        .into());
+    }
+    Ok(report)
+}
+
+/// Stdout of `<path> --version`, or why it failed.
+fn run_version(path: &Path, env: &dyn Environment) -> std::result::Result<String, String> {
+    let mut command = env.command(&path.to_string_lossy());
+    command.arg("--version");
+    let (status, stdout, _) = run_with_timeout(command, VERSION_TIMEOUT)?;
+    if !status.success() {
+        return Err(format!("--version exited with {}", status));
+    }
+    Ok(stdout)
+}
+
+/// Run `command` to completion, killing it after `timeout`, and return its
+/// exit status, stdout and stderr.
+pub(crate) fn run_with_timeout(
+    mut command: Command,
+    timeout: Duration,
+) -> std::result::Result<(ExitStatus, String, String), String> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| err.to_string())?;
+
+    // Drain the pipes while waiting so chatty commands can't block on a
+    // full pipe
+    let read = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut output = String::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_string(&mut output);
+            }
+            output
+        })
+    };
+    let stdout = read(child.stdout.take().map(|p| Box::new(p) as _));
+    let stderr = read(child.stderr.take().map(|p| Box::new(p) as _));
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait().map_err(|err| err.to_string())? {
+            Some(status) => break status,
+            None if started.elapsed() > timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("did not finish within {}s", timeout.as_secs()));
+            }
+            None => std::thread::sleep(Duration::from_millis(20)),
+        }
+    };
+
+    Ok((
+        status,
+        stdout.join().unwrap_or_default(),
+        stderr.join().unwrap_or_default(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use fs_err as fs;
+
+    use super::*;
+    use crate::environment::ProcessEnv;
+
+    fn write_script(dir: &Path, name: &str, body: &str) {
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).expect("write script");
+        fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .expect("make script executable");
+    }
+
+    #[test]
+    fn check_with_working_binaries_succeeds() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        write_script(dir.path(), "ampd", "echo 'ampd 0.3.0'");
+        write_script(dir.path(), "ampctl", "echo 'ampctl 0.3.0'");
+
+        //* When
+        let result = check(dir.path(), "v0.3.0", &ProcessEnv);
+
+        //* Then
+        assert!(result.is_ok(), "check should pass: {:?}", result.err());
+    }
+
+    #[test]
+    fn smoke_test_with_failing_command_records_results_in_manifest() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        write_script(dir.path(), "ampd", "echo 'ampd 0.3.0'");
+        write_script(
+            dir.path(),
+            "ampctl",
+            "echo 'connection refused' >&2; exit 2",
+        );
+        let commands = vec!["ampd --version".to_string(), "ampctl status".to_string()];
+
+        //* When
+        let result = smoke_test(dir.path(), "v0.3.0", &commands, &ProcessEnv);
+
+        //* Then
+        assert!(matches!(
+            result.expect_err("a failing command should fail the suite").downcast_ref::<HealthError>(),
+            Some(HealthError::SmokeTestFailed { failed, .. }) if failed.len() == 1
+        ));
+        let report = VersionManifest::load(dir.path())
+            .expect("load manifest")
+            .smoke_test
+            .expect("results should be recorded");
+        assert!(report.results[0].passed);
+        assert_eq!(
+            report.results[1].error.as_deref(),
+            Some("exited with exit status: 2: connection refused")
+        );
+    }
+
+    #[test]
+    fn check_with_crashing_binary_fails_with_binary_failed() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        write_script(dir.path(), "ampd", "echo 'ampd 0.3.0'");
+        fs::write(dir.path().join("ampctl"), b"\x7fELF truncated").expect("write ampctl");
+
+        //* When
+        let result = check(dir.path(), "v0.3.0", &ProcessEnv);
+
+        //* Then
+        let err = result.expect_err("a broken binary should fail the check");
+        assert!(matches!(
+            err.downcast_ref::<HealthError>(),
+            Some(HealthError::BinaryFailed { binary, .. }) if binary == "ampctl"
+        ));
+    }
+}
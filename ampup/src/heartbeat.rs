@@ -0,0 +1,124 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use console::Term;
+
+use crate::ui;
+
+/// Default seconds between heartbeat lines.
+pub const DEFAULT_INTERVAL_SECS: u64 = 60;
+
+/// Seconds between heartbeat lines, 0 when turned off.
+static INTERVAL_SECS: AtomicU64 = AtomicU64::new(DEFAULT_INTERVAL_SECS);
+
+/// Bytes downloaded by this process, sampled by heartbeats.
+static BYTES_RECEIVED: AtomicU64 = AtomicU64::new(0);
+
+/// Set how often long operations report they're still running when stderr
+/// isn't a terminal; `0` turns heartbeats off.
+pub fn set_interval(secs: u64) {
+    INTERVAL_SECS.store(secs, Ordering::Relaxed);
+}
+
+/// Count `bytes` received from a download, for the heartbeat lines.
+pub fn record_bytes(bytes: usize) {
+    BYTES_RECEIVED.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+/// Periodic "still running" line for a long operation, so CI systems that
+/// kill jobs after a period without output don't stop a slow download or
+/// build. Only runs when stderr isn't a terminal, where progress is
+/// otherwise silent for long stretches; stops when dropped.
+pub struct Heartbeat {
+    running: Option<(mpsc::Sender<()>, JoinHandle<()>)>,
+}
+
+impl Heartbeat {
+    /// Start reporting `phase` (e.g. "building ampd and ampctl").
+    pub fn start(phase: impl Into<String>) -> Self {
+        let interval = INTERVAL_SECS.load(Ordering::Relaxed);
+        if interval == 0 || Term::stderr().is_term() {
+            return Self { running: None };
+        }
+
+        let phase = phase.into();
+        let interval = Duration::from_secs(interval);
+        let (stop, stopped) = mpsc::channel::<()>();
+        let handle = std::thread::spawn(move || {
+            let started = Instant::now();
+            let bytes_at_start = BYTES_RECEIVED.load(Ordering::Relaxed);
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let bytes = BYTES_RECEIVED.load(Ordering::Relaxed) - bytes_at_start;
+                ui::detail!("{}", format_line(&phase, started.elapsed(), bytes));
+            }
+        });
+        Self {
+            running: Some((stop, handle)),
+        }
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        if let Some((stop, handle)) = self.running.take() {
+            let _ = stop.send(());
+            let _ = handle.join();
+        }
+    }
+}
+
+/// `Still <phase>: <elapsed> elapsed[, <MiB> downloaded]`, leaving out the
+/// download size while nothing was downloaded.
+fn format_line(phase: &str, elapsed: Duration, bytes: u64) -> String {
+    let secs = elapsed.as_secs();
+    let elapsed = match secs {
+        0..60 => format!("{}s", secs),
+        _ => format!("{}m {:02}s", secs / 60, secs % 60),
+    };
+    if bytes == 0 {
+        format!("Still {}: {} elapsed", phase, elapsed)
+    } else {
+        format!(
+            "Still {}: {} elapsed, {:.1} MiB downloaded",
+            phase,
+            elapsed,
+            bytes as f64 / (1024.0 * 1024.0)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_line_with_downloaded_bytes_includes_size() {
+        //* When
+        let line = format_line(
+            "downloading v1.0.0",
+            Duration::from_secs(125),
+            3 * 1024 * 1024,
+        );
+
+        //* Then
+        assert_eq!(
+            line,
+            "Still downloading v1.0.0: 2m 05s elapsed, 3.0 MiB downloaded"
+        );
+    }
+
+    #[test]
+    fn format_line_without_bytes_omits_size() {
+        //* When
+        let line = format_line("building ampd and ampctl", Duration::from_secs(42), 0);
+
+        //* Then
+        assert_eq!(line, "Still building ampd and ampctl: 42s elapsed");
+    }
+}
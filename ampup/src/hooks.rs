@@ -0,0 +1,423 @@
+use std::{
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::{config::Config, health, portable, ui};
+
+/// How long each hook may run.
+const HOOK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Points of a version switch at which hook scripts run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum HookEvent {
+    /// Before the active version changes; a failing hook cancels the switch
+    PreActivate,
+    /// After the active version changed; failures only warn
+    PostActivate,
+}
+
+impl HookEvent {
+    pub const ALL: [HookEvent; 2] = [Self::PreActivate, Self::PostActivate];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::PreActivate => "pre-activate",
+            Self::PostActivate => "post-activate",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum HookError {
+    /// A pre-activate hook exited with an error, cancelling the switch.
+    Cancelled {
+        hook: String,
+        version: String,
+        reason: String,
+    },
+    /// One or more hooks failed when run from `ampup hooks run`.
+    Failed {
+        event: String,
+        failed: Vec<String>,
+    },
+    AlreadyExists {
+        event: String,
+        name: String,
+    },
+    NotFound {
+        event: String,
+        name: String,
+    },
+    /// The hook name is not a single file name in the event's directory.
+    InvalidName {
+        name: String,
+    },
+}
+
+impl std::fmt::Display for HookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cancelled {
+                hook,
+                version,
+                reason,
+            } => {
+                writeln!(f, "Pre-activate hook cancelled the switch")?;
+                writeln!(f, "  Hook: {}", hook)?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Error: {}", reason)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Check the hook with 'ampup hooks run pre-activate --test'."
+                )?;
+            }
+            Self::Failed { event, failed } => {
+                writeln!(f, "Hooks failed")?;
+                writeln!(f, "  Event: {}", event)?;
+                write!(f, "  Failed: {}", failed.join(", "))?;
+            }
+            Self::AlreadyExists { event, name } => {
+                writeln!(f, "Hook already exists")?;
+                writeln!(f, "  Event: {}", event)?;
+                writeln!(f, "  Name: {}", name)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Remove it first with 'ampup hooks remove {} {}'.",
+                    event, name
+                )?;
+            }
+            Self::NotFound { event, name } => {
+                writeln!(f, "Hook not found")?;
+                writeln!(f, "  Event: {}", event)?;
+                writeln!(f, "  Name: {}", name)?;
+                writeln!(f)?;
+                write!(f, "  Try: ampup hooks list")?;
+            }
+            Self::InvalidName { name } => {
+                writeln!(f, "Invalid hook name")?;
+                writeln!(f, "  Name: {}", name)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Hook names must be a single file name, without '/' or '\\'."
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for HookError {}
+
+/// What a hook is told about the switch, as environment variables.
+pub struct HookContext {
+    /// Version key active before the switch, if any (`AMPUP_FROM_VERSION`)
+    pub from: Option<String>,
+    /// Version key being activated (`AMPUP_TO_VERSION`)
+    pub to: String,
+    /// Directory holding the new version's binaries (`AMPUP_VERSION_DIR`)
+    pub version_dir: PathBuf,
+    /// Whether the data is made up for `ampup hooks run --test`
+    /// (`AMPUP_HOOK_TEST=1`)
+    pub test: bool,
+}
+
+/// Outcome of one hook.
+pub struct HookRun {
+    pub name: String,
+    /// Why the hook failed: its exit status and last line of stderr, or a
+    /// timeout
+    pub error: Option<String>,
+    /// Combined stdout and stderr
+    pub output: String,
+}
+
+/// Executable scripts in `~/.amp/hooks/<event>/`, run in name order with
+/// the installation directory as working directory.
+pub struct Hooks {
+    dir: PathBuf,
+    amp_dir: PathBuf,
+}
+
+impl Hooks {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            dir: config.hooks_dir(),
+            amp_dir: config.amp_dir.clone(),
+        }
+    }
+
+    /// Names of the hooks for `event`, in the order they run.
+    pub fn list(&self, event: HookEvent) -> Result<Vec<String>> {
+        let dir = self.dir.join(event.as_str());
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&dir).context("Failed to read hooks directory")? {
+            let entry = entry.context("Failed to read hooks directory entry")?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with('.') && entry.path().is_file() {
+                names.push(name);
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Copy `script` in as a hook for `event`, named `name` or after the
+    /// script's file name, and make it executable.
+    pub fn add(&self, event: HookEvent, script: &Path, name: Option<&str>) -> Result<PathBuf> {
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => script
+                .file_name()
+                .context("Hook script has no file name")?
+                .to_string_lossy()
+                .to_string(),
+        };
+        validate_name(&name)?;
+        let path = self.dir.join(event.as_str()).join(&name);
+        if path.exists() {
+            return Err(HookError::AlreadyExists {
+                event: event.as_str().to_string(),
+                name,
+            }
+            .into());
+        }
+
+        fs::create_dir_all(self.dir.join(event.as_str()))
+            .context("Failed to create hooks directory")?;
+        fs::copy(script, &path).context("Failed to copy hook script")?;
+        fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .context("Failed to make hook executable")?;
+        Ok(path)
+    }
+
+    /// Remove the hook `name` of `event`.
+    pub fn remove(&self, event: HookEvent, name: &str) -> Result<()> {
+        validate_name(name)?;
+        let path = self.dir.join(event.as_str()).join(name);
+        if !path.is_file() {
+            return Err(HookError::NotFound {
+                event: event.as_str().to_string(),
+                name: name.to_string(),
+            }
+            .into());
+        }
+        fs::remove_file(&path).context("Failed to remove hook")
+    }
+
+    /// Run the hooks of `event` (or only `name`) with `context`, returning
+    /// the outcome of each.
+    pub fn run(
+        &self,
+        event: HookEvent,
+        context: &HookContext,
+        name: Option<&str>,
+    ) -> Result<Vec<HookRun>> {
+        let names = match name {
+            Some(name) => {
+                if !self.list(event)?.iter().any(|hook| hook == name) {
+                    return Err(HookError::NotFound {
+                        event: event.as_str().to_string(),
+                        name: name.to_string(),
+                    }
+                    .into());
+                }
+                vec![name.to_string()]
+            }
+            None => self.list(event)?,
+        };
+
+        let mut runs = Vec::new();
+        for name in names {
+            let mut command = Command::new(self.dir.join(event.as_str()).join(&name));
+            command
+                .current_dir(&self.amp_dir)
+                .env("AMPUP_HOOK", event.as_str())
+                .env("AMPUP_FROM_VERSION", context.from.as_deref().unwrap_or(""))
+                .env("AMPUP_TO_VERSION", &context.to)
+                .env("AMPUP_VERSION_DIR", &context.version_dir)
+                .env("AMP_DIR", &self.amp_dir);
+            if context.test {
+                command.env("AMPUP_HOOK_TEST", "1");
+            }
+            let (error, output) = match health::run_with_timeout(command, HOOK_TIMEOUT) {
+                Ok((status, stdout, stderr)) => {
+                    let error = (!status.success()).then(|| match stderr.lines().last() {
+                        Some(line) => format!("exited with {}: {}", status, line.trim()),
+                        None => format!("exited with {}", status),
+                    });
+                    (error, format!("{}{}", stdout, stderr))
+                }
+                Err(reason) => (Some(reason), String::new()),
+            };
+            let failed = error.is_some();
+            runs.push(HookRun {
+                name,
+                error,
+                output,
+            });
+            // Later pre-activate hooks may rely on earlier ones passing
+            if failed && event == HookEvent::PreActivate {
+                break;
+            }
+        }
+        Ok(runs)
+    }
+
+    /// Run the hooks of `event` for a real switch: a failing pre-activate
+    /// hook fails with [`HookError::Cancelled`], and post-activate failures
+    /// are warned about.
+    pub fn fire(&self, event: HookEvent, context: &HookContext) -> Result<()> {
+        for run in self.run(event, context, None)? {
+            let Some(reason) = run.error else {
+                ui::detail!("Ran {} hook {}", event.as_str(), run.name);
+                continue;
+            };
+            if event == HookEvent::PreActivate {
+                return Err(HookError::Cancelled {
+                    hook: run.name,
+                    version: context.to.clone(),
+                    reason,
+                }
+                .into());
+            }
+            ui::warn!("{} hook {} failed: {}", event.as_str(), run.name, reason);
+        }
+        Ok(())
+    }
+}
+
+/// Reject hook names that would leave the event's directory.
+fn validate_name(name: &str) -> Result<(), HookError> {
+    if !portable::is_safe_name(name) {
+        return Err(HookError::InvalidName {
+            name: name.to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hooks_with_script(dir: &Path, event: HookEvent, name: &str, body: &str) -> Hooks {
+        let config = Config::new(Some(dir.to_path_buf())).expect("should create config");
+        let script = dir.join(format!("{}.sh", name));
+        fs::write(&script, format!("#!/bin/sh\n{}\n", body)).expect("should write script");
+        let hooks = Hooks::new(&config);
+        hooks
+            .add(event, &script, Some(name))
+            .expect("should add hook");
+        hooks
+    }
+
+    fn context(dir: &Path) -> HookContext {
+        HookContext {
+            from: Some("edgeandnode-amp/v0.1.0".to_string()),
+            to: "edgeandnode-amp/v0.2.0".to_string(),
+            version_dir: dir.to_path_buf(),
+            test: true,
+        }
+    }
+
+    #[test]
+    fn run_with_added_hook_passes_event_data() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let hooks = hooks_with_script(
+            dir.path(),
+            HookEvent::PostActivate,
+            "notify",
+            r#"echo "$AMPUP_HOOK $AMPUP_FROM_VERSION $AMPUP_TO_VERSION $AMPUP_HOOK_TEST""#,
+        );
+
+        //* When
+        let runs = hooks
+            .run(HookEvent::PostActivate, &context(dir.path()), None)
+            .expect("should run hooks");
+
+        //* Then
+        assert_eq!(
+            hooks.list(HookEvent::PostActivate).expect("should list"),
+            vec!["notify".to_string()]
+        );
+        assert_eq!(runs.len(), 1);
+        assert!(
+            runs[0].error.is_none(),
+            "hook should pass: {:?}",
+            runs[0].error
+        );
+        assert_eq!(
+            runs[0].output.trim(),
+            "post-activate edgeandnode-amp/v0.1.0 edgeandnode-amp/v0.2.0 1"
+        );
+    }
+
+    #[test]
+    fn fire_with_failing_pre_activate_hook_cancels_switch() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let hooks = hooks_with_script(
+            dir.path(),
+            HookEvent::PreActivate,
+            "check-disk",
+            "echo 'disk full' >&2; exit 1",
+        );
+
+        //* When
+        let result = hooks.fire(HookEvent::PreActivate, &context(dir.path()));
+
+        //* Then
+        let err = result.expect_err("a failing pre-activate hook should cancel");
+        let Some(HookError::Cancelled { hook, reason, .. }) = err.downcast_ref::<HookError>()
+        else {
+            panic!("expected Cancelled, got {:?}", err);
+        };
+        assert_eq!(hook, "check-disk");
+        assert!(reason.contains("disk full"), "reason: {}", reason);
+    }
+
+    #[test]
+    fn add_with_path_traversal_name_is_rejected() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        let script = dir.path().join("hook.sh");
+        fs::write(&script, "#!/bin/sh\n").expect("should write script");
+        let hooks = Hooks::new(&config);
+
+        //* When
+        let result = hooks.add(HookEvent::PostActivate, &script, Some("../../bin/ampup"));
+
+        //* Then
+        let err = result.expect_err("a name with '/' should be rejected");
+        assert!(
+            matches!(
+                err.downcast_ref::<HookError>(),
+                Some(HookError::InvalidName { .. })
+            ),
+            "expected InvalidName, got {:?}",
+            err
+        );
+        assert!(!dir.path().join("bin").exists());
+        assert!(
+            hooks
+                .remove(HookEvent::PostActivate, "..")
+                .expect_err("'..' should be rejected")
+                .downcast_ref::<HookError>()
+                .is_some_and(|err| matches!(err, HookError::InvalidName { .. }))
+        );
+    }
+}
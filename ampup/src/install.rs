@@ -1,33 +1,160 @@
+use std::sync::Arc;
+
 use anyhow::Result;
 
 use crate::{
-    download_manager::{DownloadManager, DownloadTask},
+    channels,
+    data_dir::DataBoundary,
+    download_manager::DownloadTask,
+    environment::ProcessEnv,
+    extras,
+    fetcher::AssetFetcher,
+    gatekeeper, health,
+    manifest::{VersionManifest, binary_checksums},
     platform::{Architecture, Platform},
-    progress, ui,
+    progress::{self, ProgressReporter},
+    resolver::Channel,
+    transaction::InstallTransaction,
+    ui,
     version_manager::VersionManager,
+    version_store::{repo_namespace, version_key},
 };
 
-pub struct Installer {
+/// Installs a resolved release: fetches its binaries through an
+/// [`AssetFetcher`], checks and records them, and activates the version.
+///
+/// Resolving a spec to a release ([`crate::resolver::VersionResolver`]),
+/// fetching and installing are separate so frontends can reuse each step
+/// while drawing their own progress (see [`progress::event_stream`]).
+pub struct VersionInstaller {
     version_manager: VersionManager,
-    download_manager: DownloadManager,
+    fetcher: Arc<dyn AssetFetcher>,
+    /// Where download progress goes; the terminal's reporter when unset
+    reporter: Option<Arc<dyn ProgressReporter>>,
+    /// Repository releases are installed from, recorded in their manifest
+    repo: String,
+    /// Namespace releases of the installer's repository are stored under
+    namespace: String,
+    smoke_tests: Vec<String>,
+    /// Ad-hoc sign binaries installed on macOS
+    codesign: bool,
+    /// Record installed versions as prereleases in their manifest
+    prerelease: bool,
+    /// Data boundary to record in the installed version's manifest
+    data_boundary: Option<DataBoundary>,
+    /// Channel the version is installed from
+    channel: Option<Channel>,
+    /// Whether the binaries run here, so health checks and smoke tests apply
+    run_checks: bool,
 }
 
-impl Installer {
-    pub fn new(version_manager: VersionManager, download_manager: DownloadManager) -> Self {
+impl VersionInstaller {
+    /// Create an installer for releases of `repo` (e.g. `edgeandnode/amp`),
+    /// fetching their artifacts with `fetcher`.
+    pub fn new(
+        version_manager: VersionManager,
+        fetcher: impl AssetFetcher + 'static,
+        repo: &str,
+    ) -> Self {
         Self {
             version_manager,
-            download_manager,
+            fetcher: Arc::new(fetcher),
+            reporter: None,
+            repo: repo.to_string(),
+            namespace: repo_namespace(repo),
+            smoke_tests: health::DEFAULT_SMOKE_TESTS
+                .iter()
+                .map(|command| command.to_string())
+                .collect(),
+            codesign: false,
+            prerelease: false,
+            data_boundary: None,
+            channel: None,
+            run_checks: true,
         }
     }
 
-    /// Install ampd and ampctl from a GitHub release.
+    /// Report download progress to `reporter` instead of the terminal.
+    pub fn with_reporter(mut self, reporter: Arc<dyn ProgressReporter>) -> Self {
+        self.reporter = Some(reporter);
+        self
+    }
+
+    /// Run `commands` as smoke tests instead of the defaults.
+    pub fn with_smoke_tests(mut self, commands: Vec<String>) -> Self {
+        self.smoke_tests = commands;
+        self
+    }
+
+    /// Ad-hoc sign the binaries after installing on macOS.
+    pub fn with_codesign(mut self, codesign: bool) -> Self {
+        self.codesign = codesign;
+        self
+    }
+
+    /// Mark the installed version as a prerelease, for `ampup list` and the
+    /// production activation warning.
+    pub fn with_prerelease(mut self, prerelease: bool) -> Self {
+        self.prerelease = prerelease;
+        self
+    }
+
+    /// Record the installed version as the one installed from `channel`.
+    pub fn with_channel(mut self, channel: Option<Channel>) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// Skip the health check and smoke tests, for binaries built for another
+    /// target.
+    pub fn with_run_checks(mut self, run_checks: bool) -> Self {
+        self.run_checks = run_checks;
+        self
+    }
+
+    /// Record the release's data directory boundary in the installed
+    /// version's manifest, for compatibility warnings on later switches.
+    pub fn with_data_boundary(mut self, data_boundary: Option<DataBoundary>) -> Self {
+        self.data_boundary = data_boundary;
+        self
+    }
+
+    /// Key `version` of the installer's repository is stored under.
+    pub fn version_key(&self, version: &str) -> String {
+        version_key(&self.namespace, version)
+    }
+
+    /// Install ampd and ampctl from a GitHub release, restoring the
+    /// previously active version if activation fails.
     pub async fn install_from_release(
         &self,
         version: &str,
         platform: Platform,
         arch: Architecture,
+    ) -> Result<()> {
+        let transaction = InstallTransaction::begin(self.version_manager.config())?;
+        self.stage_release(version, platform, arch).await?;
+
+        // Activation barrier: all downloads succeeded, now switch versions
+        self.version_manager.activate(&self.version_key(version))?;
+
+        transaction.commit();
+        Ok(())
+    }
+
+    /// Download ampd and ampctl into the version directory without
+    /// activating them, then check that both run. A version failing the
+    /// check or the steps before it is removed so the next install downloads
+    /// it again; one failing its smoke tests is kept, with the results in its
+    /// manifest.
+    pub async fn stage_release(
+        &self,
+        version: &str,
+        platform: Platform,
+        arch: Architecture,
     ) -> Result<()> {
         self.version_manager.config().ensure_dirs()?;
+        self.version_manager.store().ensure_root()?;
 
         let ampd_artifact = format!("ampd-{}-{}", platform.as_str(), arch.as_str());
         let ampctl_artifact = format!("ampctl-{}-{}", platform.as_str(), arch.as_str());
@@ -43,23 +170,139 @@ impl Installer {
             DownloadTask {
                 artifact_name: ampd_artifact,
                 dest_filename: "ampd".to_string(),
+                target: Some((platform, arch)),
             },
             DownloadTask {
                 artifact_name: ampctl_artifact,
                 dest_filename: "ampctl".to_string(),
+                target: Some((platform, arch)),
             },
         ];
 
-        let reporter = progress::create_reporter();
-        let version_dir = self.version_manager.config().versions_dir.join(version);
+        let reporter = self
+            .reporter
+            .clone()
+            .unwrap_or_else(progress::create_reporter);
+        let key = self.version_key(version);
+        let version_dir = self.version_manager.version_dir(&key);
 
-        self.download_manager
-            .download_all(tasks, version, version_dir, reporter)
+        self.fetcher
+            .fetch_all(tasks, version, version_dir.clone(), reporter)
             .await?;
+        let mut transaction = InstallTransaction::detached();
+        transaction.remove_on_rollback(&version_dir);
 
-        // Activation barrier: all downloads succeeded, now create symlinks
-        self.version_manager.activate(version)?;
+        // Gatekeeper would block quarantined binaries on their first run,
+        // including the health check below
+        if cfg!(target_os = "macos") && platform == Platform::Darwin {
+            gatekeeper::prepare(
+                &version_dir,
+                &["ampd", "ampctl"],
+                self.codesign,
+                &ProcessEnv,
+            );
+        }
 
+        if self.run_checks {
+            health::check(&version_dir, version, &ProcessEnv)?;
+        }
+        let extras = extras::download(self.fetcher.as_ref(), version, &version_dir).await;
+        if !extras.is_empty() {
+            ui::detail!("Downloaded {}", extras.join(", "));
+        }
+        let checksums = binary_checksums(&version_dir)?;
+        VersionManifest::update(&version_dir, |manifest| {
+            manifest.checksums = checksums;
+            manifest.repo = Some(self.repo.clone());
+            manifest.prerelease = self.prerelease;
+            manifest.data_boundary = self.data_boundary.clone();
+        })?;
+        if let Some(channel) = self.channel {
+            channels::record(&self.version_manager, &key, channel)?;
+        }
+        transaction.commit();
+        if self.run_checks {
+            health::smoke_test(&version_dir, version, &self.smoke_tests, &ProcessEnv)?;
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use async_trait::async_trait;
+    use fs_err as fs;
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::{config::Config, progress::ProgressEvent};
+
+    /// Fetcher writing each task's file in place of a download.
+    struct FakeFetcher;
+
+    #[async_trait]
+    impl AssetFetcher for FakeFetcher {
+        async fn fetch_all(
+            &self,
+            tasks: Vec<DownloadTask>,
+            _version: &str,
+            version_dir: PathBuf,
+            reporter: Arc<dyn ProgressReporter>,
+        ) -> Result<()> {
+            fs::create_dir_all(&version_dir)?;
+            reporter.set_total(
+                tasks.len(),
+                tasks.iter().map(|t| t.dest_filename.clone()).collect(),
+            );
+            for task in &tasks {
+                fs::write(version_dir.join(&task.dest_filename), "")?;
+                reporter.component_completed(&task.dest_filename);
+            }
+            reporter.finish();
+            Ok(())
+        }
+
+        async fn fetch_optional(
+            &self,
+            _tasks: Vec<DownloadTask>,
+            _version: &str,
+            _dir: &Path,
+        ) -> Vec<String> {
+            Vec::new()
+        }
+    }
+
+    #[tokio::test]
+    async fn install_from_release_with_custom_fetcher_activates_and_streams_progress() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        let version_manager = VersionManager::new(config);
+        let (reporter, events) = progress::event_stream();
+        let installer = VersionInstaller::new(version_manager, FakeFetcher, "edgeandnode/amp")
+            .with_run_checks(false)
+            .with_reporter(reporter);
+
+        //* When
+        let result = installer
+            .install_from_release("v0.1.0", Platform::Linux, Architecture::X86_64)
+            .await;
+
+        //* Then
+        assert!(result.is_ok(), "install should succeed: {:?}", result.err());
+        let version_manager = VersionManager::new(
+            Config::new(Some(dir.path().to_path_buf())).expect("should create config"),
+        );
+        let key = installer.version_key("v0.1.0");
+        assert_eq!(
+            version_manager.get_current().ok().flatten(),
+            Some(key.clone())
+        );
+        assert!(version_manager.version_dir(&key).join("ampd").is_file());
+        drop(installer);
+        let events: Vec<ProgressEvent> = events.collect().await;
+        assert_eq!(events.last(), Some(&ProgressEvent::Finished));
+    }
+}
@@ -0,0 +1,233 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+#[derive(Debug)]
+pub enum InstanceError {
+    InvalidName {
+        name: String,
+    },
+    NotFound {
+        name: String,
+    },
+    VersionPinned {
+        version: String,
+        instances: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for InstanceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidName { name } => {
+                writeln!(f, "Invalid instance name")?;
+                writeln!(f, "  Name: {}", name)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Names may only contain letters, digits, '-' and '_', and can't start with '-'."
+                )?;
+            }
+            Self::NotFound { name } => {
+                writeln!(f, "Instance not found")?;
+                writeln!(f, "  Name: {}", name)?;
+                writeln!(f)?;
+                write!(f, "  Try: ampup instances list")?;
+            }
+            Self::VersionPinned { version, instances } => {
+                writeln!(f, "Version is pinned by instances")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Instances: {}", instances.join(", "))?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Pin them to another version or run 'ampup instances remove <name>' first."
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InstanceError {}
+
+/// An ampd instance pinned to a version, run through its wrapper script.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Instance {
+    /// Installed version the instance runs
+    pub version: String,
+    /// Arguments passed to ampd before any given to the wrapper (e.g. the
+    /// instance's config file)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+}
+
+/// Named ampd instances, stored in `~/.amp/instances.toml`.
+///
+/// Each instance gets a wrapper script at `~/.amp/bin/<name>-ampd` that runs
+/// its pinned version, so several versions can run side by side (e.g. as a
+/// systemd template with `ExecStart=~/.amp/bin/%i-ampd`) while `ampup use`
+/// only moves the default `ampd`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Instances {
+    instances: BTreeMap<String, Instance>,
+}
+
+impl Instances {
+    pub fn load(config: &Config) -> Result<Self> {
+        let path = config.instances_file();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(&path).context("Failed to read instances file")?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse instances file {}", path.display()))
+    }
+
+    pub fn save(&self, config: &Config) -> Result<()> {
+        let contents = toml::to_string(self).context("Failed to serialize instances")?;
+        fs::create_dir_all(&config.amp_dir).context("Failed to create amp directory")?;
+        fs::write(config.instances_file(), contents).context("Failed to write instances file")
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Instance> {
+        self.instances.get(name)
+    }
+
+    /// Instances in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Instance)> {
+        self.instances.iter()
+    }
+
+    /// Add `name`, or repin it when it already exists.
+    pub fn insert(&mut self, name: &str, instance: Instance) -> Result<()> {
+        validate_name(name)?;
+        self.instances.insert(name.to_string(), instance);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<Instance> {
+        self.instances
+            .remove(name)
+            .ok_or_else(|| InstanceError::NotFound {
+                name: name.to_string(),
+            })
+            .map_err(Into::into)
+    }
+
+    /// Names of the instances pinned to `version`.
+    pub fn pinning(&self, version: &str) -> Vec<String> {
+        self.instances
+            .iter()
+            .filter(|(_, instance)| instance.version == version)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+/// Write `name`'s wrapper script, running `binary` with the instance's
+/// arguments followed by the wrapper's own.
+pub fn write_wrapper(config: &Config, name: &str, binary: &Path, args: &[String]) -> Result<()> {
+    let path = config.instance_wrapper_path(name);
+    fs::create_dir_all(&config.bin_dir).context("Failed to create bin directory")?;
+    fs::write(&path, wrapper_script(name, binary, args))
+        .with_context(|| format!("Failed to write wrapper script {}", path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to set permissions on {}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn wrapper_script(name: &str, binary: &Path, args: &[String]) -> String {
+    let mut command = vec![shell_quote(&binary.to_string_lossy())];
+    command.extend(args.iter().map(|arg| shell_quote(arg)));
+    format!(
+        "#!/bin/sh\n# Generated by ampup for instance {}; regenerate with `ampup instances pin`\nexec {} \"$@\"\n",
+        name,
+        command.join(" ")
+    )
+}
+
+/// Quote `value` for a POSIX shell, leaving plain words unquoted.
+pub(crate) fn shell_quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/._-=:,+@".contains(c));
+    if plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(InstanceError::InvalidName {
+            name: name.to_string(),
+        }
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn wrapper_script_with_args_quotes_them_before_forwarded_args() {
+        //* Given
+        let binary = PathBuf::from("/home/amp/.amp/versions/v0.3.0/ampd");
+        let args = vec![
+            "--config".to_string(),
+            "/etc/amp/indexer a.toml".to_string(),
+        ];
+
+        //* When
+        let script = wrapper_script("indexer-a", &binary, &args);
+
+        //* Then
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.ends_with(
+            "exec /home/amp/.amp/versions/v0.3.0/ampd --config '/etc/amp/indexer a.toml' \"$@\"\n"
+        ));
+    }
+
+    #[test]
+    fn insert_with_path_separator_in_name_fails_with_invalid_name() {
+        //* Given
+        let mut instances = Instances::default();
+        let instance = Instance {
+            version: "v0.3.0".to_string(),
+            args: Vec::new(),
+        };
+
+        //* When
+        let result = instances.insert("../ampd", instance);
+
+        //* Then
+        let err = result.expect_err("names with path separators should be rejected");
+        assert!(matches!(
+            err.downcast_ref::<InstanceError>(),
+            Some(InstanceError::InvalidName { .. })
+        ));
+    }
+}
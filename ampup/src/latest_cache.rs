@@ -0,0 +1,110 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+
+use crate::version_store::repo_namespace;
+
+/// How long bare `ampup` reuses the latest version it looked up, unless
+/// `update.latest_cache_secs` is set.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// The latest release of a repository as last looked up, so repeated runs
+/// of bare `ampup` (e.g. from scripts) don't spend rate limit on the same
+/// answer.
+pub struct LatestCache {
+    path: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    version: String,
+    /// When the version was looked up, in seconds since the Unix epoch
+    checked_at: u64,
+}
+
+impl LatestCache {
+    /// The cache for `repo`, kept in `cache_dir/latest-<owner>-<repo>.json`.
+    pub fn new(cache_dir: &Path, repo: &str) -> Self {
+        Self {
+            path: cache_dir.join(format!("latest-{}.json", repo_namespace(repo))),
+        }
+    }
+
+    /// The cached latest version and how long ago it was looked up, when
+    /// that was less than `ttl` ago.
+    pub fn get(&self, ttl: Duration) -> Option<(String, Duration)> {
+        let contents = fs::read_to_string(&self.path).ok()?;
+        let entry: Entry = serde_json::from_str(&contents).ok()?;
+        let age = Duration::from_secs(now().saturating_sub(entry.checked_at));
+        (age < ttl).then_some((entry.version, age))
+    }
+
+    /// Record `version` as the latest, looked up now.
+    pub fn set(&self, version: &str) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).context("Failed to create cache directory")?;
+        }
+        let entry = Entry {
+            version: version.to_string(),
+            checked_at: now(),
+        };
+        let contents =
+            serde_json::to_string(&entry).context("Failed to serialize latest version")?;
+        fs::write(&self.path, contents).context("Failed to write latest version cache")
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_with_fresh_entry_returns_version() -> Result<()> {
+        //* Given
+        let dir = tempfile::tempdir()?;
+        let cache = LatestCache::new(dir.path(), "edgeandnode/amp");
+        cache.set("v0.4.0")?;
+
+        //* When
+        let cached = cache.get(DEFAULT_TTL);
+
+        //* Then
+        assert_eq!(
+            cached.map(|(version, _)| version).as_deref(),
+            Some("v0.4.0")
+        );
+        assert!(
+            LatestCache::new(dir.path(), "myorg/amp")
+                .get(DEFAULT_TTL)
+                .is_none()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn get_with_zero_ttl_returns_none() -> Result<()> {
+        //* Given
+        let dir = tempfile::tempdir()?;
+        let cache = LatestCache::new(dir.path(), "edgeandnode/amp");
+        cache.set("v0.4.0")?;
+
+        //* When
+        let cached = cache.get(Duration::ZERO);
+
+        //* Then
+        assert!(cached.is_none());
+        Ok(())
+    }
+}
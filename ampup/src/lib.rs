@@ -1,16 +1,65 @@
+pub mod advisory;
+pub mod archive;
+pub mod artifact_cache;
+pub mod build_label;
+pub mod build_log;
 pub mod builder;
+pub mod bundle;
+pub mod channels;
+pub mod chunked;
 pub mod commands;
+pub mod components;
 pub mod config;
+pub mod data_dir;
+pub mod default_command;
+pub mod dispatch;
+pub mod downgrade;
 pub mod download_manager;
+pub mod egress;
+pub mod endpoint;
+pub mod environment;
+pub mod executable;
+pub mod extras;
+pub mod fetcher;
+pub mod gatekeeper;
 pub mod github;
+pub mod health;
+pub mod heartbeat;
+pub mod hooks;
 pub mod install;
+pub mod instances;
+pub mod latest_cache;
+pub mod lock;
+pub mod manifest;
+pub mod news;
+pub mod oci;
+pub mod overrides;
+pub mod ownership;
 pub mod platform;
+pub mod policy;
+pub mod portable;
+pub mod preallocate;
 pub mod progress;
+pub mod project;
+pub mod prune;
 pub mod rate_limiter;
+pub mod release_source;
+pub mod reproducible;
+pub mod request_plan;
+pub mod resolver;
+pub mod scheduler;
+pub mod service;
+pub mod settings;
 pub mod shell;
+pub mod target_matrix;
+pub mod temp_files;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
 pub mod token;
+pub mod transaction;
 pub mod updater;
 pub mod version_manager;
+pub mod version_store;
 
 #[macro_use]
 pub mod ui;
@@ -0,0 +1,122 @@
+use std::{
+    fs::TryLockError,
+    io::{Seek, Write},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::{config::Config, ui};
+
+#[derive(Debug)]
+pub enum LockError {
+    /// Another process holds the installation lock.
+    Busy { path: PathBuf, pid: Option<u32> },
+}
+
+impl std::fmt::Display for LockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Busy { path, pid } => {
+                writeln!(f, "Another ampup is running in this installation")?;
+                if let Some(pid) = pid {
+                    writeln!(f, "  Process: {}", pid)?;
+                }
+                writeln!(f, "  Lock: {}", path.display())?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Wait for it to finish, or pass --wait to wait automatically."
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LockError {}
+
+/// Advisory lock on an installation, held by commands that change installed
+/// versions or the active version so concurrent runs (e.g. parallel CI
+/// steps sharing `$AMP_DIR`) don't race. Released when dropped.
+pub struct InstallLock {
+    _file: fs::File,
+}
+
+impl InstallLock {
+    /// Lock the installation at `config`, failing with [`LockError::Busy`]
+    /// when another process holds it, or waiting for it with `wait`.
+    pub fn acquire(config: &Config, wait: bool) -> Result<Self> {
+        fs::create_dir_all(&config.amp_dir).context("Failed to create amp directory")?;
+        let path = config.lock_file();
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&path)?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) if wait => {
+                ui::info!("Waiting for another ampup to finish");
+                file.lock().context("Failed to lock installation")?;
+            }
+            Err(TryLockError::WouldBlock) => {
+                let pid = fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|contents| contents.trim().parse().ok());
+                return Err(LockError::Busy { path, pid }.into());
+            }
+            Err(TryLockError::Error(err)) => {
+                return Err(err).context("Failed to lock installation");
+            }
+        }
+
+        // Record the holder for the busy message of other runs
+        file.set_len(0)?;
+        file.rewind()?;
+        write!(file, "{}", std::process::id())?;
+        Ok(Self { _file: file })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acquire_with_lock_held_fails_with_holder_pid() -> Result<()> {
+        //* Given
+        let dir = tempfile::tempdir()?;
+        let config = Config::new(Some(dir.path().to_path_buf()))?;
+        let _held = InstallLock::acquire(&config, false)?;
+
+        //* When
+        let result = InstallLock::acquire(&config, false);
+
+        //* Then
+        let err = result.err().expect("second lock should fail");
+        assert!(matches!(
+            err.downcast_ref::<LockError>(),
+            Some(LockError::Busy { pid: Some(pid), .. }) if *pid == std::process::id()
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn acquire_after_release_succeeds() -> Result<()> {
+        //* Given
+        let dir = tempfile::tempdir()?;
+        let config = Config::new(Some(dir.path().to_path_buf()))?;
+        drop(InstallLock::acquire(&config, false)?);
+
+        //* When
+        let result = InstallLock::acquire(&config, false);
+
+        //* Then
+        assert!(result.is_ok(), "released lock should be acquirable");
+        Ok(())
+    }
+}
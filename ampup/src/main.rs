@@ -1,5 +1,24 @@
-use ampup::{DEFAULT_DOWNLOAD_JOBS, DEFAULT_REPO, DEFAULT_SELF_REPO, commands};
-use console::style;
+use std::time::Duration;
+
+use ampup::{
+    DEFAULT_DOWNLOAD_JOBS,
+    builder::CargoOptions,
+    commands,
+    config::Config,
+    default_command::{self, DefaultCommand},
+    dispatch,
+    endpoint::MetadataEndpoint,
+    github::{ClientOptions, HttpTimeouts},
+    hooks::HookEvent,
+    lock::InstallLock,
+    ownership, prune,
+    resolver::Channel,
+    service::RestartPolicy,
+    settings::Settings,
+    temp_files,
+    ui::Theme,
+    version_manager::VersionManager,
+};
 
 /// The ampd installer and version manager
 #[derive(Debug, clap::Parser)]
@@ -7,10 +26,125 @@ use console::style;
 #[command(about = "The ampd installer and version manager", long_about = None)]
 #[command(version = env!("VERGEN_GIT_DESCRIBE"))]
 struct Cli {
+    #[command(flatten)]
+    http: HttpArgs,
+
+    /// Print status messages to stdout instead of stderr, as older versions did
+    #[arg(
+        long,
+        env = "AMPUP_LEGACY_OUTPUT",
+        global = true,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    legacy_output: bool,
+
+    /// Output theme (overrides `ui.theme` in config.toml)
+    #[arg(long, env = "AMPUP_UI", global = true, value_name = "THEME")]
+    ui: Option<Theme>,
+
+    /// Screen-reader friendly output: no color, symbols or redrawn progress,
+    /// and a sentence as each step starts and finishes (same as --ui accessible)
+    #[arg(
+        long,
+        env = "AMPUP_ACCESSIBLE",
+        global = true,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    accessible: bool,
+
+    /// Report how long each artifact took to download, verify and write
+    #[arg(
+        long,
+        env = "AMPUP_TIMINGS",
+        global = true,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    timings: bool,
+
+    /// Seconds between "still running" lines during long downloads and
+    /// builds when stderr isn't a terminal, for CI idle timeouts (0 disables)
+    #[arg(
+        long,
+        env = "AMPUP_HEARTBEAT",
+        global = true,
+        value_name = "SECONDS",
+        default_value_t = ampup::heartbeat::DEFAULT_INTERVAL_SECS
+    )]
+    heartbeat: u64,
+
+    /// With no command, look up the latest version even if it was checked recently
+    #[arg(long)]
+    refresh: bool,
+
+    /// What to do when run without a command (overrides `default_command` in config.toml)
+    #[arg(long, env = "AMPUP_DEFAULT_COMMAND", value_name = "COMMAND")]
+    default_command: Option<DefaultCommand>,
+
+    /// Wait for another ampup changing the same installation to finish instead of failing
+    #[arg(long, env = "AMPUP_WAIT", global = true, value_parser = clap::builder::BoolishValueParser::new())]
+    wait: bool,
+
+    /// Run commands changing an installation as root even when another user owns it
+    #[arg(long, env = "AMPUP_ALLOW_ROOT", global = true, value_parser = clap::builder::BoolishValueParser::new())]
+    allow_root: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+/// HTTP options accepted by every command (override config.toml)
+#[derive(Debug, clap::Args)]
+struct HttpArgs {
+    /// Seconds allowed for establishing a connection
+    #[arg(
+        long,
+        env = "AMPUP_CONNECT_TIMEOUT",
+        global = true,
+        value_name = "SECS"
+    )]
+    connect_timeout: Option<u64>,
+
+    /// Seconds allowed between reads of a response body
+    #[arg(long, env = "AMPUP_READ_TIMEOUT", global = true, value_name = "SECS")]
+    read_timeout: Option<u64>,
+
+    /// Seconds allowed for a whole request, including the download
+    #[arg(long, env = "AMPUP_TIMEOUT", global = true, value_name = "SECS")]
+    timeout: Option<u64>,
+
+    /// GitHub Enterprise REST API URL (e.g., https://github.example.com/api/v3)
+    #[arg(long, env = "AMPUP_GITHUB_API_URL", global = true, value_name = "URL")]
+    api_url: Option<String>,
+
+    /// Longest GitHub rate-limit pause to wait out before failing (default 60)
+    #[arg(
+        long,
+        env = "AMPUP_MAX_RATE_LIMIT_WAIT",
+        global = true,
+        value_name = "SECS"
+    )]
+    max_rate_limit_wait: Option<u64>,
+
+    /// Where amp and ampup release metadata is read from (default: auto, the faster of the two)
+    #[arg(long, env = "AMPUP_METADATA_ENDPOINT", global = true, value_enum)]
+    metadata_endpoint: Option<MetadataEndpoint>,
+}
+
+impl HttpArgs {
+    fn client_options(&self) -> ClientOptions {
+        ClientOptions {
+            timeouts: HttpTimeouts {
+                connect: self.connect_timeout.map(Duration::from_secs),
+                read: self.read_timeout.map(Duration::from_secs),
+                total: self.timeout.map(Duration::from_secs),
+            },
+            api_url: self.api_url.clone(),
+            max_rate_limit_wait: self.max_rate_limit_wait.map(Duration::from_secs),
+            metadata_endpoint: self.metadata_endpoint,
+        }
+    }
+}
+
 #[derive(Debug, clap::Subcommand)]
 enum Commands {
     /// Initialize ampup (called by install script)
@@ -28,8 +162,8 @@ enum Commands {
         #[arg(long)]
         no_install_latest: bool,
 
-        /// GitHub token for private repository access (defaults to $GITHUB_TOKEN)
-        #[arg(long, env = "GITHUB_TOKEN", hide_env = true)]
+        /// GitHub token for private repository access (defaults to the repo's token_command, then $GITHUB_TOKEN)
+        #[arg(long)]
         github_token: Option<String>,
     },
 
@@ -39,15 +173,15 @@ enum Commands {
         #[arg(long, env = "AMP_DIR")]
         install_dir: Option<std::path::PathBuf>,
 
-        /// Version to install (e.g., v0.1.0). If not specified, installs latest
+        /// Version to install (e.g., v0.1.0, 0.3, ^0.2, beta). If not specified, installs latest
         version: Option<String>,
 
-        /// GitHub repository in format "owner/repo"
-        #[arg(long, default_value_t = DEFAULT_REPO.to_string())]
-        repo: String,
+        /// GitHub repository in format "owner/repo" (defaults to `repo` in config.toml, then edgeandnode/amp)
+        #[arg(long)]
+        repo: Option<String>,
 
-        /// GitHub token for private repository access (defaults to $GITHUB_TOKEN)
-        #[arg(long, env = "GITHUB_TOKEN", hide_env = true)]
+        /// GitHub token for private repository access (defaults to the repo's token_command, then $GITHUB_TOKEN)
+        #[arg(long)]
         github_token: Option<String>,
 
         /// Override architecture detection (x86_64, aarch64)
@@ -61,6 +195,119 @@ enum Commands {
         /// Number of concurrent downloads
         #[arg(short = 'j', long = "jobs", default_value_t = DEFAULT_DOWNLOAD_JOBS)]
         jobs: usize,
+
+        /// Where to download binaries: github, oci://<registry>/<repo>, or a mirror URL template (e.g., https://mirror.example.com/amp/{version}/{asset})
+        #[arg(long, alias = "mirror", env = "AMPUP_SOURCE", value_name = "SOURCE")]
+        source: Option<String>,
+
+        /// Build from source without asking when the release has no binaries for this platform
+        #[arg(long)]
+        fallback_build: bool,
+
+        /// Also look up draft releases by tag (requires a token with push access)
+        #[arg(long)]
+        include_drafts: bool,
+
+        /// Install the version even if its release has been yanked
+        #[arg(long)]
+        force: bool,
+
+        /// Install the version even if its release is marked as a prerelease
+        #[arg(long)]
+        allow_prerelease: bool,
+
+        /// Resolve latest to the newest release including prereleases (release candidates, betas)
+        #[arg(long)]
+        prerelease: bool,
+
+        /// Activate the version even if it is older than the active one
+        #[arg(long)]
+        allow_downgrade: bool,
+
+        /// Re-run the smoke tests when the version is already installed
+        #[arg(long)]
+        smoke_test: bool,
+
+        /// Lay out a portable .amp tree in this directory for another machine, with relative links, instead of installing here
+        #[arg(long, value_name = "DIR")]
+        dest_root: Option<std::path::PathBuf>,
+
+        /// Install without activating the version (no bin/ links or .version)
+        #[arg(long)]
+        no_activate: bool,
+    },
+
+    /// Download a release's binaries into a directory without installing them
+    Fetch {
+        /// Installation directory whose settings and artifact cache to use (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Version to fetch (e.g., v0.1.0, 0.3, ^0.2, beta). If not specified, fetches latest
+        version: Option<String>,
+
+        /// Platform and architecture to fetch for, as <platform>-<arch> (e.g., linux-x86_64); repeatable, defaults to this host
+        #[arg(long = "target", value_name = "TARGET")]
+        targets: Vec<String>,
+
+        /// Directory to write <version>/<artifact> files into
+        #[arg(short, long, default_value = ".")]
+        output: std::path::PathBuf,
+
+        /// GitHub repository in format "owner/repo" (defaults to `repo` in config.toml, then edgeandnode/amp)
+        #[arg(long)]
+        repo: Option<String>,
+
+        /// GitHub token for private repository access (defaults to the repo's token_command, then $GITHUB_TOKEN)
+        #[arg(long)]
+        github_token: Option<String>,
+
+        /// Number of concurrent downloads
+        #[arg(short = 'j', long = "jobs", default_value_t = DEFAULT_DOWNLOAD_JOBS)]
+        jobs: usize,
+
+        /// Where to download binaries: github, oci://<registry>/<repo>, or a mirror URL template (e.g., https://mirror.example.com/amp/{version}/{asset})
+        #[arg(long, alias = "mirror", env = "AMPUP_SOURCE", value_name = "SOURCE")]
+        source: Option<String>,
+
+        /// Fetch the version even if its release is marked as a prerelease
+        #[arg(long)]
+        allow_prerelease: bool,
+    },
+
+    /// Resolve a version spec to a concrete version and print it
+    Resolve {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Version spec (e.g., latest, v0.1.0, 0.3, ^0.2, beta, nightly, pr:123, commit:abc123)
+        #[arg(default_value = "latest")]
+        spec: String,
+
+        /// GitHub repository in format "owner/repo" (defaults to `repo` in config.toml, then edgeandnode/amp)
+        #[arg(long)]
+        repo: Option<String>,
+
+        /// GitHub token for private repository access (defaults to the repo's token_command, then $GITHUB_TOKEN)
+        #[arg(long)]
+        github_token: Option<String>,
+
+        /// Override architecture detection (x86_64, aarch64)
+        #[arg(long)]
+        arch: Option<String>,
+
+        /// Override platform detection (linux, darwin)
+        #[arg(long)]
+        platform: Option<String>,
+
+        /// Also print the download URLs of the release assets
+        #[arg(long)]
+        assets: bool,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// List installed versions
@@ -68,6 +315,18 @@ enum Commands {
         /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
         #[arg(long, env = "AMP_DIR")]
         install_dir: Option<std::path::PathBuf>,
+
+        /// Print the versions as JSON, with sizes in bytes and Unix timestamps
+        #[arg(long)]
+        json: bool,
+
+        /// Show each version's disk usage, largest first, and the total
+        #[arg(long)]
+        size: bool,
+
+        /// Also show when each version was last activated and last run
+        #[arg(long, short)]
+        long: bool,
     },
 
     /// Switch to a specific installed version
@@ -78,6 +337,34 @@ enum Commands {
 
         /// Version to switch to (if not provided, shows interactive selection)
         version: Option<String>,
+
+        /// Repository the version was installed from, when it's installed from several
+        #[arg(long)]
+        repo: Option<String>,
+
+        /// Activate the version even if it is older than the active one
+        #[arg(long)]
+        allow_downgrade: bool,
+
+        /// Pin the version for the project in the current directory by writing
+        /// its .amp-version, instead of switching the active version
+        #[arg(long, conflicts_with = "allow_downgrade")]
+        project: bool,
+
+        /// Activate the version even if its binaries don't match the checksums recorded at install
+        #[arg(long, conflicts_with = "project")]
+        force: bool,
+    },
+
+    /// Switch back to the version that was active before the current one
+    Rollback {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Activate the version even if its binaries don't match the checksums recorded at install
+        #[arg(long)]
+        force: bool,
     },
 
     /// Uninstall a specific version
@@ -88,55 +375,833 @@ enum Commands {
 
         /// Version to uninstall
         version: String,
+
+        /// Repository the version was installed from, when it's installed from several
+        #[arg(long)]
+        repo: Option<String>,
+
+        /// Uninstall the version even if it's pinned
+        #[arg(long)]
+        force: bool,
     },
 
-    /// Build and install from source
-    Build {
+    /// Keep an installed version around: prune skips it and uninstall needs --force
+    Pin {
         /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
         #[arg(long, env = "AMP_DIR")]
         install_dir: Option<std::path::PathBuf>,
 
-        /// Build from local repository path
-        #[arg(short, long, conflicts_with_all = ["repo", "branch", "commit", "pr"])]
-        path: Option<std::path::PathBuf>,
+        /// Version to pin (lists pinned versions when omitted)
+        version: Option<String>,
 
-        /// GitHub repository in format "owner/repo"
-        #[arg(short, long, conflicts_with = "path")]
+        /// Repository the version was installed from, when it's installed from several
+        #[arg(long)]
         repo: Option<String>,
+    },
 
-        /// Build from specific branch
-        #[arg(short, long, conflicts_with_all = ["path", "commit", "pr"])]
-        branch: Option<String>,
+    /// Release a version held with pin
+    Unpin {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
 
-        /// Build from specific commit hash
-        #[arg(short = 'C', long, conflicts_with_all = ["path", "branch", "pr"])]
-        commit: Option<String>,
+        /// Version to unpin
+        version: String,
 
-        /// Build from pull request number
-        #[arg(short = 'P', long, conflicts_with_all = ["path", "branch", "commit"])]
-        pr: Option<u32>,
+        /// Repository the version was installed from, when it's installed from several
+        #[arg(long)]
+        repo: Option<String>,
+    },
+
+    /// Remove installed versions by retention policy, keeping the active and pinned ones
+    Prune {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Keep the N most recently installed versions
+        #[arg(long, value_name = "N")]
+        keep_latest: Option<usize>,
+
+        /// Only remove versions installed longer ago than this (e.g. 30d, 12h, 2w)
+        #[arg(long, value_name = "AGE", value_parser = prune::parse_age)]
+        older_than: Option<Duration>,
+
+        /// Only remove versions not activated or run for this long (e.g. 60d)
+        #[arg(long, value_name = "AGE", value_parser = prune::parse_age)]
+        unused_for: Option<Duration>,
+
+        /// Only remove source builds, keeping installed releases
+        #[arg(long)]
+        builds_only: bool,
+
+        /// List the versions that would be removed without removing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Remove temporary files interrupted runs left behind
+    Clean {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Also remove version directories missing ampd
+        #[arg(long)]
+        incomplete: bool,
+
+        /// Only remove temporary files last modified longer ago than this (e.g. 1d)
+        #[arg(long, value_name = "AGE", value_parser = prune::parse_age)]
+        older_than: Option<Duration>,
+
+        /// List what would be removed without removing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Package an installed version into a portable .tar.gz for ampup import
+    Export {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Version to export
+        version: String,
+
+        /// Repository the version was installed from, when it's installed from several
+        #[arg(long)]
+        repo: Option<String>,
+
+        /// Path of the tarball to write (defaults to ./<namespace>-<version>.tar.gz)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// Install a version from a tarball written by ampup export, without network access
+    Import {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Tarball to import
+        file: std::path::PathBuf,
+
+        /// Switch to the version once imported
+        #[arg(long)]
+        activate: bool,
+
+        /// Replace the version if it's already installed
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Take over an ampd binary installed outside ampup, so ampup manages it
+    Adopt {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Path of the ampd binary (e.g., /usr/local/bin/ampd)
+        path: std::path::PathBuf,
+
+        /// Path of the ampctl binary (defaults to ampctl beside ampd, if any)
+        #[arg(long, value_name = "PATH")]
+        ampctl: Option<std::path::PathBuf>,
+
+        /// Version name to store it under (defaults to the version `ampd --version` reports)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Remove the originals once adopted instead of copying them
+        #[arg(long = "move")]
+        move_files: bool,
+
+        /// Switch to the version once adopted
+        #[arg(long)]
+        activate: bool,
+
+        /// Replace the version if it's already installed
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Package versions and ampup into a signed archive for hosts without network access
+    Bundle {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR", global = true)]
+        install_dir: Option<std::path::PathBuf>,
+
+        #[command(subcommand)]
+        command: BundleCommands,
+    },
+
+    /// Show the installation directory, active version and effective settings
+    Status {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+    },
+
+    /// Collect diagnostics into a .tar.gz to attach to a bug report
+    SupportBundle {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Path of the bundle to write (defaults to ./ampup-support-<timestamp>.tar.gz)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// Check installed versions against security advisories, yanked releases and published digests
+    Audit {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// GitHub repository in format "owner/repo" (defaults to `repo` in config.toml, then edgeandnode/amp)
+        #[arg(long)]
+        repo: Option<String>,
+
+        /// GitHub token for private repository access (defaults to the repo's token_command, then $GITHUB_TOKEN)
+        #[arg(long)]
+        github_token: Option<String>,
+
+        /// Advisory feed to check against (defaults to https://ampup.sh/advisories.json)
+        #[arg(long, env = "AMPUP_ADVISORIES_URL", value_name = "URL")]
+        advisories_url: Option<String>,
+    },
+
+    /// Rebuild a version built with `ampup build --reproducible` and compare its hashes
+    Verify {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Version to rebuild from its recorded recipe
+        #[arg(long, value_name = "VERSION")]
+        rebuild: String,
+
+        /// Repository the version was installed from, when it's installed from several
+        #[arg(long)]
+        repo: Option<String>,
+
+        /// Number of CPU cores to use when building
+        #[arg(short, long)]
+        jobs: Option<usize>,
+    },
+
+    /// Check the published install script against the ampup release and print the install one-liner
+    VerifyInstallScript {
+        /// ampup release to check against (defaults to latest)
+        #[arg(long)]
+        version: Option<String>,
+
+        /// Install script URL (defaults to https://ampup.sh/install)
+        #[arg(long, value_name = "URL", conflicts_with = "file")]
+        url: Option<String>,
+
+        /// Check a downloaded copy of the install script instead of fetching it
+        #[arg(long, value_name = "PATH")]
+        file: Option<std::path::PathBuf>,
+
+        /// GitHub repository in format "owner/repo" (defaults to `self_repo` in config.toml, then edgeandnode/ampup)
+        #[arg(long)]
+        repo: Option<String>,
+
+        /// GitHub token for private repository access (defaults to the repo's token_command, then $GITHUB_TOKEN)
+        #[arg(long)]
+        github_token: Option<String>,
+    },
+
+    /// Summarize releases, notable merged pull requests and announcements since the active version
+    News {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// GitHub repository in format "owner/repo" (defaults to `repo` in config.toml, then edgeandnode/amp)
+        #[arg(long)]
+        repo: Option<String>,
+
+        /// GitHub token for private repository access (defaults to the repo's token_command, then $GITHUB_TOKEN)
+        #[arg(long)]
+        github_token: Option<String>,
+
+        /// Release to report news since (defaults to the active version)
+        #[arg(long, value_name = "VERSION")]
+        since: Option<String>,
+
+        /// Fetch fresh news instead of reusing news fetched in the last hour
+        #[arg(long)]
+        refresh: bool,
+    },
+
+    /// Manage hook scripts run when the active version changes
+    Hooks {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR", global = true)]
+        install_dir: Option<std::path::PathBuf>,
+
+        #[command(subcommand)]
+        command: HooksCommands,
+    },
+
+    /// Manage optional components of the active version, such as language servers or debug symbols
+    Component {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR", global = true)]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// GitHub repository in format "owner/repo" (defaults to `repo` in config.toml, then edgeandnode/amp)
+        #[arg(long, global = true)]
+        repo: Option<String>,
+
+        /// GitHub token for private repository access (defaults to the repo's token_command, then $GITHUB_TOKEN)
+        #[arg(long, global = true)]
+        github_token: Option<String>,
+
+        #[command(subcommand)]
+        command: ComponentCommands,
+    },
+
+    /// Inspect the downloaded artifact cache
+    Cache {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR", global = true)]
+        install_dir: Option<std::path::PathBuf>,
+
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+
+    /// Inspect what ampup would do without doing it
+    Debug {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR", global = true)]
+        install_dir: Option<std::path::PathBuf>,
+
+        #[command(subcommand)]
+        command: DebugCommands,
+    },
+
+    /// Diagnose how ampup interacts with this host
+    Doctor {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR", global = true)]
+        install_dir: Option<std::path::PathBuf>,
+
+        #[command(subcommand)]
+        command: DoctorCommands,
+    },
+
+    /// Give files in the installation left owned by another user (e.g. by sudo) back to its owner
+    FixPermissions {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+    },
+
+    /// Read and write persistent settings in config.toml
+    Config {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR", global = true)]
+        install_dir: Option<std::path::PathBuf>,
+
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+
+    /// Generate service definitions for running ampd
+    Generate {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR", global = true)]
+        install_dir: Option<std::path::PathBuf>,
+
+        #[command(subcommand)]
+        command: GenerateCommands,
+    },
+
+    /// Pin named ampd instances to versions, each run through ~/.amp/bin/<instance>-ampd
+    Instances {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR", global = true)]
+        install_dir: Option<std::path::PathBuf>,
+
+        #[command(subcommand)]
+        command: InstanceCommands,
+    },
+
+    /// Print the path of the binary ampd or ampctl runs in the current directory
+    Which {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Binary to look up
+        #[arg(default_value = "ampd", value_parser = ["ampd", "ampctl"])]
+        binary: String,
+    },
+
+    /// Override the version ampd and ampctl run in a directory and below it
+    Override {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR", global = true)]
+        install_dir: Option<std::path::PathBuf>,
+
+        #[command(subcommand)]
+        command: OverrideCommands,
+    },
+
+    /// Start a subshell where ampd and ampctl resolve to a specific installed version
+    Shell {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Version to use inside the subshell
+        version: String,
+
+        /// Print shell commands setting up the version in the current shell
+        /// instead, for `eval "$(ampup shell <version> --env)"`
+        #[arg(long)]
+        env: bool,
+    },
+
+    /// Build and install from source
+    Build {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Build from local repository path
+        #[arg(short, long, conflicts_with_all = ["repo", "branch", "commit", "tag", "pr"])]
+        path: Option<std::path::PathBuf>,
+
+        /// GitHub repository in format "owner/repo" (defaults to `repo` in config.toml, then edgeandnode/amp)
+        #[arg(short, long, conflicts_with = "path")]
+        repo: Option<String>,
+
+        /// Build from specific branch
+        #[arg(short, long, conflicts_with_all = ["path", "commit", "tag", "pr"])]
+        branch: Option<String>,
+
+        /// Build from specific commit hash
+        #[arg(short = 'C', long, conflicts_with_all = ["path", "branch", "tag", "pr"])]
+        commit: Option<String>,
+
+        /// Build from a release tag, installed under the tag name (e.g., v0.3.0)
+        #[arg(short, long, conflicts_with_all = ["path", "branch", "commit", "pr"])]
+        tag: Option<String>,
+
+        /// Build from pull request number
+        #[arg(short = 'P', long, conflicts_with_all = ["path", "branch", "commit", "tag"])]
+        pr: Option<u32>,
+
+        /// Custom version name (required for non-git local paths, optional otherwise)
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Number of CPU cores to use when building
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        /// Build reproducibly (pinned toolchain, SOURCE_DATE_EPOCH, remapped paths)
+        /// and record how, so `ampup verify --rebuild` can check the binaries
+        #[arg(long)]
+        reproducible: bool,
+
+        /// Build the local checkout even when it hasn't changed since its last build
+        #[arg(long, requires = "path")]
+        force: bool,
+
+        /// Clone the repository afresh instead of updating its cached clone in ~/.amp/cache/src
+        #[arg(long, conflicts_with = "path")]
+        fresh: bool,
+
+        /// Show only errors from git and cargo; the full output is still written to ~/.amp/logs
+        #[arg(long)]
+        summary: bool,
+
+        /// Cargo profile to build with, e.g. dev or a custom one (defaults to release; added to the version label otherwise)
+        #[arg(long, value_name = "PROFILE")]
+        profile: Option<String>,
+
+        /// Build with the dev profile, for unoptimized builds with debug info (same as --profile dev)
+        #[arg(long, conflicts_with = "profile")]
+        debug: bool,
+
+        /// Cargo features to enable, comma-separated (added to the version label)
+        #[arg(short = 'F', long, value_delimiter = ',')]
+        features: Vec<String>,
+
+        /// Build without ampd's and ampctl's default features (added to the version label)
+        #[arg(long)]
+        no_default_features: bool,
+
+        /// Further arguments for cargo build, after `--` (e.g. -- --locked --timings)
+        #[arg(last = true, value_name = "CARGO_ARGS")]
+        cargo_args: Vec<String>,
+    },
+
+    /// Update to the latest ampd version (default behavior)
+    Update {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Version spec to update within, e.g. 0.3 or "^0.2" for the newest matching release (defaults to latest)
+        version: Option<String>,
+
+        /// GitHub repository in format "owner/repo" (defaults to `repo` in config.toml, then edgeandnode/amp)
+        #[arg(long)]
+        repo: Option<String>,
+
+        /// GitHub token for private repository access (defaults to the repo's token_command, then $GITHUB_TOKEN)
+        #[arg(long)]
+        github_token: Option<String>,
+
+        /// Override architecture detection (x86_64, aarch64)
+        #[arg(long)]
+        arch: Option<String>,
+
+        /// Override platform detection (linux, darwin)
+        #[arg(long)]
+        platform: Option<String>,
+
+        /// Number of concurrent downloads
+        #[arg(short = 'j', long = "jobs", default_value_t = DEFAULT_DOWNLOAD_JOBS)]
+        jobs: usize,
+
+        /// Where to download binaries: github, oci://<registry>/<repo>, or a mirror URL template (e.g., https://mirror.example.com/amp/{version}/{asset})
+        #[arg(long, alias = "mirror", env = "AMPUP_SOURCE", value_name = "SOURCE")]
+        source: Option<String>,
+
+        /// Build from source without asking when the release has no binaries for this platform
+        #[arg(long)]
+        fallback_build: bool,
+
+        /// Resolve latest to the newest release including prereleases (release candidates, betas)
+        #[arg(long)]
+        prerelease: bool,
+
+        /// Follow a release channel from now on (stable, beta, nightly), saved as update.channel
+        #[arg(long, conflicts_with = "version")]
+        channel: Option<Channel>,
+    },
+
+    /// Upgrade the active version, running migration steps published with the release
+    Upgrade {
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// Version spec to upgrade to (defaults to latest)
+        version: Option<String>,
+
+        /// GitHub repository in format "owner/repo" (defaults to `repo` in config.toml, then edgeandnode/amp)
+        #[arg(long)]
+        repo: Option<String>,
+
+        /// GitHub token for private repository access (defaults to the repo's token_command, then $GITHUB_TOKEN)
+        #[arg(long)]
+        github_token: Option<String>,
+
+        /// Number of concurrent downloads
+        #[arg(short = 'j', long = "jobs", default_value_t = DEFAULT_DOWNLOAD_JOBS)]
+        jobs: usize,
+
+        /// Confirm each stage interactively and run the release's migration steps
+        #[arg(long)]
+        guided: bool,
+
+        /// Upgrade even if the target release is marked as a prerelease
+        #[arg(long)]
+        allow_prerelease: bool,
+
+        /// Resolve latest to the newest release including prereleases (release candidates, betas)
+        #[arg(long)]
+        prerelease: bool,
+
+        /// Activate the target even if it is older than the active version
+        #[arg(long)]
+        allow_downgrade: bool,
+    },
+
+    /// Manage the ampup executable
+    #[command(name = "self")]
+    SelfCmd {
+        #[command(subcommand)]
+        command: SelfCommands,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum ConfigCommands {
+    /// Print the value of a setting
+    Get {
+        /// Setting key (e.g., repo, http.timeout)
+        key: String,
+    },
+
+    /// Persist a setting
+    Set {
+        /// Setting key (e.g., repo, http.timeout)
+        key: String,
+
+        /// New value
+        value: String,
+    },
+
+    /// Remove a setting, restoring its default
+    Unset {
+        /// Setting key (e.g., repo, http.timeout)
+        key: String,
+    },
+
+    /// List all settings and their values
+    List,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum GenerateCommands {
+    /// Print a systemd user unit (a launchd plist on macOS) running the active ampd
+    Systemd {
+        /// Run an instance's wrapper script instead of the active ampd
+        #[arg(long)]
+        instance: Option<String>,
+
+        /// When the service manager restarts ampd after it exits
+        #[arg(long, value_enum, default_value_t = RestartPolicy::OnFailure)]
+        restart: RestartPolicy,
+
+        /// Generate for another platform (linux, darwin)
+        #[arg(long)]
+        platform: Option<String>,
+
+        /// Write the unit to the user's service directory, then enable and start it
+        #[arg(long)]
+        install: bool,
+
+        /// Arguments passed to ampd (e.g., -- --config /etc/amp/config.toml)
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum InstanceCommands {
+    /// Pin an instance to an installed version, creating or updating its wrapper script
+    Pin {
+        /// Instance name (letters, digits, '-' and '_')
+        name: String,
+
+        /// Installed version the instance runs
+        version: String,
+
+        /// Arguments always passed to ampd by the wrapper (e.g., -- --config /etc/amp/a.toml)
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+
+    /// List instances with their pinned versions and wrapper scripts
+    List,
+
+    /// Remove an instance and its wrapper script
+    Remove {
+        /// Instance name
+        name: String,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum OverrideCommands {
+    /// Override a directory with an installed version
+    Set {
+        /// Installed version to run in the directory
+        version: String,
+
+        /// Directory to override (defaults to the current directory)
+        #[arg(long)]
+        path: Option<std::path::PathBuf>,
+    },
+
+    /// Remove a directory's override
+    Unset {
+        /// Directory whose override to remove (defaults to the current directory)
+        #[arg(long, conflicts_with = "nonexistent")]
+        path: Option<std::path::PathBuf>,
+
+        /// Remove the overrides of every directory that no longer exists
+        #[arg(long)]
+        nonexistent: bool,
+    },
+
+    /// List directory overrides with their versions
+    List,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum HooksCommands {
+    /// List installed hooks by event, in the order they run
+    #[command(alias = "ls")]
+    List,
+    /// Install a script as a hook
+    Add {
+        /// Event to run the hook on
+        #[arg(value_enum)]
+        event: HookEvent,
+        /// Script to copy into the hooks directory
+        script: std::path::PathBuf,
+        /// Name of the hook, which orders it among the event's hooks (defaults to the script's file name)
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Remove a hook
+    #[command(alias = "rm")]
+    Remove {
+        #[arg(value_enum)]
+        event: HookEvent,
+        /// Name of the hook, as shown by `ampup hooks list`
+        name: String,
+    },
+    /// Run an event's hooks now and report each outcome
+    Run {
+        #[arg(value_enum)]
+        event: HookEvent,
+        /// Run only this hook
+        name: Option<String>,
+        /// Pass made-up event data (AMPUP_HOOK_TEST=1) instead of the active version
+        #[arg(long)]
+        test: bool,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum BundleCommands {
+    /// Download versions of ampd and ampctl, plus ampup, into one bundle
+    Create {
+        /// Versions to include, comma-separated (e.g., v0.3.0,v0.3.1)
+        #[arg(long, value_delimiter = ',', required = true)]
+        versions: Vec<String>,
+
+        /// Target platform (defaults to this host's)
+        #[arg(long)]
+        platform: Option<String>,
+
+        /// Target architecture (defaults to this host's)
+        #[arg(long)]
+        arch: Option<String>,
+
+        /// Bundle file to write
+        output: std::path::PathBuf,
+
+        /// GitHub repository in format "owner/repo" (defaults to `repo` in config.toml, then edgeandnode/amp)
+        #[arg(long)]
+        repo: Option<String>,
+
+        /// GitHub token for private repository access (defaults to the repo's token_command, then $GITHUB_TOKEN)
+        #[arg(long)]
+        github_token: Option<String>,
+
+        /// Where to download binaries: github, oci://<registry>/<repo>, or a mirror URL template (e.g., https://mirror.example.com/amp/{version}/{asset})
+        #[arg(long, alias = "mirror", env = "AMPUP_SOURCE", value_name = "SOURCE")]
+        source: Option<String>,
+
+        /// Number of concurrent downloads
+        #[arg(short = 'j', long = "jobs", default_value_t = DEFAULT_DOWNLOAD_JOBS)]
+        jobs: usize,
+
+        /// Sign the bundle with this private key (see `ampup bundle keygen`)
+        #[arg(long, value_name = "FILE")]
+        signing_key: Option<std::path::PathBuf>,
+
+        /// Include versions even if their releases are marked as prereleases
+        #[arg(long)]
+        allow_prerelease: bool,
+    },
+
+    /// Install the versions and ampup from a bundle, without network access
+    Install {
+        /// Bundle file to install
+        file: std::path::PathBuf,
+
+        /// Require a signature by this public key (repeatable)
+        #[arg(long = "trusted-key", value_name = "KEY")]
+        trusted_keys: Vec<String>,
+
+        /// Replace versions that are already installed
+        #[arg(long)]
+        force: bool,
+
+        /// Keep the current ampup instead of the bundled one
+        #[arg(long)]
+        no_self_update: bool,
+    },
+
+    /// Generate a key pair for signing bundles
+    Keygen {
+        /// File to write the private key to; the public key goes to <FILE>.pub
+        output: std::path::PathBuf,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum ComponentCommands {
+    /// List the components the active version's release declares
+    #[command(alias = "ls")]
+    List,
+    /// Download components into the active version and link their binaries into bin/
+    Add {
+        /// Components to add, as shown by `ampup component list`
+        #[arg(required = true)]
+        names: Vec<String>,
+        /// Number of concurrent downloads
+        #[arg(short = 'j', long = "jobs", default_value_t = DEFAULT_DOWNLOAD_JOBS)]
+        jobs: usize,
+        /// Where to download components: github, oci://<registry>/<repo>, or a mirror URL template
+        #[arg(long, alias = "mirror", env = "AMPUP_SOURCE", value_name = "SOURCE")]
+        source: Option<String>,
+    },
+    /// Remove components from the active version
+    #[command(alias = "rm")]
+    Remove {
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+}
 
-        /// Custom version name (required for non-git local paths, optional otherwise)
-        #[arg(short, long)]
-        name: Option<String>,
+#[derive(Debug, clap::Subcommand)]
+enum CacheCommands {
+    /// List cached artifacts with their logical and on-disk sizes
+    #[command(alias = "list")]
+    Ls,
+    /// Re-hash cached artifacts against their digests, evicting corrupt entries
+    Verify,
+}
 
-        /// Number of CPU cores to use when building
-        #[arg(short, long)]
-        jobs: Option<usize>,
+#[derive(Debug, clap::Subcommand)]
+enum DebugCommands {
+    /// Print the HTTP requests an operation would send, as curl commands
+    Requests {
+        #[command(subcommand)]
+        operation: RequestsOperation,
     },
+}
 
-    /// Update to the latest ampd version (default behavior)
-    Update {
-        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
-        #[arg(long, env = "AMP_DIR")]
-        install_dir: Option<std::path::PathBuf>,
+#[derive(Debug, clap::Subcommand)]
+enum RequestsOperation {
+    /// Requests made by `ampup install`
+    Install {
+        /// Version to install (e.g., v0.1.0, 0.3, ^0.2, beta). If not specified, installs latest
+        version: Option<String>,
 
-        /// GitHub repository in format "owner/repo"
-        #[arg(long, default_value_t = DEFAULT_REPO.to_string())]
-        repo: String,
+        /// GitHub repository in format "owner/repo" (defaults to `repo` in config.toml, then edgeandnode/amp)
+        #[arg(long)]
+        repo: Option<String>,
 
-        /// GitHub token for private repository access (defaults to $GITHUB_TOKEN)
-        #[arg(long, env = "GITHUB_TOKEN", hide_env = true)]
+        /// GitHub token for private repository access (defaults to the repo's token_command, then $GITHUB_TOKEN)
+        #[arg(long)]
         github_token: Option<String>,
 
         /// Override architecture detection (x86_64, aarch64)
@@ -147,29 +1212,47 @@ enum Commands {
         #[arg(long)]
         platform: Option<String>,
 
-        /// Number of concurrent downloads
-        #[arg(short = 'j', long = "jobs", default_value_t = DEFAULT_DOWNLOAD_JOBS)]
-        jobs: usize,
+        /// Where to download binaries: github, oci://<registry>/<repo>, or a mirror URL template (e.g., https://mirror.example.com/amp/{version}/{asset})
+        #[arg(long, alias = "mirror", env = "AMPUP_SOURCE", value_name = "SOURCE")]
+        source: Option<String>,
     },
+}
 
-    /// Manage the ampup executable
-    #[command(name = "self")]
-    SelfCmd {
-        #[command(subcommand)]
-        command: SelfCommands,
+#[derive(Debug, clap::Subcommand)]
+enum DoctorCommands {
+    /// Print the hosts and ports ampup connects to, for firewall allow-lists
+    Egress {
+        /// GitHub repository in format "owner/repo" (defaults to `repo` in config.toml, then edgeandnode/amp)
+        #[arg(long)]
+        repo: Option<String>,
+
+        /// Where to download binaries: github, oci://<registry>/<repo>, or a mirror URL template (e.g., https://mirror.example.com/amp/{version}/{asset})
+        #[arg(long, alias = "mirror", env = "AMPUP_SOURCE", value_name = "SOURCE")]
+        source: Option<String>,
+
+        /// Print the endpoints as JSON
+        #[arg(long)]
+        json: bool,
     },
+
+    /// Check that every file in the installation belongs to its owner
+    Permissions,
 }
 
 #[derive(Debug, clap::Subcommand)]
 enum SelfCommands {
     /// Update ampup itself to the latest version
     Update {
-        /// GitHub repository in format "owner/repo"
-        #[arg(long, default_value_t = DEFAULT_SELF_REPO.to_string())]
-        repo: String,
+        /// Installation directory (defaults to $AMP_DIR or $XDG_CONFIG_HOME/.amp or $HOME/.amp)
+        #[arg(long, env = "AMP_DIR")]
+        install_dir: Option<std::path::PathBuf>,
+
+        /// GitHub repository in format "owner/repo" (defaults to `self_repo` in config.toml, then edgeandnode/ampup)
+        #[arg(long)]
+        repo: Option<String>,
 
-        /// GitHub token for private repository access (defaults to $GITHUB_TOKEN)
-        #[arg(long, env = "GITHUB_TOKEN", hide_env = true)]
+        /// GitHub token for private repository access (defaults to the repo's token_command, then $GITHUB_TOKEN)
+        #[arg(long)]
         github_token: Option<String>,
     },
 
@@ -179,15 +1262,75 @@ enum SelfCommands {
 
 #[tokio::main]
 async fn main() {
+    // Started by an ampd or ampctl shim: run the version selected for the
+    // current directory
+    let args: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    if let Some((binary, args)) = dispatch::parse(&args)
+        && let Err(e) = dispatch::exec(binary, args)
+    {
+        eprintln!("{}{}", ampup::ui::theme().error(), e);
+        std::process::exit(1);
+    }
+
     if let Err(e) = run().await {
         // Print the error with some custom formatting
-        eprintln!("{} {}", style("✗").red().bold(), e);
+        eprintln!("{}{}", ampup::ui::theme().error(), e);
         std::process::exit(1);
     }
 }
 
 async fn run() -> anyhow::Result<()> {
     let cli = <Cli as clap::Parser>::parse();
+    ampup::ui::set_legacy_output(cli.legacy_output);
+    ampup::ui::set_timings(cli.timings);
+    ampup::heartbeat::set_interval(cli.heartbeat);
+    // The theme, default command and preallocation threshold come from the
+    // settings of $AMP_DIR (or the default installation), since each command
+    // resolves its own --install-dir after output has started
+    let settings = Config::new(std::env::var_os("AMP_DIR").map(Into::into))
+        .ok()
+        .and_then(|config| Settings::load(&config).ok());
+    let theme = cli
+        .accessible
+        .then_some(Theme::Accessible)
+        .or(cli.ui)
+        .or_else(|| settings.as_ref()?.ui.theme);
+    ampup::ui::set_theme(theme.unwrap_or_default());
+    ampup::preallocate::set_threshold(settings.as_ref().map_or(
+        ampup::preallocate::DEFAULT_THRESHOLD,
+        Settings::preallocate_threshold,
+    ));
+    let options = cli.http.client_options();
+
+    let default_command = match cli.command {
+        Some(_) => None,
+        None => Some(default_command::choose(
+            cli.default_command
+                .or_else(|| settings.as_ref()?.default_command),
+        )?),
+    };
+
+    // Commands changing installed versions or the active version hold the
+    // installation lock until they finish, and refuse to run as root on
+    // another user's installation
+    let _lock = match locked_install_dir(&cli.command, default_command) {
+        Some(install_dir) => {
+            let config = Config::new(install_dir)?;
+            ownership::check_root(&config, cli.allow_root)?;
+            let lock = InstallLock::acquire(&config, cli.wait)?;
+            // Versions installed by ampup releases predating namespaces
+            // move under the lock, so concurrent runs don't race on them,
+            // and what interrupted runs left behind is cleared out
+            let version_manager = VersionManager::new(config);
+            version_manager.migrate_flat_layout();
+            temp_files::sweep(&version_manager);
+            Some(lock)
+        }
+        None => None,
+    };
+    if let Some(Commands::Init { install_dir, .. }) = &cli.command {
+        ownership::check_root(&Config::new(install_dir.clone())?, cli.allow_root)?;
+    }
 
     match cli.command {
         Some(Commands::Init {
@@ -196,8 +1339,14 @@ async fn run() -> anyhow::Result<()> {
             no_install_latest,
             github_token,
         }) => {
-            commands::init::run(install_dir, no_modify_path, no_install_latest, github_token)
-                .await?;
+            commands::init::run(
+                install_dir,
+                no_modify_path,
+                no_install_latest,
+                github_token,
+                options,
+            )
+            .await?;
         }
         Some(Commands::Install {
             install_dir,
@@ -207,6 +1356,16 @@ async fn run() -> anyhow::Result<()> {
             arch,
             platform,
             jobs,
+            source,
+            fallback_build,
+            include_drafts,
+            force,
+            allow_prerelease,
+            prerelease,
+            allow_downgrade,
+            smoke_test,
+            dest_root,
+            no_activate,
         }) => {
             commands::install::run(
                 install_dir,
@@ -216,23 +1375,423 @@ async fn run() -> anyhow::Result<()> {
                 arch,
                 platform,
                 jobs,
+                source,
+                fallback_build,
+                include_drafts,
+                force,
+                allow_prerelease,
+                prerelease,
+                allow_downgrade,
+                smoke_test,
+                false,
+                dest_root,
+                !no_activate,
+                options,
+            )
+            .await?;
+        }
+        Some(Commands::Fetch {
+            install_dir,
+            version,
+            targets,
+            output,
+            repo,
+            github_token,
+            jobs,
+            source,
+            allow_prerelease,
+        }) => {
+            commands::fetch::run(
+                install_dir,
+                repo,
+                github_token,
+                version,
+                targets,
+                output,
+                jobs,
+                source,
+                allow_prerelease,
+                options,
+            )
+            .await?;
+        }
+        Some(Commands::Resolve {
+            install_dir,
+            spec,
+            repo,
+            github_token,
+            arch,
+            platform,
+            assets,
+            json,
+        }) => {
+            commands::resolve::run(
+                install_dir,
+                repo,
+                github_token,
+                spec,
+                arch,
+                platform,
+                assets,
+                json,
+                options,
             )
             .await?;
         }
-        Some(Commands::List { install_dir }) => {
-            commands::list::run(install_dir)?;
+        Some(Commands::List {
+            install_dir,
+            json,
+            size,
+            long,
+        }) => {
+            commands::list::run(install_dir, json, size, long)?;
         }
         Some(Commands::Use {
             install_dir,
             version,
+            repo,
+            allow_downgrade,
+            project,
+            force,
         }) => {
-            commands::use_version::run(install_dir, version)?;
+            if project {
+                commands::use_version::pin_project(install_dir, version, repo)?;
+            } else {
+                commands::use_version::run(
+                    install_dir,
+                    version,
+                    repo,
+                    allow_downgrade,
+                    force,
+                    options,
+                )
+                .await?;
+            }
+        }
+        Some(Commands::Rollback { install_dir, force }) => {
+            commands::rollback::run(install_dir, force, options).await?;
         }
         Some(Commands::Uninstall {
             install_dir,
             version,
+            repo,
+            force,
+        }) => {
+            commands::uninstall::run(install_dir, &version, repo, force)?;
+        }
+        Some(Commands::Pin {
+            install_dir,
+            version,
+            repo,
+        }) => {
+            commands::pin::pin(install_dir, version, repo)?;
+        }
+        Some(Commands::Unpin {
+            install_dir,
+            version,
+            repo,
+        }) => {
+            commands::pin::unpin(install_dir, &version, repo)?;
+        }
+        Some(Commands::Prune {
+            install_dir,
+            keep_latest,
+            older_than,
+            unused_for,
+            builds_only,
+            dry_run,
+        }) => {
+            let retention = prune::Retention {
+                keep_latest,
+                older_than,
+                unused_for,
+                builds_only,
+            };
+            commands::prune::run(install_dir, retention, dry_run)?;
+        }
+        Some(Commands::Clean {
+            install_dir,
+            incomplete,
+            older_than,
+            dry_run,
+        }) => {
+            commands::clean::run(install_dir, incomplete, older_than, dry_run)?;
+        }
+        Some(Commands::Export {
+            install_dir,
+            version,
+            repo,
+            output,
+        }) => {
+            commands::export::run(install_dir, &version, repo, output)?;
+        }
+        Some(Commands::Import {
+            install_dir,
+            file,
+            activate,
+            force,
+        }) => {
+            commands::import::run(install_dir, file, activate, force, options).await?;
+        }
+        Some(Commands::Adopt {
+            install_dir,
+            path,
+            ampctl,
+            name,
+            move_files,
+            activate,
+            force,
+        }) => {
+            commands::adopt::run(
+                install_dir,
+                path,
+                ampctl,
+                name,
+                move_files,
+                activate,
+                force,
+                options,
+            )
+            .await?;
+        }
+        Some(Commands::Bundle {
+            install_dir,
+            command,
+        }) => match command {
+            BundleCommands::Create {
+                versions,
+                platform,
+                arch,
+                output,
+                repo,
+                github_token,
+                source,
+                jobs,
+                signing_key,
+                allow_prerelease,
+            } => {
+                commands::bundle::create(
+                    install_dir,
+                    versions,
+                    platform,
+                    arch,
+                    output,
+                    repo,
+                    github_token,
+                    source,
+                    jobs,
+                    signing_key,
+                    allow_prerelease,
+                    options,
+                )
+                .await?
+            }
+            BundleCommands::Install {
+                file,
+                trusted_keys,
+                force,
+                no_self_update,
+            } => {
+                commands::bundle::install(install_dir, &file, trusted_keys, force, no_self_update)?
+            }
+            BundleCommands::Keygen { output } => commands::bundle::keygen(&output)?,
+        },
+        Some(Commands::Status { install_dir }) => {
+            commands::status::run(install_dir)?;
+        }
+        Some(Commands::SupportBundle {
+            install_dir,
+            output,
+        }) => {
+            commands::support_bundle::run(install_dir, output)?;
+        }
+        Some(Commands::Audit {
+            install_dir,
+            repo,
+            github_token,
+            advisories_url,
+        }) => {
+            commands::audit::run(install_dir, repo, github_token, advisories_url, options).await?;
+        }
+        Some(Commands::Verify {
+            install_dir,
+            rebuild,
+            repo,
+            jobs,
+        }) => {
+            commands::verify::rebuild(install_dir, &rebuild, repo, jobs).await?;
+        }
+        Some(Commands::VerifyInstallScript {
+            version,
+            url,
+            file,
+            repo,
+            github_token,
+        }) => {
+            commands::verify_install_script::run(repo, github_token, version, url, file, options)
+                .await?;
+        }
+        Some(Commands::News {
+            install_dir,
+            repo,
+            github_token,
+            since,
+            refresh,
+        }) => {
+            commands::news::run(install_dir, repo, github_token, since, refresh, options).await?;
+        }
+        Some(Commands::Hooks {
+            install_dir,
+            command,
+        }) => match command {
+            HooksCommands::List => commands::hooks::list(install_dir)?,
+            HooksCommands::Add {
+                event,
+                script,
+                name,
+            } => commands::hooks::add(install_dir, event, &script, name)?,
+            HooksCommands::Remove { event, name } => {
+                commands::hooks::remove(install_dir, event, &name)?
+            }
+            HooksCommands::Run { event, name, test } => {
+                commands::hooks::run(install_dir, event, name, test)?
+            }
+        },
+        Some(Commands::Component {
+            install_dir,
+            repo,
+            github_token,
+            command,
+        }) => match command {
+            ComponentCommands::List => {
+                commands::component::list(install_dir, repo, github_token, options).await?
+            }
+            ComponentCommands::Add {
+                names,
+                jobs,
+                source,
+            } => {
+                commands::component::add(
+                    install_dir,
+                    repo,
+                    github_token,
+                    names,
+                    jobs,
+                    source,
+                    options,
+                )
+                .await?
+            }
+            ComponentCommands::Remove { names } => commands::component::remove(install_dir, names)?,
+        },
+        Some(Commands::Cache {
+            install_dir,
+            command,
+        }) => match command {
+            CacheCommands::Ls => commands::cache::list(install_dir)?,
+            CacheCommands::Verify => commands::cache::verify(install_dir)?,
+        },
+        Some(Commands::Debug {
+            install_dir,
+            command,
+        }) => match command {
+            DebugCommands::Requests {
+                operation:
+                    RequestsOperation::Install {
+                        version,
+                        repo,
+                        github_token,
+                        arch,
+                        platform,
+                        source,
+                    },
+            } => commands::debug::requests_install(
+                install_dir,
+                repo,
+                github_token,
+                version,
+                arch,
+                platform,
+                source,
+                options,
+            )?,
+        },
+        Some(Commands::Doctor {
+            install_dir,
+            command,
+        }) => match command {
+            DoctorCommands::Egress { repo, source, json } => {
+                commands::doctor::egress(install_dir, repo, source, json, options)?
+            }
+            DoctorCommands::Permissions => commands::doctor::permissions(install_dir)?,
+        },
+        Some(Commands::FixPermissions { install_dir }) => {
+            commands::fix_permissions::run(install_dir)?;
+        }
+        Some(Commands::Config {
+            install_dir,
+            command,
+        }) => match command {
+            ConfigCommands::Get { key } => commands::config::get(install_dir, &key)?,
+            ConfigCommands::Set { key, value } => commands::config::set(install_dir, &key, &value)?,
+            ConfigCommands::Unset { key } => commands::config::unset(install_dir, &key)?,
+            ConfigCommands::List => commands::config::list(install_dir)?,
+        },
+        Some(Commands::Generate {
+            install_dir,
+            command,
+        }) => match command {
+            GenerateCommands::Systemd {
+                instance,
+                restart,
+                platform,
+                install,
+                args,
+            } => commands::generate::systemd(
+                install_dir,
+                instance,
+                restart,
+                platform,
+                install,
+                args,
+            )?,
+        },
+        Some(Commands::Instances {
+            install_dir,
+            command,
+        }) => match command {
+            InstanceCommands::Pin {
+                name,
+                version,
+                args,
+            } => commands::instances::pin(install_dir, &name, &version, args)?,
+            InstanceCommands::List => commands::instances::list(install_dir)?,
+            InstanceCommands::Remove { name } => commands::instances::remove(install_dir, &name)?,
+        },
+        Some(Commands::Which {
+            install_dir,
+            binary,
+        }) => {
+            commands::which::run(install_dir, &binary)?;
+        }
+        Some(Commands::Override {
+            install_dir,
+            command,
+        }) => match command {
+            OverrideCommands::Set { version, path } => {
+                commands::overrides::set(install_dir, &version, path)?
+            }
+            OverrideCommands::Unset { path, nonexistent } => {
+                commands::overrides::unset(install_dir, path, nonexistent)?
+            }
+            OverrideCommands::List => commands::overrides::list(install_dir)?,
+        },
+        Some(Commands::Shell {
+            install_dir,
+            version,
+            env,
         }) => {
-            commands::uninstall::run(install_dir, &version)?;
+            commands::shell::run(install_dir, &version, env)?;
         }
         Some(Commands::Build {
             install_dir,
@@ -240,42 +1799,160 @@ async fn run() -> anyhow::Result<()> {
             repo,
             branch,
             commit,
+            tag,
             pr,
             name,
             jobs,
+            reproducible,
+            force,
+            fresh,
+            summary,
+            profile,
+            debug,
+            features,
+            no_default_features,
+            cargo_args,
         }) => {
-            commands::build::run(install_dir, repo, path, branch, commit, pr, name, jobs).await?;
+            commands::build::run(
+                install_dir,
+                repo,
+                path,
+                branch,
+                commit,
+                tag,
+                pr,
+                name,
+                jobs,
+                reproducible,
+                force,
+                fresh,
+                summary,
+                CargoOptions {
+                    profile: if debug {
+                        Some("dev".to_string())
+                    } else {
+                        profile
+                    },
+                    features,
+                    no_default_features,
+                    extra_args: cargo_args,
+                },
+                options.api_url,
+            )
+            .await?;
         }
         Some(Commands::Update {
             install_dir,
+            version,
             repo,
             github_token,
             arch,
             platform,
             jobs,
+            source,
+            fallback_build,
+            prerelease,
+            channel,
         }) => {
-            // Install latest version (same as default behavior)
-            commands::install::run(install_dir, repo, github_token, None, arch, platform, jobs)
-                .await?;
+            // Switching channels saves the choice, so later updates follow it
+            if let Some(channel) = channel {
+                ampup::channels::track(&Config::new(install_dir.clone())?, channel)?;
+            }
+
+            // Install the newest matching version (latest, or the tracked
+            // channel, by default), from the active version's repository
+            let repo = commands::install::update_repo(install_dir.clone(), repo)?;
+            commands::install::run(
+                install_dir,
+                repo,
+                github_token,
+                version,
+                arch,
+                platform,
+                jobs,
+                source,
+                fallback_build,
+                false,
+                false,
+                false,
+                prerelease,
+                false,
+                false,
+                false,
+                None,
+                true,
+                options,
+            )
+            .await?;
+        }
+        Some(Commands::Upgrade {
+            install_dir,
+            version,
+            repo,
+            github_token,
+            jobs,
+            guided,
+            allow_prerelease,
+            prerelease,
+            allow_downgrade,
+        }) => {
+            commands::upgrade::run(
+                install_dir,
+                repo,
+                github_token,
+                version,
+                jobs,
+                guided,
+                allow_prerelease,
+                prerelease,
+                allow_downgrade,
+                options,
+            )
+            .await?;
         }
         Some(Commands::SelfCmd { command }) => match command {
-            SelfCommands::Update { repo, github_token } => {
-                commands::update::run(repo, github_token).await?;
+            SelfCommands::Update {
+                install_dir,
+                repo,
+                github_token,
+            } => {
+                commands::update::run(install_dir, repo, github_token, options).await?;
             }
             SelfCommands::Version => {
                 println!("ampup {}", env!("VERGEN_GIT_DESCRIBE"));
             }
         },
+        None if default_command == Some(DefaultCommand::Help) => {
+            <Cli as clap::CommandFactory>::command().print_help()?;
+        }
+        None if default_command == Some(DefaultCommand::Status) => {
+            commands::status::run(std::env::var("AMP_DIR").ok().map(std::path::PathBuf::from))?;
+        }
         None => {
-            // Default: install latest version (same as 'ampup update')
+            // Default: install latest version (same as 'ampup update'), reusing
+            // a recent latest-version lookup unless --refresh
+            let install_dir = std::env::var("AMP_DIR").ok().map(std::path::PathBuf::from);
+            let repo = commands::install::update_repo(install_dir.clone(), None)?;
             commands::install::run(
-                std::env::var("AMP_DIR").ok().map(std::path::PathBuf::from),
-                DEFAULT_REPO.to_string(),
-                std::env::var("GITHUB_TOKEN").ok(),
+                install_dir,
+                repo,
+                None,
                 None,
                 None,
                 None,
                 DEFAULT_DOWNLOAD_JOBS,
+                None,
+                false,
+                false,
+                false,
+                false,
+                false,
+                false,
+                false,
+                !cli.refresh,
+                None,
+                true,
+                options,
             )
             .await?;
         }
@@ -283,3 +1960,46 @@ async fn run() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Installation directory of a command that changes installed versions or
+/// the active version, or `None` for commands that don't.
+fn locked_install_dir(
+    command: &Option<Commands>,
+    default_command: Option<DefaultCommand>,
+) -> Option<Option<std::path::PathBuf>> {
+    match command {
+        // A cross-install only changes its destination tree
+        Some(Commands::Install {
+            dest_root: Some(dest_root),
+            ..
+        }) => Some(Some(dest_root.clone())),
+        Some(
+            Commands::Install { install_dir, .. }
+            | Commands::Update { install_dir, .. }
+            | Commands::Upgrade { install_dir, .. }
+            | Commands::Use { install_dir, .. }
+            | Commands::Rollback { install_dir, .. }
+            | Commands::Uninstall { install_dir, .. }
+            | Commands::Prune { install_dir, .. }
+            | Commands::Clean { install_dir, .. }
+            | Commands::Import { install_dir, .. }
+            | Commands::Adopt { install_dir, .. }
+            | Commands::Pin { install_dir, .. }
+            | Commands::Unpin { install_dir, .. }
+            | Commands::Build { install_dir, .. }
+            | Commands::Bundle {
+                install_dir,
+                command: BundleCommands::Install { .. },
+            }
+            | Commands::Component {
+                install_dir,
+                command: ComponentCommands::Add { .. } | ComponentCommands::Remove { .. },
+                ..
+            },
+        ) => Some(install_dir.clone()),
+        None if default_command == Some(DefaultCommand::Update) => {
+            Some(std::env::var_os("AMP_DIR").map(Into::into))
+        }
+        None | Some(_) => None,
+    }
+}
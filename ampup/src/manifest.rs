@@ -0,0 +1,129 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::InstalledComponent,
+    data_dir::DataBoundary,
+    health::SmokeTestReport,
+    reproducible::{BuildRecipe, sha256_file},
+    resolver::Channel,
+};
+
+/// File in each version directory holding its [`VersionManifest`].
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Binaries whose checksums are recorded on install and checked on every
+/// activation.
+pub const CHECKSUMMED_BINARIES: [&str; 2] = ["ampd", "ampctl"];
+
+/// What ampup knows about an installed version beyond its binaries, kept in
+/// `<version>/manifest.json`.
+///
+/// Every field is optional, so versions installed before a field existed
+/// (or without a manifest at all) load with defaults.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VersionManifest {
+    /// Results of the most recent smoke test run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smoke_test: Option<SmokeTestReport>,
+    /// Installed from a release marked as a prerelease
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub prerelease: bool,
+    /// Channels this is the version last installed from
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub channels: Vec<Channel>,
+    /// Data directories of earlier versions this release can't use as-is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_boundary: Option<DataBoundary>,
+    /// Optional components added with `ampup component add`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<InstalledComponent>,
+    /// How to rebuild the binaries, for versions built with `--reproducible`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build: Option<BuildRecipe>,
+    /// Fingerprint of the local checkout the binaries were built from, so
+    /// building it again unchanged can be skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_fingerprint: Option<String>,
+    /// Hash of the uncommitted changes a local checkout was built with, for
+    /// builds labeled `-dirty`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dirty_diff: Option<String>,
+    /// `owner/repo` the release was installed from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    /// Hex SHA-256 of each of [`CHECKSUMMED_BINARIES`] when installed
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub checksums: BTreeMap<String, String>,
+    /// Where the binaries were copied from by `ampup adopt`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adopted_from: Option<PathBuf>,
+    /// Held with `ampup pin`: prune skips it and uninstall needs `--force`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// Unix timestamp of the version's latest activation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_activated: Option<u64>,
+    /// Unix timestamp of the latest run through the shims, recorded with
+    /// `shims.track_usage`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<u64>,
+}
+
+impl VersionManifest {
+    /// Load the manifest in `version_dir`, or defaults when it has none.
+    pub fn load(version_dir: &Path) -> Result<Self> {
+        let path = version_dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(&path).context("Failed to read version manifest")?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse version manifest {}", path.display()))
+    }
+
+    /// Write the manifest to `version_dir`, replacing the old one in one
+    /// step so shims recording usage at the same time never read half of it.
+    pub fn save(&self, version_dir: &Path) -> Result<()> {
+        let contents =
+            serde_json::to_string_pretty(self).context("Failed to serialize version manifest")?;
+        let staged = version_dir.join(format!(".{}.{}", MANIFEST_FILE, std::process::id()));
+        fs::write(&staged, contents).context("Failed to write version manifest")?;
+        fs::rename(&staged, version_dir.join(MANIFEST_FILE))
+            .context("Failed to write version manifest")
+    }
+
+    /// Load the manifest in `version_dir`, apply `change` and save it.
+    pub fn update(version_dir: &Path, change: impl FnOnce(&mut Self)) -> Result<()> {
+        let mut manifest = Self::load(version_dir)?;
+        change(&mut manifest);
+        manifest.save(version_dir)
+    }
+
+    /// When the version was last activated or run through the shims,
+    /// whichever is later.
+    pub fn last_used_at(&self) -> Option<u64> {
+        self.last_activated.max(self.last_used)
+    }
+}
+
+/// Hex SHA-256 of each of [`CHECKSUMMED_BINARIES`] present in
+/// `version_dir`, for [`VersionManifest::checksums`].
+pub fn binary_checksums(version_dir: &Path) -> Result<BTreeMap<String, String>> {
+    CHECKSUMMED_BINARIES
+        .iter()
+        .filter(|binary| version_dir.join(binary).exists())
+        .map(|binary| {
+            let checksum = sha256_file(&version_dir.join(binary))
+                .with_context(|| format!("Failed to checksum {}", binary))?;
+            Ok((binary.to_string(), checksum))
+        })
+        .collect()
+}
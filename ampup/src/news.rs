@@ -0,0 +1,344 @@
+use std::{
+    cmp::Reverse,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use console::style;
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    github::{Announcement, GitHubClient, MergedPullRequest, ReleaseNotes},
+    resolver::tag_version,
+    ui,
+    version_store::repo_namespace,
+};
+
+/// How long a fetched digest is reused before `ampup news` asks GitHub again.
+pub const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Pull requests listed in a digest; the rest are only counted.
+const MAX_PULL_REQUESTS: usize = 5;
+
+/// Label keywords that make a pull request worth an operator's attention,
+/// most important first. Matched against lowercased label names, so
+/// `breaking-change` and `type: feature` both count.
+const NOTABLE_LABELS: &[&str] = &[
+    "breaking",
+    "security",
+    "feature",
+    "enhancement",
+    "performance",
+];
+
+/// Longest release note highlight shown before it's cut off.
+const MAX_HIGHLIGHT_CHARS: usize = 80;
+
+/// Upstream activity since a release, as shown by `ampup news`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Digest {
+    pub repo: String,
+    /// Release the digest starts after
+    pub since: String,
+    /// When `since` was published (RFC 3339)
+    pub since_date: String,
+    /// When the digest was fetched, in seconds since the Unix epoch
+    pub fetched_at: u64,
+    /// Newer releases, newest first
+    pub releases: Vec<ReleaseNotes>,
+    /// Most notable pull requests merged since `since_date`
+    pub pull_requests: Vec<MergedPullRequest>,
+    /// How many pull requests were merged since `since_date`
+    pub merged_total: u64,
+    /// Recent announcements, or `None` when they need a token to fetch
+    pub announcements: Option<Vec<Announcement>>,
+}
+
+impl Digest {
+    /// Fetch releases, merged pull requests and announcements published
+    /// after the release tagged `since`.
+    pub async fn fetch(github: &GitHubClient, since: &str) -> Result<Self> {
+        let releases = github.list_release_notes().await?;
+        let since_release = match releases.iter().find(|r| r.tag == since) {
+            Some(release) => release.clone(),
+            None => github.get_release_notes(since).await?,
+        };
+        let since_date = since_release
+            .published_at
+            .with_context(|| format!("Release {} has no publish date", since))?;
+
+        let since_version = tag_version(since);
+        let releases = releases
+            .into_iter()
+            .filter(|r| !r.prerelease)
+            .filter(|r| match (tag_version(&r.tag), &since_version) {
+                (Some(version), Some(since_version)) => version > *since_version,
+                _ => r.published_at.as_deref() > Some(since_date.as_str()),
+            })
+            .collect();
+
+        let merged_since = since_date.get(..10).unwrap_or(&since_date);
+        let (merged_total, pull_requests) = github.merged_pull_requests(merged_since).await?;
+
+        let announcements = match github.announcements(&since_date).await {
+            Ok(announcements) => announcements,
+            Err(err) => {
+                ui::warn!("Skipping announcements: {}", err);
+                Some(Vec::new())
+            }
+        };
+
+        Ok(Self {
+            repo: github.repo().to_string(),
+            since: since.to_string(),
+            since_date,
+            fetched_at: now(),
+            releases,
+            pull_requests: notable(pull_requests),
+            merged_total,
+            announcements,
+        })
+    }
+
+    /// Where the digest for `repo` is cached.
+    pub fn cache_path(cache_dir: &Path, repo: &str) -> PathBuf {
+        cache_dir.join(format!("news-{}.json", repo_namespace(repo)))
+    }
+
+    /// The digest cached at `path`, when it covers `repo` since `since` and
+    /// was fetched less than `ttl` ago.
+    pub fn load_cached(path: &Path, repo: &str, since: &str, ttl: Duration) -> Option<Self> {
+        let contents = fs::read_to_string(path).ok()?;
+        let digest: Self = serde_json::from_str(&contents).ok()?;
+        let fresh = now().saturating_sub(digest.fetched_at) < ttl.as_secs();
+        (fresh && digest.repo == repo && digest.since == since).then_some(digest)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create cache directory")?;
+        }
+        let contents = serde_json::to_string_pretty(self).context("Failed to serialize digest")?;
+        fs::write(path, contents).context("Failed to write news cache")
+    }
+
+    /// Render the digest for the terminal.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{} since {} {}",
+            style(&self.repo).bold(),
+            ui::version(&self.since),
+            style(format!("({})", date(&self.since_date))).dim()
+        );
+
+        let _ = writeln!(out);
+        if self.releases.is_empty() {
+            let _ = writeln!(out, "{}", style("No newer releases").bold());
+        } else {
+            let _ = writeln!(
+                out,
+                "{}",
+                style(format!("Releases: {} newer", self.releases.len())).bold()
+            );
+        }
+        for release in &self.releases {
+            let breaking = release
+                .body
+                .as_deref()
+                .is_some_and(|body| body.to_lowercase().contains("breaking"));
+            let _ = writeln!(
+                out,
+                "  {} {}{}",
+                ui::version(format!("{:<10}", release.tag)),
+                style(date(release.published_at.as_deref().unwrap_or_default())).dim(),
+                if breaking {
+                    style("  breaking changes").yellow().to_string()
+                } else {
+                    String::new()
+                }
+            );
+            if let Some(line) = release.body.as_deref().and_then(highlight) {
+                let _ = writeln!(out, "    {}", line);
+            }
+        }
+
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "{}",
+            style(format!("Merged pull requests: {}", self.merged_total)).bold()
+        );
+        for pr in &self.pull_requests {
+            let labels: Vec<&str> = pr.labels.iter().map(|l| l.name.as_str()).collect();
+            let _ = write!(out, "  #{:<6} {}", pr.number, pr.title);
+            if !labels.is_empty() {
+                let _ = write!(out, " {}", style(format!("[{}]", labels.join(", "))).dim());
+            }
+            let _ = writeln!(out);
+        }
+        let more = self
+            .merged_total
+            .saturating_sub(self.pull_requests.len() as u64);
+        if more > 0 {
+            let _ = writeln!(out, "  {}", style(format!("and {} more", more)).dim());
+        }
+
+        if let Some(announcements) = &self.announcements
+            && !announcements.is_empty()
+        {
+            let _ = writeln!(out);
+            let _ = writeln!(out, "{}", style("Announcements").bold());
+            for announcement in announcements {
+                let _ = writeln!(
+                    out,
+                    "  {} {}",
+                    style(date(&announcement.created_at)).dim(),
+                    announcement.title
+                );
+                let _ = writeln!(out, "    {}", ui::path(&announcement.url));
+            }
+        }
+        out
+    }
+}
+
+/// The [`MAX_PULL_REQUESTS`] most notable pull requests: those with notable labels first,
+/// then the most discussed.
+fn notable(mut pull_requests: Vec<MergedPullRequest>) -> Vec<MergedPullRequest> {
+    pull_requests.sort_by_key(|pr| {
+        let rank = pr
+            .labels
+            .iter()
+            .filter_map(|label| {
+                let name = label.name.to_lowercase();
+                NOTABLE_LABELS.iter().position(|k| name.contains(k))
+            })
+            .min()
+            .unwrap_or(NOTABLE_LABELS.len());
+        (rank, Reverse(pr.comments))
+    });
+    pull_requests.truncate(MAX_PULL_REQUESTS);
+    pull_requests
+}
+
+/// First line of prose in release notes, skipping headings and the
+/// changelog link GitHub appends, with list markers removed.
+fn highlight(body: &str) -> Option<String> {
+    let line = body
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .find(|line| !line.starts_with("**Full Changelog**"))?;
+    let line = line.trim_start_matches(['-', '*', ' ']);
+
+    if line.chars().count() > MAX_HIGHLIGHT_CHARS {
+        let cut: String = line.chars().take(MAX_HIGHLIGHT_CHARS - 1).collect();
+        return Some(format!("{}…", cut.trim_end()));
+    }
+    Some(line.to_string())
+}
+
+/// The `YYYY-MM-DD` part of an RFC 3339 timestamp.
+fn date(timestamp: &str) -> &str {
+    timestamp.get(..10).unwrap_or(timestamp)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::Label;
+
+    fn pull_request(number: u64, labels: &[&str], comments: u64) -> MergedPullRequest {
+        MergedPullRequest {
+            number,
+            title: format!("PR {}", number),
+            html_url: format!("https://github.com/edgeandnode/amp/pull/{}", number),
+            labels: labels
+                .iter()
+                .map(|name| Label {
+                    name: name.to_string(),
+                })
+                .collect(),
+            comments,
+        }
+    }
+
+    #[test]
+    fn notable_with_labeled_and_discussed_prs_ranks_labels_first() {
+        //* Given
+        let pull_requests = vec![
+            pull_request(1, &[], 40),
+            pull_request(2, &["type: feature"], 1),
+            pull_request(3, &["breaking-change"], 0),
+            pull_request(4, &["docs"], 3),
+            pull_request(5, &[], 2),
+            pull_request(6, &[], 1),
+            pull_request(7, &[], 0),
+        ];
+
+        //* When
+        let notable = notable(pull_requests);
+
+        //* Then
+        let numbers: Vec<u64> = notable.iter().map(|pr| pr.number).collect();
+        assert_eq!(numbers, vec![3, 2, 1, 4, 5]);
+    }
+
+    #[test]
+    fn highlight_with_headings_and_bullets_returns_first_prose_line() {
+        //* Given
+        let body = "## What's Changed\n\n- Streaming queries are now resumable by @dev in #12\n\n**Full Changelog**: v0.3.0...v0.4.0";
+
+        //* When
+        let line = highlight(body);
+
+        //* Then
+        assert_eq!(
+            line.as_deref(),
+            Some("Streaming queries are now resumable by @dev in #12")
+        );
+        assert_eq!(highlight("**Full Changelog**: v0.3.0...v0.4.0"), None);
+    }
+
+    #[test]
+    fn load_cached_with_different_since_or_expired_digest_returns_none() -> Result<()> {
+        //* Given
+        let dir = tempfile::tempdir()?;
+        let path = Digest::cache_path(dir.path(), "edgeandnode/amp");
+        let digest = Digest {
+            repo: "edgeandnode/amp".to_string(),
+            since: "v0.3.0".to_string(),
+            since_date: "2026-08-01T12:00:00Z".to_string(),
+            fetched_at: now() - 120,
+            releases: Vec::new(),
+            pull_requests: Vec::new(),
+            merged_total: 0,
+            announcements: None,
+        };
+        digest.save(&path)?;
+
+        //* When
+        let hit = Digest::load_cached(&path, "edgeandnode/amp", "v0.3.0", CACHE_TTL);
+        let other_since = Digest::load_cached(&path, "edgeandnode/amp", "v0.2.0", CACHE_TTL);
+        let expired =
+            Digest::load_cached(&path, "edgeandnode/amp", "v0.3.0", Duration::from_secs(60));
+
+        //* Then
+        assert!(hit.is_some());
+        assert!(other_since.is_none());
+        assert!(expired.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,515 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+
+use crate::{
+    github::HttpTimeouts,
+    heartbeat,
+    release_source::{ReleaseSource, ResolvedAsset, head_available},
+    request_plan::{PlannedAuth, PlannedRequest},
+};
+
+/// Annotation `oras push` sets to each layer's file name.
+const TITLE_ANNOTATION: &str = "org.opencontainers.image.title";
+
+const MANIFEST_MEDIA_TYPES: &str = "application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json";
+
+#[derive(Debug)]
+pub enum OciError {
+    InvalidReference {
+        reference: String,
+    },
+    AuthFailed {
+        registry: String,
+        status_code: u16,
+    },
+    ManifestNotFound {
+        reference: String,
+        tag: String,
+    },
+    LayerNotFound {
+        reference: String,
+        tag: String,
+        asset_name: String,
+        available: Vec<String>,
+    },
+    RequestFailed {
+        url: String,
+        status_code: u16,
+    },
+}
+
+impl std::fmt::Display for OciError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidReference { reference } => {
+                writeln!(f, "Invalid OCI reference")?;
+                writeln!(f, "  Reference: {}", reference)?;
+                writeln!(f)?;
+                writeln!(f, "  Expected: oci://<registry>/<repository>")?;
+                writeln!(f, "  Example: oci://ghcr.io/edgeandnode/amp")?;
+            }
+            Self::AuthFailed {
+                registry,
+                status_code,
+            } => {
+                writeln!(f, "OCI registry authentication failed")?;
+                writeln!(f, "  Registry: {}", registry)?;
+                writeln!(f, "  Status: HTTP {}", status_code)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Private packages need a token with read:packages scope."
+                )?;
+                writeln!(f, "  Try: export GITHUB_TOKEN=$(gh auth token)")?;
+            }
+            Self::ManifestNotFound { reference, tag } => {
+                writeln!(f, "OCI artifact not found")?;
+                writeln!(f, "  Reference: {}:{}", reference, tag)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Check that this version was published to the registry."
+                )?;
+            }
+            Self::LayerNotFound {
+                reference,
+                tag,
+                asset_name,
+                available,
+            } => {
+                writeln!(f, "OCI artifact has no layer for asset")?;
+                writeln!(f, "  Reference: {}:{}", reference, tag)?;
+                writeln!(f, "  Asset: {}", asset_name)?;
+                writeln!(f)?;
+                if available.is_empty() {
+                    writeln!(f, "  No titled layers in this artifact.")?;
+                } else {
+                    writeln!(f, "  Available layers:")?;
+                    for name in available {
+                        writeln!(f, "    - {}", name)?;
+                    }
+                }
+            }
+            Self::RequestFailed { url, status_code } => {
+                writeln!(f, "OCI registry request failed")?;
+                writeln!(f, "  URL: {}", url)?;
+                writeln!(f, "  Status: HTTP {}", status_code)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for OciError {}
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    layers: Vec<Layer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Layer {
+    digest: String,
+    #[serde(default)]
+    annotations: std::collections::HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    #[serde(alias = "access_token")]
+    token: String,
+}
+
+/// Release source that pulls binaries published as OCI artifacts.
+///
+/// Each version is a tag (e.g. `ghcr.io/edgeandnode/amp:v0.3.0`) whose
+/// manifest has one layer per asset, named by the
+/// `org.opencontainers.image.title` annotation as produced by
+/// `oras push ... ampd-linux-x86_64 ampctl-linux-x86_64`. Layer digests are
+/// carried on the resolved assets so downloads are verified.
+pub struct OciSource {
+    client: reqwest::Client,
+    reference: String,
+    registry: String,
+    repository: String,
+    credentials: Option<String>,
+    /// Bearer token obtained from the registry's token service, fetched once
+    bearer: OnceCell<Option<String>>,
+}
+
+impl OciSource {
+    /// Create a source for `oci://<registry>/<repository>`.
+    ///
+    /// `credentials` is used as the password for the registry token service
+    /// (a GitHub token works for ghcr.io); anonymous pulls are used otherwise.
+    pub fn new(
+        reference: &str,
+        credentials: Option<String>,
+        timeouts: HttpTimeouts,
+    ) -> Result<Self> {
+        let (registry, repository) = parse_reference(reference)?;
+
+        let client = timeouts
+            .apply(reqwest::Client::builder().user_agent("ampup"))
+            .build()
+            .context("Failed to create request client")?;
+
+        Ok(Self {
+            client,
+            reference: reference.to_string(),
+            registry,
+            repository,
+            credentials,
+            bearer: OnceCell::new(),
+        })
+    }
+
+    fn registry_url(&self, path: &str) -> String {
+        format!("https://{}/v2/{}/{}", self.registry, self.repository, path)
+    }
+
+    /// Send an authenticated GET, negotiating a bearer token on first 401.
+    async fn get(&self, url: &str, accept: Option<&str>) -> Result<reqwest::Response> {
+        let request = |bearer: Option<&str>| {
+            let mut request = self.client.get(url);
+            if let Some(accept) = accept {
+                request = request.header(reqwest::header::ACCEPT, accept);
+            }
+            if let Some(bearer) = bearer {
+                request = request.bearer_auth(bearer);
+            }
+            request
+        };
+
+        if let Some(bearer) = self.bearer.get() {
+            return request(bearer.as_deref())
+                .send()
+                .await
+                .context("Failed to query OCI registry");
+        }
+
+        let response = request(None)
+            .send()
+            .await
+            .context("Failed to query OCI registry")?;
+        if response.status() != reqwest::StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        let challenge = response
+            .headers()
+            .get(reqwest::header::WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let bearer = self
+            .bearer
+            .get_or_try_init(|| self.fetch_bearer(challenge))
+            .await?;
+        request(bearer.as_deref())
+            .send()
+            .await
+            .context("Failed to query OCI registry")
+    }
+
+    /// Exchange credentials for a registry bearer token, per the
+    /// `WWW-Authenticate: Bearer realm=...` challenge.
+    async fn fetch_bearer(&self, challenge: Option<String>) -> Result<Option<String>> {
+        let Some(params) = challenge.as_deref().and_then(parse_bearer_challenge) else {
+            return Err(OciError::AuthFailed {
+                registry: self.registry.clone(),
+                status_code: 401,
+            }
+            .into());
+        };
+
+        let mut request = self.client.get(&params.realm);
+        let mut query = Vec::new();
+        if let Some(service) = &params.service {
+            query.push(("service", service.clone()));
+        }
+        query.push((
+            "scope",
+            params
+                .scope
+                .clone()
+                .unwrap_or_else(|| format!("repository:{}:pull", self.repository)),
+        ));
+        request = request.query(&query);
+        if let Some(password) = &self.credentials {
+            request = request.basic_auth("ampup", Some(password));
+        }
+
+        let response = request
+            .send()
+            .await
+            .context("Failed to request OCI registry token")?;
+        if !response.status().is_success() {
+            return Err(OciError::AuthFailed {
+                registry: self.registry.clone(),
+                status_code: response.status().as_u16(),
+            }
+            .into());
+        }
+
+        let token: TokenResponse = response
+            .json()
+            .await
+            .context("Failed to parse OCI registry token")?;
+        Ok(Some(token.token))
+    }
+
+    async fn get_manifest(&self, tag: &str) -> Result<Manifest> {
+        let url = self.registry_url(&format!("manifests/{}", tag));
+        let response = self.get(&url, Some(MANIFEST_MEDIA_TYPES)).await?;
+
+        match response.status() {
+            status if status.is_success() => response
+                .json()
+                .await
+                .context("Failed to parse OCI manifest"),
+            reqwest::StatusCode::NOT_FOUND => Err(OciError::ManifestNotFound {
+                reference: self.reference.clone(),
+                tag: tag.to_string(),
+            }
+            .into()),
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
+                Err(OciError::AuthFailed {
+                    registry: self.registry.clone(),
+                    status_code: response.status().as_u16(),
+                }
+                .into())
+            }
+            status => Err(OciError::RequestFailed {
+                url,
+                status_code: status.as_u16(),
+            }
+            .into()),
+        }
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for OciSource {
+    fn describe(&self) -> String {
+        self.reference.clone()
+    }
+
+    async fn resolve_assets(
+        &self,
+        version: &str,
+        asset_names: &[&str],
+    ) -> Result<Vec<ResolvedAsset>> {
+        let manifest = self.get_manifest(version).await?;
+
+        asset_names
+            .iter()
+            .map(|&name| {
+                let layer = find_layer(&manifest, name).ok_or_else(|| OciError::LayerNotFound {
+                    reference: self.reference.clone(),
+                    tag: version.to_string(),
+                    asset_name: name.to_string(),
+                    available: manifest
+                        .layers
+                        .iter()
+                        .filter_map(|l| l.annotations.get(TITLE_ANNOTATION).cloned())
+                        .collect(),
+                })?;
+                Ok(ResolvedAsset {
+                    id: 0,
+                    name: name.to_string(),
+                    url: self.registry_url(&format!("blobs/{}", layer.digest)),
+                    digest: Some(layer.digest.clone()),
+                })
+            })
+            .collect()
+    }
+
+    /// Blobs are fetched in a single request; `chunks` is ignored.
+    async fn download_asset(&self, asset: &ResolvedAsset, _chunks: usize) -> Result<Vec<u8>> {
+        let response = self.get(&asset.url, None).await?;
+        if !response.status().is_success() {
+            return Err(OciError::RequestFailed {
+                url: asset.url.clone(),
+                status_code: response.status().as_u16(),
+            }
+            .into());
+        }
+
+        let mut buffer = Vec::new();
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.context("Error while downloading file")?;
+            heartbeat::record_bytes(chunk.len());
+            buffer.extend_from_slice(&chunk);
+        }
+
+        Ok(buffer)
+    }
+
+    /// Registries that require a token answer the first request with a
+    /// challenge naming where to get one, so that exchange is listed
+    /// without a known URL.
+    fn plan_requests(&self, version: &str, asset_names: &[&str]) -> Vec<PlannedRequest> {
+        let mut requests = vec![
+            PlannedRequest::new(
+                "GET",
+                self.registry_url(&format!("manifests/{}", version)),
+                format!("Fetch the manifest of {}", version),
+            )
+            .with_header("Accept", MANIFEST_MEDIA_TYPES),
+            PlannedRequest::new(
+                "GET",
+                "<realm from the registry's WWW-Authenticate challenge>",
+                "Exchange credentials for a registry token, if the registry asks for one",
+            )
+            .with_auth(
+                self.credentials
+                    .is_some()
+                    .then_some(PlannedAuth::Basic { user: "ampup" }),
+            ),
+        ];
+        for name in asset_names {
+            requests.push(PlannedRequest::new(
+                "GET",
+                self.registry_url(&format!("blobs/<{} layer digest>", name)),
+                format!("Download {}", name),
+            ));
+        }
+        requests
+    }
+
+    async fn asset_available(&self, asset: &ResolvedAsset) -> bool {
+        // Blobs require the bearer token, so only probe once one is known;
+        // the manifest lookup has already proven the layer is referenced.
+        match self.bearer.get() {
+            Some(_) => true,
+            None => head_available(&self.client, &asset.url).await,
+        }
+    }
+}
+
+/// Split `oci://<registry>/<repository>` into its parts.
+fn parse_reference(reference: &str) -> Result<(String, String), OciError> {
+    let invalid = || OciError::InvalidReference {
+        reference: reference.to_string(),
+    };
+    let rest = reference.strip_prefix("oci://").ok_or_else(invalid)?;
+    let (registry, repository) = rest
+        .trim_end_matches('/')
+        .split_once('/')
+        .ok_or_else(invalid)?;
+    if registry.is_empty() || repository.is_empty() {
+        return Err(invalid());
+    }
+    Ok((registry.to_string(), repository.to_string()))
+}
+
+fn find_layer<'a>(manifest: &'a Manifest, asset_name: &str) -> Option<&'a Layer> {
+    manifest.layers.iter().find(|layer| {
+        layer.annotations.get(TITLE_ANNOTATION).map(String::as_str) == Some(asset_name)
+    })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct BearerChallenge {
+    realm: String,
+    service: Option<String>,
+    scope: Option<String>,
+}
+
+/// Parse `Bearer realm="...",service="...",scope="..."`.
+fn parse_bearer_challenge(header: &str) -> Option<BearerChallenge> {
+    let params = header.strip_prefix("Bearer ")?;
+    let mut realm = None;
+    let mut service = None;
+    let mut scope = None;
+
+    let mut rest = params.trim();
+    while !rest.is_empty() {
+        let (key, after_key) = rest.split_once('=')?;
+        let after_quote = after_key.strip_prefix('"')?;
+        let (value, after_value) = after_quote.split_once('"')?;
+        match key.trim() {
+            "realm" => realm = Some(value.to_string()),
+            "service" => service = Some(value.to_string()),
+            "scope" => scope = Some(value.to_string()),
+            _ => {}
+        }
+        rest = after_value.trim_start_matches(',').trim();
+    }
+
+    Some(BearerChallenge {
+        realm: realm?,
+        service,
+        scope,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reference_with_nested_repository_splits_registry() {
+        //* When
+        let (registry, repository) =
+            parse_reference("oci://ghcr.io/edgeandnode/amp").expect("should parse reference");
+
+        //* Then
+        assert_eq!(registry, "ghcr.io");
+        assert_eq!(repository, "edgeandnode/amp");
+    }
+
+    #[test]
+    fn parse_reference_without_repository_fails() {
+        //* When
+        let result = parse_reference("oci://ghcr.io");
+
+        //* Then
+        assert!(
+            matches!(result, Err(OciError::InvalidReference { .. })),
+            "a bare registry should be rejected"
+        );
+    }
+
+    #[test]
+    fn parse_bearer_challenge_with_ghcr_header_extracts_params() {
+        //* Given
+        let header = r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:edgeandnode/amp:pull""#;
+
+        //* When
+        let challenge = parse_bearer_challenge(header);
+
+        //* Then
+        assert_eq!(
+            challenge,
+            Some(BearerChallenge {
+                realm: "https://ghcr.io/token".to_string(),
+                service: Some("ghcr.io".to_string()),
+                scope: Some("repository:edgeandnode/amp:pull".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn find_layer_with_title_annotation_returns_matching_layer() {
+        //* Given
+        let manifest: Manifest = serde_json::from_str(
+            r#"{"layers":[
+                {"digest":"sha256:aaa","annotations":{"org.opencontainers.image.title":"ampd-linux-x86_64"}},
+                {"digest":"sha256:bbb","annotations":{"org.opencontainers.image.title":"ampctl-linux-x86_64"}}
+            ]}"#,
+        )
+        .expect("should parse manifest");
+
+        //* When
+        let layer = find_layer(&manifest, "ampctl-linux-x86_64");
+
+        //* Then
+        assert_eq!(layer.map(|l| l.digest.as_str()), Some("sha256:bbb"));
+    }
+}
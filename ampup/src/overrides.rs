@@ -0,0 +1,169 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+#[derive(Debug)]
+pub enum OverrideError {
+    /// No override is set for the directory.
+    NotFound { dir: PathBuf },
+    /// The version overriding the directory isn't installed.
+    NotInstalled { version: String, dir: PathBuf },
+}
+
+impl std::fmt::Display for OverrideError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound { dir } => {
+                writeln!(f, "No override set for directory")?;
+                writeln!(f, "  Directory: {}", dir.display())?;
+                writeln!(f)?;
+                write!(f, "  Try: ampup override list")?;
+            }
+            Self::NotInstalled { version, dir } => {
+                writeln!(f, "Directory override version not installed")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Directory: {}", dir.display())?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Try: ampup install {}, or ampup override unset --path {}",
+                    version,
+                    dir.display()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for OverrideError {}
+
+/// Versions overriding the one run in a directory and below it, stored in
+/// `~/.amp/overrides.toml` keyed by canonical directory path.
+///
+/// Unlike a project file, an override lives outside the directory, so it
+/// can select a version for a checkout without changing it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Overrides {
+    overrides: BTreeMap<PathBuf, String>,
+}
+
+impl Overrides {
+    pub fn load(config: &Config) -> Result<Self> {
+        let path = config.overrides_file();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(&path).context("Failed to read overrides file")?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse overrides file {}", path.display()))
+    }
+
+    pub fn save(&self, config: &Config) -> Result<()> {
+        let contents = toml::to_string(self).context("Failed to serialize overrides")?;
+        fs::create_dir_all(&config.amp_dir).context("Failed to create amp directory")?;
+        fs::write(config.overrides_file(), contents).context("Failed to write overrides file")
+    }
+
+    /// Overrides in directory order.
+    pub fn iter(&self) -> impl Iterator<Item = (&PathBuf, &String)> {
+        self.overrides.iter()
+    }
+
+    /// Override `dir` with `version`, returning the version it replaces.
+    pub fn set(&mut self, dir: &Path, version: &str) -> Option<String> {
+        self.overrides.insert(canonical(dir), version.to_string())
+    }
+
+    /// Remove the override of `dir`, returning its version.
+    pub fn unset(&mut self, dir: &Path) -> Result<String> {
+        let dir = canonical(dir);
+        self.overrides
+            .remove(&dir)
+            .ok_or(OverrideError::NotFound { dir })
+            .map_err(Into::into)
+    }
+
+    /// Remove the overrides of directories that no longer exist, returning
+    /// them.
+    pub fn remove_nonexistent(&mut self) -> Vec<PathBuf> {
+        let missing: Vec<PathBuf> = self
+            .overrides
+            .keys()
+            .filter(|dir| !dir.is_dir())
+            .cloned()
+            .collect();
+        for dir in &missing {
+            self.overrides.remove(dir);
+        }
+        missing
+    }
+
+    /// The override applying in `dir`: its own, else that of its nearest
+    /// overridden ancestor.
+    pub fn find(&self, dir: &Path) -> Option<(&Path, &str)> {
+        canonical(dir).ancestors().find_map(|dir| {
+            self.overrides
+                .get_key_value(dir)
+                .map(|(dir, version)| (dir.as_path(), version.as_str()))
+        })
+    }
+}
+
+/// `dir` with symlinks resolved, so an override matches however the
+/// directory is reached; as given if it doesn't exist.
+fn canonical(dir: &Path) -> PathBuf {
+    fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_with_overridden_ancestor_returns_nearest_override() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let nested = dir.path().join("service/crates/indexer");
+        fs::create_dir_all(&nested).expect("should create nested directory");
+        let mut overrides = Overrides::default();
+        overrides.set(dir.path(), "edgeandnode-amp/v0.3.0");
+        overrides.set(&dir.path().join("service"), "edgeandnode-amp/v0.4.0");
+
+        //* When
+        let found = overrides.find(&nested);
+
+        //* Then
+        let service = canonical(&dir.path().join("service"));
+        assert_eq!(found, Some((service.as_path(), "edgeandnode-amp/v0.4.0")));
+    }
+
+    #[test]
+    fn remove_nonexistent_with_deleted_directory_keeps_existing_ones() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let deleted = dir.path().join("deleted");
+        fs::create_dir_all(&deleted).expect("should create directory");
+        let mut overrides = Overrides::default();
+        overrides.set(dir.path(), "edgeandnode-amp/v0.3.0");
+        overrides.set(&deleted, "edgeandnode-amp/v0.4.0");
+        let deleted = canonical(&deleted);
+        fs::remove_dir(&deleted).expect("should remove directory");
+
+        //* When
+        let removed = overrides.remove_nonexistent();
+
+        //* Then
+        assert_eq!(removed, vec![deleted]);
+        assert_eq!(overrides.iter().count(), 1);
+        assert!(overrides.find(dir.path()).is_some());
+    }
+}
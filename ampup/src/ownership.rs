@@ -0,0 +1,270 @@
+use std::{
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::{
+    config::Config,
+    environment::{Environment, ProcessEnv},
+    ui,
+};
+
+#[derive(Debug)]
+pub enum OwnershipError {
+    /// Running as root would write root-owned files into a user's
+    /// installation.
+    RootOnUserInstall { amp_dir: PathBuf, uid: u32 },
+    /// `sudo` kept root's home directory, so the installation found there
+    /// is root's rather than that of the user running sudo.
+    SudoHome { amp_dir: PathBuf, user: String },
+    /// Files in the installation belong to someone other than its owner.
+    ForeignFiles { amp_dir: PathBuf, count: usize },
+    /// Changing the files' owner needs root.
+    NeedsRoot { amp_dir: PathBuf, count: usize },
+}
+
+impl std::fmt::Display for OwnershipError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RootOnUserInstall { amp_dir, uid } => {
+                writeln!(
+                    f,
+                    "Refusing to run as root on an installation owned by another user"
+                )?;
+                writeln!(f, "  Directory: {}", amp_dir.display())?;
+                writeln!(f, "  Owner: uid {}", uid)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Files created as root can't be replaced by later runs as that user."
+                )?;
+                write!(
+                    f,
+                    "  Run ampup without sudo, or pass --allow-root to continue."
+                )?;
+            }
+            Self::SudoHome { amp_dir, user } => {
+                writeln!(f, "Refusing to run under sudo on root's installation")?;
+                writeln!(f, "  Directory: {}", amp_dir.display())?;
+                writeln!(f, "  Sudo user: {}", user)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  sudo switched HOME to root's, so this isn't {}'s installation.",
+                    user
+                )?;
+                write!(
+                    f,
+                    "  Run ampup without sudo, or pass --allow-root to use root's installation."
+                )?;
+            }
+            Self::ForeignFiles { amp_dir, count } => {
+                writeln!(f, "Installation has files owned by another user")?;
+                writeln!(f, "  Directory: {}", amp_dir.display())?;
+                writeln!(f, "  Files: {}", count)?;
+                writeln!(f)?;
+                write!(f, "  Try: sudo ampup fix-permissions")?;
+            }
+            Self::NeedsRoot { amp_dir, count } => {
+                writeln!(f, "Changing file owners needs root")?;
+                writeln!(f, "  Directory: {}", amp_dir.display())?;
+                writeln!(f, "  Files: {}", count)?;
+                writeln!(f)?;
+                write!(f, "  Try: sudo ampup fix-permissions")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for OwnershipError {}
+
+/// User and group an installation's files should belong to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Owner {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl Owner {
+    fn of(metadata: &std::fs::Metadata) -> Self {
+        Self {
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+        }
+    }
+}
+
+/// Whether ampup runs with root privileges.
+pub fn is_root() -> bool {
+    rustix::process::geteuid().is_root()
+}
+
+/// Owner of the installation in `amp_dir`: the directory's own owner,
+/// unless root owns it (or it doesn't exist yet), then the owner of the
+/// directory it's in. A `~/.amp` a `sudo` run created is thus still the
+/// home directory owner's, while `/opt/amp` belongs to root.
+pub fn installation_owner(amp_dir: &Path) -> Result<Owner> {
+    if let Ok(metadata) = fs::metadata(amp_dir)
+        && metadata.uid() != 0
+    {
+        return Ok(Owner::of(&metadata));
+    }
+    let parent = amp_dir
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.exists())
+        .unwrap_or(Path::new("/"));
+    let metadata = fs::metadata(parent).context("Failed to read installation owner")?;
+    Ok(Owner::of(&metadata))
+}
+
+/// Fail when running as root on an installation another user owns, unless
+/// `allow_root`, where files created now would be root's and break that
+/// user's later runs. Under `sudo`, root's own installation in its home
+/// directory is refused too, since the user meant their own.
+pub fn check_root(config: &Config, allow_root: bool) -> Result<()> {
+    if !is_root() {
+        return Ok(());
+    }
+    let owner = installation_owner(&config.amp_dir)?;
+    if owner.uid == 0 {
+        return match sudo_home_user(&config.amp_dir, &ProcessEnv) {
+            Some(user) if !allow_root => Err(OwnershipError::SudoHome {
+                amp_dir: config.amp_dir.clone(),
+                user,
+            }
+            .into()),
+            _ => Ok(()),
+        };
+    }
+    if !allow_root {
+        return Err(OwnershipError::RootOnUserInstall {
+            amp_dir: config.amp_dir.clone(),
+            uid: owner.uid,
+        }
+        .into());
+    }
+    ui::warn!(
+        "Running as root on {}, owned by uid {}",
+        ui::path(config.amp_dir.display()),
+        owner.uid
+    );
+    ui::detail!("Run 'sudo ampup fix-permissions' afterwards to give the files back");
+    Ok(())
+}
+
+/// The user who ran ampup through `sudo` (`SUDO_UID`, named by `SUDO_USER`),
+/// when that isn't root and `amp_dir` is in the home directory sudo gave
+/// root (`HOME`), where the default installation resolves.
+fn sudo_home_user(amp_dir: &Path, env: &dyn Environment) -> Option<String> {
+    let uid: u32 = env.var("SUDO_UID")?.parse().ok()?;
+    if uid == 0 {
+        return None;
+    }
+    let home = PathBuf::from(env.var_os("HOME")?);
+    if !amp_dir.starts_with(&home) {
+        return None;
+    }
+    Some(
+        env.var("SUDO_USER")
+            .unwrap_or_else(|| format!("uid {}", uid)),
+    )
+}
+
+/// Files and directories under `dir` (and `dir` itself) not owned by
+/// `owner`, without following symlinks.
+pub fn foreign_files(dir: &Path, owner: Owner) -> Result<Vec<PathBuf>> {
+    let mut foreign = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err).context("Failed to read file owner"),
+        };
+        if Owner::of(&metadata) != owner {
+            foreign.push(path.clone());
+        }
+        if metadata.is_dir() {
+            for entry in fs::read_dir(&path).context("Failed to read directory")? {
+                pending.push(entry.context("Failed to read directory entry")?.path());
+            }
+        }
+    }
+    foreign.sort();
+    Ok(foreign)
+}
+
+/// Give each of `paths` to `owner`, changing symlinks themselves rather
+/// than their targets.
+pub fn chown_all(paths: &[PathBuf], owner: Owner) -> Result<()> {
+    for path in paths {
+        std::os::unix::fs::lchown(path, Some(owner.uid), Some(owner.gid))
+            .with_context(|| format!("Failed to change owner of {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::MapEnv;
+
+    #[test]
+    fn installation_owner_with_missing_directory_returns_parent_owner() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        let expected = Owner::of(&fs::metadata(dir.path()).expect("should read metadata"));
+
+        //* When
+        let owner = installation_owner(&dir.path().join(".amp")).expect("should find owner");
+
+        //* Then
+        assert_eq!(owner, expected);
+    }
+
+    #[test]
+    fn sudo_home_user_with_sudo_and_root_home_returns_invoking_user() {
+        //* Given
+        let env = MapEnv::new()
+            .with("SUDO_UID", "1000")
+            .with("SUDO_USER", "alice")
+            .with("HOME", "/root");
+
+        //* When
+        let home = sudo_home_user(Path::new("/root/.amp"), &env);
+        let system = sudo_home_user(Path::new("/opt/amp"), &env);
+        let root = sudo_home_user(Path::new("/root/.amp"), &env.clone().with("SUDO_UID", "0"));
+
+        //* Then
+        assert_eq!(home.as_deref(), Some("alice"));
+        assert_eq!(system, None, "installations outside HOME aren't redirected");
+        assert_eq!(root, None, "sudo from root keeps root's installation");
+    }
+
+    #[test]
+    fn foreign_files_with_other_owner_lists_every_entry() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        fs::create_dir_all(dir.path().join("versions/v0.1.0")).expect("should create version");
+        fs::write(dir.path().join("versions/v0.1.0/ampd"), "").expect("should write ampd");
+        let owner = Owner::of(&fs::metadata(dir.path()).expect("should read metadata"));
+        let other = Owner {
+            uid: owner.uid + 1,
+            gid: owner.gid,
+        };
+
+        //* When
+        let own = foreign_files(dir.path(), owner).expect("should scan");
+        let foreign = foreign_files(dir.path(), other).expect("should scan");
+
+        //* Then
+        assert!(own.is_empty());
+        assert_eq!(foreign.len(), 4);
+        assert_eq!(foreign[0], dir.path());
+    }
+}
@@ -46,6 +46,8 @@ pub enum Platform {
 }
 
 impl Platform {
+    pub const ALL: [Platform; 2] = [Self::Linux, Self::Darwin];
+
     /// Detect the current platform
     pub fn detect() -> Result<Self> {
         match std::env::consts::OS {
@@ -58,6 +60,18 @@ impl Platform {
         }
     }
 
+    /// Use `name` if given (e.g. from `--platform`), otherwise detect the current platform
+    pub fn from_override(name: Option<String>) -> Result<Self> {
+        match name {
+            Some(p) => match p.as_str() {
+                "linux" => Ok(Self::Linux),
+                "darwin" => Ok(Self::Darwin),
+                _ => Err(PlatformError::UnsupportedPlatform { detected: p }.into()),
+            },
+            None => Self::detect(),
+        }
+    }
+
     /// Get the platform string for artifact names
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -81,6 +95,8 @@ pub enum Architecture {
 }
 
 impl Architecture {
+    pub const ALL: [Architecture; 2] = [Self::X86_64, Self::Aarch64];
+
     /// Detect the current architecture
     pub fn detect() -> Result<Self> {
         match std::env::consts::ARCH {
@@ -93,6 +109,18 @@ impl Architecture {
         }
     }
 
+    /// Use `name` if given (e.g. from `--arch`), otherwise detect the current architecture
+    pub fn from_override(name: Option<String>) -> Result<Self> {
+        match name {
+            Some(a) => match a.as_str() {
+                "x86_64" | "amd64" => Ok(Self::X86_64),
+                "aarch64" | "arm64" => Ok(Self::Aarch64),
+                _ => Err(PlatformError::UnsupportedArchitecture { detected: a }.into()),
+            },
+            None => Self::detect(),
+        }
+    }
+
     /// Get the architecture string for artifact names
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -119,6 +147,28 @@ mod tests {
         assert!(platform.is_ok());
     }
 
+    #[test]
+    fn architecture_from_override_with_arm64_alias_returns_aarch64() {
+        //* When
+        let arch = Architecture::from_override(Some("arm64".to_string()));
+
+        //* Then
+        assert_eq!(
+            arch.expect("arm64 should be accepted"),
+            Architecture::Aarch64,
+            "arm64 should be treated as an alias for aarch64"
+        );
+    }
+
+    #[test]
+    fn platform_from_override_with_unknown_name_fails() {
+        //* When
+        let platform = Platform::from_override(Some("windows".to_string()));
+
+        //* Then
+        assert!(platform.is_err(), "unsupported platform should be rejected");
+    }
+
     #[test]
     fn test_arch_detect() {
         // This will work on any supported architecture
@@ -0,0 +1,111 @@
+use std::{fmt, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+pub enum PolicyError {
+    /// The source publishes no digest to verify an artifact against.
+    MissingDigest { artifact_name: String },
+    /// A local checkout has uncommitted changes.
+    DirtyBuild { path: PathBuf },
+}
+
+impl std::fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingDigest { artifact_name } => {
+                writeln!(f, "Artifact has no published digest")?;
+                writeln!(f, "  Artifact: {}", artifact_name)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Production hosts only install artifacts whose digest can be verified."
+                )?;
+                write!(
+                    f,
+                    "  Use a source that publishes digests (GitHub releases, OCI registries)."
+                )?;
+            }
+            Self::DirtyBuild { path } => {
+                writeln!(f, "Local checkout has uncommitted changes")?;
+                writeln!(f, "  Path: {}", path.display())?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Production hosts only build committed sources. Commit or stash the changes."
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+/// Deployment environment a host declares with `environment` in
+/// config.toml.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostEnvironment {
+    Production,
+    Staging,
+    Dev,
+}
+
+impl fmt::Display for HostEnvironment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Production => write!(f, "production"),
+            Self::Staging => write!(f, "staging"),
+            Self::Dev => write!(f, "dev"),
+        }
+    }
+}
+
+/// Guard rails consulted by install, use, upgrade and build, derived from
+/// the host's environment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Refuse artifacts the source publishes no digest for
+    pub require_digests: bool,
+    /// Install prereleases only with `--allow-prerelease`, not through a
+    /// channel that tracks them
+    pub explicit_prereleases: bool,
+    /// Refuse to build local checkouts with uncommitted changes
+    pub clean_builds: bool,
+}
+
+impl Policy {
+    /// The policy for `environment`: production enables every guard, other
+    /// environments none.
+    pub fn for_environment(environment: Option<HostEnvironment>) -> Self {
+        match environment {
+            Some(HostEnvironment::Production) => Self {
+                require_digests: true,
+                explicit_prereleases: true,
+                clean_builds: true,
+            },
+            Some(HostEnvironment::Staging | HostEnvironment::Dev) | None => Self::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn for_environment_with_production_enables_every_guard() {
+        //* When
+        let production = Policy::for_environment(Some(HostEnvironment::Production));
+        let staging = Policy::for_environment(Some(HostEnvironment::Staging));
+
+        //* Then
+        assert!(
+            production.require_digests
+                && production.explicit_prereleases
+                && production.clean_builds
+        );
+        assert_eq!(staging, Policy::default());
+    }
+}
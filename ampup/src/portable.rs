@@ -0,0 +1,629 @@
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    executable::ExecutableFormat,
+    manifest::{MANIFEST_FILE, VersionManifest, binary_checksums},
+    platform::{Architecture, Platform},
+    version_manager::VersionManager,
+    version_store::split_key,
+};
+
+/// Layout version of exports, raised when it changes incompatibly.
+pub const EXPORT_FORMAT: u32 = 1;
+
+/// File describing the exported version.
+pub const EXPORT_FILE: &str = "export.json";
+
+/// File listing the SHA-256 of every exported file, in `sha256sum` format.
+pub const CHECKSUMS_FILE: &str = "SHA256SUMS";
+
+/// Directory holding the version's files.
+pub const FILES_DIR: &str = "version";
+
+#[derive(Debug)]
+pub enum ImportError {
+    /// The tarball isn't laid out like an export.
+    NotAnExport { reason: String },
+    /// The export was written by a newer ampup with another layout.
+    UnsupportedFormat { format: u32 },
+    /// An entry would be written outside the version directory or isn't a
+    /// regular file.
+    UnsafeEntry { path: String },
+    /// A file doesn't match its checksum.
+    ChecksumMismatch {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+    /// A file is in the export without a checksum, or the other way round.
+    Unlisted { file: String },
+    /// The binaries don't run on this machine.
+    TargetMismatch {
+        key: String,
+        target: String,
+        host: String,
+    },
+    /// The version is already installed.
+    AlreadyInstalled { key: String },
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotAnExport { reason } => {
+                writeln!(f, "File is not an ampup export")?;
+                writeln!(f, "  Reason: {}", reason)?;
+                writeln!(f)?;
+                write!(f, "  Create exports with: ampup export <version>")?;
+            }
+            Self::UnsupportedFormat { format } => {
+                writeln!(f, "Export was written in a newer format")?;
+                writeln!(f, "  Format: {}", format)?;
+                writeln!(f, "  Supported: {}", EXPORT_FORMAT)?;
+                writeln!(f)?;
+                write!(f, "  Try: ampup self update")?;
+            }
+            Self::UnsafeEntry { path } => {
+                writeln!(f, "Export contains an unsafe entry")?;
+                writeln!(f, "  Entry: {}", path)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Exports only hold regular files under one top-level directory."
+                )?;
+            }
+            Self::ChecksumMismatch {
+                file,
+                expected,
+                actual,
+            } => {
+                writeln!(f, "Exported file doesn't match its checksum")?;
+                writeln!(f, "  File: {}", file)?;
+                writeln!(f, "  Expected: sha256:{}", expected)?;
+                writeln!(f, "  Actual: sha256:{}", actual)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  The tarball was damaged or modified; export the version again."
+                )?;
+            }
+            Self::Unlisted { file } => {
+                writeln!(f, "Exported file has no matching checksum")?;
+                writeln!(f, "  File: {}", file)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  The tarball was damaged or modified; export the version again."
+                )?;
+            }
+            Self::TargetMismatch { key, target, host } => {
+                writeln!(f, "Exported binaries don't run on this machine")?;
+                writeln!(f, "  Version: {}", key)?;
+                writeln!(f, "  Binaries: {}", target)?;
+                writeln!(f, "  This machine: {}", host)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Export the version from a machine with the same platform and architecture."
+                )?;
+            }
+            Self::AlreadyInstalled { key } => {
+                writeln!(f, "Version is already installed")?;
+                writeln!(f, "  Version: {}", key)?;
+                writeln!(f)?;
+                write!(f, "  Pass --force to replace it with the export.")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// What an export holds, kept in its [`EXPORT_FILE`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportInfo {
+    /// [`EXPORT_FORMAT`] of the ampup that wrote it
+    pub format: u32,
+    /// Key the version was installed under (e.g. `edgeandnode-amp/v0.4.0`)
+    pub key: String,
+    /// What `ampd` runs on, e.g. `linux x86_64 (ELF)`
+    pub target: String,
+}
+
+/// File name an export of `key` is written to by default, e.g.
+/// `edgeandnode-amp-v0.4.0.tar.gz`.
+pub fn default_file_name(key: &str) -> String {
+    format!("{}.tar.gz", root_dir(key))
+}
+
+/// Write installed `key` to `output` as a gzipped tarball: a
+/// `<namespace>-<version>/` directory holding [`EXPORT_FILE`],
+/// [`CHECKSUMS_FILE`] and the version directory's files under
+/// [`FILES_DIR`].
+///
+/// The same version always exports to the same bytes: entries are sorted,
+/// timestamps and owners zeroed, and the manifest stripped of what only
+/// applies to this machine (pins and usage times). Symlinks are skipped.
+pub fn export(version_manager: &VersionManager, key: &str, output: impl Write) -> Result<()> {
+    let version_dir = version_manager.version_dir(key);
+    let files = collect_files(&version_dir)?;
+
+    let mut contents: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    for (name, path) in &files {
+        let data = match name.as_str() {
+            MANIFEST_FILE => portable_manifest(&version_dir)?,
+            _ => fs::read(path)?,
+        };
+        contents.insert(name.clone(), data);
+    }
+
+    let target = contents
+        .get("ampd")
+        .map(|data| ExecutableFormat::detect(data).to_string())
+        .unwrap_or_default();
+    let info = ExportInfo {
+        format: EXPORT_FORMAT,
+        key: key.to_string(),
+        target,
+    };
+    let mut info_json =
+        serde_json::to_string_pretty(&info).context("Failed to serialize export info")?;
+    info_json.push('\n');
+
+    let checksums: String = contents
+        .iter()
+        .map(|(name, data)| format!("{:x}  {}/{}\n", Sha256::digest(data), FILES_DIR, name))
+        .collect();
+
+    let root = root_dir(key);
+    let mut archive = tar::Builder::new(GzEncoder::new(output, Compression::default()));
+    append(
+        &mut archive,
+        &root,
+        EXPORT_FILE,
+        info_json.as_bytes(),
+        0o644,
+    )?;
+    append(
+        &mut archive,
+        &root,
+        CHECKSUMS_FILE,
+        checksums.as_bytes(),
+        0o644,
+    )?;
+    for (name, data) in &contents {
+        let mode = match is_executable(&files[name])? {
+            true => 0o755,
+            false => 0o644,
+        };
+        append(
+            &mut archive,
+            &root,
+            &format!("{}/{}", FILES_DIR, name),
+            data,
+            mode,
+        )?;
+    }
+    archive
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .context("Failed to write export")?;
+    Ok(())
+}
+
+/// Install the version in an [`export`]ed tarball read from `input` into
+/// `version_manager`'s store after checking every file against its
+/// checksum and that `ampd` runs on `platform` and `arch`, returning its
+/// key. An installed version of the same key is only replaced with
+/// `force`. Nothing is installed unless every check passes.
+pub fn import(
+    version_manager: &VersionManager,
+    input: impl Read,
+    platform: Platform,
+    arch: Architecture,
+    force: bool,
+) -> Result<String> {
+    let mut root = None;
+    let mut info = None;
+    let mut checksums = None;
+    let mut files: BTreeMap<String, (Vec<u8>, u32)> = BTreeMap::new();
+
+    let mut archive = tar::Archive::new(GzDecoder::new(input));
+    let unreadable = "Failed to read export; is it a .tar.gz written by ampup export?";
+    for entry in archive.entries().context(unreadable)? {
+        let mut entry = entry.context(unreadable)?;
+        let path = entry
+            .path()
+            .context("Failed to read export entry path")?
+            .into_owned();
+        let unsafe_entry = || ImportError::UnsafeEntry {
+            path: path.display().to_string(),
+        };
+        let mut parts = Vec::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+                _ => return Err(unsafe_entry().into()),
+            }
+        }
+        let Some((entry_root, name)) = parts.split_first() else {
+            continue;
+        };
+        if root.get_or_insert_with(|| entry_root.clone()) != entry_root {
+            return Err(ImportError::NotAnExport {
+                reason: "entries are not under one top-level directory".to_string(),
+            }
+            .into());
+        }
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => continue,
+            tar::EntryType::Regular => {}
+            _ => return Err(unsafe_entry().into()),
+        }
+        let name = name.join("/");
+        let mode = entry.header().mode().unwrap_or(0o644);
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .with_context(|| format!("Failed to read {} from export", name))?;
+
+        match name.as_str() {
+            EXPORT_FILE => {
+                let parsed: ExportInfo =
+                    serde_json::from_slice(&data).context("Failed to parse export info")?;
+                info = Some(parsed);
+            }
+            CHECKSUMS_FILE => checksums = Some(String::from_utf8_lossy(&data).to_string()),
+            _ => match name.strip_prefix(&format!("{}/", FILES_DIR)) {
+                Some(file) if !file.is_empty() => {
+                    files.insert(file.to_string(), (data, mode));
+                }
+                _ => return Err(unsafe_entry().into()),
+            },
+        }
+    }
+
+    let missing = |file: &str| ImportError::NotAnExport {
+        reason: format!("{} is missing", file),
+    };
+    let info = info.ok_or_else(|| missing(EXPORT_FILE))?;
+    let checksums = checksums.ok_or_else(|| missing(CHECKSUMS_FILE))?;
+    if info.format > EXPORT_FORMAT {
+        return Err(ImportError::UnsupportedFormat {
+            format: info.format,
+        }
+        .into());
+    }
+    let safe_key = match split_key(&info.key) {
+        (Some(namespace), version) => is_safe_name(namespace) && is_safe_name(version),
+        (None, _) => false,
+    };
+    if !safe_key {
+        return Err(ImportError::UnsafeEntry { path: info.key }.into());
+    }
+
+    verify_checksums(&checksums, &files)?;
+    let (ampd, _) = files.get("ampd").ok_or_else(|| missing("version/ampd"))?;
+    if !ExecutableFormat::detect(ampd).runs_on(platform, arch) {
+        return Err(ImportError::TargetMismatch {
+            key: info.key,
+            target: ExecutableFormat::detect(ampd).to_string(),
+            host: format!("{} {}", platform, arch),
+        }
+        .into());
+    }
+    if version_manager.is_installed(&info.key) && !force {
+        return Err(ImportError::AlreadyInstalled { key: info.key }.into());
+    }
+
+    install_files(version_manager, &info.key, &files)?;
+    Ok(info.key)
+}
+
+/// Write `files` (by `/`-separated path, with their mode) as the directory
+/// of version `key`, replacing an installed one. They are staged next to
+/// the version directory and moved into place.
+pub(crate) fn install_files(
+    version_manager: &VersionManager,
+    key: &str,
+    files: &BTreeMap<String, (impl AsRef<[u8]>, u32)>,
+) -> Result<()> {
+    let version_dir = version_manager.version_dir(key);
+    let parent = version_dir
+        .parent()
+        .context("Version directory has no parent")?;
+    fs::create_dir_all(parent).context("Failed to create namespace directory")?;
+    let staging = tempfile::tempdir_in(parent).context("Failed to create staging directory")?;
+    for (file, (data, mode)) in files {
+        let path = staging.path().join(file);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context("Failed to create directory")?;
+        }
+        fs::write(&path, data).with_context(|| format!("Failed to write {}", file))?;
+        set_mode(&path, *mode)?;
+    }
+    if version_dir.exists() {
+        fs::remove_dir_all(&version_dir).context("Failed to remove replaced version")?;
+    }
+    fs::rename(staging.keep(), &version_dir).context("Failed to move version into place")?;
+    let checksums = binary_checksums(&version_dir)?;
+    VersionManifest::update(&version_dir, |manifest| manifest.checksums = checksums)
+}
+
+/// Check every file against `checksums`, in `sha256sum` format, and that
+/// each one listed is present.
+fn verify_checksums(checksums: &str, files: &BTreeMap<String, (Vec<u8>, u32)>) -> Result<()> {
+    let mut listed = BTreeMap::new();
+    for line in checksums.lines().filter(|line| !line.trim().is_empty()) {
+        let (digest, path) = line
+            .split_once("  ")
+            .ok_or_else(|| ImportError::NotAnExport {
+                reason: format!("malformed {} line '{}'", CHECKSUMS_FILE, line),
+            })?;
+        let file = path
+            .strip_prefix(&format!("{}/", FILES_DIR))
+            .unwrap_or(path);
+        listed.insert(file.to_string(), digest.to_string());
+    }
+    for (file, (data, _)) in files {
+        let expected = listed.remove(file).ok_or_else(|| ImportError::Unlisted {
+            file: format!("{}/{}", FILES_DIR, file),
+        })?;
+        let actual = format!("{:x}", Sha256::digest(data));
+        if !actual.eq_ignore_ascii_case(&expected) {
+            return Err(ImportError::ChecksumMismatch {
+                file: format!("{}/{}", FILES_DIR, file),
+                expected,
+                actual,
+            }
+            .into());
+        }
+    }
+    match listed.into_keys().next() {
+        Some(file) => Err(ImportError::Unlisted {
+            file: format!("{}/{}", FILES_DIR, file),
+        }
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Whether `name` is usable as a single directory name.
+pub(crate) fn is_safe_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o755))
+        .context("Failed to set file permissions")
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+/// Top-level directory of an export of `key`.
+fn root_dir(key: &str) -> String {
+    match split_key(key) {
+        (Some(namespace), version) => format!("{}-{}", namespace, version),
+        (None, version) => version.to_string(),
+    }
+}
+
+/// The regular files under `dir` by their `/`-separated path relative to
+/// it, leaving out manifests being written.
+fn collect_files(dir: &Path) -> Result<BTreeMap<String, PathBuf>> {
+    let staged_manifest = format!(".{}.", MANIFEST_FILE);
+    let mut files = BTreeMap::new();
+    let mut pending = vec![(dir.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = pending.pop() {
+        for entry in fs::read_dir(&dir).context("Failed to read version directory")? {
+            let entry = entry.context("Failed to read version directory entry")?;
+            let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            let file_type = entry.file_type().context("Failed to get file type")?;
+            if file_type.is_dir() {
+                pending.push((entry.path(), format!("{}/", name)));
+            } else if file_type.is_file() && !name.starts_with(&staged_manifest) {
+                files.insert(name, entry.path());
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// The manifest in `version_dir` without this machine's pin and usage
+/// times.
+fn portable_manifest(version_dir: &Path) -> Result<Vec<u8>> {
+    let mut manifest = VersionManifest::load(version_dir)?;
+    manifest.pinned = false;
+    manifest.last_activated = None;
+    manifest.last_used = None;
+    serde_json::to_vec_pretty(&manifest).context("Failed to serialize version manifest")
+}
+
+/// Add `data` as `<root>/<name>` with fixed timestamps and owners.
+pub(crate) fn append<W: Write>(
+    archive: &mut tar::Builder<W>,
+    root: &str,
+    name: &str,
+    data: &[u8],
+    mode: u32,
+) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(mode);
+    header.set_mtime(0);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_cksum();
+    archive
+        .append_data(&mut header, format!("{}/{}", root, name), data)
+        .with_context(|| format!("Failed to add {} to export", name))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> Result<bool> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(fs::metadata(path)?.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> Result<bool> {
+    Ok(matches!(
+        path.file_name().and_then(|name| name.to_str()),
+        Some("ampd" | "ampctl")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn export_with_same_version_writes_identical_sorted_tarballs() -> Result<()> {
+        //* Given
+        let dir = tempfile::tempdir()?;
+        let version_manager = VersionManager::new(Config::new(Some(dir.path().to_path_buf()))?);
+        let key = "edgeandnode-amp/v0.4.0";
+        let version_dir = version_manager.version_dir(key);
+        fs::create_dir_all(version_dir.join("share/man"))?;
+        fs::write(version_dir.join("ampd"), "#!/bin/sh\n")?;
+        fs::write(version_dir.join("share/man/ampd.1"), "manual")?;
+        VersionManifest::update(&version_dir, |manifest| {
+            manifest.pinned = true;
+            manifest.last_used = Some(42);
+        })?;
+
+        //* When
+        let mut first = Vec::new();
+        export(&version_manager, key, &mut first)?;
+        let mut second = Vec::new();
+        export(&version_manager, key, &mut second)?;
+
+        //* Then
+        assert_eq!(first, second);
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(first.as_slice()));
+        let names: Vec<String> = archive
+            .entries()?
+            .map(|entry| Ok(entry?.path()?.to_string_lossy().to_string()))
+            .collect::<Result<_>>()?;
+        assert_eq!(
+            names,
+            vec![
+                "edgeandnode-amp-v0.4.0/export.json",
+                "edgeandnode-amp-v0.4.0/SHA256SUMS",
+                "edgeandnode-amp-v0.4.0/version/ampd",
+                "edgeandnode-amp-v0.4.0/version/manifest.json",
+                "edgeandnode-amp-v0.4.0/version/share/man/ampd.1",
+            ]
+        );
+        Ok(())
+    }
+
+    /// Export of a version holding a `#!` script as `ampd`, which runs
+    /// anywhere.
+    fn script_export() -> Result<Vec<u8>> {
+        let dir = tempfile::tempdir()?;
+        let version_manager = VersionManager::new(Config::new(Some(dir.path().to_path_buf()))?);
+        let version_dir = version_manager.version_dir("myfork-amp/v0.4.0");
+        fs::create_dir_all(&version_dir)?;
+        fs::write(version_dir.join("ampd"), "#!/bin/sh\n")?;
+        fs::write(version_dir.join("ampctl"), "#!/bin/sh\n")?;
+        let mut tarball = Vec::new();
+        export(&version_manager, "myfork-amp/v0.4.0", &mut tarball)?;
+        Ok(tarball)
+    }
+
+    #[test]
+    fn import_with_exported_version_installs_it_under_its_key() -> Result<()> {
+        //* Given
+        let tarball = script_export()?;
+        let dir = tempfile::tempdir()?;
+        let version_manager = VersionManager::new(Config::new(Some(dir.path().to_path_buf()))?);
+
+        //* When
+        let key = import(
+            &version_manager,
+            tarball.as_slice(),
+            Platform::Linux,
+            Architecture::Aarch64,
+            false,
+        )?;
+
+        //* Then
+        assert_eq!(key, "myfork-amp/v0.4.0");
+        assert!(version_manager.is_installed(&key));
+        assert_eq!(
+            version_manager.store().staging_dirs()?,
+            Vec::<PathBuf>::new()
+        );
+        let again = import(
+            &version_manager,
+            tarball.as_slice(),
+            Platform::Linux,
+            Architecture::Aarch64,
+            false,
+        );
+        assert!(matches!(
+            again
+                .err()
+                .and_then(|err| err.downcast::<ImportError>().ok()),
+            Some(ImportError::AlreadyInstalled { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn import_with_modified_file_fails_without_installing() -> Result<()> {
+        //* Given
+        let original = script_export()?;
+        let mut archive = tar::Archive::new(GzDecoder::new(original.as_slice()));
+        let mut tampered = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_string_lossy().to_string();
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            if path.ends_with("version/ampd") {
+                data = b"#!/bin/sh\nrm -rf ~\n".to_vec();
+            }
+            let (root, name) = path.split_once('/').expect("should be under the root");
+            append(&mut tampered, root, name, &data, 0o755)?;
+        }
+        let tarball = tampered.into_inner()?.finish()?;
+        let dir = tempfile::tempdir()?;
+        let version_manager = VersionManager::new(Config::new(Some(dir.path().to_path_buf()))?);
+
+        //* When
+        let result = import(
+            &version_manager,
+            tarball.as_slice(),
+            Platform::Linux,
+            Architecture::X86_64,
+            false,
+        );
+
+        //* Then
+        assert!(matches!(
+            result.err().and_then(|err| err.downcast::<ImportError>().ok()),
+            Some(ImportError::ChecksumMismatch { file, .. }) if file == "version/ampd"
+        ));
+        assert_eq!(version_manager.list_installed()?, Vec::<String>::new());
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    io::Write,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use fs_err as fs;
+use rustix::{
+    fs::FallocateFlags,
+    io::{self, Errno},
+};
+
+/// Size from which files are preallocated before they're written, unless
+/// `install.preallocate_mib` says otherwise.
+pub const DEFAULT_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// Bytes written per call once a file is preallocated.
+const WRITE_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Current preallocation threshold, as configured with [`set_threshold`] and
+/// raised past any file size once a filesystem turns out not to support
+/// preallocation.
+static THRESHOLD: AtomicU64 = AtomicU64::new(DEFAULT_THRESHOLD);
+
+/// Write `data` to a new file at `path`.
+///
+/// Files of at least [`threshold`] bytes have their full size reserved
+/// first (`fallocate`, or `F_PREALLOCATE` on macOS) and are then written in
+/// chunks, so ext4 and ZFS can place them in few extents and a full disk
+/// fails before anything is written. The first filesystem without
+/// preallocation support turns it off for the rest of the run.
+///
+/// This only shapes the file on disk: `data` is already in memory, so peak
+/// memory is set by how the download is buffered, not by this write.
+pub fn write(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let len = data.len() as u64;
+    if len < threshold() {
+        return fs::write(path, data);
+    }
+
+    let mut file = fs::File::create(path)?;
+    match rustix::fs::fallocate(file.file(), FallocateFlags::empty(), 0, len) {
+        Ok(()) => {}
+        Err(errno) if is_unsupported(errno) => THRESHOLD.store(u64::MAX, Ordering::Relaxed),
+        Err(errno) => return Err(errno.into()),
+    }
+    for chunk in data.chunks(WRITE_CHUNK_SIZE) {
+        file.write_all(chunk)?;
+    }
+    Ok(())
+}
+
+/// Set the size from which [`write`] preallocates files; `u64::MAX` turns
+/// preallocation off.
+pub fn set_threshold(bytes: u64) {
+    THRESHOLD.store(bytes, Ordering::Relaxed);
+}
+
+/// Size from which [`write`] preallocates files.
+pub fn threshold() -> u64 {
+    THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Whether `errno` means the filesystem or kernel can't preallocate.
+fn is_unsupported(errno: io::Errno) -> bool {
+    errno == Errno::OPNOTSUPP || errno == Errno::NOSYS || errno == Errno::INVAL
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_with_large_file_preallocates_and_writes_contents() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let path = dir.path().join("ampd");
+        let data: Vec<u8> = (0..DEFAULT_THRESHOLD as usize + WRITE_CHUNK_SIZE + 3)
+            .map(|i| (i % 251) as u8)
+            .collect();
+
+        //* When
+        let result = write(&path, &data);
+
+        //* Then
+        assert!(result.is_ok(), "should write: {:?}", result.err());
+        assert_eq!(fs::read(&path).expect("should read back"), data);
+    }
+
+    #[test]
+    fn write_with_small_file_writes_contents() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let path = dir.path().join("ampctl");
+
+        //* When
+        write(&path, b"#!/bin/sh\n").expect("should write");
+
+        //* Then
+        assert_eq!(fs::read(&path).expect("should read back"), b"#!/bin/sh\n");
+    }
+}
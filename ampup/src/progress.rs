@@ -1,6 +1,12 @@
 use std::sync::{Arc, Mutex};
 
 use console::{Term, style};
+use futures::{
+    Stream,
+    channel::mpsc::{self, UnboundedSender},
+};
+
+use crate::ui::Theme;
 
 // ---------------------------------------------------------------------------
 // Public trait
@@ -16,6 +22,13 @@ pub trait ProgressReporter: Send + Sync {
     /// Called once before any tasks start.
     fn set_total(&self, total: usize, names: Vec<String>);
 
+    /// Mark a component as waiting for a download slot, with `ahead` other
+    /// downloads queued before it.
+    ///
+    /// Called from inside the spawned task before it asks the scheduler for a
+    /// slot.
+    fn component_queued(&self, name: &str, ahead: usize);
+
     /// Mark a component as actively downloading.
     ///
     /// Called from inside the spawned task after the semaphore permit
@@ -41,13 +54,104 @@ pub trait ProgressReporter: Send + Sync {
 /// Create a progress reporter appropriate for the current terminal.
 ///
 /// Returns [`TtyProgress`] when stderr is a TTY (interactive terminal),
-/// [`CiProgress`] otherwise (piped output, CI environments).
+/// or [`LineProgress`] with the minimal theme, and [`CiProgress`] otherwise
+/// (piped output, CI environments). The accessible theme always gets
+/// [`SentenceProgress`].
 pub fn create_reporter() -> Arc<dyn ProgressReporter> {
     let term = Term::stderr();
-    if term.is_term() {
-        Arc::new(TtyProgress::new(term))
-    } else {
+    if crate::ui::theme() == Theme::Accessible {
+        Arc::new(SentenceProgress::new())
+    } else if !term.is_term() {
         Arc::new(CiProgress::new())
+    } else if crate::ui::theme() == Theme::Minimal {
+        Arc::new(LineProgress::new(term))
+    } else {
+        Arc::new(TtyProgress::new(term))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Event stream reporter
+// ---------------------------------------------------------------------------
+
+/// A progress change, as [`event_stream`] yields it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// The components that will be downloaded
+    Total {
+        names: Vec<String>,
+    },
+    /// A component waits for a download slot behind `ahead` others
+    Queued {
+        name: String,
+        ahead: usize,
+    },
+    Started {
+        name: String,
+    },
+    Completed {
+        name: String,
+    },
+    Failed {
+        name: String,
+    },
+    /// Every download has ended
+    Finished,
+}
+
+/// A reporter sending each change as a [`ProgressEvent`], and the stream
+/// receiving them, for frontends drawing their own progress.
+///
+/// The stream ends once every clone of the reporter is dropped.
+pub fn event_stream() -> (Arc<dyn ProgressReporter>, impl Stream<Item = ProgressEvent>) {
+    let (sender, receiver) = mpsc::unbounded();
+    (Arc::new(StreamProgress { sender }), receiver)
+}
+
+/// Reporter forwarding every change to an [`event_stream`].
+struct StreamProgress {
+    sender: UnboundedSender<ProgressEvent>,
+}
+
+impl StreamProgress {
+    fn send(&self, event: ProgressEvent) {
+        // Nobody listening once the stream is dropped, which is fine
+        let _ = self.sender.unbounded_send(event);
+    }
+}
+
+impl ProgressReporter for StreamProgress {
+    fn set_total(&self, _total: usize, names: Vec<String>) {
+        self.send(ProgressEvent::Total { names });
+    }
+
+    fn component_queued(&self, name: &str, ahead: usize) {
+        self.send(ProgressEvent::Queued {
+            name: name.to_string(),
+            ahead,
+        });
+    }
+
+    fn component_started(&self, name: &str) {
+        self.send(ProgressEvent::Started {
+            name: name.to_string(),
+        });
+    }
+
+    fn component_completed(&self, name: &str) {
+        self.send(ProgressEvent::Completed {
+            name: name.to_string(),
+        });
+    }
+
+    fn component_failed(&self, name: &str) {
+        self.send(ProgressEvent::Failed {
+            name: name.to_string(),
+        });
+    }
+
+    fn finish(&self) {
+        self.send(ProgressEvent::Finished);
     }
 }
 
@@ -58,6 +162,7 @@ pub fn create_reporter() -> Arc<dyn ProgressReporter> {
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum ComponentStatus {
     Pending,
+    Queued(usize),
     Downloading,
     Completed,
     Failed,
@@ -144,6 +249,14 @@ impl ProgressReporter for TtyProgress {
         self.redraw(&state);
     }
 
+    fn component_queued(&self, name: &str, ahead: usize) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(idx) = state.index_of(name) {
+            state.statuses[idx] = ComponentStatus::Queued(ahead);
+        }
+        self.redraw(&state);
+    }
+
     fn component_started(&self, name: &str) {
         let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
         if let Some(idx) = state.index_of(name) {
@@ -176,6 +289,82 @@ impl ProgressReporter for TtyProgress {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Single-line progress reporter
+// ---------------------------------------------------------------------------
+
+/// Interactive progress reporter that rewrites one summary line.
+///
+/// Used by the minimal theme: a single line (e.g. `Downloading 1/2: ampctl`)
+/// is friendlier to terminal multiplexers and screen readers than a block of
+/// lines redrawn in place.
+struct LineProgress {
+    term: Term,
+    state: Mutex<ProgressState>,
+    drawn: Mutex<bool>,
+}
+
+impl LineProgress {
+    fn new(term: Term) -> Self {
+        Self {
+            term,
+            state: Mutex::new(ProgressState::new()),
+            drawn: Mutex::new(false),
+        }
+    }
+
+    /// Replace the summary line. Best-effort, like [`TtyProgress::redraw`].
+    fn redraw(&self, state: &ProgressState) {
+        let mut drawn = self.drawn.lock().unwrap_or_else(|e| e.into_inner());
+        if *drawn {
+            let _ = self.term.clear_last_lines(1);
+        }
+        *drawn =
+            !state.names.is_empty() && self.term.write_line(&format_summary_line(state)).is_ok();
+    }
+
+    fn update(&self, name: &str, status: ComponentStatus) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(idx) = state.index_of(name) {
+            state.statuses[idx] = status;
+            if status == ComponentStatus::Completed {
+                state.completed_count += 1;
+            }
+        }
+        self.redraw(&state);
+    }
+}
+
+impl ProgressReporter for LineProgress {
+    fn set_total(&self, _total: usize, names: Vec<String>) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.statuses = vec![ComponentStatus::Pending; names.len()];
+        state.names = names;
+        self.redraw(&state);
+    }
+
+    fn component_queued(&self, name: &str, ahead: usize) {
+        self.update(name, ComponentStatus::Queued(ahead));
+    }
+
+    fn component_started(&self, name: &str) {
+        self.update(name, ComponentStatus::Downloading);
+    }
+
+    fn component_completed(&self, name: &str) {
+        self.update(name, ComponentStatus::Completed);
+    }
+
+    fn component_failed(&self, name: &str) {
+        self.update(name, ComponentStatus::Failed);
+    }
+
+    fn finish(&self) {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        self.redraw(&state);
+    }
+}
+
 // ---------------------------------------------------------------------------
 // CI progress reporter
 // ---------------------------------------------------------------------------
@@ -204,6 +393,13 @@ impl ProgressReporter for CiProgress {
         state.names = names;
     }
 
+    fn component_queued(&self, name: &str, ahead: usize) {
+        // Only report waits, so unqueued downloads stay silent
+        if ahead > 0 {
+            crate::ui::detail!("Queued {} ({} ahead)", name, ahead);
+        }
+    }
+
     fn component_started(&self, _name: &str) {
         // No output for CI — only report completions
     }
@@ -234,28 +430,144 @@ impl ProgressReporter for CiProgress {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Sentence progress reporter
+// ---------------------------------------------------------------------------
+
+/// Append-only reporter that announces every state change in a sentence.
+///
+/// Used by the accessible theme, so screen readers hear when each download
+/// starts and finishes instead of parsing redrawn lines.
+struct SentenceProgress {
+    state: Mutex<ProgressState>,
+}
+
+impl SentenceProgress {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(ProgressState::new()),
+        }
+    }
+}
+
+impl ProgressReporter for SentenceProgress {
+    fn set_total(&self, total: usize, names: Vec<String>) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.statuses = vec![ComponentStatus::Pending; names.len()];
+        crate::ui::status(format_args!(
+            "Downloading {} files: {}.",
+            total,
+            names.join(", ")
+        ));
+        state.names = names;
+    }
+
+    fn component_queued(&self, name: &str, ahead: usize) {
+        if ahead > 0 {
+            crate::ui::status(format_args!(
+                "Waiting to download {}, {} downloads ahead.",
+                name, ahead
+            ));
+        }
+    }
+
+    fn component_started(&self, name: &str) {
+        crate::ui::status(format_args!("Started downloading {}.", name));
+    }
+
+    fn component_completed(&self, name: &str) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(idx) = state.index_of(name) {
+            state.statuses[idx] = ComponentStatus::Completed;
+            state.completed_count += 1;
+        }
+        crate::ui::status(format_args!(
+            "Finished downloading {}, {} of {} done.",
+            name,
+            state.completed_count,
+            state.names.len()
+        ));
+    }
+
+    fn component_failed(&self, name: &str) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(idx) = state.index_of(name) {
+            state.statuses[idx] = ComponentStatus::Failed;
+        }
+        crate::ui::status(format_args!("Failed to download {}.", name));
+    }
+
+    fn finish(&self) {
+        // Every change was already announced
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Formatting helpers
 // ---------------------------------------------------------------------------
 
+/// Summarize every component in one line, for [`LineProgress`].
+fn format_summary_line(state: &ProgressState) -> String {
+    let total = state.names.len();
+    let with_status = |wanted: fn(ComponentStatus) -> bool| {
+        state
+            .names
+            .iter()
+            .zip(&state.statuses)
+            .filter(|(_, status)| wanted(**status))
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+    };
+
+    let failed = with_status(|s| s == ComponentStatus::Failed);
+    if !failed.is_empty() {
+        return format!("Download failed: {}", failed.join(", "));
+    }
+    if state.completed_count == total {
+        return format!("Downloaded {}/{}", total, total);
+    }
+    let active = with_status(|s| s == ComponentStatus::Downloading);
+    if active.is_empty() {
+        format!("Waiting to download {}/{}...", state.completed_count, total)
+    } else {
+        format!(
+            "Downloading {}/{}: {}",
+            state.completed_count,
+            total,
+            active.join(", ")
+        )
+    }
+}
+
 fn format_tty_line(name: &str, max_name_len: usize, status: ComponentStatus) -> String {
     let padded_name = format!("{:width$}", name, width = max_name_len);
     match status {
-        ComponentStatus::Pending => {
+        ComponentStatus::Pending | ComponentStatus::Queued(0) => {
             format!("  {}   {}", padded_name, style("waiting...").dim())
         }
+        ComponentStatus::Queued(ahead) => {
+            format!(
+                "  {}   {}",
+                padded_name,
+                style(format!("queued, {} ahead...", ahead)).dim()
+            )
+        }
         ComponentStatus::Downloading => {
-            format!("  {}   {} downloading...", padded_name, style("→").cyan())
+            format!(
+                "  {}   {}downloading...",
+                padded_name,
+                crate::ui::theme().info()
+            )
         }
         ComponentStatus::Completed => {
             format!(
-                "  {}   {} downloaded",
+                "  {}   {}downloaded",
                 padded_name,
-                style("✓").green().bold()
+                crate::ui::theme().success()
             )
         }
         ComponentStatus::Failed => {
-            format!("  {}   {} failed", padded_name, style("✗").red().bold())
+            format!("  {}   {}failed", padded_name, crate::ui::theme().error())
         }
     }
 }
@@ -523,6 +835,19 @@ mod tests {
             );
         }
 
+        #[test]
+        fn format_tty_line_with_queued_status_contains_queue_position() {
+            //* Given / When
+            let line = format_tty_line("ampd", 10, ComponentStatus::Queued(3));
+
+            //* Then
+            assert!(
+                line.contains("queued, 3 ahead"),
+                "queued line should show the queue position, got: {}",
+                line
+            );
+        }
+
         #[test]
         fn format_tty_line_with_downloading_status_contains_downloading() {
             //* Given / When
@@ -563,6 +888,104 @@ mod tests {
         }
     }
 
+    mod sentence_progress {
+        use super::*;
+
+        #[test]
+        fn component_completed_with_valid_name_increments_count() {
+            //* Given
+            let reporter = SentenceProgress::new();
+            reporter.set_total(2, vec!["ampd".to_string(), "ampctl".to_string()]);
+
+            //* When
+            reporter.component_completed("ampctl");
+
+            //* Then
+            let state = reporter
+                .state
+                .lock()
+                .expect("state lock should not be poisoned in tests");
+            assert_eq!(state.completed_count, 1);
+            assert_eq!(state.statuses[1], ComponentStatus::Completed);
+        }
+    }
+
+    mod event_stream {
+        use futures::StreamExt;
+
+        use super::*;
+
+        #[tokio::test]
+        async fn event_stream_with_dropped_reporter_yields_every_event_then_ends() {
+            //* Given
+            let (reporter, stream) = event_stream();
+
+            //* When
+            reporter.set_total(1, vec!["ampd".to_string()]);
+            reporter.component_started("ampd");
+            reporter.component_completed("ampd");
+            reporter.finish();
+            drop(reporter);
+            let events: Vec<ProgressEvent> = stream.collect().await;
+
+            //* Then
+            assert_eq!(
+                events,
+                vec![
+                    ProgressEvent::Total {
+                        names: vec!["ampd".to_string()]
+                    },
+                    ProgressEvent::Started {
+                        name: "ampd".to_string()
+                    },
+                    ProgressEvent::Completed {
+                        name: "ampd".to_string()
+                    },
+                    ProgressEvent::Finished,
+                ]
+            );
+        }
+    }
+
+    mod format_summary_line {
+        use super::*;
+
+        fn state(statuses: &[ComponentStatus], completed_count: usize) -> ProgressState {
+            ProgressState {
+                names: vec!["ampd".to_string(), "ampctl".to_string()],
+                statuses: statuses.to_vec(),
+                completed_count,
+            }
+        }
+
+        #[test]
+        fn format_summary_line_with_active_download_lists_it() {
+            //* Given
+            let state = state(
+                &[ComponentStatus::Completed, ComponentStatus::Downloading],
+                1,
+            );
+
+            //* When
+            let line = format_summary_line(&state);
+
+            //* Then
+            assert_eq!(line, "Downloading 1/2: ampctl");
+        }
+
+        #[test]
+        fn format_summary_line_with_failed_download_reports_failure() {
+            //* Given
+            let state = state(&[ComponentStatus::Failed, ComponentStatus::Downloading], 0);
+
+            //* When
+            let line = format_summary_line(&state);
+
+            //* Then
+            assert_eq!(line, "Download failed: ampd");
+        }
+    }
+
     mod create_reporter {
         use super::*;
 
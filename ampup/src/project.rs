@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+use serde::Deserialize;
+
+/// File pinning a project's version, containing just the version.
+pub const VERSION_FILE: &str = ".amp-version";
+
+/// Project config file, whose `version` key pins the project's version.
+pub const CONFIG_FILE: &str = ".ampup.toml";
+
+#[derive(Debug)]
+pub enum ProjectError {
+    /// The file names no version.
+    Empty { file: PathBuf },
+    /// The pinned version isn't installed.
+    NotInstalled { version: String, file: PathBuf },
+}
+
+impl std::fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty { file } => {
+                writeln!(f, "Project version file names no version")?;
+                writeln!(f, "  File: {}", file.display())?;
+                writeln!(f)?;
+                write!(f, "  Try: ampup use --project <version>")?;
+            }
+            Self::NotInstalled { version, file } => {
+                writeln!(f, "Project version not installed")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Pinned by: {}", file.display())?;
+                writeln!(f)?;
+                write!(f, "  Try: ampup install {}", version)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ProjectError {}
+
+/// A version pinned by a project file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectVersion {
+    /// Version or version key, as written in the file
+    pub version: String,
+    /// File that pins it
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectConfig {
+    version: Option<String>,
+}
+
+/// Find the version pinned by the nearest project file in `start` or one of
+/// its ancestors. In the same directory, `.amp-version` wins over
+/// `.ampup.toml`, and a `.ampup.toml` without a `version` is skipped.
+pub fn find(start: &Path) -> Result<Option<ProjectVersion>> {
+    for dir in start.ancestors() {
+        let file = dir.join(VERSION_FILE);
+        if file.is_file() {
+            let contents = fs::read_to_string(&file).context("Failed to read version file")?;
+            let version = contents
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty() && !line.starts_with('#'))
+                .ok_or_else(|| ProjectError::Empty { file: file.clone() })?;
+            return Ok(Some(ProjectVersion {
+                version: version.to_string(),
+                file,
+            }));
+        }
+
+        let file = dir.join(CONFIG_FILE);
+        if file.is_file() {
+            let contents = fs::read_to_string(&file).context("Failed to read project config")?;
+            let config: ProjectConfig = toml::from_str(&contents)
+                .with_context(|| format!("Failed to parse project config {}", file.display()))?;
+            if let Some(version) = config.version {
+                return Ok(Some(ProjectVersion { version, file }));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Pin `version` for the project in `dir` by writing its `.amp-version`.
+pub fn write(dir: &Path, version: &str) -> Result<PathBuf> {
+    let file = dir.join(VERSION_FILE);
+    fs::write(&file, format!("{}\n", version)).context("Failed to write version file")?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_with_version_file_in_ancestor_returns_its_version() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        write(dir.path(), "v0.3.0").expect("should write version file");
+        let nested = dir.path().join("crates/indexer");
+        fs::create_dir_all(&nested).expect("should create nested directory");
+
+        //* When
+        let found = find(&nested).expect("should search");
+
+        //* Then
+        assert_eq!(
+            found,
+            Some(ProjectVersion {
+                version: "v0.3.0".to_string(),
+                file: dir.path().join(VERSION_FILE),
+            })
+        );
+    }
+
+    #[test]
+    fn find_with_nearer_config_file_prefers_it_over_outer_version_file() {
+        //* Given
+        let dir = tempfile::tempdir().expect("tempdir");
+        write(dir.path(), "v0.3.0").expect("should write version file");
+        let nested = dir.path().join("service");
+        fs::create_dir_all(&nested).expect("should create nested directory");
+        fs::write(
+            nested.join(CONFIG_FILE),
+            "# pinned for the service\nversion = \"v0.4.0\"\n",
+        )
+        .expect("should write config file");
+
+        //* When
+        let found = find(&nested).expect("should search");
+
+        //* Then
+        assert_eq!(found.map(|p| p.version), Some("v0.4.0".to_string()));
+    }
+}
@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use crate::version_store::{LOCAL_NAMESPACE, split_key};
+
+/// Which installed versions `ampup prune` removes. A version goes when it
+/// matches every policy given.
+#[derive(Debug, Clone, Default)]
+pub struct Retention {
+    /// Keep this many of the most recently installed versions
+    pub keep_latest: Option<usize>,
+    /// Only remove versions installed longer ago than this
+    pub older_than: Option<Duration>,
+    /// Only remove versions not activated or run through the shims for
+    /// this long
+    pub unused_for: Option<Duration>,
+    /// Only remove source builds
+    pub builds_only: bool,
+}
+
+/// An installed version, as retention policies see it.
+#[derive(Debug, Clone)]
+pub struct Installed {
+    pub key: String,
+    /// Unix timestamp of the installation, where the filesystem records it
+    pub installed_at: Option<u64>,
+    /// Unix timestamp of the latest activation or run through the shims
+    pub last_used: Option<u64>,
+}
+
+impl Retention {
+    /// Whether no policy is set, which would match every version.
+    pub fn is_empty(&self) -> bool {
+        self.keep_latest.is_none()
+            && self.older_than.is_none()
+            && self.unused_for.is_none()
+            && !self.builds_only
+    }
+
+    /// Keys of the `installed` versions the policies match at `now` (a Unix
+    /// timestamp), oldest first. Protecting the current and pinned versions
+    /// is left to the caller, but they still count towards `keep_latest`.
+    pub fn select(&self, installed: &[Installed], now: u64) -> Vec<String> {
+        let mut candidates: Vec<&Installed> = installed
+            .iter()
+            .filter(|version| !self.builds_only || is_build(&version.key))
+            .collect();
+        // Newest first; versions without a recorded time count as the oldest
+        candidates.sort_by_key(|version| std::cmp::Reverse(version.installed_at));
+
+        let mut selected: Vec<&Installed> = candidates
+            .into_iter()
+            .skip(self.keep_latest.unwrap_or(0))
+            // An unknown install time is never old enough
+            .filter(|version| older(version.installed_at, self.older_than, now))
+            // Versions never used count from their installation
+            .filter(|version| {
+                older(
+                    version.last_used.or(version.installed_at),
+                    self.unused_for,
+                    now,
+                )
+            })
+            .collect();
+        selected.reverse();
+        selected
+            .into_iter()
+            .map(|version| version.key.clone())
+            .collect()
+    }
+}
+
+/// Whether `at` lies further than `age` before `now`; always true without
+/// an `age`, never without a time.
+fn older(at: Option<u64>, age: Option<Duration>, now: u64) -> bool {
+    match age {
+        Some(age) => at.is_some_and(|at| now.saturating_sub(at) > age.as_secs()),
+        None => true,
+    }
+}
+
+/// Whether `key` is a source build rather than an installed release.
+fn is_build(key: &str) -> bool {
+    split_key(key).0 == Some(LOCAL_NAMESPACE)
+}
+
+/// Parse an age such as `30d`, `12h` or `2w` (units `s`, `m`, `h`, `d` and
+/// `w`).
+pub fn parse_age(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing unit in '{}', e.g. 30d", value))?;
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid age '{}', e.g. 30d", value))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("unknown unit '{}', use s, m, h, d or w", unit)),
+    };
+    amount
+        .checked_mul(unit_secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("age '{}' is too large", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn installed(key: &str, days_ago: u64) -> Installed {
+        Installed {
+            key: key.to_string(),
+            installed_at: Some(100 * DAY - days_ago * DAY),
+            last_used: None,
+        }
+    }
+
+    #[test]
+    fn select_with_keep_latest_and_older_than_matches_old_versions_beyond_newest() {
+        //* Given
+        let versions = vec![
+            installed("edgeandnode-amp/v0.1.0", 60),
+            installed("edgeandnode-amp/v0.2.0", 40),
+            installed("edgeandnode-amp/v0.3.0", 20),
+            installed("edgeandnode-amp/v0.4.0", 1),
+        ];
+        let retention = Retention {
+            keep_latest: Some(1),
+            older_than: Some(parse_age("30d").expect("should parse age")),
+            unused_for: None,
+            builds_only: false,
+        };
+
+        //* When
+        let selected = retention.select(&versions, 100 * DAY);
+
+        //* Then
+        assert_eq!(
+            selected,
+            vec!["edgeandnode-amp/v0.1.0", "edgeandnode-amp/v0.2.0"]
+        );
+    }
+
+    #[test]
+    fn select_with_unused_for_spares_recently_used_old_versions() {
+        //* Given
+        let mut used = installed("edgeandnode-amp/v0.1.0", 90);
+        used.last_used = Some(100 * DAY - 5 * DAY);
+        let versions = vec![used, installed("edgeandnode-amp/v0.2.0", 80)];
+        let retention = Retention {
+            unused_for: Some(parse_age("60d").expect("should parse age")),
+            ..Retention::default()
+        };
+
+        //* When
+        let selected = retention.select(&versions, 100 * DAY);
+
+        //* Then
+        assert_eq!(selected, vec!["edgeandnode-amp/v0.2.0"]);
+    }
+
+    #[test]
+    fn select_with_builds_only_keeps_releases_and_newest_builds() {
+        //* Given
+        let versions = vec![
+            installed("edgeandnode-amp/v0.1.0", 60),
+            installed("local/branch-main-abc1234", 50),
+            installed("local/branch-fix-def5678", 10),
+        ];
+        let retention = Retention {
+            keep_latest: Some(1),
+            older_than: None,
+            unused_for: None,
+            builds_only: true,
+        };
+
+        //* When
+        let selected = retention.select(&versions, 100 * DAY);
+
+        //* Then
+        assert_eq!(selected, vec!["local/branch-main-abc1234"]);
+    }
+}
@@ -1,7 +1,15 @@
-use std::time::{Duration, Instant, SystemTime};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
 
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 
+/// Longest rate-limit pause waited out by default.
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(60);
+
 /// Shared rate limiter that respects GitHub API rate-limit headers.
 ///
 /// All concurrent downloads share one `GitHubRateLimiter` so that a 429
@@ -9,25 +17,69 @@ use tokio::sync::Mutex;
 pub struct GitHubRateLimiter {
     inner: Mutex<RateLimiterState>,
     has_token: bool,
+    /// Longest pause waited out before failing
+    max_wait: Duration,
+    /// Where state is persisted between invocations, if anywhere
+    state_file: Option<PathBuf>,
 }
 
+#[derive(Default)]
 struct RateLimiterState {
     paused_until: Option<Instant>,
     remaining: Option<u64>,
+    /// Unix time at which `remaining` resets
+    reset_at: Option<u64>,
+}
+
+/// On-disk form of [`RateLimiterState`], with instants as unix seconds.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct PersistedState {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    paused_until: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remaining: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reset_at: Option<u64>,
 }
 
 impl GitHubRateLimiter {
     /// Create a new rate limiter.
     pub fn new(has_token: bool) -> Self {
         Self {
-            inner: Mutex::new(RateLimiterState {
-                paused_until: None,
-                remaining: None,
-            }),
+            inner: Mutex::new(RateLimiterState::default()),
             has_token,
+            max_wait: DEFAULT_MAX_WAIT,
+            state_file: None,
         }
     }
 
+    /// Create a rate limiter that loads its state from `path` and saves it
+    /// back on every update, so a pause outlives the process that hit it.
+    ///
+    /// A missing or unreadable file starts from a clean state; failures to
+    /// save are ignored.
+    pub fn with_state_file(has_token: bool, path: PathBuf) -> Self {
+        let state = load_state(&path);
+        Self {
+            inner: Mutex::new(state),
+            has_token,
+            max_wait: DEFAULT_MAX_WAIT,
+            state_file: Some(path),
+        }
+    }
+
+    /// Set the longest pause [`wait_if_paused`](Self::wait_if_paused) will
+    /// sleep through (default [`DEFAULT_MAX_WAIT`]).
+    pub fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// Longest pause waited out before failing.
+    pub fn max_wait(&self) -> Duration {
+        self.max_wait
+    }
+
     /// Whether the client has an authentication token.
     pub fn has_token(&self) -> bool {
         self.has_token
@@ -35,10 +87,11 @@ impl GitHubRateLimiter {
 
     /// Block until any active rate-limit pause has expired.
     ///
-    /// Returns `Err(remaining_duration)` if the pause exceeds 60 seconds,
-    /// so the caller can fail immediately with an actionable error instead of
-    /// silently blocking for a long time (e.g., unauthenticated rate-limit
-    /// resets can be up to ~60 minutes).
+    /// Returns `Err(remaining_duration)` if the pause exceeds the maximum
+    /// wait, so the caller can fail immediately with an actionable error
+    /// instead of silently blocking for a long time (e.g., unauthenticated
+    /// rate-limit resets can be up to ~60 minutes). The sleep is jittered so
+    /// parallel processes sharing a limit don't all retry at the same moment.
     pub async fn wait_if_paused(&self) -> Result<(), Duration> {
         let wait_duration = {
             let state = self.inner.lock().await;
@@ -49,10 +102,10 @@ impl GitHubRateLimiter {
         };
 
         if let Some(duration) = wait_duration {
-            if duration > Duration::from_secs(60) {
+            if duration > self.max_wait {
                 return Err(duration);
             }
-            tokio::time::sleep(duration).await;
+            tokio::time::sleep(jittered(duration)).await;
         }
 
         Ok(())
@@ -86,9 +139,23 @@ impl GitHubRateLimiter {
         retry_after: Option<u64>,
     ) -> Option<u64> {
         let mut state = self.inner.lock().await;
+        let result = Self::apply_update(&mut state, status, remaining, reset_at, retry_after);
+        if let Some(path) = &self.state_file {
+            save_state(path, &state);
+        }
+        result
+    }
 
+    fn apply_update(
+        state: &mut RateLimiterState,
+        status: reqwest::StatusCode,
+        remaining: Option<u64>,
+        reset_at: Option<u64>,
+        retry_after: Option<u64>,
+    ) -> Option<u64> {
         if let Some(rem) = remaining {
             state.remaining = Some(rem);
+            state.reset_at = reset_at;
         }
 
         // GitHub returns 429 or 403 for rate limiting. Treat 403 as rate-limited
@@ -103,7 +170,7 @@ impl GitHubRateLimiter {
             // GitHub docs recommend waiting at least one minute when absent.
             let secs = retry_after.unwrap_or(60);
             let pause_until = Instant::now() + Duration::from_secs(secs);
-            Self::extend_pause(&mut state, pause_until);
+            Self::extend_pause(state, pause_until);
             return Some(secs);
         }
 
@@ -111,13 +178,10 @@ impl GitHubRateLimiter {
         if remaining == Some(0)
             && let Some(reset) = reset_at
         {
-            let now_unix = SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs();
+            let now_unix = unix_now();
             if reset > now_unix {
                 let pause_until = Instant::now() + Duration::from_secs(reset - now_unix);
-                Self::extend_pause(&mut state, pause_until);
+                Self::extend_pause(state, pause_until);
             }
         }
 
@@ -146,6 +210,60 @@ impl GitHubRateLimiter {
     }
 }
 
+/// `duration` plus a random extra of up to a fifth of it (at least up to
+/// one second), to spread out retries from concurrent processes.
+pub fn jittered(duration: Duration) -> Duration {
+    let spread = (duration / 5).max(Duration::from_secs(1));
+    duration + spread.mul_f64(fastrand::f64())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Load persisted state, dropping a pause or count that has since expired.
+fn load_state(path: &Path) -> RateLimiterState {
+    let persisted: PersistedState = fs::read(path)
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default();
+    let now_unix = unix_now();
+
+    let paused_until = persisted
+        .paused_until
+        .filter(|&until| until > now_unix)
+        .map(|until| Instant::now() + Duration::from_secs(until - now_unix));
+    let reset_at = persisted.reset_at.filter(|&reset| reset > now_unix);
+    RateLimiterState {
+        paused_until,
+        remaining: persisted.remaining.filter(|_| reset_at.is_some()),
+        reset_at,
+    }
+}
+
+/// Best-effort write of `state` to `path`.
+fn save_state(path: &Path, state: &RateLimiterState) {
+    let now = Instant::now();
+    let persisted = PersistedState {
+        paused_until: state
+            .paused_until
+            .filter(|&until| until > now)
+            .map(|until| unix_now() + (until - now).as_secs_f64().ceil() as u64),
+        remaining: state.remaining,
+        reset_at: state.reset_at,
+    };
+    let Ok(data) = serde_json::to_vec(&persisted) else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let _ = fs::write(path, data);
+}
+
 #[cfg(test)]
 mod tests {
     //! Tests are organized into nested modules by the method under test,
@@ -231,7 +349,10 @@ mod tests {
             let result = limiter.wait_if_paused().await;
 
             //* Then
-            assert!(result.is_err(), "should fail when pause exceeds 60");
+            assert!(
+                result.is_err(),
+                "should fail when pause exceeds the default max wait"
+            );
             assert!(
                 start.elapsed() < Duration::from_millis(50),
                 "should fail immediately without sleeping"
@@ -242,6 +363,60 @@ mod tests {
                 "should return the remaining pause duration"
             );
         }
+
+        #[tokio::test]
+        async fn with_raised_max_wait_sleeps_through_long_pause() {
+            //* Given
+            let limiter = GitHubRateLimiter::new(false).with_max_wait(Duration::from_secs(300));
+            {
+                let mut state = limiter.inner.lock().await;
+                state.paused_until = Some(Instant::now() + Duration::from_millis(100));
+            }
+            let long_pause = GitHubRateLimiter::new(false).with_max_wait(Duration::from_secs(300));
+            {
+                let mut state = long_pause.inner.lock().await;
+                state.paused_until = Some(Instant::now() + Duration::from_secs(600));
+            }
+
+            //* When
+            let short = limiter.wait_if_paused().await;
+            let long = long_pause.wait_if_paused().await;
+
+            //* Then
+            assert!(
+                short.is_ok(),
+                "pauses within the max wait should be slept through"
+            );
+            assert!(
+                long.is_err(),
+                "pauses beyond the max wait should still fail"
+            );
+        }
+    }
+
+    mod jittered {
+        use super::*;
+
+        #[test]
+        fn with_any_duration_stays_within_spread() {
+            //* Given
+            let base = Duration::from_secs(100);
+
+            //* When
+            let samples: Vec<Duration> = (0..100).map(|_| jittered(base)).collect();
+
+            //* Then
+            assert!(
+                samples
+                    .iter()
+                    .all(|&d| d >= base && d <= base + Duration::from_secs(20)),
+                "jitter should add at most a fifth of the wait"
+            );
+            assert!(
+                samples.iter().any(|&d| d != samples[0]),
+                "samples should vary"
+            );
+        }
     }
 
     /// Tests for the core state machine using direct values (no HTTP involved).
@@ -324,6 +499,81 @@ mod tests {
         }
     }
 
+    /// Tests for persisting state between limiter instances, as between
+    /// separate `ampup` invocations.
+    mod with_state_file {
+        use super::*;
+
+        #[tokio::test]
+        async fn with_pause_from_previous_limiter_stays_paused() {
+            //* Given
+            let dir = tempfile::tempdir().expect("should create temp dir");
+            let path = dir.path().join("cache").join("rate-limit.json");
+            let previous = GitHubRateLimiter::with_state_file(false, path.clone());
+            previous
+                .update_state(
+                    reqwest::StatusCode::TOO_MANY_REQUESTS,
+                    Some(0),
+                    None,
+                    Some(600),
+                )
+                .await;
+
+            //* When
+            let limiter = GitHubRateLimiter::with_state_file(false, path);
+            let result = limiter.wait_if_paused().await;
+
+            //* Then
+            let remaining = result.expect_err("should still be paused");
+            assert!(
+                remaining > Duration::from_secs(590),
+                "should restore the remaining pause, got {:?}",
+                remaining
+            );
+        }
+
+        #[tokio::test]
+        async fn with_expired_state_starts_clean() {
+            //* Given
+            let dir = tempfile::tempdir().expect("should create temp dir");
+            let path = dir.path().join("rate-limit.json");
+            let past = unix_now() - 60;
+            fs::write(
+                &path,
+                format!(
+                    r#"{{"paused_until":{},"remaining":0,"reset_at":{}}}"#,
+                    past, past
+                ),
+            )
+            .expect("should write state file");
+
+            //* When
+            let limiter = GitHubRateLimiter::with_state_file(true, path);
+
+            //* Then
+            assert!(limiter.wait_if_paused().await.is_ok());
+            assert_eq!(
+                limiter.remaining().await,
+                None,
+                "count from a past window should be dropped"
+            );
+        }
+
+        #[tokio::test]
+        async fn with_corrupt_file_starts_clean() {
+            //* Given
+            let dir = tempfile::tempdir().expect("should create temp dir");
+            let path = dir.path().join("rate-limit.json");
+            fs::write(&path, "not json").expect("should write state file");
+
+            //* When
+            let limiter = GitHubRateLimiter::with_state_file(true, path);
+
+            //* Then
+            assert!(limiter.wait_if_paused().await.is_ok());
+        }
+    }
+
     /// End-to-end tests that send real HTTP through reqwest to verify that the
     /// header names (`X-RateLimit-Remaining`, `X-RateLimit-Reset`, `Retry-After`)
     /// are parsed correctly from actual HTTP responses.
@@ -0,0 +1,585 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use fs_err as fs;
+
+use crate::{
+    chunked,
+    github::{GitHubClient, HttpTimeouts},
+    oci::OciSource,
+    request_plan::PlannedRequest,
+    ui,
+};
+
+/// Source name selecting GitHub releases, the default.
+pub const GITHUB_SOURCE: &str = "github";
+
+#[derive(Debug)]
+pub enum MirrorError {
+    InvalidTemplate {
+        template: String,
+    },
+    DownloadFailed {
+        asset_name: String,
+        status_code: u16,
+        url: String,
+    },
+}
+
+impl std::fmt::Display for MirrorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidTemplate { template } => {
+                writeln!(f, "Invalid release source")?;
+                writeln!(f, "  Source: {}", template)?;
+                writeln!(f)?;
+                writeln!(f, "  Expected one of:")?;
+                writeln!(f, "    github")?;
+                writeln!(f, "    oci://<registry>/<repository>")?;
+                writeln!(
+                    f,
+                    "    an http://, https:// or file:// URL, optionally containing"
+                )?;
+                writeln!(f, "    {{version}}, {{asset}} and {{repo}} placeholders")?;
+            }
+            Self::DownloadFailed {
+                asset_name,
+                status_code,
+                url,
+            } => {
+                writeln!(f, "Failed to download asset from mirror")?;
+                writeln!(f, "  Asset: {}", asset_name)?;
+                writeln!(f, "  HTTP status code: {}", status_code)?;
+                writeln!(f, "  URL: {}", url)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Check that the mirror contains this release for your platform."
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MirrorError {}
+
+/// A release asset resolved from a [`ReleaseSource`], ready to download.
+///
+/// Produced by [`ReleaseSource::resolve_assets`] and consumed by
+/// [`ReleaseSource::download_asset`]. This allows fetching release metadata
+/// once and then downloading multiple assets without redundant API calls.
+#[derive(Clone, Debug)]
+pub struct ResolvedAsset {
+    /// Asset ID on GitHub (used for API-based downloads of private repos);
+    /// `0` for sources without IDs.
+    pub id: u64,
+    /// Asset name (e.g. "ampd-linux-x86_64").
+    pub name: String,
+    /// Direct download URL.
+    pub url: String,
+    /// Content digest published by the source (e.g. `sha256:...`), verified
+    /// after download when present.
+    pub digest: Option<String>,
+}
+
+/// Somewhere release binaries can be downloaded from.
+///
+/// Implemented by [`GitHubClient`] for GitHub releases and by
+/// [`HttpMirrorSource`] for plain HTTP directories, S3-style buckets and
+/// local directories.
+#[async_trait]
+pub trait ReleaseSource: Send + Sync {
+    /// Short description for log messages (e.g. "github.com/edgeandnode/amp").
+    fn describe(&self) -> String;
+
+    /// Resolve asset names in a release, failing on the first missing asset.
+    async fn resolve_assets(
+        &self,
+        version: &str,
+        asset_names: &[&str],
+    ) -> Result<Vec<ResolvedAsset>>;
+
+    /// Download a previously resolved asset, splitting large downloads into
+    /// up to `chunks` concurrent ranged requests where the source supports it.
+    async fn download_asset(&self, asset: &ResolvedAsset, chunks: usize) -> Result<Vec<u8>>;
+
+    /// Check that a resolved asset can still be downloaded, without
+    /// downloading it.
+    ///
+    /// Returns `false` only when the source reports the asset as gone. Other
+    /// failures return `true` so the download itself surfaces the real error.
+    async fn asset_available(&self, asset: &ResolvedAsset) -> bool;
+
+    /// HTTP requests resolving and downloading `asset_names` of `version`
+    /// would send with an empty artifact cache, for `ampup debug requests`.
+    fn plan_requests(&self, version: &str, asset_names: &[&str]) -> Vec<PlannedRequest>;
+}
+
+#[async_trait]
+impl ReleaseSource for GitHubClient {
+    fn describe(&self) -> String {
+        format!("GitHub {}", self.repo())
+    }
+
+    async fn resolve_assets(
+        &self,
+        version: &str,
+        asset_names: &[&str],
+    ) -> Result<Vec<ResolvedAsset>> {
+        self.resolve_release_assets(version, asset_names).await
+    }
+
+    async fn download_asset(&self, asset: &ResolvedAsset, chunks: usize) -> Result<Vec<u8>> {
+        self.download_resolved_asset(asset, chunks).await
+    }
+
+    async fn asset_available(&self, asset: &ResolvedAsset) -> bool {
+        self.probe_resolved_asset(asset).await
+    }
+
+    fn plan_requests(&self, version: &str, asset_names: &[&str]) -> Vec<PlannedRequest> {
+        self.plan_release_assets(version, asset_names)
+    }
+}
+
+/// Build the release source selected by `--source` or the `source` setting.
+///
+/// `github` (or no selection) downloads from `github`'s releases, `oci://`
+/// references pull OCI artifacts authenticated with the GitHub token, and
+/// any other value is treated as a mirror URL template.
+pub fn from_spec(
+    spec: Option<&str>,
+    github: &GitHubClient,
+    timeouts: HttpTimeouts,
+) -> Result<Arc<dyn ReleaseSource>> {
+    let source: Arc<dyn ReleaseSource> = match spec {
+        None | Some(GITHUB_SOURCE) => Arc::new(github.clone()),
+        Some(spec) if spec.starts_with("oci://") => Arc::new(OciSource::new(
+            spec,
+            github.token().map(str::to_string),
+            timeouts,
+        )?),
+        Some(spec) => Arc::new(HttpMirrorSource::new(
+            spec.to_string(),
+            github.repo().to_string(),
+            timeouts,
+        )?),
+    };
+    Ok(source)
+}
+
+/// Build the selected source followed by the configured `mirrors`.
+///
+/// Without mirrors this is just [`from_spec`]; otherwise the sources are
+/// wrapped in a [`FallbackSource`].
+pub fn with_mirrors(
+    spec: Option<&str>,
+    mirrors: &[String],
+    github: &GitHubClient,
+    timeouts: HttpTimeouts,
+) -> Result<Arc<dyn ReleaseSource>> {
+    let primary = from_spec(spec, github, timeouts)?;
+    if mirrors.is_empty() {
+        return Ok(primary);
+    }
+
+    let mut sources = vec![primary];
+    for mirror in mirrors {
+        sources.push(from_spec(Some(mirror), github, timeouts)?);
+    }
+    Ok(Arc::new(FallbackSource::new(sources)))
+}
+
+/// Release source that tries an ordered chain of sources per request.
+///
+/// Each resolve, probe and download starts with the source that resolved the
+/// release and moves down the chain on failure, logging which source served
+/// each asset. Later sources re-resolve the asset by name, since URLs differ
+/// between sources.
+pub struct FallbackSource {
+    sources: Vec<Arc<dyn ReleaseSource>>,
+    /// Version and chain index from the last successful `resolve_assets`
+    resolved: Mutex<Option<(String, usize)>>,
+}
+
+impl FallbackSource {
+    pub fn new(sources: Vec<Arc<dyn ReleaseSource>>) -> Self {
+        Self {
+            sources,
+            resolved: Mutex::new(None),
+        }
+    }
+
+    fn resolved(&self) -> Option<(String, usize)> {
+        self.resolved
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Re-resolve a single asset against a later source in the chain.
+    async fn resolve_one(
+        source: &dyn ReleaseSource,
+        version: &str,
+        name: &str,
+    ) -> Result<ResolvedAsset> {
+        source
+            .resolve_assets(version, &[name])
+            .await?
+            .pop()
+            .with_context(|| format!("{} returned no asset for {}", source.describe(), name))
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for FallbackSource {
+    fn describe(&self) -> String {
+        let chain: Vec<String> = self.sources.iter().map(|s| s.describe()).collect();
+        chain.join(" → ")
+    }
+
+    async fn resolve_assets(
+        &self,
+        version: &str,
+        asset_names: &[&str],
+    ) -> Result<Vec<ResolvedAsset>> {
+        let mut last_err = None;
+        for (index, source) in self.sources.iter().enumerate() {
+            match source.resolve_assets(version, asset_names).await {
+                Ok(assets) => {
+                    if index > 0 {
+                        ui::detail!("Resolved release from {}", source.describe());
+                    }
+                    *self
+                        .resolved
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner()) =
+                        Some((version.to_string(), index));
+                    return Ok(assets);
+                }
+                Err(err) => {
+                    ui::warn!("{} failed: {:#}", source.describe(), err);
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err
+            .unwrap_or_else(|| anyhow::anyhow!("no release sources configured"))
+            .context("All release sources failed"))
+    }
+
+    async fn download_asset(&self, asset: &ResolvedAsset, chunks: usize) -> Result<Vec<u8>> {
+        let (version, start) = self
+            .resolved()
+            .context("asset downloaded before release was resolved")?;
+
+        let mut last_err = None;
+        for (index, source) in self.sources.iter().enumerate().skip(start) {
+            let result = if index == start {
+                source.download_asset(asset, chunks).await
+            } else {
+                match Self::resolve_one(source.as_ref(), &version, &asset.name).await {
+                    Ok(resolved) => source.download_asset(&resolved, chunks).await,
+                    Err(err) => Err(err),
+                }
+            };
+            match result {
+                Ok(data) => {
+                    ui::detail!("{} served by {}", asset.name, source.describe());
+                    return Ok(data);
+                }
+                Err(err) => {
+                    ui::warn!("{} failed for {}: {:#}", source.describe(), asset.name, err);
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err
+            .unwrap_or_else(|| anyhow::anyhow!("no release sources configured"))
+            .context(format!("All release sources failed for {}", asset.name)))
+    }
+
+    async fn asset_available(&self, asset: &ResolvedAsset) -> bool {
+        let Some((version, start)) = self.resolved() else {
+            return true;
+        };
+
+        for (index, source) in self.sources.iter().enumerate().skip(start) {
+            let available = if index == start {
+                source.asset_available(asset).await
+            } else {
+                match Self::resolve_one(source.as_ref(), &version, &asset.name).await {
+                    Ok(resolved) => source.asset_available(&resolved).await,
+                    Err(_) => false,
+                }
+            };
+            if available {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Every source's requests in chain order; later sources are only
+    /// tried when earlier ones fail.
+    fn plan_requests(&self, version: &str, asset_names: &[&str]) -> Vec<PlannedRequest> {
+        let mut requests = Vec::new();
+        for (index, source) in self.sources.iter().enumerate() {
+            for mut request in source.plan_requests(version, asset_names) {
+                if index > 0 {
+                    request.purpose = format!("If earlier sources fail: {}", request.purpose);
+                }
+                requests.push(request);
+            }
+        }
+        requests
+    }
+}
+
+/// Release source backed by a URL template, for mirrors that serve the same
+/// asset names as GitHub releases.
+///
+/// The template may contain `{version}`, `{asset}` and `{repo}`
+/// placeholders. A template without `{asset}` is treated as a base URL laid
+/// out as `<base>/<version>/<asset>`. `file://` URLs read from the local
+/// filesystem, for air-gapped hosts.
+pub struct HttpMirrorSource {
+    client: reqwest::Client,
+    template: String,
+    repo: String,
+}
+
+impl HttpMirrorSource {
+    pub fn new(template: String, repo: String, timeouts: HttpTimeouts) -> Result<Self> {
+        let supported = ["http://", "https://", "file://"];
+        if !supported.iter().any(|scheme| template.starts_with(scheme)) {
+            return Err(MirrorError::InvalidTemplate { template }.into());
+        }
+
+        let client = timeouts
+            .apply(reqwest::Client::builder().user_agent("ampup"))
+            .build()
+            .context("Failed to create request client")?;
+
+        Ok(Self {
+            client,
+            template,
+            repo,
+        })
+    }
+
+    /// Expand the template for one asset.
+    pub fn asset_url(&self, version: &str, asset_name: &str) -> String {
+        let template = if self.template.contains("{asset}") {
+            self.template.clone()
+        } else {
+            format!(
+                "{}/{{version}}/{{asset}}",
+                self.template.trim_end_matches('/')
+            )
+        };
+        template
+            .replace("{version}", version)
+            .replace("{asset}", asset_name)
+            .replace("{repo}", &self.repo)
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for HttpMirrorSource {
+    fn describe(&self) -> String {
+        format!("mirror {}", self.template)
+    }
+
+    async fn resolve_assets(
+        &self,
+        version: &str,
+        asset_names: &[&str],
+    ) -> Result<Vec<ResolvedAsset>> {
+        Ok(asset_names
+            .iter()
+            .map(|name| ResolvedAsset {
+                id: 0,
+                name: name.to_string(),
+                url: self.asset_url(version, name),
+                digest: None,
+            })
+            .collect())
+    }
+
+    async fn download_asset(&self, asset: &ResolvedAsset, chunks: usize) -> Result<Vec<u8>> {
+        if let Some(path) = asset.url.strip_prefix("file://") {
+            return fs::read(path).with_context(|| format!("Failed to read {}", asset.name));
+        }
+
+        let response = chunked::with_first_range(self.client.get(&asset.url), chunks)
+            .send()
+            .await
+            .with_context(|| format!("Failed to download {}", asset.name))?;
+
+        if !response.status().is_success() {
+            return Err(MirrorError::DownloadFailed {
+                asset_name: asset.name.clone(),
+                status_code: response.status().as_u16(),
+                url: asset.url.clone(),
+            }
+            .into());
+        }
+
+        chunked::read_response(response, || self.client.get(&asset.url), chunks).await
+    }
+
+    async fn asset_available(&self, asset: &ResolvedAsset) -> bool {
+        match asset.url.strip_prefix("file://") {
+            Some(path) => std::path::Path::new(path).is_file(),
+            None => head_available(&self.client, &asset.url).await,
+        }
+    }
+
+    /// `file://` mirrors are read from disk and send no requests.
+    fn plan_requests(&self, version: &str, asset_names: &[&str]) -> Vec<PlannedRequest> {
+        if self.template.starts_with("file://") {
+            return Vec::new();
+        }
+        let mut requests = Vec::new();
+        for name in asset_names {
+            let url = self.asset_url(version, name);
+            requests.push(PlannedRequest::new(
+                "HEAD",
+                url.clone(),
+                format!("Check that {} is available", name),
+            ));
+            requests.push(PlannedRequest::new(
+                "GET",
+                url,
+                format!("Download {}", name),
+            ));
+        }
+        requests
+    }
+}
+
+/// Probe a URL with a HEAD request.
+///
+/// Only 404 and 410 count as missing; servers that reject HEAD or fail
+/// transiently are given the benefit of the doubt.
+pub(crate) async fn head_available(client: &reqwest::Client, url: &str) -> bool {
+    match client.head(url).send().await {
+        Ok(response) => !matches!(
+            response.status(),
+            reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE
+        ),
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn asset_url_with_placeholders_expands_template() {
+        //* Given
+        let source = HttpMirrorSource::new(
+            "https://mirror.example.com/{repo}/releases/{version}/{asset}".to_string(),
+            "edgeandnode/amp".to_string(),
+            HttpTimeouts::default(),
+        )
+        .expect("should create mirror source");
+
+        //* When
+        let url = source.asset_url("v0.3.0", "ampd-linux-x86_64");
+
+        //* Then
+        assert_eq!(
+            url,
+            "https://mirror.example.com/edgeandnode/amp/releases/v0.3.0/ampd-linux-x86_64"
+        );
+    }
+
+    #[test]
+    fn asset_url_with_base_url_appends_version_and_asset() {
+        //* Given
+        let source = HttpMirrorSource::new(
+            "https://bucket.s3.amazonaws.com/amp/".to_string(),
+            "edgeandnode/amp".to_string(),
+            HttpTimeouts::default(),
+        )
+        .expect("should create mirror source");
+
+        //* When
+        let url = source.asset_url("v0.3.0", "ampctl-darwin-aarch64");
+
+        //* Then
+        assert_eq!(
+            url,
+            "https://bucket.s3.amazonaws.com/amp/v0.3.0/ampctl-darwin-aarch64"
+        );
+    }
+
+    #[tokio::test]
+    async fn fallback_source_with_failing_primary_downloads_from_mirror() {
+        //* Given
+        let empty = tempfile::tempdir().expect("should create temp directory");
+        let mirror = tempfile::tempdir().expect("should create temp directory");
+        fs::create_dir_all(mirror.path().join("v0.3.0")).expect("should create version directory");
+        fs::write(mirror.path().join("v0.3.0/ampd-linux-x86_64"), b"binary")
+            .expect("should write asset");
+        let source =
+            FallbackSource::new(vec![file_source(empty.path()), file_source(mirror.path())]);
+
+        //* When
+        let assets = source
+            .resolve_assets("v0.3.0", &["ampd-linux-x86_64"])
+            .await
+            .expect("should resolve assets");
+        let data = source
+            .download_asset(&assets[0], 1)
+            .await
+            .expect("should fall back to the mirror");
+
+        //* Then
+        assert_eq!(data, b"binary");
+    }
+
+    fn file_source(dir: &std::path::Path) -> Arc<dyn ReleaseSource> {
+        Arc::new(
+            HttpMirrorSource::new(
+                format!("file://{}", dir.display()),
+                "edgeandnode/amp".to_string(),
+                HttpTimeouts::default(),
+            )
+            .expect("should create mirror source"),
+        )
+    }
+
+    #[tokio::test]
+    async fn download_asset_with_file_url_reads_local_file() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let version_dir = dir.path().join("v0.3.0");
+        fs::create_dir_all(&version_dir).expect("should create version directory");
+        fs::write(version_dir.join("ampd-linux-x86_64"), b"binary").expect("should write asset");
+        let source = HttpMirrorSource::new(
+            format!("file://{}", dir.path().display()),
+            "edgeandnode/amp".to_string(),
+            HttpTimeouts::default(),
+        )
+        .expect("should create mirror source");
+
+        //* When
+        let assets = source
+            .resolve_assets("v0.3.0", &["ampd-linux-x86_64"])
+            .await
+            .expect("should resolve assets");
+        let data = source
+            .download_asset(&assets[0], 1)
+            .await
+            .expect("should read asset");
+
+        //* Then
+        assert_eq!(data, b"binary");
+    }
+}
@@ -0,0 +1,359 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::environment::Environment;
+
+/// Where the checkout is mapped to in the binaries' debug info and panic
+/// messages, so the path it was built in doesn't change them.
+const REMAPPED_SOURCE_DIR: &str = "/build/amp";
+
+/// Where the Cargo home (registry sources) is mapped to.
+const REMAPPED_CARGO_HOME: &str = "/cargo";
+
+/// Binaries a build produces and a recipe records the hashes of.
+pub const BINARIES: [&str; 2] = ["ampd", "ampctl"];
+
+#[derive(Debug)]
+pub enum ReproducibleError {
+    /// The checkout has no commit to pin the build to.
+    NotGitRepo { path: PathBuf },
+    /// The checkout has uncommitted changes a rebuild couldn't see.
+    DirtyTree { path: PathBuf },
+    /// The version wasn't built with `--reproducible`.
+    NoRecipe { version: String },
+    /// The rebuilt binaries differ from the recorded ones.
+    Mismatch {
+        version: String,
+        binaries: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for ReproducibleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotGitRepo { path } => {
+                writeln!(f, "Reproducible builds need a git checkout")?;
+                writeln!(f, "  Path: {}", path.display())?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  The build is pinned to a commit so it can be checked out again."
+                )?;
+            }
+            Self::DirtyTree { path } => {
+                writeln!(f, "Checkout has uncommitted changes")?;
+                writeln!(f, "  Path: {}", path.display())?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Commit or stash them; a rebuild only sees what is committed."
+                )?;
+            }
+            Self::NoRecipe { version } => {
+                writeln!(f, "Version has no reproducible build recipe")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Only versions built with 'ampup build --reproducible' can be rebuilt."
+                )?;
+            }
+            Self::Mismatch { version, binaries } => {
+                writeln!(f, "Rebuilt binaries differ from the recorded build")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Differing: {}", binaries.join(", "))?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  A differing rebuild usually means another toolchain or host target."
+                )?;
+                write!(
+                    f,
+                    "  A binary marked (installed) was changed after it was built."
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ReproducibleError {}
+
+/// Everything needed to rebuild a version's binaries bit for bit, recorded
+/// under `build` in its `manifest.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildRecipe {
+    /// Repository URL or local checkout the build was cloned from
+    pub source: String,
+    /// Full hash of the commit built
+    pub commit: String,
+    /// Commit time, exported as `SOURCE_DATE_EPOCH`
+    pub source_date_epoch: u64,
+    /// Toolchain the build is pinned to through `RUSTUP_TOOLCHAIN`
+    pub toolchain: String,
+    /// Compiler the toolchain resolved to, as `rustc --version` prints it
+    pub rustc: String,
+    /// Target the binaries were built for
+    pub host: String,
+    /// Arguments passed to cargo
+    pub cargo_args: Vec<String>,
+    /// SHA-256 of each binary built
+    #[serde(default)]
+    pub sha256: BTreeMap<String, String>,
+}
+
+impl BuildRecipe {
+    /// Pin a build of the checkout in `repo_path`, cloned from `source`, to
+    /// its HEAD commit and the toolchain it selects, running cargo with
+    /// `cargo_args` and `--locked`.
+    pub fn prepare(
+        source: &str,
+        repo_path: &Path,
+        mut cargo_args: Vec<String>,
+        env: &dyn Environment,
+    ) -> Result<Self> {
+        if !repo_path.join(".git").exists() {
+            return Err(ReproducibleError::NotGitRepo {
+                path: repo_path.to_path_buf(),
+            }
+            .into());
+        }
+        let status = git_output(repo_path, &["status", "--porcelain"], env)?;
+        if !status.is_empty() {
+            return Err(ReproducibleError::DirtyTree {
+                path: repo_path.to_path_buf(),
+            }
+            .into());
+        }
+        let commit = git_output(repo_path, &["rev-parse", "HEAD"], env)?;
+        if !cargo_args.iter().any(|arg| arg == "--locked") {
+            cargo_args.push("--locked".to_string());
+        }
+        let source_date_epoch = git_output(repo_path, &["log", "-1", "--format=%ct", "HEAD"], env)?
+            .parse()
+            .context("Failed to parse commit time")?;
+
+        // rustup picks the toolchain from the checkout's rust-toolchain file,
+        // if any, so ask rustc from within it
+        let rustc = rustc_version(repo_path, None, env)?;
+        let (release, host) = parse_rustc_version(&rustc)?;
+        let rustc = rustc.lines().next().unwrap_or_default().trim().to_string();
+        let toolchain = pin_toolchain(active_toolchain(repo_path, env).as_deref(), &release);
+
+        Ok(Self {
+            source: source.to_string(),
+            commit,
+            source_date_epoch,
+            toolchain,
+            rustc,
+            host,
+            cargo_args,
+            sha256: BTreeMap::new(),
+        })
+    }
+
+    /// Set up `command` (cargo, run in `repo_path`) to build with the
+    /// recipe's timestamp, toolchain and path remapping.
+    pub fn apply(&self, command: &mut Command, repo_path: &Path, env: &dyn Environment) {
+        command
+            .env("SOURCE_DATE_EPOCH", self.source_date_epoch.to_string())
+            .env("RUSTUP_TOOLCHAIN", &self.toolchain)
+            .env("CARGO_INCREMENTAL", "0")
+            .env(
+                "CARGO_ENCODED_RUSTFLAGS",
+                rustflags(repo_path, env).join("\x1f"),
+            )
+            .env_remove("RUSTFLAGS");
+    }
+
+    /// The cargo profile the recipe builds with.
+    pub fn profile(&self) -> &str {
+        let mut args = self.cargo_args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--release" | "-r" => return "release",
+                "--profile" => return args.next().map_or("dev", String::as_str),
+                arg => {
+                    if let Some(profile) = arg.strip_prefix("--profile=") {
+                        return profile;
+                    }
+                }
+            }
+        }
+        "dev"
+    }
+
+    /// Record the hashes of the binaries in `dir`.
+    pub fn record_binaries(&mut self, dir: &Path) -> Result<()> {
+        for binary in BINARIES {
+            self.sha256
+                .insert(binary.to_string(), sha256_file(&dir.join(binary))?);
+        }
+        Ok(())
+    }
+
+    /// The compiler and host the recipe's toolchain resolves to here, for
+    /// comparing with the recorded ones before a rebuild.
+    pub fn pinned_rustc(
+        &self,
+        repo_path: &Path,
+        env: &dyn Environment,
+    ) -> Result<(String, String)> {
+        let rustc = rustc_version(repo_path, Some(&self.toolchain), env)?;
+        let (_, host) = parse_rustc_version(&rustc)?;
+        let version = rustc.lines().next().unwrap_or_default().trim().to_string();
+        Ok((version, host))
+    }
+}
+
+/// Flags remapping the checkout and Cargo home to fixed paths.
+fn rustflags(repo_path: &Path, env: &dyn Environment) -> Vec<String> {
+    let mut flags = vec![format!(
+        "--remap-path-prefix={}={}",
+        repo_path.display(),
+        REMAPPED_SOURCE_DIR
+    )];
+    let cargo_home = env.var("CARGO_HOME").map(PathBuf::from).or_else(|| {
+        env.var("HOME")
+            .map(|home| PathBuf::from(home).join(".cargo"))
+    });
+    if let Some(cargo_home) = cargo_home {
+        flags.push(format!(
+            "--remap-path-prefix={}={}",
+            cargo_home.display(),
+            REMAPPED_CARGO_HOME
+        ));
+    }
+    flags
+}
+
+/// Release and host target from `rustc -vV` output.
+fn parse_rustc_version(output: &str) -> Result<(String, String)> {
+    let field = |name: &str| {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(": "))
+            .map(str::trim)
+    };
+    let release = field("release").context("rustc did not report its release")?;
+    let host = field("host").context("rustc did not report its host")?;
+    Ok((release.to_string(), host.to_string()))
+}
+
+/// The toolchain to pin: a stable toolchain as its release, so a later
+/// `stable` still selects the same compiler, and any other (a dated nightly,
+/// an explicit version) by its rustup name.
+fn pin_toolchain(active: Option<&str>, release: &str) -> String {
+    match active {
+        Some(name) if !name.starts_with("stable") => name.to_string(),
+        _ => release.to_string(),
+    }
+}
+
+/// Name of the rustup toolchain active in `repo_path`, if rustup is used.
+fn active_toolchain(repo_path: &Path, env: &dyn Environment) -> Option<String> {
+    let output = env
+        .command("rustup")
+        .args(["show", "active-toolchain"])
+        .current_dir(repo_path)
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    stdout.split_whitespace().next().map(str::to_string)
+}
+
+fn rustc_version(
+    repo_path: &Path,
+    toolchain: Option<&str>,
+    env: &dyn Environment,
+) -> Result<String> {
+    let mut command = env.command("rustc");
+    command.arg("-vV").current_dir(repo_path);
+    if let Some(toolchain) = toolchain {
+        command.env("RUSTUP_TOOLCHAIN", toolchain);
+    }
+    let output = command
+        .stderr(Stdio::inherit())
+        .output()
+        .context("Failed to execute rustc -vV")?;
+    if !output.status.success() {
+        anyhow::bail!("rustc -vV failed with {}", output.status);
+    }
+    String::from_utf8(output.stdout).context("Failed to parse rustc output")
+}
+
+fn git_output(repo_path: &Path, args: &[&str], env: &dyn Environment) -> Result<String> {
+    let output = env
+        .command("git")
+        .args(args)
+        .current_dir(repo_path)
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("Failed to execute git {}", args[0]))?;
+    if !output.status.success() {
+        anyhow::bail!("git {} failed with {}", args[0], output.status);
+    }
+    Ok(String::from_utf8(output.stdout)
+        .context("Failed to parse git output")?
+        .trim()
+        .to_string())
+}
+
+/// Hex SHA-256 of the file at `path`, read in chunks rather than loaded
+/// whole.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path).context("Failed to open binary")?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).context("Failed to read binary")?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::MapEnv;
+
+    #[test]
+    fn pin_toolchain_with_stable_or_dated_nightly_pins_exact_compiler() {
+        //* When
+        let stable = pin_toolchain(Some("stable-x86_64-unknown-linux-gnu"), "1.86.0");
+        let nightly = pin_toolchain(
+            Some("nightly-2025-04-02-aarch64-apple-darwin"),
+            "1.88.0-nightly",
+        );
+        let without_rustup = pin_toolchain(None, "1.86.0");
+
+        //* Then
+        assert_eq!(stable, "1.86.0");
+        assert_eq!(nightly, "nightly-2025-04-02-aarch64-apple-darwin");
+        assert_eq!(without_rustup, "1.86.0");
+    }
+
+    #[test]
+    fn rustflags_with_cargo_home_remaps_both_to_fixed_paths() {
+        //* Given
+        let env = MapEnv::new().with("CARGO_HOME", "/opt/cargo");
+
+        //* When
+        let flags = rustflags(Path::new("/tmp/.tmpX1y2/amp"), &env);
+
+        //* Then
+        assert_eq!(
+            flags,
+            vec![
+                "--remap-path-prefix=/tmp/.tmpX1y2/amp=/build/amp".to_string(),
+                "--remap-path-prefix=/opt/cargo=/cargo".to_string(),
+            ]
+        );
+    }
+}
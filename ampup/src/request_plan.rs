@@ -0,0 +1,134 @@
+/// Credentials a planned request carries, printed as a reference to the
+/// token instead of its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlannedAuth {
+    /// `Authorization: Bearer <token>`
+    Bearer,
+    /// HTTP basic auth with `<token>` as the password of `user`
+    Basic { user: &'static str },
+}
+
+/// An HTTP request an operation would send, as printed by
+/// `ampup debug requests`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedRequest {
+    pub method: &'static str,
+    /// Request URL; parts only known once earlier responses arrive are shown
+    /// as `<placeholders>`
+    pub url: String,
+    /// Headers other than credentials
+    pub headers: Vec<(String, String)>,
+    pub auth: Option<PlannedAuth>,
+    /// JSON request body
+    pub body: Option<String>,
+    /// What the request is for
+    pub purpose: String,
+}
+
+impl PlannedRequest {
+    pub fn new(method: &'static str, url: impl Into<String>, purpose: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: vec![("User-Agent".to_string(), "ampup".to_string())],
+            auth: None,
+            body: None,
+            purpose: purpose.into(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_auth(mut self, auth: Option<PlannedAuth>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    pub fn with_body(mut self, body: String) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    /// A curl command sending the same request, reading the token from
+    /// `$GITHUB_TOKEN`.
+    pub fn to_curl(&self) -> String {
+        let mut args = vec!["curl".to_string(), "-sSL".to_string()];
+        match self.method {
+            "GET" => {}
+            "HEAD" => args.push("-I".to_string()),
+            method => args.extend(["-X".to_string(), method.to_string()]),
+        }
+        for (name, value) in &self.headers {
+            args.extend([
+                "-H".to_string(),
+                shell_quote(&format!("{}: {}", name, value)),
+            ]);
+        }
+        match self.auth {
+            Some(PlannedAuth::Bearer) => args.extend([
+                "-H".to_string(),
+                "\"Authorization: Bearer $GITHUB_TOKEN\"".to_string(),
+            ]),
+            Some(PlannedAuth::Basic { user }) => {
+                args.extend(["-u".to_string(), format!("\"{}:$GITHUB_TOKEN\"", user)])
+            }
+            None => {}
+        }
+        if let Some(body) = &self.body {
+            args.extend(["--data".to_string(), shell_quote(body)]);
+        }
+        args.push(shell_quote(&self.url));
+        args.join(" ")
+    }
+}
+
+/// Quote `value` as a single shell word.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_curl_with_bearer_auth_references_token_variable() {
+        //* Given
+        let request = PlannedRequest::new(
+            "GET",
+            "https://api.github.com/repos/edgeandnode/amp/releases/assets/<asset id>",
+            "Download ampd-linux-x86_64",
+        )
+        .with_header("Accept", "application/octet-stream")
+        .with_auth(Some(PlannedAuth::Bearer));
+
+        //* When
+        let curl = request.to_curl();
+
+        //* Then
+        assert_eq!(
+            curl,
+            "curl -sSL -H 'User-Agent: ampup' -H 'Accept: application/octet-stream' \
+             -H \"Authorization: Bearer $GITHUB_TOKEN\" \
+             'https://api.github.com/repos/edgeandnode/amp/releases/assets/<asset id>'"
+        );
+    }
+
+    #[test]
+    fn to_curl_with_head_and_quote_in_url_escapes_it() {
+        //* Given
+        let request = PlannedRequest::new("HEAD", "https://mirror.example.com/it's", "Probe");
+
+        //* When
+        let curl = request.to_curl();
+
+        //* Then
+        assert_eq!(
+            curl,
+            "curl -sSL -I -H 'User-Agent: ampup' 'https://mirror.example.com/it'\\''s'"
+        );
+    }
+}
@@ -0,0 +1,573 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::Result;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    build_label::LabelScheme,
+    builder::BuildSource,
+    github::{GitHubClient, ReleaseSummary},
+    policy::Policy,
+    request_plan::PlannedRequest,
+};
+
+#[derive(Debug)]
+pub enum ResolveError {
+    InvalidSpec { spec: String, reason: String },
+    NoMatchingRelease { spec: String, repo: String },
+    NotARelease { spec: String, hint: String },
+    Yanked { version: String, repo: String },
+    Prerelease { version: String, repo: String },
+}
+
+impl std::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidSpec { spec, reason } => {
+                writeln!(f, "Invalid version specification")?;
+                writeln!(f, "  Spec: {}", spec)?;
+                writeln!(f, "  Reason: {}", reason)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Expected a tag (v0.3.0), a semver range (0.3, ^0.2), latest, a channel"
+                )?;
+                writeln!(
+                    f,
+                    "  (stable, beta/prerelease, nightly), pr:<number> or commit:<sha>."
+                )?;
+            }
+            Self::NoMatchingRelease { spec, repo } => {
+                writeln!(f, "No release matches the version specification")?;
+                writeln!(f, "  Spec: {}", spec)?;
+                writeln!(f, "  Repository: {}", repo)?;
+                writeln!(f)?;
+                writeln!(f, "  Only the 100 most recent releases are considered.")?;
+            }
+            Self::NotARelease { spec, hint } => {
+                writeln!(f, "Version specification refers to source, not a release")?;
+                writeln!(f, "  Spec: {}", spec)?;
+                writeln!(f)?;
+                writeln!(f, "  Try: {}", hint)?;
+            }
+            Self::Yanked { version, repo } => {
+                writeln!(f, "Release has been yanked")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Repository: {}", repo)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Yanked releases were withdrawn by the maintainers and should not be used."
+                )?;
+                writeln!(
+                    f,
+                    "  To install it anyway: ampup install {} --force",
+                    version
+                )?;
+            }
+            Self::Prerelease { version, repo } => {
+                writeln!(f, "Release is a prerelease")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Repository: {}", repo)?;
+                writeln!(f)?;
+                writeln!(f, "  Prereleases are only installed when asked for.")?;
+                writeln!(f, "  To install it anyway, pass --allow-prerelease.")?;
+                writeln!(f, "  To track prereleases: ampup install prerelease")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+/// Release channels that can be tracked instead of a fixed version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    /// Newest non-prerelease release
+    Stable,
+    /// Newest release, including pre-releases
+    #[serde(alias = "prerelease")]
+    #[value(alias = "prerelease")]
+    Beta,
+    /// Newest release tagged `nightly*`
+    Nightly,
+}
+
+impl Channel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stable => "stable",
+            Self::Beta => "beta",
+            Self::Nightly => "nightly",
+        }
+    }
+}
+
+/// A user-supplied version specification
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionSpec {
+    /// The release GitHub marks as latest
+    Latest,
+    /// The newest release on a channel
+    Channel(Channel),
+    /// An exact release tag, used verbatim
+    Exact(String),
+    /// The newest release whose tag satisfies a semver requirement
+    Range(VersionReq),
+    /// The head commit of a pull request (source only)
+    Pr(u32),
+    /// A specific commit (source only)
+    Commit(String),
+}
+
+impl VersionSpec {
+    /// Whether resolving this spec requires querying the release API.
+    pub fn needs_lookup(&self) -> bool {
+        !matches!(self, Self::Exact(_))
+    }
+
+    /// Whether the spec opts into prereleases by tracking a channel that
+    /// publishes them.
+    pub fn tracks_prereleases(&self) -> bool {
+        matches!(self, Self::Channel(Channel::Beta | Channel::Nightly))
+    }
+
+    /// This spec with `latest` widened to the newest release including
+    /// prereleases when `include` is set, since GitHub's latest release
+    /// never is one. Other specs are returned unchanged.
+    pub fn including_prereleases(self, include: bool) -> Self {
+        match self {
+            Self::Latest if include => Self::Channel(Channel::Beta),
+            spec => spec,
+        }
+    }
+}
+
+impl FromStr for VersionSpec {
+    type Err = ResolveError;
+
+    /// Parse a version spec.
+    ///
+    /// Bare `X.Y.Z` is treated as `=X.Y.Z` rather than an exact tag so it
+    /// matches `vX.Y.Z` tags, while `vX.Y.Z` and any other unrecognized string
+    /// are used verbatim as tags.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let spec = spec.trim();
+        let invalid = |reason: &str| ResolveError::InvalidSpec {
+            spec: spec.to_string(),
+            reason: reason.to_string(),
+        };
+
+        match spec {
+            "" => return Err(invalid("empty specification")),
+            "latest" => return Ok(Self::Latest),
+            "stable" => return Ok(Self::Channel(Channel::Stable)),
+            "beta" | "prerelease" => return Ok(Self::Channel(Channel::Beta)),
+            "nightly" => return Ok(Self::Channel(Channel::Nightly)),
+            _ => {}
+        }
+
+        if let Some(number) = spec.strip_prefix("pr:") {
+            let number = number
+                .parse()
+                .map_err(|_| invalid("pull request number must be a positive integer"))?;
+            return Ok(Self::Pr(number));
+        }
+
+        if let Some(commit) = spec.strip_prefix("commit:") {
+            if commit.len() < 4 || !commit.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(invalid("commit must be at least 4 hexadecimal characters"));
+            }
+            return Ok(Self::Commit(commit.to_string()));
+        }
+
+        if let Some(rest) = spec.strip_prefix('v')
+            && Version::parse(rest).is_ok()
+        {
+            return Ok(Self::Exact(spec.to_string()));
+        }
+
+        if Version::parse(spec).is_ok() {
+            let req = VersionReq::parse(&format!("={}", spec))
+                .map_err(|err| invalid(&err.to_string()))?;
+            return Ok(Self::Range(req));
+        }
+
+        let unprefixed = match spec.strip_prefix('v') {
+            Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => rest,
+            _ => spec,
+        };
+        if let Ok(req) = VersionReq::parse(unprefixed) {
+            return Ok(Self::Range(req));
+        }
+
+        Ok(Self::Exact(spec.to_string()))
+    }
+}
+
+impl fmt::Display for VersionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Latest => write!(f, "latest"),
+            Self::Channel(channel) => write!(f, "{}", channel.as_str()),
+            Self::Exact(tag) => write!(f, "{}", tag),
+            Self::Range(req) => write!(f, "{}", req),
+            Self::Pr(number) => write!(f, "pr:{}", number),
+            Self::Commit(commit) => write!(f, "commit:{}", commit),
+        }
+    }
+}
+
+/// The concrete target a [`VersionSpec`] resolved to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    /// A published release
+    Release { tag: String },
+    /// A source revision that must be built with `ampup build`
+    Source {
+        /// Version key `ampup build` would install it under (e.g.
+        /// `edgeandnode-amp/pr-42-abc1234`)
+        label: String,
+        /// `owner/repo` containing the commit
+        repo: String,
+        /// Full commit SHA
+        sha: String,
+    },
+}
+
+/// Resolves version specs against a repository's releases
+pub struct VersionResolver<'a> {
+    github: &'a GitHubClient,
+    label_scheme: LabelScheme,
+}
+
+impl<'a> VersionResolver<'a> {
+    pub fn new(github: &'a GitHubClient) -> Self {
+        Self {
+            github,
+            label_scheme: LabelScheme::default(),
+        }
+    }
+
+    /// Label source builds with `scheme`, as `ampup build` would.
+    pub fn with_label_scheme(mut self, scheme: LabelScheme) -> Self {
+        self.label_scheme = scheme;
+        self
+    }
+
+    /// Resolve a spec to a release tag or source revision.
+    pub async fn resolve(&self, spec: &VersionSpec) -> Result<Resolution> {
+        let repo = self.github.repo().to_string();
+        match spec {
+            VersionSpec::Pr(number) => {
+                let head = self.github.get_pull_request(*number).await?;
+                let source = BuildSource::Pr {
+                    repo,
+                    number: *number,
+                    head: Some(head.clone()),
+                };
+                Ok(Resolution::Source {
+                    label: source.generate_version_key(
+                        Some(short_sha(&head.sha)),
+                        None,
+                        &self.label_scheme,
+                    )?,
+                    repo: head.repo,
+                    sha: head.sha,
+                })
+            }
+            VersionSpec::Commit(commit) => {
+                let sha = self.github.get_commit_sha(commit).await?;
+                let source = BuildSource::Commit {
+                    repo: repo.clone(),
+                    commit: commit.clone(),
+                };
+                Ok(Resolution::Source {
+                    label: source.generate_version_key(None, None, &self.label_scheme)?,
+                    repo,
+                    sha,
+                })
+            }
+            _ => Ok(Resolution::Release {
+                tag: self.resolve_release(spec).await?,
+            }),
+        }
+    }
+
+    /// Requests [`Self::resolve_release`] sends for `spec`, for
+    /// `ampup debug requests`. Exact tags need none.
+    pub fn plan_release(&self, spec: &VersionSpec) -> Result<Vec<PlannedRequest>> {
+        Ok(match spec {
+            VersionSpec::Exact(_) => Vec::new(),
+            VersionSpec::Latest | VersionSpec::Channel(Channel::Stable) => {
+                self.github.plan_latest_version()
+            }
+            VersionSpec::Channel(_) | VersionSpec::Range(_) => self.github.plan_list_releases(),
+            VersionSpec::Pr(number) => {
+                return Err(ResolveError::NotARelease {
+                    spec: spec.to_string(),
+                    hint: format!("ampup build --repo {} --pr {}", self.github.repo(), number),
+                }
+                .into());
+            }
+            VersionSpec::Commit(commit) => {
+                return Err(ResolveError::NotARelease {
+                    spec: spec.to_string(),
+                    hint: format!(
+                        "ampup build --repo {} --commit {}",
+                        self.github.repo(),
+                        commit
+                    ),
+                }
+                .into());
+            }
+        })
+    }
+
+    /// Whether the release tagged `version`, resolved from `spec`, is a
+    /// prerelease, falling back to its tag when the release can't be
+    /// fetched.
+    ///
+    /// Fails with [`ResolveError::Prerelease`] unless `allow` is set or
+    /// `spec` tracks prereleases and `policy` accepts that as opting in.
+    pub async fn check_prerelease(
+        &self,
+        spec: &VersionSpec,
+        version: &str,
+        allow: bool,
+        policy: &Policy,
+    ) -> Result<bool> {
+        let prerelease = match self.github.is_prerelease(version).await {
+            Ok(prerelease) => prerelease,
+            Err(_) => tag_version(version).is_some_and(|v| !v.pre.is_empty()),
+        };
+        let opted_in = allow || (spec.tracks_prereleases() && !policy.explicit_prereleases);
+        if prerelease && !opted_in {
+            return Err(ResolveError::Prerelease {
+                version: version.to_string(),
+                repo: self.github.repo().to_string(),
+            }
+            .into());
+        }
+        Ok(prerelease)
+    }
+
+    /// Resolve a spec that must name a published release.
+    ///
+    /// Fails with [`ResolveError::NotARelease`] for `pr:` and `commit:` specs.
+    pub async fn resolve_release(&self, spec: &VersionSpec) -> Result<String> {
+        let repo = self.github.repo();
+        let tag = match spec {
+            VersionSpec::Exact(tag) => Some(tag.clone()),
+            VersionSpec::Latest | VersionSpec::Channel(Channel::Stable) => {
+                Some(self.github.get_latest_version().await?)
+            }
+            VersionSpec::Channel(Channel::Beta) => {
+                let releases = self.github.list_releases().await?;
+                newest_matching(&releases, |_| true)
+            }
+            VersionSpec::Channel(Channel::Nightly) => {
+                let releases = self.github.list_releases().await?;
+                releases
+                    .into_iter()
+                    .find(|r| r.tag.starts_with("nightly"))
+                    .map(|r| r.tag)
+            }
+            VersionSpec::Range(req) => {
+                let releases = self.github.list_releases().await?;
+                newest_matching(&releases, |v| req.matches(v))
+            }
+            VersionSpec::Pr(number) => {
+                return Err(ResolveError::NotARelease {
+                    spec: spec.to_string(),
+                    hint: format!("ampup build --repo {} --pr {}", repo, number),
+                }
+                .into());
+            }
+            VersionSpec::Commit(commit) => {
+                return Err(ResolveError::NotARelease {
+                    spec: spec.to_string(),
+                    hint: format!("ampup build --repo {} --commit {}", repo, commit),
+                }
+                .into());
+            }
+        };
+
+        tag.ok_or_else(|| {
+            ResolveError::NoMatchingRelease {
+                spec: spec.to_string(),
+                repo: repo.to_string(),
+            }
+            .into()
+        })
+    }
+}
+
+/// Parse a release tag as semver, ignoring a leading `v`.
+pub fn tag_version(tag: &str) -> Option<Version> {
+    Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()
+}
+
+/// Pick the highest-versioned release whose tag satisfies `predicate`.
+///
+/// Tags that are not valid semver are ignored.
+fn newest_matching(
+    releases: &[ReleaseSummary],
+    predicate: impl Fn(&Version) -> bool,
+) -> Option<String> {
+    releases
+        .iter()
+        .filter_map(|r| tag_version(&r.tag).map(|v| (v, &r.tag)))
+        .filter(|(v, _)| predicate(v))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, tag)| tag.clone())
+}
+
+fn short_sha(sha: &str) -> &str {
+    &sha[..8.min(sha.len())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod parse {
+        use super::*;
+
+        #[test]
+        fn from_str_with_v_prefixed_version_returns_exact() {
+            //* When
+            let spec: VersionSpec = "v0.3.0".parse().expect("should parse");
+
+            //* Then
+            assert_eq!(spec, VersionSpec::Exact("v0.3.0".to_string()));
+        }
+
+        #[test]
+        fn from_str_with_bare_version_returns_exact_range() {
+            //* When
+            let spec: VersionSpec = "0.3.0".parse().expect("should parse");
+
+            //* Then
+            let VersionSpec::Range(req) = spec else {
+                panic!("bare version should parse as a range, got {:?}", spec);
+            };
+            assert!(req.matches(&Version::new(0, 3, 0)));
+            assert!(
+                !req.matches(&Version::new(0, 3, 1)),
+                "bare version should only match itself"
+            );
+        }
+
+        #[test]
+        fn from_str_with_partial_version_returns_caret_range() {
+            //* When
+            let spec: VersionSpec = "0.3".parse().expect("should parse");
+
+            //* Then
+            let VersionSpec::Range(req) = spec else {
+                panic!("partial version should parse as a range, got {:?}", spec);
+            };
+            assert!(req.matches(&Version::new(0, 3, 7)));
+            assert!(!req.matches(&Version::new(0, 4, 0)));
+        }
+
+        #[test]
+        fn from_str_with_source_prefixes_returns_source_specs() {
+            //* When
+            let pr: VersionSpec = "pr:123".parse().expect("should parse pr");
+            let commit: VersionSpec = "commit:abc123".parse().expect("should parse commit");
+
+            //* Then
+            assert_eq!(pr, VersionSpec::Pr(123));
+            assert_eq!(commit, VersionSpec::Commit("abc123".to_string()));
+        }
+
+        #[test]
+        fn from_str_with_prerelease_returns_beta_channel() {
+            //* When
+            let spec: VersionSpec = "prerelease".parse().expect("should parse");
+
+            //* Then
+            assert_eq!(spec, VersionSpec::Channel(Channel::Beta));
+            assert!(spec.tracks_prereleases());
+            assert!(
+                !VersionSpec::Exact("v0.5.0-rc.1".to_string()).tracks_prereleases(),
+                "an exact tag should need --allow-prerelease"
+            );
+        }
+
+        #[test]
+        fn including_prereleases_with_latest_returns_beta_channel() {
+            //* When
+            let latest = VersionSpec::Latest.including_prereleases(true);
+            let range: VersionSpec = "0.3".parse().expect("should parse");
+
+            //* Then
+            assert_eq!(latest, VersionSpec::Channel(Channel::Beta));
+            assert_eq!(
+                VersionSpec::Latest.including_prereleases(false),
+                VersionSpec::Latest
+            );
+            assert_eq!(range.clone().including_prereleases(true), range);
+        }
+
+        #[test]
+        fn from_str_with_non_hex_commit_fails() {
+            //* When
+            let result = "commit:main".parse::<VersionSpec>();
+
+            //* Then
+            assert!(
+                matches!(result, Err(ResolveError::InvalidSpec { .. })),
+                "non-hex commit should be rejected"
+            );
+        }
+    }
+
+    mod newest_matching {
+        use super::*;
+
+        fn releases(tags: &[&str]) -> Vec<ReleaseSummary> {
+            tags.iter()
+                .map(|tag| ReleaseSummary {
+                    tag: tag.to_string(),
+                    prerelease: tag.contains('-'),
+                })
+                .collect()
+        }
+
+        #[test]
+        fn newest_matching_with_range_returns_highest_match() {
+            //* Given
+            let releases = releases(&["v0.4.0", "v0.2.9", "v0.2.10", "v0.1.0", "nightly"]);
+            let req = VersionReq::parse("^0.2").expect("should parse requirement");
+
+            //* When
+            let tag = newest_matching(&releases, |v| req.matches(v));
+
+            //* Then
+            assert_eq!(
+                tag.as_deref(),
+                Some("v0.2.10"),
+                "should compare by semver, not by string or list order"
+            );
+        }
+
+        #[test]
+        fn newest_matching_with_no_match_returns_none() {
+            //* Given
+            let releases = releases(&["v0.4.0"]);
+            let req = VersionReq::parse("^0.2").expect("should parse requirement");
+
+            //* When
+            let tag = newest_matching(&releases, |v| req.matches(v));
+
+            //* Then
+            assert_eq!(tag, None);
+        }
+    }
+}
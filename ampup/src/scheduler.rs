@@ -0,0 +1,159 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use tokio::sync::{AcquireError, OwnedSemaphorePermit, Semaphore};
+
+/// Central gate for download slots, shared by every download in a batch.
+///
+/// Caps the total number of concurrent downloads and the number per host, so
+/// a batch spanning several mirrors or installs can't open unbounded
+/// connections or let one slow host take every slot. Waiters are served in
+/// FIFO order per host.
+pub struct DownloadScheduler {
+    global: Arc<Semaphore>,
+    per_host: Mutex<HashMap<String, Arc<Semaphore>>>,
+    max_per_host: usize,
+    queued: AtomicUsize,
+}
+
+/// A download slot; released when dropped.
+pub struct DownloadSlot {
+    _host: OwnedSemaphorePermit,
+    _global: OwnedSemaphorePermit,
+}
+
+impl DownloadScheduler {
+    /// Create a scheduler allowing `max_concurrent` downloads overall and
+    /// `max_per_host` per host. Both are clamped to a minimum of 1.
+    pub fn new(max_concurrent: usize, max_per_host: usize) -> Self {
+        Self {
+            global: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            per_host: Mutex::new(HashMap::new()),
+            max_per_host: max_per_host.max(1),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    /// Number of downloads currently waiting for a slot.
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Wait for a slot to download from `url`.
+    ///
+    /// The host slot is taken first so waiters for a saturated host don't
+    /// hold global slots that other hosts could use.
+    pub async fn acquire(&self, url: &str) -> Result<DownloadSlot, AcquireError> {
+        let host = self.host_semaphore(host_of(url));
+
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        // Leave the queue even if the waiting task is cancelled
+        let _queued = QueueGuard(&self.queued);
+
+        let host = host.acquire_owned().await?;
+        let global = self.global.clone().acquire_owned().await?;
+        Ok(DownloadSlot {
+            _host: host,
+            _global: global,
+        })
+    }
+
+    fn host_semaphore(&self, host: &str) -> Arc<Semaphore> {
+        self.per_host
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_per_host)))
+            .clone()
+    }
+}
+
+struct QueueGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Host part of a URL, or the whole string for URLs without one (e.g.
+/// `file://` paths share a single slot pool).
+fn host_of(url: &str) -> &str {
+    let Some((_, rest)) = url.split_once("://") else {
+        return url;
+    };
+    rest.split('/').next().unwrap_or(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn acquire_with_saturated_host_lets_other_hosts_proceed() {
+        //* Given
+        let scheduler = DownloadScheduler::new(4, 1);
+        let _held = scheduler
+            .acquire("https://github.com/a")
+            .await
+            .expect("should acquire first slot");
+
+        //* When
+        let same_host = tokio::time::timeout(
+            Duration::from_millis(50),
+            scheduler.acquire("https://github.com/b"),
+        )
+        .await;
+        let other_host = tokio::time::timeout(
+            Duration::from_millis(50),
+            scheduler.acquire("https://mirror.example.com/b"),
+        )
+        .await;
+
+        //* Then
+        assert!(
+            same_host.is_err(),
+            "second slot on the same host should queue"
+        );
+        assert!(other_host.is_ok(), "another host should not be blocked");
+    }
+
+    #[tokio::test]
+    async fn acquire_with_global_cap_reached_queues_until_slot_released() {
+        //* Given
+        let scheduler = Arc::new(DownloadScheduler::new(1, 4));
+        let held = scheduler
+            .acquire("https://github.com/a")
+            .await
+            .expect("should acquire first slot");
+        let waiter = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move {
+                scheduler
+                    .acquire("https://mirror.example.com/b")
+                    .await
+                    .is_ok()
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(
+            scheduler.queued(),
+            1,
+            "waiter should be visible in the queue"
+        );
+
+        //* When
+        drop(held);
+
+        //* Then
+        assert!(waiter.await.expect("waiter should not panic"));
+        assert_eq!(scheduler.queued(), 0);
+    }
+}
@@ -0,0 +1,246 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::{environment::Environment, platform::Platform};
+
+/// Prefix of launchd job labels (e.g. `sh.ampup.ampd`).
+const LAUNCHD_LABEL_PREFIX: &str = "sh.ampup.";
+
+/// When the service manager restarts ampd after it exits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum RestartPolicy {
+    /// Restart whenever ampd exits
+    Always,
+    /// Restart only when ampd exits with an error or is killed by a signal
+    #[default]
+    OnFailure,
+    /// Never restart
+    No,
+}
+
+impl RestartPolicy {
+    fn as_systemd(&self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::OnFailure => "on-failure",
+            Self::No => "no",
+        }
+    }
+}
+
+/// A service running an ampup-managed ampd.
+#[derive(Debug, Clone)]
+pub struct ServiceSpec {
+    /// Service name: `ampd`, or `ampd-<instance>` for a pinned instance
+    pub name: String,
+    /// Executable the service runs, such as the `~/.amp/bin/ampd` shim so
+    /// `ampup use` switches the version on the next restart
+    pub program: PathBuf,
+    /// Arguments passed to the program
+    pub args: Vec<String>,
+    pub restart: RestartPolicy,
+}
+
+impl ServiceSpec {
+    /// Render the service definition for `platform`'s service manager.
+    pub fn render(&self, platform: Platform) -> String {
+        match platform {
+            Platform::Linux => self.systemd_unit(),
+            Platform::Darwin => self.launchd_plist(),
+        }
+    }
+
+    /// Where `platform`'s service manager looks for this user service:
+    /// `$XDG_CONFIG_HOME/systemd/user/<name>.service` (default
+    /// `~/.config/systemd/user`) or `~/Library/LaunchAgents/sh.ampup.<name>.plist`.
+    pub fn install_path(&self, platform: Platform, env: &dyn Environment) -> Result<PathBuf> {
+        let home = || {
+            env.var("HOME")
+                .map(PathBuf::from)
+                .context("Could not determine home directory")
+        };
+        Ok(match platform {
+            Platform::Linux => env
+                .var("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .map_or_else(|| home().map(|h| h.join(".config")), Ok)?
+                .join("systemd/user")
+                .join(format!("{}.service", self.name)),
+            Platform::Darwin => home()?
+                .join("Library/LaunchAgents")
+                .join(format!("{}.plist", self.launchd_label())),
+        })
+    }
+
+    fn launchd_label(&self) -> String {
+        format!("{}{}", LAUNCHD_LABEL_PREFIX, self.name)
+    }
+
+    fn systemd_unit(&self) -> String {
+        let mut exec_start = vec![systemd_quote(&self.program.to_string_lossy())];
+        exec_start.extend(self.args.iter().map(|arg| systemd_quote(arg)));
+
+        format!(
+            "\
+# Generated by ampup; regenerate with `ampup generate systemd`
+[Unit]
+Description=Amp daemon ({name})
+After=network-online.target
+Wants=network-online.target
+
+[Service]
+Type=simple
+ExecStart={exec_start}
+Restart={restart}
+RestartSec=5
+
+[Install]
+WantedBy=default.target
+",
+            name = self.name,
+            exec_start = exec_start.join(" "),
+            restart = self.restart.as_systemd(),
+        )
+    }
+
+    fn launchd_plist(&self) -> String {
+        let mut arguments = String::new();
+        for arg in std::iter::once(self.program.to_string_lossy().to_string())
+            .chain(self.args.iter().cloned())
+        {
+            arguments.push_str(&format!("        <string>{}</string>\n", xml_escape(&arg)));
+        }
+        let keep_alive = match self.restart {
+            RestartPolicy::Always => "<true/>".to_string(),
+            RestartPolicy::OnFailure => {
+                "<dict>\n        <key>SuccessfulExit</key>\n        <false/>\n    </dict>"
+                    .to_string()
+            }
+            RestartPolicy::No => "<false/>".to_string(),
+        };
+
+        format!(
+            "\
+<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!-- Generated by ampup; regenerate with `ampup generate systemd` -->
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
+<plist version=\"1.0\">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+{arguments}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    {keep_alive}
+</dict>
+</plist>
+",
+            label = xml_escape(&self.launchd_label()),
+        )
+    }
+}
+
+/// Quote an `ExecStart=` word for systemd, escaping specifiers (`%`).
+fn systemd_quote(value: &str) -> String {
+    let value = value.replace('%', "%%");
+    if !value.is_empty() && !value.contains([' ', '\t', '"', '\'', '\\', '$', ';']) {
+        return value;
+    }
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('$', "$$")
+    )
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::MapEnv;
+
+    fn spec() -> ServiceSpec {
+        ServiceSpec {
+            name: "ampd".to_string(),
+            program: PathBuf::from("/home/amp/.amp/bin/ampd"),
+            args: vec![
+                "--config".to_string(),
+                "/etc/amp/my config.toml".to_string(),
+            ],
+            restart: RestartPolicy::OnFailure,
+        }
+    }
+
+    #[test]
+    fn render_with_linux_quotes_exec_start_arguments() {
+        //* Given
+        let spec = spec();
+
+        //* When
+        let unit = spec.render(Platform::Linux);
+
+        //* Then
+        assert!(
+            unit.contains(
+                "ExecStart=/home/amp/.amp/bin/ampd --config \"/etc/amp/my config.toml\"\n"
+            )
+        );
+        assert!(unit.contains("Restart=on-failure\n"));
+        assert!(unit.contains("WantedBy=default.target\n"));
+    }
+
+    #[test]
+    fn render_with_darwin_lists_program_arguments_and_keep_alive() {
+        //* Given
+        let spec = ServiceSpec {
+            restart: RestartPolicy::Always,
+            ..spec()
+        };
+
+        //* When
+        let plist = spec.render(Platform::Darwin);
+
+        //* Then
+        assert!(plist.contains("<string>sh.ampup.ampd</string>"));
+        assert!(plist.contains(
+            "        <string>/home/amp/.amp/bin/ampd</string>\n        <string>--config</string>\n"
+        ));
+        assert!(plist.contains("<key>KeepAlive</key>\n    <true/>"));
+    }
+
+    #[test]
+    fn install_path_with_xdg_config_home_uses_it_for_systemd() -> Result<()> {
+        //* Given
+        let env = MapEnv::new()
+            .with("HOME", "/home/amp")
+            .with("XDG_CONFIG_HOME", "/home/amp/.xdg");
+
+        //* When
+        let linux = spec().install_path(Platform::Linux, &env)?;
+        let darwin = spec().install_path(Platform::Darwin, &env)?;
+
+        //* Then
+        assert_eq!(
+            linux,
+            PathBuf::from("/home/amp/.xdg/systemd/user/ampd.service")
+        );
+        assert_eq!(
+            darwin,
+            PathBuf::from("/home/amp/Library/LaunchAgents/sh.ampup.ampd.plist")
+        );
+        Ok(())
+    }
+}
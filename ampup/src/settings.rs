@@ -0,0 +1,958 @@
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DEFAULT_REPO, DEFAULT_SELF_REPO,
+    artifact_cache::{ArtifactCache, CacheCompression, Zstd},
+    build_label::{LabelError, LabelScheme, PLACEHOLDERS},
+    config::Config,
+    default_command::DefaultCommand,
+    endpoint::MetadataEndpoint,
+    environment::ProcessEnv,
+    github::{ClientOptions, GitHubClient, HttpTimeouts},
+    health, latest_cache,
+    policy::{HostEnvironment, Policy},
+    preallocate,
+    resolver::Channel,
+    token::{self, TokenLookup},
+    ui::Theme,
+};
+
+/// Keys accepted by `ampup config`, with a short description of each.
+pub const KEYS: &[(&str, &str)] = &[
+    (
+        "repo",
+        "Default repository for install, update, build and resolve",
+    ),
+    ("self_repo", "Default repository for self update"),
+    (
+        "environment",
+        "Deployment environment of this host: production (enables guard rails), staging or dev",
+    ),
+    (
+        "default_command",
+        "What ampup does without a command: update, help or status",
+    ),
+    (
+        "data_dir",
+        "ampd data directory on this host, checked for incompatible versions on switches",
+    ),
+    (
+        "source",
+        "Where to download binaries: github, oci://<registry>/<repo> or a mirror URL",
+    ),
+    (
+        "mirrors",
+        "Comma-separated fallback sources tried in order when the source fails",
+    ),
+    (
+        "api_url",
+        "GitHub Enterprise REST API URL (e.g. https://github.example.com/api/v3)",
+    ),
+    (
+        "metadata_endpoint",
+        "Where amp and ampup release metadata is read from: auto (faster of the two, default), ampup or github",
+    ),
+    (
+        "http.connect_timeout",
+        "Seconds allowed for establishing a connection",
+    ),
+    (
+        "http.read_timeout",
+        "Seconds allowed between reads of a response body",
+    ),
+    ("http.timeout", "Seconds allowed for a whole request"),
+    (
+        "http.max_rate_limit_wait",
+        "Longest GitHub rate-limit pause, in seconds, to wait out before failing",
+    ),
+    (
+        "ui.theme",
+        "Output theme: unicode, ascii or minimal (single-line progress)",
+    ),
+    (
+        "smoke_test.commands",
+        "Semicolon-separated commands run after install and build (default: ampd --version; ampctl --help)",
+    ),
+    (
+        "macos.codesign",
+        "Ad-hoc sign ampd and ampctl after installing on macOS (true or false)",
+    ),
+    (
+        "update.latest_cache_secs",
+        "Seconds bare `ampup` reuses the latest version it looked up (default 900, 0 disables)",
+    ),
+    (
+        "cache.compression_level",
+        "zstd level (1-22) for storing downloaded artifacts compressed (default: uncompressed)",
+    ),
+    (
+        "update.prerelease",
+        "Resolve latest to the newest release including prereleases (true or false)",
+    ),
+    (
+        "update.channel",
+        "Channel followed by update and bare ampup instead of latest: stable, beta or nightly",
+    ),
+    (
+        "build.label_template",
+        "Template naming source builds, from {repo_slug}, {kind}, {ref} and {hash} (default: built-in scheme)",
+    ),
+    (
+        "build.label_prefix",
+        "Prefix put in front of generated build labels and required of --name",
+    ),
+    (
+        "shims.track_usage",
+        "Record when each version last ran through the ampd and ampctl shims, for list --long and prune --unused-for (true or false)",
+    ),
+    (
+        "install.preallocate_mib",
+        "Size in MiB from which binaries have their disk space reserved before they're written (default 16, 0 disables)",
+    ),
+    (
+        "repos.<owner/repo>.token_command",
+        "Shell command printing a GitHub token for that repository",
+    ),
+];
+
+#[derive(Debug)]
+pub enum SettingsError {
+    UnknownKey {
+        key: String,
+    },
+    InvalidValue {
+        key: String,
+        value: String,
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownKey { key } => {
+                writeln!(f, "Unknown configuration key")?;
+                writeln!(f, "  Key: {}", key)?;
+                writeln!(f)?;
+                writeln!(f, "  Known keys:")?;
+                for (known, _) in KEYS {
+                    writeln!(f, "    {}", known)?;
+                }
+            }
+            Self::InvalidValue { key, value, reason } => {
+                writeln!(f, "Invalid configuration value")?;
+                writeln!(f, "  Key: {}", key)?;
+                writeln!(f, "  Value: {}", value)?;
+                writeln!(f, "  Reason: {}", reason)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+/// User settings persisted in `~/.amp/config.toml`.
+///
+/// Every field is optional so that a missing file, or a file containing only
+/// some sections, falls back to built-in defaults.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Default `owner/repo` for amp releases and source builds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    /// Default `owner/repo` for ampup self-updates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub self_repo: Option<String>,
+    /// Release source for binary downloads (`github`, `oci://...` or a
+    /// mirror URL template)
+    #[serde(alias = "mirror_url", skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Fallback sources tried in order when `source` fails
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
+    /// GitHub Enterprise REST API base URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+    /// Which API amp and ampup release metadata is read from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_endpoint: Option<MetadataEndpoint>,
+    /// Deployment environment of this host, which selects its guard rails
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<HostEnvironment>,
+    /// What bare `ampup` does
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_command: Option<DefaultCommand>,
+    /// ampd data directory, whose compatibility is checked on version switches
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_dir: Option<PathBuf>,
+    /// HTTP client settings
+    pub http: HttpSettings,
+    /// Terminal output settings
+    pub ui: UiSettings,
+    /// Post-install smoke tests
+    pub smoke_test: SmokeTestSettings,
+    /// Default update behavior
+    pub update: UpdateSettings,
+    /// macOS install behavior
+    pub macos: MacosSettings,
+    /// Artifact cache storage
+    pub cache: CacheSettings,
+    /// Source build labels
+    pub build: BuildSettings,
+    /// Shim behavior
+    pub shims: ShimSettings,
+    /// Binary install behavior
+    pub install: InstallSettings,
+    /// Per-repository settings, keyed by `owner/repo`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub repos: BTreeMap<String, RepoSettings>,
+    /// Installation cache directory, set when loaded from an installation
+    #[serde(skip)]
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// `[repos."owner/repo"]` section of the settings file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoSettings {
+    /// Shell command whose stdout is the GitHub token for this repository
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_command: Option<String>,
+}
+
+/// `[http]` section of the settings file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpSettings {
+    /// Seconds allowed for establishing a connection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_timeout: Option<u64>,
+    /// Seconds allowed between reads of a response body
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_timeout: Option<u64>,
+    /// Seconds allowed for a whole request, including the body
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Longest GitHub rate-limit pause, in seconds, to wait out before failing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_rate_limit_wait: Option<u64>,
+}
+
+/// `[ui]` section of the settings file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiSettings {
+    /// Output theme
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+}
+
+/// `[smoke_test]` section of the settings file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmokeTestSettings {
+    /// Shell commands run with the new version's `ampd` and `ampctl` first
+    /// on `PATH`; the defaults when empty
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+}
+
+/// `[update]` section of the settings file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpdateSettings {
+    /// Seconds bare `ampup` reuses the latest version it looked up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest_cache_secs: Option<u64>,
+    /// Resolve `latest` to the newest release including prereleases
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub prerelease: bool,
+    /// Channel followed by `ampup update` and bare `ampup` instead of the
+    /// latest release
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<Channel>,
+}
+
+/// `[macos]` section of the settings file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MacosSettings {
+    /// Ad-hoc sign installed binaries so Gatekeeper runs them
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub codesign: bool,
+}
+
+/// `[cache]` section of the settings file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheSettings {
+    /// zstd level cached artifacts are compressed with; 0 stores them as-is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression_level: Option<i32>,
+}
+
+/// `[build]` section of the settings file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildSettings {
+    /// Template source build labels are rendered from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label_template: Option<String>,
+    /// Prefix generated labels get and custom names must have
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label_prefix: Option<String>,
+}
+
+/// `[shims]` section of the settings file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShimSettings {
+    /// Record each run's time in the version's manifest
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub track_usage: bool,
+}
+
+/// `[install]` section of the settings file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InstallSettings {
+    /// MiB from which binaries are preallocated; 0 turns preallocation off
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preallocate_mib: Option<u64>,
+}
+
+impl HttpSettings {
+    /// Timeouts configured in the settings file.
+    pub fn timeouts(&self) -> HttpTimeouts {
+        HttpTimeouts {
+            connect: self.connect_timeout.map(Duration::from_secs),
+            read: self.read_timeout.map(Duration::from_secs),
+            total: self.timeout.map(Duration::from_secs),
+        }
+    }
+}
+
+impl Settings {
+    /// Load settings from the installation's `config.toml`.
+    ///
+    /// Returns defaults when the file does not exist.
+    pub fn load(config: &Config) -> Result<Self> {
+        let path = config.settings_file();
+        let mut settings = if path.exists() {
+            let contents = fs::read_to_string(&path).context("Failed to read settings file")?;
+            toml::from_str::<Self>(&contents)
+                .with_context(|| format!("Failed to parse settings file {}", path.display()))?
+        } else {
+            Self::default()
+        };
+        settings.cache_dir = Some(config.cache_dir());
+        Ok(settings)
+    }
+
+    /// Repository for amp releases: the explicit flag, then `repo`, then the default.
+    pub fn repo(&self, flag: Option<String>) -> String {
+        flag.or_else(|| self.repo.clone())
+            .unwrap_or_else(|| DEFAULT_REPO.to_string())
+    }
+
+    /// Repository for ampup releases: the explicit flag, then `self_repo`, then the default.
+    pub fn self_repo(&self, flag: Option<String>) -> String {
+        flag.or_else(|| self.self_repo.clone())
+            .unwrap_or_else(|| DEFAULT_SELF_REPO.to_string())
+    }
+
+    /// Smoke test commands: `smoke_test.commands`, or the defaults.
+    pub fn smoke_tests(&self) -> Vec<String> {
+        if self.smoke_test.commands.is_empty() {
+            health::DEFAULT_SMOKE_TESTS
+                .iter()
+                .map(|command| command.to_string())
+                .collect()
+        } else {
+            self.smoke_test.commands.clone()
+        }
+    }
+
+    /// Whether `environment` flags this host as production.
+    pub fn is_production(&self) -> bool {
+        self.environment == Some(HostEnvironment::Production)
+    }
+
+    /// Guard rails for this host's `environment`.
+    pub fn policy(&self) -> Policy {
+        Policy::for_environment(self.environment)
+    }
+
+    /// How long bare `ampup` reuses the latest version it looked up:
+    /// `update.latest_cache_secs`, or the default.
+    pub fn latest_cache_ttl(&self) -> Duration {
+        self.update
+            .latest_cache_secs
+            .map_or(latest_cache::DEFAULT_TTL, Duration::from_secs)
+    }
+
+    /// Size in bytes from which binaries are preallocated:
+    /// `install.preallocate_mib`, or the default.
+    pub fn preallocate_threshold(&self) -> u64 {
+        match self.install.preallocate_mib {
+            None => preallocate::DEFAULT_THRESHOLD,
+            Some(0) => u64::MAX,
+            Some(mib) => mib.saturating_mul(1024 * 1024),
+        }
+    }
+
+    /// The artifact cache in `dir`, compressing new entries as configured.
+    pub fn artifact_cache(&self, dir: PathBuf) -> ArtifactCache {
+        let compression = self
+            .cache
+            .compression_level
+            .filter(|level| *level != 0)
+            .map(|level| Box::new(Zstd { level }) as Box<dyn CacheCompression>);
+        ArtifactCache::new(dir).with_compression(compression)
+    }
+
+    /// How source builds are labeled: `build.label_template` and
+    /// `build.label_prefix`, validated, or the built-in scheme.
+    pub fn label_scheme(&self) -> Result<LabelScheme> {
+        Ok(LabelScheme::new(
+            self.build.label_template.clone(),
+            self.build.label_prefix.clone(),
+        )?)
+    }
+
+    /// Client options configured in the settings file.
+    pub fn client_options(&self) -> ClientOptions {
+        ClientOptions {
+            timeouts: self.http.timeouts(),
+            api_url: self.api_url.clone(),
+            max_rate_limit_wait: self.http.max_rate_limit_wait.map(Duration::from_secs),
+            metadata_endpoint: self.metadata_endpoint,
+        }
+    }
+
+    /// Build a GitHub client for `repo`, filling unset `options` from these
+    /// settings and resolving the token for that repository and host.
+    pub fn github_client(
+        &self,
+        repo: String,
+        github_token: Option<String>,
+        options: ClientOptions,
+    ) -> Result<GitHubClient> {
+        let options = options.or(self.client_options());
+        let hostname = options.enterprise_host();
+        let lookup = TokenLookup {
+            command: self
+                .repos
+                .get(&repo)
+                .and_then(|r| r.token_command.as_deref()),
+            hostname: hostname.as_deref(),
+        };
+        let resolved_token = token::resolve_github_token(github_token, &lookup, &ProcessEnv);
+        let client = GitHubClient::new(repo, resolved_token, options)?;
+        Ok(match &self.cache_dir {
+            Some(cache_dir) => client
+                .with_rate_limit_state(cache_dir)
+                .with_endpoint_cache(cache_dir),
+            None => client,
+        })
+    }
+
+    /// Read a single dotted key (e.g. `http.timeout`) from the settings file.
+    ///
+    /// Returns `None` when the key is known but not set.
+    pub fn get(config: &Config, key: &str) -> Result<Option<String>> {
+        let path = key_path(key)?;
+        let table = load_table(config)?;
+        let separator = if key == "smoke_test.commands" {
+            "; "
+        } else {
+            ", "
+        };
+        let value = lookup(&table, &path).map(|value| match value {
+            toml::Value::String(s) => s.clone(),
+            toml::Value::Array(items) => items
+                .iter()
+                .map(|item| {
+                    item.as_str()
+                        .map_or_else(|| item.to_string(), str::to_string)
+                })
+                .collect::<Vec<_>>()
+                .join(separator),
+            other => other.to_string(),
+        });
+        Ok(value)
+    }
+
+    /// Set a single dotted key in the settings file.
+    ///
+    /// The value is validated against the settings schema before anything is
+    /// written, so an invalid value leaves the file untouched.
+    pub fn set(config: &Config, key: &str, value: &str) -> Result<()> {
+        let path = key_path(key)?;
+        match key {
+            "repo" | "self_repo" => validate_repo(key, value)?,
+            "api_url" => validate_url(key, value)?,
+            "source" => validate_source(key, value)?,
+            "cache.compression_level" => validate_compression_level(key, value)?,
+            "build.label_template" => {
+                validate_label(key, value, LabelScheme::new(Some(value.to_string()), None))?
+            }
+            "build.label_prefix" => {
+                validate_label(key, value, LabelScheme::new(None, Some(value.to_string())))?
+            }
+            "mirrors" => {
+                for mirror in split_list(value) {
+                    validate_source(key, mirror)?;
+                }
+            }
+            _ => {}
+        }
+
+        let candidates = if key == "mirrors" {
+            let items = split_list(value).map(|m| toml::Value::String(m.to_string()));
+            vec![toml::Value::Array(items.collect())]
+        } else if key == "smoke_test.commands" {
+            let items = value
+                .split(';')
+                .map(str::trim)
+                .filter(|command| !command.is_empty())
+                .map(|command| toml::Value::String(command.to_string()));
+            vec![toml::Value::Array(items.collect())]
+        } else {
+            candidate_values(value)
+        };
+
+        let mut table = load_table(config)?;
+        let mut last_err = None;
+        for candidate in candidates {
+            let mut updated = table.clone();
+            insert(&mut updated, &path, candidate);
+            match updated.clone().try_into::<Settings>() {
+                Ok(_) => {
+                    table = updated;
+                    last_err = None;
+                    break;
+                }
+                Err(err) => last_err = Some(err),
+            }
+        }
+        if let Some(err) = last_err {
+            return Err(SettingsError::InvalidValue {
+                key: key.to_string(),
+                value: value.to_string(),
+                reason: err.message().to_string(),
+            }
+            .into());
+        }
+
+        save_table(config, &table)
+    }
+
+    /// Remove a single dotted key from the settings file.
+    ///
+    /// Returns whether the key was set.
+    pub fn unset(config: &Config, key: &str) -> Result<bool> {
+        let path = key_path(key)?;
+        let mut table = load_table(config)?;
+        let removed = remove(&mut table, &path);
+        if removed {
+            save_table(config, &table)?;
+        }
+        Ok(removed)
+    }
+}
+
+/// Split a dotted key into table path segments.
+///
+/// `repos.<owner/repo>.<field>` keeps the repository (which contains no dots)
+/// as a single segment.
+fn key_path(key: &str) -> Result<Vec<&str>, SettingsError> {
+    let unknown = || SettingsError::UnknownKey {
+        key: key.to_string(),
+    };
+
+    if let Some(rest) = key.strip_prefix("repos.") {
+        let (repo, field) = rest.rsplit_once('.').ok_or_else(unknown)?;
+        validate_repo(key, repo)?;
+        return match field {
+            "token_command" => Ok(vec!["repos", repo, field]),
+            _ => Err(unknown()),
+        };
+    }
+
+    if KEYS.iter().any(|(known, _)| *known == key) {
+        Ok(key.split('.').collect())
+    } else {
+        Err(unknown())
+    }
+}
+
+fn validate_repo(key: &str, value: &str) -> Result<(), SettingsError> {
+    let valid = value
+        .split_once('/')
+        .is_some_and(|(owner, name)| !owner.is_empty() && !name.is_empty() && !name.contains('/'));
+    if valid {
+        Ok(())
+    } else {
+        Err(SettingsError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+            reason: "expected format \"owner/repo\"".to_string(),
+        })
+    }
+}
+
+fn validate_url(key: &str, value: &str) -> Result<(), SettingsError> {
+    if value.starts_with("https://") || value.starts_with("http://") {
+        Ok(())
+    } else {
+        Err(SettingsError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+            reason: "expected an http:// or https:// URL".to_string(),
+        })
+    }
+}
+
+fn validate_label(
+    key: &str,
+    value: &str,
+    scheme: Result<LabelScheme, LabelError>,
+) -> Result<(), SettingsError> {
+    let reason = match scheme {
+        Ok(_) => return Ok(()),
+        Err(LabelError::UnknownPlaceholder { placeholder, .. }) => format!(
+            "unknown placeholder {{{}}}, expected one of {}",
+            placeholder,
+            PLACEHOLDERS.map(|name| format!("{{{}}}", name)).join(", ")
+        ),
+        Err(LabelError::Unclosed { .. }) => "placeholder opened without a closing '}'".to_string(),
+        Err(LabelError::Unsafe { value, reason }) => {
+            format!("'{}' is not a safe directory name: {}", value, reason)
+        }
+        Err(LabelError::MissingPrefix { prefix, .. }) => format!("missing prefix {}", prefix),
+    };
+    Err(SettingsError::InvalidValue {
+        key: key.to_string(),
+        value: value.to_string(),
+        reason,
+    })
+}
+
+fn validate_compression_level(key: &str, value: &str) -> Result<(), SettingsError> {
+    match value.parse::<i32>() {
+        Ok(level) if (0..=22).contains(&level) => Ok(()),
+        _ => Err(SettingsError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+            reason: "expected a zstd level from 1 to 22, or 0 to disable compression".to_string(),
+        }),
+    }
+}
+
+fn validate_source(key: &str, value: &str) -> Result<(), SettingsError> {
+    let schemes = ["oci://", "file://"];
+    if value == crate::release_source::GITHUB_SOURCE
+        || schemes.iter().any(|scheme| value.starts_with(scheme))
+    {
+        return Ok(());
+    }
+    validate_url(key, value).map_err(|_| SettingsError::InvalidValue {
+        key: key.to_string(),
+        value: value.to_string(),
+        reason: "expected github, an oci:// reference or a mirror URL".to_string(),
+    })
+}
+
+/// Split a comma-separated list value, ignoring empty entries.
+fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// Interpretations of a command-line value, most specific first.
+fn candidate_values(value: &str) -> Vec<toml::Value> {
+    let mut candidates = Vec::new();
+    if let Ok(int) = value.parse::<i64>() {
+        candidates.push(toml::Value::Integer(int));
+    }
+    if let Ok(bool) = value.parse::<bool>() {
+        candidates.push(toml::Value::Boolean(bool));
+    }
+    candidates.push(toml::Value::String(value.to_string()));
+    candidates
+}
+
+fn lookup<'a>(table: &'a toml::Table, path: &[&str]) -> Option<&'a toml::Value> {
+    let (leaf, sections) = path.split_last()?;
+    let mut table = table;
+    for section in sections {
+        table = table.get(*section)?.as_table()?;
+    }
+    table.get(*leaf)
+}
+
+fn insert(table: &mut toml::Table, path: &[&str], value: toml::Value) {
+    let Some((leaf, sections)) = path.split_last() else {
+        return;
+    };
+    let mut table = table;
+    for section in sections {
+        let entry = table
+            .entry(section.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        let Some(next) = entry.as_table_mut() else {
+            return;
+        };
+        table = next;
+    }
+    table.insert(leaf.to_string(), value);
+}
+
+/// Remove the value at `path`, pruning sections left empty.
+fn remove(table: &mut toml::Table, path: &[&str]) -> bool {
+    match path {
+        [] => false,
+        [leaf] => table.remove(*leaf).is_some(),
+        [section, rest @ ..] => {
+            let Some(inner) = table.get_mut(*section).and_then(toml::Value::as_table_mut) else {
+                return false;
+            };
+            let removed = remove(inner, rest);
+            if inner.is_empty() {
+                table.remove(*section);
+            }
+            removed
+        }
+    }
+}
+
+fn load_table(config: &Config) -> Result<toml::Table> {
+    let path = config.settings_file();
+    if !path.exists() {
+        return Ok(toml::Table::new());
+    }
+    let contents = fs::read_to_string(&path).context("Failed to read settings file")?;
+    contents
+        .parse()
+        .with_context(|| format!("Failed to parse settings file {}", path.display()))
+}
+
+fn save_table(config: &Config, table: &toml::Table) -> Result<()> {
+    fs::create_dir_all(&config.amp_dir).context("Failed to create amp directory")?;
+    let contents = toml::to_string_pretty(table).context("Failed to serialize settings")?;
+    fs::write(config.settings_file(), contents).context("Failed to write settings file")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_with_missing_file_returns_defaults() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+
+        //* When
+        let settings = Settings::load(&config).expect("should load settings");
+
+        //* Then
+        assert!(
+            settings.http.timeout.is_none(),
+            "missing settings file should produce default settings"
+        );
+    }
+
+    #[test]
+    fn load_with_http_section_parses_timeouts() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        fs::write(
+            config.settings_file(),
+            "[http]\nconnect_timeout = 5\nread_timeout = 120\n",
+        )
+        .expect("should write settings file");
+
+        //* When
+        let timeouts = Settings::load(&config)
+            .expect("should load settings")
+            .http
+            .timeouts();
+
+        //* Then
+        assert_eq!(timeouts.connect, Some(Duration::from_secs(5)));
+        assert_eq!(timeouts.read, Some(Duration::from_secs(120)));
+        assert_eq!(timeouts.total, None, "unset keys should stay unset");
+    }
+
+    #[test]
+    fn set_with_repo_persists_and_is_used_as_default() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+
+        //* When
+        Settings::set(&config, "repo", "myorg/amp-fork").expect("should set repo");
+        Settings::set(&config, "http.timeout", "30").expect("should set timeout");
+
+        //* Then
+        let settings = Settings::load(&config).expect("should load settings");
+        assert_eq!(settings.repo(None), "myorg/amp-fork");
+        assert_eq!(
+            settings.repo(Some("other/repo".to_string())),
+            "other/repo",
+            "explicit flag should override the configured repo"
+        );
+        assert_eq!(settings.http.timeout, Some(30));
+    }
+
+    #[test]
+    fn preallocate_threshold_with_configured_size_converts_mib_and_zero_disables() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        let default = Settings::load(&config).expect("should load settings");
+
+        //* When
+        Settings::set(&config, "install.preallocate_mib", "64").expect("should set threshold");
+        let configured = Settings::load(&config).expect("should load settings");
+        Settings::set(&config, "install.preallocate_mib", "0").expect("should set threshold");
+        let disabled = Settings::load(&config).expect("should load settings");
+
+        //* Then
+        assert_eq!(
+            default.preallocate_threshold(),
+            preallocate::DEFAULT_THRESHOLD
+        );
+        assert_eq!(configured.preallocate_threshold(), 64 * 1024 * 1024);
+        assert_eq!(disabled.preallocate_threshold(), u64::MAX);
+    }
+
+    #[test]
+    fn set_with_mirrors_list_persists_ordered_array() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+
+        //* When
+        Settings::set(
+            &config,
+            "mirrors",
+            "https://mirror.example.com/amp, oci://ghcr.io/edgeandnode/amp",
+        )
+        .expect("should set mirrors");
+
+        //* Then
+        let settings = Settings::load(&config).expect("should load settings");
+        assert_eq!(
+            settings.mirrors,
+            [
+                "https://mirror.example.com/amp",
+                "oci://ghcr.io/edgeandnode/amp"
+            ]
+        );
+        assert_eq!(
+            Settings::get(&config, "mirrors").expect("should get mirrors"),
+            Some("https://mirror.example.com/amp, oci://ghcr.io/edgeandnode/amp".to_string())
+        );
+    }
+
+    #[test]
+    fn set_with_invalid_value_leaves_file_untouched() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        Settings::set(&config, "http.timeout", "30").expect("should set timeout");
+
+        //* When
+        let result = Settings::set(&config, "http.timeout", "soon");
+
+        //* Then
+        assert!(result.is_err(), "non-numeric timeout should be rejected");
+        assert_eq!(
+            Settings::get(&config, "http.timeout").expect("should get timeout"),
+            Some("30".to_string())
+        );
+    }
+
+    #[test]
+    fn unset_with_last_key_in_section_removes_section() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        Settings::set(&config, "http.timeout", "30").expect("should set timeout");
+
+        //* When
+        let removed = Settings::unset(&config, "http.timeout").expect("should unset timeout");
+
+        //* Then
+        assert!(removed, "unset should report that the key was set");
+        let contents = fs::read_to_string(config.settings_file()).expect("should read file");
+        assert!(
+            !contents.contains("[http]"),
+            "empty section should be removed, got: {}",
+            contents
+        );
+    }
+
+    #[test]
+    fn set_with_repo_token_command_writes_quoted_repo_table() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+
+        //* When
+        Settings::set(
+            &config,
+            "repos.myorg/amp-fork.token_command",
+            "op read op://dev/fork/token",
+        )
+        .expect("should set token command");
+
+        //* Then
+        let settings = Settings::load(&config).expect("should load settings");
+        assert_eq!(
+            settings.repos["myorg/amp-fork"].token_command.as_deref(),
+            Some("op read op://dev/fork/token")
+        );
+        assert!(
+            Settings::unset(&config, "repos.myorg/amp-fork.token_command")
+                .expect("should unset token command")
+        );
+        let contents = fs::read_to_string(config.settings_file()).expect("should read file");
+        assert!(
+            !contents.contains("repos"),
+            "empty repo tables should be pruned, got: {}",
+            contents
+        );
+    }
+
+    #[test]
+    fn load_with_unknown_key_fails() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        fs::write(config.settings_file(), "[http]\nconect_timeout = 5\n")
+            .expect("should write settings file");
+
+        //* When
+        let result = Settings::load(&config);
+
+        //* Then
+        assert!(
+            result.is_err(),
+            "misspelled keys should be rejected instead of silently ignored"
+        );
+    }
+}
@@ -1,9 +1,13 @@
-use std::path::PathBuf;
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 use anyhow::{Context, Result};
 use fs_err as fs;
 
-use crate::ui;
+use crate::{environment::Environment, instances::shell_quote, ui};
 
 #[derive(Debug)]
 pub enum ShellError {
@@ -45,8 +49,8 @@ pub enum Shell {
 
 impl Shell {
     /// Detect the current shell from the SHELL environment variable
-    pub fn detect() -> Option<Self> {
-        let shell = std::env::var("SHELL").ok()?;
+    pub fn detect(env: &dyn Environment) -> Option<Self> {
+        let shell = env.var("SHELL")?;
 
         if shell.ends_with("/zsh") {
             Some(Shell::Zsh)
@@ -62,14 +66,15 @@ impl Shell {
     }
 
     /// Get the profile file path for this shell
-    pub fn profile_path(&self) -> Result<PathBuf> {
-        let home = std::env::var("HOME")
-            .or_else(|_| std::env::var("USERPROFILE"))
+    pub fn profile_path(&self, env: &dyn Environment) -> Result<PathBuf> {
+        let home = env
+            .var("HOME")
+            .or_else(|| env.var("USERPROFILE"))
             .context("Could not determine home directory")?;
 
         let path = match self {
             Shell::Zsh => {
-                let zdotdir = std::env::var("ZDOTDIR").unwrap_or(home);
+                let zdotdir = env.var("ZDOTDIR").unwrap_or(home);
                 PathBuf::from(zdotdir).join(".zshenv")
             }
             Shell::Bash => PathBuf::from(home).join(".bashrc"),
@@ -87,12 +92,105 @@ impl Shell {
             _ => format!("export PATH=\"$PATH:{}\"", bin_dir),
         }
     }
+
+    /// Line exporting `name` as `value` in this shell's syntax, for `eval`.
+    pub fn export_line(&self, name: &str, value: &str) -> String {
+        match self {
+            // fish splits colon-separated values of *PATH variables itself
+            Shell::Fish => format!(
+                "set -gx {} '{}';",
+                name,
+                value.replace('\\', "\\\\").replace('\'', "\\'")
+            ),
+            _ => format!("export {}={};", name, shell_quote(value)),
+        }
+    }
+
+    /// Configure `command` (which runs this shell) to prefix the interactive
+    /// prompt with `label`.
+    ///
+    /// The user's own startup files are still sourced first, so only the prompt
+    /// prefix is added on top of their configuration. Any generated startup
+    /// files are written to `scratch_dir`, which must outlive the spawned shell.
+    pub fn annotate_prompt(
+        &self,
+        command: &mut Command,
+        label: &str,
+        scratch_dir: &Path,
+        env: &dyn Environment,
+    ) -> Result<()> {
+        match self {
+            Shell::Bash => {
+                let rcfile = scratch_dir.join("bashrc");
+                fs::write(&rcfile, bash_rc(label)).context("Failed to write subshell rcfile")?;
+                command.arg("--rcfile").arg(rcfile);
+            }
+            Shell::Zsh => {
+                // zsh reads its startup files from $ZDOTDIR, so point it at the
+                // scratch dir and have each file chain to the user's original.
+                let original = env
+                    .var_os("ZDOTDIR")
+                    .or_else(|| env.var_os("HOME"))
+                    .unwrap_or_default();
+                for name in [".zshenv", ".zprofile", ".zshrc", ".zlogin"] {
+                    let contents = zsh_startup_file(name, label);
+                    fs::write(scratch_dir.join(name), contents)
+                        .context("Failed to write subshell startup file")?;
+                }
+                command
+                    .env("AMPUP_ORIGINAL_ZDOTDIR", original)
+                    .env("ZDOTDIR", scratch_dir);
+            }
+            Shell::Fish => {
+                command.arg("--init-command").arg(fish_init_command(label));
+            }
+            Shell::Ash => {
+                let prompt = env.var("PS1").unwrap_or_else(|| "$ ".to_string());
+                command.env("PS1", format!("({}) {}", label, prompt));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Build a PATH value with `dir` placed ahead of every existing entry.
+pub fn prepend_to_path(dir: &Path, current: Option<OsString>) -> Result<OsString> {
+    let mut entries = vec![dir.to_path_buf()];
+    if let Some(current) = current {
+        entries.extend(std::env::split_paths(&current).filter(|p| p != dir));
+    }
+    std::env::join_paths(entries).context("Failed to build PATH for subshell")
+}
+
+fn bash_rc(label: &str) -> String {
+    format!(
+        "[ -f \"$HOME/.bashrc\" ] && . \"$HOME/.bashrc\"\nPS1=\"({}) $PS1\"\n",
+        label
+    )
+}
+
+fn zsh_startup_file(name: &str, label: &str) -> String {
+    let mut contents = format!(
+        "[ -f \"$AMPUP_ORIGINAL_ZDOTDIR/{name}\" ] && . \"$AMPUP_ORIGINAL_ZDOTDIR/{name}\"\n"
+    );
+    if name == ".zshrc" {
+        contents.push_str(&format!("PROMPT=\"({}) $PROMPT\"\n", label));
+    }
+    contents
+}
+
+fn fish_init_command(label: &str) -> String {
+    format!(
+        "functions -q fish_prompt; and functions -c fish_prompt __ampup_fish_prompt; \
+         function fish_prompt; echo -n '({}) '; __ampup_fish_prompt; end",
+        label
+    )
 }
 
 /// Add a directory to PATH by modifying the shell profile
-pub fn add_to_path(bin_dir: &str) -> Result<()> {
-    let shell = Shell::detect().ok_or(ShellError::ShellNotDetected)?;
-    let profile_path = shell.profile_path()?;
+pub fn add_to_path(bin_dir: &str, env: &dyn Environment) -> Result<()> {
+    let shell = Shell::detect(env).ok_or(ShellError::ShellNotDetected)?;
+    let profile_path = shell.profile_path(env)?;
     let export_line = shell.path_export_line(bin_dir);
 
     // Ensure parent directory exists
@@ -136,3 +234,62 @@ pub fn add_to_path(bin_dir: &str) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prepend_to_path_with_existing_entries_places_dir_first() {
+        //* Given
+        let dir = PathBuf::from("/amp/versions/v0.3.0");
+        let current = Some(OsString::from("/usr/bin:/bin"));
+
+        //* When
+        let result = prepend_to_path(&dir, current).expect("should join paths");
+
+        //* Then
+        assert_eq!(
+            result,
+            OsString::from("/amp/versions/v0.3.0:/usr/bin:/bin"),
+            "version directory should take precedence over existing PATH entries"
+        );
+    }
+
+    #[test]
+    fn prepend_to_path_with_dir_already_present_removes_duplicate() {
+        //* Given
+        let dir = PathBuf::from("/amp/versions/v0.3.0");
+        let current = Some(OsString::from("/usr/bin:/amp/versions/v0.3.0"));
+
+        //* When
+        let result = prepend_to_path(&dir, current).expect("should join paths");
+
+        //* Then
+        assert_eq!(
+            result,
+            OsString::from("/amp/versions/v0.3.0:/usr/bin"),
+            "version directory should appear exactly once"
+        );
+    }
+
+    #[test]
+    fn export_line_with_quotes_escapes_them_per_shell() {
+        //* Given
+        let value = "/home/o'brien/.amp/versions/v0.3.0:/usr/bin";
+
+        //* When
+        let posix = Shell::Bash.export_line("PATH", value);
+        let fish = Shell::Fish.export_line("PATH", value);
+
+        //* Then
+        assert_eq!(
+            posix,
+            r"export PATH='/home/o'\''brien/.amp/versions/v0.3.0:/usr/bin';"
+        );
+        assert_eq!(
+            fish,
+            r"set -gx PATH '/home/o\'brien/.amp/versions/v0.3.0:/usr/bin';"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use anyhow::Result;
+
+use crate::{
+    archive::{COMPRESSED_SUFFIX, archive_suffixes},
+    github::GitHubClient,
+    platform::{Architecture, Platform},
+    ui,
+};
+
+#[derive(Debug)]
+pub enum TargetError {
+    /// The release publishes no binaries for the requested targets.
+    Unavailable {
+        version: String,
+        requested: Vec<String>,
+        matrix: String,
+    },
+}
+
+impl std::fmt::Display for TargetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unavailable {
+                version,
+                requested,
+                matrix,
+            } => {
+                writeln!(f, "Release has no binaries for the requested target")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Requested: {}", requested.join(", "))?;
+                writeln!(f)?;
+                writeln!(f, "  Targets published for {}:", version)?;
+                for line in matrix.lines() {
+                    writeln!(f, "    {}", line)?;
+                }
+                writeln!(f)?;
+                write!(
+                    f,
+                    "  Pick an available combination with --platform and --arch."
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TargetError {}
+
+/// The platform and architecture combinations a release publishes both
+/// ampd and ampctl for.
+#[derive(Debug)]
+pub struct TargetMatrix {
+    available: Vec<(Platform, Architecture)>,
+}
+
+impl TargetMatrix {
+    /// Read the matrix from a release's asset names, counting bare,
+    /// compressed and archived binaries alike.
+    pub fn from_assets(asset_names: &[String]) -> Self {
+        let published = |binary: &str, platform: Platform, arch: Architecture| {
+            let name = format!("{}-{}-{}", binary, platform.as_str(), arch.as_str());
+            asset_names
+                .iter()
+                .any(|asset| asset.strip_prefix(&name).is_some_and(is_binary_suffix))
+        };
+        let available = Platform::ALL
+            .into_iter()
+            .flat_map(|platform| Architecture::ALL.map(|arch| (platform, arch)))
+            .filter(|&(platform, arch)| {
+                published("ampd", platform, arch) && published("ampctl", platform, arch)
+            })
+            .collect();
+        Self { available }
+    }
+
+    pub fn contains(&self, platform: Platform, arch: Architecture) -> bool {
+        self.available.contains(&(platform, arch))
+    }
+
+    /// A table with a row per platform and a column per architecture.
+    pub fn render(&self) -> String {
+        let mut table = format!("{:<8}", "");
+        for arch in Architecture::ALL {
+            table.push_str(&format!("{:<9}", arch.as_str()));
+        }
+        for platform in Platform::ALL {
+            table.push('\n');
+            table.push_str(&format!("{:<8}", platform.as_str()));
+            for arch in Architecture::ALL {
+                let cell = if self.contains(platform, arch) {
+                    "yes"
+                } else {
+                    "-"
+                };
+                table.push_str(&format!("{:<9}", cell));
+            }
+        }
+        table
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Check that the release of `version` publishes binaries for each of
+/// `targets` before anything is downloaded, failing with the release's
+/// [`TargetMatrix`] when it doesn't.
+///
+/// When the assets can't be listed (e.g. installing from a mirror while
+/// GitHub is unreachable) the check is skipped, and the download reports any
+/// missing asset.
+pub async fn check(
+    github: &GitHubClient,
+    version: &str,
+    targets: &[(Platform, Architecture)],
+) -> Result<()> {
+    let asset_names = match github.release_asset_names(version).await {
+        Ok(names) => names,
+        Err(err) => {
+            ui::detail!("Could not list release assets to check targets: {}", err);
+            return Ok(());
+        }
+    };
+    let matrix = TargetMatrix::from_assets(&asset_names);
+    let missing: Vec<String> = targets
+        .iter()
+        .filter(|&&(platform, arch)| !matrix.contains(platform, arch))
+        .map(|(platform, arch)| format!("{}-{}", platform, arch))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(TargetError::Unavailable {
+        version: version.to_string(),
+        requested: missing,
+        matrix: matrix.render(),
+    }
+    .into())
+}
+
+fn is_binary_suffix(suffix: &str) -> bool {
+    suffix.is_empty() || suffix == COMPRESSED_SUFFIX || archive_suffixes().any(|s| s == suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assets(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn from_assets_with_partial_release_contains_only_complete_targets() {
+        //* Given
+        let names = assets(&[
+            "ampd-linux-x86_64",
+            "ampctl-linux-x86_64.zst",
+            "ampd-darwin-aarch64.tar.gz",
+            "ampctl-darwin-aarch64.tar.gz",
+            "ampd-linux-aarch64",
+            "ampctl-linux-aarch64.from-v0.1.0.zst",
+        ]);
+
+        //* When
+        let matrix = TargetMatrix::from_assets(&names);
+
+        //* Then
+        assert!(matrix.contains(Platform::Linux, Architecture::X86_64));
+        assert!(matrix.contains(Platform::Darwin, Architecture::Aarch64));
+        assert!(
+            !matrix.contains(Platform::Linux, Architecture::Aarch64),
+            "a patch is not a published binary"
+        );
+        assert!(!matrix.contains(Platform::Darwin, Architecture::X86_64));
+    }
+
+    #[test]
+    fn render_with_available_targets_marks_them_in_table() {
+        //* Given
+        let matrix = TargetMatrix::from_assets(&assets(&[
+            "ampd-linux-x86_64",
+            "ampctl-linux-x86_64",
+            "ampd-darwin-aarch64",
+            "ampctl-darwin-aarch64",
+        ]));
+
+        //* When
+        let table = matrix.render();
+
+        //* Then
+        assert_eq!(
+            table,
+            "        x86_64   aarch64\nlinux   yes      -\ndarwin  -        yes"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::{
+    manifest::MANIFEST_FILE,
+    version_manager::{VersionManager, dir_usage, unix_now},
+};
+
+/// Age after which the cleanup ampup runs on start removes a temporary
+/// file; no install or self-update takes this long.
+pub const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A temporary file or directory an interrupted operation left behind.
+#[derive(Debug, Clone)]
+pub struct TempArtifact {
+    pub path: PathBuf,
+    /// What left it behind
+    pub kind: &'static str,
+    /// Bytes taken, counting every file of a directory
+    pub bytes: u64,
+}
+
+/// Temporary artifacts under the installation last modified at least
+/// `older_than` before `now` (a Unix timestamp): staging and backup
+/// directories of installs, half-written manifests and artifact cache
+/// entries, and a self-update's downloaded binary.
+pub fn find(
+    version_manager: &VersionManager,
+    older_than: Duration,
+    now: u64,
+) -> Result<Vec<TempArtifact>> {
+    let config = version_manager.config();
+    let mut candidates: Vec<(PathBuf, &'static str)> = Vec::new();
+
+    for dir in version_manager.store().staging_dirs()? {
+        let kind = match dir.extension().is_some_and(|extension| extension == "old") {
+            true => "install backup",
+            false => "install staging directory",
+        };
+        candidates.push((dir, kind));
+    }
+    let staged_manifest = format!(".{}.", MANIFEST_FILE);
+    for key in version_manager.list_installed()? {
+        for name in file_names(&version_manager.version_dir(&key))? {
+            if name.starts_with(&staged_manifest) {
+                candidates.push((
+                    version_manager.version_dir(&key).join(name),
+                    "partial manifest",
+                ));
+            }
+        }
+    }
+    let cache_dir = config.artifact_cache_dir();
+    for name in file_names(&cache_dir)? {
+        if name.starts_with('.') && name.ends_with(".tmp") {
+            candidates.push((cache_dir.join(name), "partial cache entry"));
+        }
+    }
+    candidates.push((
+        config.ampup_binary_path().with_extension("tmp"),
+        "partial self-update",
+    ));
+
+    let mut artifacts = Vec::new();
+    for (path, kind) in candidates {
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_secs());
+        if now.saturating_sub(modified) < older_than.as_secs() {
+            continue;
+        }
+        let bytes = match metadata.is_dir() {
+            true => dir_usage(&path)?,
+            false => metadata.len(),
+        };
+        artifacts.push(TempArtifact { path, kind, bytes });
+    }
+    Ok(artifacts)
+}
+
+/// Delete `artifact`.
+pub fn remove(artifact: &TempArtifact) -> Result<()> {
+    if artifact.path.is_dir() {
+        fs::remove_dir_all(&artifact.path).context("Failed to remove temporary directory")
+    } else {
+        fs::remove_file(&artifact.path).context("Failed to remove temporary file")
+    }
+}
+
+/// Remove the artifacts of `version_manager`'s installation older than
+/// [`STALE_AFTER`], ignoring failures. Runs at the start of the commands
+/// holding the installation lock, which the caller must hold.
+pub fn sweep(version_manager: &VersionManager) {
+    let Ok(artifacts) = find(version_manager, STALE_AFTER, unix_now()) else {
+        return;
+    };
+    for artifact in &artifacts {
+        let _ = remove(artifact);
+    }
+}
+
+/// Names of the entries in `dir`; an empty list when `dir` doesn't exist.
+fn file_names(dir: &Path) -> Result<Vec<String>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir).context("Failed to read directory")? {
+        let entry = entry.context("Failed to read directory entry")?;
+        names.push(entry.file_name().to_string_lossy().to_string());
+    }
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn find_with_threshold_skips_fresh_artifacts_and_versions() -> Result<()> {
+        //* Given
+        let dir = tempfile::tempdir()?;
+        let version_manager = VersionManager::new(Config::new(Some(dir.path().to_path_buf()))?);
+        let version_dir = version_manager.version_dir("edgeandnode-amp/v0.4.0");
+        fs::create_dir_all(&version_dir)?;
+        fs::write(version_dir.join("ampd"), "")?;
+        fs::write(version_dir.join(".manifest.json.4242"), "{")?;
+        let staging = version_dir.with_file_name(".tmpAbC123");
+        fs::create_dir_all(&staging)?;
+        fs::write(staging.join("ampd"), "partial")?;
+
+        //* When
+        let fresh = find(&version_manager, STALE_AFTER, unix_now())?;
+        let all = find(
+            &version_manager,
+            STALE_AFTER,
+            unix_now() + STALE_AFTER.as_secs(),
+        )?;
+
+        //* Then
+        assert!(fresh.is_empty());
+        let paths: Vec<_> = all.iter().map(|artifact| artifact.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![staging, version_dir.join(".manifest.json.4242")]
+        );
+        assert_eq!(all[0].bytes, 7);
+        assert!(version_dir.join("ampd").exists());
+        Ok(())
+    }
+}
@@ -4,6 +4,11 @@ use anyhow::{Context, Result};
 use fs_err as fs;
 use tempfile::TempDir;
 
+use crate::{
+    DEFAULT_REPO,
+    version_store::{repo_namespace, split_key, version_key},
+};
+
 /// Temporary ampup installation directory for testing.
 ///
 /// Creates an isolated `.amp` directory structure that is automatically
@@ -52,7 +57,7 @@ impl TempInstallDir {
         self.bin_dir().join("ampup")
     }
 
-    /// Get the path to the active amp binary symlink.
+    /// Get the path to the ampd shim.
     pub fn active_binary(&self) -> PathBuf {
         self.bin_dir().join("ampd")
     }
@@ -62,9 +67,15 @@ impl TempInstallDir {
         self.amp_dir.join(".version")
     }
 
-    /// Get the path to a specific version directory.
+    /// Get the path to a specific version directory, given its key or a
+    /// version of the default repository.
     pub fn version_dir(&self, version: &str) -> PathBuf {
-        self.versions_dir().join(version)
+        match split_key(version) {
+            (Some(_), _) => self.versions_dir().join(version),
+            (None, _) => self
+                .versions_dir()
+                .join(version_key(&repo_namespace(DEFAULT_REPO), version)),
+        }
     }
 
     /// Get the path to a specific version binary.
@@ -0,0 +1,8 @@
+//! Fixtures for hermetic tests of ampup and of tools built on it as a
+//! library, enabled outside this crate with the `test-utils` feature.
+
+mod fixtures;
+mod release_server;
+
+pub use fixtures::{MockBinary, TempInstallDir};
+pub use release_server::{MockReleaseServer, Route};
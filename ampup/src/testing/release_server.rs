@@ -0,0 +1,237 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use anyhow::{Context, Result};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    task::JoinHandle,
+};
+
+use crate::github::GitHubClient;
+
+/// Response the mock release server sends for matching request paths.
+#[derive(Clone)]
+pub struct Route {
+    /// Path substring to match against the request path.
+    prefix: String,
+    /// Response body to return on success.
+    body: Vec<u8>,
+    /// Number of times to return 500 before succeeding.
+    /// Shared across connections so retries see the updated count.
+    fail_count: Arc<AtomicUsize>,
+}
+
+impl Route {
+    /// Create a route that always succeeds.
+    pub fn ok(prefix: impl Into<String>, body: impl Into<Vec<u8>>) -> Self {
+        Self::fail_then_ok(prefix, body, 0)
+    }
+
+    /// Create a route that returns 500 for the first `n` requests, then
+    /// succeeds.
+    pub fn fail_then_ok(prefix: impl Into<String>, body: impl Into<Vec<u8>>, n: usize) -> Self {
+        Self {
+            prefix: prefix.into(),
+            body: body.into(),
+            fail_count: Arc::new(AtomicUsize::new(n)),
+        }
+    }
+
+    /// Response to a request with `method`: 200 with the body, or 500 while
+    /// failures remain.
+    fn respond(&self, method: &str) -> Vec<u8> {
+        // Availability probes never consume failures
+        if method == "HEAD" {
+            return b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".to_vec();
+        }
+
+        // Decrement without wrapping 0 to usize::MAX when requests race
+        let should_fail = self
+            .fail_count
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok();
+        if should_fail {
+            return b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n".to_vec();
+        }
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+            self.body.len()
+        )
+        .into_bytes();
+        response.extend_from_slice(&self.body);
+        response
+    }
+}
+
+/// Local HTTP server standing in for the GitHub releases API and its asset
+/// downloads.
+///
+/// Each request is answered by the first route whose prefix its path
+/// contains, or with 404. Release metadata added with
+/// [`with_release`](Self::with_release) points asset downloads at
+/// `/download/<asset>` on the same server. The server stops when dropped.
+pub struct MockReleaseServer {
+    addr: SocketAddr,
+    routes: Arc<Mutex<Vec<Route>>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockReleaseServer {
+    /// Start a server on a random local port, with no routes.
+    pub async fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .context("Failed to bind mock release server")?;
+        let addr = listener
+            .local_addr()
+            .context("Failed to get mock release server address")?;
+        let routes: Arc<Mutex<Vec<Route>>> = Arc::default();
+        let handle = tokio::spawn(serve(listener, routes.clone()));
+
+        Ok(Self {
+            addr,
+            routes,
+            handle,
+        })
+    }
+
+    /// Add a route, matched after those added before it.
+    pub fn with_route(self, route: Route) -> Self {
+        self.routes
+            .lock()
+            .expect("routes lock poisoned")
+            .push(route);
+        self
+    }
+
+    /// Serve release `tag` with `asset_names`, as the releases API does at
+    /// `/repos/<repo>/releases/tags/<tag>`.
+    pub fn with_release(self, tag: &str, asset_names: &[&str]) -> Self {
+        let body = self.release_json(tag, asset_names);
+        self.with_route(Route::ok(format!("tags/{}", tag), body))
+    }
+
+    /// Address the server listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Base URL of the server, usable as a GitHub API URL.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Client sending GitHub API requests to this server.
+    pub fn github_client(&self) -> Result<GitHubClient> {
+        GitHubClient::with_api_base(self.url())
+    }
+
+    /// Release metadata for `tag` whose assets download from this server.
+    pub fn release_json(&self, tag: &str, asset_names: &[&str]) -> Vec<u8> {
+        let assets: Vec<serde_json::Value> = asset_names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                serde_json::json!({
+                    "id": i + 1,
+                    "name": name,
+                    "browser_download_url": format!("{}/download/{}", self.url(), name),
+                })
+            })
+            .collect();
+        serde_json::json!({ "tag_name": tag, "assets": assets })
+            .to_string()
+            .into_bytes()
+    }
+}
+
+impl Drop for MockReleaseServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Accept connections until the server is dropped, answering each request
+/// from `routes`.
+async fn serve(listener: TcpListener, routes: Arc<Mutex<Vec<Route>>>) {
+    while let Ok((mut stream, _)) = listener.accept().await {
+        let routes = routes.clone();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 4096];
+            let Ok(n) = stream.read(&mut buf).await else {
+                return;
+            };
+            let request = String::from_utf8_lossy(&buf[..n]);
+            let mut request_line = request
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .split_whitespace();
+            let method = request_line.next().unwrap_or("GET");
+            let path = request_line.next().unwrap_or("/");
+
+            let route = routes
+                .lock()
+                .expect("routes lock poisoned")
+                .iter()
+                .find(|route| path.contains(&route.prefix))
+                .cloned();
+            let response = match route {
+                Some(route) => route.respond(method),
+                None => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_vec(),
+            };
+            let _ = stream.write_all(&response).await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn with_release_serves_metadata_pointing_downloads_at_server() -> Result<()> {
+        //* Given
+        let server = MockReleaseServer::start()
+            .await?
+            .with_release("v1.0.0", &["ampd-linux-x86_64"])
+            .with_route(Route::ok("download/ampd-linux-x86_64", "ampd"));
+        let client = server.github_client()?;
+
+        //* When
+        let assets = client
+            .resolve_release_assets("v1.0.0", &["ampd-linux-x86_64"])
+            .await?;
+        let data = client.download_resolved_asset(&assets[0], 1).await?;
+
+        //* Then
+        assert_eq!(data, b"ampd");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fail_then_ok_route_fails_given_number_of_times() -> Result<()> {
+        //* Given
+        let server = MockReleaseServer::start()
+            .await?
+            .with_route(Route::fail_then_ok("flaky", "ok", 1));
+        let url = format!("{}/flaky", server.url());
+
+        //* When
+        let first = reqwest::get(&url).await?.status();
+        let second = reqwest::get(&url).await?.status();
+
+        //* Then
+        assert_eq!(first, reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(second, reqwest::StatusCode::OK);
+
+        Ok(())
+    }
+}
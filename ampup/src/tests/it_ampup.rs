@@ -1,11 +1,20 @@
-use std::env;
+use std::{path::PathBuf, sync::Arc};
 
 use anyhow::Result;
 use fs_err as fs;
 use tempfile::TempDir;
 
-use super::fixtures::{MockBinary, TempInstallDir};
-use crate::{DEFAULT_DOWNLOAD_JOBS, DEFAULT_REPO};
+use crate::{
+    DEFAULT_DOWNLOAD_JOBS, DEFAULT_REPO,
+    builder::{BuildError, BuildOptions, BuildSource, Builder, CargoOptions},
+    config::Config,
+    environment::MapEnv,
+    github::{ClientOptions, PullRequestHead},
+    manifest::VersionManifest,
+    shell::Shell,
+    testing::{MockBinary, TempInstallDir},
+    version_manager::{VersionError, VersionManager},
+};
 
 #[tokio::test]
 async fn init_creates_directory_structure() -> Result<()> {
@@ -16,6 +25,7 @@ async fn init_creates_directory_structure() -> Result<()> {
         true, // no_modify_path
         true, // no_install_latest
         None, // github_token
+        ClientOptions::default(),
     )
     .await?;
 
@@ -34,11 +44,24 @@ async fn init_fails_if_already_initialized() -> Result<()> {
     let temp = TempInstallDir::new()?;
 
     // First init should succeed
-    crate::commands::init::run(Some(temp.path().to_path_buf()), true, true, None).await?;
+    crate::commands::init::run(
+        Some(temp.path().to_path_buf()),
+        true,
+        true,
+        None,
+        ClientOptions::default(),
+    )
+    .await?;
 
     // Second init should fail
-    let result =
-        crate::commands::init::run(Some(temp.path().to_path_buf()), true, true, None).await;
+    let result = crate::commands::init::run(
+        Some(temp.path().to_path_buf()),
+        true,
+        true,
+        None,
+        ClientOptions::default(),
+    )
+    .await;
 
     assert!(
         result.is_err(),
@@ -59,7 +82,7 @@ async fn list_shows_no_versions_when_empty() -> Result<()> {
     let temp = TempInstallDir::new()?;
 
     // Just verify it doesn't crash - actual output goes to stdout
-    crate::commands::list::run(Some(temp.path().to_path_buf()))?;
+    crate::commands::list::run(Some(temp.path().to_path_buf()), false, false, false)?;
 
     Ok(())
 }
@@ -73,10 +96,10 @@ async fn list_shows_installed_versions() -> Result<()> {
     MockBinary::create(&temp, "v1.1.0")?;
 
     // Set current version
-    fs::write(temp.current_version_file(), "v1.0.0")?;
+    fs::write(temp.current_version_file(), "edgeandnode-amp/v1.0.0")?;
 
     // Just verify it doesn't crash - actual output goes to stdout
-    crate::commands::list::run(Some(temp.path().to_path_buf()))?;
+    crate::commands::list::run(Some(temp.path().to_path_buf()), false, false, false)?;
 
     Ok(())
 }
@@ -90,21 +113,68 @@ async fn use_switches_to_installed_version() -> Result<()> {
     MockBinary::create(&temp, "v1.1.0")?;
 
     // Switch to v1.0.0
-    crate::commands::use_version::run(Some(temp.path().to_path_buf()), Some("v1.0.0".to_string()))?;
+    crate::commands::use_version::run(
+        Some(temp.path().to_path_buf()),
+        Some("v1.0.0".to_string()),
+        None,
+        false,
+        false,
+        ClientOptions::default(),
+    )
+    .await?;
 
     // Verify current version
     let current = fs::read_to_string(temp.current_version_file())?;
-    assert_eq!(current.trim(), "v1.0.0");
+    assert_eq!(current.trim(), "edgeandnode-amp/v1.0.0");
 
-    // Verify symlink points to correct binary
+    // Verify the shim is in place
     let active_binary = temp.active_binary();
     assert!(active_binary.exists() || active_binary.is_symlink());
 
     // Switch to v1.1.0
-    crate::commands::use_version::run(Some(temp.path().to_path_buf()), Some("v1.1.0".to_string()))?;
+    crate::commands::use_version::run(
+        Some(temp.path().to_path_buf()),
+        Some("v1.1.0".to_string()),
+        None,
+        false,
+        false,
+        ClientOptions::default(),
+    )
+    .await?;
 
     let current = fs::read_to_string(temp.current_version_file())?;
-    assert_eq!(current.trim(), "v1.1.0");
+    assert_eq!(current.trim(), "edgeandnode-amp/v1.1.0");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn use_with_allow_downgrade_switches_to_older_version() -> Result<()> {
+    let temp = TempInstallDir::new()?;
+    MockBinary::create(&temp, "v1.0.0")?;
+    MockBinary::create(&temp, "v1.1.0")?;
+    crate::commands::use_version::run(
+        Some(temp.path().to_path_buf()),
+        Some("v1.1.0".to_string()),
+        None,
+        false,
+        false,
+        ClientOptions::default(),
+    )
+    .await?;
+
+    crate::commands::use_version::run(
+        Some(temp.path().to_path_buf()),
+        Some("v1.0.0".to_string()),
+        None,
+        true,
+        false,
+        ClientOptions::default(),
+    )
+    .await?;
+
+    let current = fs::read_to_string(temp.current_version_file())?;
+    assert_eq!(current.trim(), "edgeandnode-amp/v1.0.0");
 
     Ok(())
 }
@@ -116,7 +186,12 @@ async fn use_fails_for_non_existent_version() -> Result<()> {
     let result = crate::commands::use_version::run(
         Some(temp.path().to_path_buf()),
         Some("v99.99.99".to_string()),
-    );
+        None,
+        false,
+        false,
+        ClientOptions::default(),
+    )
+    .await;
 
     assert!(
         result.is_err(),
@@ -136,10 +211,18 @@ async fn uninstall_removes_version() -> Result<()> {
     MockBinary::create(&temp, "v1.1.0")?;
 
     // Set current version to v1.1.0
-    crate::commands::use_version::run(Some(temp.path().to_path_buf()), Some("v1.1.0".to_string()))?;
+    crate::commands::use_version::run(
+        Some(temp.path().to_path_buf()),
+        Some("v1.1.0".to_string()),
+        None,
+        false,
+        false,
+        ClientOptions::default(),
+    )
+    .await?;
 
     // Uninstall v1.0.0 (not current)
-    crate::commands::uninstall::run(Some(temp.path().to_path_buf()), "v1.0.0")?;
+    crate::commands::uninstall::run(Some(temp.path().to_path_buf()), "v1.0.0", None, false)?;
 
     assert!(
         !temp.version_dir("v1.0.0").exists(),
@@ -153,11 +236,143 @@ async fn uninstall_removes_version() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn uninstall_fails_for_version_pinned_by_instance() -> Result<()> {
+    let temp = TempInstallDir::new()?;
+    MockBinary::create(&temp, "v1.0.0")?;
+    crate::commands::instances::pin(
+        Some(temp.path().to_path_buf()),
+        "indexer-a",
+        "v1.0.0",
+        vec!["--config".to_string(), "a.toml".to_string()],
+    )?;
+
+    let wrapper = temp.bin_dir().join("indexer-a-ampd");
+    assert!(wrapper.exists(), "wrapper script should be created");
+
+    let result =
+        crate::commands::uninstall::run(Some(temp.path().to_path_buf()), "v1.0.0", None, false);
+
+    assert!(
+        result.is_err(),
+        "Expected uninstall to fail for a pinned version"
+    );
+    assert!(
+        temp.version_dir("v1.0.0").exists(),
+        "Pinned version should still exist"
+    );
+
+    crate::commands::instances::remove(Some(temp.path().to_path_buf()), "indexer-a")?;
+    assert!(!wrapper.exists(), "wrapper script should be removed");
+    crate::commands::uninstall::run(Some(temp.path().to_path_buf()), "v1.0.0", None, false)?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn uninstall_requires_force_for_version_held_with_pin() -> Result<()> {
+    let temp = TempInstallDir::new()?;
+    MockBinary::create(&temp, "v1.0.0")?;
+    crate::commands::pin::pin(
+        Some(temp.path().to_path_buf()),
+        Some("v1.0.0".to_string()),
+        None,
+    )?;
+
+    let result =
+        crate::commands::uninstall::run(Some(temp.path().to_path_buf()), "v1.0.0", None, false);
+
+    assert!(
+        result.is_err(),
+        "Expected uninstall to fail for a pinned version"
+    );
+    assert!(
+        temp.version_dir("v1.0.0").exists(),
+        "Pinned version should still exist"
+    );
+
+    crate::commands::uninstall::run(Some(temp.path().to_path_buf()), "v1.0.0", None, true)?;
+    assert!(
+        !temp.version_dir("v1.0.0").exists(),
+        "Version directory should be removed with --force"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn use_with_same_version_from_two_repos_requires_repo() -> Result<()> {
+    let temp = TempInstallDir::new()?;
+    MockBinary::create(&temp, "v1.0.0")?;
+    MockBinary::create(&temp, "myfork-amp/v1.0.0")?;
+
+    let result = crate::commands::use_version::run(
+        Some(temp.path().to_path_buf()),
+        Some("v1.0.0".to_string()),
+        None,
+        false,
+        false,
+        ClientOptions::default(),
+    )
+    .await;
+
+    assert!(
+        matches!(
+            result.as_ref().map_err(|err| err.downcast_ref::<VersionError>()),
+            Err(Some(VersionError::Ambiguous { keys, .. })) if keys.len() == 2
+        ),
+        "Expected an ambiguous version error: {:?}",
+        result
+    );
+
+    crate::commands::use_version::run(
+        Some(temp.path().to_path_buf()),
+        Some("v1.0.0".to_string()),
+        Some("myfork/amp".to_string()),
+        false,
+        false,
+        ClientOptions::default(),
+    )
+    .await?;
+    let current = fs::read_to_string(temp.current_version_file())?;
+    assert_eq!(current.trim(), "myfork-amp/v1.0.0");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn migrate_flat_layout_with_legacy_version_moves_it_and_active_links() -> Result<()> {
+    let temp = TempInstallDir::new()?;
+    MockBinary::create(&temp, "v1.0.0")?;
+    let legacy_dir = temp.versions_dir().join("v1.0.0");
+    fs::rename(temp.version_dir("v1.0.0"), &legacy_dir)?;
+    fs::remove_dir(temp.versions_dir().join("edgeandnode-amp"))?;
+    fs::write(temp.current_version_file(), "v1.0.0")?;
+    std::os::unix::fs::symlink(legacy_dir.join("ampd"), temp.active_binary())?;
+
+    // Read-only commands leave the layout to commands holding the lock
+    crate::commands::list::run(Some(temp.path().to_path_buf()), false, false, false)?;
+    assert!(legacy_dir.exists(), "list should not migrate");
+    VersionManager::new(Config::new(Some(temp.path().to_path_buf()))?).migrate_flat_layout();
+
+    assert!(!legacy_dir.exists(), "Legacy directory should be moved");
+    assert!(temp.version_binary("v1.0.0").exists());
+    let current = fs::read_to_string(temp.current_version_file())?;
+    assert_eq!(current.trim(), "edgeandnode-amp/v1.0.0");
+    assert!(
+        !temp.active_binary().is_symlink(),
+        "Legacy symlink should be replaced by a shim"
+    );
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn uninstall_fails_for_non_existent_version() -> Result<()> {
     let temp = TempInstallDir::new()?;
 
-    let result = crate::commands::uninstall::run(Some(temp.path().to_path_buf()), "v99.99.99");
+    let result =
+        crate::commands::uninstall::run(Some(temp.path().to_path_buf()), "v99.99.99", None, false);
 
     assert!(
         result.is_err(),
@@ -175,12 +390,24 @@ async fn install_latest_version() -> Result<()> {
     // Install latest version
     crate::commands::install::run(
         Some(temp.path().to_path_buf()),
-        DEFAULT_REPO.to_string(),
+        Some(DEFAULT_REPO.to_string()),
         None,
         None,
         None,
         None,
         DEFAULT_DOWNLOAD_JOBS,
+        None,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        None,
+        true,
+        ClientOptions::default(),
     )
     .await?;
 
@@ -204,12 +431,24 @@ async fn install_specific_version() -> Result<()> {
     let version = "v0.0.21";
     crate::commands::install::run(
         Some(temp.path().to_path_buf()),
-        DEFAULT_REPO.to_string(),
+        Some(DEFAULT_REPO.to_string()),
         None,
         Some(version.to_string()),
         None,
         None,
         DEFAULT_DOWNLOAD_JOBS,
+        None,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        None,
+        true,
+        ClientOptions::default(),
     )
     .await?;
 
@@ -231,24 +470,48 @@ async fn install_already_installed_version_switches_to_it() -> Result<()> {
     // Install once
     crate::commands::install::run(
         Some(temp.path().to_path_buf()),
-        DEFAULT_REPO.to_string(),
+        Some(DEFAULT_REPO.to_string()),
         None,
         Some(version.to_string()),
         None,
         None,
         DEFAULT_DOWNLOAD_JOBS,
+        None,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        None,
+        true,
+        ClientOptions::default(),
     )
     .await?;
 
     // Install again - should just switch to it
     crate::commands::install::run(
         Some(temp.path().to_path_buf()),
-        DEFAULT_REPO.to_string(),
+        Some(DEFAULT_REPO.to_string()),
         None,
         Some(version.to_string()),
         None,
         None,
         DEFAULT_DOWNLOAD_JOBS,
+        None,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        false,
+        None,
+        true,
+        ClientOptions::default(),
     )
     .await?;
 
@@ -304,41 +567,884 @@ async fn build_from_local_path_with_custom_name() -> Result<()> {
         fs::set_permissions(&mock_cargo, perms)?;
     }
 
-    // Temporarily modify PATH to use mock cargo
-    let original_path = env::var("PATH").unwrap_or_default();
-    let new_path = format!("{}:{}", mock_cargo_dir.path().display(), original_path);
-    unsafe {
-        env::set_var("PATH", &new_path);
-    }
+    // Resolve cargo to the mock through the builder's environment, leaving
+    // the process PATH untouched
+    let env = MapEnv::new().with("PATH", mock_cargo_dir.path());
+    let builder = Builder::with_env(
+        VersionManager::new(Config::new(Some(temp.path().to_path_buf()))?),
+        Arc::new(env),
+    );
 
     let custom_name = "my-custom-build";
-    let result = crate::commands::build::run(
-        Some(temp.path().to_path_buf()),
-        None, // repo
-        Some(fake_repo.path().to_path_buf()),
-        None, // branch
-        None, // commit
-        None, // pr
-        Some(custom_name.to_string()),
-        None, // jobs
+    builder
+        .build(
+            BuildSource::Local {
+                path: fake_repo.path().to_path_buf(),
+            },
+            BuildOptions {
+                name: Some(custom_name.to_string()),
+                jobs: None,
+                web_url: ClientOptions::default().web_url(),
+                reproducible: false,
+                force: false,
+                fresh: false,
+                cargo: CargoOptions::default(),
+            },
+        )
+        .await?;
+
+    assert!(
+        temp.version_dir(&format!("local/{}", custom_name)).exists(),
+        "Custom version not created"
+    );
+    assert!(
+        temp.version_binary(&format!("local/{}", custom_name))
+            .exists(),
+        "Binary not installed"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn build_without_activation_installs_but_keeps_active_version() -> Result<()> {
+    //* Given
+    let temp = TempInstallDir::new()?;
+    let fake_repo = TempDir::new()?;
+    let target_dir = fake_repo.path().join("target/release");
+    fs::create_dir_all(&target_dir)?;
+    let mock_cargo_dir = TempDir::new()?;
+    for (dir, name, script) in [
+        (&target_dir, "ampd", "#!/bin/sh\necho 'ampd test-version'"),
+        (
+            &target_dir,
+            "ampctl",
+            "#!/bin/sh\necho 'ampctl test-version'",
+        ),
+        (
+            &mock_cargo_dir.path().to_path_buf(),
+            "cargo",
+            "#!/bin/sh\nexit 0",
+        ),
+    ] {
+        use std::os::unix::fs::PermissionsExt;
+        fs::write(dir.join(name), script)?;
+        fs::set_permissions(dir.join(name), std::fs::Permissions::from_mode(0o755))?;
+    }
+    let builder = Builder::with_env(
+        VersionManager::new(Config::new(Some(temp.path().to_path_buf()))?),
+        Arc::new(MapEnv::new().with("PATH", mock_cargo_dir.path())),
     )
-    .await;
+    .with_activation(false);
+
+    //* When
+    builder
+        .build(
+            BuildSource::Local {
+                path: fake_repo.path().to_path_buf(),
+            },
+            BuildOptions {
+                name: Some("staged".to_string()),
+                jobs: None,
+                web_url: ClientOptions::default().web_url(),
+                reproducible: false,
+                force: false,
+                fresh: false,
+                cargo: CargoOptions::default(),
+            },
+        )
+        .await?;
+
+    //* Then
+    assert!(temp.version_binary("local/staged").exists());
+    assert!(
+        !temp.current_version_file().exists(),
+        "the build should not be activated"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn build_from_unchanged_local_checkout_skips_cargo_until_sources_change() -> Result<()> {
+    //* Given
+    let temp = TempInstallDir::new()?;
+    let repo = TempDir::new()?;
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args([
+                "-c",
+                "user.name=ampup",
+                "-c",
+                "user.email=ampup@example.com",
+            ])
+            .args(args)
+            .current_dir(repo.path())
+            .stdout(std::process::Stdio::null())
+            .status()
+            .expect("should run git");
+        assert!(status.success(), "git {:?} failed", args);
+    };
+    git(&["init", "-q"]);
+    fs::write(repo.path().join(".gitignore"), "target/\n")?;
+    fs::write(repo.path().join("lib.rs"), "fn main() {}\n")?;
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "initial"]);
+    let release = repo.path().join("target/release");
+    fs::create_dir_all(&release)?;
+    for binary in ["ampd", "ampctl"] {
+        fs::write(release.join(binary), "#!/bin/sh\necho 'test-version'")?;
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(release.join(binary), std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    // cargo counts its builds in a file outside the checkout
+    let bin = TempDir::new()?;
+    let runs = bin.path().join("runs");
+    fs::write(
+        bin.path().join("cargo"),
+        format!(
+            "#!/bin/sh\n[ \"$1\" = build ] && echo run >> '{}'\nexit 0\n",
+            runs.display()
+        ),
+    )?;
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(
+            bin.path().join("cargo"),
+            std::fs::Permissions::from_mode(0o755),
+        )?;
+    }
+    let path = std::env::join_paths(std::iter::once(bin.path().to_path_buf()).chain(
+        std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default()),
+    ))?;
+    let builder = Builder::with_env(
+        VersionManager::new(Config::new(Some(temp.path().to_path_buf()))?),
+        Arc::new(MapEnv::new().with("PATH", path)),
+    );
+    let build = || {
+        builder.build(
+            BuildSource::Local {
+                path: repo.path().to_path_buf(),
+            },
+            BuildOptions {
+                name: None,
+                jobs: None,
+                web_url: ClientOptions::default().web_url(),
+                reproducible: false,
+                force: false,
+                fresh: false,
+                cargo: CargoOptions::default(),
+            },
+        )
+    };
+
+    //* When
+    build().await?;
+    build().await?;
+    let runs_unchanged = fs::read_to_string(&runs)?.lines().count();
+    fs::write(repo.path().join("lib.rs"), "fn main() { todo!() }\n")?;
+    build().await?;
+    let runs_after_edit = fs::read_to_string(&runs)?.lines().count();
+
+    //* Then
+    assert_eq!(runs_unchanged, 1, "unchanged rebuild should skip cargo");
+    assert_eq!(runs_after_edit, 2, "edited sources should be rebuilt");
+
+    Ok(())
+}
 
-    // Restore PATH
-    unsafe {
-        env::set_var("PATH", &original_path);
+#[tokio::test]
+async fn build_of_local_checkout_with_uncommitted_changes_labels_it_dirty() -> Result<()> {
+    //* Given
+    let temp = TempInstallDir::new()?;
+    let repo = TempDir::new()?;
+    let git = |args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .args([
+                "-c",
+                "user.name=ampup",
+                "-c",
+                "user.email=ampup@example.com",
+            ])
+            .args(args)
+            .current_dir(repo.path())
+            .output()
+            .expect("should run git");
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+    git(&["init", "-q"]);
+    fs::write(repo.path().join(".gitignore"), "target/\n")?;
+    fs::write(repo.path().join("lib.rs"), "fn main() {}\n")?;
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "initial"]);
+    let hash = git(&["rev-parse", "--short=8", "HEAD"]);
+    fs::write(repo.path().join("lib.rs"), "fn main() { todo!() }\n")?;
+    let release = repo.path().join("target/release");
+    fs::create_dir_all(&release)?;
+    for binary in ["ampd", "ampctl"] {
+        fs::write(release.join(binary), "#!/bin/sh\necho 'test-version'")?;
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(release.join(binary), std::fs::Permissions::from_mode(0o755))?;
     }
 
-    result?;
+    let bin = TempDir::new()?;
+    fs::write(bin.path().join("cargo"), "#!/bin/sh\nexit 0\n")?;
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(
+            bin.path().join("cargo"),
+            std::fs::Permissions::from_mode(0o755),
+        )?;
+    }
+    let path = std::env::join_paths(std::iter::once(bin.path().to_path_buf()).chain(
+        std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default()),
+    ))?;
+    let builder = Builder::with_env(
+        VersionManager::new(Config::new(Some(temp.path().to_path_buf()))?),
+        Arc::new(MapEnv::new().with("PATH", path)),
+    );
 
+    //* When
+    builder
+        .build(
+            BuildSource::Local {
+                path: repo.path().to_path_buf(),
+            },
+            BuildOptions {
+                name: None,
+                jobs: None,
+                web_url: ClientOptions::default().web_url(),
+                reproducible: false,
+                force: false,
+                fresh: false,
+                cargo: CargoOptions::default(),
+            },
+        )
+        .await?;
+
+    //* Then
+    let version_dir = temp.version_dir(&format!("local/local-{}-dirty", hash));
+    assert!(version_dir.is_dir(), "dirty build should be labeled -dirty");
+    let manifest = VersionManifest::load(&version_dir)?;
     assert!(
-        temp.version_dir(custom_name).exists(),
-        "Custom version not created"
+        manifest.dirty_diff.is_some(),
+        "diff hash should be recorded"
     );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn build_with_features_and_extra_args_passes_them_to_cargo_and_labels_the_build() -> Result<()>
+{
+    //* Given
+    let temp = TempInstallDir::new()?;
+    let repo = TempDir::new()?;
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args([
+                "-c",
+                "user.name=ampup",
+                "-c",
+                "user.email=ampup@example.com",
+            ])
+            .args(args)
+            .current_dir(repo.path())
+            .stdout(std::process::Stdio::null())
+            .status()
+            .expect("should run git");
+        assert!(status.success(), "git {:?} failed", args);
+    };
+    git(&["init", "-q"]);
+    fs::write(repo.path().join(".gitignore"), "target/\n")?;
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "initial"]);
+    let release = repo.path().join("target/release");
+    fs::create_dir_all(&release)?;
+    for binary in ["ampd", "ampctl"] {
+        fs::write(release.join(binary), "#!/bin/sh\necho 'test-version'")?;
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(release.join(binary), std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    // cargo records its arguments in a file outside the checkout
+    let bin = TempDir::new()?;
+    let args = bin.path().join("args");
+    fs::write(
+        bin.path().join("cargo"),
+        format!(
+            "#!/bin/sh\n[ \"$1\" = build ] && echo \"$@\" >> '{}'\nexit 0\n",
+            args.display()
+        ),
+    )?;
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(
+            bin.path().join("cargo"),
+            std::fs::Permissions::from_mode(0o755),
+        )?;
+    }
+    let path = std::env::join_paths(std::iter::once(bin.path().to_path_buf()).chain(
+        std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default()),
+    ))?;
+    let builder = Builder::with_env(
+        VersionManager::new(Config::new(Some(temp.path().to_path_buf()))?),
+        Arc::new(MapEnv::new().with("PATH", path)),
+    );
+
+    //* When
+    builder
+        .build(
+            BuildSource::Local {
+                path: repo.path().to_path_buf(),
+            },
+            BuildOptions {
+                name: None,
+                jobs: None,
+                web_url: ClientOptions::default().web_url(),
+                reproducible: false,
+                force: false,
+                fresh: false,
+                cargo: CargoOptions {
+                    features: vec!["postgres".to_string(), "otel,postgres".to_string()],
+                    no_default_features: true,
+                    extra_args: vec!["--timings".to_string()],
+                    ..Default::default()
+                },
+            },
+        )
+        .await?;
+
+    //* Then
+    let args = fs::read_to_string(&args)?;
     assert!(
-        temp.version_binary(custom_name).exists(),
-        "Binary not installed"
+        args.trim_end()
+            .ends_with("--features otel,postgres --no-default-features --timings"),
+        "unexpected cargo arguments: {}",
+        args
+    );
+    let labels: Vec<String> = fs::read_dir(temp.path().join("versions/local"))?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<_>>()?;
+    assert_eq!(labels.len(), 1, "expected one build, got {:?}", labels);
+    assert!(
+        labels[0].ends_with("+nodefault.otel.postgres"),
+        "label should name the features: {}",
+        labels[0]
     );
 
     Ok(())
 }
+
+#[tokio::test]
+async fn build_of_branch_twice_reuses_cached_clone_unless_fresh() -> Result<()> {
+    //* Given
+    let temp = TempInstallDir::new()?;
+    let web = TempDir::new()?;
+    let upstream = web.path().join("owner/amp");
+    fs::create_dir_all(&upstream)?;
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args([
+                "-c",
+                "user.name=ampup",
+                "-c",
+                "user.email=ampup@example.com",
+            ])
+            .args(args)
+            .current_dir(&upstream)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .expect("should run git");
+        assert!(status.success(), "git {:?} failed", args);
+    };
+    git(&["init", "-q", "-b", "main"]);
+    fs::write(upstream.join(".gitignore"), "target/\n")?;
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "initial"]);
+    fs::rename(&upstream, web.path().join("owner/amp.git"))?;
+
+    // cargo notes whether it found a target dir from an earlier build
+    let bin = TempDir::new()?;
+    let runs = bin.path().join("runs");
+    fs::write(
+        bin.path().join("cargo"),
+        format!(
+            "#!/bin/sh\n[ \"$1\" = build ] || exit 0\n\
+             if [ -d target ]; then echo warm >> '{runs}'; else echo cold >> '{runs}'; fi\n\
+             mkdir -p target/release\n\
+             for b in ampd ampctl; do\n\
+             printf '#!/bin/sh\\necho test-version\\n' > target/release/$b\n\
+             chmod +x target/release/$b\n\
+             done\n",
+            runs = runs.display()
+        ),
+    )?;
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(
+            bin.path().join("cargo"),
+            std::fs::Permissions::from_mode(0o755),
+        )?;
+    }
+    let path = std::env::join_paths(std::iter::once(bin.path().to_path_buf()).chain(
+        std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default()),
+    ))?;
+    let builder = Builder::with_env(
+        VersionManager::new(Config::new(Some(temp.path().to_path_buf()))?),
+        Arc::new(MapEnv::new().with("PATH", path)),
+    );
+    let build = |fresh: bool| {
+        builder.build(
+            BuildSource::Branch {
+                repo: "owner/amp".to_string(),
+                branch: "main".to_string(),
+            },
+            BuildOptions {
+                name: None,
+                jobs: None,
+                web_url: web.path().display().to_string(),
+                reproducible: false,
+                force: false,
+                fresh,
+                cargo: CargoOptions::default(),
+            },
+        )
+    };
+
+    //* When
+    build(false).await?;
+    build(false).await?;
+    build(true).await?;
+
+    //* Then
+    assert_eq!(fs::read_to_string(&runs)?, "cold\nwarm\ncold\n");
+    assert!(temp.path().join("cache/src/owner-amp/.git").is_dir());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn build_of_commit_outside_shallow_clone_fetches_full_history() -> Result<()> {
+    //* Given
+    let temp = TempInstallDir::new()?;
+    let web = TempDir::new()?;
+    let upstream = web.path().join("owner/amp.git");
+    fs::create_dir_all(&upstream)?;
+    let git = |args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .args([
+                "-c",
+                "user.name=ampup",
+                "-c",
+                "user.email=ampup@example.com",
+            ])
+            .args(args)
+            .current_dir(&upstream)
+            .output()
+            .expect("should run git");
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+    git(&["init", "-q", "-b", "main"]);
+    fs::write(upstream.join(".gitignore"), "target/\n")?;
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "initial"]);
+    git(&["checkout", "-q", "-b", "feature"]);
+    git(&["commit", "-q", "--allow-empty", "-m", "feature"]);
+    let commit = git(&["rev-parse", "--short=8", "HEAD"]);
+    git(&["checkout", "-q", "main"]);
+    git(&["commit", "-q", "--allow-empty", "-m", "newer"]);
+
+    // cargo leaves stub binaries where the build expects them
+    let bin = TempDir::new()?;
+    fs::write(
+        bin.path().join("cargo"),
+        "#!/bin/sh\n[ \"$1\" = build ] || exit 0\n\
+         mkdir -p target/release\n\
+         for b in ampd ampctl; do\n\
+         printf '#!/bin/sh\\necho test-version\\n' > target/release/$b\n\
+         chmod +x target/release/$b\n\
+         done\n",
+    )?;
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(
+            bin.path().join("cargo"),
+            std::fs::Permissions::from_mode(0o755),
+        )?;
+    }
+    let path = std::env::join_paths(std::iter::once(bin.path().to_path_buf()).chain(
+        std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default()),
+    ))?;
+    let builder = Builder::with_env(
+        VersionManager::new(Config::new(Some(temp.path().to_path_buf()))?),
+        Arc::new(MapEnv::new().with("PATH", path)),
+    );
+
+    //* When
+    builder
+        .build(
+            BuildSource::Commit {
+                repo: "owner/amp".to_string(),
+                commit: commit.clone(),
+            },
+            BuildOptions {
+                name: None,
+                jobs: None,
+                web_url: format!("file://{}", web.path().display()),
+                reproducible: false,
+                force: false,
+                fresh: false,
+                cargo: CargoOptions::default(),
+            },
+        )
+        .await?;
+
+    //* Then
+    let versions: Vec<String> = fs::read_dir(temp.path().join("versions/owner-amp"))?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<_>>()?;
+    assert_eq!(versions, vec![format!("commit-{}", commit)]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn build_of_pr_from_fork_fetches_head_branch_from_fork() -> Result<()> {
+    //* Given
+    let temp = TempInstallDir::new()?;
+    let web = TempDir::new()?;
+    let git = |dir: &std::path::Path, args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .args([
+                "-c",
+                "user.name=ampup",
+                "-c",
+                "user.email=ampup@example.com",
+            ])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .expect("should run git");
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+    // The base repository has no pull/<n>/head refs; only the fork has the commit
+    let base = web.path().join("owner/amp.git");
+    fs::create_dir_all(&base)?;
+    git(&base, &["init", "-q", "-b", "main"]);
+    fs::write(base.join(".gitignore"), "target/\n")?;
+    git(&base, &["add", "."]);
+    git(&base, &["commit", "-q", "-m", "initial"]);
+    let fork = web.path().join("contributor/amp.git");
+    git(
+        web.path(),
+        &[
+            "clone",
+            "-q",
+            base.to_str().unwrap(),
+            fork.to_str().unwrap(),
+        ],
+    );
+    git(&fork, &["checkout", "-q", "-b", "fix"]);
+    git(&fork, &["commit", "-q", "--allow-empty", "-m", "fix"]);
+    let sha = git(&fork, &["rev-parse", "HEAD"]);
+    // The branch moves on after the PR head was looked up
+    git(&fork, &["commit", "-q", "--allow-empty", "-m", "later"]);
+
+    // cargo leaves stub binaries where the build expects them
+    let bin = TempDir::new()?;
+    fs::write(
+        bin.path().join("cargo"),
+        "#!/bin/sh\n[ \"$1\" = build ] || exit 0\n\
+         mkdir -p target/release\n\
+         for b in ampd ampctl; do\n\
+         printf '#!/bin/sh\\necho test-version\\n' > target/release/$b\n\
+         chmod +x target/release/$b\n\
+         done\n",
+    )?;
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(
+            bin.path().join("cargo"),
+            std::fs::Permissions::from_mode(0o755),
+        )?;
+    }
+    let path = std::env::join_paths(std::iter::once(bin.path().to_path_buf()).chain(
+        std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default()),
+    ))?;
+    let builder = Builder::with_env(
+        VersionManager::new(Config::new(Some(temp.path().to_path_buf()))?),
+        Arc::new(MapEnv::new().with("PATH", path)),
+    );
+
+    //* When
+    builder
+        .build(
+            BuildSource::Pr {
+                repo: "owner/amp".to_string(),
+                number: 7,
+                head: Some(PullRequestHead {
+                    sha: sha.clone(),
+                    repo: "contributor/amp".to_string(),
+                    branch: "fix".to_string(),
+                }),
+            },
+            BuildOptions {
+                name: None,
+                jobs: None,
+                web_url: web.path().display().to_string(),
+                reproducible: false,
+                force: false,
+                fresh: false,
+                cargo: CargoOptions::default(),
+            },
+        )
+        .await?;
+
+    //* Then
+    assert!(
+        temp.version_dir(&format!("owner-amp/pr-7-{}", &sha[..8]))
+            .is_dir(),
+        "PR build should be of the fork's head"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn build_of_pr_from_deleted_fork_falls_back_to_base_pull_ref() -> Result<()> {
+    //* Given
+    let temp = TempInstallDir::new()?;
+    let web = TempDir::new()?;
+    let git = |dir: &std::path::Path, args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .args([
+                "-c",
+                "user.name=ampup",
+                "-c",
+                "user.email=ampup@example.com",
+            ])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .expect("should run git");
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+    // The fork is gone; only the base repository's pull/<n>/head has the commit
+    let base = web.path().join("owner/amp.git");
+    fs::create_dir_all(&base)?;
+    git(&base, &["init", "-q", "-b", "main"]);
+    fs::write(base.join(".gitignore"), "target/\n")?;
+    git(&base, &["add", "."]);
+    git(&base, &["commit", "-q", "-m", "initial"]);
+    git(&base, &["checkout", "-q", "-b", "fix"]);
+    git(&base, &["commit", "-q", "--allow-empty", "-m", "fix"]);
+    let sha = git(&base, &["rev-parse", "HEAD"]);
+    git(&base, &["update-ref", "refs/pull/7/head", &sha]);
+    git(&base, &["checkout", "-q", "main"]);
+    git(&base, &["branch", "-q", "-D", "fix"]);
+
+    // cargo leaves stub binaries where the build expects them
+    let bin = TempDir::new()?;
+    fs::write(
+        bin.path().join("cargo"),
+        "#!/bin/sh\n[ \"$1\" = build ] || exit 0\n\
+         mkdir -p target/release\n\
+         for b in ampd ampctl; do\n\
+         printf '#!/bin/sh\\necho test-version\\n' > target/release/$b\n\
+         chmod +x target/release/$b\n\
+         done\n",
+    )?;
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(
+            bin.path().join("cargo"),
+            std::fs::Permissions::from_mode(0o755),
+        )?;
+    }
+    let path = std::env::join_paths(std::iter::once(bin.path().to_path_buf()).chain(
+        std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default()),
+    ))?;
+    let builder = Builder::with_env(
+        VersionManager::new(Config::new(Some(temp.path().to_path_buf()))?),
+        Arc::new(MapEnv::new().with("PATH", path)),
+    );
+
+    //* When
+    builder
+        .build(
+            BuildSource::Pr {
+                repo: "owner/amp".to_string(),
+                number: 7,
+                head: Some(PullRequestHead {
+                    sha: sha.clone(),
+                    repo: "contributor/amp".to_string(),
+                    branch: "fix".to_string(),
+                }),
+            },
+            BuildOptions {
+                name: None,
+                jobs: None,
+                web_url: web.path().display().to_string(),
+                reproducible: false,
+                force: false,
+                fresh: false,
+                cargo: CargoOptions::default(),
+            },
+        )
+        .await?;
+
+    //* Then
+    assert!(
+        temp.version_dir(&format!("owner-amp/pr-7-{}", &sha[..8]))
+            .is_dir(),
+        "PR build should be of the head the base repository keeps"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn build_with_failing_cargo_points_at_log_with_its_output() -> Result<()> {
+    //* Given
+    let temp = TempInstallDir::new()?;
+    let checkout = TempDir::new()?;
+    let bin = TempDir::new()?;
+    fs::write(
+        bin.path().join("cargo"),
+        "#!/bin/sh\n[ \"$1\" = build ] || exit 0\necho 'error: could not compile `ampd`' >&2\nexit 101\n",
+    )?;
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(
+            bin.path().join("cargo"),
+            std::fs::Permissions::from_mode(0o755),
+        )?;
+    }
+    let builder = Builder::with_env(
+        VersionManager::new(Config::new(Some(temp.path().to_path_buf()))?),
+        Arc::new(MapEnv::new().with("PATH", bin.path())),
+    )
+    .with_summarized_output(true);
+
+    //* When
+    let result = builder
+        .build(
+            BuildSource::Local {
+                path: checkout.path().to_path_buf(),
+            },
+            BuildOptions {
+                name: Some("broken".to_string()),
+                jobs: None,
+                web_url: ClientOptions::default().web_url(),
+                reproducible: false,
+                force: false,
+                fresh: false,
+                cargo: CargoOptions::default(),
+            },
+        )
+        .await;
+
+    //* Then
+    let err = result.expect_err("build should fail");
+    let Some(BuildError::CargoBuildFailed { log: Some(log) }) = err.downcast_ref::<BuildError>()
+    else {
+        panic!("unexpected error: {:#}", err);
+    };
+    assert!(log.starts_with(temp.path().join("logs")));
+    assert!(fs::read_to_string(log)?.contains("could not compile `ampd`"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn build_without_cargo_on_path_fails_with_command_not_found() -> Result<()> {
+    let temp = TempInstallDir::new()?;
+    let fake_repo = TempDir::new()?;
+    let empty_bin = TempDir::new()?;
+
+    let builder = Builder::with_env(
+        VersionManager::new(Config::new(Some(temp.path().to_path_buf()))?),
+        Arc::new(MapEnv::new().with("PATH", empty_bin.path())),
+    );
+    let result = builder
+        .build(
+            BuildSource::Local {
+                path: fake_repo.path().to_path_buf(),
+            },
+            BuildOptions {
+                name: Some("no-cargo".to_string()),
+                jobs: None,
+                web_url: ClientOptions::default().web_url(),
+                reproducible: false,
+                force: false,
+                fresh: false,
+                cargo: CargoOptions::default(),
+            },
+        )
+        .await;
+
+    let err = result.expect_err("build should fail without cargo");
+    assert!(
+        matches!(
+            err.downcast_ref::<BuildError>(),
+            Some(BuildError::CommandNotFound { command }) if command == "cargo"
+        ),
+        "unexpected error: {:#}",
+        err
+    );
+    assert!(!temp.version_dir("local/no-cargo").exists());
+
+    Ok(())
+}
+
+#[test]
+fn config_without_install_dir_prefers_xdg_config_home() -> Result<()> {
+    let env = MapEnv::new()
+        .with("HOME", "/home/alice")
+        .with("XDG_CONFIG_HOME", "/home/alice/.config");
+
+    let config = Config::from_env(None, &env)?;
+
+    assert_eq!(config.amp_dir, PathBuf::from("/home/alice/.config/.amp"));
+    assert_eq!(
+        config.versions_dir,
+        PathBuf::from("/home/alice/.config/.amp/versions")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn config_without_home_fails() {
+    let result = Config::from_env(None, &MapEnv::new());
+
+    assert!(result.is_err(), "no home directory should be an error");
+}
+
+#[test]
+fn shell_profile_path_with_zdotdir_uses_zdotdir() -> Result<()> {
+    let env = MapEnv::new()
+        .with("SHELL", "/usr/bin/zsh")
+        .with("HOME", "/home/alice")
+        .with("ZDOTDIR", "/home/alice/.zsh");
+
+    let shell = Shell::detect(&env).expect("zsh should be detected");
+    let profile = shell.profile_path(&env)?;
+
+    assert_eq!(profile, PathBuf::from("/home/alice/.zsh/.zshenv"));
+
+    Ok(())
+}
+
+#[test]
+fn shell_detect_with_unsupported_shell_returns_none() {
+    let env = MapEnv::new().with("SHELL", "/usr/bin/tcsh");
+
+    assert!(Shell::detect(&env).is_none());
+}
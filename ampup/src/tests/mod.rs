@@ -1,2 +1 @@
-mod fixtures;
 mod it_ampup;
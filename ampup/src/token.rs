@@ -1,32 +1,100 @@
-use std::process::Command;
+use crate::{environment::Environment, ui};
+
+/// Environment variable consulted when no repository-specific token applies.
+pub const GITHUB_TOKEN_ENV: &str = "GITHUB_TOKEN";
+
+/// Repository- and host-specific token sources.
+#[derive(Debug, Default)]
+pub struct TokenLookup<'a> {
+    /// Shell command printing a token (`[repos."owner/repo"] token_command`)
+    pub command: Option<&'a str>,
+    /// GitHub Enterprise host passed to `gh auth token --hostname`
+    pub hostname: Option<&'a str>,
+}
 
 /// Resolve a GitHub token using the following fallback chain:
 ///
-/// 1. Explicit token passed via `--github-token` flag or `GITHUB_TOKEN` env var
-/// 2. Token from `gh auth token` (GitHub CLI)
-/// 3. `None` (unauthenticated — lower rate limits)
+/// 1. Explicit token passed via `--github-token` flag
+/// 2. Output of the repository's configured `token_command`
+/// 3. `GITHUB_TOKEN` env var
+/// 4. Token from `gh auth token` (GitHub CLI), for `hostname` when given
+/// 5. `None` (unauthenticated — lower rate limits)
+///
+/// The repository command sits above `GITHUB_TOKEN` so a globally exported
+/// token for the public repo doesn't shadow a private fork's token.
 ///
 /// Note: `--github-token` values may be visible in process listings (`ps aux`).
 /// Prefer `GITHUB_TOKEN` env var or `gh auth token` for sensitive environments.
-pub fn resolve_github_token(explicit: Option<String>) -> Option<String> {
+pub fn resolve_github_token(
+    explicit: Option<String>,
+    lookup: &TokenLookup,
+    env: &dyn Environment,
+) -> Option<String> {
     // Filter out empty/whitespace-only tokens so they fall through to the
-    // gh CLI fallback instead of sending a useless `Bearer ` header.
-    if let Some(token) = explicit
-        && !token.trim().is_empty()
-    {
-        return Some(token);
-    }
+    // next source instead of sending a useless `Bearer ` header.
+    non_empty(explicit)
+        .or_else(|| {
+            lookup
+                .command
+                .and_then(|command| run_token_command(command, env))
+        })
+        .or_else(|| non_empty(env.var(GITHUB_TOKEN_ENV)))
+        .or_else(|| try_gh_auth_token(lookup.hostname, env))
+}
+
+fn non_empty(token: Option<String>) -> Option<String> {
+    token.filter(|token| !token.trim().is_empty())
+}
 
-    try_gh_auth_token()
+/// Run a configured `token_command` through the shell and return its trimmed
+/// stdout.
+///
+/// Failures are reported as warnings and fall through to the next source, so
+/// a broken password-manager integration doesn't block public installs.
+fn run_token_command(command: &str, env: &dyn Environment) -> Option<String> {
+    let output = env
+        .command("sh")
+        .args(["-c", command])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::inherit())
+        .output();
+
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            ui::warn!("token_command exited with {}, ignoring", output.status);
+            return None;
+        }
+        Err(err) => {
+            ui::warn!("Failed to run token_command: {}", err);
+            return None;
+        }
+    };
+
+    let token = non_empty(
+        String::from_utf8(output.stdout)
+            .ok()
+            .map(|s| s.trim().to_string()),
+    );
+    if token.is_none() {
+        ui::warn!("token_command printed no token, ignoring");
+    }
+    token
 }
 
 /// Attempt to retrieve a token from the GitHub CLI.
 ///
-/// Runs `gh auth token` as a subprocess. Returns `None` on any failure:
-/// `gh` not installed, not logged in, timeout, etc.
-fn try_gh_auth_token() -> Option<String> {
-    let output = Command::new("gh")
-        .args(["auth", "token"])
+/// Runs `gh auth token` as a subprocess, scoped to `hostname` for GitHub
+/// Enterprise. Returns `None` on any failure: `gh` not installed, not logged
+/// in, timeout, etc.
+fn try_gh_auth_token(hostname: Option<&str>, env: &dyn Environment) -> Option<String> {
+    let mut command = env.command("gh");
+    command.args(["auth", "token"]);
+    if let Some(hostname) = hostname {
+        command.args(["--hostname", hostname]);
+    }
+    let output = command
         .stdin(std::process::Stdio::null())
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::null())
@@ -49,6 +117,18 @@ fn try_gh_auth_token() -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::environment::MapEnv;
+
+    /// Environment without `GITHUB_TOKEN` and with no `gh` on `PATH`.
+    fn empty_env() -> MapEnv {
+        MapEnv::new().with("PATH", "/nonexistent")
+    }
+
+    /// Environment without `GITHUB_TOKEN` but with the real `PATH`, for
+    /// running `token_command` through `sh`.
+    fn env_with_shell() -> MapEnv {
+        MapEnv::new().with("PATH", std::env::var_os("PATH").unwrap_or_default())
+    }
 
     #[test]
     fn resolve_github_token_with_explicit_token_returns_explicit() {
@@ -56,7 +136,7 @@ mod tests {
         let explicit = Some("my-explicit-token".to_string());
 
         //* When
-        let result = resolve_github_token(explicit);
+        let result = resolve_github_token(explicit, &TokenLookup::default(), &empty_env());
 
         //* Then
         assert_eq!(
@@ -72,12 +152,11 @@ mod tests {
         let explicit = Some("".to_string());
 
         //* When — empty token should be treated as absent, not as a valid credential
-        let result = resolve_github_token(explicit);
+        let result = resolve_github_token(explicit, &TokenLookup::default(), &empty_env());
 
-        //* Then — result depends on gh CLI availability, but must NOT be Some("")
-        assert_ne!(
-            result,
-            Some("".to_string()),
+        //* Then
+        assert_eq!(
+            result, None,
             "should not return an empty string as a valid token"
         );
     }
@@ -88,13 +167,63 @@ mod tests {
         let explicit = Some("   ".to_string());
 
         //* When
-        let result = resolve_github_token(explicit);
+        let result = resolve_github_token(explicit, &TokenLookup::default(), &empty_env());
 
         //* Then
-        assert_ne!(
-            result,
-            Some("   ".to_string()),
+        assert_eq!(
+            result, None,
             "should not return a whitespace-only string as a valid token"
         );
     }
+
+    #[test]
+    fn resolve_github_token_with_token_command_returns_command_output() {
+        //* Given
+        let lookup = TokenLookup {
+            command: Some("echo '  fork-token  '"),
+            hostname: None,
+        };
+
+        //* When
+        let result = resolve_github_token(
+            None,
+            &lookup,
+            &env_with_shell().with(GITHUB_TOKEN_ENV, "env-token"),
+        );
+
+        //* Then
+        assert_eq!(
+            result,
+            Some("fork-token".to_string()),
+            "repository command should be used before GITHUB_TOKEN and trimmed"
+        );
+    }
+
+    #[test]
+    fn resolve_github_token_with_explicit_token_skips_token_command() {
+        //* Given
+        let lookup = TokenLookup {
+            command: Some("echo fork-token"),
+            hostname: None,
+        };
+
+        //* When
+        let result =
+            resolve_github_token(Some("flag-token".to_string()), &lookup, &env_with_shell());
+
+        //* Then
+        assert_eq!(result, Some("flag-token".to_string()));
+    }
+
+    #[test]
+    fn resolve_github_token_with_env_token_returns_env_token() {
+        //* Given
+        let env = empty_env().with(GITHUB_TOKEN_ENV, "env-token");
+
+        //* When
+        let result = resolve_github_token(None, &TokenLookup::default(), &env);
+
+        //* Then
+        assert_eq!(result, Some("env-token".to_string()));
+    }
 }
@@ -0,0 +1,215 @@
+use std::{
+    os::unix::fs::symlink,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+use crate::{config::Config, ui};
+
+/// Changes to an installation that are undone unless committed, so a failure
+/// partway through an install, build or self-update leaves the previous
+/// consistent state: paths created for it are removed and the active version
+/// (`.version`, and the `bin/` symlinks of installs predating shims) is
+/// restored. Rolls back when dropped
+/// without [`commit`](Self::commit).
+pub struct InstallTransaction {
+    /// Active version before the transaction, restored on rollback
+    active: Option<ActiveState>,
+    /// Paths removed on rollback, in reverse order
+    created: Vec<PathBuf>,
+    committed: bool,
+}
+
+/// The `.version` file and active symlinks as they were, with `None` for
+/// ones that did not exist or are shims.
+struct ActiveState {
+    version_file: PathBuf,
+    version: Option<String>,
+    links: Vec<(PathBuf, Option<PathBuf>)>,
+}
+
+impl InstallTransaction {
+    /// Begin a transaction on the installation at `config`, recording its
+    /// active version to restore on rollback.
+    pub fn begin(config: &Config) -> Result<Self> {
+        let version_file = config.current_version_file();
+        let version = if version_file.exists() {
+            Some(fs::read_to_string(&version_file).context("Failed to read current version file")?)
+        } else {
+            None
+        };
+        let links = [config.active_binary_path(), config.active_ampctl_path()]
+            .into_iter()
+            .map(|link| {
+                let target = fs::read_link(&link).ok();
+                (link, target)
+            })
+            .collect();
+        Ok(Self {
+            active: Some(ActiveState {
+                version_file,
+                version,
+                links,
+            }),
+            created: Vec::new(),
+            committed: false,
+        })
+    }
+
+    /// Begin a transaction that only removes the paths it is told about, for
+    /// changes outside an installation such as replacing the ampup executable.
+    pub fn detached() -> Self {
+        Self {
+            active: None,
+            created: Vec::new(),
+            committed: false,
+        }
+    }
+
+    /// Remove the file or directory at `path` on rollback.
+    pub fn remove_on_rollback(&mut self, path: impl Into<PathBuf>) {
+        self.created.push(path.into());
+    }
+
+    /// Keep `path` on rollback, e.g. once a version directory holds a
+    /// complete version that is only left inactive.
+    pub fn keep(&mut self, path: &Path) {
+        self.created.retain(|created| created != path);
+    }
+
+    /// Keep every change.
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+
+    /// Undo the changes, returning whether there were any.
+    fn rollback(&mut self) -> Result<bool> {
+        let mut changed = false;
+        // Restore the links first, as they may point into removed paths
+        if let Some(active) = &self.active {
+            for (link, target) in &active.links {
+                if fs::read_link(link).ok() == *target {
+                    continue;
+                }
+                // Shims don't depend on the active version, so they're kept
+                let Some(target) = target else {
+                    if link.is_symlink() {
+                        fs::remove_file(link)?;
+                        changed = true;
+                    }
+                    continue;
+                };
+                if link.exists() || link.is_symlink() {
+                    fs::remove_file(link)?;
+                }
+                symlink(target, link)
+                    .with_context(|| format!("Failed to restore {}", link.display()))?;
+                changed = true;
+            }
+            if fs::read_to_string(&active.version_file).ok() != active.version {
+                match &active.version {
+                    Some(version) => fs::write(&active.version_file, version)?,
+                    None => fs::remove_file(&active.version_file)?,
+                }
+                changed = true;
+            }
+        }
+        for path in self.created.iter().rev() {
+            if path.is_dir() {
+                fs::remove_dir_all(path)?;
+            } else if path.exists() {
+                fs::remove_file(path)?;
+            } else {
+                continue;
+            }
+            changed = true;
+        }
+        Ok(changed)
+    }
+}
+
+impl Drop for InstallTransaction {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+        match self.rollback() {
+            Ok(true) => ui::warn!("Rolled back to the previous installation"),
+            Ok(false) => {}
+            Err(err) => ui::warn!("Failed to roll back the installation: {:#}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activate(config: &Config, dir: &Path, version: &str) {
+        for name in ["ampd", "ampctl"] {
+            let link = config.bin_dir.join(name);
+            if link.is_symlink() {
+                fs::remove_file(&link).expect("should unlink");
+            }
+            symlink(dir.join(name), link).expect("should link");
+        }
+        config
+            .set_current_version(version)
+            .expect("should write version");
+    }
+
+    #[test]
+    fn drop_without_commit_restores_active_version_and_removes_created_dirs() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        config.ensure_dirs().expect("should create directories");
+        let previous = config.versions_dir.join("edgeandnode-amp/v1.0.0");
+        activate(&config, &previous, "edgeandnode-amp/v1.0.0");
+
+        //* When
+        {
+            let mut transaction = InstallTransaction::begin(&config).expect("should begin");
+            let partial = config.versions_dir.join("edgeandnode-amp/v1.1.0");
+            fs::create_dir_all(&partial).expect("should create version dir");
+            transaction.remove_on_rollback(&partial);
+            activate(&config, &partial, "edgeandnode-amp/v1.1.0");
+        }
+
+        //* Then
+        assert!(!config.versions_dir.join("edgeandnode-amp/v1.1.0").exists());
+        assert_eq!(
+            config.current_version().expect("should read version"),
+            Some("edgeandnode-amp/v1.0.0".to_string())
+        );
+        assert_eq!(
+            fs::read_link(config.active_ampctl_path()).expect("ampctl should be linked"),
+            previous.join("ampctl")
+        );
+    }
+
+    #[test]
+    fn commit_keeps_created_paths_and_activation() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        config.ensure_dirs().expect("should create directories");
+        let created = config.versions_dir.join("edgeandnode-amp/v1.1.0");
+
+        //* When
+        let mut transaction = InstallTransaction::begin(&config).expect("should begin");
+        fs::create_dir_all(&created).expect("should create version dir");
+        transaction.remove_on_rollback(&created);
+        activate(&config, &created, "edgeandnode-amp/v1.1.0");
+        transaction.commit();
+
+        //* Then
+        assert!(created.exists());
+        assert_eq!(
+            config.current_version().expect("should read version"),
+            Some("edgeandnode-amp/v1.1.0".to_string())
+        );
+    }
+}
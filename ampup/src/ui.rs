@@ -1,30 +1,53 @@
+use std::{
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, AtomicU8, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use console::style;
+use serde::{Deserialize, Serialize};
+
+use crate::environment::{Environment, ProcessEnv};
+
+/// Whether status messages go to stdout (the pre-separation behavior).
+static LEGACY_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Whether per-artifact download timings are reported.
+static TIMINGS: AtomicBool = AtomicBool::new(false);
+
+/// Active [`Theme`], stored as its discriminant.
+static THEME: AtomicU8 = AtomicU8::new(Theme::Unicode as u8);
+
+/// [`Locale`] of the process environment, read on first use.
+static LOCALE: OnceLock<Locale> = OnceLock::new();
 
 /// Print a success message with a green checkmark
 macro_rules! success {
     ($($arg:tt)*) => {
-        println!("{} {}", console::style("✓").green().bold(), format!($($arg)*))
+        $crate::ui::status(format_args!("{}{}", $crate::ui::theme().success(), format!($($arg)*)))
     };
 }
 
 /// Print an info message with a cyan arrow
 macro_rules! info {
     ($($arg:tt)*) => {
-        println!("{} {}", console::style("→").cyan(), format!($($arg)*))
+        $crate::ui::status(format_args!("{}{}", $crate::ui::theme().info(), format!($($arg)*)))
     };
 }
 
 /// Print a warning message with a yellow warning symbol
 macro_rules! warning {
     ($($arg:tt)*) => {
-        eprintln!("{} {}", console::style("⚠").yellow().bold(), format!($($arg)*))
+        eprintln!("{}{}", $crate::ui::theme().warning(), format!($($arg)*))
     };
 }
 
 /// Print a dimmed detail message (indented)
 macro_rules! detail {
     ($($arg:tt)*) => {
-        println!("  {}", console::style(format!($($arg)*)).dim())
+        $crate::ui::status(format_args!("  {}", console::style(format!($($arg)*)).dim()))
     };
 }
 
@@ -33,6 +56,115 @@ pub(crate) use info;
 pub(crate) use success;
 pub(crate) use warning as warn;
 
+/// How status lines and download progress are drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum Theme {
+    /// Unicode symbols and a progress line per download
+    #[default]
+    Unicode,
+    /// ASCII-only symbols, for terminals and multiplexers that mangle Unicode
+    Ascii,
+    /// Words instead of symbols and a single progress line
+    Minimal,
+    /// Plain sentences for screen readers: no color, symbols or redrawn
+    /// lines, and a sentence as each download starts and finishes
+    Accessible,
+}
+
+impl Theme {
+    /// Prefix of success lines, including the separating space.
+    pub fn success(self) -> String {
+        match self {
+            Self::Unicode => format!("{} ", style("✓").green().bold()),
+            Self::Ascii => format!("{} ", style("[ok]").green().bold()),
+            Self::Minimal => String::new(),
+            Self::Accessible => "Done: ".to_string(),
+        }
+    }
+
+    /// Prefix of info lines, including the separating space.
+    pub fn info(self) -> String {
+        match self {
+            Self::Unicode => format!("{} ", style("→").cyan()),
+            Self::Ascii => format!("{} ", style("->").cyan()),
+            Self::Minimal | Self::Accessible => String::new(),
+        }
+    }
+
+    /// Prefix of warning lines, including the separating space.
+    pub fn warning(self) -> String {
+        match self {
+            Self::Unicode => format!("{} ", style("⚠").yellow().bold()),
+            Self::Ascii => format!("{} ", style("[!]").yellow().bold()),
+            Self::Minimal => "warning: ".to_string(),
+            Self::Accessible => "Warning: ".to_string(),
+        }
+    }
+
+    /// Prefix of error lines, including the separating space.
+    pub fn error(self) -> String {
+        match self {
+            Self::Unicode => format!("{} ", style("✗").red().bold()),
+            Self::Ascii => format!("{} ", style("[x]").red().bold()),
+            Self::Minimal => "error: ".to_string(),
+            Self::Accessible => "Error: ".to_string(),
+        }
+    }
+}
+
+/// Select the theme for all subsequent output.
+///
+/// The accessible theme also turns off color, so no state is signaled by
+/// color alone.
+pub fn set_theme(theme: Theme) {
+    THEME.store(theme as u8, Ordering::Relaxed);
+    if theme == Theme::Accessible {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+}
+
+/// The theme selected with [`set_theme`].
+pub fn theme() -> Theme {
+    match THEME.load(Ordering::Relaxed) {
+        1 => Theme::Ascii,
+        2 => Theme::Minimal,
+        3 => Theme::Accessible,
+        _ => Theme::Unicode,
+    }
+}
+
+/// Send status messages to stdout alongside command results, as ampup did
+/// before status and results were separated.
+pub fn set_legacy_output(enabled: bool) {
+    LEGACY_OUTPUT.store(enabled, Ordering::Relaxed);
+}
+
+/// Report how long each artifact took to fetch, verify and write.
+pub fn set_timings(enabled: bool) {
+    TIMINGS.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether [`set_timings`] turned timing reports on.
+pub fn timings() -> bool {
+    TIMINGS.load(Ordering::Relaxed)
+}
+
+/// Write a human-readable status line.
+///
+/// Status goes to stderr so stdout carries only command results (versions,
+/// paths, URLs) and can be captured by scripts, e.g.
+/// `VERSION=$(ampup resolve latest)`.
+pub fn status(line: std::fmt::Arguments) {
+    if LEGACY_OUTPUT.load(Ordering::Relaxed) {
+        println!("{}", line);
+    } else {
+        eprintln!("{}", line);
+    }
+}
+
 /// Style a version string (bold white)
 pub fn version(v: impl std::fmt::Display) -> String {
     style(v).bold().to_string()
@@ -42,3 +174,199 @@ pub fn version(v: impl std::fmt::Display) -> String {
 pub fn path(p: impl std::fmt::Display) -> String {
     style(p).cyan().to_string()
 }
+
+/// Format `bytes` for display in the user's locale, e.g. `142.3 MiB`.
+pub fn size(bytes: u64) -> String {
+    locale().format_size(bytes)
+}
+
+/// Format a Unix timestamp as a calendar date (UTC) in the user's locale.
+pub fn date(unix_secs: u64) -> String {
+    locale().format_date(unix_secs)
+}
+
+/// How long before now a Unix timestamp was, e.g. `3 days ago`.
+pub fn ago(unix_secs: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    format_relative(unix_secs, now)
+}
+
+fn locale() -> &'static Locale {
+    LOCALE.get_or_init(|| Locale::from_env(&ProcessEnv))
+}
+
+/// How numbers and dates are written, from the POSIX locale variables.
+///
+/// Only the separators and field order are localized; unit and month names
+/// stay in English, like the rest of the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Locale {
+    /// Separator between a number's integer and fractional parts
+    decimal: char,
+    dates: DateStyle,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DateStyle {
+    /// `2025-03-05`, for the C locale and where no other style is known
+    Iso,
+    /// `03/05/2025`
+    MonthDayYear,
+    /// `05.03.2025`, with the separator given
+    DayMonthYear(char),
+}
+
+impl Locale {
+    /// Locale selected by `LC_ALL`, else `LC_NUMERIC` (for numbers) and
+    /// `LC_TIME` (for dates), else `LANG`.
+    pub fn from_env(env: &dyn Environment) -> Self {
+        let category = |name: &str| {
+            ["LC_ALL", name, "LANG"]
+                .into_iter()
+                .find_map(|var| env.var(var).filter(|value| !value.is_empty()))
+                .unwrap_or_default()
+        };
+        Self {
+            decimal: decimal_separator(&category("LC_NUMERIC")),
+            dates: date_style(&category("LC_TIME")),
+        }
+    }
+
+    /// `bytes` with a binary unit and one decimal, or as is below 1 KiB.
+    pub fn format_size(&self, bytes: u64) -> String {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+        if bytes < 1024 {
+            return format!("{} B", bytes);
+        }
+        let mut value = bytes as f64 / 1024.0;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        let value = format!("{:.1}", value).replace('.', &self.decimal.to_string());
+        format!("{} {}", value, UNITS[unit])
+    }
+
+    /// The UTC calendar date of `unix_secs`.
+    pub fn format_date(&self, unix_secs: u64) -> String {
+        let (year, month, day) = civil_date(unix_secs);
+        match self.dates {
+            DateStyle::Iso => format!("{:04}-{:02}-{:02}", year, month, day),
+            DateStyle::MonthDayYear => format!("{:02}/{:02}/{:04}", month, day, year),
+            DateStyle::DayMonthYear(separator) => {
+                format!(
+                    "{:02}{sep}{:02}{sep}{:04}",
+                    day,
+                    month,
+                    year,
+                    sep = separator
+                )
+            }
+        }
+    }
+}
+
+/// Language and territory of a locale name like `de_DE.UTF-8@euro`.
+fn language_territory(locale: &str) -> (&str, &str) {
+    let name = locale.split(['.', '@']).next().unwrap_or_default();
+    name.split_once('_').unwrap_or((name, ""))
+}
+
+fn decimal_separator(locale: &str) -> char {
+    match language_territory(locale).0 {
+        "cs" | "da" | "de" | "es" | "fi" | "fr" | "it" | "nb" | "nl" | "pl" | "pt" | "ru"
+        | "sv" | "tr" | "uk" => ',',
+        _ => '.',
+    }
+}
+
+fn date_style(locale: &str) -> DateStyle {
+    match language_territory(locale) {
+        ("en", "US") => DateStyle::MonthDayYear,
+        ("en", "GB" | "AU" | "NZ" | "IE" | "IN") | ("es" | "fr" | "it" | "pt", _) => {
+            DateStyle::DayMonthYear('/')
+        }
+        ("cs" | "de" | "fi" | "nb" | "pl" | "ru" | "tr" | "uk", _) => DateStyle::DayMonthYear('.'),
+        ("nl" | "da", _) => DateStyle::DayMonthYear('-'),
+        _ => DateStyle::Iso,
+    }
+}
+
+/// Year, month and day of `unix_secs` in the proleptic Gregorian calendar.
+pub(crate) fn civil_date(unix_secs: u64) -> (i64, u32, u32) {
+    // Days since 0000-03-01, so leap days fall at the end of each year
+    let days = (unix_secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// `then` relative to `now`, in the largest whole unit; times in the future
+/// (clock skew) read as just now.
+fn format_relative(then: u64, now: u64) -> String {
+    let secs = now.saturating_sub(then);
+    let (count, unit) = match secs {
+        0..60 => return "just now".to_string(),
+        60..3_600 => (secs / 60, "minute"),
+        3_600..86_400 => (secs / 3_600, "hour"),
+        86_400..2_592_000 => (secs / 86_400, "day"),
+        2_592_000..31_536_000 => (secs / 2_592_000, "month"),
+        _ => (secs / 31_536_000, "year"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{} {}{} ago", count, unit, plural)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::MapEnv;
+
+    #[test]
+    fn locale_from_env_with_german_numbers_and_us_dates_formats_each_category() {
+        //* Given
+        let env = MapEnv::new()
+            .with("LANG", "en_US.UTF-8")
+            .with("LC_NUMERIC", "de_DE.UTF-8");
+
+        //* When
+        let locale = Locale::from_env(&env);
+
+        //* Then
+        assert_eq!(locale.format_size(149_212_364), "142,3 MiB");
+        assert_eq!(locale.format_size(512), "512 B");
+        // 2025-03-05T12:00:00Z
+        assert_eq!(locale.format_date(1_741_176_000), "03/05/2025");
+        assert_eq!(
+            Locale::from_env(&MapEnv::new()).format_date(1_741_176_000),
+            "2025-03-05"
+        );
+    }
+
+    #[test]
+    fn format_relative_with_elapsed_durations_uses_largest_whole_unit() {
+        //* Given
+        let now = 1_741_176_000;
+
+        //* Then
+        assert_eq!(format_relative(now - 30, now), "just now");
+        assert_eq!(format_relative(now - 3_600, now), "1 hour ago");
+        assert_eq!(format_relative(now - 3 * 86_400, now), "3 days ago");
+        assert_eq!(format_relative(now + 60, now), "just now");
+    }
+}
@@ -2,8 +2,13 @@ use anyhow::{Context, Result};
 use fs_err as fs;
 
 use crate::{
+    archive,
+    download_manager::DownloadError,
+    executable::ExecutableFormat,
     github::GitHubClient,
     platform::{Architecture, Platform},
+    preallocate,
+    transaction::InstallTransaction,
     ui,
 };
 
@@ -43,14 +48,29 @@ impl Updater {
             .download_release_asset(version, &artifact_name)
             .await
             .context("Failed to download ampup binary")?;
+        let binary_data = archive::unpack_binary(binary_data, "ampup")
+            .context("Failed to extract ampup binary")?;
+
+        // Refuse to replace the running executable with one that can't run
+        let format = ExecutableFormat::detect(&binary_data);
+        if !format.runs_on(platform, arch) {
+            return Err(DownloadError::TargetMismatch {
+                artifact_name,
+                expected: format!("{} {}", platform, arch),
+                found: format.to_string(),
+            }
+            .into());
+        }
 
         // Get the current executable path
         let current_exe =
             std::env::current_exe().context("Failed to get current executable path")?;
 
-        // Write to a temporary file first
+        // Write to a temporary file first, removed if it can't be swapped in
         let temp_path = current_exe.with_extension("tmp");
-        fs::write(&temp_path, &binary_data).context("Failed to write temporary file")?;
+        let mut transaction = InstallTransaction::detached();
+        transaction.remove_on_rollback(&temp_path);
+        preallocate::write(&temp_path, &binary_data).context("Failed to write temporary file")?;
 
         // Make it executable
         #[cfg(unix)]
@@ -66,6 +86,7 @@ impl Updater {
 
         // Replace the current executable
         fs::rename(&temp_path, &current_exe).context("Failed to replace executable")?;
+        transaction.commit();
 
         ui::success!("Updated to {}", ui::version(version));
 
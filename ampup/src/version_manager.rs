@@ -1,16 +1,60 @@
-use std::os::unix::fs::symlink;
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::{Context, Result};
 use fs_err as fs;
 
-use crate::config::Config;
+use crate::{
+    DEFAULT_REPO, components,
+    config::Config,
+    dispatch, extras,
+    hooks::{HookContext, HookEvent, Hooks},
+    instances::{self, Instances},
+    manifest::VersionManifest,
+    reproducible::sha256_file,
+    settings::Settings,
+    ui,
+    version_store::{DirectoryStore, VersionStore, repo_namespace, split_key, version_key},
+};
 
 /// Version management errors
 #[derive(Debug)]
 pub enum VersionError {
-    NotInstalled { version: String },
+    NotInstalled {
+        version: String,
+    },
+    /// A bare version is installed from more than one repository.
+    Ambiguous {
+        version: String,
+        keys: Vec<String>,
+    },
     NoVersionsInstalled,
-    BinaryNotFound { version: String },
+    /// No other version was active before, or it's been uninstalled.
+    NoPreviousVersion,
+    /// The version is held with `ampup pin`.
+    Pinned {
+        version: String,
+    },
+    /// Another repository's release is installed under the same key, as
+    /// `foo-bar/amp` and `foo/bar-amp` share a namespace.
+    RepoCollision {
+        key: String,
+        installed_from: String,
+        repo: String,
+    },
+    BinaryNotFound {
+        version: String,
+    },
+    /// A binary no longer matches the checksum recorded when it was
+    /// installed.
+    ChecksumMismatch {
+        version: String,
+        binary: String,
+        expected: String,
+        actual: String,
+    },
 }
 
 impl std::fmt::Display for VersionError {
@@ -22,11 +66,57 @@ impl std::fmt::Display for VersionError {
                 writeln!(f)?;
                 writeln!(f, "  Try: ampup install {}", version)?;
             }
+            Self::Ambiguous { version, keys } => {
+                writeln!(f, "Version is installed from more than one repository")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Installed: {}", keys.join(", "))?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Pass --repo, or name it with its repository (e.g. {}).",
+                    keys[0]
+                )?;
+            }
             Self::NoVersionsInstalled => {
                 writeln!(f, "No versions installed")?;
                 writeln!(f)?;
                 writeln!(f, "  Try: ampup install")?;
             }
+            Self::NoPreviousVersion => {
+                writeln!(f, "No previous version to roll back to")?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  ampup remembers the version active before each switch; the previous one is gone or there was none."
+                )?;
+                writeln!(f, "  Try: ampup use <version>")?;
+            }
+            Self::Pinned { version } => {
+                writeln!(f, "Version is pinned")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Try: ampup unpin {}, or ampup uninstall {} --force",
+                    version, version
+                )?;
+            }
+            Self::RepoCollision {
+                key,
+                installed_from,
+                repo,
+            } => {
+                writeln!(f, "Version is installed from another repository")?;
+                writeln!(f, "  Version: {}", key)?;
+                writeln!(f, "  Installed from: {}", installed_from)?;
+                writeln!(f, "  Requested from: {}", repo)?;
+                writeln!(f)?;
+                writeln!(
+                    f,
+                    "  Both repositories map to the same namespace, so one would replace the other."
+                )?;
+                writeln!(f, "  Try: ampup uninstall {}, then install again", key)?;
+            }
             Self::BinaryNotFound { version } => {
                 writeln!(f, "Binary not found")?;
                 writeln!(f, "  Version: {}", version)?;
@@ -34,6 +124,25 @@ impl std::fmt::Display for VersionError {
                 writeln!(f, "  Installation may be corrupted.")?;
                 writeln!(f, "  Try: ampup install {}", version)?;
             }
+            Self::ChecksumMismatch {
+                version,
+                binary,
+                expected,
+                actual,
+            } => {
+                writeln!(f, "Binary changed since it was installed")?;
+                writeln!(f, "  Version: {}", version)?;
+                writeln!(f, "  Binary: {}", binary)?;
+                writeln!(f, "  Expected: {}", expected)?;
+                writeln!(f, "  Actual: {}", actual)?;
+                writeln!(f)?;
+                writeln!(f, "  It may have been tampered with or truncated.")?;
+                writeln!(
+                    f,
+                    "  Try: ampup uninstall {} and install it again, or pass --force to activate it anyway",
+                    version
+                )?;
+            }
         }
         Ok(())
     }
@@ -44,12 +153,33 @@ impl std::error::Error for VersionError {}
 /// Manages installed ampd versions
 pub struct VersionManager {
     config: Config,
+    store: Box<dyn VersionStore>,
+    check_checksums: bool,
 }
 
 impl VersionManager {
-    /// Create a new version manager
+    /// Create a version manager storing versions under `config.versions_dir`,
+    /// first moving any versions left there by the flat layout of earlier
+    /// releases into the configured repository's namespace
     pub fn new(config: Config) -> Self {
-        Self { config }
+        let store = DirectoryStore::new(config.versions_dir.clone());
+        Self::with_store(config, Box::new(store))
+    }
+
+    /// Create a version manager backed by a custom version store
+    pub fn with_store(config: Config, store: Box<dyn VersionStore>) -> Self {
+        Self {
+            config,
+            store,
+            check_checksums: true,
+        }
+    }
+
+    /// Whether activation checks the binaries against the checksums
+    /// recorded when they were installed (default: true)
+    pub fn with_checksum_verification(mut self, verify: bool) -> Self {
+        self.check_checksums = verify;
+        self
     }
 
     /// Get the configuration
@@ -57,29 +187,30 @@ impl VersionManager {
         &self.config
     }
 
+    /// Get the version store
+    pub fn store(&self) -> &dyn VersionStore {
+        self.store.as_ref()
+    }
+
+    /// Directory holding a version's binaries
+    pub fn version_dir(&self, version: &str) -> PathBuf {
+        self.store.version_dir(version)
+    }
+
     /// List all installed versions, sorted alphabetically
     pub fn list_installed(&self) -> Result<Vec<String>> {
-        if !self.config.versions_dir.exists() {
-            return Ok(Vec::new());
-        }
-
-        let mut versions = Vec::new();
-        for entry in
-            fs::read_dir(&self.config.versions_dir).context("Failed to read versions directory")?
-        {
-            let entry = entry.context("Failed to read directory entry")?;
-            if entry
-                .file_type()
-                .context("Failed to get file type")?
-                .is_dir()
-            {
-                let version = entry.file_name().to_string_lossy().to_string();
-                versions.push(version);
-            }
-        }
+        self.store.list()
+    }
 
-        versions.sort();
-        Ok(versions)
+    /// Installed versions whose directory lacks `ampd`, left by an
+    /// interrupted install or removal, sorted alphabetically
+    pub fn list_incomplete(&self) -> Result<Vec<String>> {
+        Ok(self
+            .store
+            .list()?
+            .into_iter()
+            .filter(|key| !self.is_installed(key))
+            .collect())
     }
 
     /// Get the currently active version
@@ -87,22 +218,187 @@ impl VersionManager {
         self.config.current_version()
     }
 
+    /// Get the version active before the current one, when it's still
+    /// installed
+    pub fn get_previous(&self) -> Result<Option<String>> {
+        let current = self.get_current()?;
+        Ok(self
+            .config
+            .previous_version()?
+            .filter(|previous| Some(previous) != current.as_ref() && self.is_installed(previous)))
+    }
+
+    /// Fail when installed `key` was recorded as coming from another
+    /// repository than `repo`.
+    pub fn check_repo(&self, key: &str, repo: &str) -> Result<()> {
+        let manifest = VersionManifest::load(&self.version_dir(key))?;
+        match manifest.repo {
+            Some(installed_from) if !installed_from.eq_ignore_ascii_case(repo) => {
+                Err(VersionError::RepoCollision {
+                    key: key.to_string(),
+                    installed_from,
+                    repo: repo.to_string(),
+                }
+                .into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Repository the active version was installed from, when recorded.
+    pub fn current_repo(&self) -> Result<Option<String>> {
+        match self.get_current()? {
+            Some(current) if self.is_installed(&current) => {
+                Ok(VersionManifest::load(&self.version_dir(&current))?.repo)
+            }
+            _ => Ok(None),
+        }
+    }
+
     /// Check if a version is installed
     pub fn is_installed(&self, version: &str) -> bool {
-        self.config.version_binary_path(version).exists()
+        self.store.contains(version)
     }
 
-    /// Activate a specific version by creating symlinks and updating version file
+    /// When `version` was installed, as a Unix timestamp: when its
+    /// directory was created, where the filesystem records that.
+    pub fn installed_at(&self, version: &str) -> Option<u64> {
+        let metadata = fs::metadata(self.version_dir(version)).ok()?;
+        let time = metadata.created().or_else(|_| metadata.modified()).ok()?;
+        Some(time.duration_since(UNIX_EPOCH).ok()?.as_secs())
+    }
+
+    /// Bytes taken by the files in `version`'s directory, not following
+    /// symlinks.
+    pub fn disk_usage(&self, version: &str) -> Result<u64> {
+        dir_usage(&self.version_dir(version))
+    }
+
+    /// Key of the installed `version`, which is either a key
+    /// (`edgeandnode-amp/v0.4.0`) or a bare version looked up in `repo`'s
+    /// namespace, or in every namespace when no repo is given.
+    ///
+    /// Fails with `VersionError::Ambiguous` when a bare version is installed
+    /// from several repositories and no repo picks one.
+    pub fn resolve(&self, version: &str, repo: Option<&str>) -> Result<String> {
+        let not_installed = || VersionError::NotInstalled {
+            version: version.to_string(),
+        };
+        let installed = self.store.list()?;
+        if let (Some(_), _) = split_key(version) {
+            return installed
+                .into_iter()
+                .find(|key| key == version)
+                .ok_or_else(|| not_installed().into());
+        }
+        if let Some(repo) = repo {
+            let key = version_key(&repo_namespace(repo), version);
+            return installed
+                .into_iter()
+                .find(|installed| *installed == key)
+                .ok_or_else(|| not_installed().into());
+        }
+
+        let mut keys: Vec<String> = installed
+            .into_iter()
+            .filter(|key| split_key(key).1 == version)
+            .collect();
+        match keys.len() {
+            0 => Err(not_installed().into()),
+            1 => Ok(keys.remove(0)),
+            _ => Err(VersionError::Ambiguous {
+                version: version.to_string(),
+                keys,
+            }
+            .into()),
+        }
+    }
+
+    /// Move versions from the flat `versions/<version>` layout into
+    /// namespaces, repointing the active version and instances pinned to
+    /// them. Warns instead of failing, so a stuck migration doesn't block the
+    /// command. Callers hold the installation lock.
+    pub fn migrate_flat_layout(&self) {
+        if let Err(err) = self.try_migrate_flat_layout() {
+            ui::warn!("Failed to migrate installed versions: {:#}", err);
+        }
+    }
+
+    fn try_migrate_flat_layout(&self) -> Result<()> {
+        let store = DirectoryStore::new(self.config.versions_dir.clone());
+        let repo = Settings::load(&self.config)
+            .map(|settings| settings.repo(None))
+            .unwrap_or_else(|_| DEFAULT_REPO.to_string());
+        let migration = store.migrate_flat_layout(&repo_namespace(&repo))?;
+        if migration.moved.is_empty() {
+            return Ok(());
+        }
+        let keys: Vec<&str> = migration
+            .moved
+            .iter()
+            .map(|(_, key)| key.as_str())
+            .collect();
+        ui::info!(
+            "Moved {} into {}",
+            keys.join(", "),
+            ui::path(self.config.versions_dir.display())
+        );
+        for (key, kept) in &migration.conflicts {
+            ui::warn!(
+                "{} was already installed; kept the older copy at {}",
+                ui::version(key),
+                ui::path(kept.display())
+            );
+            ui::detail!("Remove it once you've checked it isn't needed");
+        }
+        let new_key = |name: &str| {
+            migration
+                .moved
+                .iter()
+                .find(|(legacy, _)| legacy == name)
+                .map(|(_, key)| key.clone())
+        };
+
+        // Record the new key before relinking, so a failed relink is fixed
+        // by the next 'ampup use' rather than leaving a stale version file
+        if let Some(key) = self.get_current()?.as_deref().and_then(new_key) {
+            self.config.set_current_version(&key)?;
+            self.activate(&key)?;
+        }
+
+        let mut instances = Instances::load(&self.config)?;
+        let pinned: Vec<(String, instances::Instance)> = instances
+            .iter()
+            .filter_map(|(name, instance)| {
+                let key = new_key(&instance.version)?;
+                let mut instance = instance.clone();
+                instance.version = key;
+                Some((name.clone(), instance))
+            })
+            .collect();
+        if pinned.is_empty() {
+            return Ok(());
+        }
+        for (name, instance) in pinned {
+            let binary = self.version_dir(&instance.version).join("ampd");
+            instances::write_wrapper(&self.config, &name, &binary, &instance.args)?;
+            instances.insert(&name, instance)?;
+        }
+        instances.save(&self.config)
+    }
+
+    /// Activate a specific version by updating the version file and shims
     pub fn activate(&self, version: &str) -> Result<()> {
-        let version_dir = self.config.versions_dir.join(version);
-        if !version_dir.exists() {
+        if !self.store.list()?.iter().any(|v| v == version) {
             return Err(VersionError::NotInstalled {
                 version: version.to_string(),
             }
             .into());
         }
+        self.store.prepare(version)?;
 
-        let ampd_binary_path = self.config.version_binary_path(version);
+        let version_dir = self.store.version_dir(version);
+        let ampd_binary_path = version_dir.join("ampd");
         if !ampd_binary_path.exists() {
             return Err(VersionError::BinaryNotFound {
                 version: version.to_string(),
@@ -110,40 +406,81 @@ impl VersionManager {
             .into());
         }
 
-        let ampctl_binary_path = self.config.version_ampctl_path(version);
+        let ampctl_binary_path = version_dir.join("ampctl");
         if !ampctl_binary_path.exists() {
             return Err(VersionError::BinaryNotFound {
                 version: version.to_string(),
             }
             .into());
         }
-
-        // Handle ampd symlink
-        let ampd_active_path = self.config.active_binary_path();
-        if ampd_active_path.exists() || ampd_active_path.is_symlink() {
-            fs::remove_file(&ampd_active_path).context("Failed to remove existing ampd symlink")?;
+        if self.check_checksums {
+            self.verify_checksums(version)?;
         }
-        symlink(&ampd_binary_path, &ampd_active_path).context("Failed to create ampd symlink")?;
 
-        // Handle ampctl symlink
-        let ampctl_active_path = self.config.active_ampctl_path();
-        if ampctl_active_path.exists() || ampctl_active_path.is_symlink() {
-            fs::remove_file(&ampctl_active_path)
-                .context("Failed to remove existing ampctl symlink")?;
+        // Hooks only run when the active version actually changes
+        let current = self.get_current()?;
+        let current_dir = current
+            .as_deref()
+            .map(|current| self.store.version_dir(current))
+            .filter(|dir| dir.exists());
+        let hook_context = (current.as_deref() != Some(version)).then(|| HookContext {
+            from: current,
+            to: version.to_string(),
+            version_dir: version_dir.clone(),
+            test: false,
+        });
+        let hooks = Hooks::new(&self.config);
+        if let Some(context) = &hook_context {
+            hooks.fire(HookEvent::PreActivate, context)?;
         }
-        symlink(&ampctl_binary_path, &ampctl_active_path)
-            .context("Failed to create ampctl symlink")?;
 
-        // Update current version file
+        // The shims resolve the version when they run, so they only need
+        // writing once (or replacing the symlinks of earlier releases)
+        dispatch::write_shims(&self.config)?;
+        extras::link(&self.config, &version_dir)?;
+        components::relink(&self.config, current_dir.as_deref(), &version_dir)?;
+
+        // Update current version file, remembering the one it replaces for
+        // 'ampup rollback'
         self.config.set_current_version(version)?;
+        if let Some(context) = &hook_context
+            && let Some(from) = &context.from
+        {
+            self.config.set_previous_version(from)?;
+        }
+        let now = unix_now();
+        VersionManifest::update(&version_dir, |manifest| manifest.last_activated = Some(now))?;
+
+        if let Some(context) = &hook_context {
+            hooks.fire(HookEvent::PostActivate, context)?;
+        }
+        Ok(())
+    }
 
+    /// Check a version's binaries against the checksums recorded when it was
+    /// installed. Versions installed before checksums were recorded pass.
+    pub fn verify_checksums(&self, version: &str) -> Result<()> {
+        let version_dir = self.store.version_dir(version);
+        let recorded = VersionManifest::load(&version_dir)?.checksums;
+        for (binary, expected) in recorded {
+            let actual = sha256_file(&version_dir.join(&binary))
+                .with_context(|| format!("Failed to checksum {}", binary))?;
+            if actual != expected {
+                return Err(VersionError::ChecksumMismatch {
+                    version: version.to_string(),
+                    binary,
+                    expected,
+                    actual,
+                }
+                .into());
+            }
+        }
         Ok(())
     }
 
     /// Uninstall a specific version
     pub fn uninstall(&self, version: &str) -> Result<()> {
-        let version_dir = self.config.versions_dir.join(version);
-        if !version_dir.exists() {
+        if !self.store.list()?.iter().any(|v| v == version) {
             return Err(VersionError::NotInstalled {
                 version: version.to_string(),
             }
@@ -154,29 +491,276 @@ impl VersionManager {
         let current = self.get_current()?;
         let is_current = current.as_deref() == Some(version);
 
-        // Remove the version directory
-        fs::remove_dir_all(&version_dir).context("Failed to remove version directory")?;
+        // Unlink its components while its manifest still lists them
+        if is_current {
+            for component in VersionManifest::load(&self.store.version_dir(version))?.components {
+                components::unlink(&self.config, &component)?;
+            }
+        }
+
+        // Remove the version from the store
+        self.store.remove(version)?;
 
-        // If this was the current version, clear the current version file and symlinks
+        // If this was the current version, clear the current version file.
+        // The shims stay, so versions pinned by projects keep running.
         if is_current {
             let current_file = self.config.current_version_file();
             if current_file.exists() {
                 fs::remove_file(&current_file).context("Failed to remove current version file")?;
             }
-
-            // Remove the ampd symlink
-            let ampd_active_path = self.config.active_binary_path();
-            if ampd_active_path.exists() || ampd_active_path.is_symlink() {
-                fs::remove_file(&ampd_active_path).context("Failed to remove ampd symlink")?;
+            // Symlinks from releases before the shims point into the version
+            for link in [
+                self.config.active_binary_path(),
+                self.config.active_ampctl_path(),
+            ] {
+                if link.is_symlink() {
+                    fs::remove_file(&link).context("Failed to remove symlink")?;
+                }
             }
+            extras::unlink(&self.config)?;
+        }
+
+        Ok(())
+    }
+}
 
-            // Remove the ampctl symlink
-            let ampctl_active_path = self.config.active_ampctl_path();
-            if ampctl_active_path.exists() || ampctl_active_path.is_symlink() {
-                fs::remove_file(&ampctl_active_path).context("Failed to remove ampctl symlink")?;
+/// Bytes taken by the files under `dir`, not following symlinks.
+pub fn dir_usage(dir: &Path) -> Result<u64> {
+    let mut total = 0;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir).context("Failed to read version directory")? {
+            let entry = entry.context("Failed to read version directory entry")?;
+            let metadata = fs::symlink_metadata(entry.path())
+                .context("Failed to read version file metadata")?;
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                total += metadata.len();
             }
         }
+    }
+    Ok(total)
+}
 
-        Ok(())
+/// Current time as a Unix timestamp.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::binary_checksums;
+
+    /// Store that lists a version but only writes its binaries on `prepare`,
+    /// like a backend fetching from object storage on first activation.
+    struct LazyStore {
+        inner: DirectoryStore,
+    }
+
+    impl VersionStore for LazyStore {
+        fn list(&self) -> Result<Vec<String>> {
+            Ok(vec!["v1.0.0".to_string()])
+        }
+
+        fn version_dir(&self, version: &str) -> PathBuf {
+            self.inner.version_dir(version)
+        }
+
+        fn prepare(&self, version: &str) -> Result<()> {
+            let dir = self.version_dir(version);
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join("ampd"), "")?;
+            fs::write(dir.join("ampctl"), "")?;
+            Ok(())
+        }
+
+        fn ensure_root(&self) -> Result<()> {
+            self.inner.ensure_root()
+        }
+
+        fn remove(&self, version: &str) -> Result<()> {
+            self.inner.remove(version)
+        }
+    }
+
+    #[test]
+    fn activate_with_lazy_store_prepares_binaries_before_linking() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        config.ensure_dirs().expect("should create directories");
+        let store = LazyStore {
+            inner: DirectoryStore::new(dir.path().join("store")),
+        };
+        let version_manager = VersionManager::with_store(config, Box::new(store));
+
+        //* When
+        version_manager
+            .activate("v1.0.0")
+            .expect("should activate lazily fetched version");
+
+        //* Then
+        assert!(dir.path().join("store/v1.0.0/ampd").exists());
+        assert!(version_manager.config().active_binary_path().exists());
+        assert_eq!(
+            version_manager.get_current().expect("should read current"),
+            Some("v1.0.0".to_string())
+        );
+    }
+
+    #[test]
+    fn activate_with_relative_links_writes_shims_that_run_after_moving_tree() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let staging = dir.path().join("staging");
+        let config = Config::new(Some(staging.clone()))
+            .expect("should create config")
+            .with_relative_links();
+        config.ensure_dirs().expect("should create directories");
+        let version_dir = config.versions_dir.join("edgeandnode/v1.0.0");
+        fs::create_dir_all(&version_dir).expect("should create version directory");
+        for binary in ["ampd", "ampctl"] {
+            fs::write(
+                version_dir.join(binary),
+                format!("#!/bin/sh\necho {} v1.0.0\n", binary),
+            )
+            .expect("should write binary");
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(
+                version_dir.join(binary),
+                std::fs::Permissions::from_mode(0o755),
+            )
+            .expect("should make binary executable");
+        }
+        let version_manager = VersionManager::new(config);
+
+        //* When
+        version_manager
+            .activate("edgeandnode/v1.0.0")
+            .expect("should activate version");
+        let moved = dir.path().join("moved");
+        fs::rename(&staging, &moved).expect("should move tree");
+
+        //* Then
+        let shim = moved.join("bin/ampd");
+        assert!(!shim.is_symlink(), "ampd should be a shim, not a link");
+        let output = std::process::Command::new(&shim)
+            .output()
+            .expect("shim should run");
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "ampd v1.0.0\n");
+    }
+
+    #[test]
+    fn activate_with_other_version_active_records_previous_and_activation_time() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        config.ensure_dirs().expect("should create directories");
+        for version in ["v1.0.0", "v1.1.0"] {
+            let version_dir = config.versions_dir.join("edgeandnode-amp").join(version);
+            fs::create_dir_all(&version_dir).expect("should create version directory");
+            fs::write(version_dir.join("ampd"), "").expect("should write ampd");
+            fs::write(version_dir.join("ampctl"), "").expect("should write ampctl");
+        }
+        let version_manager = VersionManager::new(config);
+        version_manager
+            .activate("edgeandnode-amp/v1.0.0")
+            .expect("should activate first version");
+        let previous_before = version_manager
+            .get_previous()
+            .expect("should read previous");
+
+        //* When
+        version_manager
+            .activate("edgeandnode-amp/v1.1.0")
+            .expect("should activate second version");
+
+        //* Then
+        assert_eq!(previous_before, None);
+        assert_eq!(
+            version_manager
+                .get_previous()
+                .expect("should read previous"),
+            Some("edgeandnode-amp/v1.0.0".to_string())
+        );
+        let manifest =
+            VersionManifest::load(&version_manager.version_dir("edgeandnode-amp/v1.1.0"))
+                .expect("should load manifest");
+        assert!(manifest.last_activated.is_some());
+    }
+
+    #[test]
+    fn activate_with_binary_changed_since_install_fails_unless_unverified() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        config.ensure_dirs().expect("should create directories");
+        let version_dir = config.versions_dir.join("edgeandnode-amp/v1.0.0");
+        fs::create_dir_all(&version_dir).expect("should create version directory");
+        fs::write(version_dir.join("ampd"), "ampd").expect("should write ampd");
+        fs::write(version_dir.join("ampctl"), "ampctl").expect("should write ampctl");
+        let checksums = binary_checksums(&version_dir).expect("should checksum binaries");
+        VersionManifest::update(&version_dir, |manifest| manifest.checksums = checksums)
+            .expect("should write manifest");
+        fs::write(version_dir.join("ampd"), "amp").expect("should truncate ampd");
+
+        //* When
+        let version_manager = VersionManager::new(config);
+        let verified = version_manager.activate("edgeandnode-amp/v1.0.0");
+        let unverified = version_manager
+            .with_checksum_verification(false)
+            .activate("edgeandnode-amp/v1.0.0");
+
+        //* Then
+        let err = verified.expect_err("changed binary should fail activation");
+        assert!(
+            matches!(
+                err.downcast_ref::<VersionError>(),
+                Some(VersionError::ChecksumMismatch { binary, .. }) if binary == "ampd"
+            ),
+            "expected ChecksumMismatch for ampd, got: {:?}",
+            err
+        );
+        assert!(
+            unverified.is_ok(),
+            "unverified activation should pass: {:?}",
+            unverified
+        );
+    }
+
+    #[test]
+    fn check_repo_with_other_recorded_repo_fails_with_collision() {
+        //* Given
+        let dir = tempfile::tempdir().expect("should create temp directory");
+        let config = Config::new(Some(dir.path().to_path_buf())).expect("should create config");
+        let version_dir = config.versions_dir.join("foo-bar-amp/v0.1.0");
+        fs::create_dir_all(&version_dir).expect("should create version directory");
+        VersionManifest::update(&version_dir, |manifest| {
+            manifest.repo = Some("foo-bar/amp".to_string())
+        })
+        .expect("should write manifest");
+        let version_manager = VersionManager::new(config);
+
+        //* When
+        let same = version_manager.check_repo("foo-bar-amp/v0.1.0", "Foo-Bar/amp");
+        let other = version_manager.check_repo("foo-bar-amp/v0.1.0", "foo/bar-amp");
+
+        //* Then
+        assert!(
+            same.is_ok(),
+            "same repository should pass: {:?}",
+            same.err()
+        );
+        assert!(matches!(
+            other
+                .expect_err("other repository should collide")
+                .downcast_ref::<VersionError>(),
+            Some(VersionError::RepoCollision { installed_from, .. }) if installed_from == "foo-bar/amp"
+        ));
     }
 }
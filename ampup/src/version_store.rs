@@ -0,0 +1,335 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+
+/// Namespace of versions built from a local checkout.
+pub const LOCAL_NAMESPACE: &str = "local";
+
+/// Namespace a repository's versions are stored under (e.g.
+/// `edgeandnode/amp` → `edgeandnode-amp`), so the same tag from a fork
+/// doesn't collide with the default repository's.
+pub fn repo_namespace(repo: &str) -> String {
+    repo.replace('/', "-")
+}
+
+/// Key identifying an installed version in a store: `<namespace>/<version>`
+/// (e.g. `edgeandnode-amp/v0.4.0`).
+pub fn version_key(namespace: &str, version: &str) -> String {
+    format!("{}/{}", namespace, version)
+}
+
+/// Split a version key into its namespace and version; a bare version has
+/// no namespace.
+pub fn split_key(key: &str) -> (Option<&str>, &str) {
+    match key.split_once('/') {
+        Some((namespace, version)) => (Some(namespace), version),
+        None => (None, key),
+    }
+}
+
+/// Storage backend for installed version directories.
+///
+/// Versions are identified by their [`version_key`]. A store owns the
+/// `<namespace>/<version>/{ampd,ampctl}` directories; the `bin/`
+/// shims and the `.version` file stay under the installation's own
+/// directory. Backends that don't keep every version on local disk (e.g. a
+/// store fetching binaries from object storage on first use) download them
+/// in [`prepare`](Self::prepare), which runs before a version is activated.
+pub trait VersionStore: Send + Sync {
+    /// Keys of the installed versions, sorted alphabetically.
+    fn list(&self) -> Result<Vec<String>>;
+
+    /// Local directory holding `version`'s binaries. It may not exist yet;
+    /// installs write into it.
+    fn version_dir(&self, version: &str) -> PathBuf;
+
+    /// Whether `version` is installed.
+    fn contains(&self, version: &str) -> bool {
+        self.version_dir(version).join("ampd").exists()
+    }
+
+    /// Make `version`'s binaries available in [`version_dir`](Self::version_dir).
+    fn prepare(&self, _version: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Create the store's root so versions can be written into it.
+    fn ensure_root(&self) -> Result<()>;
+
+    /// Delete `version` from the store.
+    fn remove(&self, version: &str) -> Result<()>;
+
+    /// Staging and backup directories left on local disk by installs that
+    /// were interrupted before finishing.
+    fn staging_dirs(&self) -> Result<Vec<PathBuf>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Versions kept as `<namespace>/<version>` directories under a root
+/// directory: `~/.amp/versions` by default, or a directory shared between
+/// machines (e.g. over NFS).
+pub struct DirectoryStore {
+    root: PathBuf,
+}
+
+impl DirectoryStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Directory containing one subdirectory per namespace.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Move versions left in the root by releases of ampup predating
+    /// namespaces (`versions/v0.4.0`) into `namespace`, or local builds
+    /// (`local-*`) into [`LOCAL_NAMESPACE`].
+    ///
+    /// A top-level directory holding `ampd` directly is a version; anything
+    /// else is left alone. A version already present in its namespace is
+    /// kept, and the legacy copy renamed to `<version>.conflict` beside it
+    /// rather than deleted, since the two may differ.
+    pub fn migrate_flat_layout(&self, namespace: &str) -> Result<FlatLayoutMigration> {
+        let mut migration = FlatLayoutMigration::default();
+        for name in subdirectories(&self.root)? {
+            let legacy = self.root.join(&name);
+            if name.ends_with(CONFLICT_SUFFIX) || !legacy.join("ampd").is_file() {
+                continue;
+            }
+            let namespace = match name.starts_with("local-") {
+                true => LOCAL_NAMESPACE,
+                false => namespace,
+            };
+            let key = version_key(namespace, &name);
+            let dest = self.version_dir(&key);
+            if dest.exists() {
+                let mut kept = self.root.join(format!("{}{}", name, CONFLICT_SUFFIX));
+                match kept.exists() {
+                    true => kept = legacy,
+                    false => fs::rename(&legacy, &kept)
+                        .context("Failed to set aside legacy version directory")?,
+                }
+                migration.conflicts.push((key.clone(), kept));
+            } else {
+                fs::create_dir_all(self.root.join(namespace))
+                    .context("Failed to create namespace directory")?;
+                fs::rename(&legacy, &dest).context("Failed to move legacy version directory")?;
+            }
+            migration.moved.push((name, key));
+        }
+        Ok(migration)
+    }
+}
+
+/// Suffix of a legacy version directory set aside because its namespaced
+/// version already existed.
+const CONFLICT_SUFFIX: &str = ".conflict";
+
+/// Outcome of [`DirectoryStore::migrate_flat_layout`].
+#[derive(Debug, Default)]
+pub struct FlatLayoutMigration {
+    /// Legacy version names and the keys they're now installed as
+    pub moved: Vec<(String, String)>,
+    /// Keys that were already installed, and where their legacy copy is
+    /// (`<version>.conflict`, or the legacy directory itself when that's
+    /// taken too)
+    pub conflicts: Vec<(String, PathBuf)>,
+}
+
+/// Names of the visible subdirectories of `dir`, skipping hidden ones such
+/// as staging directories (`.tmp*`) and backups of replaced versions
+/// (`.<version>.old`); an empty list when `dir` doesn't exist.
+fn subdirectories(dir: &Path) -> Result<Vec<String>> {
+    Ok(entries(dir)?
+        .into_iter()
+        .filter(|name| !name.starts_with('.'))
+        .collect())
+}
+
+/// Paths of the staging and backup directories directly in `dir`.
+fn staging_subdirectories(dir: &Path) -> Result<Vec<PathBuf>> {
+    Ok(entries(dir)?
+        .into_iter()
+        .filter(|name| is_staging(name))
+        .map(|name| dir.join(name))
+        .collect())
+}
+
+/// Whether `name` is a staging directory (`.tmp*`) or the backup a reinstall
+/// keeps of the version it replaces until the new one is in place
+/// (`.<version>.old`). Visible names are always versions, whatever they end
+/// with.
+fn is_staging(name: &str) -> bool {
+    name.starts_with(".tmp") || (name.starts_with('.') && name.ends_with(".old"))
+}
+
+/// Sorted names of the subdirectories of `dir`; an empty list when `dir`
+/// doesn't exist.
+fn entries(dir: &Path) -> Result<Vec<String>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir).context("Failed to read versions directory")? {
+        let entry = entry.context("Failed to read directory entry")?;
+        if entry
+            .file_type()
+            .context("Failed to get file type")?
+            .is_dir()
+        {
+            names.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+impl VersionStore for DirectoryStore {
+    fn list(&self) -> Result<Vec<String>> {
+        let mut versions = Vec::new();
+        for namespace in subdirectories(&self.root)? {
+            let dir = self.root.join(&namespace);
+            // Legacy versions still in the root aren't namespaces
+            if dir.join("ampd").is_file() {
+                continue;
+            }
+            for version in subdirectories(&dir)? {
+                versions.push(version_key(&namespace, &version));
+            }
+        }
+
+        versions.sort();
+        Ok(versions)
+    }
+
+    fn version_dir(&self, version: &str) -> PathBuf {
+        self.root.join(version)
+    }
+
+    fn ensure_root(&self) -> Result<()> {
+        fs::create_dir_all(&self.root).context("Failed to create versions directory")
+    }
+
+    fn remove(&self, version: &str) -> Result<()> {
+        let dir = self.version_dir(version);
+        fs::remove_dir_all(&dir).context("Failed to remove version directory")?;
+        // Drop the namespace once its last version is gone
+        if let Some(namespace) = dir.parent().filter(|parent| *parent != self.root) {
+            let _ = std::fs::remove_dir(namespace);
+        }
+        Ok(())
+    }
+
+    fn staging_dirs(&self) -> Result<Vec<PathBuf>> {
+        // Installs stage next to the version directory, in its namespace
+        let mut dirs = staging_subdirectories(&self.root)?;
+        for namespace in subdirectories(&self.root)? {
+            dirs.extend(staging_subdirectories(&self.root.join(namespace))?);
+        }
+        Ok(dirs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_flat_layout_with_legacy_versions_moves_them_into_namespace() -> Result<()> {
+        //* Given
+        let dir = tempfile::tempdir()?;
+        let store = DirectoryStore::new(dir.path().to_path_buf());
+        for version in ["v0.3.0", "v0.4.0"] {
+            fs::create_dir_all(dir.path().join(version))?;
+            fs::write(dir.path().join(version).join("ampd"), "")?;
+        }
+        fs::create_dir_all(dir.path().join("myfork-amp/v0.4.0"))?;
+        fs::write(dir.path().join("myfork-amp/v0.4.0/ampd"), "")?;
+
+        //* When
+        let migration = store.migrate_flat_layout("edgeandnode-amp")?;
+
+        //* Then
+        let moved: Vec<&str> = migration
+            .moved
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(moved, vec!["v0.3.0", "v0.4.0"]);
+        assert!(migration.conflicts.is_empty());
+        assert_eq!(
+            store.list()?,
+            vec![
+                "edgeandnode-amp/v0.3.0",
+                "edgeandnode-amp/v0.4.0",
+                "myfork-amp/v0.4.0"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_flat_layout_with_local_build_and_conflict_keeps_legacy_copy() -> Result<()> {
+        //* Given
+        let dir = tempfile::tempdir()?;
+        let store = DirectoryStore::new(dir.path().to_path_buf());
+        for version in ["local-ab12cd34", "v0.4.0", "edgeandnode-amp/v0.4.0"] {
+            fs::create_dir_all(dir.path().join(version))?;
+            fs::write(dir.path().join(version).join("ampd"), version)?;
+        }
+
+        //* When
+        let migration = store.migrate_flat_layout("edgeandnode-amp")?;
+
+        //* Then
+        assert_eq!(
+            store.list()?,
+            vec!["edgeandnode-amp/v0.4.0", "local/local-ab12cd34"]
+        );
+        assert_eq!(
+            migration.conflicts,
+            vec![(
+                "edgeandnode-amp/v0.4.0".to_string(),
+                dir.path().join("v0.4.0.conflict")
+            )]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("v0.4.0.conflict/ampd"))?,
+            "v0.4.0"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn staging_dirs_with_interrupted_install_lists_them_but_not_versions() -> Result<()> {
+        //* Given
+        let dir = tempfile::tempdir()?;
+        let store = DirectoryStore::new(dir.path().to_path_buf());
+        fs::create_dir_all(dir.path().join("edgeandnode-amp/v0.4.0"))?;
+        fs::create_dir_all(dir.path().join("edgeandnode-amp/.tmpAbC123"))?;
+        fs::create_dir_all(dir.path().join("edgeandnode-amp/.v0.3.0.old"))?;
+        fs::create_dir_all(dir.path().join("local/build.old"))?;
+        fs::create_dir_all(dir.path().join("local/.cache"))?;
+
+        //* When
+        let staging = store.staging_dirs()?;
+
+        //* Then
+        assert_eq!(
+            staging,
+            vec![
+                dir.path().join("edgeandnode-amp/.tmpAbC123"),
+                dir.path().join("edgeandnode-amp/.v0.3.0.old")
+            ]
+        );
+        assert_eq!(
+            store.list()?,
+            vec!["edgeandnode-amp/v0.4.0", "local/build.old"]
+        );
+        Ok(())
+    }
+}